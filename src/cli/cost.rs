@@ -1,57 +1,210 @@
-use crate::core::models::{DailyCost, Provider};
-use crate::cost::{CostScanResult, CostStore};
+use crate::cli::style::{ColorMode, Style};
+use crate::core::models::{DailyCost, DailyTokenUsage, Provider};
+use crate::core::schema::{json_schema, SCHEMA_VERSION};
+use crate::core::settings::Settings;
+use crate::cost::{
+    combined_project_costs, project_spend, today, unmatched_budgets, CostScanResult, CostStore,
+    ProjectSpend,
+};
+use crate::ui::colors::ColorScheme;
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use schemars::JsonSchema;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
 
-#[derive(Serialize)]
+/// `--csv` columns, one row per provider, model and day.
+const CSV_HEADER: [&str; 8] = [
+    "date",
+    "provider",
+    "model",
+    "input_tokens",
+    "output_tokens",
+    "cache_read_tokens",
+    "cache_creation_tokens",
+    "cost_usd",
+];
+
+#[derive(Serialize, JsonSchema)]
 struct CostOutput {
+    schema_version: u32,
     providers: HashMap<String, CostSummary>,
     #[serde(with = "chrono::serde::ts_seconds")]
+    #[schemars(with = "i64")]
     scanned_at: DateTime<Utc>,
     days: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    projects: Option<Vec<ProjectSummary>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
+struct ProjectSummary {
+    project: String,
+    spent: f64,
+    budget: Option<f64>,
+    percent: Option<f64>,
+}
+
+#[derive(Serialize, JsonSchema)]
 struct CostSummary {
     today: f64,
     monthly: f64,
     currency: String,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     daily_breakdown: Vec<DailyBreakdown>,
+    /// Token totals per day over the last `--days` days (at most 30), with a
+    /// per-model breakdown.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    daily_tokens: Vec<DailyTokenUsage>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct DailyBreakdown {
     date: String,
     model: String,
     cost: f64,
 }
 
-pub async fn run(json: bool, days: u32) -> Result<()> {
+/// A `--csv` row, in `CSV_HEADER` order.
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    date: NaiveDate,
+    provider: &'a str,
+    model: &'a str,
+    input_tokens: u64,
+    output_tokens: u64,
+    cache_read_tokens: u64,
+    cache_creation_tokens: u64,
+    cost_usd: Option<f64>,
+}
+
+pub async fn run(
+    json: bool,
+    csv: bool,
+    days: u32,
+    by_project: bool,
+    color: ColorMode,
+) -> Result<()> {
+    let settings = Settings::load()?;
+    let style = Style::detect(color, settings.notifications.threshold)
+        .with_scheme(ColorScheme::new(settings.display.colorblind_mode));
+    if by_project {
+        settings.validate()?;
+    }
+    if !settings.cost.enabled {
+        anyhow::bail!(
+            "Cost tracking is disabled. Set `enabled = true` under [cost] in {} to use this command.",
+            Settings::config_path()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "config.toml".to_string())
+        );
+    }
+
     let mut cost_store = CostStore::new();
+    cost_store.set_timezone(settings.cost.timezone);
+    cost_store.set_include_claude_desktop(settings.cost.include_claude_desktop);
+    cost_store.set_scan_days(days);
+    cost_store.set_project_filter(
+        &settings.cost.include_projects,
+        &settings.cost.exclude_projects,
+    );
 
     cost_store.refresh_pricing(false).await?;
 
-    let costs = cost_store.scan_all();
+    let mut costs = cost_store.scan_all();
+    let since = first_day(today(settings.cost.timezone), days);
+    for result in costs.values_mut() {
+        Arc::make_mut(&mut result.tokens)
+            .daily
+            .retain(|day| day.date >= since);
+    }
+    let projects = by_project.then(|| {
+        let budgets = &settings.cost.budgets;
+        let project_costs =
+            combined_project_costs(costs.values().map(|result| result.cost.as_ref()));
+        for project in unmatched_budgets(budgets, &project_costs) {
+            eprintln!(
+                "Warning: budget for {:?} matches no project in the scanned logs",
+                project
+            );
+        }
+        project_spend(&project_costs, budgets)
+    });
 
     if json {
-        let output = build_json_output(costs, days);
+        let output = build_json_output(costs, days, projects);
         println!("{}", serde_json::to_string_pretty(&output)?);
+    } else if csv {
+        write_csv(&costs, std::io::stdout().lock())?;
+    } else if let Some(projects) = projects {
+        print_project_output(&projects, &style);
     } else {
-        print_text_output(&costs);
+        print_text_output(&costs, &style);
     }
 
     Ok(())
 }
 
-fn build_json_output(costs: HashMap<Provider, CostScanResult>, days: u32) -> CostOutput {
+/// Prints the JSON Schema of `cost --json`.
+pub fn print_schema() -> Result<()> {
+    println!("{}", json_schema::<CostOutput>()?);
+    Ok(())
+}
+
+/// The first day of a `days`-long range ending `today`.
+fn first_day(today: NaiveDate, days: u32) -> NaiveDate {
+    today
+        .checked_sub_days(Days::new(u64::from(days.saturating_sub(1))))
+        .unwrap_or(NaiveDate::MIN)
+}
+
+/// Writes the per-model token rows `--json` reports as `daily_tokens`, as
+/// RFC 4180 CSV sorted by date, provider and model. The header is written
+/// even when there are no rows.
+fn write_csv(costs: &HashMap<Provider, CostScanResult>, out: impl Write) -> Result<()> {
+    let mut rows: Vec<CsvRow> = costs
+        .iter()
+        .flat_map(|(provider, result)| {
+            result.tokens.daily.iter().flat_map(move |day| {
+                day.models.iter().map(move |model| CsvRow {
+                    date: day.date,
+                    provider: provider.name(),
+                    model: &model.model,
+                    input_tokens: model.input_tokens,
+                    output_tokens: model.output_tokens,
+                    cache_read_tokens: model.cache_read_tokens,
+                    cache_creation_tokens: model.cache_creation_tokens,
+                    cost_usd: model.cost_usd,
+                })
+            })
+        })
+        .collect();
+    rows.sort_by(|a, b| (a.date, a.provider, a.model).cmp(&(b.date, b.provider, b.model)));
+
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(out);
+    writer.write_record(CSV_HEADER)?;
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn build_json_output(
+    costs: HashMap<Provider, CostScanResult>,
+    days: u32,
+    projects: Option<Vec<ProjectSpend>>,
+) -> CostOutput {
     let providers = costs
         .into_iter()
         .map(|(provider, result)| {
             let name = provider.name().to_string();
-            let snapshot = result.cost;
+            let snapshot = Arc::unwrap_or_clone(result.cost);
             let summary = CostSummary {
                 today: snapshot.today_cost,
                 monthly: snapshot.monthly_cost,
@@ -65,19 +218,32 @@ fn build_json_output(costs: HashMap<Provider, CostScanResult>, days: u32) -> Cos
                         cost: d.cost,
                     })
                     .collect(),
+                daily_tokens: Arc::unwrap_or_clone(result.tokens).daily,
             };
             (name, summary)
         })
         .collect();
 
     CostOutput {
+        schema_version: SCHEMA_VERSION,
         providers,
         scanned_at: Utc::now(),
         days,
+        projects: projects.map(|projects| {
+            projects
+                .into_iter()
+                .map(|p| ProjectSummary {
+                    percent: p.percent(),
+                    project: p.project,
+                    spent: p.spent,
+                    budget: p.budget,
+                })
+                .collect()
+        }),
     }
 }
 
-fn print_text_output(costs: &HashMap<Provider, CostScanResult>) {
+fn print_text_output(costs: &HashMap<Provider, CostScanResult>, style: &Style) {
     if costs.is_empty() {
         println!("No cost data found.");
         return;
@@ -89,7 +255,7 @@ fn print_text_output(costs: &HashMap<Provider, CostScanResult>) {
         }
 
         let cost = &snapshot.cost;
-        println!("{}", provider.name());
+        println!("{}", style.provider(Some(*provider), provider.name()));
         println!("  Today:      ${:.2}", cost.today_cost);
         println!("  This month: ${:.2}", cost.monthly_cost);
 
@@ -99,6 +265,62 @@ fn print_text_output(costs: &HashMap<Provider, CostScanResult>) {
     }
 }
 
+fn print_project_output(projects: &[ProjectSpend], style: &Style) {
+    if projects.is_empty() {
+        println!("No project cost data found.");
+        return;
+    }
+
+    let width = projects
+        .iter()
+        .map(|p| p.project.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("Project".len());
+    println!(
+        "{:<width$}  {:>10}  {:>10}  {:>6}",
+        "Project",
+        "Spent",
+        "Budget",
+        "Used",
+        width = width
+    );
+    for line in project_lines(projects, width, style) {
+        println!("{}", line);
+    }
+}
+
+/// One row per project, leaving the budget columns blank without a budget.
+/// Budget use is colored like usage, with "over" as a spent-out budget.
+fn project_lines(projects: &[ProjectSpend], width: usize, style: &Style) -> Vec<String> {
+    projects
+        .iter()
+        .map(|p| {
+            let budget = p
+                .budget
+                .map(|budget| format!("${:.2}", budget))
+                .unwrap_or_default();
+            let (percent, used) = match (p.budget, p.percent()) {
+                (_, Some(percent)) => (format!("{:.0}%", percent), percent / 100.0),
+                (Some(_), None) if p.spent > 0.0 => ("over".to_string(), 1.0),
+                _ => (String::new(), 0.0),
+            };
+            let line = format!(
+                "{:<width$}  {:>10}  {:>10}",
+                p.project,
+                format!("${:.2}", p.spent),
+                budget,
+                width = width
+            );
+            if percent.is_empty() {
+                return line.trim_end().to_string();
+            }
+            let pad = 6usize.saturating_sub(percent.len());
+            format!("{}  {:>pad$}{}", line, "", style.usage(used, &percent))
+        })
+        .collect()
+}
+
 fn print_daily_summary(breakdown: &[DailyCost]) {
     let mut daily_totals: HashMap<String, f64> = HashMap::new();
 
@@ -120,3 +342,177 @@ fn print_daily_summary(breakdown: &[DailyCost]) {
         println!("    {}: ${:.2}", date, cost);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{CostSnapshot, CostUsageTokenSnapshot, ModelTokenUsage};
+    use crate::core::schema::assert_schema_snapshot;
+
+    fn model(name: &str, input_tokens: u64, cost_usd: Option<f64>) -> ModelTokenUsage {
+        ModelTokenUsage {
+            model: name.to_string(),
+            input_tokens,
+            output_tokens: input_tokens / 10,
+            cache_creation_tokens: 7,
+            cache_read_tokens: input_tokens * 3,
+            cost_usd,
+        }
+    }
+
+    fn scan(daily: Vec<DailyTokenUsage>) -> CostScanResult {
+        CostScanResult {
+            cost: Arc::new(CostSnapshot::default()),
+            tokens: Arc::new(CostUsageTokenSnapshot {
+                session_tokens: None,
+                session_cost_usd: None,
+                session_start: None,
+                last_30_days_tokens: None,
+                last_30_days_cost_usd: None,
+                daily,
+                heatmap: Default::default(),
+                updated_at: Utc::now(),
+            }),
+        }
+    }
+
+    fn day(date: &str, models: Vec<ModelTokenUsage>) -> DailyTokenUsage {
+        DailyTokenUsage {
+            date: date.parse().unwrap(),
+            total_tokens: Some(models.iter().map(ModelTokenUsage::total_tokens).sum()),
+            cost_usd: models.iter().map(|m| m.cost_usd).sum(),
+            models,
+        }
+    }
+
+    #[test]
+    fn test_csv_matches_json_totals() {
+        let costs = HashMap::from([
+            (
+                Provider::Claude,
+                scan(vec![
+                    day(
+                        "2026-10-15",
+                        vec![
+                            model("claude-sonnet-4", 1_000, Some(0.42)),
+                            model("custom, \"fast\"", 50, None),
+                        ],
+                    ),
+                    day("2026-10-16", vec![model("claude-opus-4", 2_000, Some(1.5))]),
+                ]),
+            ),
+            (
+                Provider::Codex,
+                scan(vec![day(
+                    "2026-10-16",
+                    vec![model("gpt-5", 300, Some(0.1))],
+                )]),
+            ),
+        ]);
+
+        let mut out = Vec::new();
+        write_csv(&costs, &mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with(&format!("{}\r\n", CSV_HEADER.join(","))));
+        assert!(text.contains(",\"custom, \"\"fast\"\"\","));
+        assert_eq!(text.matches("\r\n").count(), 5);
+
+        // Sum each provider's rows, as a spreadsheet would.
+        let mut reader = csv::Reader::from_reader(text.as_bytes());
+        assert_eq!(reader.headers().unwrap(), CSV_HEADER.as_slice());
+        let mut csv_totals: HashMap<String, (u64, f64)> = HashMap::new();
+        for record in reader.records() {
+            let record = record.unwrap();
+            let total = csv_totals.entry(record[1].to_string()).or_default();
+            total.0 += (3..7)
+                .map(|i| record[i].parse::<u64>().unwrap())
+                .sum::<u64>();
+            total.1 += record[7].parse::<f64>().unwrap_or(0.0);
+        }
+
+        let json = serde_json::to_value(build_json_output(costs, 30, None)).unwrap();
+        let providers = json["providers"].as_object().unwrap();
+        assert_eq!(providers.len(), csv_totals.len());
+        for (name, summary) in providers {
+            let models: Vec<&serde_json::Value> = summary["daily_tokens"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .flat_map(|day| day["models"].as_array().unwrap())
+                .collect();
+            let tokens: u64 = models
+                .iter()
+                .flat_map(|m| {
+                    [
+                        "input_tokens",
+                        "output_tokens",
+                        "cache_creation_tokens",
+                        "cache_read_tokens",
+                    ]
+                    .map(|field| m[field].as_u64().unwrap())
+                })
+                .sum();
+            let cost: f64 = models.iter().filter_map(|m| m["cost_usd"].as_f64()).sum();
+            let (csv_tokens, csv_cost) = csv_totals[name];
+            assert_eq!(csv_tokens, tokens, "{}", name);
+            assert!((csv_cost - cost).abs() < 1e-9, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_first_day() {
+        let today: NaiveDate = "2026-10-16".parse().unwrap();
+        assert_eq!(first_day(today, 7), "2026-10-10".parse().unwrap());
+        assert_eq!(first_day(today, 1), today);
+        assert_eq!(first_day(today, 0), today);
+    }
+
+    #[test]
+    fn test_project_lines() {
+        let projects = vec![
+            ProjectSpend {
+                project: "acme-backend".to_string(),
+                spent: 41.2,
+                budget: Some(50.0),
+            },
+            ProjectSpend {
+                project: "scratch".to_string(),
+                spent: 1.0,
+                budget: None,
+            },
+            ProjectSpend {
+                project: "frozen".to_string(),
+                spent: 0.5,
+                budget: Some(0.0),
+            },
+        ];
+
+        let plain = Style::new(ColorMode::Never, false, true, 0.8);
+        assert_eq!(
+            project_lines(&projects, 12, &plain),
+            vec![
+                "acme-backend      $41.20      $50.00     82%",
+                "scratch            $1.00",
+                "frozen             $0.50       $0.00    over",
+            ]
+        );
+
+        let colored = Style::new(ColorMode::Always, false, false, 0.8);
+        assert_eq!(
+            project_lines(&projects, 12, &colored),
+            vec![
+                "acme-backend      $41.20      $50.00     \u{1b}[38;2;229;165;10m82%\u{1b}[0m",
+                "scratch            $1.00",
+                "frozen             $0.50       $0.00    \u{1b}[38;2;224;27;36mover\u{1b}[0m",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schema_snapshot() {
+        assert_schema_snapshot::<CostOutput>(
+            "cost",
+            include_str!("../../tests/fixtures/schema/cost.json"),
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use crate::core::schema::{Versioned, SCHEMA_VERSION};
+use crate::daemon::{bus_name, DBUS_NAME, DBUS_PATH};
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use zbus::fdo::DBusProxy;
+use zbus::names::{BusName, WellKnownName};
+
+pub async fn call_method(method: &str, autostart: bool) -> Result<()> {
+    let connection = zbus::Connection::session()
+        .await
+        .context("Failed to connect to session D-Bus")?;
+
+    ensure_daemon(&connection, autostart).await?;
+
+    let _reply: () = connection
+        .call_method(Some(bus_name()), DBUS_PATH, Some(DBUS_NAME), method, &())
+        .await
+        .with_context(|| format!("Failed to call {} method", method))?
+        .body()
+        .deserialize()
+        .context("Failed to deserialize response")?;
+
+    Ok(())
+}
+
+/// Calls a daemon method that returns a string, without starting the daemon.
+/// Returns `Ok(None)` when no daemon is running.
+pub async fn query_string<B>(method: &str, body: &B) -> Result<Option<String>>
+where
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let connection = zbus::Connection::session()
+        .await
+        .context("Failed to connect to session D-Bus")?;
+
+    let proxy = DBusProxy::new(&connection)
+        .await
+        .context("Failed to create D-Bus proxy")?;
+    if !daemon_running(&proxy).await? {
+        return Ok(None);
+    }
+
+    let reply: String = connection
+        .call_method(Some(bus_name()), DBUS_PATH, Some(DBUS_NAME), method, body)
+        .await
+        .with_context(|| format!("Failed to call {} method", method))?
+        .body()
+        .deserialize()
+        .context("Failed to deserialize response")?;
+
+    Ok(Some(reply))
+}
+
+/// Like `query_string`, for the methods that reply with `Versioned` JSON.
+/// Fails when the daemon is on another `schema_version`.
+pub async fn query_json<T, B>(method: &str, body: &B) -> Result<Option<T>>
+where
+    T: DeserializeOwned,
+    B: serde::Serialize + zbus::zvariant::DynamicType,
+{
+    let Some(reply) = query_string(method, body).await? else {
+        return Ok(None);
+    };
+    parse_versioned(method, &reply).map(Some)
+}
+
+fn parse_versioned<T: DeserializeOwned>(method: &str, reply: &str) -> Result<T> {
+    let version: Versioned<serde::de::IgnoredAny> = serde_json::from_str(reply)
+        .with_context(|| format!("Invalid {} reply from daemon", method))?;
+    if version.schema_version != SCHEMA_VERSION {
+        anyhow::bail!(
+            "daemon replied with schema version {}, expected {}; restart it",
+            version.schema_version,
+            SCHEMA_VERSION
+        );
+    }
+    let reply: Versioned<T> = serde_json::from_str(reply)
+        .with_context(|| format!("Invalid {} reply from daemon", method))?;
+    Ok(reply.data)
+}
+
+async fn daemon_running(proxy: &DBusProxy<'_>) -> Result<bool> {
+    let name = WellKnownName::try_from(bus_name())?;
+    proxy
+        .name_has_owner(BusName::from(name))
+        .await
+        .context("Failed to query D-Bus name owner")
+}
+
+async fn ensure_daemon(connection: &zbus::Connection, autostart: bool) -> Result<()> {
+    let proxy = DBusProxy::new(connection)
+        .await
+        .context("Failed to create D-Bus proxy")?;
+
+    if daemon_running(&proxy).await? {
+        return Ok(());
+    }
+
+    if !autostart {
+        anyhow::bail!("daemon not running (start it with `claude-bar daemon` or pass --autostart)");
+    }
+
+    tracing::info!("Daemon not running, requesting D-Bus activation");
+    let name = WellKnownName::try_from(bus_name())?;
+    proxy.start_service_by_name(name, 0).await.context(
+        "daemon not running and D-Bus activation failed - run `claude-bar install-service` first",
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_versioned_checks_schema_version() {
+        let reply = serde_json::to_string(&Versioned::new(vec![1u32, 2])).unwrap();
+        assert_eq!(
+            parse_versioned::<Vec<u32>>("GetThings", &reply).unwrap(),
+            [1, 2]
+        );
+
+        let stale = r#"{"schema_version":1,"data":{"claude":[]}}"#;
+        let error = parse_versioned::<Vec<u32>>("GetThings", stale).unwrap_err();
+        assert!(error.to_string().contains("schema version 1"));
+
+        let unversioned = r#"[1, 2]"#;
+        assert!(parse_versioned::<Vec<u32>>("GetThings", unversioned).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use crate::icons::named_icon_files;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+pub async fn run(dry_run: bool, uninstall: bool) -> Result<()> {
+    let data_dir = dirs::data_dir().context("Could not determine data directory")?;
+    let files = named_icon_files(&data_dir);
+
+    if uninstall {
+        remove_icons(&files, dry_run)
+    } else {
+        install_icons(&files, dry_run)?;
+        if !dry_run {
+            println!();
+            println!("Set `icon_mode = \"named\"` under [display] to use them.");
+        }
+        Ok(())
+    }
+}
+
+/// The icons are generated, so any that differ are simply rewritten.
+fn install_icons(files: &[(PathBuf, String)], dry_run: bool) -> Result<()> {
+    for (path, contents) in files {
+        if is_unchanged(path, contents) {
+            println!("Up to date: {}", path.display());
+            continue;
+        }
+
+        if dry_run {
+            println!("Would write {}", path.display());
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::write(path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        println!("Wrote {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn remove_icons(files: &[(PathBuf, String)], dry_run: bool) -> Result<()> {
+    for (path, _) in files {
+        if !path.exists() {
+            println!("Not installed: {}", path.display());
+            continue;
+        }
+
+        if dry_run {
+            println!("Would remove {}", path.display());
+            continue;
+        }
+
+        std::fs::remove_file(path)
+            .with_context(|| format!("Failed to remove {}", path.display()))?;
+        println!("Removed {}", path.display());
+    }
+
+    Ok(())
+}
+
+fn is_unchanged(path: &Path, contents: &str) -> bool {
+    std::fs::read_to_string(path).is_ok_and(|existing| existing == contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_and_remove_icons() {
+        let data_dir = std::env::temp_dir().join(format!(
+            "claude-bar-install-icons-test-{}",
+            std::process::id()
+        ));
+        let files = named_icon_files(&data_dir);
+
+        install_icons(&files, true).unwrap();
+        assert!(files.iter().all(|(path, _)| !path.exists()));
+
+        install_icons(&files, false).unwrap();
+        for (path, contents) in &files {
+            assert!(is_unchanged(path, contents), "{}", path.display());
+        }
+
+        std::fs::write(&files[0].0, "edited").unwrap();
+        assert!(!is_unchanged(&files[0].0, &files[0].1));
+        install_icons(&files, false).unwrap();
+        assert!(is_unchanged(&files[0].0, &files[0].1));
+
+        remove_icons(&files, false).unwrap();
+        assert!(files.iter().all(|(path, _)| !path.exists()));
+
+        std::fs::remove_dir_all(&data_dir).unwrap();
+    }
+}
@@ -0,0 +1,275 @@
+use crate::core::instance;
+use crate::core::settings::Settings;
+use crate::daemon::DBUS_NAME;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+const SYSTEMD_UNIT_NAME: &str = "claude-bar";
+const DBUS_SERVICE_FILE_NAME: &str = "com.github.kabilan.claudebar";
+
+struct ServiceFile {
+    path: PathBuf,
+    contents: String,
+}
+
+pub async fn run(dry_run: bool, uninstall: bool, force: bool) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to determine claude-bar executable path")?;
+    let suffix = instance::suffix();
+    let config = Settings::config_path_override();
+    let files = service_files(&exe, suffix, config.as_deref())?;
+
+    if uninstall {
+        remove_files(&files, dry_run)
+    } else {
+        install_files(&files, dry_run, force, &unit_name(suffix))
+    }
+}
+
+/// Each daemon instance gets its own unit, e.g. `claude-bar-work`.
+fn unit_name(suffix: Option<&str>) -> String {
+    instance::scoped_with(SYSTEMD_UNIT_NAME, '-', suffix)
+}
+
+fn service_files(
+    exe: &Path,
+    suffix: Option<&str>,
+    config: Option<&Path>,
+) -> Result<Vec<ServiceFile>> {
+    let config_dir = dirs::config_dir().context("Could not determine config directory")?;
+    let data_dir = dirs::data_dir().context("Could not determine data directory")?;
+    let dbus_file_name = format!(
+        "{}.service",
+        instance::scoped_with(DBUS_SERVICE_FILE_NAME, '.', suffix)
+    );
+
+    Ok(vec![
+        ServiceFile {
+            path: config_dir
+                .join("systemd/user")
+                .join(format!("{}.service", unit_name(suffix))),
+            contents: systemd_unit(exe, suffix, config),
+        },
+        ServiceFile {
+            path: data_dir.join("dbus-1/services").join(dbus_file_name),
+            contents: dbus_service(exe, suffix, config),
+        },
+    ])
+}
+
+/// The daemon command line, passing the instance along so the unit starts
+/// the daemon that owns the unit's bus name, and the config file when one
+/// was given with `--config` or `CLAUDE_BAR_CONFIG`.
+fn daemon_command(exe: &Path, suffix: Option<&str>, config: Option<&Path>) -> String {
+    let mut command = exe.display().to_string();
+    if let Some(suffix) = suffix {
+        command.push_str(&format!(" --instance {}", suffix));
+    }
+    if let Some(config) = config {
+        command.push_str(&format!(" --config {}", config.display()));
+    }
+    command.push_str(" daemon");
+    command
+}
+
+fn systemd_unit(exe: &Path, suffix: Option<&str>, config: Option<&Path>) -> String {
+    format!(
+        "[Unit]
+Description=Claude Bar usage monitor
+After=graphical-session-pre.target
+PartOf=graphical-session.target
+
+[Service]
+Type=dbus
+BusName={bus_name}
+ExecStart={command}
+Restart=on-failure
+RestartSec=5
+
+[Install]
+WantedBy=graphical-session.target
+",
+        bus_name = instance::scoped_with(DBUS_NAME, '.', suffix),
+        command = daemon_command(exe, suffix, config),
+    )
+}
+
+fn dbus_service(exe: &Path, suffix: Option<&str>, config: Option<&Path>) -> String {
+    format!(
+        "[D-BUS Service]
+Name={bus_name}
+Exec={command}
+SystemdService={unit}.service
+",
+        bus_name = instance::scoped_with(DBUS_NAME, '.', suffix),
+        command = daemon_command(exe, suffix, config),
+        unit = unit_name(suffix),
+    )
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum FileStatus {
+    Missing,
+    Unchanged,
+    Different,
+}
+
+fn file_status(file: &ServiceFile) -> Result<FileStatus> {
+    if !file.path.exists() {
+        return Ok(FileStatus::Missing);
+    }
+    let existing = std::fs::read_to_string(&file.path)
+        .with_context(|| format!("Failed to read {}", file.path.display()))?;
+    if existing == file.contents {
+        Ok(FileStatus::Unchanged)
+    } else {
+        Ok(FileStatus::Different)
+    }
+}
+
+fn install_files(files: &[ServiceFile], dry_run: bool, force: bool, unit: &str) -> Result<()> {
+    let statuses = files.iter().map(file_status).collect::<Result<Vec<_>>>()?;
+
+    if !force {
+        if let Some((file, _)) = files
+            .iter()
+            .zip(&statuses)
+            .find(|(_, status)| **status == FileStatus::Different)
+        {
+            anyhow::bail!(
+                "{} already exists with different content; re-run with --force to overwrite",
+                file.path.display()
+            );
+        }
+    }
+
+    for (file, status) in files.iter().zip(&statuses) {
+        if *status == FileStatus::Unchanged {
+            println!("Up to date: {}", file.path.display());
+            continue;
+        }
+
+        if dry_run {
+            println!("Would write {}:\n", file.path.display());
+            println!("{}", file.contents);
+            continue;
+        }
+
+        if let Some(parent) = file.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        std::fs::write(&file.path, &file.contents)
+            .with_context(|| format!("Failed to write {}", file.path.display()))?;
+        println!("Wrote {}", file.path.display());
+    }
+
+    if !dry_run {
+        println!();
+        println!("Run `systemctl --user daemon-reload` to pick up the new unit.");
+        println!(
+            "Enable it at login with `systemctl --user enable --now {}`.",
+            unit
+        );
+    }
+
+    Ok(())
+}
+
+fn remove_files(files: &[ServiceFile], dry_run: bool) -> Result<()> {
+    for file in files {
+        if !file.path.exists() {
+            println!("Not installed: {}", file.path.display());
+            continue;
+        }
+
+        if dry_run {
+            println!("Would remove {}", file.path.display());
+            continue;
+        }
+
+        std::fs::remove_file(&file.path)
+            .with_context(|| format!("Failed to remove {}", file.path.display()))?;
+        println!("Removed {}", file.path.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_files_reference_executable() {
+        let exe = Path::new("/usr/bin/claude-bar");
+
+        let unit = systemd_unit(exe, None, None);
+        assert!(unit.contains("Type=dbus"));
+        assert!(unit.contains("ExecStart=/usr/bin/claude-bar daemon"));
+        assert!(unit.contains(&format!("BusName={}", DBUS_NAME)));
+
+        let service = dbus_service(exe, None, None);
+        assert!(service.contains(&format!("Name={}", DBUS_NAME)));
+        assert!(service.contains("Exec=/usr/bin/claude-bar daemon"));
+        assert!(service.contains("SystemdService=claude-bar.service"));
+    }
+
+    #[test]
+    fn test_service_files_for_instance() {
+        let exe = Path::new("/usr/bin/claude-bar");
+
+        let unit = systemd_unit(exe, Some("work"), None);
+        assert!(unit.contains("ExecStart=/usr/bin/claude-bar --instance work daemon"));
+        assert!(unit.contains("BusName=com.github.kabilan.ClaudeBar.work"));
+
+        let service = dbus_service(exe, Some("work"), None);
+        assert!(service.contains("Name=com.github.kabilan.ClaudeBar.work"));
+        assert!(service.contains("SystemdService=claude-bar-work.service"));
+
+        let files = service_files(exe, Some("work"), None).unwrap();
+        assert!(files[0]
+            .path
+            .ends_with("systemd/user/claude-bar-work.service"));
+        assert!(files[1]
+            .path
+            .ends_with("dbus-1/services/com.github.kabilan.claudebar.work.service"));
+    }
+
+    #[test]
+    fn test_service_files_pass_config_override() {
+        let exe = Path::new("/usr/bin/claude-bar");
+        let config = Path::new("/nix/store/abc-claude-bar-config.toml");
+
+        let unit = systemd_unit(exe, Some("work"), Some(config));
+        assert!(unit.contains(
+            "ExecStart=/usr/bin/claude-bar --instance work \
+             --config /nix/store/abc-claude-bar-config.toml daemon"
+        ));
+        let service = dbus_service(exe, None, Some(config));
+        assert!(service.contains(
+            "Exec=/usr/bin/claude-bar --config /nix/store/abc-claude-bar-config.toml daemon"
+        ));
+    }
+
+    #[test]
+    fn test_file_status_detects_changes() {
+        let path = std::env::temp_dir().join(format!(
+            "claude-bar-install-service-test-{}",
+            std::process::id()
+        ));
+        let file = ServiceFile {
+            path: path.clone(),
+            contents: "expected".to_string(),
+        };
+
+        assert_eq!(file_status(&file).unwrap(), FileStatus::Missing);
+
+        std::fs::write(&path, "expected").unwrap();
+        assert_eq!(file_status(&file).unwrap(), FileStatus::Unchanged);
+
+        std::fs::write(&path, "edited").unwrap();
+        assert_eq!(file_status(&file).unwrap(), FileStatus::Different);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+use crate::cli::source::{CredentialOverrides, UsageSource};
+use crate::core::limits_file::{LimitsFile, ProviderLimits};
+use crate::core::models::Provider;
+use crate::core::schema::json_schema;
+use crate::core::settings::Settings;
+use crate::core::status_output::format_reset_time;
+use anyhow::{Context, Result};
+
+/// Exit status when usage is over `--max`. A Claude Code PreToolUse hook
+/// that exits with 2 blocks the tool call and shows Claude the reason.
+pub const EXCEEDED_EXIT_CODE: i32 = 2;
+
+/// Whether `provider` is within `max`. Prints the reason to stderr when it
+/// isn't. With `credentials` given, fetches from those files directly
+/// without reading the config file or asking the daemon.
+pub async fn run(provider: String, max: f64, credentials: CredentialOverrides) -> Result<bool> {
+    if !(0.0..=1.0).contains(&max) {
+        anyhow::bail!("--max must be between 0.0 and 1.0, got {}", max);
+    }
+    let id = provider;
+    let provider = Provider::from_id(&id).with_context(|| {
+        format!(
+            "Unknown provider: {}. Valid providers: claude, codex, codex-<n>",
+            id
+        )
+    })?;
+
+    let mut source = if credentials.is_empty() {
+        let settings = Settings::load()?;
+        UsageSource::for_provider(&settings, provider)
+    } else {
+        UsageSource::direct(credentials.providers(Some(&id))?)
+    };
+    if source.is_empty() {
+        anyhow::bail!("{} is not enabled. Check your configuration.", id);
+    }
+    let usage = source
+        .read()
+        .await
+        .into_iter()
+        .next()
+        .context("No usage returned")?;
+    let Some(snapshot) = usage.snapshot else {
+        anyhow::bail!(
+            "No usage for {}: {}",
+            usage.name,
+            usage.error.as_deref().unwrap_or("not fetched yet")
+        );
+    };
+
+    let limits = ProviderLimits::from_snapshot(usage.name, &snapshot);
+    match over_limit_message(&limits, max) {
+        Some(message) => {
+            eprintln!("{}", message);
+            Ok(false)
+        }
+        None => Ok(true),
+    }
+}
+
+/// Prints the JSON Schema of the limits file.
+pub fn print_schema() -> Result<()> {
+    println!("{}", json_schema::<LimitsFile>()?);
+    Ok(())
+}
+
+fn over_limit_message(limits: &ProviderLimits, max: f64) -> Option<String> {
+    let (label, window) = limits.exceeded_window(max)?;
+    let mut message = format!(
+        "{} {} usage is at {:.0}%, over the {:.0}% limit",
+        limits.name,
+        label,
+        window.used_percent * 100.0,
+        max * 100.0
+    );
+    if let Some(resets_at) = window.resets_at {
+        message.push_str(&format!("; resets {}", format_reset_time(resets_at)));
+    }
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::limits_file::WindowLimit;
+    use chrono::Utc;
+
+    #[test]
+    fn test_over_limit_message() {
+        let limits = ProviderLimits {
+            name: "Claude Code".to_string(),
+            max_used_percent: 0.93,
+            session: Some(WindowLimit {
+                used_percent: 0.93,
+                resets_at: None,
+            }),
+            weekly: Some(WindowLimit {
+                used_percent: 0.41,
+                resets_at: None,
+            }),
+            updated_at: Utc::now(),
+        };
+
+        assert_eq!(
+            over_limit_message(&limits, 0.9).as_deref(),
+            Some("Claude Code session usage is at 93%, over the 90% limit")
+        );
+        assert_eq!(over_limit_message(&limits, 0.93), None);
+        assert_eq!(over_limit_message(&limits, 1.0), None);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::core::models::Provider;
+use crate::core::settings::Settings;
+use crate::daemon::login::{
+    run_provider_login, trigger_refresh, LoginEvent, LoginMode, LoginOutcome,
+};
+use crate::providers::{ClaudeProvider, CodexProvider, UsageProvider};
+use anyhow::Result;
+use std::io::Write;
+
+const EXIT_FAILED: i32 = 1;
+const EXIT_TIMED_OUT: i32 = 2;
+const EXIT_MISSING_BINARY: i32 = 3;
+const EXIT_VERIFY_FAILED: i32 = 4;
+
+pub async fn run(provider: String, no_browser: bool) -> Result<()> {
+    let Some(provider) = Provider::from_id(&provider) else {
+        anyhow::bail!(
+            "Unknown provider: {}. Valid providers: claude, codex, codex-<n>",
+            provider
+        );
+    };
+
+    let settings = Settings::load()?;
+    let usage_provider = build_provider(&settings, provider);
+    let open_browser = !no_browser && has_display();
+
+    println!("Starting {} login...", usage_provider.display_name());
+    let result = tokio::task::spawn_blocking(move || {
+        let mut stdout = std::io::stdout();
+        run_provider_login(
+            provider,
+            LoginMode::Reauthenticate,
+            &mut |event| match event {
+                LoginEvent::Output(text) => {
+                    let _ = stdout.write_all(text.as_bytes());
+                    let _ = stdout.flush();
+                }
+                LoginEvent::AuthLink(url) => announce_link(url, open_browser),
+            },
+        )
+    })
+    .await?;
+    println!();
+
+    let code = match result.outcome {
+        LoginOutcome::Success => None,
+        LoginOutcome::TimedOut => {
+            eprintln!("Login timed out");
+            Some(EXIT_TIMED_OUT)
+        }
+        LoginOutcome::MissingBinary => {
+            eprintln!("Login failed: {}", usage_provider.credential_error_hint());
+            Some(EXIT_MISSING_BINARY)
+        }
+        LoginOutcome::Failed(status) => {
+            eprintln!("Login failed (exit code {})", status);
+            Some(EXIT_FAILED)
+        }
+        LoginOutcome::LaunchFailed(message) => {
+            eprintln!("Login failed to start: {}", message);
+            Some(EXIT_FAILED)
+        }
+    };
+    if let Some(code) = code {
+        if let Some(url) = result.auth_link.as_deref() {
+            eprintln!("Last auth URL: {}", url);
+        }
+        std::process::exit(code);
+    }
+
+    if !usage_provider.has_valid_credentials() {
+        eprintln!(
+            "Login finished but no credentials were found. {}",
+            usage_provider.credential_error_hint()
+        );
+        std::process::exit(EXIT_VERIFY_FAILED);
+    }
+
+    match usage_provider.fetch_usage().await {
+        Ok(snapshot) => {
+            let plan = snapshot.identity.plan.as_deref().unwrap_or("unknown plan");
+            match snapshot.identity.email.as_deref() {
+                Some(email) => println!("Logged in as {} ({})", email, plan),
+                None => println!("Logged in ({})", plan),
+            }
+        }
+        Err(e) => {
+            eprintln!("Login finished but fetching usage failed: {}", e);
+            std::process::exit(EXIT_VERIFY_FAILED);
+        }
+    }
+
+    // Let a running daemon pick up the new credentials; it's fine if none is.
+    let _ = tokio::task::spawn_blocking(trigger_refresh).await;
+    Ok(())
+}
+
+fn build_provider(settings: &Settings, provider: Provider) -> Box<dyn UsageProvider> {
+    match provider {
+        Provider::Claude => Box::new(ClaudeProvider::new()),
+        Provider::Codex | Provider::CodexWorkspace(_) => {
+            let codex = &settings.providers.codex;
+            Box::new(CodexProvider::for_workspace(
+                provider,
+                codex.account_id_for(provider),
+                codex.label_for(provider),
+            ))
+        }
+    }
+}
+
+fn has_display() -> bool {
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| std::env::var_os(var).is_some_and(|value| !value.is_empty()))
+}
+
+fn announce_link(url: &str, open_browser: bool) {
+    if open_browser && open::that(url).is_ok() {
+        println!("\nOpened {} in your browser", url);
+        return;
+    }
+
+    println!();
+    println!("==================================================");
+    println!("Open this URL in a browser to finish logging in:");
+    println!();
+    println!("    {}", url);
+    println!();
+    println!("==================================================");
+}
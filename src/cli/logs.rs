@@ -0,0 +1,376 @@
+use crate::core::log_file::{log_file_path, rotated_path, KEPT_LOG_FILES};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local, Utc};
+use clap::ValueEnum;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+
+const FOLLOW_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    fn from_json(level: &str) -> Option<Self> {
+        match level {
+            "TRACE" => Some(Self::Trace),
+            "DEBUG" => Some(Self::Debug),
+            "INFO" => Some(Self::Info),
+            "WARN" => Some(Self::Warn),
+            "ERROR" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Trace => "TRACE",
+            Self::Debug => "DEBUG",
+            Self::Info => "INFO",
+            Self::Warn => "WARN",
+            Self::Error => "ERROR",
+        }
+    }
+}
+
+/// Parses `--since` values like `90s`, `15m`, `1h` or `2d`.
+pub fn parse_since(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let split = input.char_indices().last().map_or(0, |(i, _)| i);
+    let (amount, unit) = input.split_at(split);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("expected a number and a unit (s, m, h, d), got {:?}", input))?;
+    match unit {
+        "s" => Ok(Duration::seconds(amount)),
+        "m" => Ok(Duration::minutes(amount)),
+        "h" => Ok(Duration::hours(amount)),
+        "d" => Ok(Duration::days(amount)),
+        _ => Err(format!("unknown unit in {:?}; use s, m, h or d", input)),
+    }
+}
+
+pub async fn run(follow: bool, since: Option<Duration>, level: Option<LogLevel>) -> Result<()> {
+    let path = log_file_path().context("Could not determine data directory")?;
+    let filter = Filter {
+        min_level: level,
+        since: since.map(|since| Utc::now() - since),
+    };
+
+    let rotated: Vec<PathBuf> = (1..=KEPT_LOG_FILES)
+        .rev()
+        .map(|n| rotated_path(&path, n))
+        .filter(|path| path.exists())
+        .collect();
+    if !follow && rotated.is_empty() && !path.exists() {
+        anyhow::bail!(
+            "No daemon log at {}. Is the daemon running?",
+            path.display()
+        );
+    }
+
+    // Oldest rotated file first, then the live log.
+    for rotated_path in rotated {
+        let file = File::open(&rotated_path)
+            .with_context(|| format!("Failed to read {}", rotated_path.display()))?;
+        for line in BufReader::new(file).lines() {
+            print_line(&line?, &filter);
+        }
+    }
+
+    let mut follower = Follower::new(path);
+    loop {
+        for line in follower.poll()? {
+            print_line(&line, &filter);
+        }
+        if !follow {
+            return Ok(());
+        }
+        tokio::time::sleep(FOLLOW_POLL_INTERVAL).await;
+    }
+}
+
+fn print_line(line: &str, filter: &Filter) {
+    if let Some(output) = render(line, filter) {
+        println!("{}", output);
+    }
+}
+
+struct Filter {
+    min_level: Option<LogLevel>,
+    since: Option<DateTime<Utc>>,
+}
+
+impl Filter {
+    fn is_empty(&self) -> bool {
+        self.min_level.is_none() && self.since.is_none()
+    }
+
+    fn matches(&self, entry: &LogEntry) -> bool {
+        self.min_level.is_none_or(|min| entry.level >= min)
+            && self.since.is_none_or(|since| entry.timestamp >= since)
+    }
+}
+
+/// A line of the daemon's JSON log, pretty-printed when it matches `filter`.
+/// Lines that aren't log entries pass through unless a filter is set.
+fn render(line: &str, filter: &Filter) -> Option<String> {
+    if line.trim().is_empty() {
+        return None;
+    }
+    match parse_entry(line) {
+        Some(entry) => filter.matches(&entry).then(|| entry.format()),
+        None => filter.is_empty().then(|| line.to_string()),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct LogEntry {
+    timestamp: DateTime<Utc>,
+    level: LogLevel,
+    target: String,
+    message: String,
+    fields: Vec<(String, String)>,
+}
+
+impl LogEntry {
+    fn format(&self) -> String {
+        let mut line = format!(
+            "{} {:<5} {}: {}",
+            self.timestamp
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            self.level.label(),
+            self.target,
+            self.message
+        );
+        for (key, value) in &self.fields {
+            line.push_str(&format!(" {}={}", key, value));
+        }
+        line
+    }
+}
+
+fn parse_entry(line: &str) -> Option<LogEntry> {
+    let json: Value = serde_json::from_str(line).ok()?;
+    let timestamp = DateTime::parse_from_rfc3339(json.get("timestamp")?.as_str()?)
+        .ok()?
+        .with_timezone(&Utc);
+    let level = LogLevel::from_json(json.get("level")?.as_str()?)?;
+    let target = json
+        .get("target")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    let mut message = String::new();
+    let mut fields = Vec::new();
+    if let Some(Value::Object(map)) = json.get("fields") {
+        for (key, value) in map {
+            let value = match value {
+                Value::String(s) if s.contains(char::is_whitespace) && key != "message" => {
+                    format!("{:?}", s)
+                }
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            };
+            if key == "message" {
+                message = value;
+            } else {
+                fields.push((key.clone(), value));
+            }
+        }
+    }
+
+    Some(LogEntry {
+        timestamp,
+        level,
+        target,
+        message,
+        fields,
+    })
+}
+
+/// Reads lines appended to a log file, reopening it when the daemon rotates
+/// it away or it gets truncated.
+struct Follower {
+    path: PathBuf,
+    reader: Option<BufReader<File>>,
+    inode: Option<u64>,
+    offset: u64,
+    partial: String,
+}
+
+impl Follower {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            reader: None,
+            inode: None,
+            offset: 0,
+            partial: String::new(),
+        }
+    }
+
+    /// Complete lines written since the last poll.
+    fn poll(&mut self) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        if self.reader.is_none() && !self.reopen()? {
+            return Ok(lines);
+        }
+        // Finish the current file first, even if it was just rotated away.
+        self.drain(&mut lines)?;
+
+        let replaced = fs::metadata(&self.path)
+            .is_ok_and(|meta| Some(meta.ino()) != self.inode || meta.len() < self.offset);
+        if replaced && self.reopen()? {
+            self.drain(&mut lines)?;
+        }
+        Ok(lines)
+    }
+
+    fn reopen(&mut self) -> io::Result<bool> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        self.inode = Some(file.metadata()?.ino());
+        self.reader = Some(BufReader::new(file));
+        self.offset = 0;
+        Ok(true)
+    }
+
+    fn drain(&mut self, lines: &mut Vec<String>) -> io::Result<()> {
+        let Some(reader) = self.reader.as_mut() else {
+            return Ok(());
+        };
+        loop {
+            let read = reader.read_line(&mut self.partial)?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.offset += read as u64;
+            if self.partial.ends_with('\n') {
+                lines.push(self.partial.trim_end().to_string());
+                self.partial.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::Path;
+
+    const WARN_LINE: &str = r#"{"timestamp":"2026-03-04T12:30:05.123456Z","level":"WARN","fields":{"message":"Pricing refresh failed, retrying in 5 minutes","error":"connection reset","attempt":2},"target":"claude_bar::daemon::app"}"#;
+    const INFO_LINE: &str = r#"{"timestamp":"2026-03-04T12:31:00Z","level":"INFO","fields":{"message":"Manual refresh requested"},"target":"claude_bar::daemon::app"}"#;
+
+    #[test]
+    fn test_parse_since() {
+        assert_eq!(parse_since("90s"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_since("15m"), Ok(Duration::minutes(15)));
+        assert_eq!(parse_since("1h"), Ok(Duration::hours(1)));
+        assert_eq!(parse_since("2d"), Ok(Duration::days(2)));
+        assert!(parse_since("1w").is_err());
+        assert!(parse_since("h").is_err());
+        assert!(parse_since("").is_err());
+        assert!(parse_since("5µ").is_err());
+        assert!(parse_since("1ä").is_err());
+    }
+
+    #[test]
+    fn test_parse_and_format_entry() {
+        let entry = parse_entry(WARN_LINE).unwrap();
+        assert_eq!(entry.level, LogLevel::Warn);
+        assert_eq!(entry.target, "claude_bar::daemon::app");
+        assert_eq!(
+            entry.message,
+            "Pricing refresh failed, retrying in 5 minutes"
+        );
+        assert_eq!(
+            entry.fields,
+            vec![
+                ("attempt".to_string(), "2".to_string()),
+                ("error".to_string(), "\"connection reset\"".to_string()),
+            ]
+        );
+
+        let formatted = entry.format();
+        assert!(formatted.contains(" WARN  claude_bar::daemon::app: "));
+        assert!(formatted.ends_with("in 5 minutes attempt=2 error=\"connection reset\""));
+
+        assert_eq!(parse_entry("not json"), None);
+    }
+
+    #[test]
+    fn test_level_and_since_filters() {
+        let none = Filter {
+            min_level: None,
+            since: None,
+        };
+        assert!(render(INFO_LINE, &none).is_some());
+        assert_eq!(render("plain text", &none).as_deref(), Some("plain text"));
+
+        let warn = Filter {
+            min_level: Some(LogLevel::Warn),
+            since: None,
+        };
+        assert!(render(WARN_LINE, &warn).is_some());
+        assert!(render(INFO_LINE, &warn).is_none());
+        assert!(render("plain text", &warn).is_none());
+
+        let recent = Filter {
+            min_level: None,
+            since: Some("2026-03-04T12:31:00Z".parse().unwrap()),
+        };
+        assert!(render(WARN_LINE, &recent).is_none());
+        assert!(render(INFO_LINE, &recent).is_some());
+    }
+
+    #[test]
+    fn test_follower_reads_across_rotation() {
+        let dir = std::env::temp_dir().join(format!("claude-bar-logs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("claude-bar.log");
+        let append = |path: &Path, text: &str| {
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .unwrap();
+            file.write_all(text.as_bytes()).unwrap();
+        };
+
+        let mut follower = Follower::new(path.clone());
+        assert!(follower.poll().unwrap().is_empty());
+
+        append(&path, "one\ntwo\nthr");
+        assert_eq!(follower.poll().unwrap(), vec!["one", "two"]);
+        append(&path, "ee\n");
+        assert_eq!(follower.poll().unwrap(), vec!["three"]);
+
+        // The daemon finishes the old file, then rotates it away.
+        append(&path, "four\n");
+        fs::rename(&path, rotated_path(&path, 1)).unwrap();
+        append(&path, "five\n");
+        assert_eq!(follower.poll().unwrap(), vec!["four", "five"]);
+
+        append(&path, "six\n");
+        assert_eq!(follower.poll().unwrap(), vec!["six"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,4 +1,15 @@
 pub mod cost;
+mod daemon_client;
+pub mod install_icons;
+pub mod install_service;
+pub mod limit_check;
+pub mod login;
+pub mod logs;
 pub mod refresh;
 pub mod refresh_pricing;
+mod source;
+pub mod stats;
 pub mod status;
+mod status_template;
+pub mod style;
+pub mod swaybar;
@@ -1,24 +1,8 @@
-use crate::daemon::{DBUS_NAME, DBUS_PATH};
-use anyhow::{Context, Result};
+use crate::cli::daemon_client;
+use anyhow::Result;
 
-pub async fn run() -> Result<()> {
-    let connection = zbus::Connection::session()
-        .await
-        .context("Failed to connect to session D-Bus")?;
-
-    let _reply: () = connection
-        .call_method(
-            Some(DBUS_NAME),
-            DBUS_PATH,
-            Some(DBUS_NAME),
-            "Refresh",
-            &(),
-        )
-        .await
-        .context("Failed to call Refresh method - is the daemon running?")?
-        .body()
-        .deserialize()
-        .context("Failed to deserialize response")?;
+pub async fn run(autostart: bool) -> Result<()> {
+    daemon_client::call_method("Refresh", autostart).await?;
 
     println!("Refresh triggered successfully");
     Ok(())
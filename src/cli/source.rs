@@ -0,0 +1,270 @@
+use crate::cli::daemon_client;
+use crate::core::models::{Provider, UsageReport, UsageSnapshot};
+use crate::core::settings::Settings;
+use crate::providers::{ClaudeProvider, CodexProvider, UsageProvider};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Without a daemon every read hits the provider APIs directly, so results
+/// are reused for at least this long.
+const DIRECT_FETCH_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone)]
+pub struct ProviderUsage {
+    pub provider: Provider,
+    pub name: String,
+    pub snapshot: Option<UsageSnapshot>,
+    pub error: Option<String>,
+}
+
+/// Usage for the enabled providers, read from the running daemon over D-Bus
+/// when there is one and fetched directly otherwise.
+pub struct UsageSource {
+    providers: Vec<Box<dyn UsageProvider>>,
+    /// Off for `UsageSource::direct`, which never asks the daemon.
+    daemon: bool,
+    direct_interval: Duration,
+    last_direct: Option<(Instant, Vec<ProviderUsage>)>,
+}
+
+impl UsageSource {
+    pub fn new(settings: &Settings) -> Self {
+        Self {
+            providers: build_provider_list(settings, None),
+            daemon: true,
+            direct_interval: DIRECT_FETCH_INTERVAL,
+            last_direct: None,
+        }
+    }
+
+    /// The providers `status --provider` would pick.
+    pub fn filtered(settings: &Settings, provider_filter: Option<&str>) -> Self {
+        Self {
+            providers: build_provider_list(settings, provider_filter),
+            daemon: true,
+            direct_interval: DIRECT_FETCH_INTERVAL,
+            last_direct: None,
+        }
+    }
+
+    /// Only `provider`; empty when it isn't enabled.
+    pub fn for_provider(settings: &Settings, provider: Provider) -> Self {
+        Self {
+            providers: build_provider_list(settings, None)
+                .into_iter()
+                .filter(|p| p.identifier() == provider)
+                .collect(),
+            daemon: true,
+            direct_interval: DIRECT_FETCH_INTERVAL,
+            last_direct: None,
+        }
+    }
+
+    /// Always fetches `providers` directly, even with a daemon running.
+    pub fn direct(providers: Vec<Box<dyn UsageProvider>>) -> Self {
+        Self {
+            providers,
+            daemon: false,
+            direct_interval: DIRECT_FETCH_INTERVAL,
+            last_direct: None,
+        }
+    }
+
+    /// Reuses direct fetches for `interval` instead of five minutes.
+    pub fn with_direct_interval(mut self, interval: Duration) -> Self {
+        self.direct_interval = interval;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+
+    pub async fn read(&mut self) -> Vec<ProviderUsage> {
+        if self.daemon {
+            match self.read_from_daemon().await {
+                Ok(Some(usage)) => {
+                    self.last_direct = None;
+                    return usage;
+                }
+                Ok(None) => {}
+                Err(e) => tracing::debug!(error = %e, "Failed to read usage from daemon"),
+            }
+        }
+
+        if let Some((fetched_at, usage)) = &self.last_direct {
+            if fetched_at.elapsed() < self.direct_interval {
+                return usage.clone();
+            }
+        }
+
+        let mut usage = Vec::with_capacity(self.providers.len());
+        for provider in &self.providers {
+            usage.push(fetch_direct(provider.as_ref()).await);
+        }
+        self.last_direct = Some((Instant::now(), usage.clone()));
+        usage
+    }
+
+    /// `None` when no daemon is running.
+    async fn read_from_daemon(&self) -> anyhow::Result<Option<Vec<ProviderUsage>>> {
+        let mut usage = Vec::with_capacity(self.providers.len());
+        for provider in &self.providers {
+            let id = provider.identifier().id();
+            let Some(report) = daemon_client::query_json::<UsageReport, _>("GetUsage", &id).await?
+            else {
+                return Ok(None);
+            };
+            usage.push(ProviderUsage {
+                provider: provider.identifier(),
+                name: provider.display_name(),
+                snapshot: report.snapshot,
+                error: report.error,
+            });
+        }
+        Ok(Some(usage))
+    }
+}
+
+async fn fetch_direct(provider: &dyn UsageProvider) -> ProviderUsage {
+    let (snapshot, error) = if !provider.has_valid_credentials() {
+        (None, Some(provider.credential_error_hint().to_string()))
+    } else {
+        match provider.fetch_usage().await {
+            Ok(snapshot) => (Some(snapshot), None),
+            Err(e) => (None, Some(e.to_string())),
+        }
+    };
+
+    ProviderUsage {
+        provider: provider.identifier(),
+        name: provider.display_name(),
+        snapshot,
+        error,
+    }
+}
+
+/// `--claude-credentials` and `--codex-credentials`: credential files to
+/// read instead of the configured providers, for service accounts whose
+/// secrets live outside the usual paths. Settings and the daemon are left
+/// out entirely.
+#[derive(Debug, Clone, Default)]
+pub struct CredentialOverrides {
+    pub claude: Option<PathBuf>,
+    pub codex: Option<PathBuf>,
+}
+
+impl CredentialOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.claude.is_none() && self.codex.is_none()
+    }
+
+    /// A provider for each given file that `provider_filter` matches. Fails
+    /// when one of those files is unreadable, malformed or has no token.
+    pub fn providers(
+        &self,
+        provider_filter: Option<&str>,
+    ) -> anyhow::Result<Vec<Box<dyn UsageProvider>>> {
+        let filter = provider_filter.map(|s| s.to_lowercase());
+        let matches_filter = |name: &str| filter.is_none() || filter.as_deref() == Some(name);
+
+        let mut providers: Vec<Box<dyn UsageProvider>> = Vec::new();
+
+        if let Some(path) = self.claude.clone().filter(|_| matches_filter("claude")) {
+            let provider = ClaudeProvider::with_credentials_path(path);
+            provider.check_credentials()?;
+            providers.push(Box::new(provider));
+        }
+
+        if let Some(path) = self.codex.clone().filter(|_| matches_filter("codex")) {
+            let provider = CodexProvider::with_credentials_path(path);
+            provider.check_credentials()?;
+            providers.push(Box::new(provider));
+        }
+
+        if let (true, Some(filter)) = (providers.is_empty(), provider_filter) {
+            anyhow::bail!(
+                "No credentials file given for {}. Use --claude-credentials or --codex-credentials.",
+                filter
+            );
+        }
+
+        Ok(providers)
+    }
+}
+
+pub fn build_provider_list(
+    settings: &Settings,
+    provider_filter: Option<&str>,
+) -> Vec<Box<dyn UsageProvider>> {
+    let filter = provider_filter.map(|s| s.to_lowercase());
+    let matches_filter = |name: &str| filter.is_none() || filter.as_deref() == Some(name);
+
+    let mut providers: Vec<Box<dyn UsageProvider>> = Vec::new();
+
+    if settings.providers.claude.enabled && matches_filter("claude") {
+        providers.push(Box::new(
+            ClaudeProvider::with_beta_header(settings.providers.claude.beta_header.clone())
+                .with_debug(settings.debug),
+        ));
+    }
+
+    if settings.providers.codex.enabled && matches_filter("codex") {
+        let codex = &settings.providers.codex;
+        for provider in codex.instances() {
+            providers.push(Box::new(CodexProvider::for_workspace(
+                provider,
+                codex.account_id_for(provider),
+                codex.label_for(provider),
+            )));
+        }
+    }
+
+    providers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_overrides_check_files() {
+        let dir = std::env::temp_dir().join(format!("claude-bar-creds-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let claude = dir.join("claude.json");
+        std::fs::write(&claude, r#"{"claudeAiOauth":{"accessToken":"ci-token"}}"#).unwrap();
+        let malformed = dir.join("malformed.json");
+        std::fs::write(&malformed, "{not json").unwrap();
+
+        let overrides = CredentialOverrides {
+            claude: Some(claude.clone()),
+            codex: None,
+        };
+        let providers = overrides.providers(None).unwrap();
+        assert_eq!(providers.len(), 1);
+        assert_eq!(providers[0].identifier(), Provider::Claude);
+        assert_eq!(providers[0].credentials_path(), Some(claude));
+        let error = overrides.providers(Some("codex")).err().unwrap();
+        assert!(error
+            .to_string()
+            .contains("No credentials file given for codex"));
+
+        let missing = CredentialOverrides {
+            claude: Some(dir.join("missing.json")),
+            codex: None,
+        };
+        let error = missing.providers(None).err().unwrap();
+        assert!(error.to_string().contains("missing.json"));
+
+        let malformed = CredentialOverrides {
+            claude: None,
+            codex: Some(malformed),
+        };
+        let error = malformed.providers(None).err().unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("Failed to parse Codex credentials in"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
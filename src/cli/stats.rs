@@ -0,0 +1,575 @@
+use crate::core::lockouts::{weekly_counts, LockoutLog};
+use crate::core::models::Provider;
+use crate::core::schema::{json_schema, SCHEMA_VERSION};
+use crate::core::settings::Settings;
+use crate::core::weekly_summary::{WeeklySummary, WeeklySummaryLog};
+use crate::cost::{scan_usage_stats, today, CostStore, UsageStats, MAX_STATS_DAYS};
+use crate::ui::popup_model::format_compact_tokens;
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Weeks `stats` covers, about three months.
+const WEEKS: u32 = 13;
+
+#[derive(Serialize, JsonSchema)]
+struct StatsOutput {
+    schema_version: u32,
+    /// Monday each week starts on, local time, oldest first.
+    weeks: Vec<NaiveDate>,
+    /// Keyed by provider id; only providers with lockouts in these weeks.
+    providers: BTreeMap<String, ProviderStats>,
+    /// Days `usage` covers, ending today.
+    usage_days: u32,
+    /// What the logs say about each provider's sessions, keyed by provider
+    /// id. Empty while cost tracking is off.
+    usage: BTreeMap<String, ProviderUsage>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ProviderStats {
+    name: String,
+    total: usize,
+    /// Lockouts per week, in `weeks` order.
+    lockouts: Vec<usize>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ProviderUsage {
+    name: String,
+    #[serde(flatten)]
+    stats: UsageStats,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct WeeklyStatsOutput {
+    schema_version: u32,
+    /// Keyed by provider id; only providers with a finished week.
+    providers: BTreeMap<String, ProviderWeeks>,
+}
+
+#[derive(Serialize, JsonSchema)]
+struct ProviderWeeks {
+    name: String,
+    /// The last 13 weekly windows that reset, oldest first.
+    weeks: Vec<WeeklySummary>,
+}
+
+/// Prints how often each provider's windows ran out per week over the last
+/// three months, from the lockouts the daemon recorded, then session and
+/// cost statistics from the last `days` days of logs.
+pub async fn run(json: bool, days: u32, provider: Option<&str>) -> Result<()> {
+    let filter = provider.map(parse_provider).transpose()?;
+    let settings = Settings::load()?;
+    let mut output = build_output(&LockoutLog::load(), Utc::now(), &Local);
+    output
+        .providers
+        .retain(|id, _| Provider::from_id(id).is_some_and(|p| matches_filter(filter, p)));
+    output.usage_days = days.min(MAX_STATS_DAYS);
+    if settings.cost.enabled {
+        output.usage = scan_usage(&settings, days, filter).await?;
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        print!("{}", format_text_output(&output));
+        if settings.cost.enabled {
+            print!("\n{}", format_usage_text(&output));
+        } else {
+            println!("\nSet `enabled = true` under [cost] for session and cost statistics.");
+        }
+    }
+    Ok(())
+}
+
+/// `--provider`: Codex workspaces share one set of logs, so any of them
+/// means Codex.
+fn parse_provider(name: &str) -> Result<Provider> {
+    match Provider::from_id(name) {
+        Some(Provider::Claude) => Ok(Provider::Claude),
+        Some(provider) if provider.is_codex() => Ok(Provider::Codex),
+        _ => anyhow::bail!("Unknown provider: {}. Valid providers: claude, codex", name),
+    }
+}
+
+fn matches_filter(filter: Option<Provider>, provider: Provider) -> bool {
+    match filter {
+        Some(Provider::Claude) => provider == Provider::Claude,
+        Some(_) => provider.is_codex(),
+        None => true,
+    }
+}
+
+async fn scan_usage(
+    settings: &Settings,
+    days: u32,
+    filter: Option<Provider>,
+) -> Result<BTreeMap<String, ProviderUsage>> {
+    let mut cost_store = CostStore::new();
+    cost_store.set_timezone(settings.cost.timezone);
+    cost_store.set_include_claude_desktop(settings.cost.include_claude_desktop);
+    cost_store.set_project_filter(
+        &settings.cost.include_projects,
+        &settings.cost.exclude_projects,
+    );
+    cost_store.refresh_pricing(false).await?;
+
+    let today = today(settings.cost.timezone);
+    let mut usage = BTreeMap::new();
+    for (provider, enabled) in [
+        (Provider::Claude, settings.providers.claude.enabled),
+        (Provider::Codex, settings.providers.codex.enabled),
+    ] {
+        if !enabled || !matches_filter(filter, provider) {
+            continue;
+        }
+        let stats = ProviderUsage {
+            name: provider.name().to_string(),
+            stats: scan_usage_stats(&cost_store, provider, days, today)?,
+        };
+        usage.insert(provider.id(), stats);
+    }
+    Ok(usage)
+}
+
+/// Prints the recap of each weekly window the daemon saw reset.
+pub fn run_weekly(json: bool) -> Result<()> {
+    let output = build_weekly_output(&WeeklySummaryLog::load());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        print!("{}", format_weekly_text_output(&output, Utc::now(), &Local));
+    }
+    Ok(())
+}
+
+/// Prints the JSON Schema of `stats --json`, or of `stats --weekly --json`.
+pub fn print_schema(weekly: bool) -> Result<()> {
+    let schema = if weekly {
+        json_schema::<WeeklyStatsOutput>()?
+    } else {
+        json_schema::<StatsOutput>()?
+    };
+    println!("{}", schema);
+    Ok(())
+}
+
+fn build_output<Tz: TimeZone>(log: &LockoutLog, now: DateTime<Utc>, tz: &Tz) -> StatsOutput {
+    let weeks = weekly_counts(&[], now, WEEKS, tz)
+        .into_iter()
+        .map(|(week, _)| week)
+        .collect();
+    let providers = log
+        .providers()
+        .filter_map(|(provider, lockouts)| {
+            let lockouts: Vec<usize> = weekly_counts(lockouts, now, WEEKS, tz)
+                .into_iter()
+                .map(|(_, count)| count)
+                .collect();
+            let total = lockouts.iter().sum();
+            (total > 0).then(|| {
+                let stats = ProviderStats {
+                    name: provider.name().to_string(),
+                    total,
+                    lockouts,
+                };
+                (provider.id(), stats)
+            })
+        })
+        .collect();
+    StatsOutput {
+        schema_version: SCHEMA_VERSION,
+        weeks,
+        providers,
+        usage_days: 0,
+        usage: BTreeMap::new(),
+    }
+}
+
+/// A table with a row per week and a column per provider.
+fn format_text_output(output: &StatsOutput) -> String {
+    if output.providers.is_empty() {
+        return format!("No lockouts in the last {} weeks.\n", output.weeks.len());
+    }
+
+    let providers: Vec<&ProviderStats> = output.providers.values().collect();
+    let mut rows: Vec<(String, Vec<usize>)> = output
+        .weeks
+        .iter()
+        .enumerate()
+        .map(|(i, week)| {
+            let counts = providers.iter().map(|stats| stats.lockouts[i]).collect();
+            (week.format("%b %-d").to_string(), counts)
+        })
+        .collect();
+    rows.push((
+        "Total".to_string(),
+        providers.iter().map(|stats| stats.total).collect(),
+    ));
+
+    let label_width = "Week of".len();
+    let mut text = format!("Lockouts per week, last {} weeks\n\n", output.weeks.len());
+    text.push_str(&format!("{:<label_width$}", "Week of"));
+    for stats in &providers {
+        text.push_str(&format!("  {}", stats.name));
+    }
+    text.push('\n');
+    for (label, counts) in rows {
+        text.push_str(&format!("{:<label_width$}", label));
+        for (stats, count) in providers.iter().zip(counts) {
+            text.push_str(&format!("  {:>width$}", count, width = stats.name.len()));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+/// A table with a row per statistic and a column per provider.
+fn format_usage_text(output: &StatsOutput) -> String {
+    if output.usage.is_empty() {
+        return "No providers with cost tracking enabled.\n".to_string();
+    }
+
+    let tokens = |tokens: Option<u64>| tokens.map_or("-".to_string(), format_compact_tokens);
+    let cost = |cost: Option<f64>| cost.map_or("-".to_string(), |cost| format!("${:.2}", cost));
+    let providers: Vec<&ProviderUsage> = output.usage.values().collect();
+    let rows: Vec<(&str, Vec<String>)> = vec![
+        (
+            "Sessions",
+            providers
+                .iter()
+                .map(|p| p.stats.sessions.to_string())
+                .collect(),
+        ),
+        (
+            "Median session tokens",
+            providers
+                .iter()
+                .map(|p| tokens(p.stats.median_session_tokens))
+                .collect(),
+        ),
+        (
+            "P90 session tokens",
+            providers
+                .iter()
+                .map(|p| tokens(p.stats.p90_session_tokens))
+                .collect(),
+        ),
+        (
+            "Active days this month",
+            providers
+                .iter()
+                .map(|p| p.stats.active_days_this_month.to_string())
+                .collect(),
+        ),
+        (
+            "Busiest model",
+            providers
+                .iter()
+                .map(|p| {
+                    p.stats
+                        .busiest_model
+                        .as_ref()
+                        .map_or("-".to_string(), |busiest| busiest.model.clone())
+                })
+                .collect(),
+        ),
+        (
+            "Daily cost, weekdays",
+            providers
+                .iter()
+                .map(|p| cost(p.stats.weekday_avg_cost_usd))
+                .collect(),
+        ),
+        (
+            "Daily cost, weekends",
+            providers
+                .iter()
+                .map(|p| cost(p.stats.weekend_avg_cost_usd))
+                .collect(),
+        ),
+    ];
+
+    let label_width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = providers
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            rows.iter()
+                .map(|(_, values)| values[i].chars().count())
+                .chain([p.name.len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut text = format!("Usage, last {} days\n\n", output.usage_days);
+    text.push_str(&format!("{:<label_width$}", ""));
+    for (p, width) in providers.iter().zip(&widths) {
+        text.push_str(&format!("  {:>width$}", p.name));
+    }
+    text.push('\n');
+    for (label, values) in rows {
+        text.push_str(&format!("{:<label_width$}", label));
+        for (value, width) in values.iter().zip(&widths) {
+            text.push_str(&format!("  {:>width$}", value));
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn build_weekly_output(log: &WeeklySummaryLog) -> WeeklyStatsOutput {
+    let providers = log
+        .providers()
+        .map(|(provider, weeks)| {
+            let weeks = ProviderWeeks {
+                name: provider.name().to_string(),
+                weeks: weeks.to_vec(),
+            };
+            (provider.id(), weeks)
+        })
+        .collect();
+    WeeklyStatsOutput {
+        schema_version: SCHEMA_VERSION,
+        providers,
+    }
+}
+
+/// A table per provider with a row per week, newest last, then the recap
+/// of the week that just ended.
+fn format_weekly_text_output<Tz: TimeZone>(
+    output: &WeeklyStatsOutput,
+    now: DateTime<Utc>,
+    tz: &Tz,
+) -> String {
+    if output.providers.is_empty() {
+        return "No weekly windows have reset since the daemon started recording them.\n"
+            .to_string();
+    }
+
+    let mut text = String::new();
+    for stats in output.providers.values() {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&format!("{}\n", stats.name));
+        text.push_str("Week ending  Quota      Cost  Busiest day\n");
+        for week in &stats.weeks {
+            let cost = week
+                .cost_usd
+                .map(|cost| format!("${:.2}", cost))
+                .unwrap_or_else(|| "-".to_string());
+            let busiest = week
+                .busiest_day
+                .map(|day| day.format("%a %b %-d").to_string())
+                .unwrap_or_else(|| "-".to_string());
+            text.push_str(&format!(
+                "{:<11}  {:>4}%  {:>8}  {}\n",
+                week.ended_at
+                    .with_timezone(tz)
+                    .date_naive()
+                    .format("%b %-d"),
+                (week.used_percent * 100.0).round() as u32,
+                cost,
+                busiest
+            ));
+        }
+        if let Some(last) = stats.weeks.last().filter(|week| week.is_recent(now)) {
+            text.push_str(&format!("{}\n", last.message()));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{DailyTokenUsage, Provider, RateWindow, UsageSnapshot};
+    use crate::core::schema::assert_schema_snapshot;
+    use crate::core::weekly_summary::{summarize_week, TrackedWeek};
+    use crate::cost::stats::ModelTokens;
+    use chrono::Duration;
+
+    fn full_session(now: DateTime<Utc>) -> UsageSnapshot {
+        let session =
+            RateWindow::from_reported(1.0, Some(300), Some(now + Duration::hours(1)), None);
+        UsageSnapshot::new(Some(session), None, now)
+    }
+
+    #[test]
+    fn test_stats_table() {
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let mut log = LockoutLog::default();
+        for (provider, days_ago) in [
+            (Provider::Codex, 120),
+            (Provider::Claude, 12),
+            (Provider::Claude, 1),
+            (Provider::Claude, 0),
+        ] {
+            let at = now - Duration::days(days_ago);
+            log.record(provider, &full_session(at), at);
+        }
+
+        let output = build_output(&log, now, &Utc);
+        assert_eq!(output.weeks.len(), 13);
+        // Codex's lockout is older than the 13 weeks.
+        assert_eq!(output.providers.keys().collect::<Vec<_>>(), ["claude"]);
+        let text = format_text_output(&output);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "Lockouts per week, last 13 weeks");
+        assert_eq!(lines[2], "Week of  Claude Code");
+        assert_eq!(lines[3], "Jul 20             0");
+        assert_eq!(lines[13], "Sep 28             1");
+        assert_eq!(lines[15], "Oct 12             2");
+        assert_eq!(lines[16], "Total              3");
+
+        assert_eq!(
+            format_text_output(&build_output(&LockoutLog::default(), now, &Utc)),
+            "No lockouts in the last 13 weeks.\n"
+        );
+    }
+
+    #[test]
+    fn test_usage_table() {
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let mut output = build_output(&LockoutLog::default(), now, &Utc);
+        output.usage_days = 90;
+        output.usage.insert(
+            "claude".to_string(),
+            ProviderUsage {
+                name: "Claude Code".to_string(),
+                stats: UsageStats {
+                    sessions: 142,
+                    median_session_tokens: Some(1_240_000),
+                    p90_session_tokens: Some(8_900_000),
+                    active_days_this_month: 12,
+                    busiest_model: Some(ModelTokens {
+                        model: "claude-sonnet-4-5".to_string(),
+                        tokens: 310_000_000,
+                    }),
+                    weekday_avg_cost_usd: Some(12.4),
+                    weekend_avg_cost_usd: Some(2.05),
+                },
+            },
+        );
+        output.usage.insert(
+            "codex".to_string(),
+            ProviderUsage {
+                name: "Codex".to_string(),
+                stats: UsageStats {
+                    sessions: 0,
+                    median_session_tokens: None,
+                    p90_session_tokens: None,
+                    active_days_this_month: 0,
+                    busiest_model: None,
+                    weekday_avg_cost_usd: Some(0.0),
+                    weekend_avg_cost_usd: None,
+                },
+            },
+        );
+
+        assert_eq!(
+            format_usage_text(&output),
+            "Usage, last 90 days\n\n\
+             \x20                             Claude Code  Codex\n\
+             Sessions                              142      0\n\
+             Median session tokens                1.2M      -\n\
+             P90 session tokens                   8.9M      -\n\
+             Active days this month                 12      0\n\
+             Busiest model           claude-sonnet-4-5      -\n\
+             Daily cost, weekdays               $12.40  $0.00\n\
+             Daily cost, weekends                $2.05      -\n"
+        );
+
+        output.usage.clear();
+        assert_eq!(
+            format_usage_text(&output),
+            "No providers with cost tracking enabled.\n"
+        );
+    }
+
+    #[test]
+    fn test_provider_filter() {
+        assert_eq!(parse_provider("Claude").unwrap(), Provider::Claude);
+        assert_eq!(parse_provider("codex-2").unwrap(), Provider::Codex);
+        assert!(parse_provider("gemini").is_err());
+
+        assert!(matches_filter(None, Provider::Claude));
+        assert!(matches_filter(
+            Some(Provider::Codex),
+            Provider::CodexWorkspace(1)
+        ));
+        assert!(!matches_filter(Some(Provider::Codex), Provider::Claude));
+        assert!(!matches_filter(Some(Provider::Claude), Provider::Codex));
+    }
+
+    #[test]
+    fn test_stats_schema_snapshot() {
+        assert_schema_snapshot::<StatsOutput>(
+            "stats",
+            include_str!("../../tests/fixtures/schema/stats.json"),
+        );
+    }
+
+    #[test]
+    fn test_weekly_stats_table() {
+        let mut log = WeeklySummaryLog::default();
+        let ends: [DateTime<Utc>; 2] = [
+            "2026-10-05T09:00:00Z".parse().unwrap(),
+            "2026-10-12T09:00:00Z".parse().unwrap(),
+        ];
+        let daily = [DailyTokenUsage {
+            date: NaiveDate::from_ymd_opt(2026, 10, 8).unwrap(),
+            total_tokens: Some(50_000),
+            cost_usd: Some(23.4),
+            models: Vec::new(),
+        }];
+        for (resets_at, used_percent) in ends.into_iter().zip([0.25, 0.78]) {
+            let week = TrackedWeek {
+                starts_at: resets_at - Duration::weeks(1),
+                resets_at,
+                used_percent,
+            };
+            log.push(
+                Provider::Claude,
+                summarize_week(&week, &daily, |at| at.date_naive()),
+            );
+        }
+
+        let output = build_weekly_output(&log);
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        assert_eq!(
+            format_weekly_text_output(&output, now, &Utc),
+            "Claude Code\n\
+             Week ending  Quota      Cost  Busiest day\n\
+             Oct 5          25%         -  -\n\
+             Oct 12         78%    $23.40  Thu Oct 8\n\
+             Last week: 78% of weekly quota used, $23.40 in API-equivalent cost, \
+             busiest day Thursday.\n"
+        );
+        // A recap weeks old isn't "last week" anymore.
+        let later = now + Duration::weeks(3);
+        assert!(!format_weekly_text_output(&output, later, &Utc).contains("Last week"));
+
+        assert_eq!(
+            format_weekly_text_output(
+                &build_weekly_output(&WeeklySummaryLog::default()),
+                now,
+                &Utc
+            ),
+            "No weekly windows have reset since the daemon started recording them.\n"
+        );
+    }
+
+    #[test]
+    fn test_weekly_stats_schema_snapshot() {
+        assert_schema_snapshot::<WeeklyStatsOutput>(
+            "stats-weekly",
+            include_str!("../../tests/fixtures/schema/stats-weekly.json"),
+        );
+    }
+}
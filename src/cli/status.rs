@@ -1,213 +1,965 @@
-use crate::core::models::{ProviderIdentity, RateWindow, UsageSnapshot};
-use crate::core::settings::Settings;
-use crate::providers::{ClaudeProvider, CodexProvider, UsageProvider};
+use crate::cli::daemon_client;
+use crate::cli::source::{build_provider_list, CredentialOverrides, ProviderUsage, UsageSource};
+use crate::cli::status_template::StatusTemplate;
+use crate::cli::style::{ColorMode, Style};
+use crate::core::clock::{format_reset_clock, HourCycle};
+use crate::core::lockouts::LockoutLog;
+use crate::core::models::{
+    display_used_percent, DailyTokenUsage, ErrorEvent, FetchLatency, Provider, ProviderHealth,
+    ProviderIdentity, TrayStatus,
+};
+use crate::core::schema::{json_schema, SCHEMA_VERSION};
+use crate::core::settings::{ResetTimeFormat, Settings};
+use crate::core::status_output::{
+    error_status, snapshot_to_status, window_to_status, ProviderCostStatus, ProviderStatus,
+    StatusOutput, WindowStatus,
+};
+use crate::core::week_compare::{daily_token_series, week_over_week_percent};
+use crate::providers::UsageProvider;
+use crate::ui::colors::ColorScheme;
 use anyhow::Result;
-use chrono::{DateTime, Utc};
-use serde::Serialize;
-use std::collections::HashMap;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{ErrorKind, IsTerminal, Write};
+
+/// `status --errors --json`: each provider's events keyed by its id.
+#[derive(Serialize, JsonSchema)]
+struct ErrorHistoryOutput {
+    schema_version: u32,
+    providers: HashMap<String, Vec<ErrorEvent>>,
+}
 
-#[derive(Serialize)]
-struct StatusOutput {
-    providers: HashMap<String, ProviderStatus>,
-    #[serde(with = "chrono::serde::ts_seconds")]
-    fetched_at: DateTime<Utc>,
+/// `status --watch --output`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum WatchOutput {
+    /// Redraw the usual text display on every refresh.
+    #[default]
+    Text,
+    /// One compact JSON object per provider per refresh.
+    JsonLines,
 }
 
-#[derive(Serialize)]
-struct ProviderStatus {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    session: Option<WindowStatus>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    weekly: Option<WindowStatus>,
-    #[serde(skip_serializing_if = "Vec::is_empty")]
-    carveouts: Vec<CarveoutStatus>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    identity: Option<ProviderIdentity>,
+/// A `--output json-lines` line.
+#[derive(Serialize, JsonSchema)]
+struct WatchLine {
+    schema_version: u32,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schemars(with = "i64")]
+    timestamp: DateTime<Utc>,
+    provider: String,
+    /// Keyed `session`, `weekly` and `tertiary`; empty when the fetch failed.
+    windows: BTreeMap<&'static str, WindowStatus>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error: Option<String>,
 }
 
-#[derive(Serialize)]
-struct CarveoutStatus {
-    label: String,
-    window: WindowStatus,
-}
+/// With `credentials` given, fetches only those providers and touches
+/// neither the config file nor the daemon.
+pub async fn run(
+    json: bool,
+    provider_filter: Option<String>,
+    errors: bool,
+    format: Option<String>,
+    all_windows: bool,
+    credentials: CredentialOverrides,
+    color: ColorMode,
+) -> Result<()> {
+    let use_daemon = credentials.is_empty();
+    let (settings, providers) = if use_daemon {
+        let settings = Settings::load()?;
+        let providers = build_provider_list(&settings, provider_filter.as_deref());
+        (settings, providers)
+    } else {
+        let providers = credentials.providers(provider_filter.as_deref())?;
+        (Settings::default(), providers)
+    };
+    let style = Style::detect(color, settings.notifications.threshold)
+        .with_scheme(ColorScheme::new(settings.display.colorblind_mode));
 
-#[derive(Serialize)]
-struct WindowStatus {
-    used_percent: f64,
-    remaining_percent: f64,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    resets_in: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    window_minutes: Option<i32>,
-}
+    if providers.is_empty() {
+        return Err(no_providers(provider_filter.as_deref()));
+    }
 
-pub async fn run(json: bool, provider_filter: Option<String>) -> Result<()> {
-    let settings = Settings::load()?;
+    if errors {
+        return print_error_history(&providers, json, &style).await;
+    }
 
-    let providers = build_provider_list(&settings, provider_filter.as_deref());
+    if let Some(format) = format {
+        let ids: Vec<String> = providers.iter().map(|p| p.identifier().id()).collect();
+        let template = StatusTemplate::parse(&format, &ids)?;
 
-    if providers.is_empty() {
-        if let Some(filter) = &provider_filter {
-            anyhow::bail!("Unknown provider: {}. Valid providers: claude, codex", filter);
-        } else {
-            anyhow::bail!("No providers enabled. Check your configuration.");
+        let mut statuses: HashMap<String, ProviderStatus> = HashMap::new();
+        for provider in providers {
+            let status = fetch_provider_status(provider.as_ref()).await;
+            statuses.insert(provider.identifier().id(), status);
         }
+        println!("{}", render_template(&template, &statuses)?);
+        return Ok(());
     }
 
     let mut results: HashMap<String, ProviderStatus> = HashMap::new();
+    let (mut latencies, mut health) = if json && use_daemon {
+        (
+            daemon_fetch_latencies().await,
+            daemon_provider_health().await,
+        )
+    } else {
+        (HashMap::new(), HashMap::new())
+    };
+    let lockouts = if json && use_daemon {
+        LockoutLog::load()
+    } else {
+        LockoutLog::default()
+    };
 
     for provider in providers {
-        let name = provider.name().to_string();
-        let status = fetch_provider_status(provider.as_ref()).await;
+        let name = provider.display_name();
+        let id = provider.identifier().id();
+        let mut status = fetch_provider_status(provider.as_ref()).await;
+        status.provider = Some(provider.identifier());
+        status.fetch_latency_ms = latencies.remove(&id);
+        apply_health(
+            &mut status,
+            health.remove(&id).unwrap_or_default(),
+            Utc::now(),
+        );
+        if json && use_daemon {
+            status.week_over_week_percent = daemon_week_over_week(&id, status.week_start).await;
+            status.lockouts = lockouts.for_provider(provider.identifier()).to_vec();
+        }
         results.insert(name, status);
     }
 
+    let tray = if use_daemon {
+        daemon_tray_status().await
+    } else {
+        None
+    };
     if json {
         let output = StatusOutput {
+            schema_version: SCHEMA_VERSION,
             providers: results,
             fetched_at: Utc::now(),
+            tray,
         };
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
-        print_text_output(&results);
+        let reset_format = settings.display.reset_time_format;
+        print!(
+            "{}",
+            format_text_output(&results, all_windows, reset_format, &style)
+        );
+        if tray == Some(TrayStatus::Unavailable) {
+            println!("\nTray: unavailable; the daemon is retrying registration");
+        }
     }
 
     Ok(())
 }
 
-fn build_provider_list(
-    settings: &Settings,
-    provider_filter: Option<&str>,
-) -> Vec<Box<dyn UsageProvider>> {
-    let filter = provider_filter.map(|s| s.to_lowercase());
-    let matches_filter = |name: &str| filter.is_none() || filter.as_deref() == Some(name);
-
-    let mut providers: Vec<Box<dyn UsageProvider>> = Vec::new();
+/// Prints the JSON Schema of what `status` prints as JSON: `--json`, or
+/// `--errors --json` with `errors`, or `--output json-lines` with `watch`.
+pub fn print_schema(errors: bool, watch: Option<WatchOutput>) -> Result<()> {
+    let schema = match (errors, watch) {
+        (true, _) => json_schema::<ErrorHistoryOutput>()?,
+        (false, Some(WatchOutput::JsonLines)) => json_schema::<WatchLine>()?,
+        (false, Some(WatchOutput::Text)) => {
+            anyhow::bail!("--watch only prints JSON with --output json-lines")
+        }
+        (false, None) => json_schema::<StatusOutput>()?,
+    };
+    println!("{}", schema);
+    Ok(())
+}
 
-    if settings.providers.claude.enabled && matches_filter("claude") {
-        providers.push(Box::new(ClaudeProvider::new()));
+/// Prints usage every `interval` seconds until interrupted or stdout is
+/// closed. Reads from the running daemon when there is one, and otherwise
+/// fetches directly every `interval`.
+pub async fn watch(
+    provider_filter: Option<String>,
+    interval: u64,
+    output: WatchOutput,
+    all_windows: bool,
+    color: ColorMode,
+) -> Result<()> {
+    let settings = Settings::load()?;
+    let interval = std::time::Duration::from_secs(interval.max(1));
+    let mut source =
+        UsageSource::filtered(&settings, provider_filter.as_deref()).with_direct_interval(interval);
+    if source.is_empty() {
+        return Err(no_providers(provider_filter.as_deref()));
     }
 
-    if settings.providers.codex.enabled && matches_filter("codex") {
-        providers.push(Box::new(CodexProvider::new()));
+    let style = Style::detect(color, settings.notifications.threshold)
+        .with_scheme(ColorScheme::new(settings.display.colorblind_mode));
+    let clear_screen = std::io::stdout().is_terminal();
+    loop {
+        let usage = source.read().await;
+        let lines = match output {
+            WatchOutput::JsonLines => {
+                let now = Utc::now();
+                usage
+                    .iter()
+                    .map(|usage| serde_json::to_string(&watch_line(usage, now)))
+                    .collect::<serde_json::Result<Vec<_>>>()?
+            }
+            WatchOutput::Text => {
+                let statuses: HashMap<String, ProviderStatus> = usage
+                    .into_iter()
+                    .map(|usage| (usage.name.clone(), usage_to_status(usage)))
+                    .collect();
+                let text = format_text_output(
+                    &statuses,
+                    all_windows,
+                    settings.display.reset_time_format,
+                    &style,
+                );
+                let clear = if clear_screen {
+                    "\u{1b}[2J\u{1b}[H"
+                } else {
+                    ""
+                };
+                vec![format!("{}{}", clear, text.trim_end())]
+            }
+        };
+        for line in lines {
+            if !emit(&line)? {
+                return Ok(());
+            }
+        }
+        tokio::time::sleep(interval).await;
     }
-
-    providers
 }
 
-async fn fetch_provider_status(provider: &dyn UsageProvider) -> ProviderStatus {
-    if !provider.has_valid_credentials() {
-        return ProviderStatus {
-            session: None,
-            weekly: None,
-            carveouts: Vec::new(),
-            identity: None,
-            error: Some(provider.credential_error_hint().to_string()),
-        };
+fn no_providers(provider_filter: Option<&str>) -> anyhow::Error {
+    match provider_filter {
+        Some(filter) => anyhow::anyhow!(
+            "Unknown provider: {}. Valid providers: claude, codex",
+            filter
+        ),
+        None => anyhow::anyhow!("No providers enabled. Check your configuration."),
     }
+}
 
-    match provider.fetch_usage().await {
-        Ok(snapshot) => snapshot_to_status(snapshot),
-        Err(e) => ProviderStatus {
-            session: None,
-            weekly: None,
-            carveouts: Vec::new(),
-            identity: None,
-            error: Some(e.to_string()),
+fn usage_to_status(usage: ProviderUsage) -> ProviderStatus {
+    let mut status = match (usage.snapshot, usage.error) {
+        (_, Some(error)) => error_status(error),
+        (Some(snapshot), None) => snapshot_to_status(snapshot),
+        // The daemon hasn't fetched it yet.
+        (None, None) => ProviderStatus {
+            error: None,
+            ..error_status(String::new())
         },
-    }
+    };
+    status.provider = Some(usage.provider);
+    status
 }
 
-fn snapshot_to_status(snapshot: UsageSnapshot) -> ProviderStatus {
-    let carveouts = snapshot
-        .carveouts
-        .into_iter()
-        .map(|c| CarveoutStatus {
-            label: c.label,
-            window: window_to_status(&c.window),
+fn watch_line(usage: &ProviderUsage, now: DateTime<Utc>) -> WatchLine {
+    let windows = usage
+        .snapshot
+        .iter()
+        .flat_map(|snapshot| {
+            [
+                ("session", snapshot.primary.as_ref()),
+                ("weekly", snapshot.secondary.as_ref()),
+                ("tertiary", snapshot.tertiary.as_ref()),
+            ]
         })
+        .filter_map(|(key, window)| Some((key, window_to_status(window?))))
         .collect();
-
-    ProviderStatus {
-        session: snapshot.primary.map(|w| window_to_status(&w)),
-        weekly: snapshot.secondary.map(|w| window_to_status(&w)),
-        carveouts,
-        identity: Some(snapshot.identity),
-        error: None,
+    WatchLine {
+        schema_version: SCHEMA_VERSION,
+        timestamp: now,
+        provider: usage.provider.id(),
+        windows,
+        error: usage.error.clone(),
     }
 }
 
-fn window_to_status(window: &RateWindow) -> WindowStatus {
-    WindowStatus {
-        used_percent: window.used_percent,
-        remaining_percent: window.remaining_percent(),
-        resets_in: window.resets_at.map(format_reset_time),
-        window_minutes: window.window_minutes,
+/// Writes one line and flushes it. Returns `false` once the reader has
+/// closed stdout, so streaming commands can stop quietly instead of
+/// panicking like `println!` would.
+pub fn emit(line: &str) -> Result<bool> {
+    let mut stdout = std::io::stdout().lock();
+    match writeln!(stdout, "{}", line).and_then(|_| stdout.flush()) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(false),
+        Err(e) => Err(e.into()),
     }
 }
 
-fn format_reset_time(resets_at: DateTime<Utc>) -> String {
-    let now = Utc::now();
-    let duration = resets_at.signed_duration_since(now);
+/// Fetch latency the running daemon has measured, keyed by provider id.
+/// Empty when no daemon is running; `status` never starts one.
+async fn daemon_fetch_latencies() -> HashMap<String, FetchLatency> {
+    match daemon_client::query_json("GetFetchLatency", &()).await {
+        Ok(latencies) => latencies.unwrap_or_default(),
+        Err(e) => {
+            tracing::debug!(error = %e, "Could not query daemon fetch latency");
+            HashMap::new()
+        }
+    }
+}
 
-    if duration.num_seconds() <= 0 {
-        return "now".to_string();
+/// Success and outage times the running daemon has seen, keyed by provider
+/// id. Empty when no daemon is running.
+async fn daemon_provider_health() -> HashMap<String, ProviderHealth> {
+    match daemon_client::query_json("GetProviderHealth", &()).await {
+        Ok(health) => health.unwrap_or_default(),
+        Err(e) => {
+            tracing::debug!(error = %e, "Could not query daemon provider health");
+            HashMap::new()
+        }
     }
+}
 
-    let total_minutes = duration.num_minutes();
-    let days = total_minutes / (24 * 60);
-    let hours = (total_minutes % (24 * 60)) / 60;
-    let minutes = total_minutes % 60;
+#[derive(Deserialize)]
+struct TokenHistory {
+    daily: Vec<TokenHistoryDay>,
+}
 
-    if days > 0 {
-        format!("{}d {}h", days, hours)
-    } else if hours > 0 {
-        format!("{}h {:02}m", hours, minutes)
+#[derive(Deserialize)]
+struct TokenHistoryDay {
+    date: NaiveDate,
+    total_tokens: Option<u64>,
+    cost_usd: Option<f64>,
+}
+
+/// Tokens this week against the same days of last week, from the running
+/// daemon's token scan. `None` without a daemon or last week's data.
+async fn daemon_week_over_week(id: &str, week_start: Option<NaiveDate>) -> Option<f64> {
+    let week_start = week_start?;
+    let history: TokenHistory =
+        match daemon_client::query_json("GetCostHistory", &(id, 14u32)).await {
+            Ok(history) => history?,
+            Err(e) => {
+                tracing::debug!(error = %e, "Could not query daemon cost history");
+                return None;
+            }
+        };
+    let daily: Vec<DailyTokenUsage> = history
+        .daily
+        .into_iter()
+        .map(|day| DailyTokenUsage {
+            date: day.date,
+            total_tokens: day.total_tokens,
+            cost_usd: day.cost_usd,
+            models: Vec::new(),
+        })
+        .collect();
+    week_over_week_percent(
+        &daily_token_series(&daily),
+        week_start,
+        Local::now().date_naive(),
+    )
+}
+
+/// Whether the running daemon's tray icons are registered. `None` when no
+/// daemon is running.
+async fn daemon_tray_status() -> Option<TrayStatus> {
+    daemon_client::query_json("GetTrayStatus", &())
+        .await
+        .unwrap_or_else(|e| {
+            tracing::debug!(error = %e, "Could not query daemon tray status");
+            None
+        })
+}
+
+/// The fetch `status` just made wins over the daemon's view: a success is
+/// the latest good data, and only a failure reports the daemon's outage.
+fn apply_health(status: &mut ProviderStatus, health: ProviderHealth, now: DateTime<Utc>) {
+    if status.error.is_none() {
+        status.last_success_at = Some(now);
+        status.error_since = None;
     } else {
-        format!("{}m", minutes)
+        status.last_success_at = health.last_success_at;
+        status.error_since = health.error_since;
     }
 }
 
-fn print_text_output(results: &HashMap<String, ProviderStatus>) {
-    for (i, (name, status)) in results.iter().enumerate() {
+async fn print_error_history(
+    providers: &[Box<dyn UsageProvider>],
+    json: bool,
+    style: &Style,
+) -> Result<()> {
+    let mut histories: Vec<(Provider, String, Vec<ErrorEvent>)> = Vec::new();
+    for provider in providers {
+        let id = provider.identifier().id();
+        let Some(events) = daemon_client::query_json("GetErrorHistory", &id).await? else {
+            anyhow::bail!("daemon not running; error history is only kept by `claude-bar daemon`");
+        };
+        histories.push((provider.identifier(), provider.display_name(), events));
+    }
+
+    if json {
+        let output = ErrorHistoryOutput {
+            schema_version: SCHEMA_VERSION,
+            providers: histories
+                .into_iter()
+                .map(|(provider, _, events)| (provider.id(), events))
+                .collect(),
+        };
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    for (i, (provider, name, events)) in histories.iter().enumerate() {
         if i > 0 {
             println!();
         }
+        println!("{}", style.provider(Some(*provider), name));
+        if events.is_empty() {
+            println!("  No errors recorded");
+        }
+        for event in events {
+            println!(
+                "  {}  {}",
+                event.at().with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+                event.describe()
+            );
+        }
+    }
+    Ok(())
+}
 
-        println!("{}", name);
+async fn fetch_provider_status(provider: &dyn UsageProvider) -> ProviderStatus {
+    if !provider.has_valid_credentials() {
+        return error_status(provider.credential_error_hint().to_string());
+    }
 
-        if let Some(error) = &status.error {
-            println!("  Error: {}", error);
-            continue;
-        }
+    match provider.fetch_usage().await {
+        Ok(snapshot) => snapshot_to_status(snapshot),
+        Err(e) => error_status(e.to_string()),
+    }
+}
+
+/// " (resets in 2h 14m)", with the local reset time as `format` says. An
+/// estimated countdown ("~2h 14m") marks the time as estimated too.
+fn reset_info(
+    countdown: Option<&str>,
+    resets_at: Option<DateTime<Utc>>,
+    format: ResetTimeFormat,
+) -> String {
+    let Some(countdown) = countdown else {
+        return String::new();
+    };
+    let Some(resets_at) = resets_at else {
+        return format!(" (resets in {})", countdown);
+    };
+    let approx = if countdown.starts_with('~') { "~" } else { "" };
+    let clock = format_reset_clock(resets_at, Utc::now(), &Local, HourCycle::from_env());
+    format!(
+        " (resets {})",
+        format.render(countdown, &format!("{}{}", approx, clock))
+    )
+}
+
+/// Renders from the same structs `--json` serializes, keyed by provider id.
+fn render_template(
+    template: &StatusTemplate,
+    statuses: &HashMap<String, ProviderStatus>,
+) -> Result<String> {
+    Ok(template.render(&serde_json::to_value(statuses)?))
+}
+
+/// One block per provider, sorted by name. `all_windows` adds the plan,
+/// the tertiary window and extra usage to the session, weekly and carveout
+/// lines.
+fn format_text_output(
+    results: &HashMap<String, ProviderStatus>,
+    all_windows: bool,
+    reset_format: ResetTimeFormat,
+    style: &Style,
+) -> String {
+    let mut names: Vec<&String> = results.keys().collect();
+    names.sort();
+
+    let blocks: Vec<String> = names
+        .into_iter()
+        .map(|name| format_provider_text(name, &results[name], all_windows, reset_format, style))
+        .collect();
+    blocks.join("\n")
+}
 
-        if let Some(session) = &status.session {
-            print_window_line("Session", session);
+fn format_provider_text(
+    name: &str,
+    status: &ProviderStatus,
+    all_windows: bool,
+    reset_format: ResetTimeFormat,
+    style: &Style,
+) -> String {
+    let mut out = format!("{}\n", style.provider(status.provider, name));
+
+    if let Some(error) = &status.error {
+        out.push_str(&format!(
+            "  {}\n",
+            style.error(&format!("Error: {}", error))
+        ));
+        return out;
+    }
+
+    if all_windows {
+        if let Some(line) = status.identity.as_ref().and_then(identity_line) {
+            out.push_str(&line);
         }
+    }
+
+    if let Some(session) = &status.session {
+        out.push_str(&window_line("Session", session, reset_format, style));
+    }
 
-        if let Some(weekly) = &status.weekly {
-            print_window_line("Weekly", weekly);
+    if let Some(weekly) = &status.weekly {
+        out.push_str(&window_line("Weekly", weekly, reset_format, style));
+    }
+
+    if all_windows {
+        let tertiary = status.tertiary.as_ref().filter(|tertiary| {
+            !status
+                .carveouts
+                .iter()
+                .any(|carveout| carveout.window == **tertiary)
+        });
+        if let Some(tertiary) = tertiary {
+            out.push_str(&window_line("Other", tertiary, reset_format, style));
         }
+    }
+
+    for carveout in &status.carveouts {
+        out.push_str(&window_line(
+            &carveout.label,
+            &carveout.window,
+            reset_format,
+            style,
+        ));
+    }
 
-        for carveout in &status.carveouts {
-            print_window_line(&carveout.label, &carveout.window);
+    if all_windows {
+        if let Some(cost) = &status.provider_cost {
+            out.push_str(&provider_cost_line(cost, reset_format));
         }
     }
-}
 
-fn print_window_line(label: &str, window: &WindowStatus) {
-    let reset_info = window
-        .resets_in
-        .as_ref()
-        .map(|r| format!(" (resets in {})", r))
-        .unwrap_or_default();
+    out
+}
 
-    println!(
-        "  {:<8} {:>5.1}% used{}",
+fn window_line(
+    label: &str,
+    window: &WindowStatus,
+    reset_format: ResetTimeFormat,
+    style: &Style,
+) -> String {
+    let reset_info = reset_info(window.resets_in.as_deref(), window.resets_at, reset_format);
+
+    // Pad before coloring, so escape codes don't count towards the width.
+    let percent = format!(
+        "{:.1}%",
+        display_used_percent(window.used_percent, window.used_percent_exact)
+    );
+    format!(
+        "  {:<8} {:>pad$}{} used{}{}\n",
         format!("{}:", label),
-        window.used_percent * 100.0,
+        "",
+        style.usage(window.used_percent, &percent),
+        if window.reported_used_percent.is_some() {
+            " (!)"
+        } else {
+            ""
+        },
+        reset_info,
+        pad = 6usize.saturating_sub(percent.len())
+    )
+}
+
+fn identity_line(identity: &ProviderIdentity) -> Option<String> {
+    let details: Vec<&str> = [&identity.email, &identity.organization]
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    let line = match (&identity.plan, details.is_empty()) {
+        (Some(plan), true) => plan.clone(),
+        (Some(plan), false) => format!("{} ({})", plan, details.join(", ")),
+        (None, false) => details.join(", "),
+        (None, true) => return None,
+    };
+    Some(format!("  Plan: {}\n", line))
+}
+
+/// Extra usage in money, or a quota or bonus credit pool as counts.
+fn provider_cost_line(cost: &ProviderCostStatus, reset_format: ResetTimeFormat) -> String {
+    let code = cost.currency_code.as_str();
+    let (title, is_count) = match code {
+        "Quota" => ("Quota usage", true),
+        "Credits" => ("Bonus credits", true),
+        _ => ("Extra usage", false),
+    };
+    let amount = |value: f64| match code {
+        _ if is_count => format!("{:.0}", value),
+        "USD" => format!("${:.2}", value),
+        _ => format!("{} {:.2}", code, value),
+    };
+    let percent = if cost.limit > 0.0 {
+        let share = (cost.used / cost.limit * 100.0).clamp(0.0, 100.0);
+        format!(" ({:.0}%)", share)
+    } else {
+        String::new()
+    };
+    let reset_info = reset_info(cost.resets_in.as_deref(), cost.resets_at, reset_format);
+    format!(
+        "  {}: {} / {}{}{}\n",
+        title,
+        amount(cost.used),
+        amount(cost.limit),
+        percent,
         reset_info
-    );
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{RateWindow, UsageSnapshot};
+    use crate::core::schema::assert_schema_snapshot;
+    use crate::core::status_output::CarveoutStatus;
+    use chrono::Duration;
+
+    #[test]
+    fn test_template_renders_status_structs() {
+        let snapshot = UsageSnapshot {
+            primary: Some(RateWindow {
+                used_percent: 0.25,
+                window_minutes: Some(300),
+                resets_at: Some(Utc::now() + Duration::minutes(90)),
+                reset_description: None,
+                used_percent_exact: None,
+                resets_at_estimated: false,
+            }),
+            secondary: None,
+            tertiary: None,
+            provider_cost: None,
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            updated_at: Utc::now(),
+            received_at: None,
+            identity: ProviderIdentity {
+                email: None,
+                organization: None,
+                plan: Some("Pro".to_string()),
+                login_method: None,
+            },
+        };
+        let statuses = HashMap::from([("claude".to_string(), snapshot_to_status(snapshot))]);
+        let template = StatusTemplate::parse(
+            "{claude.plan} {claude.session.remaining_percent} {claude.weekly.used_percent}",
+            &["claude".to_string()],
+        )
+        .unwrap();
+
+        assert_eq!(
+            render_template(&template, &statuses).unwrap(),
+            "Pro 75% n/a"
+        );
+    }
+
+    #[test]
+    fn test_watch_lines_are_compact_json() {
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let snapshot = UsageSnapshot {
+            primary: Some(RateWindow::from_reported(0.25, Some(300), None, None)),
+            secondary: None,
+            tertiary: None,
+            provider_cost: None,
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            updated_at: now,
+            received_at: None,
+            identity: ProviderIdentity {
+                email: None,
+                organization: None,
+                plan: None,
+                login_method: None,
+            },
+        };
+        let fetched = ProviderUsage {
+            provider: Provider::Claude,
+            name: "Claude".to_string(),
+            snapshot: Some(snapshot),
+            error: None,
+        };
+        let failed = ProviderUsage {
+            provider: Provider::CodexWorkspace(1),
+            name: "Codex (work)".to_string(),
+            snapshot: None,
+            error: Some("Network error".to_string()),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&watch_line(&fetched, now)).unwrap(),
+            r#"{"schema_version":2,"timestamp":1792144800,"provider":"claude","windows":{"session":{"used_percent":0.25,"remaining_percent":0.75,"window_minutes":300}}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&watch_line(&failed, now)).unwrap(),
+            r#"{"schema_version":2,"timestamp":1792144800,"provider":"codex-1","windows":{},"error":"Network error"}"#
+        );
+    }
+
+    #[test]
+    fn test_status_keeps_exact_percent() {
+        let status = window_to_status(&RateWindow::from_reported_percent(
+            45.5,
+            Some(300),
+            None,
+            None,
+        ));
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["used_percent_exact"], 45.5);
+        assert!((json["used_percent"].as_f64().unwrap() - 0.455).abs() < 1e-12);
+        let plain = Style::new(ColorMode::Never, false, true, 0.9);
+        assert!(
+            window_line("Session", &status, ResetTimeFormat::Relative, &plain)
+                .contains("45.5% used")
+        );
+
+        let fraction_only = window_to_status(&RateWindow::from_reported(0.45, None, None, None));
+        let json = serde_json::to_value(&fraction_only).unwrap();
+        assert!(json.get("used_percent_exact").is_none());
+        assert!(json.get("reported_used_percent").is_none());
+
+        let clamped = window_to_status(&RateWindow::from_reported_percent(250.0, None, None, None));
+        let json = serde_json::to_value(&clamped).unwrap();
+        assert_eq!(json["used_percent"], 1.0);
+        assert_eq!(json["used_percent_exact"], 250.0);
+        assert_eq!(json["reported_used_percent"], 2.5);
+    }
+
+    fn window_status(used_percent: f64, resets_in: &str) -> WindowStatus {
+        WindowStatus {
+            used_percent,
+            used_percent_exact: None,
+            remaining_percent: 1.0 - used_percent,
+            resets_in: Some(resets_in.to_string()),
+            resets_at: None,
+            window_minutes: Some(10080),
+            reported_used_percent: None,
+        }
+    }
+
+    fn text_statuses() -> HashMap<String, ProviderStatus> {
+        let opus = window_status(0.15, "3d 4h");
+        let claude = ProviderStatus {
+            session: Some(window_status(0.452, "2h 05m")),
+            weekly: Some(window_status(0.32, "3d 4h")),
+            tertiary: Some(window_status(0.15, "3d 4h")),
+            carveouts: vec![
+                CarveoutStatus {
+                    label: "Opus Weekly".to_string(),
+                    window: opus,
+                },
+                CarveoutStatus {
+                    label: "Sonnet Weekly".to_string(),
+                    window: window_status(0.6, "3d 4h"),
+                },
+            ],
+            surfaces: Vec::new(),
+            provider_cost: Some(ProviderCostStatus {
+                used: 23.45,
+                limit: 123.45,
+                currency_code: "USD".to_string(),
+                period: Some("Monthly".to_string()),
+                resets_at: None,
+                resets_in: Some("9d 2h".to_string()),
+            }),
+            identity: Some(ProviderIdentity {
+                email: Some("me@example.com".to_string()),
+                organization: None,
+                plan: Some("Claude Max".to_string()),
+                login_method: None,
+            }),
+            error: None,
+            fetch_latency_ms: None,
+            last_success_at: None,
+            error_since: None,
+            week_over_week_percent: None,
+            lockouts: Vec::new(),
+            week_start: None,
+            provider: Some(Provider::Claude),
+        };
+        let codex = ProviderStatus {
+            session: Some(window_status(0.1, "4h 30m")),
+            weekly: Some(window_status(0.05, "6d 1h")),
+            tertiary: Some(WindowStatus {
+                reported_used_percent: Some(0.31),
+                ..window_status(0.3, "1h 10m")
+            }),
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            provider_cost: Some(ProviderCostStatus {
+                used: 120.0,
+                limit: 500.0,
+                currency_code: "Credits".to_string(),
+                period: None,
+                resets_at: None,
+                resets_in: None,
+            }),
+            identity: Some(ProviderIdentity {
+                email: None,
+                organization: None,
+                plan: Some("Plus".to_string()),
+                login_method: None,
+            }),
+            error: None,
+            fetch_latency_ms: None,
+            last_success_at: None,
+            error_since: None,
+            week_over_week_percent: None,
+            lockouts: Vec::new(),
+            week_start: None,
+            provider: Some(Provider::Codex),
+        };
+        let failing = ProviderStatus {
+            session: None,
+            weekly: None,
+            tertiary: None,
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            provider_cost: None,
+            identity: None,
+            error: Some("Network error".to_string()),
+            fetch_latency_ms: None,
+            last_success_at: None,
+            error_since: None,
+            week_over_week_percent: None,
+            lockouts: Vec::new(),
+            week_start: None,
+            provider: Some(Provider::CodexWorkspace(1)),
+        };
+        HashMap::from([
+            ("Claude".to_string(), claude),
+            ("Codex".to_string(), codex),
+            ("Codex (work)".to_string(), failing),
+        ])
+    }
+
+    #[test]
+    fn test_text_output_matches_golden_files() {
+        let statuses = text_statuses();
+        let plain = Style::new(ColorMode::Never, false, true, 0.9);
+        assert_eq!(
+            format_text_output(&statuses, false, ResetTimeFormat::Relative, &plain),
+            include_str!("../../tests/fixtures/status/concise.txt")
+        );
+        assert_eq!(
+            format_text_output(&statuses, true, ResetTimeFormat::Relative, &plain),
+            include_str!("../../tests/fixtures/status/all_windows.txt")
+        );
+    }
+
+    #[test]
+    fn test_text_output_reset_time_format() {
+        let now = Utc::now();
+        let resets_at = now + Duration::minutes(2 * 60 + 14) + Duration::seconds(30);
+        let clock = format_reset_clock(resets_at, now, &Local, HourCycle::from_env());
+        let line = |window: &RateWindow, format| {
+            let plain = Style::new(ColorMode::Never, false, true, 0.9);
+            window_line("Session", &window_to_status(window), format, &plain)
+        };
+        let mut window = RateWindow::from_reported(0.25, Some(300), Some(resets_at), None);
+
+        assert!(line(&window, ResetTimeFormat::Relative).ends_with("(resets in 2h 14m)\n"));
+        assert!(
+            line(&window, ResetTimeFormat::Absolute).ends_with(&format!("(resets {})\n", clock))
+        );
+        assert!(line(&window, ResetTimeFormat::Both)
+            .ends_with(&format!("(resets in 2h 14m ({}))\n", clock)));
+        window.resets_at_estimated = true;
+        assert!(line(&window, ResetTimeFormat::Both)
+            .ends_with(&format!("(resets in ~2h 14m (~{}))\n", clock)));
+        // The JSON keeps the countdown alone.
+        let json = serde_json::to_value(window_to_status(&window)).unwrap();
+        assert_eq!(json["resets_in"], "~2h 14m");
+        assert!(json.get("resets_at").is_none());
+    }
+
+    #[test]
+    fn test_color_modes_match_golden_files() {
+        let statuses = text_statuses();
+        let concise = include_str!("../../tests/fixtures/status/concise.txt");
+        let colored = include_str!("../../tests/fixtures/status/concise_color.txt");
+        let output = |mode, is_tty| {
+            let style = Style::new(mode, false, is_tty, 0.5);
+            format_text_output(&statuses, false, ResetTimeFormat::Relative, &style)
+        };
+
+        assert_eq!(output(ColorMode::Auto, true), colored);
+        assert_eq!(output(ColorMode::Auto, false), concise);
+        assert_eq!(output(ColorMode::Always, false), colored);
+        assert_eq!(output(ColorMode::Never, true), concise);
+        let no_color = Style::new(ColorMode::Auto, true, true, 0.5);
+        assert_eq!(
+            format_text_output(&statuses, false, ResetTimeFormat::Relative, &no_color),
+            concise
+        );
+    }
+
+    #[test]
+    fn test_health_timestamps_in_json() {
+        let now = Utc::now();
+        let daemon = ProviderHealth {
+            last_success_at: Some(now - Duration::hours(2)),
+            error_since: Some(now - Duration::minutes(30)),
+        };
+
+        let mut failing = ProviderStatus {
+            session: None,
+            weekly: None,
+            tertiary: None,
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            provider_cost: None,
+            identity: None,
+            error: Some("Network error".to_string()),
+            fetch_latency_ms: None,
+            last_success_at: None,
+            error_since: None,
+            week_over_week_percent: None,
+            lockouts: Vec::new(),
+            week_start: None,
+            provider: None,
+        };
+        apply_health(&mut failing, daemon, now);
+        let json = serde_json::to_value(&failing).unwrap();
+        assert_eq!(
+            json["last_success_at"],
+            daemon.last_success_at.unwrap().timestamp()
+        );
+        assert_eq!(json["error_since"], daemon.error_since.unwrap().timestamp());
+
+        let mut healthy = ProviderStatus {
+            error: None,
+            ..failing
+        };
+        apply_health(&mut healthy, daemon, now);
+        let json = serde_json::to_value(&healthy).unwrap();
+        assert_eq!(json["last_success_at"], now.timestamp());
+        assert!(json["error_since"].is_null());
+    }
+
+    #[test]
+    fn test_schema_snapshots() {
+        assert_schema_snapshot::<StatusOutput>(
+            "status",
+            include_str!("../../tests/fixtures/schema/status.json"),
+        );
+        assert_schema_snapshot::<ErrorHistoryOutput>(
+            "status-errors",
+            include_str!("../../tests/fixtures/schema/status-errors.json"),
+        );
+        assert_schema_snapshot::<WatchLine>(
+            "status-watch",
+            include_str!("../../tests/fixtures/schema/status-watch.json"),
+        );
+    }
 }
@@ -0,0 +1,309 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+/// Template fields and where each lives in a provider's `status --json` entry.
+const FIELDS: &[(&str, &str)] = &[
+    ("session.used_percent", "/session/used_percent"),
+    ("session.remaining_percent", "/session/remaining_percent"),
+    ("session.resets_in", "/session/resets_in"),
+    ("weekly.used_percent", "/weekly/used_percent"),
+    ("weekly.remaining_percent", "/weekly/remaining_percent"),
+    ("weekly.resets_in", "/weekly/resets_in"),
+    ("plan", "/identity/plan"),
+    ("email", "/identity/email"),
+    ("error", "/error"),
+];
+
+/// Shown for values the provider didn't report, like a missing weekly window.
+const MISSING: &str = "n/a";
+
+const DEFAULT_PERCENT: Precision = Precision {
+    decimals: 0,
+    percent: true,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Precision {
+    decimals: usize,
+    percent: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Field {
+        provider: String,
+        pointer: &'static str,
+        precision: Option<Precision>,
+    },
+}
+
+/// A `status --format` template such as `{claude.session.used_percent:.1%}`.
+#[derive(Debug, PartialEq)]
+pub struct StatusTemplate {
+    segments: Vec<Segment>,
+}
+
+impl StatusTemplate {
+    /// Parses `template`, accepting placeholders for the given provider ids.
+    /// `{{` and `}}` produce literal braces.
+    pub fn parse(template: &str, providers: &[String]) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+
+        while let Some(start) = rest.find(['{', '}']) {
+            text.push_str(&rest[..start]);
+            let brace = if rest[start..].starts_with('{') {
+                '{'
+            } else {
+                '}'
+            };
+            let after = &rest[start + 1..];
+            if let Some(escaped) = after.strip_prefix(brace) {
+                text.push(brace);
+                rest = escaped;
+                continue;
+            }
+            if brace == '}' {
+                anyhow::bail!("Unmatched '}}' in format template; use '}}}}' for a literal brace");
+            }
+            let Some(end) = after.find('}') else {
+                anyhow::bail!("Unclosed '{{' in format template {:?}", template);
+            };
+
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(parse_placeholder(&after[..end], providers)?);
+            rest = &after[end + 1..];
+        }
+
+        text.push_str(rest);
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+        Ok(Self { segments })
+    }
+
+    /// Renders against `status --json` provider entries keyed by provider id.
+    pub fn render(&self, statuses: &Value) -> String {
+        let mut output = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Field {
+                    provider,
+                    pointer,
+                    precision,
+                } => {
+                    let value = statuses
+                        .get(provider)
+                        .and_then(|status| status.pointer(pointer));
+                    output.push_str(&format_value(value, *precision));
+                }
+            }
+        }
+        output
+    }
+}
+
+fn parse_placeholder(placeholder: &str, providers: &[String]) -> Result<Segment> {
+    let (key, spec) = match placeholder.split_once(':') {
+        Some((key, spec)) => (key.trim(), Some(spec.trim())),
+        None => (placeholder.trim(), None),
+    };
+
+    let field = providers.iter().find_map(|provider| {
+        let name = key.strip_prefix(provider.as_str())?.strip_prefix('.')?;
+        FIELDS
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(field, pointer)| (provider, *field, *pointer))
+    });
+    let Some((provider, name, pointer)) = field else {
+        anyhow::bail!(
+            "Unknown placeholder {{{}}}. Valid placeholders: {}",
+            key,
+            valid_keys(providers).join(", ")
+        );
+    };
+
+    let is_percent = name.ends_with("_percent");
+    let precision = match spec {
+        Some(spec) => {
+            if !is_percent {
+                anyhow::bail!(
+                    "{{{}}} is not a number, so it can't take a format spec",
+                    key
+                );
+            }
+            Some(parse_precision(spec).with_context(|| {
+                format!(
+                    "Invalid format spec ':{}' in {{{}}}; expected e.g. :.0% or :.2",
+                    spec, key
+                )
+            })?)
+        }
+        None if is_percent => Some(DEFAULT_PERCENT),
+        None => None,
+    };
+
+    Ok(Segment::Field {
+        provider: provider.clone(),
+        pointer,
+        precision,
+    })
+}
+
+/// `.N%` renders a fraction as a percentage, `.N` as a plain number.
+fn parse_precision(spec: &str) -> Result<Precision> {
+    let digits = spec
+        .strip_prefix('.')
+        .context("precision must start with '.'")?;
+    let (digits, percent) = match digits.strip_suffix('%') {
+        Some(digits) => (digits, true),
+        None => (digits, false),
+    };
+    let decimals: usize = digits.parse().context("precision must be a number")?;
+    if decimals > 6 {
+        anyhow::bail!("precision must be at most 6");
+    }
+    Ok(Precision { decimals, percent })
+}
+
+fn format_value(value: Option<&Value>, precision: Option<Precision>) -> String {
+    match value {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Number(number)) => match (number.as_f64(), precision) {
+            (Some(x), Some(p)) if p.percent => format!("{:.*}%", p.decimals, x * 100.0),
+            (Some(x), Some(p)) => format!("{:.*}", p.decimals, x),
+            _ => number.to_string(),
+        },
+        _ => MISSING.to_string(),
+    }
+}
+
+fn valid_keys(providers: &[String]) -> Vec<String> {
+    providers
+        .iter()
+        .flat_map(|provider| {
+            FIELDS
+                .iter()
+                .map(move |(field, _)| format!("{}.{}", provider, field))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn providers() -> Vec<String> {
+        vec!["claude".to_string(), "codex".to_string()]
+    }
+
+    fn statuses() -> Value {
+        json!({
+            "claude": {
+                "session": {"used_percent": 0.456, "remaining_percent": 0.544, "resets_in": "2h 05m"},
+                "weekly": {"used_percent": 0.6, "remaining_percent": 0.4},
+                "identity": {"plan": "Max"}
+            },
+            "codex": {
+                "error": "Token expired"
+            }
+        })
+    }
+
+    fn render(template: &str) -> String {
+        StatusTemplate::parse(template, &providers())
+            .unwrap()
+            .render(&statuses())
+    }
+
+    #[test]
+    fn test_parse_template() {
+        let template = StatusTemplate::parse("C {claude.plan}: {{x}}", &providers()).unwrap();
+        assert_eq!(
+            template.segments,
+            vec![
+                Segment::Text("C ".to_string()),
+                Segment::Field {
+                    provider: "claude".to_string(),
+                    pointer: "/identity/plan",
+                    precision: None,
+                },
+                Segment::Text(": {x}".to_string()),
+            ]
+        );
+
+        let template =
+            StatusTemplate::parse("{codex.weekly.used_percent:.1%}", &providers()).unwrap();
+        assert_eq!(
+            template.segments,
+            vec![Segment::Field {
+                provider: "codex".to_string(),
+                pointer: "/weekly/used_percent",
+                precision: Some(Precision {
+                    decimals: 1,
+                    percent: true,
+                }),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_render_percentages() {
+        assert_eq!(render("{claude.session.used_percent}"), "46%");
+        assert_eq!(render("{claude.session.used_percent:.1%}"), "45.6%");
+        assert_eq!(render("{claude.session.remaining_percent:.2}"), "0.54");
+        assert_eq!(
+            render("{claude.plan} {claude.weekly.used_percent} resets {claude.session.resets_in}"),
+            "Max 60% resets 2h 05m"
+        );
+    }
+
+    #[test]
+    fn test_render_missing_values() {
+        assert_eq!(render("{claude.weekly.resets_in}"), "n/a");
+        assert_eq!(render("{claude.email}"), "n/a");
+        assert_eq!(render("{codex.session.used_percent}"), "n/a");
+        assert_eq!(render("{codex.error}"), "Token expired");
+        assert_eq!(render("{claude.error}"), "n/a");
+    }
+
+    #[test]
+    fn test_unknown_placeholder_lists_valid_keys() {
+        let err = StatusTemplate::parse("{claude.sesion.used_percent}", &providers())
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("Unknown placeholder {claude.sesion.used_percent}"));
+        assert!(err.contains("claude.session.used_percent"));
+        assert!(err.contains("codex.weekly.resets_in"));
+        assert!(err.contains("codex.plan"));
+
+        let err = StatusTemplate::parse("{codex-1.plan}", &providers()).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Unknown placeholder {codex-1.plan}"));
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        for template in [
+            "{claude.plan",
+            "claude.plan}",
+            "{claude.plan:.0%}",
+            "{claude.session.used_percent:0%}",
+            "{claude.session.used_percent:.x%}",
+        ] {
+            assert!(
+                StatusTemplate::parse(template, &providers()).is_err(),
+                "{:?} should be rejected",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::core::models::Provider;
+use crate::ui::colors::{provider_rgb, ColorScheme};
+use clap::ValueEnum;
+use std::io::IsTerminal;
+
+/// `--color`: whether text output is colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Colors for `status` and `cost` text output. Everything goes through
+/// here, so turning color off gives exactly the plain output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Style {
+    color: bool,
+    /// `notifications.threshold`; usage at or above it is yellow.
+    threshold: f64,
+    scheme: ColorScheme,
+}
+
+impl Style {
+    /// `no_color` is whether `NO_COLOR` is set to something non-empty, which
+    /// only `auto` honors: an explicit `--color always` wins.
+    pub fn new(mode: ColorMode, no_color: bool, is_tty: bool, threshold: f64) -> Self {
+        let color = match mode {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => is_tty && !no_color,
+        };
+        Self {
+            color,
+            threshold,
+            scheme: ColorScheme::default(),
+        }
+    }
+
+    /// `display.colorblind_mode`'s palette for warnings and errors.
+    pub fn with_scheme(mut self, scheme: ColorScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// For output to this process's stdout.
+    pub fn detect(mode: ColorMode, threshold: f64) -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self::new(mode, no_color, std::io::stdout().is_terminal(), threshold)
+    }
+
+    pub fn provider(&self, provider: Option<Provider>, text: &str) -> String {
+        match provider {
+            Some(provider) => self.paint(provider_rgb(provider), text),
+            None => text.to_string(),
+        }
+    }
+
+    /// `text` shows `used` (a fraction): yellow from the threshold, red once
+    /// the limit is reached.
+    pub fn usage(&self, used: f64, text: &str) -> String {
+        if used >= 1.0 {
+            self.paint(self.scheme.critical_rgb(), text)
+        } else if used >= self.threshold {
+            self.paint(self.scheme.warning_rgb(), text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    pub fn error(&self, text: &str) -> String {
+        self.paint(self.scheme.critical_rgb(), text)
+    }
+
+    fn paint(&self, (r, g, b): (u8, u8, u8), text: &str) -> String {
+        if !self.color {
+            return text.to_string();
+        }
+        format!("\u{1b}[38;2;{};{};{}m{}\u{1b}[0m", r, g, b, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modes_with_forced_terminal() {
+        let colored = |style: Style| style.color;
+        for is_tty in [true, false] {
+            for no_color in [true, false] {
+                assert!(colored(Style::new(
+                    ColorMode::Always,
+                    no_color,
+                    is_tty,
+                    0.9
+                )));
+                assert!(!colored(Style::new(
+                    ColorMode::Never,
+                    no_color,
+                    is_tty,
+                    0.9
+                )));
+            }
+        }
+        assert!(colored(Style::new(ColorMode::Auto, false, true, 0.9)));
+        assert!(!colored(Style::new(ColorMode::Auto, false, false, 0.9)));
+        assert!(!colored(Style::new(ColorMode::Auto, true, true, 0.9)));
+    }
+
+    #[test]
+    fn test_usage_colors_follow_threshold() {
+        let style = Style::new(ColorMode::Always, false, false, 0.8);
+        assert_eq!(style.usage(0.5, "50%"), "50%");
+        assert_eq!(
+            style.usage(0.8, "80%"),
+            "\u{1b}[38;2;229;165;10m80%\u{1b}[0m"
+        );
+        assert_eq!(
+            style.usage(1.0, "100%"),
+            "\u{1b}[38;2;224;27;36m100%\u{1b}[0m"
+        );
+        assert_eq!(style.provider(None, "Total"), "Total");
+
+        let plain = Style::new(ColorMode::Never, false, true, 0.8);
+        assert_eq!(plain.usage(1.0, "100%"), "100%");
+        assert_eq!(plain.error("Error"), "Error");
+
+        let colorblind = style.with_scheme(ColorScheme::Colorblind);
+        assert_eq!(
+            colorblind.usage(0.8, "80%"),
+            "\u{1b}[38;2;230;159;0m80%\u{1b}[0m"
+        );
+        assert_eq!(
+            colorblind.usage(1.0, "100%"),
+            "\u{1b}[38;2;213;94;0m100%\u{1b}[0m"
+        );
+    }
+}
@@ -0,0 +1,239 @@
+use crate::cli::source::{ProviderUsage, UsageSource};
+use crate::cli::status::emit;
+use crate::core::models::Provider;
+use crate::core::settings::Settings;
+use crate::core::status_output::format_reset_time;
+use anyhow::Result;
+use serde::Serialize;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+
+// Linux signal numbers; the bar sends these to pause and resume us.
+const SIGSTOP: i32 = 19;
+const SIGCONT: i32 = 18;
+
+const WARNING_COLOR: &str = "#E5C07B";
+const CRITICAL_COLOR: &str = "#E06C75";
+
+#[derive(Serialize)]
+struct Header {
+    version: u32,
+    stop_signal: i32,
+    cont_signal: i32,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct Block {
+    name: &'static str,
+    instance: String,
+    full_text: String,
+    short_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    pub warning: f64,
+    pub critical: f64,
+}
+
+pub async fn run(interval: u64, thresholds: Thresholds) -> Result<()> {
+    let settings = Settings::load()?;
+    let mut source = UsageSource::new(&settings);
+    if source.is_empty() {
+        anyhow::bail!("No providers enabled. Check your configuration.");
+    }
+
+    let interval = Duration::from_secs(interval.max(1));
+    let mut resumed = signal(SignalKind::from_raw(SIGCONT))?;
+
+    let header = serde_json::to_string(&Header {
+        version: 1,
+        stop_signal: SIGSTOP,
+        cont_signal: SIGCONT,
+    })?;
+    if !emit(&format!("{}\n[", header))? {
+        return Ok(());
+    }
+
+    let mut first = true;
+    loop {
+        let blocks: Vec<Block> = source
+            .read()
+            .await
+            .iter()
+            .map(|usage| block(usage, thresholds))
+            .collect();
+        let line = serde_json::to_string(&blocks)?;
+        let separator = if first { "" } else { "," };
+        if !emit(&format!("{}{}", separator, line))? {
+            return Ok(());
+        }
+        first = false;
+
+        // After a stop the data is stale, so refresh as soon as we resume.
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = resumed.recv() => {}
+        }
+    }
+}
+
+fn block(usage: &ProviderUsage, thresholds: Thresholds) -> Block {
+    let short_name = short_name(usage.provider);
+    let instance = usage.provider.id();
+
+    let Some(snapshot) = &usage.snapshot else {
+        let full_text = match &usage.error {
+            Some(error) => format!("{} error: {}", usage.name, error),
+            None => format!("{} loading", usage.name),
+        };
+        return Block {
+            name: "claude-bar",
+            instance,
+            full_text,
+            short_text: format!("{} !", short_name),
+            color: usage.error.as_ref().map(|_| CRITICAL_COLOR),
+        };
+    };
+
+    let session = snapshot.primary.as_ref();
+    let weekly = snapshot.secondary.as_ref();
+    let percent = |used: f64| format!("{:.0}%", used * 100.0);
+
+    let headline = session
+        .or(weekly)
+        .map(|window| percent(window.used_percent))
+        .unwrap_or_else(|| "--".to_string());
+    let mut full_text = format!("{} {}", usage.name, headline);
+    if let (Some(_), Some(weekly)) = (session, weekly) {
+        full_text.push_str(&format!(
+            " \u{00b7} weekly {}",
+            percent(weekly.used_percent)
+        ));
+    }
+    if let Some(resets_at) = session.or(weekly).and_then(|window| window.resets_at) {
+        full_text.push_str(&format!(
+            " \u{00b7} resets {}",
+            format_reset_time(resets_at)
+        ));
+    }
+
+    let peak = [session, weekly]
+        .into_iter()
+        .flatten()
+        .map(|window| window.used_percent)
+        .fold(0.0, f64::max);
+    let color = if peak >= thresholds.critical {
+        Some(CRITICAL_COLOR)
+    } else if peak >= thresholds.warning {
+        Some(WARNING_COLOR)
+    } else {
+        None
+    };
+
+    Block {
+        name: "claude-bar",
+        instance,
+        full_text,
+        short_text: format!("{} {}", short_name, headline),
+        color,
+    }
+}
+
+fn short_name(provider: Provider) -> String {
+    match provider {
+        Provider::Claude => "C".to_string(),
+        Provider::Codex => "X".to_string(),
+        Provider::CodexWorkspace(n) => format!("X{}", n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{ProviderIdentity, RateWindow, UsageSnapshot};
+    use chrono::Utc;
+
+    const THRESHOLDS: Thresholds = Thresholds {
+        warning: 0.75,
+        critical: 0.9,
+    };
+
+    fn window(used_percent: f64) -> RateWindow {
+        RateWindow {
+            used_percent,
+            window_minutes: None,
+            resets_at: None,
+            reset_description: None,
+            used_percent_exact: None,
+            resets_at_estimated: false,
+        }
+    }
+
+    fn usage(provider: Provider, session: f64, weekly: f64) -> ProviderUsage {
+        ProviderUsage {
+            provider,
+            name: provider.name().to_string(),
+            snapshot: Some(UsageSnapshot {
+                primary: Some(window(session)),
+                secondary: Some(window(weekly)),
+                tertiary: None,
+                provider_cost: None,
+                carveouts: Vec::new(),
+                surfaces: Vec::new(),
+                updated_at: Utc::now(),
+                received_at: None,
+                identity: ProviderIdentity {
+                    email: None,
+                    organization: None,
+                    plan: None,
+                    login_method: None,
+                },
+            }),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_block_text() {
+        let block = block(&usage(Provider::Claude, 0.45, 0.6), THRESHOLDS);
+        assert_eq!(block.instance, "claude");
+        assert_eq!(block.short_text, "C 45%");
+        assert_eq!(block.full_text, "Claude 45% \u{00b7} weekly 60%");
+        assert_eq!(block.color, None);
+    }
+
+    #[test]
+    fn test_block_color_follows_thresholds() {
+        let warning = block(&usage(Provider::Codex, 0.2, 0.8), THRESHOLDS);
+        assert_eq!(warning.color, Some(WARNING_COLOR));
+        assert_eq!(warning.short_text, "X 20%");
+
+        let critical = block(&usage(Provider::Claude, 0.95, 0.5), THRESHOLDS);
+        assert_eq!(critical.color, Some(CRITICAL_COLOR));
+    }
+
+    #[test]
+    fn test_block_for_error() {
+        let failed = ProviderUsage {
+            provider: Provider::CodexWorkspace(2),
+            name: "Work".to_string(),
+            snapshot: None,
+            error: Some("Token expired".to_string()),
+        };
+        let block = block(&failed, THRESHOLDS);
+        assert_eq!(block.full_text, "Work error: Token expired");
+        assert_eq!(block.short_text, "X2 !");
+        assert_eq!(block.color, Some(CRITICAL_COLOR));
+    }
+
+    #[test]
+    fn test_block_serialization_skips_missing_color() {
+        let block = block(&usage(Provider::Claude, 0.1, 0.1), THRESHOLDS);
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(json["name"], "claude-bar");
+        assert!(json.get("color").is_none());
+    }
+}
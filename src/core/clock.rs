@@ -0,0 +1,281 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use reqwest::header::{HeaderMap, DATE};
+
+/// Clock differences up to this many seconds are network latency and the
+/// `Date` header's one-second resolution, not skew worth correcting.
+pub const SKEW_TOLERANCE_SECS: i64 = 30;
+
+/// How far the API server's clock runs ahead of ours (negative when it is
+/// behind), from the `Date` header of a response received at `received_at`.
+/// `None` when the header is missing or the clocks agree within
+/// `SKEW_TOLERANCE_SECS`.
+pub fn server_clock_skew(headers: &HeaderMap, received_at: DateTime<Utc>) -> Option<Duration> {
+    let date = headers.get(DATE)?.to_str().ok()?;
+    skew_from_date(date, received_at)
+}
+
+fn skew_from_date(date: &str, received_at: DateTime<Utc>) -> Option<Duration> {
+    let server_now = DateTime::parse_from_rfc2822(date).ok()?.with_timezone(&Utc);
+    let skew = server_now - received_at;
+    if skew.num_seconds().abs() <= SKEW_TOLERANCE_SECS {
+        return None;
+    }
+    tracing::debug!(
+        skew_secs = skew.num_seconds(),
+        "Clock skew detected against the API server"
+    );
+    Some(skew)
+}
+
+/// A reset time reported on the server's clock, moved onto ours.
+pub fn to_local_clock(server_time: DateTime<Utc>, skew: Duration) -> DateTime<Utc> {
+    server_time - skew
+}
+
+/// Time since a snapshot was fetched. `monotonic_elapsed` is measured from
+/// when this process received it and is immune to wall clock jumps; without
+/// it the wall clock delta is used, clamped at zero when `updated_at` lies in
+/// the future.
+pub fn snapshot_age(
+    updated_at: DateTime<Utc>,
+    monotonic_elapsed: Option<std::time::Duration>,
+    now: DateTime<Utc>,
+) -> Duration {
+    if let Some(elapsed) = monotonic_elapsed {
+        return Duration::from_std(elapsed).unwrap_or_else(|_| Duration::zero());
+    }
+    let age = now - updated_at;
+    if age < Duration::zero() {
+        tracing::debug!(
+            ahead_secs = -age.num_seconds(),
+            "Clock skew detected: snapshot is newer than the local clock"
+        );
+        return Duration::zero();
+    }
+    age
+}
+
+/// Whether times are written on a 12- or 24-hour clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HourCycle {
+    H12,
+    H24,
+}
+
+/// Locales whose glibc time format uses AM/PM.
+const TWELVE_HOUR_LOCALES: &[&str] = &[
+    "en_US", "en_CA", "en_AU", "en_NZ", "en_PH", "en_IN", "es_US", "hi_IN", "ar_EG", "ar_SA",
+    "ur_PK",
+];
+
+impl HourCycle {
+    /// From the first of `LC_ALL`, `LC_TIME` and `LANG` that is set, the
+    /// order the C library reads them in. 24-hour without a locale.
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_TIME", "LANG"]
+            .into_iter()
+            .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+            .map_or(HourCycle::H24, |locale| Self::for_locale(&locale))
+    }
+
+    fn for_locale(locale: &str) -> Self {
+        // Drop the codeset and modifier, as in "en_US.UTF-8" or "de_DE@euro".
+        let name = locale.split(['.', '@']).next().unwrap_or_default();
+        if TWELVE_HOUR_LOCALES.contains(&name) {
+            HourCycle::H12
+        } else {
+            HourCycle::H24
+        }
+    }
+
+    fn time_format(self) -> &'static str {
+        match self {
+            HourCycle::H12 => "%-I:%M %p",
+            HourCycle::H24 => "%H:%M",
+        }
+    }
+}
+
+/// When a reset happens in `tz`: "17:42" today, "tomorrow 09:00", the day
+/// name later in the week ("Tue 09:00") and the date after that
+/// ("Mar 12 09:00").
+pub fn format_reset_clock<Tz: TimeZone>(
+    resets_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    cycle: HourCycle,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    match reset_day_and_time(resets_at, now, tz, cycle) {
+        (Some(day), time) => format!("{} {}", day, time),
+        (None, time) => time,
+    }
+}
+
+/// `format_reset_clock` for tooltips, which spell out "today" too.
+pub fn format_reset_tooltip<Tz: TimeZone>(
+    resets_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    cycle: HourCycle,
+) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let (day, time) = reset_day_and_time(resets_at, now, tz, cycle);
+    format!("{} {}", day.as_deref().unwrap_or("today"), time)
+}
+
+fn reset_day_and_time<Tz: TimeZone>(
+    resets_at: DateTime<Utc>,
+    now: DateTime<Utc>,
+    tz: &Tz,
+    cycle: HourCycle,
+) -> (Option<String>, String)
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let local = resets_at.with_timezone(tz);
+    let days_ahead = (local.date_naive() - now.with_timezone(tz).date_naive()).num_days();
+    let day = match days_ahead {
+        ..=0 => None,
+        1 => Some("tomorrow".to_string()),
+        2..=6 => Some(local.format("%a").to_string()),
+        _ => Some(local.format("%b %-d").to_string()),
+    };
+    (day, local.format(cycle.time_format()).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(rfc3339: &str) -> DateTime<Utc> {
+        rfc3339.parse().unwrap()
+    }
+
+    #[test]
+    fn test_skew_from_date_header() {
+        let received = at("2026-03-04T12:00:00Z");
+
+        // Server two minutes ahead of us.
+        assert_eq!(
+            skew_from_date("Wed, 04 Mar 2026 12:02:00 GMT", received),
+            Some(Duration::minutes(2))
+        );
+        // Our clock running ahead after a resume, before NTP catches up.
+        assert_eq!(
+            skew_from_date("Wed, 04 Mar 2026 11:15:00 GMT", received),
+            Some(Duration::minutes(-45))
+        );
+        // Latency and rounding are not skew.
+        assert_eq!(
+            skew_from_date("Wed, 04 Mar 2026 11:59:31 GMT", received),
+            None
+        );
+        assert_eq!(
+            skew_from_date("Wed, 04 Mar 2026 12:00:30 GMT", received),
+            None
+        );
+        assert_eq!(skew_from_date("yesterday", received), None);
+
+        let mut headers = HeaderMap::new();
+        assert_eq!(server_clock_skew(&headers, received), None);
+        headers.insert(DATE, "Wed, 04 Mar 2026 12:02:00 GMT".parse().unwrap());
+        assert_eq!(
+            server_clock_skew(&headers, received),
+            Some(Duration::minutes(2))
+        );
+    }
+
+    #[test]
+    fn test_reset_countdown_follows_server_clock() {
+        // The server says the window resets at 14:00 its time, and its clock
+        // is 10 minutes ahead, so the reset happens at 13:50 on ours.
+        let skew = Duration::minutes(10);
+        assert_eq!(
+            to_local_clock(at("2026-03-04T14:00:00Z"), skew),
+            at("2026-03-04T13:50:00Z")
+        );
+        assert_eq!(
+            to_local_clock(at("2026-03-04T14:00:00Z"), -skew),
+            at("2026-03-04T14:10:00Z")
+        );
+    }
+
+    #[test]
+    fn test_snapshot_age_with_skewed_clock() {
+        let updated_at = at("2026-03-04T12:00:00Z");
+
+        // The wall clock jumped back an hour since the fetch, but the
+        // monotonic clock says 90 seconds passed.
+        let now = at("2026-03-04T11:01:30Z");
+        assert_eq!(
+            snapshot_age(updated_at, Some(std::time::Duration::from_secs(90)), now),
+            Duration::seconds(90)
+        );
+
+        // Without a monotonic reading a future timestamp counts as new.
+        assert_eq!(snapshot_age(updated_at, None, now), Duration::zero());
+        assert_eq!(
+            snapshot_age(updated_at, None, at("2026-03-04T12:05:00Z")),
+            Duration::minutes(5)
+        );
+    }
+
+    #[test]
+    fn test_reset_clock_names_the_day() {
+        // A Tuesday.
+        let now = at("2026-03-03T12:00:00Z");
+        let clock = |resets_at: &str| format_reset_clock(at(resets_at), now, &Utc, HourCycle::H24);
+
+        assert_eq!(clock("2026-03-03T17:42:00Z"), "17:42");
+        assert_eq!(clock("2026-03-04T09:00:00Z"), "tomorrow 09:00");
+        assert_eq!(clock("2026-03-06T09:00:00Z"), "Fri 09:00");
+        assert_eq!(clock("2026-03-09T23:59:00Z"), "Mon 23:59");
+        // A week or more out, the weekday alone would be ambiguous.
+        assert_eq!(clock("2026-03-10T09:00:00Z"), "Mar 10 09:00");
+
+        assert_eq!(
+            format_reset_tooltip(at("2026-03-03T17:42:00Z"), now, &Utc, HourCycle::H24),
+            "today 17:42"
+        );
+        assert_eq!(
+            format_reset_tooltip(at("2026-03-04T09:00:00Z"), now, &Utc, HourCycle::H12),
+            "tomorrow 9:00 AM"
+        );
+    }
+
+    #[test]
+    fn test_reset_clock_uses_the_local_day() {
+        // 01:00 on Wednesday at UTC+5, still Tuesday in UTC.
+        let now = at("2026-03-03T20:00:00Z");
+        let plus_five = chrono::FixedOffset::east_opt(5 * 3600).unwrap();
+        let resets_at = at("2026-03-04T03:00:00Z");
+
+        assert_eq!(
+            format_reset_clock(resets_at, now, &plus_five, HourCycle::H24),
+            "08:00"
+        );
+        assert_eq!(
+            format_reset_clock(resets_at, now, &Utc, HourCycle::H24),
+            "tomorrow 03:00"
+        );
+        assert_eq!(
+            format_reset_clock(resets_at, now, &plus_five, HourCycle::H12),
+            "8:00 AM"
+        );
+    }
+
+    #[test]
+    fn test_hour_cycle_for_locale() {
+        assert_eq!(HourCycle::for_locale("en_US.UTF-8"), HourCycle::H12);
+        assert_eq!(HourCycle::for_locale("en_AU"), HourCycle::H12);
+        assert_eq!(HourCycle::for_locale("en_GB.UTF-8"), HourCycle::H24);
+        assert_eq!(HourCycle::for_locale("de_DE@euro"), HourCycle::H24);
+        assert_eq!(HourCycle::for_locale("C.UTF-8"), HourCycle::H24);
+        assert_eq!(HourCycle::for_locale("POSIX"), HourCycle::H24);
+    }
+}
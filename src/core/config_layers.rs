@@ -0,0 +1,302 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Top-level key listing files to merge over the main config.
+const INCLUDE_KEY: &str = "include";
+
+/// The main config file with the files it includes merged in.
+#[derive(Debug)]
+pub struct LayeredConfig {
+    pub table: Table,
+    /// Every included file, including ones that don't exist (yet).
+    pub includes: Vec<PathBuf>,
+}
+
+/// Parses `content`, the config file at `path`, and merges each file its
+/// `include` list names over it in order, so later files win. Included
+/// files can't include others, and ones that don't exist are skipped.
+pub fn read_layers(content: &str, path: &Path) -> Result<LayeredConfig> {
+    let mut table: Table = toml::from_str(content)?;
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let includes = take_includes(&mut table, dir)?;
+
+    for include in &includes {
+        let content = match std::fs::read_to_string(include) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!(path = ?include, "Included config file not found, skipping");
+                continue;
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("Failed to read included config file: {}", include.display())
+                })
+            }
+        };
+        let layer: Table = toml::from_str(&content).with_context(|| {
+            format!(
+                "Failed to parse included config file: {}",
+                include.display()
+            )
+        })?;
+        if layer.contains_key(INCLUDE_KEY) {
+            anyhow::bail!(
+                "{} has an `{}` list, but only the main config file can include others",
+                include.display(),
+                INCLUDE_KEY
+            );
+        }
+        deep_merge(&mut table, layer);
+    }
+
+    Ok(LayeredConfig { table, includes })
+}
+
+/// Removes the `include` list from `table`, with each entry expanded and
+/// relative ones resolved against `dir`.
+fn take_includes(table: &mut Table, dir: &Path) -> Result<Vec<PathBuf>> {
+    let Some(value) = table.remove(INCLUDE_KEY) else {
+        return Ok(Vec::new());
+    };
+    let entries = match value {
+        Value::String(path) => vec![Value::String(path)],
+        Value::Array(entries) => entries,
+        other => anyhow::bail!(
+            "{} must be a list of file paths, got {}",
+            INCLUDE_KEY,
+            other
+        ),
+    };
+    entries
+        .into_iter()
+        .map(|entry| {
+            let Value::String(path) = entry else {
+                anyhow::bail!("{} entries must be file paths, got {}", INCLUDE_KEY, entry);
+            };
+            Ok(dir.join(expand_path(&path, INCLUDE_KEY)?))
+        })
+        .collect()
+}
+
+/// Merges `overrides` into `base`. Tables present in both are merged key by
+/// key; any other value in `overrides`, arrays included, replaces `base`'s.
+pub fn deep_merge(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(existing)), Value::Table(value)) => deep_merge(existing, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Expands a leading `~` and every `$VAR` or `${VAR}` in `value`, the
+/// setting `key`. An unset variable is an error naming the key.
+pub fn expand_path(value: &str, key: &str) -> Result<String> {
+    expand_with(
+        value,
+        key,
+        |name| std::env::var(name).ok(),
+        dirs::home_dir(),
+    )
+}
+
+fn expand_with(
+    value: &str,
+    key: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+    home: Option<PathBuf>,
+) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+
+    if rest == "~" || rest.starts_with("~/") {
+        let home = home
+            .with_context(|| format!("{} starts with ~, but the home directory is unknown", key))?;
+        expanded.push_str(&home.to_string_lossy());
+        rest = &rest[1..];
+    }
+
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let (name, remainder) = if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .with_context(|| format!("{} has an unclosed ${{ in {:?}", key, value))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        if name.is_empty() {
+            // A lone `$` is just a character.
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        let Some(variable) = lookup(name) else {
+            anyhow::bail!("{} uses ${}, which is not set", key, name);
+        };
+        expanded.push_str(&variable);
+        rest = remainder;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> Table {
+        toml::from_str(content).unwrap()
+    }
+
+    fn expand(value: &str) -> Result<String> {
+        let lookup = |name: &str| (name == "WORK_HOME").then(|| "/work".to_string());
+        expand_with(
+            value,
+            "limits_file.path",
+            lookup,
+            Some(PathBuf::from("/home/me")),
+        )
+    }
+
+    #[test]
+    fn test_deep_merge_overrides_keys_and_merges_tables() {
+        let mut base = table(
+            r#"
+            debug = false
+
+            [display]
+            show_as_remaining = false
+            tray_windows = ["session", "weekly"]
+
+            [theme]
+            mode = "dark"
+            "#,
+        );
+        deep_merge(
+            &mut base,
+            table(
+                r#"
+                debug = true
+
+                [display]
+                tray_windows = ["opus"]
+
+                [cost]
+                enabled = false
+                "#,
+            ),
+        );
+
+        assert_eq!(
+            base,
+            table(
+                r#"
+                debug = true
+
+                [display]
+                show_as_remaining = false
+                tray_windows = ["opus"]
+
+                [theme]
+                mode = "dark"
+
+                [cost]
+                enabled = false
+                "#,
+            )
+        );
+    }
+
+    #[test]
+    fn test_includes_are_merged_in_order() {
+        let dir =
+            std::env::temp_dir().join(format!("claude-bar-layers-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("shared.toml"),
+            "[display]\ntray_title = \"percent\"\nhide_costs = true\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("local.toml"), "[display]\nhide_costs = false\n").unwrap();
+        std::fs::write(dir.join("nested.toml"), "include = [\"shared.toml\"]\n").unwrap();
+
+        let main = "include = [\"shared.toml\", \"local.toml\", \"missing.toml\"]\n\n\
+                    [display]\ntray_title = \"name\"\nanimations = \"off\"\n";
+        let layers = read_layers(main, &dir.join("config.toml")).unwrap();
+        let nested = read_layers("include = \"nested.toml\"\n", &dir.join("config.toml"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            layers.table,
+            table(
+                "[display]\ntray_title = \"percent\"\nanimations = \"off\"\nhide_costs = false\n"
+            )
+        );
+        assert_eq!(
+            layers.includes,
+            vec![
+                dir.join("shared.toml"),
+                dir.join("local.toml"),
+                dir.join("missing.toml")
+            ]
+        );
+        let err = nested.unwrap_err().to_string();
+        assert!(err.contains("only the main config file"), "{}", err);
+    }
+
+    #[test]
+    fn test_include_must_list_paths() {
+        let path = Path::new("/nonexistent/config.toml");
+        let err = read_layers("include = 3\n", path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "include must be a list of file paths, got 3"
+        );
+        let err =
+            read_layers("include = [\"$NO_SUCH_VAR_FOR_CLAUDE_BAR/x.toml\"]\n", path).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "include uses $NO_SUCH_VAR_FOR_CLAUDE_BAR, which is not set"
+        );
+    }
+
+    #[test]
+    fn test_expand_path() {
+        assert_eq!(expand("~/limits.json").unwrap(), "/home/me/limits.json");
+        assert_eq!(expand("~").unwrap(), "/home/me");
+        assert_eq!(
+            expand("$WORK_HOME/.claude/limits.json").unwrap(),
+            "/work/.claude/limits.json"
+        );
+        assert_eq!(expand("${WORK_HOME}_old/x").unwrap(), "/work_old/x");
+        // Only a leading `~` is the home directory.
+        assert_eq!(expand("/tmp/~user/$").unwrap(), "/tmp/~user/$");
+        assert_eq!(expand("/tmp/a$-b").unwrap(), "/tmp/a$-b");
+    }
+
+    #[test]
+    fn test_expand_path_failures_name_the_key() {
+        assert_eq!(
+            expand("$UNSET_HOME/limits.json").unwrap_err().to_string(),
+            "limits_file.path uses $UNSET_HOME, which is not set"
+        );
+        assert_eq!(
+            expand("${WORK_HOME/limits.json").unwrap_err().to_string(),
+            "limits_file.path has an unclosed ${ in \"${WORK_HOME/limits.json\""
+        );
+        let no_home = expand_with("~/x", "notifications.sound", |_| None, None);
+        assert_eq!(
+            no_home.unwrap_err().to_string(),
+            "notifications.sound starts with ~, but the home directory is unknown"
+        );
+    }
+}
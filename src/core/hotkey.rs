@@ -0,0 +1,257 @@
+use global_hotkey::hotkey::{Code, HotKey, Modifiers};
+
+/// Why a `shortcuts.popup` value isn't a shortcut, naming the part that
+/// isn't understood.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum HotkeyParseError {
+    #[error("Shortcut is empty")]
+    Empty,
+    #[error("Shortcut has modifiers but no key")]
+    MissingKey,
+    #[error("Unknown key {0:?}")]
+    UnknownKey(String),
+    #[error("{second:?} follows the key {first:?}; a shortcut has only one")]
+    ExtraKey { first: String, second: String },
+}
+
+/// Parses shortcuts like "Ctrl+Shift+U": any modifiers, then one key.
+/// Case and spaces around the parts don't matter.
+pub fn parse_hotkey(input: &str) -> Result<HotKey, HotkeyParseError> {
+    let mut modifiers = Modifiers::empty();
+    let mut key: Option<(&str, Code)> = None;
+
+    for raw in input.split('+') {
+        let token = raw.trim();
+        if token.is_empty() {
+            continue;
+        }
+        let part = token.to_lowercase();
+        match part.as_str() {
+            "ctrl" | "control" => modifiers |= Modifiers::CONTROL,
+            "shift" => modifiers |= Modifiers::SHIFT,
+            "alt" | "option" => modifiers |= Modifiers::ALT,
+            "super" | "cmd" | "meta" => modifiers |= Modifiers::SUPER,
+            _ => {
+                if let Some((first, _)) = key {
+                    return Err(HotkeyParseError::ExtraKey {
+                        first: first.to_string(),
+                        second: token.to_string(),
+                    });
+                }
+                let code = key_code_for(&part)
+                    .ok_or_else(|| HotkeyParseError::UnknownKey(token.to_string()))?;
+                key = Some((token, code));
+            }
+        }
+    }
+
+    match key {
+        Some((_, code)) => Ok(HotKey::new(Some(modifiers), code)),
+        None if modifiers.is_empty() => Err(HotkeyParseError::Empty),
+        None => Err(HotkeyParseError::MissingKey),
+    }
+}
+
+/// The key for a lowercase key name: a letter, digit or punctuation mark,
+/// F1 to F12, or a name such as "space" or "comma".
+fn key_code_for(input: &str) -> Option<Code> {
+    let mut chars = input.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return char_key(ch);
+    }
+    if let Some(number) = input.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+        return function_key(number);
+    }
+
+    match input {
+        "space" => Some(Code::Space),
+        "tab" => Some(Code::Tab),
+        "enter" | "return" => Some(Code::Enter),
+        "escape" | "esc" => Some(Code::Escape),
+        "backspace" => Some(Code::Backspace),
+        "delete" | "del" => Some(Code::Delete),
+        "insert" | "ins" => Some(Code::Insert),
+        "home" => Some(Code::Home),
+        "end" => Some(Code::End),
+        "pageup" | "pgup" => Some(Code::PageUp),
+        "pagedown" | "pgdn" => Some(Code::PageDown),
+        "up" => Some(Code::ArrowUp),
+        "down" => Some(Code::ArrowDown),
+        "left" => Some(Code::ArrowLeft),
+        "right" => Some(Code::ArrowRight),
+        "comma" => Some(Code::Comma),
+        "period" | "dot" => Some(Code::Period),
+        "slash" => Some(Code::Slash),
+        "backslash" => Some(Code::Backslash),
+        "semicolon" => Some(Code::Semicolon),
+        "quote" | "apostrophe" => Some(Code::Quote),
+        "bracketleft" => Some(Code::BracketLeft),
+        "bracketright" => Some(Code::BracketRight),
+        "minus" => Some(Code::Minus),
+        "equal" | "equals" => Some(Code::Equal),
+        "backquote" | "grave" => Some(Code::Backquote),
+        _ => None,
+    }
+}
+
+fn char_key(ch: char) -> Option<Code> {
+    match ch.to_ascii_uppercase() {
+        'A' => Some(Code::KeyA),
+        'B' => Some(Code::KeyB),
+        'C' => Some(Code::KeyC),
+        'D' => Some(Code::KeyD),
+        'E' => Some(Code::KeyE),
+        'F' => Some(Code::KeyF),
+        'G' => Some(Code::KeyG),
+        'H' => Some(Code::KeyH),
+        'I' => Some(Code::KeyI),
+        'J' => Some(Code::KeyJ),
+        'K' => Some(Code::KeyK),
+        'L' => Some(Code::KeyL),
+        'M' => Some(Code::KeyM),
+        'N' => Some(Code::KeyN),
+        'O' => Some(Code::KeyO),
+        'P' => Some(Code::KeyP),
+        'Q' => Some(Code::KeyQ),
+        'R' => Some(Code::KeyR),
+        'S' => Some(Code::KeyS),
+        'T' => Some(Code::KeyT),
+        'U' => Some(Code::KeyU),
+        'V' => Some(Code::KeyV),
+        'W' => Some(Code::KeyW),
+        'X' => Some(Code::KeyX),
+        'Y' => Some(Code::KeyY),
+        'Z' => Some(Code::KeyZ),
+        '0' => Some(Code::Digit0),
+        '1' => Some(Code::Digit1),
+        '2' => Some(Code::Digit2),
+        '3' => Some(Code::Digit3),
+        '4' => Some(Code::Digit4),
+        '5' => Some(Code::Digit5),
+        '6' => Some(Code::Digit6),
+        '7' => Some(Code::Digit7),
+        '8' => Some(Code::Digit8),
+        '9' => Some(Code::Digit9),
+        ',' => Some(Code::Comma),
+        '.' => Some(Code::Period),
+        '/' => Some(Code::Slash),
+        '\\' => Some(Code::Backslash),
+        ';' => Some(Code::Semicolon),
+        '\'' => Some(Code::Quote),
+        '[' => Some(Code::BracketLeft),
+        ']' => Some(Code::BracketRight),
+        '-' => Some(Code::Minus),
+        '=' => Some(Code::Equal),
+        '`' => Some(Code::Backquote),
+        _ => None,
+    }
+}
+
+fn function_key(number: u8) -> Option<Code> {
+    match number {
+        1 => Some(Code::F1),
+        2 => Some(Code::F2),
+        3 => Some(Code::F3),
+        4 => Some(Code::F4),
+        5 => Some(Code::F5),
+        6 => Some(Code::F6),
+        7 => Some(Code::F7),
+        8 => Some(Code::F8),
+        9 => Some(Code::F9),
+        10 => Some(Code::F10),
+        11 => Some(Code::F11),
+        12 => Some(Code::F12),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hotkey() {
+        let hotkey = parse_hotkey("Ctrl+Shift+U").unwrap();
+        assert_eq!(hotkey.mods, Modifiers::CONTROL | Modifiers::SHIFT);
+        assert_eq!(hotkey.key, Code::KeyU);
+        assert_eq!(parse_hotkey(" super + f5 ").unwrap().key, Code::F5);
+        assert_eq!(parse_hotkey("F12").unwrap().mods, Modifiers::empty());
+        assert_eq!(parse_hotkey("Alt+Space").unwrap().key, Code::Space);
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_token() {
+        assert_eq!(
+            parse_hotkey("Ctrl+ß"),
+            Err(HotkeyParseError::UnknownKey("ß".to_string()))
+        );
+        assert_eq!(
+            parse_hotkey("Ctrl+Shfit+C"),
+            Err(HotkeyParseError::UnknownKey("Shfit".to_string()))
+        );
+        assert_eq!(
+            parse_hotkey("Ctrl+A+B"),
+            Err(HotkeyParseError::ExtraKey {
+                first: "A".to_string(),
+                second: "B".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_hotkey("Ctrl+Shift"),
+            Err(HotkeyParseError::MissingKey)
+        );
+        assert_eq!(parse_hotkey(" "), Err(HotkeyParseError::Empty));
+        assert_eq!(
+            parse_hotkey("Ctrl+F13"),
+            Err(HotkeyParseError::UnknownKey("F13".to_string()))
+        );
+        assert_eq!(
+            HotkeyParseError::UnknownKey("ß".to_string()).to_string(),
+            "Unknown key \"ß\""
+        );
+    }
+
+    #[test]
+    fn test_key_codes_cover_punctuation_and_function_keys() {
+        let function_keys = [
+            Code::F1,
+            Code::F2,
+            Code::F3,
+            Code::F4,
+            Code::F5,
+            Code::F6,
+            Code::F7,
+            Code::F8,
+            Code::F9,
+            Code::F10,
+            Code::F11,
+            Code::F12,
+        ];
+        for (n, code) in (1..=12).zip(function_keys) {
+            assert_eq!(key_code_for(&format!("f{}", n)), Some(code));
+        }
+        assert_eq!(key_code_for("f"), Some(Code::KeyF));
+        assert_eq!(key_code_for("f0"), None);
+
+        let punctuation = [
+            (",", "comma", Code::Comma),
+            (".", "period", Code::Period),
+            ("/", "slash", Code::Slash),
+            ("\\", "backslash", Code::Backslash),
+            (";", "semicolon", Code::Semicolon),
+            ("'", "quote", Code::Quote),
+            ("[", "bracketleft", Code::BracketLeft),
+            ("]", "bracketright", Code::BracketRight),
+            ("-", "minus", Code::Minus),
+            ("=", "equal", Code::Equal),
+            ("`", "grave", Code::Backquote),
+        ];
+        for (mark, name, code) in punctuation {
+            assert_eq!(key_code_for(mark), Some(code), "{}", mark);
+            assert_eq!(key_code_for(name), Some(code), "{}", name);
+        }
+        assert_eq!(key_code_for("7"), Some(Code::Digit7));
+        assert_eq!(key_code_for("esc"), Some(Code::Escape));
+        assert_eq!(key_code_for("ß"), None);
+    }
+}
@@ -0,0 +1,198 @@
+use crate::core::models::Provider;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Status pages are only consulted while a provider is failing, and at most
+/// this often per page.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// An incident the provider's status page reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub description: String,
+    pub url: String,
+}
+
+impl Incident {
+    /// Shown in place of the usual error hint while the incident lasts.
+    pub fn hint(&self, provider: Provider) -> String {
+        format!(
+            "{} is reporting an incident: {}",
+            vendor(provider),
+            self.description
+        )
+    }
+}
+
+/// The statuspage.io `api/v2/status.json` summary.
+#[derive(Debug, Deserialize)]
+struct StatusSummary {
+    page: Option<StatusPage>,
+    status: PageStatus,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusPage {
+    url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageStatus {
+    indicator: String,
+    description: String,
+}
+
+/// Parses a status summary. `None` means the page reports everything as
+/// operational.
+pub fn parse_status_summary(json: &str, fallback_url: &str) -> Result<Option<Incident>> {
+    let summary: StatusSummary = serde_json::from_str(json)?;
+    if summary.status.indicator == "none" {
+        return Ok(None);
+    }
+
+    let url = summary
+        .page
+        .and_then(|page| page.url)
+        .unwrap_or_else(|| fallback_url.to_string());
+    Ok(Some(Incident {
+        description: summary.status.description,
+        url,
+    }))
+}
+
+fn vendor(provider: Provider) -> &'static str {
+    match provider {
+        Provider::Claude => "Anthropic",
+        Provider::Codex | Provider::CodexWorkspace(_) => "OpenAI",
+    }
+}
+
+fn summary_url(provider: Provider) -> String {
+    format!("{}api/v2/status.json", provider.status_url())
+}
+
+pub struct IncidentTracker {
+    http_client: reqwest::Client,
+    /// Last result per status page; Codex workspaces share OpenAI's.
+    checked: HashMap<&'static str, (Instant, Option<Incident>)>,
+}
+
+impl IncidentTracker {
+    pub fn new() -> Self {
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "Failed to build status page HTTP client; falling back to default");
+                reqwest::Client::new()
+            });
+
+        Self {
+            http_client,
+            checked: HashMap::new(),
+        }
+    }
+
+    /// The incident on `provider`'s status page, if any. Results, including
+    /// failed checks, are reused for `CHECK_INTERVAL`.
+    pub async fn check(&mut self, provider: Provider) -> Option<Incident> {
+        let page = provider.status_url();
+        if let Some((checked_at, incident)) = self.checked.get(page) {
+            if checked_at.elapsed() < CHECK_INTERVAL {
+                return incident.clone();
+            }
+        }
+
+        let incident = match self.fetch(provider).await {
+            Ok(incident) => incident,
+            Err(e) => {
+                tracing::debug!(?provider, error = %e, "Status page check failed");
+                None
+            }
+        };
+        tracing::debug!(?provider, ?incident, "Checked provider status page");
+        self.checked
+            .insert(page, (Instant::now(), incident.clone()));
+        incident
+    }
+
+    async fn fetch(&self, provider: Provider) -> Result<Option<Incident>> {
+        let body = self
+            .http_client
+            .get(summary_url(provider))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        parse_status_summary(&body, provider.status_url())
+    }
+}
+
+impl Default for IncidentTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLAUDE_OPERATIONAL: &str =
+        include_str!("../../tests/fixtures/statuspage/claude_operational.json");
+    const CLAUDE_MAJOR: &str = include_str!("../../tests/fixtures/statuspage/claude_major.json");
+    const OPENAI_MINOR: &str = include_str!("../../tests/fixtures/statuspage/openai_minor.json");
+
+    #[test]
+    fn test_operational_page_has_no_incident() {
+        let incident = parse_status_summary(CLAUDE_OPERATIONAL, "https://status.claude.com/");
+        assert_eq!(incident.unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_incidents() {
+        let incident = parse_status_summary(CLAUDE_MAJOR, "https://status.claude.com/")
+            .unwrap()
+            .unwrap();
+        assert_eq!(incident.description, "Partial System Outage");
+        assert_eq!(incident.url, "https://status.claude.com");
+        assert_eq!(
+            incident.hint(Provider::Claude),
+            "Anthropic is reporting an incident: Partial System Outage"
+        );
+
+        let incident = parse_status_summary(OPENAI_MINOR, "https://status.openai.com/")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            incident.hint(Provider::CodexWorkspace(1)),
+            "OpenAI is reporting an incident: Elevated error rates for Codex"
+        );
+    }
+
+    #[test]
+    fn test_parse_falls_back_to_known_page_url() {
+        let json = r#"{"status":{"indicator":"critical","description":"Major Outage"}}"#;
+        let incident = parse_status_summary(json, "https://status.claude.com/")
+            .unwrap()
+            .unwrap();
+        assert_eq!(incident.url, "https://status.claude.com/");
+
+        assert!(parse_status_summary("<html>Too many requests</html>", "").is_err());
+    }
+
+    #[test]
+    fn test_summary_url() {
+        assert_eq!(
+            summary_url(Provider::Claude),
+            "https://status.claude.com/api/v2/status.json"
+        );
+        assert_eq!(
+            summary_url(Provider::CodexWorkspace(2)),
+            "https://status.openai.com/api/v2/status.json"
+        );
+    }
+}
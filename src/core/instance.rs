@@ -0,0 +1,90 @@
+use anyhow::Result;
+use std::sync::OnceLock;
+
+/// Read by the CLI when `--instance` isn't given.
+pub const INSTANCE_ENV: &str = "CLAUDE_BAR_INSTANCE";
+
+static INSTANCE: OnceLock<Option<String>> = OnceLock::new();
+
+/// Chooses the daemon instance this process runs as or talks to. Without a
+/// suffix every name stays exactly as it was before instances existed.
+pub fn select(suffix: Option<String>) -> Result<()> {
+    let suffix = suffix.filter(|s| !s.is_empty());
+    if let Some(suffix) = &suffix {
+        validate_suffix(suffix)?;
+    }
+    if INSTANCE.set(suffix).is_err() {
+        anyhow::bail!("Daemon instance was already selected");
+    }
+    Ok(())
+}
+
+pub fn suffix() -> Option<&'static str> {
+    INSTANCE.get().and_then(Option::as_deref)
+}
+
+/// `base` with the instance suffix appended after `separator`, e.g.
+/// `com.github.kabilan.ClaudeBar.work`.
+pub fn scoped(base: &str, separator: char) -> String {
+    scoped_with(base, separator, suffix())
+}
+
+/// `scoped` for an instance other than this process's own.
+pub(crate) fn scoped_with(base: &str, separator: char, suffix: Option<&str>) -> String {
+    match suffix {
+        Some(suffix) => format!("{}{}{}", base, separator, suffix),
+        None => base.to_string(),
+    }
+}
+
+/// The suffix becomes a D-Bus name element, so it follows the same rules.
+fn validate_suffix(suffix: &str) -> Result<()> {
+    let valid_chars = suffix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid_chars || suffix.starts_with(|c: char| c.is_ascii_digit()) {
+        anyhow::bail!(
+            "Invalid instance suffix {:?}: use letters, digits, '_' or '-', not starting with a digit",
+            suffix
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DBUS_NAME: &str = "com.github.kabilan.ClaudeBar";
+
+    #[test]
+    fn test_scoped_names() {
+        assert_eq!(scoped_with(DBUS_NAME, '.', None), DBUS_NAME);
+        assert_eq!(
+            scoped_with(DBUS_NAME, '.', Some("work")),
+            "com.github.kabilan.ClaudeBar.work"
+        );
+        assert_eq!(
+            scoped_with("claude-bar", '-', Some("work")),
+            "claude-bar-work"
+        );
+    }
+
+    #[test]
+    fn test_validate_suffix() {
+        for suffix in ["work", "personal_2", "alice-laptop"] {
+            assert!(
+                validate_suffix(suffix).is_ok(),
+                "{:?} should be valid",
+                suffix
+            );
+        }
+        for suffix in ["2nd", "work.alt", "a b", "ü"] {
+            assert!(
+                validate_suffix(suffix).is_err(),
+                "{:?} should be rejected",
+                suffix
+            );
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::core::instance;
+use crate::core::models::{RateWindow, UsageSnapshot};
+use crate::core::settings::{write_atomically, LimitsFileSettings};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// What the daemon writes for hooks to read. Timestamps are Unix seconds and
+/// percentages fractions in [0, 1].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct LimitsFile {
+    pub schema_version: u32,
+    /// Same as `schema_version`; hooks written before it read this.
+    pub version: u32,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schemars(with = "i64")]
+    pub updated_at: DateTime<Utc>,
+    /// Keyed by provider id: `claude`, `codex` or `codex-<n>`.
+    pub providers: BTreeMap<String, ProviderLimits>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProviderLimits {
+    pub name: String,
+    /// The higher of the session and weekly usage; what `limit-check`
+    /// compares against `--max`.
+    pub max_used_percent: f64,
+    pub session: Option<WindowLimit>,
+    pub weekly: Option<WindowLimit>,
+    /// When the provider last returned this data.
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schemars(with = "i64")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WindowLimit {
+    pub used_percent: f64,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[schemars(with = "Option<i64>")]
+    pub resets_at: Option<DateTime<Utc>>,
+}
+
+impl ProviderLimits {
+    pub fn from_snapshot(name: String, snapshot: &UsageSnapshot) -> Self {
+        let window = |window: &RateWindow| WindowLimit {
+            used_percent: window.used_percent,
+            resets_at: window.resets_at,
+        };
+        let session = snapshot.primary.as_ref().map(window);
+        let weekly = snapshot.secondary.as_ref().map(window);
+        let max_used_percent = session
+            .iter()
+            .chain(weekly.iter())
+            .map(|window| window.used_percent)
+            .fold(0.0, f64::max);
+        Self {
+            name,
+            max_used_percent,
+            session,
+            weekly,
+            updated_at: snapshot.updated_at,
+        }
+    }
+
+    /// The window that is over `max`, preferring the session window.
+    pub fn exceeded_window(&self, max: f64) -> Option<(&'static str, &WindowLimit)> {
+        [("session", &self.session), ("weekly", &self.weekly)]
+            .into_iter()
+            .find_map(|(label, window)| {
+                window
+                    .as_ref()
+                    .filter(|window| window.used_percent > max)
+                    .map(|window| (label, window))
+            })
+    }
+}
+
+/// `limits_file.path`, which `Settings::load` has already expanded, or
+/// `~/.local/share/claude-bar/limits.json` (instance-scoped) when unset.
+pub fn limits_file_path(settings: &LimitsFileSettings) -> Option<PathBuf> {
+    match settings.path.as_deref() {
+        Some(path) => Some(PathBuf::from(path)),
+        None => {
+            let name = format!("{}.json", instance::scoped("limits", '-'));
+            dirs::data_local_dir().map(|d| d.join("claude-bar").join(name))
+        }
+    }
+}
+
+/// Replaces the file at `path` in one rename, so a hook reading it never
+/// sees a partial write.
+pub fn write_limits_file(path: &Path, limits: &LimitsFile) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut content = serde_json::to_string_pretty(limits)?;
+    content.push('\n');
+    write_atomically(path, &content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::{assert_schema_snapshot, SCHEMA_VERSION};
+
+    fn window(used_percent: f64, resets_at: Option<DateTime<Utc>>) -> RateWindow {
+        RateWindow::from_reported(used_percent, Some(300), resets_at, None)
+    }
+
+    fn snapshot(session: f64, weekly: f64, resets_at: DateTime<Utc>) -> UsageSnapshot {
+        UsageSnapshot::new(
+            Some(window(session, Some(resets_at))),
+            Some(window(weekly, None)),
+            resets_at,
+        )
+    }
+
+    #[test]
+    fn test_provider_limits_and_threshold() {
+        let resets_at = DateTime::from_timestamp(1_772_630_000, 0).unwrap();
+        let limits = ProviderLimits::from_snapshot(
+            "Claude Code".to_string(),
+            &snapshot(0.93, 0.4, resets_at),
+        );
+        assert_eq!(limits.max_used_percent, 0.93);
+
+        let (label, window) = limits.exceeded_window(0.9).unwrap();
+        assert_eq!(label, "session");
+        assert_eq!(window.resets_at, Some(resets_at));
+        assert!(limits.exceeded_window(0.95).is_none());
+
+        let weekly =
+            ProviderLimits::from_snapshot("Codex".to_string(), &snapshot(0.1, 0.97, resets_at));
+        assert_eq!(
+            weekly.exceeded_window(0.9).map(|(label, _)| label),
+            Some("weekly")
+        );
+    }
+
+    #[test]
+    fn test_write_replaces_file_with_documented_format() {
+        let dir = std::env::temp_dir().join(format!("claude-bar-limits-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("nested").join("limits.json");
+        let resets_at = DateTime::from_timestamp(1_772_630_000, 0).unwrap();
+        let limits = LimitsFile {
+            schema_version: SCHEMA_VERSION,
+            version: SCHEMA_VERSION,
+            updated_at: resets_at,
+            providers: BTreeMap::from([(
+                "claude".to_string(),
+                ProviderLimits::from_snapshot(
+                    "Claude Code".to_string(),
+                    &snapshot(0.5, 0.25, resets_at),
+                ),
+            )]),
+        };
+
+        write_limits_file(&path, &limits).unwrap();
+        write_limits_file(&path, &limits).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], 2);
+        assert_eq!(json["version"], 2);
+        assert_eq!(json["providers"]["claude"]["max_used_percent"], 0.5);
+        assert_eq!(
+            json["providers"]["claude"]["session"]["resets_at"],
+            1_772_630_000
+        );
+        assert!(json["providers"]["claude"]["weekly"]["resets_at"].is_null());
+        // Only the file itself; no temporary file is left behind.
+        assert_eq!(
+            std::fs::read_dir(path.parent().unwrap()).unwrap().count(),
+            1
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_configured_path_is_used() {
+        let settings = LimitsFileSettings {
+            enabled: true,
+            path: Some("/run/user/1000/limits.json".to_string()),
+        };
+        assert_eq!(
+            limits_file_path(&settings),
+            Some(PathBuf::from("/run/user/1000/limits.json"))
+        );
+    }
+
+    #[test]
+    fn test_schema_snapshot() {
+        assert_schema_snapshot::<LimitsFile>(
+            "limits",
+            include_str!("../../tests/fixtures/schema/limits.json"),
+        );
+    }
+}
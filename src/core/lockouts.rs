@@ -0,0 +1,319 @@
+use crate::core::models::{Provider, RateWindow, UsageSnapshot};
+use crate::core::reset_estimate::{same_window_reset, WindowSlot};
+use crate::core::state_file;
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Usage at or above this, 99.5%, locks you out until the window resets.
+pub const LOCKOUT_THRESHOLD: f64 = 0.995;
+/// Lockouts older than this are dropped when a new one is recorded.
+const RETENTION_DAYS: i64 = 400;
+
+/// A window that ran out: usage reached 99.5% at `at`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Lockout {
+    pub at: DateTime<Utc>,
+    pub window: WindowSlot,
+    /// When the window was due to reset, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resets_at: Option<DateTime<Utc>>,
+}
+
+impl Lockout {
+    /// Whether `window`, still full as of `now`, is the same window period
+    /// this lockout was recorded for.
+    fn covers(&self, window: &RateWindow, now: DateTime<Utc>) -> bool {
+        if let (Some(recorded), Some(current)) = (self.resets_at, window.resets_at) {
+            if same_window_reset(recorded, current) {
+                return true;
+            }
+        }
+        now < self.period_end(window)
+    }
+
+    /// When the locked-out window was due to reset, falling back to its
+    /// length, or the usual one for the slot, after the lockout.
+    fn period_end(&self, window: &RateWindow) -> DateTime<Utc> {
+        self.resets_at.unwrap_or_else(|| {
+            let minutes = window
+                .window_minutes
+                .map(i64::from)
+                .unwrap_or(match self.window {
+                    WindowSlot::Primary => 5 * 60,
+                    WindowSlot::Secondary | WindowSlot::Tertiary => 7 * 24 * 60,
+                });
+            self.at + Duration::minutes(minutes)
+        })
+    }
+}
+
+/// Every lockout seen, oldest first, keyed by provider id. Persisted so the
+/// monthly count survives daemon restarts.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LockoutLog {
+    lockouts: BTreeMap<String, Vec<Lockout>>,
+}
+
+impl LockoutLog {
+    pub fn load() -> Self {
+        state_file::load("lockouts")
+    }
+
+    pub fn save(&self) -> Result<()> {
+        state_file::save("lockouts", self)
+    }
+
+    pub fn for_provider(&self, provider: Provider) -> &[Lockout] {
+        self.lockouts
+            .get(&provider.id())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Each provider with recorded lockouts.
+    pub fn providers(&self) -> impl Iterator<Item = (Provider, &[Lockout])> {
+        self.lockouts.iter().filter_map(|(id, lockouts)| {
+            Provider::from_id(id).map(|provider| (provider, lockouts.as_slice()))
+        })
+    }
+
+    /// Records the windows of `snapshot` that are full as of `now` and
+    /// weren't already recorded for the same window period, and returns them.
+    pub fn record(
+        &mut self,
+        provider: Provider,
+        snapshot: &UsageSnapshot,
+        now: DateTime<Utc>,
+    ) -> Vec<Lockout> {
+        let windows = [
+            (WindowSlot::Primary, snapshot.primary.as_ref()),
+            (WindowSlot::Secondary, snapshot.secondary.as_ref()),
+            (WindowSlot::Tertiary, snapshot.tertiary.as_ref()),
+        ];
+        let log = self.lockouts.entry(provider.id()).or_default();
+        let mut recorded = Vec::new();
+        for (slot, window) in windows {
+            let Some(window) = window.filter(|window| window.used_percent >= LOCKOUT_THRESHOLD)
+            else {
+                continue;
+            };
+            let last = log.iter().rev().find(|lockout| lockout.window == slot);
+            if last.is_some_and(|last| last.covers(window, now)) {
+                continue;
+            }
+            let lockout = Lockout {
+                at: now,
+                window: slot,
+                resets_at: window.resets_at,
+            };
+            log.push(lockout);
+            recorded.push(lockout);
+        }
+        if !recorded.is_empty() {
+            log.retain(|lockout| now - lockout.at < Duration::days(RETENTION_DAYS));
+        }
+        recorded
+    }
+}
+
+/// Lockouts in the calendar month of `now`, in `tz`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonthSummary {
+    pub count: usize,
+    pub last: Option<Lockout>,
+}
+
+pub fn month_summary<Tz: TimeZone>(
+    lockouts: &[Lockout],
+    now: DateTime<Utc>,
+    tz: &Tz,
+) -> MonthSummary {
+    let month = |at: DateTime<Utc>| {
+        let local = at.with_timezone(tz);
+        (local.year(), local.month())
+    };
+    let this_month: Vec<&Lockout> = lockouts
+        .iter()
+        .filter(|lockout| month(lockout.at) == month(now))
+        .collect();
+    MonthSummary {
+        count: this_month.len(),
+        last: this_month
+            .into_iter()
+            .max_by_key(|lockout| lockout.at)
+            .copied(),
+    }
+}
+
+/// Lockouts per week for the `weeks` weeks up to and including the one
+/// containing `now`, oldest first, keyed by the Monday each starts on in `tz`.
+pub fn weekly_counts<Tz: TimeZone>(
+    lockouts: &[Lockout],
+    now: DateTime<Utc>,
+    weeks: u32,
+    tz: &Tz,
+) -> Vec<(NaiveDate, usize)> {
+    let week_start = |at: DateTime<Utc>| {
+        let day = at.with_timezone(tz).date_naive();
+        day - Duration::days(i64::from(day.weekday().num_days_from_monday()))
+    };
+    let current = week_start(now);
+    (0..weeks)
+        .rev()
+        .map(|back| {
+            let start = current - Duration::weeks(i64::from(back));
+            let count = lockouts
+                .iter()
+                .filter(|lockout| week_start(lockout.at) == start)
+                .count();
+            (start, count)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(session: f64, weekly: f64, now: DateTime<Utc>) -> UsageSnapshot {
+        UsageSnapshot::new(
+            Some(RateWindow::from_reported(
+                session,
+                Some(300),
+                Some(now + Duration::hours(2)),
+                None,
+            )),
+            Some(RateWindow::from_reported(
+                weekly,
+                Some(10080),
+                Some(now + Duration::days(3)),
+                None,
+            )),
+            now,
+        )
+    }
+
+    fn lockout(at: &str, window: WindowSlot) -> Lockout {
+        Lockout {
+            at: at.parse().unwrap(),
+            window,
+            resets_at: None,
+        }
+    }
+
+    #[test]
+    fn test_full_window_is_recorded_once_per_period() {
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let mut log = LockoutLog::default();
+
+        assert!(log
+            .record(Provider::Claude, &snapshot(0.99, 0.40, now), now)
+            .is_empty());
+        let full = snapshot(1.0, 0.40, now);
+        assert_eq!(
+            log.record(Provider::Claude, &full, now),
+            [Lockout {
+                at: now,
+                window: WindowSlot::Primary,
+                resets_at: Some(now + Duration::hours(2)),
+            }]
+        );
+
+        // Hovering at 100% over the next polls, with the reset time jittering.
+        for minutes in [1, 2, 30] {
+            let later = now + Duration::minutes(minutes);
+            let mut snapshot = snapshot(0.996, 0.40, later);
+            snapshot.primary.as_mut().unwrap().resets_at =
+                Some(now + Duration::hours(2) + Duration::seconds(minutes));
+            assert!(log.record(Provider::Claude, &snapshot, later).is_empty());
+        }
+        // Dipping below and coming back within the same window.
+        let later = now + Duration::minutes(40);
+        log.record(Provider::Claude, &snapshot(0.98, 0.40, later), later);
+        let mut back = full.clone();
+        back.updated_at = later;
+        assert!(log.record(Provider::Claude, &back, later).is_empty());
+
+        // The next window fills up too.
+        let next = now + Duration::hours(3);
+        assert_eq!(
+            log.record(Provider::Claude, &snapshot(1.0, 1.0, next), next)
+                .len(),
+            2
+        );
+        assert_eq!(log.for_provider(Provider::Claude).len(), 3);
+        assert!(log.for_provider(Provider::Codex).is_empty());
+        assert_eq!(
+            log.providers()
+                .map(|(provider, lockouts)| (provider, lockouts.len()))
+                .collect::<Vec<_>>(),
+            [(Provider::Claude, 3)]
+        );
+    }
+
+    #[test]
+    fn test_window_without_reset_time_uses_its_length() {
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let mut log = LockoutLog::default();
+        let full = |at: DateTime<Utc>| {
+            let mut snapshot = snapshot(1.0, 0.40, at);
+            snapshot.primary.as_mut().unwrap().resets_at = None;
+            snapshot
+        };
+
+        assert_eq!(log.record(Provider::Codex, &full(now), now).len(), 1);
+        let later = now + Duration::hours(4);
+        assert!(log.record(Provider::Codex, &full(later), later).is_empty());
+        let next = now + Duration::hours(5);
+        assert_eq!(log.record(Provider::Codex, &full(next), next).len(), 1);
+    }
+
+    #[test]
+    fn test_month_summary() {
+        let lockouts = [
+            lockout("2026-09-30T23:00:00Z", WindowSlot::Primary),
+            lockout("2026-10-06T12:05:00Z", WindowSlot::Secondary),
+            lockout("2026-10-13T14:05:00Z", WindowSlot::Secondary),
+            lockout("2026-10-02T09:00:00Z", WindowSlot::Primary),
+        ];
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+
+        let summary = month_summary(&lockouts, now, &Utc);
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.last, Some(lockouts[2]));
+
+        // September 30th 23:00 UTC is already October at UTC+2.
+        let east = chrono::FixedOffset::east_opt(2 * 3600).unwrap();
+        assert_eq!(month_summary(&lockouts, now, &east).count, 4);
+        assert_eq!(month_summary(&[], now, &Utc).last, None);
+    }
+
+    #[test]
+    fn test_weekly_counts() {
+        let lockouts = [
+            lockout("2026-10-12T08:00:00Z", WindowSlot::Primary),
+            lockout("2026-10-15T20:00:00Z", WindowSlot::Secondary),
+            lockout("2026-10-04T23:00:00Z", WindowSlot::Primary),
+            lockout("2026-06-01T12:00:00Z", WindowSlot::Primary),
+        ];
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let day = |day: &str| NaiveDate::parse_from_str(day, "%Y-%m-%d").unwrap();
+
+        assert_eq!(
+            weekly_counts(&lockouts, now, 3, &Utc),
+            [
+                (day("2026-09-28"), 1),
+                (day("2026-10-05"), 0),
+                (day("2026-10-12"), 2)
+            ]
+        );
+        let weeks = weekly_counts(&lockouts, now, 13, &Utc);
+        assert_eq!(weeks.len(), 13);
+        assert_eq!(weeks[0].0, day("2026-07-20"));
+        assert_eq!(weeks.iter().map(|(_, count)| count).sum::<usize>(), 3);
+    }
+}
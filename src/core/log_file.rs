@@ -0,0 +1,219 @@
+use crate::core::instance;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::thread::JoinHandle;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// The daemon log rotates once it would grow past this size.
+pub const MAX_LOG_BYTES: u64 = 10 * 1024 * 1024;
+/// Rotated files kept next to the live log, `claude-bar.log.1` being newest.
+pub const KEPT_LOG_FILES: usize = 3;
+/// Log lines buffered for the writer thread before new ones are dropped.
+const QUEUE_LINES: usize = 4096;
+
+/// `~/.local/share/claude-bar/claude-bar.log`, with the instance suffix in
+/// the file name when one is set.
+pub fn log_file_path() -> Option<PathBuf> {
+    let name = format!("{}.log", instance::scoped("claude-bar", '-'));
+    dirs::data_local_dir().map(|d| d.join("claude-bar").join(name))
+}
+
+/// The `n`th rotated file of `path`, e.g. `claude-bar.log.2`.
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+/// A log file that is renamed to `.1` (shifting older files up and dropping
+/// the oldest) before a write would take it past `max_bytes`.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            keep,
+            file,
+            written,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                let from = rotated_path(&self.path, n);
+                if from.exists() {
+                    fs::rename(&from, rotated_path(&self.path, n + 1))?;
+                }
+            }
+            fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+enum Message {
+    Line(Vec<u8>),
+    Shutdown,
+}
+
+/// Hands each formatted event to a writer thread, so logging never waits on
+/// the disk. Events are dropped while the queue is full.
+#[derive(Clone)]
+pub struct NonBlocking {
+    sender: SyncSender<Message>,
+}
+
+impl Write for NonBlocking {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.sender.try_send(Message::Line(buf.to_vec())) {
+            Ok(()) | Err(TrySendError::Full(_)) => Ok(buf.len()),
+            Err(TrySendError::Disconnected(_)) => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a> MakeWriter<'a> for NonBlocking {
+    type Writer = NonBlocking;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+/// Writes out queued events and stops the writer thread when dropped.
+pub struct LogGuard {
+    sender: SyncSender<Message>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl Drop for LogGuard {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Message::Shutdown);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+pub fn non_blocking(mut writer: impl Write + Send + 'static) -> (NonBlocking, LogGuard) {
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_LINES);
+    let worker = std::thread::Builder::new()
+        .name("claude-bar-log".to_string())
+        .spawn(move || {
+            while let Ok(Message::Line(line)) = receiver.recv() {
+                // Nowhere left to report a failing log write.
+                let _ = writer.write_all(&line);
+            }
+            let _ = writer.flush();
+        })
+        .ok();
+    (
+        NonBlocking {
+            sender: sender.clone(),
+        },
+        LogGuard { sender, worker },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("claude-bar-log-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_rotation_keeps_newest_files() {
+        let dir = test_dir("rotate");
+        let path = dir.join("claude-bar.log");
+        let mut file = RotatingFile::open(&path, 10, 3).unwrap();
+
+        for line in ["one\n", "two\n", "tri\n", "for\n", "fiv\n", "six\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(path.clone()), "six\nsix\n");
+        assert_eq!(read(rotated_path(&path, 1)), "fiv\nfiv\n");
+        assert_eq!(read(rotated_path(&path, 3)), "tri\ntri\n");
+        assert!(!rotated_path(&path, 4).exists());
+
+        // Reopening picks up the existing size.
+        let mut file = RotatingFile::open(&path, 10, 3).unwrap();
+        file.write_all(b"seven\n").unwrap();
+        assert_eq!(read(path.clone()), "seven\n");
+        assert_eq!(read(rotated_path(&path, 1)), "six\nsix\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_non_blocking_writes_everything_before_guard_drops() {
+        let dir = test_dir("non-blocking");
+        let path = dir.join("claude-bar.log");
+        let (mut writer, guard) =
+            non_blocking(RotatingFile::open(&path, MAX_LOG_BYTES, 1).unwrap());
+
+        for n in 0..100 {
+            writer
+                .write_all(format!("line {}\n", n).as_bytes())
+                .unwrap();
+        }
+        drop(guard);
+
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(content.lines().count(), 100);
+        assert_eq!(content.lines().last(), Some("line 99"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
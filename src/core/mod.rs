@@ -1,6 +1,25 @@
+pub mod clock;
+pub mod config_layers;
 pub mod credentials;
+pub mod hotkey;
+pub mod incidents;
+pub mod instance;
+pub mod limits_file;
+pub mod lockouts;
+pub mod log_file;
 pub mod models;
 pub mod notifications;
+pub mod quota_adjust;
+pub mod quota_value;
+pub mod reset_estimate;
 pub mod retry;
+pub mod schema;
 pub mod settings;
+pub mod state_file;
+pub mod status_output;
 pub mod store;
+pub mod terminal;
+pub mod urgency;
+pub mod velocity;
+pub mod week_compare;
+pub mod weekly_summary;
@@ -1,55 +1,179 @@
-use chrono::{DateTime, NaiveDate, Utc};
+use crate::core::clock::{snapshot_age, to_local_clock};
+use crate::core::reset_estimate::WindowSlot;
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Provider {
     Claude,
     Codex,
+    /// An additional Codex workspace, indexed into `providers.codex.workspaces`.
+    CodexWorkspace(u8),
 }
 
 impl Provider {
     pub fn name(&self) -> &'static str {
         match self {
             Provider::Claude => "Claude Code",
-            Provider::Codex => "Codex",
+            Provider::Codex | Provider::CodexWorkspace(_) => "Codex",
         }
     }
 
+    pub fn id(&self) -> String {
+        match self {
+            Provider::Claude => "claude".to_string(),
+            Provider::Codex => "codex".to_string(),
+            Provider::CodexWorkspace(index) => format!("codex-{}", index),
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id.trim().to_lowercase().as_str() {
+            "claude" => Some(Provider::Claude),
+            "codex" => Some(Provider::Codex),
+            other => other
+                .strip_prefix("codex-")
+                .and_then(|index| index.parse().ok())
+                .filter(|index| *index > 0)
+                .map(Provider::CodexWorkspace),
+        }
+    }
+
+    pub fn is_codex(&self) -> bool {
+        matches!(self, Provider::Codex | Provider::CodexWorkspace(_))
+    }
+
     pub fn dashboard_url(&self) -> &'static str {
         match self {
             Provider::Claude => "https://console.anthropic.com/settings/billing",
-            Provider::Codex => "https://chatgpt.com/codex/settings/usage",
+            Provider::Codex | Provider::CodexWorkspace(_) => {
+                "https://chatgpt.com/codex/settings/usage"
+            }
         }
     }
 
     pub fn status_url(&self) -> &'static str {
         match self {
             Provider::Claude => "https://status.claude.com/",
-            Provider::Codex => "https://status.openai.com/",
+            Provider::Codex | Provider::CodexWorkspace(_) => "https://status.openai.com/",
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RateWindow {
+    /// Always within [0, 1].
     pub used_percent: f64,
     pub window_minutes: Option<i32>,
     pub resets_at: Option<DateTime<Utc>>,
     pub reset_description: Option<String>,
+    /// The percentage exactly as the API sent it (e.g. 45.5), so display and
+    /// scripts don't work from `used_percent` multiplied back up. Outside
+    /// 0-100 when `used_percent` had to be clamped (e.g. 250 during an
+    /// incident).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub used_percent_exact: Option<f64>,
+    /// The API left out the reset time and `resets_at` was estimated from
+    /// when the daemon saw the window roll over.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub resets_at_estimated: bool,
 }
 
 impl RateWindow {
+    /// A window for the usage fraction an API reported, clamped to [0, 1].
+    /// NaN counts as no usage.
+    pub fn from_reported(
+        reported: f64,
+        window_minutes: Option<i32>,
+        resets_at: Option<DateTime<Utc>>,
+        reset_description: Option<String>,
+    ) -> Self {
+        let used_percent = if reported.is_nan() {
+            0.0
+        } else {
+            reported.clamp(0.0, 1.0)
+        };
+        let clamped = reported.is_nan() || used_percent != reported;
+        Self {
+            used_percent,
+            window_minutes,
+            resets_at,
+            reset_description,
+            used_percent_exact: clamped.then_some(reported * 100.0),
+            resets_at_estimated: false,
+        }
+    }
+
+    /// A window for a usage percentage (0-100) as an API reported it.
+    pub fn from_reported_percent(
+        percent: f64,
+        window_minutes: Option<i32>,
+        resets_at: Option<DateTime<Utc>>,
+        reset_description: Option<String>,
+    ) -> Self {
+        Self {
+            used_percent_exact: Some(percent),
+            ..Self::from_reported(
+                percent / 100.0,
+                window_minutes,
+                resets_at,
+                reset_description,
+            )
+        }
+    }
+
+    /// The API reported usage outside [0, 1].
+    pub fn was_clamped(&self) -> bool {
+        self.used_percent_exact
+            .is_some_and(|exact| !(0.0..=100.0).contains(&exact))
+    }
+
     pub fn remaining_percent(&self) -> f64 {
         1.0 - self.used_percent
     }
 
-    #[allow(dead_code)]
+    /// `used_percent` as a percentage, taken from the API's exact value when
+    /// it is within range.
+    pub fn used_percent_display(&self) -> f64 {
+        display_used_percent(self.used_percent, self.used_percent_exact)
+    }
+
+    /// e.g. "45.5%", or "46%" when there is no tenth worth showing.
+    pub fn used_text(&self) -> String {
+        format_percent(self.used_percent_display())
+    }
+
+    pub fn remaining_text(&self) -> String {
+        format_percent(100.0 - self.used_percent_display())
+    }
+
     pub fn is_high_usage(&self, threshold: f64) -> bool {
         self.used_percent >= threshold
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A window's usage as a percentage: `exact` when the API sent one within
+/// range, otherwise the normalized fraction scaled up.
+pub fn display_used_percent(used_percent: f64, exact: Option<f64>) -> f64 {
+    exact
+        .filter(|exact| (0.0..=100.0).contains(exact))
+        .unwrap_or(used_percent * 100.0)
+}
+
+/// A percentage with one decimal only when it has one worth showing.
+fn format_percent(percent: f64) -> String {
+    let tenths = (percent * 10.0).round();
+    if tenths % 10.0 == 0.0 {
+        format!("{:.0}%", percent)
+    } else {
+        format!("{:.1}%", tenths / 10.0)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct ProviderIdentity {
     pub email: Option<String>,
     pub organization: Option<String>,
@@ -67,11 +191,52 @@ pub struct UsageSnapshot {
     pub provider_cost: Option<ProviderCostSnapshot>,
     #[serde(default)]
     pub carveouts: Vec<ModelWindow>,
+    /// Usage split by where it happened, e.g. "Code usage" and "Web usage",
+    /// for accounts whose endpoint reports it. Breaks down the other windows
+    /// rather than limiting anything itself.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub surfaces: Vec<ModelWindow>,
     pub updated_at: DateTime<Utc>,
+    /// When this process received the snapshot from the provider, on the
+    /// monotonic clock. Not serialized, so absent in snapshots read over
+    /// D-Bus.
+    #[serde(skip)]
+    pub received_at: Option<Instant>,
     pub identity: ProviderIdentity,
 }
 
 impl UsageSnapshot {
+    /// A snapshot of a session and a weekly window alone, without cost,
+    /// model windows or identity.
+    pub fn new(
+        primary: Option<RateWindow>,
+        secondary: Option<RateWindow>,
+        updated_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            primary,
+            secondary,
+            tertiary: None,
+            provider_cost: None,
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            updated_at,
+            received_at: None,
+            identity: ProviderIdentity::default(),
+        }
+    }
+
+    /// Same usage and identity as `other`, regardless of when each was fetched.
+    pub fn same_usage_as(&self, other: &UsageSnapshot) -> bool {
+        self.primary == other.primary
+            && self.secondary == other.secondary
+            && self.tertiary == other.tertiary
+            && self.provider_cost == other.provider_cost
+            && self.carveouts == other.carveouts
+            && self.surfaces == other.surfaces
+            && self.identity == other.identity
+    }
+
     #[allow(dead_code)]
     pub fn max_usage(&self) -> f64 {
         self.primary
@@ -82,15 +247,82 @@ impl UsageSnapshot {
             .map(|r| r.used_percent)
             .fold(0.0, f64::max)
     }
+
+    /// Window with the highest usage across every window in the snapshot.
+    pub fn max_window(&self) -> Option<&RateWindow> {
+        self.primary
+            .iter()
+            .chain(self.secondary.iter())
+            .chain(self.tertiary.iter())
+            .chain(self.carveouts.iter().map(|c| &c.window))
+            .max_by(|a, b| a.used_percent.total_cmp(&b.used_percent))
+    }
+
+    /// Stamps the snapshot as received just now and, when the API server's
+    /// clock is off from ours by `skew`, moves its reset times onto our clock
+    /// so countdowns come out right.
+    pub fn mark_received(mut self, skew: Option<chrono::Duration>) -> Self {
+        self.received_at = Some(Instant::now());
+        let Some(skew) = skew else {
+            return self;
+        };
+        let windows = self
+            .primary
+            .iter_mut()
+            .chain(self.secondary.iter_mut())
+            .chain(self.tertiary.iter_mut())
+            .chain(self.carveouts.iter_mut().map(|c| &mut c.window))
+            .chain(self.surfaces.iter_mut().map(|c| &mut c.window));
+        for window in windows {
+            window.resets_at = window.resets_at.map(|at| to_local_clock(at, skew));
+        }
+        if let Some(cost) = self.provider_cost.as_mut() {
+            cost.resets_at = cost.resets_at.map(|at| to_local_clock(at, skew));
+        }
+        self
+    }
+
+    /// How long ago the snapshot was fetched, robust against the wall clock
+    /// jumping since.
+    pub fn age(&self, now: DateTime<Utc>) -> chrono::Duration {
+        snapshot_age(
+            self.updated_at,
+            self.received_at.map(|at| at.elapsed()),
+            now,
+        )
+    }
+
+    /// Drops a provider cost whose billing period has ended, so last period's
+    /// spend isn't shown against the new one. Returns whether it was dropped.
+    pub fn clear_expired_provider_cost(&mut self, now: DateTime<Utc>) -> bool {
+        if self
+            .provider_cost
+            .as_ref()
+            .is_some_and(|cost| cost.has_reset(now))
+        {
+            self.provider_cost = None;
+            return true;
+        }
+        false
+    }
+
+    /// Carveout window for a model family, matching labels like "Opus Weekly"
+    /// by their leading model name.
+    pub fn carveout_window(&self, model: &str) -> Option<&RateWindow> {
+        self.carveouts
+            .iter()
+            .find(|c| c.label.trim_end_matches(" Weekly").eq_ignore_ascii_case(model))
+            .map(|c| &c.window)
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ModelWindow {
     pub label: String,
     pub window: RateWindow,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ProviderCostSnapshot {
     pub used: f64,
     pub limit: f64,
@@ -100,21 +332,75 @@ pub struct ProviderCostSnapshot {
     pub updated_at: DateTime<Utc>,
 }
 
+impl ProviderCostSnapshot {
+    pub fn has_reset(&self, now: DateTime<Utc>) -> bool {
+        self.resets_at.is_some_and(|resets_at| resets_at <= now)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CostUsageTokenSnapshot {
     pub session_tokens: Option<u64>,
     pub session_cost_usd: Option<f64>,
+    #[serde(default)]
+    pub session_start: Option<DateTime<Utc>>,
     pub last_30_days_tokens: Option<u64>,
     pub last_30_days_cost_usd: Option<f64>,
     pub daily: Vec<DailyTokenUsage>,
+    #[serde(default)]
+    pub heatmap: UsageHeatmap,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct DailyTokenUsage {
     pub date: NaiveDate,
     pub total_tokens: Option<u64>,
     pub cost_usd: Option<f64>,
+    /// Per-model breakdown of the totals above, sorted by model.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub models: Vec<ModelTokenUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ModelTokenUsage {
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost_usd: Option<f64>,
+}
+
+impl ModelTokenUsage {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_tokens + self.cache_read_tokens
+    }
+}
+
+/// Tokens by weekday (Monday first) and hour of day over the last 30 days,
+/// in the cost timezone.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageHeatmap {
+    pub tokens: [[u64; 24]; 7],
+}
+
+impl UsageHeatmap {
+    pub fn add(&mut self, weekday: Weekday, hour: u32, tokens: u64) {
+        self.tokens[weekday.num_days_from_monday() as usize][hour as usize % 24] += tokens;
+    }
+
+    pub fn get(&self, weekday: Weekday, hour: u32) -> u64 {
+        self.tokens[weekday.num_days_from_monday() as usize][hour as usize % 24]
+    }
+
+    pub fn max(&self) -> u64 {
+        self.tokens.iter().flatten().copied().max().unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max() == 0
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -122,6 +408,8 @@ pub struct DailyCost {
     pub date: NaiveDate,
     pub model: String,
     pub cost: f64,
+    #[serde(default)]
+    pub tokens: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -134,6 +422,13 @@ pub struct CostSnapshot {
     pub pricing_estimate: bool,
     #[serde(default)]
     pub log_error: bool,
+    /// This month's spend per project, for every project seen in the scan.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub project_costs: BTreeMap<String, f64>,
+    /// Project directories `cost.include_projects`/`cost.exclude_projects`
+    /// kept out of the scan.
+    #[serde(default)]
+    pub excluded_projects: usize,
 }
 
 impl Default for CostSnapshot {
@@ -145,7 +440,208 @@ impl Default for CostSnapshot {
             daily_breakdown: Vec::new(),
             pricing_estimate: false,
             log_error: false,
+            project_costs: BTreeMap::new(),
+            excluded_projects: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Auth,
+    RateLimited,
+    Server,
+    Network,
+    InvalidResponse,
+    Other,
+}
+
+impl ErrorKind {
+    /// Best-effort classification of the provider error messages, which are
+    /// plain strings by the time they reach the store.
+    pub fn classify(message: &str) -> Self {
+        let lower = message.to_lowercase();
+        if lower.contains("authentication failed")
+            || lower.contains("token expired")
+            || lower.contains("access forbidden")
+            || lower.contains("access token is empty")
+            || lower.contains("credentials")
+        {
+            ErrorKind::Auth
+        } else if lower.contains("api error: 429") {
+            ErrorKind::RateLimited
+        } else if lower.contains("api error: 5") {
+            ErrorKind::Server
+        } else if lower.contains("failed to fetch") {
+            ErrorKind::Network
+        } else if lower.contains("failed to parse") {
+            ErrorKind::InvalidResponse
+        } else {
+            ErrorKind::Other
+        }
+    }
+
+    /// For a usage API's error status.
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorKind::Auth,
+            429 => ErrorKind::RateLimited,
+            500..=599 => ErrorKind::Server,
+            _ => ErrorKind::Other,
+        }
+    }
+
+    /// Errors that usually clear up on their own, unlike a bad login.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::RateLimited | ErrorKind::Server | ErrorKind::Network
+        )
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ErrorKind::Auth => "auth",
+            ErrorKind::RateLimited => "rate limited",
+            ErrorKind::Server => "server",
+            ErrorKind::Network => "network",
+            ErrorKind::InvalidResponse => "bad response",
+            ErrorKind::Other => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ErrorEvent {
+    Failed {
+        at: DateTime<Utc>,
+        kind: ErrorKind,
+        message: String,
+    },
+    Recovered {
+        at: DateTime<Utc>,
+        failures: u32,
+        outage_secs: i64,
+    },
+    /// Usage fell sharply mid-window, likely the provider resizing the
+    /// quota.
+    QuotaAdjusted {
+        at: DateTime<Utc>,
+        window: WindowSlot,
+        from: f64,
+        to: f64,
+    },
+}
+
+impl ErrorEvent {
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            ErrorEvent::Failed { at, .. }
+            | ErrorEvent::Recovered { at, .. }
+            | ErrorEvent::QuotaAdjusted { at, .. } => *at,
+        }
+    }
+
+    /// One-line description, e.g. "network: Failed to fetch Claude usage",
+    /// "ok after 3 failures, 12m outage" or "weekly usage dropped from 80% to
+    /// 40% without a reset (quota adjusted?)".
+    pub fn describe(&self) -> String {
+        match self {
+            ErrorEvent::Failed { kind, message, .. } => format!("{}: {}", kind.label(), message),
+            ErrorEvent::Recovered {
+                failures,
+                outage_secs,
+                ..
+            } => {
+                let plural = if *failures == 1 { "" } else { "s" };
+                format!(
+                    "ok after {} failure{}, {} outage",
+                    failures,
+                    plural,
+                    format_outage(*outage_secs)
+                )
+            }
+            ErrorEvent::QuotaAdjusted {
+                window, from, to, ..
+            } => format!(
+                "{} usage dropped from {:.0}% to {:.0}% without a reset (quota adjusted?)",
+                window.label(),
+                from * 100.0,
+                to * 100.0
+            ),
+        }
+    }
+}
+
+fn format_outage(secs: i64) -> String {
+    let secs = secs.max(0);
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+/// The daemon's latest view of one provider, as served over D-Bus.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub snapshot: Option<UsageSnapshot>,
+    pub error: Option<String>,
+}
+
+/// When a provider last fetched successfully and, while it is failing, when
+/// the current run of failures began.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProviderHealth {
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub error_since: Option<DateTime<Utc>>,
+}
+
+/// Whether the daemon's tray icons are registered with a
+/// StatusNotifierWatcher. `Unavailable` while registration keeps failing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayStatus {
+    Registered,
+    Unavailable,
+}
+
+/// Summary of recent `fetch_usage` wall-clock durations for one provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct FetchLatency {
+    pub min: u64,
+    pub median: u64,
+    pub max: u64,
+    pub samples: usize,
+}
+
+impl FetchLatency {
+    pub fn from_samples(samples: impl IntoIterator<Item = std::time::Duration>) -> Option<Self> {
+        let mut millis: Vec<u64> = samples
+            .into_iter()
+            .map(|d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+            .collect();
+        if millis.is_empty() {
+            return None;
         }
+        millis.sort_unstable();
+
+        let mid = millis.len() / 2;
+        let median = if millis.len() % 2 == 0 {
+            (millis[mid - 1] + millis[mid]) / 2
+        } else {
+            millis[mid]
+        };
+        Some(Self {
+            min: millis[0],
+            median,
+            max: millis[millis.len() - 1],
+            samples: millis.len(),
+        })
     }
 }
 
@@ -161,10 +657,58 @@ mod tests {
             window_minutes: Some(300),
             resets_at: None,
             reset_description: None,
+            used_percent_exact: None,
+            resets_at_estimated: false,
         };
         assert!((window.remaining_percent() - 0.25).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn test_rate_window_from_reported_clamps() {
+        let window = RateWindow::from_reported(0.45, Some(300), None, None);
+        assert_eq!(window.used_percent, 0.45);
+        assert!(!window.was_clamped());
+
+        let window = RateWindow::from_reported(2.5, Some(300), None, None);
+        assert_eq!(window.used_percent, 1.0);
+        assert_eq!(window.used_percent_exact, Some(250.0));
+        assert_eq!(window.remaining_percent(), 0.0);
+
+        let window = RateWindow::from_reported(-0.2, Some(300), None, None);
+        assert_eq!(window.used_percent, 0.0);
+        assert_eq!(window.used_percent_exact, Some(-20.0));
+
+        let window = RateWindow::from_reported(f64::NAN, Some(300), None, None);
+        assert_eq!(window.used_percent, 0.0);
+        assert!(window.was_clamped());
+    }
+
+    #[test]
+    fn test_rate_window_keeps_exact_percent() {
+        let window = RateWindow::from_reported_percent(45.5, Some(300), None, None);
+        assert!((window.used_percent - 0.455).abs() < 1e-12);
+        assert_eq!(window.used_percent_exact, Some(45.5));
+        assert_eq!(window.used_text(), "45.5%");
+        assert_eq!(window.remaining_text(), "54.5%");
+
+        let window = RateWindow::from_reported_percent(32.0, Some(300), None, None);
+        assert_eq!(window.used_text(), "32%");
+
+        // Out of range: clamped for display, still exact in the JSON.
+        let window = RateWindow::from_reported_percent(250.0, None, None, None);
+        assert_eq!(window.used_percent_exact, Some(250.0));
+        assert_eq!(window.used_text(), "100%");
+
+        let window = RateWindow::from_reported_percent(f64::NAN, None, None, None);
+        assert!(window.was_clamped());
+        assert_eq!(window.used_text(), "0%");
+
+        let window = RateWindow::from_reported(0.4549999, None, None, None);
+        assert_eq!(window.used_text(), "45.5%");
+        let window = RateWindow::from_reported(0.7, None, None, None);
+        assert_eq!(window.used_text(), "70%");
+    }
+
     #[test]
     fn test_rate_window_high_usage() {
         let window = RateWindow {
@@ -172,6 +716,8 @@ mod tests {
             window_minutes: None,
             resets_at: None,
             reset_description: None,
+            used_percent_exact: None,
+            resets_at_estimated: false,
         };
         assert!(window.is_high_usage(0.9));
         assert!(!window.is_high_usage(0.95));
@@ -183,6 +729,20 @@ mod tests {
         assert_eq!(Provider::Codex.name(), "Codex");
     }
 
+    #[test]
+    fn test_provider_id_roundtrip() {
+        for provider in [
+            Provider::Claude,
+            Provider::Codex,
+            Provider::CodexWorkspace(2),
+        ] {
+            assert_eq!(Provider::from_id(&provider.id()), Some(provider));
+        }
+        assert_eq!(Provider::from_id(" Claude "), Some(Provider::Claude));
+        assert_eq!(Provider::from_id("codex-0"), None);
+        assert_eq!(Provider::from_id("gemini"), None);
+    }
+
     #[test]
     fn test_provider_serialization_roundtrip() {
         for provider in [Provider::Claude, Provider::Codex] {
@@ -199,6 +759,8 @@ mod tests {
             window_minutes: Some(300),
             resets_at: Some(Utc.with_ymd_and_hms(2026, 1, 18, 15, 30, 0).unwrap()),
             reset_description: Some("Resets in 2h 14m".to_string()),
+            used_percent_exact: None,
+            resets_at_estimated: false,
         };
 
         let json = serde_json::to_string(&window).unwrap();
@@ -213,6 +775,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rate_window_exact_percent_roundtrip() {
+        let window = RateWindow::from_reported_percent(45.5, Some(300), None, None);
+        let json = serde_json::to_value(&window).unwrap();
+        assert_eq!(json["used_percent_exact"], 45.5);
+
+        let deserialized: RateWindow = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, window);
+        assert_eq!(deserialized.used_text(), "45.5%");
+
+        // Snapshots cached before the field existed still load.
+        let legacy: RateWindow = serde_json::from_str(
+            r#"{"used_percent":0.455,"window_minutes":300,"resets_at":null,"reset_description":null}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.used_percent_exact, None);
+        assert_eq!(legacy.used_text(), "45.5%");
+    }
+
     #[test]
     fn test_usage_snapshot_serialization_roundtrip() {
         let snapshot = UsageSnapshot {
@@ -221,17 +802,23 @@ mod tests {
                 window_minutes: Some(300),
                 resets_at: None,
                 reset_description: None,
+                used_percent_exact: None,
+                resets_at_estimated: false,
             }),
             secondary: Some(RateWindow {
                 used_percent: 0.32,
                 window_minutes: Some(10080),
                 resets_at: None,
                 reset_description: Some("Weekly quota".to_string()),
+                used_percent_exact: None,
+                resets_at_estimated: false,
             }),
             tertiary: None,
             provider_cost: None,
             carveouts: Vec::new(),
+            surfaces: Vec::new(),
             updated_at: Utc::now(),
+            received_at: None,
             identity: ProviderIdentity {
                 email: Some("user@example.com".to_string()),
                 organization: Some("Acme Corp".to_string()),
@@ -241,11 +828,13 @@ mod tests {
         };
 
         let json = serde_json::to_string(&snapshot).unwrap();
+        assert!(!json.contains("surfaces"));
         let deserialized: UsageSnapshot = serde_json::from_str(&json).unwrap();
 
         assert!(deserialized.primary.is_some());
         assert!(deserialized.secondary.is_some());
         assert!(deserialized.carveouts.is_empty());
+        assert!(deserialized.surfaces.is_empty());
         assert_eq!(
             deserialized.identity.email,
             Some("user@example.com".to_string())
@@ -263,15 +852,19 @@ mod tests {
                     date: NaiveDate::from_ymd_opt(2026, 1, 18).unwrap(),
                     model: "claude-3-5-sonnet".to_string(),
                     cost: 8.50,
+                    tokens: 0,
                 },
                 DailyCost {
                     date: NaiveDate::from_ymd_opt(2026, 1, 18).unwrap(),
                     model: "claude-3-opus".to_string(),
                     cost: 3.95,
+                    tokens: 0,
                 },
             ],
             pricing_estimate: false,
             log_error: false,
+            project_costs: BTreeMap::from([("acme-backend".to_string(), 12.0)]),
+            excluded_projects: 2,
         };
 
         let json = serde_json::to_string(&cost).unwrap();
@@ -281,6 +874,8 @@ mod tests {
         assert!((deserialized.monthly_cost - 234.56).abs() < f64::EPSILON);
         assert_eq!(deserialized.currency, "USD");
         assert_eq!(deserialized.daily_breakdown.len(), 2);
+        assert_eq!(deserialized.project_costs, cost.project_costs);
+        assert_eq!(deserialized.excluded_projects, 2);
     }
 
     #[test]
@@ -291,12 +886,16 @@ mod tests {
                 window_minutes: None,
                 resets_at: None,
                 reset_description: None,
+                used_percent_exact: None,
+                resets_at_estimated: false,
             }),
             secondary: Some(RateWindow {
                 used_percent: 0.80,
                 window_minutes: None,
                 resets_at: None,
                 reset_description: None,
+                used_percent_exact: None,
+                resets_at_estimated: false,
             }),
             tertiary: None,
             provider_cost: None,
@@ -307,9 +906,13 @@ mod tests {
                     window_minutes: None,
                     resets_at: None,
                     reset_description: None,
+                    used_percent_exact: None,
+                    resets_at_estimated: false,
                 },
             }],
+            surfaces: Vec::new(),
             updated_at: Utc::now(),
+            received_at: None,
             identity: ProviderIdentity {
                 email: None,
                 organization: None,
@@ -319,5 +922,224 @@ mod tests {
         };
 
         assert!((snapshot.max_usage() - 0.80).abs() < f64::EPSILON);
+        assert!((snapshot.max_window().unwrap().used_percent - 0.80).abs() < f64::EPSILON);
+        assert!(
+            (snapshot.carveout_window("opus").unwrap().used_percent - 0.45).abs() < f64::EPSILON
+        );
+        assert!(snapshot.carveout_window("sonnet").is_none());
+    }
+
+    #[test]
+    fn test_same_usage_ignores_fetch_time() {
+        let snapshot = UsageSnapshot {
+            primary: Some(RateWindow {
+                used_percent: 0.50,
+                window_minutes: Some(300),
+                resets_at: None,
+                reset_description: None,
+                used_percent_exact: None,
+                resets_at_estimated: false,
+            }),
+            secondary: None,
+            tertiary: None,
+            provider_cost: None,
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            updated_at: Utc::now() - chrono::Duration::minutes(5),
+            received_at: None,
+            identity: ProviderIdentity {
+                email: None,
+                organization: None,
+                plan: Some("Claude Max".to_string()),
+                login_method: None,
+            },
+        };
+
+        let mut refreshed = snapshot.clone();
+        refreshed.updated_at = Utc::now();
+        assert!(refreshed.same_usage_as(&snapshot));
+
+        refreshed.primary.as_mut().unwrap().used_percent = 0.51;
+        assert!(!refreshed.same_usage_as(&snapshot));
+    }
+
+    #[test]
+    fn test_clear_expired_provider_cost() {
+        let now = Utc::now();
+        let mut snapshot = UsageSnapshot {
+            primary: None,
+            secondary: None,
+            tertiary: None,
+            provider_cost: Some(ProviderCostSnapshot {
+                used: 12.0,
+                limit: 50.0,
+                currency_code: "USD".to_string(),
+                period: Some("Monthly".to_string()),
+                resets_at: Some(now + chrono::Duration::days(9)),
+                updated_at: now,
+            }),
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            updated_at: now,
+            received_at: None,
+            identity: ProviderIdentity {
+                email: None,
+                organization: None,
+                plan: None,
+                login_method: None,
+            },
+        };
+
+        assert!(!snapshot.clear_expired_provider_cost(now));
+        assert!(snapshot.provider_cost.is_some());
+
+        assert!(snapshot.clear_expired_provider_cost(now + chrono::Duration::days(9)));
+        assert!(snapshot.provider_cost.is_none());
+
+        // Without a known reset the cost is kept.
+        snapshot.provider_cost = Some(ProviderCostSnapshot {
+            used: 1.0,
+            limit: 5.0,
+            currency_code: "USD".to_string(),
+            period: None,
+            resets_at: None,
+            updated_at: now,
+        });
+        assert!(!snapshot.clear_expired_provider_cost(now + chrono::Duration::days(90)));
+    }
+
+    #[test]
+    fn test_mark_received_moves_resets_onto_local_clock() {
+        let now = Utc::now();
+        let resets_at = now + chrono::Duration::hours(2);
+        let window = RateWindow::from_reported(0.5, Some(300), Some(resets_at), None);
+        let snapshot = UsageSnapshot {
+            primary: Some(window.clone()),
+            secondary: None,
+            tertiary: None,
+            provider_cost: None,
+            carveouts: vec![ModelWindow {
+                label: "Opus Weekly".to_string(),
+                window,
+            }],
+            surfaces: Vec::new(),
+            updated_at: now,
+            received_at: None,
+            identity: ProviderIdentity {
+                email: None,
+                organization: None,
+                plan: None,
+                login_method: None,
+            },
+        };
+
+        let unskewed = snapshot.clone().mark_received(None);
+        assert!(unskewed.received_at.is_some());
+        assert_eq!(unskewed.primary.unwrap().resets_at, Some(resets_at));
+
+        // The server runs five minutes ahead, so its resets come five
+        // minutes sooner on our clock.
+        let skewed = snapshot.mark_received(Some(chrono::Duration::minutes(5)));
+        let expected = Some(resets_at - chrono::Duration::minutes(5));
+        assert_eq!(skewed.primary.as_ref().unwrap().resets_at, expected);
+        assert_eq!(skewed.carveouts[0].window.resets_at, expected);
+        assert!(skewed.age(now) < chrono::Duration::seconds(1));
+    }
+
+    #[test]
+    fn test_fetch_latency_from_samples() {
+        use std::time::Duration;
+
+        assert!(FetchLatency::from_samples(Vec::new()).is_none());
+
+        let odd = FetchLatency::from_samples(
+            [900, 300, 2_100].map(Duration::from_millis),
+        )
+        .unwrap();
+        assert_eq!((odd.min, odd.median, odd.max, odd.samples), (300, 900, 2_100, 3));
+
+        let even = FetchLatency::from_samples(
+            [400, 800, 880, 1_200].map(Duration::from_millis),
+        )
+        .unwrap();
+        assert_eq!(even.median, 840);
+    }
+
+    #[test]
+    fn test_error_kind_classification() {
+        assert_eq!(
+            ErrorKind::classify("Claude authentication failed. Run `claude` to refresh credentials."),
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            ErrorKind::classify("Codex token expired. Waiting for Codex to refresh credentials."),
+            ErrorKind::Auth
+        );
+        assert_eq!(
+            ErrorKind::classify("Claude API error: 429 Too Many Requests - slow down"),
+            ErrorKind::RateLimited
+        );
+        assert_eq!(
+            ErrorKind::classify("Codex API error: 502 Bad Gateway - "),
+            ErrorKind::Server
+        );
+        assert_eq!(ErrorKind::classify("Failed to fetch Claude usage"), ErrorKind::Network);
+        assert_eq!(
+            ErrorKind::classify("Failed to parse Codex usage response"),
+            ErrorKind::InvalidResponse
+        );
+        assert_eq!(ErrorKind::classify("something odd"), ErrorKind::Other);
+    }
+
+    #[test]
+    fn test_error_kind_from_status() {
+        assert_eq!(ErrorKind::from_status(401), ErrorKind::Auth);
+        assert_eq!(ErrorKind::from_status(403), ErrorKind::Auth);
+        assert_eq!(ErrorKind::from_status(429), ErrorKind::RateLimited);
+        assert_eq!(ErrorKind::from_status(500), ErrorKind::Server);
+        assert_eq!(ErrorKind::from_status(503), ErrorKind::Server);
+        assert_eq!(ErrorKind::from_status(404), ErrorKind::Other);
+
+        assert!(!ErrorKind::Auth.is_transient());
+        assert!(ErrorKind::RateLimited.is_transient());
+        assert!(ErrorKind::Server.is_transient());
+        assert!(ErrorKind::Network.is_transient());
+        assert!(!ErrorKind::InvalidResponse.is_transient());
+    }
+
+    #[test]
+    fn test_error_event_describe() {
+        let at = Utc::now();
+        let failed = ErrorEvent::Failed {
+            at,
+            kind: ErrorKind::Server,
+            message: "Claude API error: 503".to_string(),
+        };
+        assert_eq!(failed.describe(), "server: Claude API error: 503");
+
+        let recovered = ErrorEvent::Recovered {
+            at,
+            failures: 3,
+            outage_secs: 12 * 60 + 5,
+        };
+        assert_eq!(recovered.describe(), "ok after 3 failures, 12m outage");
+
+        let json = serde_json::to_value(&recovered).unwrap();
+        assert_eq!(json["event"], "recovered");
+        assert_eq!(json["failures"], 3);
+
+        let adjusted = ErrorEvent::QuotaAdjusted {
+            at,
+            window: WindowSlot::Secondary,
+            from: 0.8,
+            to: 0.4,
+        };
+        assert_eq!(
+            adjusted.describe(),
+            "weekly usage dropped from 80% to 40% without a reset (quota adjusted?)"
+        );
+        let json = serde_json::to_value(&adjusted).unwrap();
+        assert_eq!(json["event"], "quota_adjusted");
+        assert_eq!(json["window"], "weekly");
     }
 }
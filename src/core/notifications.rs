@@ -1,27 +1,361 @@
-use crate::core::models::Provider;
+use crate::core::models::{Provider, UsageSnapshot};
+use crate::core::reset_estimate::WindowSlot;
+use crate::core::state_file;
+use crate::core::weekly_summary::WeeklySummary;
 use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
 use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
 
-#[allow(dead_code)]
-pub fn send_high_usage_notification(provider: Provider, percent: f64) -> Result<()> {
-    let percent_display = (percent * 100.0).round() as u32;
+/// A session or weekly window that just reached the notification threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UsageAlert {
+    pub window: WindowSlot,
+    pub used: f64,
+    pub resets_at: Option<DateTime<Utc>>,
+}
 
-    Notification::new()
+/// Decides when the usage notification fires: once when a provider's session
+/// or weekly usage reaches the threshold, and again only after it has dropped
+/// back below. Snoozed windows stay quiet until the snooze ends.
+#[derive(Debug, Default)]
+pub struct UsageAlerts {
+    alerted: HashSet<(Provider, WindowSlot)>,
+}
+
+impl UsageAlerts {
+    /// The window to report when `snapshot` just crossed `threshold`, the
+    /// fuller one when both did.
+    pub fn check(
+        &mut self,
+        provider: Provider,
+        snapshot: &UsageSnapshot,
+        threshold: f64,
+        snoozes: &AlertSnoozes,
+        now: DateTime<Utc>,
+    ) -> Option<UsageAlert> {
+        let windows = [
+            (WindowSlot::Primary, snapshot.primary.as_ref()),
+            (WindowSlot::Secondary, snapshot.secondary.as_ref()),
+        ];
+        let mut crossed: Option<UsageAlert> = None;
+        for (slot, window) in windows {
+            let Some(window) = window.filter(|window| window.is_high_usage(threshold)) else {
+                self.alerted.remove(&(provider, slot));
+                continue;
+            };
+            // Not marked as alerted, so it notifies once the snooze ends.
+            if snoozes.is_snoozed(provider, slot, now) || !self.alerted.insert((provider, slot)) {
+                continue;
+            }
+            if crossed.is_none_or(|alert| window.used_percent > alert.used) {
+                crossed = Some(UsageAlert {
+                    window: slot,
+                    used: window.used_percent,
+                    resets_at: window.resets_at,
+                });
+            }
+        }
+        crossed
+    }
+}
+
+/// The actions a usage notification offers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Snooze {
+    Hour,
+    UntilReset,
+}
+
+impl Snooze {
+    fn action(self) -> &'static str {
+        match self {
+            Snooze::Hour => "snooze-1h",
+            Snooze::UntilReset => "snooze-reset",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Snooze::Hour => "Snooze 1h",
+            Snooze::UntilReset => "Snooze until reset",
+        }
+    }
+
+    fn from_action(action: &str) -> Option<Self> {
+        [Snooze::Hour, Snooze::UntilReset]
+            .into_iter()
+            .find(|snooze| snooze.action() == action)
+    }
+
+    /// When the snooze ends; `None` for "until reset" on a window without a
+    /// reset time.
+    pub fn until(self, alert: &UsageAlert, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Snooze::Hour => Some(now + Duration::hours(1)),
+            Snooze::UntilReset => alert.resets_at,
+        }
+    }
+}
+
+/// Usage alerts snoozed from the notification, until when, keyed
+/// `<provider id>/<window>`. Persisted so a daemon restart doesn't bring a
+/// snoozed alert back early.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertSnoozes {
+    until: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl AlertSnoozes {
+    pub fn load() -> Self {
+        state_file::load("snoozes")
+    }
+
+    pub fn save(&self) -> Result<()> {
+        state_file::save("snoozes", self)
+    }
+
+    /// Snoozes `window` until `until`, dropping snoozes that have ended.
+    pub fn snooze(
+        &mut self,
+        provider: Provider,
+        window: WindowSlot,
+        until: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) {
+        self.until.retain(|_, until| *until > now);
+        self.until.insert(snooze_key(provider, window), until);
+    }
+
+    pub fn is_snoozed(&self, provider: Provider, window: WindowSlot, now: DateTime<Utc>) -> bool {
+        self.until
+            .get(&snooze_key(provider, window))
+            .is_some_and(|until| now < *until)
+    }
+}
+
+fn snooze_key(provider: Provider, window: WindowSlot) -> String {
+    format!("{}/{}", provider.id(), window.label())
+}
+
+/// Whether the notification server shows action buttons.
+pub fn notification_actions_supported() -> bool {
+    notify_rust::get_capabilities()
+        .is_ok_and(|capabilities| capabilities.iter().any(|c| c == "actions"))
+}
+
+/// Sends the usage notification. With `with_actions` it offers the snooze
+/// actions and blocks until the notification is acted on or closed,
+/// returning the snooze picked.
+pub fn send_high_usage_notification(
+    provider: Provider,
+    alert: &UsageAlert,
+    with_actions: bool,
+) -> Result<Option<Snooze>> {
+    let percent_display = (alert.used * 100.0).round() as u32;
+
+    let mut notification = Notification::new();
+    notification
         .summary(&format!("{} Usage Warning", provider.name()))
         .body(&format!(
             "You've used {}% of your {} quota.",
             percent_display,
             provider.name()
         ))
+        .appname("claude-bar");
+    if with_actions {
+        // The server's own timeout, long enough to reach for a button.
+        notification.action(Snooze::Hour.action(), Snooze::Hour.label());
+        if alert.resets_at.is_some() {
+            notification.action(Snooze::UntilReset.action(), Snooze::UntilReset.label());
+        }
+    } else {
+        notification.timeout(notify_rust::Timeout::Milliseconds(5000));
+    }
+    let handle = notification.show()?;
+
+    tracing::info!(
+        provider = ?provider,
+        window = ?alert.window,
+        percent = percent_display,
+        "Sent high usage notification"
+    );
+
+    let mut snooze = None;
+    if with_actions {
+        handle.wait_for_action(|action| snooze = Snooze::from_action(action));
+    }
+    Ok(snooze)
+}
+
+pub fn send_budget_notification(
+    project: &str,
+    threshold: u32,
+    spent: f64,
+    budget: f64,
+) -> Result<()> {
+    let summary = if threshold >= 100 {
+        format!("{} is over budget", project)
+    } else {
+        format!("{} is at {}% of its budget", project, threshold)
+    };
+
+    Notification::new()
+        .summary(&summary)
+        .body(&format!(
+            "${:.2} of the ${:.2} monthly budget spent.",
+            spent, budget
+        ))
         .appname("claude-bar")
         .timeout(notify_rust::Timeout::Milliseconds(5000))
         .show()?;
 
+    tracing::info!(
+        project,
+        threshold,
+        spent,
+        budget,
+        "Sent budget notification"
+    );
+
+    Ok(())
+}
+
+pub fn send_weekly_summary_notification(provider: Provider, summary: &WeeklySummary) -> Result<()> {
+    Notification::new()
+        .summary(&format!("{} weekly recap", provider.name()))
+        .body(&summary.message())
+        .appname("claude-bar")
+        .timeout(notify_rust::Timeout::Milliseconds(10000))
+        .show()?;
+
     tracing::info!(
         provider = ?provider,
-        percent = percent_display,
-        "Sent high usage notification"
+        ended_at = %summary.ended_at,
+        "Sent weekly summary notification"
     );
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::RateWindow;
+
+    fn snapshot(session: f64, weekly: f64) -> UsageSnapshot {
+        let window = |used_percent| RateWindow::from_reported(used_percent, None, None, None);
+        UsageSnapshot::new(Some(window(session)), Some(window(weekly)), Utc::now())
+    }
+
+    fn check(
+        alerts: &mut UsageAlerts,
+        provider: Provider,
+        snapshot: &UsageSnapshot,
+        snoozes: &AlertSnoozes,
+        now: DateTime<Utc>,
+    ) -> Option<(WindowSlot, f64)> {
+        alerts
+            .check(provider, snapshot, 0.9, snoozes, now)
+            .map(|alert| (alert.window, alert.used))
+    }
+
+    #[test]
+    fn test_usage_alert_fires_once_per_crossing() {
+        let mut alerts = UsageAlerts::default();
+        let snoozes = AlertSnoozes::default();
+        let now = Utc::now();
+        let claude = Provider::Claude;
+        let mut observe = |provider, session, weekly| {
+            check(
+                &mut alerts,
+                provider,
+                &snapshot(session, weekly),
+                &snoozes,
+                now,
+            )
+        };
+
+        assert_eq!(observe(claude, 0.5, 0.2), None);
+        assert_eq!(
+            observe(claude, 0.92, 0.95),
+            Some((WindowSlot::Secondary, 0.95))
+        );
+        assert_eq!(observe(claude, 0.97, 0.95), None);
+        // Other providers are tracked separately.
+        assert_eq!(
+            observe(Provider::Codex, 0.9, 0.1),
+            Some((WindowSlot::Primary, 0.9))
+        );
+
+        // The session window reset, so the next crossing notifies again.
+        assert_eq!(observe(claude, 0.1, 0.95), None);
+        assert_eq!(
+            observe(claude, 0.91, 0.95),
+            Some((WindowSlot::Primary, 0.91))
+        );
+    }
+
+    #[test]
+    fn test_snoozed_window_waits_for_snooze_to_end() {
+        let mut alerts = UsageAlerts::default();
+        let mut snoozes = AlertSnoozes::default();
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let claude = Provider::Claude;
+        let high = snapshot(0.95, 0.5);
+
+        let alert = alerts.check(claude, &high, 0.9, &snoozes, now).unwrap();
+        let until = Snooze::Hour.until(&alert, now).unwrap();
+        snoozes.snooze(claude, WindowSlot::Primary, until, now);
+        // Only that window is snoozed.
+        assert!(!snoozes.is_snoozed(claude, WindowSlot::Secondary, now));
+        assert!(!snoozes.is_snoozed(Provider::Codex, WindowSlot::Primary, now));
+
+        // A restart forgets what already notified, but not the snooze.
+        let mut restarted = UsageAlerts::default();
+        let later = now + Duration::minutes(30);
+        assert_eq!(check(&mut restarted, claude, &high, &snoozes, later), None);
+        // Still high when the hour is up.
+        let after = now + Duration::minutes(61);
+        assert_eq!(
+            check(&mut restarted, claude, &high, &snoozes, after),
+            Some((WindowSlot::Primary, 0.95))
+        );
+
+        // Expired snoozes are dropped when the next one is recorded.
+        snoozes.snooze(
+            claude,
+            WindowSlot::Secondary,
+            after + Duration::hours(5),
+            after,
+        );
+        let json = serde_json::to_string(&snoozes).unwrap();
+        let restored: AlertSnoozes = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, snoozes);
+        assert_eq!(restored.until.keys().collect::<Vec<_>>(), ["claude/weekly"]);
+    }
+
+    #[test]
+    fn test_snooze_until_reset_needs_reset_time() {
+        let now = Utc::now();
+        let alert = UsageAlert {
+            window: WindowSlot::Secondary,
+            used: 0.92,
+            resets_at: None,
+        };
+        assert_eq!(Snooze::UntilReset.until(&alert, now), None);
+        let resets_at = now + Duration::days(2);
+        let alert = UsageAlert {
+            resets_at: Some(resets_at),
+            ..alert
+        };
+        assert_eq!(Snooze::UntilReset.until(&alert, now), Some(resets_at));
+        assert_eq!(
+            Snooze::from_action("snooze-reset"),
+            Some(Snooze::UntilReset)
+        );
+        assert_eq!(Snooze::from_action("__closed"), None);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::core::models::{RateWindow, UsageSnapshot};
+use crate::core::reset_estimate::{same_window_reset, WindowSlot};
+use chrono::{DateTime, Utc};
+
+/// A drop in usage bigger than this, 20 percentage points, without the
+/// window resetting means the provider resized the quota.
+const ADJUSTMENT_DROP: f64 = 0.20;
+
+/// A window whose usage fell from `from` to `to` mid-window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuotaAdjustment {
+    pub window: WindowSlot,
+    pub from: f64,
+    pub to: f64,
+}
+
+/// The windows of `current` that dropped sharply since `previous` while
+/// keeping the same reset time, checked as of `now`.
+pub fn detect_quota_adjustments(
+    previous: &UsageSnapshot,
+    current: &UsageSnapshot,
+    now: DateTime<Utc>,
+) -> Vec<QuotaAdjustment> {
+    [
+        (WindowSlot::Primary, &previous.primary, &current.primary),
+        (
+            WindowSlot::Secondary,
+            &previous.secondary,
+            &current.secondary,
+        ),
+        (WindowSlot::Tertiary, &previous.tertiary, &current.tertiary),
+    ]
+    .into_iter()
+    .filter_map(|(slot, before, after)| {
+        let (before, after) = (before.as_ref()?, after.as_ref()?);
+        is_adjustment(before, after, now).then_some(QuotaAdjustment {
+            window: slot,
+            from: before.used_percent,
+            to: after.used_percent,
+        })
+    })
+    .collect()
+}
+
+fn is_adjustment(before: &RateWindow, after: &RateWindow, now: DateTime<Utc>) -> bool {
+    if before.used_percent - after.used_percent <= ADJUSTMENT_DROP {
+        return false;
+    }
+    // Only reset times the provider reported can rule out a rollover.
+    if before.resets_at_estimated || after.resets_at_estimated {
+        return false;
+    }
+    let (Some(reset_before), Some(reset_after)) = (before.resets_at, after.resets_at) else {
+        return false;
+    };
+    // The old window is over, whatever the new one reports.
+    if reset_before <= now {
+        return false;
+    }
+    same_window_reset(reset_before, reset_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn snapshot(weekly: f64, resets_at: DateTime<Utc>) -> UsageSnapshot {
+        let window = RateWindow::from_reported(weekly, Some(10080), Some(resets_at), None);
+        UsageSnapshot::new(None, Some(window), Utc::now())
+    }
+
+    #[test]
+    fn test_drop_without_reset_is_an_adjustment() {
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let resets_at = now + Duration::days(3);
+        let before = snapshot(0.80, resets_at);
+
+        assert_eq!(
+            detect_quota_adjustments(&before, &snapshot(0.40, resets_at), now),
+            [QuotaAdjustment {
+                window: WindowSlot::Secondary,
+                from: 0.80,
+                to: 0.40,
+            }]
+        );
+        // A reset time a few seconds off is still the same window.
+        let jittered = snapshot(0.40, resets_at + Duration::seconds(2));
+        assert_eq!(detect_quota_adjustments(&before, &jittered, now).len(), 1);
+    }
+
+    #[test]
+    fn test_reset_and_noise_are_not_adjustments() {
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let resets_at = now + Duration::days(3);
+        let before = snapshot(0.80, resets_at);
+        let detect = |after: &UsageSnapshot| detect_quota_adjustments(&before, after, now);
+
+        // The window rolled over: a new reset time.
+        assert!(detect(&snapshot(0.05, resets_at + Duration::days(7))).is_empty());
+        // Usage wobbling by a couple of points.
+        assert!(detect(&snapshot(0.78, resets_at)).is_empty());
+        assert!(detect(&snapshot(0.82, resets_at)).is_empty());
+
+        // The old reset time passed before the new one was reported.
+        let after_reset = resets_at + Duration::minutes(1);
+        assert!(
+            detect_quota_adjustments(&before, &snapshot(0.05, resets_at), after_reset).is_empty()
+        );
+
+        // An estimated reset time can't tell a rollover apart.
+        let mut estimated = snapshot(0.40, resets_at);
+        estimated.secondary.as_mut().unwrap().resets_at_estimated = true;
+        assert!(detect(&estimated).is_empty());
+        let mut no_reset = snapshot(0.40, resets_at);
+        no_reset.secondary.as_mut().unwrap().resets_at = None;
+        assert!(detect(&no_reset).is_empty());
+    }
+}
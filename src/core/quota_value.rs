@@ -0,0 +1,132 @@
+use crate::core::models::{DailyCost, RateWindow};
+use chrono::{Duration, Local, NaiveDate};
+use std::collections::HashMap;
+
+/// Below this usage the implied capacity swings wildly with every request.
+pub const MIN_USED_PERCENT: f64 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuotaValueEstimate {
+    pub remaining_tokens: u64,
+    pub remaining_cost_usd: f64,
+}
+
+/// Extrapolates the tokens left in a window from the tokens spent so far and
+/// the provider-reported usage, then prices them at `usd_per_token`.
+pub fn estimate_remaining_value(
+    tokens_so_far: u64,
+    used_percent: f64,
+    usd_per_token: f64,
+) -> Option<QuotaValueEstimate> {
+    if tokens_so_far == 0 || !(MIN_USED_PERCENT..1.0).contains(&used_percent) {
+        return None;
+    }
+    if !usd_per_token.is_finite() || usd_per_token < 0.0 {
+        return None;
+    }
+
+    let remaining_percent = 1.0 - used_percent;
+    let remaining_tokens = tokens_so_far as f64 / used_percent * remaining_percent;
+    Some(QuotaValueEstimate {
+        remaining_tokens: remaining_tokens.round() as u64,
+        remaining_cost_usd: remaining_tokens * usd_per_token,
+    })
+}
+
+/// Sums the tokens logged since `window_start` and returns them with the
+/// per-token price of the model that cost the most over that span.
+pub fn window_usage(breakdown: &[DailyCost], window_start: NaiveDate) -> Option<(u64, f64)> {
+    let mut by_model: HashMap<&str, (u64, f64)> = HashMap::new();
+    for row in breakdown.iter().filter(|row| row.date >= window_start) {
+        let entry = by_model.entry(row.model.as_str()).or_insert((0, 0.0));
+        entry.0 += row.tokens;
+        entry.1 += row.cost;
+    }
+
+    let total_tokens: u64 = by_model.values().map(|(tokens, _)| tokens).sum();
+    let (dominant_tokens, dominant_cost) = by_model
+        .values()
+        .filter(|(tokens, _)| *tokens > 0)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .copied()?;
+
+    Some((total_tokens, dominant_cost / dominant_tokens as f64))
+}
+
+/// Estimate for a usage window, using local log days from the window start.
+/// Days are the finest granularity in the cost cache, so the first day of the
+/// window counts in full.
+pub fn estimate_for_window(
+    window: &RateWindow,
+    breakdown: &[DailyCost],
+) -> Option<QuotaValueEstimate> {
+    let window_start = window_start_day(window)?;
+    let (tokens, usd_per_token) = window_usage(breakdown, window_start)?;
+    estimate_remaining_value(tokens, window.used_percent, usd_per_token)
+}
+
+/// The local day a window started on, worked back from its reset time.
+pub fn window_start_day(window: &RateWindow) -> Option<NaiveDate> {
+    let resets_at = window.resets_at?;
+    let minutes = window.window_minutes.filter(|m| *m > 0)?;
+    Some(
+        (resets_at - Duration::minutes(i64::from(minutes)))
+            .with_timezone(&Local)
+            .date_naive(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(day: u32, model: &str, tokens: u64, cost: f64) -> DailyCost {
+        DailyCost {
+            date: NaiveDate::from_ymd_opt(2026, 1, day).unwrap(),
+            model: model.to_string(),
+            cost,
+            tokens,
+        }
+    }
+
+    #[test]
+    fn test_estimate_remaining_value() {
+        let estimate = estimate_remaining_value(2_000_000, 0.25, 0.000_003).unwrap();
+        assert_eq!(estimate.remaining_tokens, 6_000_000);
+        assert!((estimate.remaining_cost_usd - 18.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_estimate_skips_noisy_or_exhausted_windows() {
+        assert!(estimate_remaining_value(10_000, 0.049, 0.000_003).is_none());
+        assert!(estimate_remaining_value(10_000, 0.0, 0.000_003).is_none());
+        assert!(estimate_remaining_value(10_000, 1.0, 0.000_003).is_none());
+        assert!(estimate_remaining_value(0, 0.5, 0.000_003).is_none());
+        assert!(estimate_remaining_value(10_000, 0.5, f64::NAN).is_none());
+        assert!(estimate_remaining_value(10_000, 0.05, 0.000_003).is_some());
+    }
+
+    #[test]
+    fn test_window_usage_prices_by_dominant_model() {
+        let breakdown = vec![
+            row(1, "claude-opus-4", 1_000_000, 50.0),
+            row(10, "claude-sonnet-4", 3_000_000, 9.0),
+            row(11, "claude-opus-4", 500_000, 7.5),
+            row(11, "claude-haiku-4", 1_000_000, 1.0),
+        ];
+        let start = NaiveDate::from_ymd_opt(2026, 1, 10).unwrap();
+
+        let (tokens, usd_per_token) = window_usage(&breakdown, start).unwrap();
+        assert_eq!(tokens, 4_500_000);
+        // Sonnet has the highest spend inside the window: $9 over 3M tokens.
+        assert!((usd_per_token - 0.000_003).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_window_usage_without_token_counts() {
+        let breakdown = vec![row(10, "claude-sonnet-4", 0, 9.0)];
+        let start = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        assert!(window_usage(&breakdown, start).is_none());
+        assert!(window_usage(&[], start).is_none());
+    }
+}
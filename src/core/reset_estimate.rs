@@ -0,0 +1,163 @@
+use crate::core::models::RateWindow;
+use chrono::{DateTime, Duration, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// One of a snapshot's windows, e.g. the one a `RolloverTracker` follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum WindowSlot {
+    #[serde(rename = "session")]
+    Primary,
+    #[serde(rename = "weekly")]
+    Secondary,
+    #[serde(rename = "tertiary")]
+    Tertiary,
+}
+
+impl WindowSlot {
+    pub fn label(self) -> &'static str {
+        match self {
+            WindowSlot::Primary => "session",
+            WindowSlot::Secondary => "weekly",
+            WindowSlot::Tertiary => "tertiary",
+        }
+    }
+}
+
+/// Reported reset times can move by a few seconds between fetches.
+const RESET_TOLERANCE_SECS: i64 = 60;
+
+/// Whether reset times `a` and `b`, both reported by the provider, are the
+/// same window's.
+pub fn same_window_reset(a: DateTime<Utc>, b: DateTime<Utc>) -> bool {
+    (a - b).num_seconds().abs() <= RESET_TOLERANCE_SECS
+}
+
+/// Estimates when a window resets for providers that sometimes leave out
+/// `resets_at`. A window starts with the first usage after it rolled over,
+/// which shows up as `used_percent` dropping (or sitting at zero) and then
+/// rising again; it resets `window_minutes` after that.
+#[derive(Debug, Clone, Default)]
+pub struct RolloverTracker {
+    last_used: Option<f64>,
+    /// A rollover was seen and the new window hasn't been used yet.
+    awaiting_start: bool,
+    started_at: Option<DateTime<Utc>>,
+}
+
+impl RolloverTracker {
+    /// Records the window as of `at` and, when it has no reset time but a
+    /// length, fills in `resets_at` from the observed start.
+    pub fn apply(&mut self, at: DateTime<Utc>, window: &mut RateWindow) {
+        let used = window.used_percent;
+        if self.last_used.is_some_and(|last| used < last) {
+            self.awaiting_start = true;
+            self.started_at = None;
+        }
+        if used <= 0.0 {
+            self.awaiting_start = true;
+            self.started_at = None;
+        } else if self.awaiting_start {
+            self.awaiting_start = false;
+            self.started_at = Some(at);
+        }
+        self.last_used = Some(used);
+
+        if window.resets_at.is_some() {
+            return;
+        }
+        let (Some(started_at), Some(minutes)) = (self.started_at, window.window_minutes) else {
+            return;
+        };
+        let resets_at = started_at + Duration::minutes(minutes.into());
+        // Past it without seeing a drop: the estimate is stale.
+        if resets_at > at {
+            window.resets_at = Some(resets_at);
+            window.resets_at_estimated = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(used_percent: f64) -> RateWindow {
+        RateWindow::from_reported(used_percent, Some(300), None, None)
+    }
+
+    fn minutes(start: DateTime<Utc>, minutes: i64) -> DateTime<Utc> {
+        start + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn test_estimate_starts_after_rollover() {
+        let start: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let mut tracker = RolloverTracker::default();
+        let mut observe = |minute: i64, used: f64| {
+            let mut window = window(used);
+            tracker.apply(minutes(start, minute), &mut window);
+            window
+        };
+
+        // Mid-window when the daemon started: no way to tell when it began.
+        assert_eq!(observe(0, 0.40).resets_at, None);
+        assert_eq!(observe(10, 0.45).resets_at, None);
+
+        // Rolled over, idle until minute 30, then used again.
+        assert_eq!(observe(20, 0.0).resets_at, None);
+        let first_use = observe(30, 0.02);
+        assert_eq!(first_use.resets_at, Some(minutes(start, 30 + 300)));
+        assert!(first_use.resets_at_estimated);
+        assert_eq!(observe(40, 0.05).resets_at, Some(minutes(start, 330)));
+
+        // The next rollover is seen already in use.
+        let rolled = observe(340, 0.01);
+        assert_eq!(rolled.resets_at, Some(minutes(start, 340 + 300)));
+    }
+
+    #[test]
+    fn test_reported_reset_wins_and_stale_estimate_is_dropped() {
+        let start: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let mut tracker = RolloverTracker::default();
+
+        tracker.apply(start, &mut window(0.0));
+        let reported_at = minutes(start, 200);
+        let mut reported = RateWindow {
+            resets_at: Some(reported_at),
+            ..window(0.10)
+        };
+        tracker.apply(minutes(start, 5), &mut reported);
+        assert_eq!(reported.resets_at, Some(reported_at));
+        assert!(!reported.resets_at_estimated);
+
+        let mut missing = window(0.20);
+        tracker.apply(minutes(start, 10), &mut missing);
+        assert_eq!(missing.resets_at, Some(minutes(start, 305)));
+
+        // Still no drop an hour after the estimated reset.
+        let mut late = window(0.20);
+        tracker.apply(minutes(start, 365), &mut late);
+        assert_eq!(late.resets_at, None);
+        assert!(!late.resets_at_estimated);
+
+        let mut no_length = RateWindow {
+            window_minutes: None,
+            ..window(0.0)
+        };
+        tracker.apply(minutes(start, 370), &mut no_length);
+        no_length.used_percent = 0.1;
+        tracker.apply(minutes(start, 375), &mut no_length);
+        assert_eq!(no_length.resets_at, None);
+    }
+
+    #[test]
+    fn test_same_window_reset_allows_jitter() {
+        let resets_at = Utc::now();
+        let moved = |secs| resets_at + Duration::seconds(secs);
+        assert!(same_window_reset(resets_at, moved(2)));
+        assert!(same_window_reset(moved(60), resets_at));
+        assert!(!same_window_reset(resets_at, moved(61)));
+        assert!(!same_window_reset(moved(-5 * 3600), resets_at));
+    }
+}
@@ -7,12 +7,21 @@ const BACKOFF_FACTOR: u32 = 2;
 #[derive(Debug, Clone)]
 pub struct RetryState {
     consecutive_failures: u32,
+    base_delay: Duration,
+    max_delay: Duration,
 }
 
 impl RetryState {
     pub fn new() -> Self {
+        Self::with_delays(BASE_DELAY, MAX_DELAY)
+    }
+
+    /// Backoff that starts at `base_delay` and doubles up to `max_delay`.
+    pub fn with_delays(base_delay: Duration, max_delay: Duration) -> Self {
         Self {
             consecutive_failures: 0,
+            base_delay,
+            max_delay,
         }
     }
 
@@ -26,13 +35,13 @@ impl RetryState {
 
     pub fn current_delay(&self) -> Duration {
         if self.consecutive_failures == 0 {
-            return BASE_DELAY;
+            return self.base_delay;
         }
 
         let factor = BACKOFF_FACTOR.saturating_pow(self.consecutive_failures - 1);
-        let delay_secs = BASE_DELAY.as_secs().saturating_mul(factor as u64);
+        let delay_secs = self.base_delay.as_secs().saturating_mul(factor as u64);
 
-        Duration::from_secs(delay_secs).min(MAX_DELAY)
+        Duration::from_secs(delay_secs).min(self.max_delay)
     }
 
     pub fn consecutive_failures(&self) -> u32 {
@@ -106,6 +115,22 @@ mod tests {
         assert!(!state.is_in_backoff());
     }
 
+    #[test]
+    fn test_custom_delays() {
+        let mut state = RetryState::with_delays(Duration::from_secs(2), Duration::from_secs(120));
+
+        let schedule: Vec<u64> = (0..9)
+            .map(|_| {
+                state.record_failure();
+                state.current_delay().as_secs()
+            })
+            .collect();
+        assert_eq!(schedule, vec![2, 4, 8, 16, 32, 64, 120, 120, 120]);
+
+        state.record_success();
+        assert_eq!(state.current_delay(), Duration::from_secs(2));
+    }
+
     #[test]
     fn test_failure_count_saturates() {
         let mut state = RetryState::new();
@@ -0,0 +1,50 @@
+//! Versioning for the JSON scripts read: `status --json`, `cost --json`,
+//! `status --watch --output json-lines`, the limits file and the daemon's
+//! D-Bus replies.
+
+use schemars::generate::SchemaSettings;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Every JSON output carries this as `schema_version`. Bumped only when a
+/// field is renamed, removed or changes meaning; new fields keep it.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The JSON a D-Bus method replies with: its payload under `data`, so a
+/// client can check `schema_version` before reading it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Versioned<T> {
+    pub schema_version: u32,
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    pub fn new(data: T) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            data,
+        }
+    }
+}
+
+/// The JSON Schema of `T` as it serializes, for the `--schema` flags.
+pub fn json_schema<T: JsonSchema>() -> serde_json::Result<String> {
+    let schema = SchemaSettings::draft2020_12()
+        .for_serialize()
+        .into_generator()
+        .into_root_schema_for::<T>();
+    serde_json::to_string_pretty(&schema)
+}
+
+/// Fails when the schema of `T` no longer matches the snapshot in
+/// `tests/fixtures/schema`, so output changes are made on purpose.
+#[cfg(test)]
+pub fn assert_schema_snapshot<T: JsonSchema>(name: &str, snapshot: &str) {
+    let schema = json_schema::<T>().unwrap();
+    assert!(
+        schema.trim_end() == snapshot.trim_end(),
+        "The {name} JSON schema changed. Bump SCHEMA_VERSION if existing readers \
+         break (a field renamed, removed or retyped), then update \
+         tests/fixtures/schema/{name}.json. Current schema:\n{schema}"
+    );
+}
@@ -1,9 +1,32 @@
+use crate::core::config_layers::{self, expand_path};
+use crate::core::models::Provider;
 use anyhow::{Context, Result};
 use notify::{Config, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
 use tokio::sync::{broadcast, mpsc, RwLock};
+use toml_edit::{DocumentMut, Item, Table, TableLike};
+
+/// Bumped when the config layout changes incompatibly. Saves refuse to
+/// overwrite a config stamped with a newer version than this.
+pub const CONFIG_VERSION: i64 = 1;
+const CONFIG_VERSION_KEY: &str = "config_version";
+
+/// Read when `--config` isn't given.
+pub const CONFIG_ENV: &str = "CLAUDE_BAR_CONFIG";
+
+/// The config file this process reads and saves, once resolved.
+static CONFIG_PATH: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+/// Hash of the last config content this process wrote.
+static LAST_OWN_WRITE: Mutex<Option<u64>> = Mutex::new(None);
+/// Delivers settings saved by this process to the running watcher.
+static OWN_SAVES: OnceLock<mpsc::UnboundedSender<Settings>> = OnceLock::new();
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
@@ -11,47 +34,279 @@ pub struct Settings {
     pub providers: ProviderSettings,
     pub display: DisplaySettings,
     pub browser: BrowserSettings,
+    pub terminal: TerminalSettings,
     pub notifications: NotificationSettings,
     pub theme: ThemeSettings,
     pub shortcuts: ShortcutSettings,
     pub popup: PopupSettings,
+    pub cost: CostSettings,
+    pub daemon: DaemonSettings,
+    pub limits_file: LimitsFileSettings,
+    pub webhook: WebhookSettings,
     pub debug: bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ProviderSettings {
-    pub claude: ProviderConfig,
-    pub codex: ProviderConfig,
+    pub claude: ClaudeConfig,
+    pub codex: CodexConfig,
     pub merge_icons: bool,
 }
 
-impl Default for ProviderSettings {
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClaudeConfig {
+    pub enabled: bool,
+    /// `anthropic-beta` header value to try before the built-in ones.
+    pub beta_header: Option<String>,
+}
+
+impl Default for ClaudeConfig {
     fn default() -> Self {
         Self {
-            claude: ProviderConfig { enabled: true },
-            codex: ProviderConfig { enabled: true },
-            merge_icons: false,
+            enabled: true,
+            beta_header: None,
         }
     }
 }
 
+const MAX_CODEX_WORKSPACES: usize = 16;
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
-pub struct ProviderConfig {
+pub struct CodexConfig {
     pub enabled: bool,
+    pub account_id: Option<String>,
+    pub workspaces: Vec<CodexWorkspace>,
 }
 
-impl Default for ProviderConfig {
+impl Default for CodexConfig {
     fn default() -> Self {
-        Self { enabled: true }
+        Self {
+            enabled: true,
+            account_id: None,
+            workspaces: Vec::new(),
+        }
     }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CodexWorkspace {
+    pub account_id: String,
+    pub label: String,
+}
+
+impl CodexConfig {
+    /// Codex instances to monitor. The first workspace keeps the plain `Codex`
+    /// identity; each additional one gets its own `CodexWorkspace` index.
+    pub fn instances(&self) -> Vec<Provider> {
+        if self.workspaces.is_empty() {
+            return vec![Provider::Codex];
+        }
+        (0..self.workspaces.len().min(MAX_CODEX_WORKSPACES))
+            .map(|index| match index {
+                0 => Provider::Codex,
+                n => Provider::CodexWorkspace(n as u8),
+            })
+            .collect()
+    }
+
+    fn workspace(&self, provider: Provider) -> Option<&CodexWorkspace> {
+        match provider {
+            Provider::Codex => self.workspaces.first(),
+            Provider::CodexWorkspace(index) => self.workspaces.get(usize::from(index)),
+            Provider::Claude => None,
+        }
+    }
+
+    pub fn account_id_for(&self, provider: Provider) -> Option<String> {
+        self.workspace(provider)
+            .map(|w| w.account_id.clone())
+            .or_else(|| self.account_id.clone())
+    }
+
+    pub fn label_for(&self, provider: Provider) -> Option<String> {
+        self.workspace(provider).map(|w| w.label.clone())
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.account_id.as_deref().is_some_and(|id| id.trim().is_empty()) {
+            anyhow::bail!("providers.codex.account_id must not be empty");
+        }
+        if self.workspaces.len() > MAX_CODEX_WORKSPACES {
+            anyhow::bail!(
+                "providers.codex.workspaces supports at most {} entries, got {}",
+                MAX_CODEX_WORKSPACES,
+                self.workspaces.len()
+            );
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for workspace in &self.workspaces {
+            if workspace.account_id.trim().is_empty() || workspace.label.trim().is_empty() {
+                anyhow::bail!("providers.codex.workspaces entries need an account_id and a label");
+            }
+            if !seen.insert(workspace.account_id.as_str()) {
+                anyhow::bail!(
+                    "providers.codex.workspaces lists account_id {} more than once",
+                    workspace.account_id
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The settings `Settings::set_runtime` can change while the daemon runs.
+pub const RUNTIME_SETTINGS: &[&str] = &[
+    "display.show_as_remaining",
+    "theme.mode",
+    "providers.merge_icons",
+    "popup.dismiss_timeout_ms",
+];
+
+pub const TOOLTIP_PLACEHOLDERS: &[&str] = &[
+    "provider",
+    "session",
+    "weekly",
+    "reset_session",
+    "reset_weekly",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TrayTitleMode {
+    #[default]
+    Name,
+    Percent,
+    Both,
+    None,
+}
+
+/// How tray icons are drawn. Some panels show named icons from the icon
+/// theme but not the pixmaps the daemon renders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum IconMode {
+    /// Bars drawn to scale with the usage.
+    #[default]
+    Pixmap,
+    /// The normal, warning or error icon installed by `install-icons`; the
+    /// percentages are only in the title and tooltip.
+    Named,
+}
+
+/// How reset times are written in the popup and `status` text output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ResetTimeFormat {
+    /// "in 2h 14m"
+    #[default]
+    Relative,
+    /// "17:42", "tomorrow 09:00" or "Tue 09:00"
+    Absolute,
+    /// "in 2h 14m (17:42)"
+    Both,
+}
+
+impl ResetTimeFormat {
+    /// Joins a countdown ("2h 14m") and the local reset time ("17:42").
+    pub fn render(self, countdown: &str, clock: &str) -> String {
+        match self {
+            Self::Relative => format!("in {}", countdown),
+            Self::Absolute => clock.to_string(),
+            Self::Both => format!("in {} ({})", countdown, clock),
+        }
+    }
+}
+
+/// Whether the tray loading animation and popup transitions run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationMode {
+    /// Follow the desktop's enable-animations preference.
+    #[default]
+    Auto,
+    On,
+    Off,
+}
+
+impl AnimationMode {
+    /// `system_enabled` is GTK's `gtk-enable-animations`, which mirrors
+    /// `org.gnome.desktop.interface enable-animations`.
+    pub fn enabled(self, system_enabled: bool) -> bool {
+        match self {
+            Self::Auto => system_enabled,
+            Self::On => true,
+            Self::Off => false,
+        }
+    }
+}
+
+/// Usage windows the two tray icon bars can represent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayWindow {
+    Session,
+    Weekly,
+    Opus,
+    Sonnet,
+    MaxOfAll,
+}
+
+impl TrayWindow {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Session => "Session",
+            Self::Weekly => "Weekly",
+            Self::Opus => "Opus",
+            Self::Sonnet => "Sonnet",
+            Self::MaxOfAll => "Highest",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct DisplaySettings {
     pub show_as_remaining: bool,
+    pub show_value_estimate: bool,
+    /// Hides dollar amounts in the popup, e.g. while screen sharing.
+    pub hide_costs: bool,
+    pub tray_title: TrayTitleMode,
+    pub tray_windows: [TrayWindow; 2],
+    pub tooltip_template: String,
+    /// Orders popup tabs by how close each provider is to its limits.
+    pub sort_providers_by_urgency: bool,
+    pub animations: AnimationMode,
+    pub reset_time_format: ResetTimeFormat,
+    /// Seconds between switching the bottom tray bar to the next window
+    /// when a provider has more than two; 0 keeps it on `tray_windows[1]`.
+    pub icon_cycle_secs: u64,
+    /// Red-green safe colors for warnings and pace, pace markers drawn as
+    /// shapes, and a divider between the tray icon's bars.
+    pub colorblind_mode: bool,
+    pub icon_mode: IconMode,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            show_as_remaining: false,
+            show_value_estimate: false,
+            hide_costs: false,
+            tray_title: TrayTitleMode::Name,
+            tray_windows: [TrayWindow::Session, TrayWindow::Weekly],
+            tooltip_template: "Session: {session} used | Weekly: {weekly} used".to_string(),
+            sort_providers_by_urgency: false,
+            animations: AnimationMode::Auto,
+            reset_time_format: ResetTimeFormat::Relative,
+            icon_cycle_secs: 0,
+            colorblind_mode: false,
+            icon_mode: IconMode::Pixmap,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
@@ -60,11 +315,25 @@ pub struct BrowserSettings {
     pub preferred: Option<String>,
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalSettings {
+    /// Terminal for the popup's "Run in terminal" button, e.g. "wezterm".
+    /// Detected when unset.
+    pub preferred: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct NotificationSettings {
     pub enabled: bool,
     pub threshold: f64,
+    /// Consult the provider's status page while it is failing.
+    pub incident_check: bool,
+    /// Sound played with the usage threshold notification.
+    pub sound: AlertSound,
+    /// Recap last week's usage when the weekly window rolls over.
+    pub weekly_summary: bool,
 }
 
 impl Default for NotificationSettings {
@@ -72,10 +341,34 @@ impl Default for NotificationSettings {
         Self {
             enabled: true,
             threshold: 0.9,
+            incident_check: true,
+            sound: AlertSound::default(),
+            weekly_summary: false,
         }
     }
 }
 
+/// `true` plays the desktop's warning sound; a string is the path of a sound
+/// file to play instead, with a leading `~/` expanded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum AlertSound {
+    Enabled(bool),
+    File(String),
+}
+
+impl Default for AlertSound {
+    fn default() -> Self {
+        Self::Enabled(false)
+    }
+}
+
+impl AlertSound {
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::Enabled(false)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum ThemeMode {
@@ -117,6 +410,17 @@ pub enum PopupAnchor {
     BottomRight,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PopupLayout {
+    /// One provider at a time, switched with the tabs or Tab key.
+    #[default]
+    Tabs,
+    /// Every provider in one scrollable page, sharing one Cost section and
+    /// footer.
+    Stacked,
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct PopupSettings {
@@ -126,6 +430,11 @@ pub struct PopupSettings {
     pub margin_bottom: i32,
     pub margin_left: i32,
     pub dismiss_timeout_ms: u64,
+    /// Popup width in pixels, 260 to 600.
+    pub width: i32,
+    /// Multiplier for the popup's font sizes, 0.8 to 1.5.
+    pub font_scale: f64,
+    pub layout: PopupLayout,
 }
 
 impl Default for PopupSettings {
@@ -137,31 +446,300 @@ impl Default for PopupSettings {
             margin_bottom: 0,
             margin_left: 0,
             dismiss_timeout_ms: 300,
+            width: 350,
+            font_scale: 1.0,
+            layout: PopupLayout::Tabs,
+        }
+    }
+}
+
+impl PopupSettings {
+    const WIDTH_RANGE: (i32, i32) = (260, 600);
+    const FONT_SCALE_RANGE: (f64, f64) = (0.8, 1.5);
+
+    /// `width`, clamped into range with a warning when it is out of it.
+    pub fn clamped_width(&self) -> i32 {
+        let (min, max) = Self::WIDTH_RANGE;
+        let width = self.width.clamp(min, max);
+        if width != self.width {
+            tracing::warn!(
+                width = self.width,
+                min,
+                max,
+                "popup.width out of range; clamping"
+            );
         }
+        width
+    }
+
+    /// `font_scale`, clamped into range with a warning when it is out of it.
+    pub fn clamped_font_scale(&self) -> f64 {
+        let (min, max) = Self::FONT_SCALE_RANGE;
+        let scale = if self.font_scale.is_nan() {
+            1.0
+        } else {
+            self.font_scale.clamp(min, max)
+        };
+        if scale != self.font_scale {
+            tracing::warn!(
+                font_scale = self.font_scale,
+                min,
+                max,
+                "popup.font_scale out of range; clamping"
+            );
+        }
+        scale
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CostSettings {
+    /// Scan local JSONL logs for cost and token totals. Disabling also skips
+    /// the pricing refresh and hides the popup's Cost section.
+    pub enabled: bool,
+    /// Calendar the logs are bucketed into days by, for "Today" and the
+    /// monthly and 30-day totals.
+    pub timezone: CostTimezone,
+    /// Monthly spend limits in USD, keyed by project (the name of the
+    /// directory the agent ran in). Crossing 80% and 100% notifies once each
+    /// per month.
+    pub budgets: BTreeMap<String, f64>,
+    /// Monthly limit in USD for Claude and Codex spend combined. Shown in
+    /// the popup and tray tooltip, and notifies at 80% and 100%.
+    pub monthly_budget: Option<f64>,
+    /// Also count usage from Claude Desktop's logs under
+    /// `~/.config/Claude/logs`, skipping messages Claude Code logged too.
+    pub include_claude_desktop: bool,
+    /// Days of logs each scan reads, ending today. The scan always reaches
+    /// back to the first of the month; fewer than 30 days shortens the
+    /// 30-day totals and the heatmap.
+    pub scan_days: u32,
+    /// Globs over project directories under `~/.claude/projects` (e.g.
+    /// `"-home-me-src-*"`); when set, only matching projects are scanned.
+    pub include_projects: Vec<String>,
+    /// Globs over project directories under `~/.claude/projects` (e.g.
+    /// `"*-scratch-*"`, `"archive/**"`) whose logs are never read.
+    pub exclude_projects: Vec<String>,
+}
+
+/// Default `cost.scan_days`: the 30-day totals plus a few days of slack.
+pub const DEFAULT_SCAN_DAYS: u32 = 35;
+
+impl Default for CostSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            timezone: CostTimezone::default(),
+            budgets: BTreeMap::new(),
+            monthly_budget: None,
+            include_claude_desktop: false,
+            scan_days: DEFAULT_SCAN_DAYS,
+            include_projects: Vec::new(),
+            exclude_projects: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CostTimezone {
+    #[default]
+    Local,
+    Utc,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaemonSettings {
+    /// Appended to the D-Bus name, GTK application id and tray icon ids so
+    /// several daemons can share a session. Read once at startup.
+    pub instance_suffix: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsFileSettings {
+    /// Write every provider's used percentages and reset times to a JSON
+    /// file on each usage update, for hooks and shell prompts to read.
+    pub enabled: bool,
+    /// Defaults to `~/.local/share/claude-bar/limits.json`. A leading `~/`
+    /// is expanded.
+    pub path: Option<String>,
+}
+
+/// Shortest `webhook.interval_secs` allowed.
+pub const MIN_WEBHOOK_INTERVAL_SECS: u64 = 300;
+
+/// Stands in for the webhook URL and header values in redacted settings.
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WebhookSettings {
+    /// POST the `status --json` document here after usage updates. Nothing
+    /// is sent while this is unset.
+    pub url: Option<String>,
+    /// Least time between posts, at least five minutes.
+    pub interval_secs: u64,
+    /// Also send each provider's cost scan.
+    pub include_cost: bool,
+    /// Extra request headers, e.g. `Authorization`.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl Default for WebhookSettings {
+    fn default() -> Self {
+        Self {
+            url: None,
+            interval_secs: MIN_WEBHOOK_INTERVAL_SECS,
+            include_cost: false,
+            headers: BTreeMap::new(),
+        }
+    }
+}
+
+impl WebhookSettings {
+    /// Without the URL and header values, which often carry credentials.
+    /// Header names stay so callers can see which are set.
+    pub fn redacted(&self) -> Self {
+        Self {
+            url: self.url.as_ref().map(|_| REDACTED.to_string()),
+            headers: self
+                .headers
+                .keys()
+                .map(|name| (name.clone(), REDACTED.to_string()))
+                .collect(),
+            ..self.clone()
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if let Some(url) = &self.url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                anyhow::bail!("webhook.url must be an http or https URL, got {:?}", url);
+            }
+        }
+        if self.interval_secs < MIN_WEBHOOK_INTERVAL_SECS {
+            anyhow::bail!(
+                "webhook.interval_secs must be at least {}, got {}",
+                MIN_WEBHOOK_INTERVAL_SECS,
+                self.interval_secs
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A config file that can't be written, e.g. one in the Nix store.
+#[derive(Debug, thiserror::Error)]
+#[error("{} is read-only; change it where it is managed", path.display())]
+pub struct ReadOnlyConfig {
+    pub path: PathBuf,
+}
+
 impl Settings {
+    /// These settings safe to hand to other processes, with the webhook's
+    /// secrets replaced by `REDACTED`.
+    pub fn redacted(&self) -> Self {
+        Self {
+            webhook: self.webhook.redacted(),
+            ..self.clone()
+        }
+    }
+
+    /// Uses `flag`, then `CLAUDE_BAR_CONFIG`, as the config file instead of
+    /// `~/.config/claude-bar/config.toml`. Must run before anything loads
+    /// settings.
+    pub fn select_config_path(flag: Option<PathBuf>) -> Result<()> {
+        let path = resolve_config_path(
+            flag,
+            std::env::var_os(CONFIG_ENV),
+            Self::default_config_path(),
+        );
+        if CONFIG_PATH.set(path).is_err() {
+            anyhow::bail!("Config path was already selected");
+        }
+        Ok(())
+    }
+
     pub fn config_path() -> Option<PathBuf> {
+        CONFIG_PATH
+            .get_or_init(|| {
+                resolve_config_path(
+                    None,
+                    std::env::var_os(CONFIG_ENV),
+                    Self::default_config_path(),
+                )
+            })
+            .clone()
+    }
+
+    /// The config file given with `--config` or `CLAUDE_BAR_CONFIG`, if any.
+    pub fn config_path_override() -> Option<PathBuf> {
+        Self::config_path().filter(|path| Some(path) != Self::default_config_path().as_ref())
+    }
+
+    fn default_config_path() -> Option<PathBuf> {
         dirs::config_dir().map(|p| p.join("claude-bar").join("config.toml"))
     }
 
     pub fn load() -> Result<Self> {
+        Ok(Self::load_with_includes()?.0)
+    }
+
+    /// Loads the config along with the files it includes, which may not
+    /// exist.
+    fn load_with_includes() -> Result<(Self, Vec<PathBuf>)> {
         let path = Self::config_path().context("Could not determine config directory")?;
 
         if !path.exists() {
             tracing::info!(?path, "Config file not found, using defaults");
-            return Ok(Self::default());
+            return Ok((Self::default(), Vec::new()));
         }
 
         let content = std::fs::read_to_string(&path)
             .with_context(|| format!("Failed to read config file: {}", path.display()))?;
-
-        let settings: Settings = toml::from_str(&content)
+        let loaded = Self::parse_layered(&content, &path)
             .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
 
-        tracing::info!(?path, "Loaded config");
-        Ok(settings)
+        tracing::info!(?path, includes = ?loaded.1, "Loaded config");
+        Ok(loaded)
+    }
+
+    /// `content` is the config file at `path`.
+    fn parse(content: &str, path: &Path) -> Result<Self> {
+        Ok(Self::parse_layered(content, path)?.0)
+    }
+
+    /// Parses the config file at `path` with its includes merged over it
+    /// and path settings expanded. Also returns the included files.
+    fn parse_layered(content: &str, path: &Path) -> Result<(Self, Vec<PathBuf>)> {
+        let document: DocumentMut = content.parse()?;
+        if let Some(version) = document.get(CONFIG_VERSION_KEY).and_then(Item::as_integer) {
+            if version > CONFIG_VERSION {
+                tracing::warn!(
+                    version,
+                    supported = CONFIG_VERSION,
+                    "Config was written by a newer claude-bar; unknown settings are ignored"
+                );
+            }
+        }
+        let layers = config_layers::read_layers(content, path)?;
+        let mut settings: Self = toml::Value::Table(layers.table).try_into()?;
+        settings.expand_paths()?;
+        Ok((settings, layers.includes))
+    }
+
+    /// Expands `~` and `$VAR` in the settings that hold a path.
+    fn expand_paths(&mut self) -> Result<()> {
+        if let Some(path) = &mut self.limits_file.path {
+            *path = expand_path(path, "limits_file.path")?;
+        }
+        if let AlertSound::File(path) = &mut self.notifications.sound {
+            *path = expand_path(path, "notifications.sound")?;
+        }
+        Ok(())
     }
 
     pub fn validate(&self) -> Result<()> {
@@ -171,31 +749,344 @@ impl Settings {
                 self.notifications.threshold
             );
         }
+        validate_tooltip_template(&self.display.tooltip_template)?;
+        self.providers.codex.validate()?;
+        for (project, budget) in &self.cost.budgets {
+            if budget.is_nan() || *budget < 0.0 {
+                anyhow::bail!(
+                    "cost.budgets.{:?} must not be negative, got {}",
+                    project,
+                    budget
+                );
+            }
+        }
+        if let Some(budget) = self.cost.monthly_budget {
+            if budget.is_nan() || budget <= 0.0 {
+                anyhow::bail!("cost.monthly_budget must be positive, got {}", budget);
+            }
+        }
+        if self.cost.scan_days == 0 {
+            anyhow::bail!("cost.scan_days must be at least 1");
+        }
+        for (key, patterns) in [
+            ("include_projects", &self.cost.include_projects),
+            ("exclude_projects", &self.cost.exclude_projects),
+        ] {
+            for pattern in patterns {
+                if let Err(e) = globset::Glob::new(pattern) {
+                    anyhow::bail!("cost.{}: invalid pattern {:?}: {}", key, pattern, e);
+                }
+            }
+        }
+        self.webhook.validate()?;
+        Ok(())
+    }
+
+    pub fn enabled_providers(&self) -> Vec<Provider> {
+        let mut providers = Vec::new();
+        if self.providers.claude.enabled {
+            providers.push(Provider::Claude);
+        }
+        if self.providers.codex.enabled {
+            providers.extend(self.providers.codex.instances());
+        }
+        providers
+    }
+
+    pub fn provider_label(&self, provider: Provider) -> String {
+        self.providers
+            .codex
+            .label_for(provider)
+            .unwrap_or_else(|| provider.name().to_string())
+    }
+
+    /// Sets one of `RUNTIME_SETTINGS` from its string form, e.g. `true`,
+    /// `dark` or `500`. `show_as_remaining` and `merge_icons` also work
+    /// without their table.
+    pub fn set_runtime(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        match key {
+            "display.show_as_remaining" | "show_as_remaining" => {
+                self.display.show_as_remaining = parse_bool(key, value)?;
+            }
+            "providers.merge_icons" | "merge_icons" => {
+                self.providers.merge_icons = parse_bool(key, value)?;
+            }
+            "theme.mode" => {
+                self.theme.mode = match value {
+                    "system" => ThemeMode::System,
+                    "light" => ThemeMode::Light,
+                    "dark" => ThemeMode::Dark,
+                    _ => anyhow::bail!("theme.mode must be system, light or dark, got {:?}", value),
+                };
+            }
+            "popup.dismiss_timeout_ms" => {
+                self.popup.dismiss_timeout_ms = value.parse().with_context(|| {
+                    format!(
+                        "popup.dismiss_timeout_ms must be a number of milliseconds, got {:?}",
+                        value
+                    )
+                })?;
+            }
+            _ => anyhow::bail!(
+                "Unsupported setting {:?}, expected one of: {}",
+                key,
+                RUNTIME_SETTINGS.join(", ")
+            ),
+        }
         Ok(())
     }
 
     pub fn save(&self) -> Result<()> {
         let path = Self::config_path().context("Could not determine config directory")?;
+        self.save_to(&path)?;
+        if let Some(tx) = OWN_SAVES.get() {
+            let _ = tx.send(self.clone());
+        }
+        Ok(())
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        // Write through symlinks so dotfile-managed configs stay linked.
+        let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if is_read_only(&path) {
+            return Err(ReadOnlyConfig { path }.into());
+        }
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let content = toml::to_string_pretty(self).context("Failed to serialize settings")?;
-        std::fs::write(&path, content)
-            .with_context(|| format!("Failed to write config file: {}", path.display()))?;
+
+        let existing = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read config file: {}", path.display()))
+            }
+        };
+        // Compare against what the file loads as, so values from includes
+        // and paths written with `~` or `$VAR` stay as they are.
+        let on_disk = Self::parse(&existing, &path)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))?;
+        let content = self
+            .merge_into(&existing, &on_disk)
+            .with_context(|| format!("Failed to update config file: {}", path.display()))?;
+
+        remember_own_write(&content);
+        if let Err(e) = write_atomically(&path, &content) {
+            let denied = e.downcast_ref::<std::io::Error>().is_some_and(|e| {
+                matches!(
+                    e.kind(),
+                    std::io::ErrorKind::PermissionDenied | std::io::ErrorKind::ReadOnlyFilesystem
+                )
+            });
+            if denied {
+                return Err(ReadOnlyConfig { path }.into());
+            }
+            return Err(e.context(format!("Failed to write config file: {}", path.display())));
+        }
         Ok(())
     }
+
+    /// Renders these settings over an existing config file, which loads as
+    /// `on_disk`, touching only the values that changed so comments,
+    /// formatting and keys this version doesn't know about survive.
+    fn merge_into(&self, existing: &str, on_disk: &Settings) -> Result<String> {
+        let mut document: DocumentMut = existing.parse()?;
+        let version = document
+            .get(CONFIG_VERSION_KEY)
+            .and_then(Item::as_integer)
+            .unwrap_or(0);
+        if version > CONFIG_VERSION {
+            anyhow::bail!(
+                "{} is {}, but this claude-bar only understands up to {}; refusing to overwrite a config written by a newer version",
+                CONFIG_VERSION_KEY,
+                version,
+                CONFIG_VERSION
+            );
+        }
+
+        let before: DocumentMut = toml::to_string_pretty(on_disk)?.parse()?;
+        let after: DocumentMut = toml::to_string_pretty(self)?.parse()?;
+        apply_changes(document.as_table_mut(), before.as_table(), after.as_table());
+
+        match document.get_mut(CONFIG_VERSION_KEY) {
+            Some(Item::Value(current)) => {
+                let decor = current.decor().clone();
+                *current = CONFIG_VERSION.into();
+                *current.decor_mut() = decor;
+            }
+            _ => {
+                document.insert(CONFIG_VERSION_KEY, toml_edit::value(CONFIG_VERSION));
+            }
+        }
+        Ok(document.to_string())
+    }
+}
+
+/// Copies every value that differs between `before` and `after` into
+/// `target`, recursing into tables so untouched siblings keep their layout.
+fn apply_changes(target: &mut dyn TableLike, before: &dyn TableLike, after: &dyn TableLike) {
+    for (key, after_item) in after.iter() {
+        let before_item = before.get(key);
+
+        if let (Some(after_table), Some(before_table)) = (
+            after_item.as_table_like(),
+            before_item.and_then(Item::as_table_like),
+        ) {
+            match target.get_mut(key).and_then(Item::as_table_like_mut) {
+                Some(target_table) => apply_changes(target_table, before_table, after_table),
+                None => {
+                    let mut table = Table::new();
+                    table.set_implicit(true);
+                    apply_changes(&mut table, before_table, after_table);
+                    if !table.is_empty() {
+                        target.insert(key, Item::Table(table));
+                    }
+                }
+            }
+            continue;
+        }
+
+        if before_item.map(Item::to_string) == Some(after_item.to_string()) {
+            continue;
+        }
+        match (target.get_mut(key), after_item) {
+            (Some(Item::Value(current)), Item::Value(value)) => {
+                let decor = current.decor().clone();
+                *current = value.clone();
+                *current.decor_mut() = decor;
+            }
+            (Some(current), _) => *current = after_item.clone(),
+            (None, _) => {
+                target.insert(key, after_item.clone());
+            }
+        }
+    }
+
+    let removed: Vec<String> = before
+        .iter()
+        .filter(|(key, _)| !after.contains_key(key))
+        .map(|(key, _)| key.to_string())
+        .collect();
+    for key in removed {
+        target.remove(&key);
+    }
+}
+
+/// Writes to a temporary file next to `path` and renames it into place, so
+/// readers never see a half-written file.
+pub fn write_atomically(path: &Path, content: &str) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} has no file name", path.display()))?
+        .to_string_lossy();
+    let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name, std::process::id()));
+
+    let result = (|| -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&temp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        if let Ok(metadata) = std::fs::metadata(path) {
+            std::fs::set_permissions(&temp_path, metadata.permissions())?;
+        }
+        std::fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    Ok(result?)
+}
+
+/// `flag`, then a non-empty `env`, then `default`. Relative paths are taken
+/// from the working directory, so the daemon watches the right directory.
+fn resolve_config_path(
+    flag: Option<PathBuf>,
+    env: Option<std::ffi::OsString>,
+    default: Option<PathBuf>,
+) -> Option<PathBuf> {
+    let chosen = flag.or_else(|| env.filter(|env| !env.is_empty()).map(PathBuf::from));
+    match chosen {
+        Some(path) => Some(std::path::absolute(&path).unwrap_or(path)),
+        None => default,
+    }
+}
+
+/// Whether writing `path` is bound to fail: the file, or the directory a
+/// new one would go in, has no write permission at all.
+fn is_read_only(path: &Path) -> bool {
+    let target = if path.exists() {
+        path
+    } else {
+        match path.parent() {
+            Some(parent) if parent.exists() => parent,
+            _ => return false,
+        }
+    };
+    std::fs::metadata(target).is_ok_and(|metadata| metadata.permissions().readonly())
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn remember_own_write(content: &str) {
+    if let Ok(mut last) = LAST_OWN_WRITE.lock() {
+        *last = Some(content_hash(content));
+    }
+}
+
+fn is_own_write(content: &str) -> bool {
+    LAST_OWN_WRITE
+        .lock()
+        .is_ok_and(|last| *last == Some(content_hash(content)))
+}
+
+fn validate_tooltip_template(template: &str) -> Result<()> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let Some(end) = after.find('}') else {
+            anyhow::bail!(
+                "display.tooltip_template has an unclosed '{{' in {:?}",
+                template
+            );
+        };
+        let name = &after[..end];
+        if !TOOLTIP_PLACEHOLDERS.contains(&name) {
+            anyhow::bail!(
+                "display.tooltip_template has unknown placeholder {{{}}}, expected one of: {}",
+                name,
+                TOOLTIP_PLACEHOLDERS.join(", ")
+            );
+        }
+        rest = &after[end + 1..];
+    }
+    Ok(())
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => anyhow::bail!("{} must be true or false, got {:?}", key, value),
+    }
 }
 
 pub struct SettingsWatcher {
     settings: Arc<RwLock<Settings>>,
     #[allow(dead_code)]
     update_tx: broadcast::Sender<Settings>,
-    _watcher: Option<RecommendedWatcher>,
+    /// Files the config includes; changes to them reload it too.
+    includes: Arc<Mutex<Vec<PathBuf>>>,
+    _watcher: Option<Arc<Mutex<RecommendedWatcher>>>,
 }
 
 impl SettingsWatcher {
     pub fn new() -> Result<Self> {
-        let settings = Settings::load()?;
+        let (settings, includes) = Settings::load_with_includes()?;
         settings.validate()?;
 
         let (update_tx, _) = broadcast::channel(16);
@@ -204,6 +1095,7 @@ impl SettingsWatcher {
         Ok(Self {
             settings,
             update_tx,
+            includes: Arc::new(Mutex::new(includes)),
             _watcher: None,
         })
     }
@@ -222,7 +1114,14 @@ impl SettingsWatcher {
             );
             if let Some(parent) = config_path.parent() {
                 if !parent.exists() {
-                    std::fs::create_dir_all(parent)?;
+                    if let Err(e) = std::fs::create_dir_all(parent) {
+                        tracing::warn!(
+                            ?parent,
+                            error = %e,
+                            "Could not create config directory, hot-reload disabled"
+                        );
+                        return Ok(());
+                    }
                 }
             }
         }
@@ -235,7 +1134,11 @@ impl SettingsWatcher {
             .map(std::ffi::OsStr::to_os_string)
             .unwrap_or_default();
 
-        let (tx, mut rx) = mpsc::unbounded_channel::<()>();
+        // Sends whether an included file changed, as opposed to only the
+        // main config.
+        let (tx, mut rx) = mpsc::unbounded_channel::<bool>();
+        let includes = Arc::clone(&self.includes);
+        let includes_clone = Arc::clone(&self.includes);
 
         let mut watcher = RecommendedWatcher::new(
             move |res: notify::Result<notify::Event>| {
@@ -245,8 +1148,12 @@ impl SettingsWatcher {
                             .paths
                             .iter()
                             .any(|path| path.file_name() == Some(config_file_name.as_os_str()));
-                        if touches_config {
-                            let _ = tx.send(());
+                        let touches_include = {
+                            let includes = lock_includes(&includes_clone);
+                            event.paths.iter().any(|path| includes.contains(path))
+                        };
+                        if touches_config || touches_include {
+                            let _ = tx.send(touches_include);
                         }
                     }
                 }
@@ -257,39 +1164,90 @@ impl SettingsWatcher {
         let watch_path = config_path
             .parent()
             .unwrap_or_else(|| std::path::Path::new("."));
-        watcher.watch(watch_path, RecursiveMode::NonRecursive)?;
+        // A read-only directory, e.g. in the Nix store, watches fine and
+        // just never fires.
+        if let Err(e) = watcher.watch(watch_path, RecursiveMode::NonRecursive) {
+            tracing::warn!(
+                ?watch_path,
+                error = %e,
+                "Could not watch config directory, hot-reload disabled"
+            );
+            return Ok(());
+        }
+        let mut watched_dirs = HashSet::from([watch_path.to_path_buf()]);
+        watch_include_dirs(&mut watcher, &mut watched_dirs, &lock_includes(&includes));
 
         tracing::info!(?watch_path, "Started watching config directory");
+        let watcher = Arc::new(Mutex::new(watcher));
+        let watcher_clone = Arc::clone(&watcher);
 
-        tokio::spawn(async move {
-            while rx.recv().await.is_some() {
-                tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                while rx.try_recv().is_ok() {}
+        // Saves from the settings window are applied straight from memory;
+        // the file events they cause are recognised by hash and skipped.
+        let (own_tx, mut own_rx) = mpsc::unbounded_channel::<Settings>();
+        let _ = OWN_SAVES.set(own_tx);
 
-                match Settings::load() {
-                    Ok(new_settings) => {
-                        if let Err(e) = new_settings.validate() {
-                            tracing::error!(?e, "Config validation failed, keeping old settings");
-                            continue;
+        tokio::spawn(async move {
+            loop {
+                let new_settings = tokio::select! {
+                    event = rx.recv() => {
+                        let Some(mut include_changed) = event else {
+                            break;
+                        };
+                        tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+                        while let Ok(changed) = rx.try_recv() {
+                            include_changed |= changed;
                         }
 
-                        let mut current_settings = settings_clone.write().await;
-                        if *current_settings == new_settings {
-                            tracing::debug!(
-                                ?config_path_clone,
-                                "Config unchanged, skipping reload"
-                            );
+                        let content = match std::fs::read_to_string(&config_path_clone) {
+                            Ok(content) => content,
+                            Err(e) => {
+                                tracing::error!(?e, "Failed to reload config");
+                                continue;
+                            }
+                        };
+                        if !include_changed && is_own_write(&content) {
+                            tracing::debug!(?config_path_clone, "Ignoring our own config write");
                             continue;
                         }
-
-                        tracing::info!(?config_path_clone, "Config reloaded");
-                        *current_settings = new_settings.clone();
-                        let _ = update_tx_clone.send(new_settings);
+                        match Settings::parse_layered(&content, &config_path_clone) {
+                            Ok((settings, new_includes)) => {
+                                let mut watcher = watcher_clone
+                                    .lock()
+                                    .unwrap_or_else(PoisonError::into_inner);
+                                watch_include_dirs(&mut watcher, &mut watched_dirs, &new_includes);
+                                *lock_includes(&includes) = new_includes;
+                                settings
+                            }
+                            Err(e) => {
+                                tracing::error!(?e, "Failed to reload config");
+                                continue;
+                            }
+                        }
                     }
-                    Err(e) => {
-                        tracing::error!(?e, "Failed to reload config");
+                    Some(mut saved) = own_rx.recv() => {
+                        // The window saves paths as typed.
+                        if let Err(e) = saved.expand_paths() {
+                            tracing::error!(?e, "Config validation failed, keeping old settings");
+                            continue;
+                        }
+                        saved
                     }
+                };
+
+                if let Err(e) = new_settings.validate() {
+                    tracing::error!(?e, "Config validation failed, keeping old settings");
+                    continue;
                 }
+
+                let mut current_settings = settings_clone.write().await;
+                if *current_settings == new_settings {
+                    tracing::debug!(?config_path_clone, "Config unchanged, skipping reload");
+                    continue;
+                }
+
+                tracing::info!(?config_path_clone, "Config reloaded");
+                *current_settings = new_settings.clone();
+                let _ = update_tx_clone.send(new_settings);
             }
         });
 
@@ -306,12 +1264,43 @@ impl SettingsWatcher {
         self.settings.read().await.clone()
     }
 
+    /// The live settings, for readers that can't hold a subscription.
+    pub fn shared(&self) -> Arc<RwLock<Settings>> {
+        Arc::clone(&self.settings)
+    }
+
     #[allow(dead_code)]
     pub fn get_blocking(&self) -> Settings {
         self.settings.blocking_read().clone()
     }
 }
 
+fn lock_includes(includes: &Mutex<Vec<PathBuf>>) -> std::sync::MutexGuard<'_, Vec<PathBuf>> {
+    includes.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Starts watching the directories of `includes` that aren't yet.
+/// Directories that don't exist can't be watched; creating one later needs
+/// a touch of the main config to be noticed.
+fn watch_include_dirs(
+    watcher: &mut RecommendedWatcher,
+    watched_dirs: &mut HashSet<PathBuf>,
+    includes: &[PathBuf],
+) {
+    for dir in includes.iter().filter_map(|include| include.parent()) {
+        if watched_dirs.contains(dir) || !dir.is_dir() {
+            continue;
+        }
+        match watcher.watch(dir, RecursiveMode::NonRecursive) {
+            Ok(()) => {
+                tracing::info!(?dir, "Watching included config directory");
+                watched_dirs.insert(dir.to_path_buf());
+            }
+            Err(e) => tracing::warn!(?dir, error = %e, "Failed to watch included config directory"),
+        }
+    }
+}
+
 impl Default for SettingsWatcher {
     fn default() -> Self {
         Self::new().expect("Failed to create default SettingsWatcher")
@@ -329,12 +1318,36 @@ mod tests {
         assert!(settings.providers.codex.enabled);
         assert!(!settings.providers.merge_icons);
         assert!(!settings.display.show_as_remaining);
+        assert!(!settings.display.show_value_estimate);
+        assert!(!settings.display.hide_costs);
+        assert!(!settings.display.sort_providers_by_urgency);
+        assert_eq!(settings.display.animations, AnimationMode::Auto);
+        assert_eq!(
+            settings.display.reset_time_format,
+            ResetTimeFormat::Relative
+        );
+        assert_eq!(settings.display.icon_cycle_secs, 0);
+        assert!(!settings.display.colorblind_mode);
+        assert_eq!(settings.display.icon_mode, IconMode::Pixmap);
         assert!(settings.notifications.enabled);
         assert!((settings.notifications.threshold - 0.9).abs() < f64::EPSILON);
+        assert!(settings.notifications.incident_check);
+        assert!(!settings.notifications.sound.is_enabled());
+        assert!(!settings.notifications.weekly_summary);
         assert!(matches!(settings.theme.mode, ThemeMode::System));
         assert!(matches!(settings.popup.anchor, PopupAnchor::TopRight));
         assert_eq!(settings.popup.margin_top, 40);
         assert_eq!(settings.popup.dismiss_timeout_ms, 300);
+        assert_eq!(settings.popup.clamped_width(), 350);
+        assert_eq!(settings.popup.clamped_font_scale(), 1.0);
+        assert_eq!(settings.popup.layout, PopupLayout::Tabs);
+        assert!(settings.cost.enabled);
+        assert_eq!(settings.cost.timezone, CostTimezone::Local);
+        assert_eq!(settings.daemon.instance_suffix, None);
+        assert!(!settings.limits_file.enabled);
+        assert_eq!(settings.limits_file.path, None);
+        assert_eq!(settings.webhook.url, None);
+        assert_eq!(settings.webhook.interval_secs, 300);
     }
 
     #[test]
@@ -349,6 +1362,251 @@ mod tests {
         assert!(settings.validate().is_err());
     }
 
+    #[test]
+    fn test_budget_validation() {
+        let mut settings = Settings::default();
+        settings.cost.budgets = BTreeMap::from([
+            ("acme-backend".to_string(), 50.0),
+            ("side-project".to_string(), 0.0),
+        ]);
+        assert!(settings.validate().is_ok());
+
+        for budget in [-5.0, f64::NAN] {
+            settings.cost.budgets.insert("acme-web".to_string(), budget);
+            assert!(settings.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_monthly_budget_validation() {
+        let mut settings = Settings::default();
+        settings.cost.monthly_budget = Some(200.0);
+        assert!(settings.validate().is_ok());
+
+        for budget in [0.0, -5.0, f64::NAN] {
+            settings.cost.monthly_budget = Some(budget);
+            assert!(settings.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn test_scan_days_validation() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.cost.scan_days, DEFAULT_SCAN_DAYS);
+
+        settings.cost.scan_days = 7;
+        assert!(settings.validate().is_ok());
+        settings.cost.scan_days = 0;
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_project_filter_validation() {
+        let toml = r#"
+            [cost]
+            exclude_projects = ["*-scratch-*", "archive/**"]
+        "#;
+        let mut settings: Settings = toml::from_str(toml).unwrap();
+        assert_eq!(
+            settings.cost.exclude_projects,
+            vec!["*-scratch-*", "archive/**"]
+        );
+        assert!(settings.cost.include_projects.is_empty());
+        assert!(settings.validate().is_ok());
+
+        settings.cost.include_projects = vec!["archive/[".to_string()];
+        let err = settings.validate().unwrap_err().to_string();
+        assert!(err.starts_with("cost.include_projects: invalid pattern"));
+    }
+
+    #[test]
+    fn test_webhook_validation() {
+        let toml = r#"
+            [webhook]
+            url = "https://metrics.example.com/claude-bar"
+            interval_secs = 600
+            include_cost = true
+
+            [webhook.headers]
+            Authorization = "Bearer secret"
+        "#;
+        let mut settings: Settings = toml::from_str(toml).unwrap();
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.webhook.headers["Authorization"], "Bearer secret");
+
+        settings.webhook.interval_secs = 60;
+        assert!(settings.validate().is_err());
+
+        settings.webhook.interval_secs = 300;
+        let redacted = settings.redacted();
+        assert_eq!(redacted.webhook.url.as_deref(), Some(REDACTED));
+        assert_eq!(redacted.webhook.headers["Authorization"], REDACTED);
+        assert_eq!(redacted.webhook.interval_secs, 300);
+        assert!(redacted.webhook.include_cost);
+        assert_eq!(Settings::default().redacted(), Settings::default());
+
+        settings.webhook.url = Some("metrics.example.com".to_string());
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_popup_size_is_clamped_not_rejected() {
+        let mut settings = Settings::default();
+        settings.popup.width = 4000;
+        settings.popup.font_scale = 0.2;
+        assert!(settings.validate().is_ok());
+        assert_eq!(settings.popup.clamped_width(), 600);
+        assert_eq!(settings.popup.clamped_font_scale(), 0.8);
+
+        settings.popup.width = 100;
+        settings.popup.font_scale = f64::NAN;
+        assert_eq!(settings.popup.clamped_width(), 260);
+        assert_eq!(settings.popup.clamped_font_scale(), 1.0);
+
+        settings.popup.width = 480;
+        settings.popup.font_scale = 1.25;
+        assert_eq!(settings.popup.clamped_width(), 480);
+        assert_eq!(settings.popup.clamped_font_scale(), 1.25);
+    }
+
+    #[test]
+    fn test_popup_layout_parses() {
+        let settings: Settings = toml::from_str("[popup]\nlayout = \"stacked\"").unwrap();
+        assert_eq!(settings.popup.layout, PopupLayout::Stacked);
+        assert!(toml::from_str::<Settings>("[popup]\nlayout = \"grid\"").is_err());
+    }
+
+    #[test]
+    fn test_tooltip_template_validation() {
+        let mut settings = Settings::default();
+        settings.display.tooltip_template = "{session} ({reset_session})".to_string();
+        assert!(settings.validate().is_ok());
+
+        settings.display.tooltip_template = "{monthly}".to_string();
+        assert!(settings.validate().is_err());
+
+        settings.display.tooltip_template = "Session: {session".to_string();
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_tray_title() {
+        let toml = r#"
+            [display]
+            tray_title = "both"
+        "#;
+
+        let settings: Settings = toml::from_str(toml).unwrap();
+        assert_eq!(settings.display.tray_title, TrayTitleMode::Both);
+        assert_eq!(
+            settings.display.tooltip_template,
+            DisplaySettings::default().tooltip_template
+        );
+    }
+
+    #[test]
+    fn test_parse_tray_windows() {
+        let toml = r#"
+            [display]
+            tray_windows = ["opus", "max_of_all"]
+        "#;
+
+        let settings: Settings = toml::from_str(toml).unwrap();
+        assert_eq!(
+            settings.display.tray_windows,
+            [TrayWindow::Opus, TrayWindow::MaxOfAll]
+        );
+        assert_eq!(
+            Settings::default().display.tray_windows,
+            [TrayWindow::Session, TrayWindow::Weekly]
+        );
+
+        let toml = r#"
+            [display]
+            tray_windows = ["opus"]
+        "#;
+        assert!(toml::from_str::<Settings>(toml).is_err());
+    }
+
+    #[test]
+    fn test_parse_codex_account_override() {
+        let toml = r#"
+            [providers.codex]
+            account_id = "acct-personal"
+        "#;
+
+        let settings: Settings = toml::from_str(toml).unwrap();
+        let codex = &settings.providers.codex;
+        assert!(codex.enabled);
+        assert_eq!(codex.instances(), vec![Provider::Codex]);
+        assert_eq!(
+            codex.account_id_for(Provider::Codex).as_deref(),
+            Some("acct-personal")
+        );
+        assert_eq!(settings.provider_label(Provider::Codex), "Codex");
+    }
+
+    #[test]
+    fn test_parse_codex_workspaces() {
+        let toml = r#"
+            [providers.codex]
+            account_id = "acct-ignored"
+
+            [[providers.codex.workspaces]]
+            account_id = "acct-personal"
+            label = "Personal"
+
+            [[providers.codex.workspaces]]
+            account_id = "acct-team"
+            label = "Team"
+        "#;
+
+        let settings: Settings = toml::from_str(toml).unwrap();
+        assert!(settings.validate().is_ok());
+
+        let codex = &settings.providers.codex;
+        assert_eq!(
+            codex.instances(),
+            vec![Provider::Codex, Provider::CodexWorkspace(1)]
+        );
+        assert_eq!(
+            codex.account_id_for(Provider::CodexWorkspace(1)).as_deref(),
+            Some("acct-team")
+        );
+        assert_eq!(settings.provider_label(Provider::Codex), "Personal");
+        assert_eq!(settings.provider_label(Provider::CodexWorkspace(1)), "Team");
+        assert_eq!(
+            settings.enabled_providers(),
+            vec![
+                Provider::Claude,
+                Provider::Codex,
+                Provider::CodexWorkspace(1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_codex_workspace_validation() {
+        let mut settings = Settings::default();
+        settings.providers.codex.workspaces = vec![
+            CodexWorkspace {
+                account_id: "acct-team".to_string(),
+                label: "Team".to_string(),
+            },
+            CodexWorkspace {
+                account_id: "acct-team".to_string(),
+                label: "Team again".to_string(),
+            },
+        ];
+        assert!(settings.validate().is_err());
+
+        settings.providers.codex.workspaces[1].account_id = "acct-other".to_string();
+        assert!(settings.validate().is_ok());
+
+        settings.providers.codex.workspaces[1].label = " ".to_string();
+        assert!(settings.validate().is_err());
+    }
+
     #[test]
     fn test_parse_toml() {
         let toml = r#"
@@ -359,30 +1617,83 @@ mod tests {
 
             [providers.claude]
             enabled = true
+            beta_header = "oauth-2026-01-01"
 
             [providers.codex]
             enabled = false
 
             [display]
             show_as_remaining = true
+            sort_providers_by_urgency = true
+            animations = "off"
+            reset_time_format = "both"
+            icon_cycle_secs = 5
+            colorblind_mode = true
+            icon_mode = "named"
 
             [notifications]
             enabled = false
             threshold = 0.85
+            incident_check = false
+            sound = "~/sounds/ding.ogg"
+            weekly_summary = true
 
             [theme]
             mode = "dark"
+
+            [cost]
+            enabled = false
+            timezone = "utc"
+            monthly_budget = 200.0
+
+            [cost.budgets]
+            "acme-backend" = 50.0
+            side-project = 10
+
+            [daemon]
+            instance_suffix = "work"
+
+            [limits_file]
+            enabled = true
+            path = "~/.cache/claude-limits.json"
         "#;
 
         let settings: Settings = toml::from_str(toml).unwrap();
         assert!(settings.debug);
         assert!(!settings.providers.merge_icons);
         assert!(settings.providers.claude.enabled);
+        assert_eq!(
+            settings.providers.claude.beta_header.as_deref(),
+            Some("oauth-2026-01-01")
+        );
         assert!(!settings.providers.codex.enabled);
         assert!(settings.display.show_as_remaining);
+        assert!(settings.display.sort_providers_by_urgency);
+        assert_eq!(settings.display.animations, AnimationMode::Off);
+        assert_eq!(settings.display.reset_time_format, ResetTimeFormat::Both);
+        assert_eq!(settings.display.icon_cycle_secs, 5);
+        assert!(settings.display.colorblind_mode);
+        assert_eq!(settings.display.icon_mode, IconMode::Named);
         assert!(!settings.notifications.enabled);
         assert!((settings.notifications.threshold - 0.85).abs() < f64::EPSILON);
+        assert!(!settings.notifications.incident_check);
+        assert_eq!(
+            settings.notifications.sound,
+            AlertSound::File("~/sounds/ding.ogg".to_string())
+        );
+        assert!(settings.notifications.weekly_summary);
         assert!(matches!(settings.theme.mode, ThemeMode::Dark));
+        assert!(!settings.cost.enabled);
+        assert_eq!(settings.cost.timezone, CostTimezone::Utc);
+        assert_eq!(settings.cost.monthly_budget, Some(200.0));
+        assert_eq!(settings.cost.budgets["acme-backend"], 50.0);
+        assert_eq!(settings.cost.budgets["side-project"], 10.0);
+        assert_eq!(settings.daemon.instance_suffix.as_deref(), Some("work"));
+        assert!(settings.limits_file.enabled);
+        assert_eq!(
+            settings.limits_file.path.as_deref(),
+            Some("~/.cache/claude-limits.json")
+        );
     }
 
     #[test]
@@ -399,4 +1710,269 @@ mod tests {
         next.providers.merge_icons = !next.providers.merge_icons;
         assert_ne!(current, next);
     }
+
+    const HAND_EDITED: &str = r#"# My claude-bar config
+config_version = 1
+
+[display]
+# Flip this when the week gets tight
+show_as_remaining = false  # keep this comment
+my_custom_key = "kept"
+
+[my_plugin]
+enabled = true
+"#;
+
+    #[test]
+    fn test_save_preserves_comments_and_unknown_keys() {
+        let path = std::env::temp_dir().join(format!(
+            "claude-bar-settings-test-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, HAND_EDITED).unwrap();
+
+        let mut settings = Settings::parse(HAND_EDITED, &path).unwrap();
+        settings.display.show_as_remaining = true;
+        settings.theme.mode = ThemeMode::Dark;
+        settings.save_to(&path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(saved.starts_with("# My claude-bar config\n"));
+        assert!(saved.contains("# Flip this when the week gets tight\n"));
+        assert!(saved.contains("show_as_remaining = true  # keep this comment"));
+        assert!(saved.contains("my_custom_key = \"kept\""));
+        assert!(saved.contains("[my_plugin]\nenabled = true"));
+        assert!(saved.contains("[theme]\nmode = \"dark\""));
+        // Defaults the user never wrote stay out of the file.
+        assert!(!saved.contains("[popup]"));
+
+        assert_eq!(Settings::parse(&saved, &path).unwrap(), settings);
+        assert!(is_own_write(&saved));
+    }
+
+    #[test]
+    fn test_set_runtime_parses_values() {
+        let mut settings = Settings::default();
+        settings
+            .set_runtime("display.show_as_remaining", "true")
+            .unwrap();
+        settings
+            .set_runtime("providers.merge_icons", " true ")
+            .unwrap();
+        settings.set_runtime("theme.mode", "light").unwrap();
+        settings
+            .set_runtime("popup.dismiss_timeout_ms", "750")
+            .unwrap();
+        assert!(settings.display.show_as_remaining);
+        assert!(settings.providers.merge_icons);
+        assert_eq!(settings.theme.mode, ThemeMode::Light);
+        assert_eq!(settings.popup.dismiss_timeout_ms, 750);
+
+        let before = settings.clone();
+        assert!(settings.set_runtime("merge_icons", "yes").is_err());
+        assert!(settings.set_runtime("show_as_remaining", "1").is_err());
+        assert!(settings.set_runtime("theme.mode", "Dark").is_err());
+        assert!(settings
+            .set_runtime("popup.dismiss_timeout_ms", "-1")
+            .is_err());
+        assert_eq!(settings, before);
+
+        let err = settings
+            .set_runtime("cost.scan_days", "7")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("\"cost.scan_days\""));
+        for key in RUNTIME_SETTINGS {
+            assert!(err.contains(key));
+        }
+    }
+
+    #[test]
+    fn test_set_runtime_persists() {
+        let path = std::env::temp_dir().join(format!(
+            "claude-bar-runtime-test-{}.toml",
+            std::process::id()
+        ));
+        std::fs::write(&path, HAND_EDITED).unwrap();
+
+        let mut settings = Settings::parse(HAND_EDITED, &path).unwrap();
+        settings.set_runtime("show_as_remaining", "true").unwrap();
+        settings.set_runtime("theme.mode", "dark").unwrap();
+        settings
+            .set_runtime("popup.dismiss_timeout_ms", "1000")
+            .unwrap();
+        settings.validate().unwrap();
+        settings.save_to(&path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(saved.contains("show_as_remaining = true  # keep this comment"));
+        assert!(saved.contains("[popup]\ndismiss_timeout_ms = 1000"));
+        let reloaded = Settings::parse(&saved, &path).unwrap();
+        assert_eq!(reloaded, settings);
+        assert_eq!(reloaded.theme.mode, ThemeMode::Dark);
+    }
+
+    #[test]
+    fn test_save_stamps_config_version() {
+        let defaults = Settings::default();
+        let merged = defaults.merge_into("", &defaults).unwrap();
+        assert_eq!(merged, format!("config_version = {}\n", CONFIG_VERSION));
+
+        let merged = defaults
+            .merge_into("config_version = 0 # old\n", &defaults)
+            .unwrap();
+        assert_eq!(
+            merged,
+            format!("config_version = {} # old\n", CONFIG_VERSION)
+        );
+    }
+
+    #[test]
+    fn test_save_refuses_newer_config_version() {
+        let newer = format!("config_version = {}\n", CONFIG_VERSION + 1);
+        let defaults = Settings::default();
+        let err = defaults.merge_into(&newer, &defaults).unwrap_err();
+        assert!(err.to_string().contains("newer version"));
+        // Loading still works so a downgrade doesn't brick the daemon.
+        let path = Path::new("config.toml");
+        assert_eq!(Settings::parse(&newer, path).unwrap(), defaults);
+    }
+
+    #[test]
+    fn test_path_settings_are_expanded() {
+        let home = dirs::home_dir().unwrap();
+        let path = Path::new("config.toml");
+        let settings = Settings::parse(
+            r#"
+            [limits_file]
+            path = "~/limits.json"
+
+            [notifications]
+            sound = "${HOME}/ding.ogg"
+            "#,
+            path,
+        )
+        .unwrap();
+        assert_eq!(
+            settings.limits_file.path,
+            Some(home.join("limits.json").to_string_lossy().into_owned())
+        );
+        assert_eq!(
+            settings.notifications.sound,
+            AlertSound::File(format!("{}/ding.ogg", std::env::var("HOME").unwrap()))
+        );
+
+        let err = Settings::parse(
+            "[limits_file]\npath = \"$CLAUDE_BAR_TEST_UNSET/limits.json\"\n",
+            path,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "limits_file.path uses $CLAUDE_BAR_TEST_UNSET, which is not set"
+        );
+    }
+
+    #[test]
+    fn test_save_leaves_included_and_unexpanded_values() {
+        let dir = std::env::temp_dir().join(format!(
+            "claude-bar-include-save-test-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let main = "include = [\"local.toml\"]\n\n[limits_file]\npath = \"~/limits.json\"\n";
+        std::fs::write(&path, main).unwrap();
+        std::fs::write(dir.join("local.toml"), "[display]\nhide_costs = true\n").unwrap();
+
+        let mut settings = Settings::parse(main, &path).unwrap();
+        assert!(settings.display.hide_costs);
+        settings.theme.mode = ThemeMode::Dark;
+        settings.save_to(&path).unwrap();
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        let reloaded = Settings::parse(&saved, &path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(saved.contains("include = [\"local.toml\"]"));
+        assert!(saved.contains("path = \"~/limits.json\""));
+        assert!(saved.contains("[theme]\nmode = \"dark\""));
+        assert!(!saved.contains("hide_costs"));
+        assert_eq!(reloaded, settings);
+    }
+
+    #[test]
+    fn test_config_path_resolution_precedence() {
+        let default = Some(PathBuf::from("/home/me/.config/claude-bar/config.toml"));
+        let flag = Some(PathBuf::from("/etc/claude-bar/flag.toml"));
+        let env = Some(std::ffi::OsString::from("/nix/store/abc-config.toml"));
+
+        assert_eq!(
+            resolve_config_path(flag.clone(), env.clone(), default.clone()),
+            flag
+        );
+        assert_eq!(
+            resolve_config_path(None, env, default.clone()),
+            Some(PathBuf::from("/nix/store/abc-config.toml"))
+        );
+        assert_eq!(resolve_config_path(None, None, default.clone()), default);
+        // An empty variable counts as unset.
+        assert_eq!(
+            resolve_config_path(None, Some(Default::default()), default.clone()),
+            default
+        );
+        assert_eq!(
+            resolve_config_path(Some(PathBuf::from("local.toml")), None, default),
+            Some(std::env::current_dir().unwrap().join("local.toml"))
+        );
+    }
+
+    #[test]
+    fn test_save_refuses_read_only_config() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir =
+            std::env::temp_dir().join(format!("claude-bar-read-only-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        std::fs::write(&path, "[display]\nhide_costs = true\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o444)).unwrap();
+
+        let mut settings = Settings::parse("", &path).unwrap();
+        settings.theme.mode = ThemeMode::Dark;
+        let err = settings.save_to(&path).unwrap_err();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let read_only = err.downcast_ref::<ReadOnlyConfig>().unwrap();
+        assert_eq!(read_only.path, path);
+        assert_eq!(content, "[display]\nhide_costs = true\n");
+    }
+
+    #[test]
+    fn test_animation_mode_follows_system_only_on_auto() {
+        assert!(AnimationMode::Auto.enabled(true));
+        assert!(!AnimationMode::Auto.enabled(false));
+        assert!(AnimationMode::On.enabled(true));
+        assert!(AnimationMode::On.enabled(false));
+        assert!(!AnimationMode::Off.enabled(true));
+        assert!(!AnimationMode::Off.enabled(false));
+    }
+
+    #[test]
+    fn test_reset_time_format_render() {
+        assert_eq!(
+            ResetTimeFormat::Relative.render("2h 14m", "17:42"),
+            "in 2h 14m"
+        );
+        assert_eq!(ResetTimeFormat::Absolute.render("2h 14m", "17:42"), "17:42");
+        assert_eq!(
+            ResetTimeFormat::Both.render("2h 14m", "17:42"),
+            "in 2h 14m (17:42)"
+        );
+    }
 }
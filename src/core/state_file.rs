@@ -0,0 +1,38 @@
+//! JSON files under the data directory that carry daemon state across
+//! restarts, one per instance.
+
+use crate::core::instance;
+use crate::core::settings::write_atomically;
+use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Where the state file `name` lives for this instance, e.g.
+/// `~/.local/share/claude-bar/lockouts-work.json`.
+fn path(name: &str) -> Option<PathBuf> {
+    let file_name = format!("{}.json", instance::scoped(name, '-'));
+    dirs::data_local_dir().map(|d| d.join("claude-bar").join(file_name))
+}
+
+/// The state saved as `name`, or the default when there is none or it
+/// can't be read.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    path(name)
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Replaces the state saved as `name` with `state`.
+pub fn save<T: Serialize>(name: &str, state: &T) -> Result<()> {
+    let path = path(name).context("Could not determine data directory")?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    write_atomically(&path, &serde_json::to_string_pretty(state)?)?;
+    tracing::debug!(?path, "Saved {}", name);
+    Ok(())
+}
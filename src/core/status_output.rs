@@ -0,0 +1,329 @@
+//! The `status --json` document, shared by `claude-bar status` and the
+//! daemon's webhook.
+
+use crate::core::lockouts::Lockout;
+use crate::core::models::{
+    FetchLatency, ModelWindow, Provider, ProviderHealth, ProviderIdentity, RateWindow, TrayStatus,
+    UsageSnapshot,
+};
+use crate::core::quota_value;
+use crate::core::schema::SCHEMA_VERSION;
+use chrono::{DateTime, NaiveDate, Utc};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize, JsonSchema)]
+pub struct StatusOutput {
+    pub schema_version: u32,
+    pub providers: HashMap<String, ProviderStatus>,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    #[schemars(with = "i64")]
+    pub fetched_at: DateTime<Utc>,
+    /// Whether the running daemon's tray icons are registered; absent when
+    /// no daemon is running.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tray: Option<TrayStatus>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ProviderStatus {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<WindowStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weekly: Option<WindowStatus>,
+    /// A third window, e.g. Claude's model-specific weekly limit. Often the
+    /// same window as one of the carveouts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tertiary: Option<WindowStatus>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub carveouts: Vec<CarveoutStatus>,
+    /// Usage split by surface (Claude Code, claude.ai, API), when reported.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub surfaces: Vec<CarveoutStatus>,
+    /// Extra usage or credit pool, e.g. Claude's monthly overage spend.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_cost: Option<ProviderCostStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity: Option<ProviderIdentity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_latency_ms: Option<FetchLatency>,
+    /// From the running daemon, or this fetch when it succeeded.
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[schemars(with = "Option<i64>")]
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// When the daemon's current run of failures began; `null` while healthy.
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[schemars(with = "Option<i64>")]
+    pub error_since: Option<DateTime<Utc>>,
+    /// Change in tokens from the same days of last week, from the running
+    /// daemon's token scan.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_over_week_percent: Option<f64>,
+    /// Every time a window ran out, oldest first, as recorded by the daemon.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub lockouts: Vec<Lockout>,
+    /// Local day the weekly window started.
+    #[serde(skip)]
+    pub week_start: Option<NaiveDate>,
+    /// Picks the accent the name is printed in.
+    #[serde(skip)]
+    pub provider: Option<Provider>,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct CarveoutStatus {
+    pub label: String,
+    pub window: WindowStatus,
+}
+
+#[derive(Serialize, JsonSchema)]
+pub struct ProviderCostStatus {
+    pub used: f64,
+    pub limit: f64,
+    pub currency_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<String>,
+    #[serde(with = "chrono::serde::ts_seconds_option")]
+    #[schemars(with = "Option<i64>")]
+    pub resets_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resets_in: Option<String>,
+}
+
+#[derive(Serialize, PartialEq, JsonSchema)]
+pub struct WindowStatus {
+    pub used_percent: f64,
+    /// The API's own percentage (e.g. 45.5), for comparisons without
+    /// rounding error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub used_percent_exact: Option<f64>,
+    pub remaining_percent: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resets_in: Option<String>,
+    /// For the local reset time in text output.
+    #[serde(skip)]
+    pub resets_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_minutes: Option<i32>,
+    /// The out-of-range value the API sent, when `used_percent` was clamped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reported_used_percent: Option<f64>,
+}
+
+/// A provider as the daemon last saw it, for `StatusOutput::from_daemon`.
+pub struct DaemonProviderState {
+    pub provider: Provider,
+    pub name: String,
+    pub snapshot: Option<UsageSnapshot>,
+    pub error: Option<String>,
+    pub health: ProviderHealth,
+    pub fetch_latency: Option<FetchLatency>,
+    pub lockouts: Vec<Lockout>,
+}
+
+impl StatusOutput {
+    /// The `status --json` document from what the daemon already holds
+    /// rather than a fresh fetch, as the webhook posts it. A provider that
+    /// is failing reports its error like a failed fetch would; one that
+    /// hasn't fetched yet is left out.
+    pub fn from_daemon(providers: Vec<DaemonProviderState>, now: DateTime<Utc>) -> Self {
+        let providers = providers
+            .into_iter()
+            .filter_map(|state| {
+                let mut status = match (state.error, state.snapshot) {
+                    (Some(error), _) => error_status(error),
+                    (None, Some(snapshot)) => snapshot_to_status(snapshot),
+                    (None, None) => return None,
+                };
+                status.provider = Some(state.provider);
+                status.fetch_latency_ms = state.fetch_latency;
+                status.last_success_at = state.health.last_success_at;
+                status.error_since = state.health.error_since;
+                status.lockouts = state.lockouts;
+                Some((state.name, status))
+            })
+            .collect();
+        Self {
+            schema_version: SCHEMA_VERSION,
+            providers,
+            fetched_at: now,
+            tray: None,
+        }
+    }
+}
+
+pub fn error_status(error: String) -> ProviderStatus {
+    ProviderStatus {
+        session: None,
+        weekly: None,
+        tertiary: None,
+        carveouts: Vec::new(),
+        surfaces: Vec::new(),
+        provider_cost: None,
+        identity: None,
+        error: Some(error),
+        fetch_latency_ms: None,
+        last_success_at: None,
+        error_since: None,
+        week_over_week_percent: None,
+        lockouts: Vec::new(),
+        week_start: None,
+        provider: None,
+    }
+}
+
+pub fn snapshot_to_status(mut snapshot: UsageSnapshot) -> ProviderStatus {
+    snapshot.clear_expired_provider_cost(Utc::now());
+    let week_start = snapshot
+        .secondary
+        .as_ref()
+        .and_then(quota_value::window_start_day);
+    let to_status = |c: ModelWindow| CarveoutStatus {
+        label: c.label,
+        window: window_to_status(&c.window),
+    };
+
+    ProviderStatus {
+        session: snapshot.primary.map(|w| window_to_status(&w)),
+        weekly: snapshot.secondary.map(|w| window_to_status(&w)),
+        tertiary: snapshot.tertiary.map(|w| window_to_status(&w)),
+        carveouts: snapshot.carveouts.into_iter().map(to_status).collect(),
+        surfaces: snapshot.surfaces.into_iter().map(to_status).collect(),
+        provider_cost: snapshot.provider_cost.map(|cost| ProviderCostStatus {
+            resets_in: cost.resets_at.map(format_reset_time),
+            used: cost.used,
+            limit: cost.limit,
+            currency_code: cost.currency_code,
+            period: cost.period,
+            resets_at: cost.resets_at,
+        }),
+        identity: Some(snapshot.identity),
+        error: None,
+        fetch_latency_ms: None,
+        last_success_at: None,
+        error_since: None,
+        week_over_week_percent: None,
+        lockouts: Vec::new(),
+        week_start,
+        provider: None,
+    }
+}
+
+pub fn window_to_status(window: &RateWindow) -> WindowStatus {
+    WindowStatus {
+        used_percent: window.used_percent,
+        used_percent_exact: window.used_percent_exact,
+        remaining_percent: window.remaining_percent(),
+        resets_in: window.resets_at.map(|resets_at| {
+            let approx = if window.resets_at_estimated { "~" } else { "" };
+            format!("{}{}", approx, format_reset_time(resets_at))
+        }),
+        resets_at: window.resets_at,
+        window_minutes: window.window_minutes,
+        reported_used_percent: window
+            .used_percent_exact
+            .filter(|_| window.was_clamped())
+            .map(|exact| exact / 100.0),
+    }
+}
+
+pub fn format_reset_time(resets_at: DateTime<Utc>) -> String {
+    let now = Utc::now();
+    let duration = resets_at.signed_duration_since(now);
+
+    if duration.num_seconds() <= 0 {
+        return "now".to_string();
+    }
+
+    let total_minutes = duration.num_minutes();
+    let days = total_minutes / (24 * 60);
+    let hours = (total_minutes % (24 * 60)) / 60;
+    let minutes = total_minutes % 60;
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::ProviderCostSnapshot;
+    use chrono::Duration;
+
+    #[test]
+    fn test_provider_cost_reset_in_json() {
+        let now = Utc::now();
+        let resets_at = now + Duration::days(9) + Duration::minutes(5);
+        let mut snapshot = UsageSnapshot {
+            primary: None,
+            secondary: None,
+            tertiary: None,
+            provider_cost: Some(ProviderCostSnapshot {
+                used: 12.5,
+                limit: 50.0,
+                currency_code: "USD".to_string(),
+                period: Some("Monthly".to_string()),
+                resets_at: Some(resets_at),
+                updated_at: now,
+            }),
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            updated_at: now,
+            received_at: None,
+            identity: ProviderIdentity {
+                email: None,
+                organization: None,
+                plan: None,
+                login_method: None,
+            },
+        };
+
+        let json = serde_json::to_value(snapshot_to_status(snapshot.clone())).unwrap();
+        assert_eq!(json["provider_cost"]["used"], 12.5);
+        assert_eq!(json["provider_cost"]["resets_at"], resets_at.timestamp());
+        assert_eq!(json["provider_cost"]["resets_in"], "9d 0h");
+
+        // A period that already reset isn't reported with last period's spend.
+        snapshot.provider_cost.as_mut().unwrap().resets_at = Some(now - Duration::minutes(1));
+        let json = serde_json::to_value(snapshot_to_status(snapshot)).unwrap();
+        assert!(json.get("provider_cost").is_none());
+    }
+
+    #[test]
+    fn test_surfaces_in_json() {
+        let mut snapshot = UsageSnapshot {
+            primary: None,
+            secondary: None,
+            tertiary: None,
+            provider_cost: None,
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            updated_at: Utc::now(),
+            received_at: None,
+            identity: ProviderIdentity {
+                email: None,
+                organization: None,
+                plan: None,
+                login_method: None,
+            },
+        };
+        let json = serde_json::to_value(snapshot_to_status(snapshot.clone())).unwrap();
+        assert!(json.get("surfaces").is_none());
+
+        snapshot.surfaces.push(ModelWindow {
+            label: "Web usage".to_string(),
+            window: RateWindow::from_reported(0.41, Some(10080), None, None),
+        });
+        let json = serde_json::to_value(snapshot_to_status(snapshot)).unwrap();
+        assert_eq!(json["surfaces"][0]["label"], "Web usage");
+        assert_eq!(json["surfaces"][0]["window"]["used_percent"], 0.41);
+    }
+}
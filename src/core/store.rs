@@ -1,9 +1,28 @@
-use crate::core::models::{CostSnapshot, CostUsageTokenSnapshot, Provider, UsageSnapshot};
-use std::collections::{HashMap, HashSet};
+use crate::core::incidents::Incident;
+use crate::core::models::{
+    CostSnapshot, CostUsageTokenSnapshot, ErrorEvent, ErrorKind, FetchLatency, Provider,
+    ProviderHealth, UsageSnapshot,
+};
+use crate::core::quota_adjust::detect_quota_adjustments;
+use crate::core::reset_estimate::{RolloverTracker, WindowSlot};
+use crate::core::velocity::{Velocity, VelocityEstimator};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, RwLock};
 
+/// How many recent `fetch_usage` durations are kept per provider.
+const FETCH_LATENCY_SAMPLES: usize = 20;
+/// How many failure, recovery and quota adjustment events are kept per
+/// provider.
+const ERROR_HISTORY_LEN: usize = 20;
+/// Updates held for subscribers that have yet to read them. The channel
+/// never grows past this: a subscriber that falls further behind loses the
+/// oldest and gets `RecvError::Lagged`, on which every loop re-reads the
+/// store instead of replaying what it missed.
+const UPDATE_CHANNEL_CAPACITY: usize = 64;
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum StoreUpdate {
@@ -17,14 +36,61 @@ pub enum StoreUpdate {
 #[derive(Default)]
 struct StoreInner {
     snapshots: HashMap<Provider, UsageSnapshot>,
-    costs: HashMap<Provider, CostSnapshot>,
-    token_snapshots: HashMap<Provider, CostUsageTokenSnapshot>,
+    /// Shared with the `CostStore` cache and the popup rather than copied.
+    costs: HashMap<Provider, Arc<CostSnapshot>>,
+    token_snapshots: HashMap<Provider, Arc<CostUsageTokenSnapshot>>,
     errors: HashMap<Provider, String>,
     last_fetch: HashMap<Provider, Instant>,
+    fetch_latencies: HashMap<Provider, VecDeque<Duration>>,
+    error_history: HashMap<Provider, VecDeque<ErrorEvent>>,
+    /// Consecutive failures and when the current outage began.
+    failure_streaks: HashMap<Provider, (u32, DateTime<Utc>)>,
+    /// Kept across failures, so an outage can say what data it replaced.
+    last_success_at: HashMap<Provider, DateTime<Utc>>,
+    /// Status page incidents reported while a provider is failing.
+    incidents: HashMap<Provider, Incident>,
+    /// Burn rate of each provider's session window.
+    velocity: HashMap<Provider, VelocityEstimator>,
+    /// When each window was last seen to start, for reset times the
+    /// provider leaves out.
+    rollovers: HashMap<(Provider, WindowSlot), RolloverTracker>,
     #[allow(dead_code)]
     notified_90_percent: HashSet<Provider>,
 }
 
+impl StoreInner {
+    fn push_error_event(&mut self, provider: Provider, event: ErrorEvent) {
+        let events = self.error_history.entry(provider).or_default();
+        if events.len() == ERROR_HISTORY_LEN {
+            events.pop_front();
+        }
+        events.push_back(event);
+    }
+
+    fn health(&self, provider: Provider) -> ProviderHealth {
+        ProviderHealth {
+            last_success_at: self.last_success_at.get(&provider).copied(),
+            error_since: self.failure_streaks.get(&provider).map(|(_, since)| *since),
+        }
+    }
+}
+
+/// Approximate size of the store's collections, for the daemon's periodic
+/// memory report. Each is bounded; these show that they stay so.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreSizes {
+    pub providers: usize,
+    pub error_events: usize,
+    pub latency_samples: usize,
+    pub velocity_samples: usize,
+    pub rollover_trackers: usize,
+    /// Days across every provider's cost breakdown and token history.
+    pub cost_days: usize,
+    pub token_days: usize,
+    /// Updates sent but not yet read by every subscriber.
+    pub queued_updates: usize,
+}
+
 #[derive(Clone)]
 pub struct UsageStore {
     inner: Arc<RwLock<StoreInner>>,
@@ -33,14 +99,13 @@ pub struct UsageStore {
 
 impl UsageStore {
     pub fn new() -> Self {
-        let (update_tx, _) = broadcast::channel(64);
+        let (update_tx, _) = broadcast::channel(UPDATE_CHANNEL_CAPACITY);
         Self {
             inner: Arc::new(RwLock::new(StoreInner::default())),
             update_tx,
         }
     }
 
-    #[allow(dead_code)]
     pub fn subscribe(&self) -> broadcast::Receiver<StoreUpdate> {
         self.update_tx.subscribe()
     }
@@ -49,11 +114,14 @@ impl UsageStore {
         self.inner.read().await.snapshots.get(&provider).cloned()
     }
 
-    pub async fn get_cost(&self, provider: Provider) -> Option<CostSnapshot> {
+    pub async fn get_cost(&self, provider: Provider) -> Option<Arc<CostSnapshot>> {
         self.inner.read().await.costs.get(&provider).cloned()
     }
 
-    pub async fn get_token_snapshot(&self, provider: Provider) -> Option<CostUsageTokenSnapshot> {
+    pub async fn get_token_snapshot(
+        &self,
+        provider: Provider,
+    ) -> Option<Arc<CostUsageTokenSnapshot>> {
         self.inner
             .read()
             .await
@@ -70,7 +138,54 @@ impl UsageStore {
         let had_error = {
             let mut inner = self.inner.write().await;
             let had_error = inner.errors.remove(&provider).is_some();
+            inner.incidents.remove(&provider);
+            let now = Utc::now();
+            if let Some((failures, since)) = inner.failure_streaks.remove(&provider) {
+                inner.push_error_event(
+                    provider,
+                    ErrorEvent::Recovered {
+                        at: now,
+                        failures,
+                        outage_secs: (now - since).num_seconds(),
+                    },
+                );
+            }
+            let adjustments = inner
+                .snapshots
+                .get(&provider)
+                .map(|previous| detect_quota_adjustments(previous, &snapshot, now))
+                .unwrap_or_default();
+            for adjustment in adjustments {
+                tracing::info!(
+                    provider = ?provider,
+                    window = adjustment.window.label(),
+                    from = adjustment.from,
+                    to = adjustment.to,
+                    "Usage dropped without a reset; quota adjusted?"
+                );
+                inner.push_error_event(
+                    provider,
+                    ErrorEvent::QuotaAdjusted {
+                        at: now,
+                        window: adjustment.window,
+                        from: adjustment.from,
+                        to: adjustment.to,
+                    },
+                );
+            }
+            if let Some(session) = &snapshot.primary {
+                let session_tokens = inner
+                    .token_snapshots
+                    .get(&provider)
+                    .and_then(|tokens| tokens.session_tokens);
+                inner
+                    .velocity
+                    .entry(provider)
+                    .or_default()
+                    .record(now, session, session_tokens);
+            }
             inner.snapshots.insert(provider, snapshot);
+            inner.last_success_at.insert(provider, now);
             inner.last_fetch.insert(provider, Instant::now());
             had_error
         };
@@ -82,13 +197,17 @@ impl UsageStore {
     }
 
     #[allow(dead_code)]
-    pub async fn update_cost(&self, provider: Provider, cost: CostSnapshot) {
+    pub async fn update_cost(&self, provider: Provider, cost: Arc<CostSnapshot>) {
         self.inner.write().await.costs.insert(provider, cost);
         let _ = self.update_tx.send(StoreUpdate::CostUpdated(provider));
     }
 
     #[allow(dead_code)]
-    pub async fn update_token_snapshot(&self, provider: Provider, snapshot: CostUsageTokenSnapshot) {
+    pub async fn update_token_snapshot(
+        &self,
+        provider: Provider,
+        snapshot: Arc<CostUsageTokenSnapshot>,
+    ) {
         self.inner
             .write()
             .await
@@ -100,6 +219,16 @@ impl UsageStore {
     pub async fn set_error(&self, provider: Provider, error: String) {
         {
             let mut inner = self.inner.write().await;
+            let now = Utc::now();
+            inner.failure_streaks.entry(provider).or_insert((0, now)).0 += 1;
+            inner.push_error_event(
+                provider,
+                ErrorEvent::Failed {
+                    at: now,
+                    kind: ErrorKind::classify(&error),
+                    message: error.clone(),
+                },
+            );
             inner.errors.insert(provider, error.clone());
             inner.snapshots.remove(&provider);
             inner.last_fetch.insert(provider, Instant::now());
@@ -107,6 +236,110 @@ impl UsageStore {
         let _ = self.update_tx.send(StoreUpdate::ErrorOccurred(provider, error));
     }
 
+    pub async fn get_incident(&self, provider: Provider) -> Option<Incident> {
+        self.inner.read().await.incidents.get(&provider).cloned()
+    }
+
+    /// Ignored once the provider has recovered, so a slow status page check
+    /// can't outlive the error it was for.
+    pub async fn set_incident(&self, provider: Provider, incident: Option<Incident>) {
+        let mut inner = self.inner.write().await;
+        match incident {
+            Some(incident) if inner.errors.contains_key(&provider) => {
+                inner.incidents.insert(provider, incident);
+            }
+            _ => {
+                inner.incidents.remove(&provider);
+            }
+        }
+    }
+
+    /// Fills in the reset times `snapshot` is missing from when each window
+    /// was seen to roll over. Call it on every successful fetch, before the
+    /// snapshot is stored or shown.
+    pub async fn estimate_missing_resets(&self, provider: Provider, snapshot: &mut UsageSnapshot) {
+        let mut inner = self.inner.write().await;
+        let now = Utc::now();
+        let windows = [
+            (WindowSlot::Primary, snapshot.primary.as_mut()),
+            (WindowSlot::Secondary, snapshot.secondary.as_mut()),
+            (WindowSlot::Tertiary, snapshot.tertiary.as_mut()),
+        ];
+        for (slot, window) in windows {
+            if let Some(window) = window {
+                inner
+                    .rollovers
+                    .entry((provider, slot))
+                    .or_default()
+                    .apply(now, window);
+            }
+        }
+    }
+
+    /// How fast `provider`'s session window is being used up right now.
+    pub async fn get_velocity(&self, provider: Provider) -> Option<Velocity> {
+        self.inner
+            .read()
+            .await
+            .velocity
+            .get(&provider)
+            .and_then(|estimator| estimator.estimate(Utc::now()))
+    }
+
+    pub async fn get_health(&self, provider: Provider) -> ProviderHealth {
+        self.inner.read().await.health(provider)
+    }
+
+    /// Health of every provider that has been fetched at least once.
+    pub async fn get_all_health(&self) -> Vec<(Provider, ProviderHealth)> {
+        let inner = self.inner.read().await;
+        let providers: HashSet<Provider> = inner
+            .last_success_at
+            .keys()
+            .chain(inner.failure_streaks.keys())
+            .copied()
+            .collect();
+        providers
+            .into_iter()
+            .map(|provider| (provider, inner.health(provider)))
+            .collect()
+    }
+
+    /// Recent failures and recoveries for `provider`, oldest first.
+    pub async fn get_error_history(&self, provider: Provider) -> Vec<ErrorEvent> {
+        self.inner
+            .read()
+            .await
+            .error_history
+            .get(&provider)
+            .map(|events| events.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Records how long a `fetch_usage` call took and returns the updated summary.
+    pub async fn record_fetch_latency(&self, provider: Provider, elapsed: Duration) -> FetchLatency {
+        let mut inner = self.inner.write().await;
+        let samples = inner.fetch_latencies.entry(provider).or_default();
+        if samples.len() == FETCH_LATENCY_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(elapsed);
+        FetchLatency::from_samples(samples.iter().copied())
+            .expect("latency window holds the sample just recorded")
+    }
+
+    pub async fn get_fetch_latencies(&self) -> Vec<(Provider, FetchLatency)> {
+        self.inner
+            .read()
+            .await
+            .fetch_latencies
+            .iter()
+            .filter_map(|(provider, samples)| {
+                FetchLatency::from_samples(samples.iter().copied()).map(|l| (*provider, l))
+            })
+            .collect()
+    }
+
     pub async fn clear_last_fetch(&self, provider: Provider) {
         self.inner.write().await.last_fetch.remove(&provider);
     }
@@ -120,6 +353,16 @@ impl UsageStore {
             .is_none_or(|last| last.elapsed() >= cooldown)
     }
 
+    /// When `provider` is next due for a refresh; `None` means it is due now.
+    pub async fn next_refresh_at(&self, provider: Provider, cooldown: Duration) -> Option<Instant> {
+        self.inner
+            .read()
+            .await
+            .last_fetch
+            .get(&provider)
+            .map(|last| *last + cooldown)
+    }
+
     #[allow(dead_code)]
     pub async fn should_notify(&self, provider: Provider, threshold: f64) -> bool {
         let inner = self.inner.read().await;
@@ -153,6 +396,32 @@ impl UsageStore {
             .remove(&provider);
     }
 
+    pub async fn sizes(&self) -> StoreSizes {
+        let inner = self.inner.read().await;
+        StoreSizes {
+            providers: inner.snapshots.len().max(inner.errors.len()),
+            error_events: inner.error_history.values().map(VecDeque::len).sum(),
+            latency_samples: inner.fetch_latencies.values().map(VecDeque::len).sum(),
+            velocity_samples: inner
+                .velocity
+                .values()
+                .map(VelocityEstimator::sample_count)
+                .sum(),
+            rollover_trackers: inner.rollovers.len(),
+            cost_days: inner
+                .costs
+                .values()
+                .map(|cost| cost.daily_breakdown.len())
+                .sum(),
+            token_days: inner
+                .token_snapshots
+                .values()
+                .map(|tokens| tokens.daily.len())
+                .sum(),
+            queued_updates: self.update_tx.len(),
+        }
+    }
+
     #[allow(dead_code)]
     pub async fn all_providers_with_snapshots(&self) -> Vec<(Provider, UsageSnapshot)> {
         self.inner
@@ -184,12 +453,16 @@ mod tests {
                 window_minutes: Some(300),
                 resets_at: None,
                 reset_description: None,
+                used_percent_exact: None,
+                resets_at_estimated: false,
             }),
             secondary: None,
             tertiary: None,
             provider_cost: None,
             carveouts: Vec::new(),
+            surfaces: Vec::new(),
             updated_at: Utc::now(),
+            received_at: None,
             identity: ProviderIdentity {
                 email: None,
                 organization: None,
@@ -301,4 +574,241 @@ mod tests {
         let update = receiver.try_recv().unwrap();
         assert!(matches!(update, StoreUpdate::UsageUpdated(Provider::Claude)));
     }
+
+    #[tokio::test]
+    async fn test_next_refresh_at_follows_last_fetch() {
+        let store = UsageStore::new();
+        let cooldown = Duration::from_secs(60);
+        assert!(store.next_refresh_at(Provider::Claude, cooldown).await.is_none());
+
+        store.update_snapshot(Provider::Claude, make_snapshot(0.3)).await;
+        let due = store.next_refresh_at(Provider::Claude, cooldown).await.unwrap();
+        assert!(due > Instant::now() + Duration::from_secs(59));
+        assert!(!store.should_refresh(Provider::Claude, cooldown).await);
+
+        store.clear_last_fetch(Provider::Claude).await;
+        assert!(store.next_refresh_at(Provider::Claude, cooldown).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_latency_keeps_rolling_window() {
+        let store = UsageStore::new();
+        assert!(store.get_fetch_latencies().await.is_empty());
+
+        for millis in 1..=25 {
+            store
+                .record_fetch_latency(Provider::Claude, Duration::from_millis(millis * 100))
+                .await;
+        }
+        let latency = store
+            .record_fetch_latency(Provider::Claude, Duration::from_millis(50))
+            .await;
+
+        // Only the last 20 samples count: 700ms..=2500ms plus the new 50ms one.
+        assert_eq!(latency.samples, FETCH_LATENCY_SAMPLES);
+        assert_eq!(latency.min, 50);
+        assert_eq!(latency.max, 2_500);
+
+        let all = store.get_fetch_latencies().await;
+        assert_eq!(all, vec![(Provider::Claude, latency)]);
+    }
+
+    #[tokio::test]
+    async fn test_error_history_records_failures_and_recovery() {
+        let store = UsageStore::new();
+        assert!(store.get_error_history(Provider::Claude).await.is_empty());
+
+        for _ in 0..3 {
+            store
+                .set_error(Provider::Claude, "Claude API error: 503 - ".to_string())
+                .await;
+        }
+        store.update_snapshot(Provider::Claude, make_snapshot(0.3)).await;
+        // A second success does not log another recovery.
+        store.update_snapshot(Provider::Claude, make_snapshot(0.3)).await;
+
+        let history = store.get_error_history(Provider::Claude).await;
+        assert_eq!(history.len(), 4);
+        assert!(matches!(
+            history[0],
+            ErrorEvent::Failed {
+                kind: ErrorKind::Server,
+                ..
+            }
+        ));
+        assert!(matches!(
+            history[3],
+            ErrorEvent::Recovered { failures: 3, .. }
+        ));
+        assert!(store.get_error_history(Provider::Codex).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_quota_adjustment_is_recorded() {
+        let store = UsageStore::new();
+        let resets_at = Utc::now() + chrono::Duration::hours(3);
+        let session = |used_percent| {
+            let mut snapshot = make_snapshot(used_percent);
+            snapshot.primary.as_mut().unwrap().resets_at = Some(resets_at);
+            snapshot
+        };
+
+        store.update_snapshot(Provider::Claude, session(0.8)).await;
+        store.update_snapshot(Provider::Claude, session(0.4)).await;
+
+        let history = store.get_error_history(Provider::Claude).await;
+        assert_eq!(history.len(), 1);
+        assert!(matches!(
+            history[0],
+            ErrorEvent::QuotaAdjusted {
+                window: WindowSlot::Primary,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_error_history_is_bounded() {
+        let store = UsageStore::new();
+        for i in 0..(ERROR_HISTORY_LEN + 5) {
+            store.set_error(Provider::Codex, format!("error {}", i)).await;
+        }
+
+        let history = store.get_error_history(Provider::Codex).await;
+        assert_eq!(history.len(), ERROR_HISTORY_LEN);
+        assert!(matches!(
+            &history[0],
+            ErrorEvent::Failed { message, .. } if message == "error 5"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_health_tracks_error_success_error() {
+        let store = UsageStore::new();
+        assert_eq!(
+            store.get_health(Provider::Claude).await,
+            ProviderHealth::default()
+        );
+
+        store
+            .set_error(Provider::Claude, "Network error".to_string())
+            .await;
+        let first_outage = store.get_health(Provider::Claude).await;
+        let since = first_outage.error_since.unwrap();
+        assert_eq!(first_outage.last_success_at, None);
+
+        // Repeated failures keep the start of the outage.
+        store
+            .set_error(Provider::Claude, "Network error".to_string())
+            .await;
+        assert_eq!(
+            store.get_health(Provider::Claude).await.error_since,
+            Some(since)
+        );
+
+        store
+            .update_snapshot(Provider::Claude, make_snapshot(0.3))
+            .await;
+        let recovered = store.get_health(Provider::Claude).await;
+        let success = recovered.last_success_at.unwrap();
+        assert_eq!(recovered.error_since, None);
+        assert!(success >= since);
+
+        store
+            .set_error(Provider::Claude, "HTTP 529".to_string())
+            .await;
+        let second_outage = store.get_health(Provider::Claude).await;
+        assert_eq!(second_outage.last_success_at, Some(success));
+        assert!(second_outage.error_since.unwrap() >= success);
+
+        assert_eq!(
+            store.get_all_health().await,
+            vec![(Provider::Claude, second_outage)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_incident_only_kept_while_failing() {
+        let store = UsageStore::new();
+        let incident = Incident {
+            description: "Partial System Outage".to_string(),
+            url: "https://status.claude.com".to_string(),
+        };
+
+        store
+            .set_incident(Provider::Claude, Some(incident.clone()))
+            .await;
+        assert_eq!(store.get_incident(Provider::Claude).await, None);
+
+        store
+            .set_error(Provider::Claude, "HTTP 529".to_string())
+            .await;
+        store
+            .set_incident(Provider::Claude, Some(incident.clone()))
+            .await;
+        assert_eq!(store.get_incident(Provider::Claude).await, Some(incident));
+
+        store
+            .update_snapshot(Provider::Claude, make_snapshot(0.3))
+            .await;
+        assert_eq!(store.get_incident(Provider::Claude).await, None);
+    }
+
+    /// Pumps a week's worth of refreshes through the store, with a
+    /// subscriber that never reads, and checks nothing grows with them.
+    #[cfg(feature = "soak-tests")]
+    #[tokio::test]
+    async fn test_store_collections_stay_bounded() {
+        let store = UsageStore::new();
+        let mut idle = store.subscribe();
+
+        for i in 0..10_000u32 {
+            let provider = if i % 2 == 0 {
+                Provider::Claude
+            } else {
+                Provider::Codex
+            };
+            let mut snapshot = make_snapshot(f64::from(i % 100) / 100.0);
+            store.estimate_missing_resets(provider, &mut snapshot).await;
+            store.update_snapshot(provider, snapshot).await;
+            store
+                .record_fetch_latency(provider, Duration::from_millis(u64::from(i % 900)))
+                .await;
+            if i % 7 == 0 {
+                store
+                    .set_error(provider, format!("HTTP 5{:02}", i % 100))
+                    .await;
+            }
+            store
+                .update_cost(provider, Arc::new(CostSnapshot::default()))
+                .await;
+            store
+                .update_token_snapshot(
+                    provider,
+                    Arc::new(CostUsageTokenSnapshot {
+                        session_tokens: Some(u64::from(i)),
+                        session_cost_usd: None,
+                        session_start: None,
+                        last_30_days_tokens: None,
+                        last_30_days_cost_usd: None,
+                        daily: Vec::new(),
+                        heatmap: Default::default(),
+                        updated_at: Utc::now(),
+                    }),
+                )
+                .await;
+        }
+
+        let sizes = store.sizes().await;
+        assert_eq!(sizes.providers, 2);
+        assert!(sizes.error_events <= 2 * ERROR_HISTORY_LEN);
+        assert!(sizes.latency_samples <= 2 * FETCH_LATENCY_SAMPLES);
+        assert!(sizes.velocity_samples <= 2 * 120);
+        assert!(sizes.rollover_trackers <= 2 * 3);
+        assert!(sizes.queued_updates <= UPDATE_CHANNEL_CAPACITY);
+        assert!(matches!(
+            idle.try_recv(),
+            Err(broadcast::error::TryRecvError::Lagged(_))
+        ));
+    }
 }
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Terminals looked for, in order, when `terminal.preferred` is unset or
+/// not installed.
+pub const KNOWN_TERMINALS: &[&str] = &["foot", "kitty", "alacritty", "gnome-terminal"];
+
+/// The terminal to run commands in: `preferred` when its program is
+/// installed, otherwise the first installed one of `KNOWN_TERMINALS`.
+pub fn resolve_terminal(
+    preferred: Option<&str>,
+    installed: impl Fn(&str) -> bool,
+) -> Option<String> {
+    if let Some(preferred) = preferred.map(str::trim).filter(|p| !p.is_empty()) {
+        let program = preferred.split_whitespace().next().unwrap_or(preferred);
+        if installed(program) {
+            return Some(preferred.to_string());
+        }
+        tracing::warn!(
+            terminal = preferred,
+            "terminal.preferred is not installed; looking for another terminal"
+        );
+    }
+    KNOWN_TERMINALS
+        .iter()
+        .find(|terminal| installed(terminal))
+        .map(|terminal| terminal.to_string())
+}
+
+/// `resolve_terminal` against the programs on `PATH`.
+pub fn find_terminal(preferred: Option<&str>) -> Option<String> {
+    resolve_terminal(preferred, on_path)
+}
+
+fn on_path(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// `terminal` running `command` through `sh`, then a shell so the output
+/// stays on screen. A `terminal` with arguments, e.g. "wezterm start --",
+/// is taken to end with whatever flag precedes the command.
+fn terminal_command(terminal: &str, command: &str) -> Option<Command> {
+    let mut words = terminal.split_whitespace();
+    let program = words.next()?;
+    let args: Vec<&str> = words.collect();
+    let exec_flag: &[&str] = match program {
+        _ if !args.is_empty() => &[],
+        "foot" | "kitty" => &[],
+        "gnome-terminal" => &["--"],
+        // The xterm convention, which most other terminals follow.
+        _ => &["-e"],
+    };
+
+    let mut cmd = Command::new(program);
+    cmd.args(args)
+        .args(exec_flag)
+        .args(["sh", "-c"])
+        .arg(format!("{}; exec \"${{SHELL:-sh}}\"", command));
+    Some(cmd)
+}
+
+/// Opens `terminal` running `command`, without waiting for it to close.
+pub fn run_in_terminal(terminal: &str, command: &str) -> Result<()> {
+    let mut cmd = terminal_command(terminal, command).context("No terminal configured")?;
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to start {}", terminal))?;
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_resolution_order() {
+        let installed = |names: &'static [&'static str]| move |name: &str| names.contains(&name);
+
+        // The preferred terminal wins when it is installed, arguments and all.
+        assert_eq!(
+            resolve_terminal(Some("wezterm start --"), installed(&["wezterm", "foot"])),
+            Some("wezterm start --".to_string())
+        );
+        // Otherwise detection, in KNOWN_TERMINALS order.
+        assert_eq!(
+            resolve_terminal(Some("wezterm"), installed(&["alacritty", "kitty"])),
+            Some("kitty".to_string())
+        );
+        assert_eq!(
+            resolve_terminal(None, installed(&["gnome-terminal", "foot"])),
+            Some("foot".to_string())
+        );
+        assert_eq!(
+            resolve_terminal(Some("  "), installed(&["alacritty"])),
+            Some("alacritty".to_string())
+        );
+        assert_eq!(resolve_terminal(None, installed(&["xterm"])), None);
+    }
+
+    #[test]
+    fn test_terminal_command_per_terminal() {
+        let script = "claude; exec \"${SHELL:-sh}\"";
+
+        let foot = terminal_command("foot", "claude").unwrap();
+        assert_eq!(foot.get_program(), "foot");
+        assert_eq!(args(&foot), vec!["sh", "-c", script]);
+
+        let gnome = terminal_command("gnome-terminal", "claude").unwrap();
+        assert_eq!(args(&gnome), vec!["--", "sh", "-c", script]);
+
+        let alacritty = terminal_command("alacritty", "claude").unwrap();
+        assert_eq!(args(&alacritty), vec!["-e", "sh", "-c", script]);
+
+        let wezterm = terminal_command("wezterm start --", "claude").unwrap();
+        assert_eq!(wezterm.get_program(), "wezterm");
+        assert_eq!(args(&wezterm), vec!["start", "--", "sh", "-c", script]);
+
+        assert!(terminal_command("", "claude").is_none());
+    }
+}
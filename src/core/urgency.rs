@@ -0,0 +1,122 @@
+use crate::core::models::{Provider, RateWindow, UsageSnapshot};
+use chrono::{DateTime, Utc};
+
+/// Weight of a window that is about to reset, relative to one that just
+/// started. A nearly full window that lifts in minutes blocks less than one
+/// that stays full for days.
+const RESET_WEIGHT_FLOOR: f64 = 0.5;
+
+/// How close a provider is to blocking work: its highest windowed usage,
+/// discounted the closer that window is to resetting. `None` for a provider
+/// that is failing, which ranks below any provider with data.
+pub fn urgency_score(
+    snapshot: Option<&UsageSnapshot>,
+    has_error: bool,
+    now: DateTime<Utc>,
+) -> Option<f64> {
+    if has_error {
+        return None;
+    }
+    let Some(snapshot) = snapshot else {
+        return Some(0.0);
+    };
+    let score = snapshot
+        .primary
+        .iter()
+        .chain(snapshot.secondary.iter())
+        .chain(snapshot.tertiary.iter())
+        .chain(snapshot.carveouts.iter().map(|c| &c.window))
+        .map(|window| window.used_percent * reset_weight(window, now))
+        .fold(0.0, f64::max);
+    Some(score)
+}
+
+/// `providers` ordered most urgent first. Ties keep their given order, so
+/// equally idle providers stay in the configured order.
+pub fn rank_by_urgency(
+    providers: &[Provider],
+    score: impl Fn(Provider) -> Option<f64>,
+) -> Vec<Provider> {
+    let mut ranked: Vec<(Provider, Option<f64>)> =
+        providers.iter().map(|&p| (p, score(p))).collect();
+    ranked.sort_by(|(_, a), (_, b)| match (a, b) {
+        (Some(a), Some(b)) => b.total_cmp(a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+    ranked.into_iter().map(|(provider, _)| provider).collect()
+}
+
+/// 1.0 for a window that just started (or has no known reset), falling to
+/// `RESET_WEIGHT_FLOOR` as its reset approaches.
+fn reset_weight(window: &RateWindow, now: DateTime<Utc>) -> f64 {
+    let (Some(resets_at), Some(minutes)) = (window.resets_at, window.window_minutes) else {
+        return 1.0;
+    };
+    if minutes <= 0 {
+        return 1.0;
+    }
+    let remaining = (resets_at - now).num_seconds() as f64 / (f64::from(minutes) * 60.0);
+    RESET_WEIGHT_FLOOR + (1.0 - RESET_WEIGHT_FLOOR) * remaining.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn window(used_percent: f64, resets_in: Option<Duration>, now: DateTime<Utc>) -> RateWindow {
+        RateWindow::from_reported(used_percent, Some(300), resets_in.map(|d| now + d), None)
+    }
+
+    fn snapshot(primary: Option<RateWindow>, secondary: Option<RateWindow>) -> UsageSnapshot {
+        UsageSnapshot::new(primary, secondary, Utc::now())
+    }
+
+    #[test]
+    fn test_score_discounts_windows_about_to_reset() {
+        let now = Utc::now();
+        let fresh = snapshot(Some(window(0.8, Some(Duration::minutes(300)), now)), None);
+        let resetting = snapshot(Some(window(0.8, Some(Duration::zero()), now)), None);
+        let unknown_reset = snapshot(Some(window(0.8, None, now)), None);
+
+        assert_eq!(urgency_score(Some(&fresh), false, now), Some(0.8));
+        assert_eq!(urgency_score(Some(&resetting), false, now), Some(0.4));
+        assert_eq!(urgency_score(Some(&unknown_reset), false, now), Some(0.8));
+    }
+
+    #[test]
+    fn test_score_uses_highest_window_and_handles_missing_ones() {
+        let now = Utc::now();
+        let both = snapshot(Some(window(0.2, None, now)), Some(window(0.6, None, now)));
+        assert_eq!(urgency_score(Some(&both), false, now), Some(0.6));
+        assert_eq!(
+            urgency_score(Some(&snapshot(None, None)), false, now),
+            Some(0.0)
+        );
+        assert_eq!(urgency_score(None, false, now), Some(0.0));
+        assert_eq!(urgency_score(Some(&both), true, now), None);
+    }
+
+    #[test]
+    fn test_rank_by_urgency() {
+        let providers = [Provider::Claude, Provider::Codex];
+
+        let ranked = rank_by_urgency(&providers, |p| match p {
+            Provider::Claude => Some(0.3),
+            _ => Some(0.9),
+        });
+        assert_eq!(ranked, vec![Provider::Codex, Provider::Claude]);
+
+        // Ties keep the configured order.
+        assert_eq!(rank_by_urgency(&providers, |_| Some(0.5)), providers);
+
+        // A failing provider sorts last, even below one with no usage.
+        let ranked = rank_by_urgency(&providers, |p| match p {
+            Provider::Claude => None,
+            _ => Some(0.0),
+        });
+        assert_eq!(ranked, vec![Provider::Codex, Provider::Claude]);
+    }
+}
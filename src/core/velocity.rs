@@ -0,0 +1,211 @@
+use crate::core::models::RateWindow;
+use crate::core::reset_estimate::same_window_reset;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::VecDeque;
+
+/// The two snapshots a rate is taken from must be at least this far apart,
+/// so a single poll's rounding doesn't read as a burst.
+const MIN_SPAN_MINUTES: i64 = 5;
+/// Only snapshots this recent count; once usage has been flat for this long
+/// the session reads as idle.
+const LOOKBACK_MINUTES: i64 = 30;
+/// Refreshes run every minute or so; this covers the lookback with room.
+const MAX_SAMPLES: usize = 120;
+
+/// How fast the session window is being used up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Velocity {
+    Burning {
+        /// Fraction of the window per hour, like `RateWindow::used_percent`.
+        percent_per_hour: f64,
+        /// From the cost scanner's session token count, when it moved too.
+        tokens_per_hour: Option<f64>,
+    },
+    Idle,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    at: DateTime<Utc>,
+    used_percent: f64,
+    session_tokens: Option<u64>,
+}
+
+/// Burn rate of one provider's session window, from consecutive usage
+/// snapshots. The daemon feeds it on every successful fetch; readers call
+/// `estimate`.
+#[derive(Debug, Clone, Default)]
+pub struct VelocityEstimator {
+    resets_at: Option<DateTime<Utc>>,
+    samples: VecDeque<Sample>,
+}
+
+impl VelocityEstimator {
+    /// Adds the session window as of `at`, starting over when the window
+    /// has reset since the last sample.
+    pub fn record(&mut self, at: DateTime<Utc>, window: &RateWindow, session_tokens: Option<u64>) {
+        let moved = match (self.resets_at, window.resets_at) {
+            (Some(previous), Some(current)) => !same_window_reset(previous, current),
+            _ => false,
+        };
+        let dropped = self
+            .samples
+            .back()
+            .is_some_and(|last| window.used_percent < last.used_percent);
+        if moved || dropped {
+            self.samples.clear();
+        }
+        if window.resets_at.is_some() {
+            self.resets_at = window.resets_at;
+        }
+
+        self.samples.push_back(Sample {
+            at,
+            used_percent: window.used_percent,
+            session_tokens,
+        });
+        let oldest = at - Duration::minutes(LOOKBACK_MINUTES);
+        while self.samples.len() > MAX_SAMPLES
+            || self
+                .samples
+                .front()
+                .is_some_and(|sample| sample.at < oldest)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Samples held, at most `MAX_SAMPLES`.
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// The rate over the last half hour; `None` until two snapshots at
+    /// least five minutes apart fall inside it.
+    pub fn estimate(&self, now: DateTime<Utc>) -> Option<Velocity> {
+        let oldest = now - Duration::minutes(LOOKBACK_MINUTES);
+        let mut recent = self.samples.iter().filter(|sample| sample.at >= oldest);
+        let first = recent.next()?;
+        let newest = recent.next_back()?;
+        let span = newest.at - first.at;
+        if span < Duration::minutes(MIN_SPAN_MINUTES) {
+            return None;
+        }
+
+        let hours = span.num_seconds() as f64 / 3600.0;
+        let percent_per_hour = (newest.used_percent - first.used_percent) / hours;
+        if percent_per_hour <= 0.0 {
+            return Some(Velocity::Idle);
+        }
+        let tokens_per_hour = match (first.session_tokens, newest.session_tokens) {
+            (Some(first), Some(newest)) if newest > first => Some((newest - first) as f64 / hours),
+            _ => None,
+        };
+        Some(Velocity::Burning {
+            percent_per_hour,
+            tokens_per_hour,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(used_percent: f64, resets_at: DateTime<Utc>) -> RateWindow {
+        RateWindow {
+            used_percent,
+            window_minutes: Some(300),
+            resets_at: Some(resets_at),
+            reset_description: None,
+            used_percent_exact: None,
+            resets_at_estimated: false,
+        }
+    }
+
+    fn minutes(start: DateTime<Utc>, minutes: i64) -> DateTime<Utc> {
+        start + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn test_rate_needs_two_snapshots_five_minutes_apart() {
+        let start: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let resets_at = minutes(start, 240);
+        let mut estimator = VelocityEstimator::default();
+
+        estimator.record(start, &window(0.10, resets_at), Some(1_000_000));
+        estimator.record(minutes(start, 3), &window(0.11, resets_at), None);
+        assert_eq!(estimator.estimate(minutes(start, 3)), None);
+
+        estimator.record(
+            minutes(start, 10),
+            &window(0.12, resets_at),
+            Some(1_040_000),
+        );
+        let Some(Velocity::Burning {
+            percent_per_hour,
+            tokens_per_hour,
+        }) = estimator.estimate(minutes(start, 10))
+        else {
+            panic!("expected a burn rate");
+        };
+        assert!((percent_per_hour - 0.12).abs() < 1e-9);
+        assert_eq!(tokens_per_hour, Some(240_000.0));
+    }
+
+    #[test]
+    fn test_window_reset_starts_over() {
+        let start: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let resets_at = minutes(start, 20);
+        let mut estimator = VelocityEstimator::default();
+
+        estimator.record(start, &window(0.80, resets_at), None);
+        estimator.record(minutes(start, 10), &window(0.90, resets_at), None);
+        assert!(matches!(
+            estimator.estimate(minutes(start, 10)),
+            Some(Velocity::Burning { .. })
+        ));
+
+        // Reset times jitter by a few seconds between polls.
+        estimator.record(
+            minutes(start, 11),
+            &window(0.91, resets_at + Duration::seconds(2)),
+            None,
+        );
+        assert_eq!(estimator.samples.len(), 3);
+
+        let next_resets_at = minutes(start, 320);
+        estimator.record(minutes(start, 21), &window(0.01, next_resets_at), None);
+        assert_eq!(estimator.estimate(minutes(start, 21)), None);
+        estimator.record(minutes(start, 27), &window(0.04, next_resets_at), None);
+        let Some(Velocity::Burning {
+            percent_per_hour, ..
+        }) = estimator.estimate(minutes(start, 27))
+        else {
+            panic!("expected a burn rate for the new window");
+        };
+        assert!((percent_per_hour - 0.30).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rate_decays_to_idle() {
+        let start: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let resets_at = minutes(start, 240);
+        let mut estimator = VelocityEstimator::default();
+
+        estimator.record(start, &window(0.10, resets_at), None);
+        for minute in (5..=60).step_by(5) {
+            let used = if minute <= 15 {
+                0.10 + minute as f64 / 1000.0
+            } else {
+                0.115
+            };
+            estimator.record(minutes(start, minute), &window(used, resets_at), None);
+        }
+
+        // Flat for 45 minutes: the burst has left the lookback.
+        assert_eq!(estimator.estimate(minutes(start, 60)), Some(Velocity::Idle));
+        // Nothing recent enough to say anything.
+        assert_eq!(estimator.estimate(minutes(start, 120)), None);
+    }
+}
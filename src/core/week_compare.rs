@@ -0,0 +1,108 @@
+use crate::core::models::DailyTokenUsage;
+use chrono::{Duration, NaiveDate};
+
+/// Tokens per day from the token scan, skipping days without a count.
+pub fn daily_token_series(daily: &[DailyTokenUsage]) -> Vec<(NaiveDate, u64)> {
+    daily
+        .iter()
+        .filter_map(|day| Some((day.date, day.total_tokens?)))
+        .collect()
+}
+
+/// How many percent more tokens went into this week so far than into the
+/// same days of last week: `week_start..=today` against the seven days
+/// before each. Days missing from `daily` count as idle. `None` when last
+/// week's stretch has no tokens to compare against.
+pub fn week_over_week_percent(
+    daily: &[(NaiveDate, u64)],
+    week_start: NaiveDate,
+    today: NaiveDate,
+) -> Option<f64> {
+    if today < week_start {
+        return None;
+    }
+    let week = Duration::days(7);
+    let current = tokens_between(daily, week_start, today);
+    let previous = tokens_between(daily, week_start - week, today - week);
+    if previous == 0 {
+        return None;
+    }
+    Some((current as f64 - previous as f64) / previous as f64 * 100.0)
+}
+
+fn tokens_between(daily: &[(NaiveDate, u64)], first: NaiveDate, last: NaiveDate) -> u64 {
+    daily
+        .iter()
+        .filter(|(date, _)| (first..=last).contains(date))
+        .map(|(_, tokens)| tokens)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+    }
+
+    #[test]
+    fn test_compares_same_stretch_of_last_week() {
+        // This week started on the 12th; today is the 14th, so last week's
+        // 5th to 7th count and the rest of last week doesn't.
+        let daily = vec![
+            (day(5), 400),
+            (day(6), 300),
+            (day(7), 300),
+            (day(8), 5_000),
+            (day(11), 5_000),
+            (day(12), 500),
+            (day(13), 500),
+            (day(14), 220),
+        ];
+        let percent = week_over_week_percent(&daily, day(12), day(14)).unwrap();
+        assert!((percent - 22.0).abs() < 1e-9);
+
+        // First day of the week: only the 12th against the 5th.
+        let percent = week_over_week_percent(&daily, day(12), day(12)).unwrap();
+        assert!((percent - 25.0).abs() < 1e-9);
+
+        let lighter = vec![(day(5), 1_000), (day(12), 250)];
+        let percent = week_over_week_percent(&lighter, day(12), day(14)).unwrap();
+        assert!((percent + 75.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_hidden_without_last_week_data() {
+        // Only this week has tokens, e.g. right after the cache was created.
+        let daily = vec![(day(12), 500), (day(13), 500)];
+        assert_eq!(week_over_week_percent(&daily, day(12), day(14)), None);
+        assert_eq!(week_over_week_percent(&[], day(12), day(14)), None);
+
+        // Last week's usage all came after the point this week has reached.
+        let daily = vec![(day(9), 5_000), (day(12), 500)];
+        assert_eq!(week_over_week_percent(&daily, day(12), day(13)), None);
+
+        // A window that hasn't started yet.
+        assert_eq!(week_over_week_percent(&daily, day(12), day(11)), None);
+    }
+
+    #[test]
+    fn test_series_skips_days_without_counts() {
+        let daily = vec![
+            DailyTokenUsage {
+                date: day(12),
+                total_tokens: Some(500),
+                cost_usd: None,
+                models: Vec::new(),
+            },
+            DailyTokenUsage {
+                date: day(13),
+                total_tokens: None,
+                cost_usd: Some(1.0),
+                models: Vec::new(),
+            },
+        ];
+        assert_eq!(daily_token_series(&daily), vec![(day(12), 500)]);
+    }
+}
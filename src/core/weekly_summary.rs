@@ -0,0 +1,307 @@
+use crate::core::models::{DailyTokenUsage, Provider, RateWindow};
+use crate::core::reset_estimate::same_window_reset;
+use crate::core::state_file;
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Finished weeks kept per provider, as many as `stats` covers.
+const RETENTION_WEEKS: usize = 13;
+
+/// The weekly window in progress: when it started and resets, and the most
+/// of it used so far.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TrackedWeek {
+    pub starts_at: DateTime<Utc>,
+    pub resets_at: DateTime<Utc>,
+    pub used_percent: f64,
+}
+
+impl TrackedWeek {
+    fn new(window: &RateWindow, resets_at: DateTime<Utc>) -> Self {
+        let minutes = window.window_minutes.map(i64::from).unwrap_or(7 * 24 * 60);
+        Self {
+            starts_at: resets_at - Duration::minutes(minutes),
+            resets_at,
+            used_percent: window.used_percent,
+        }
+    }
+}
+
+/// What a usage update did to the tracked weekly window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WeekUpdate {
+    Unchanged,
+    /// A new window is tracked or usage went up; worth saving.
+    Progressed,
+    /// The window reset and this is the week that ended.
+    Ended(TrackedWeek),
+}
+
+/// A weekly window that rolled over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WeeklySummary {
+    pub starts_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    /// The most of the weekly quota used, 0.0 to 1.0.
+    pub used_percent: f64,
+    /// Tokens over the week's days, from the local logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<u64>,
+    /// API-equivalent cost over the week's days, from the local logs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_usd: Option<f64>,
+    /// The day with the most tokens.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub busiest_day: Option<NaiveDate>,
+}
+
+impl WeeklySummary {
+    /// A recap that ended long ago, e.g. after a while away, isn't news.
+    pub fn is_recent(&self, now: DateTime<Utc>) -> bool {
+        now - self.ended_at < Duration::days(7)
+    }
+
+    /// "Last week: 78% of weekly quota used, $23.40 in API-equivalent cost,
+    /// busiest day Thursday."
+    pub fn message(&self) -> String {
+        let mut parts = vec![format!(
+            "{}% of weekly quota used",
+            (self.used_percent * 100.0).round() as u32
+        )];
+        if let Some(cost) = self.cost_usd {
+            parts.push(format!("${:.2} in API-equivalent cost", cost));
+        }
+        if let Some(day) = self.busiest_day {
+            parts.push(format!("busiest day {}", day.format("%A")));
+        }
+        format!("Last week: {}.", parts.join(", "))
+    }
+}
+
+/// Sums the token scan's days from the one `week` started on up to, not
+/// including, the one it reset on. `day_of` maps a time to the day the
+/// scan counts it toward.
+pub fn summarize_week(
+    week: &TrackedWeek,
+    daily: &[DailyTokenUsage],
+    day_of: impl Fn(DateTime<Utc>) -> NaiveDate,
+) -> WeeklySummary {
+    let (first, end) = (day_of(week.starts_at), day_of(week.resets_at));
+    let days: Vec<&DailyTokenUsage> = daily
+        .iter()
+        .filter(|day| first <= day.date && day.date < end)
+        .collect();
+    let tokens: Vec<u64> = days.iter().filter_map(|day| day.total_tokens).collect();
+    let costs: Vec<f64> = days.iter().filter_map(|day| day.cost_usd).collect();
+    WeeklySummary {
+        starts_at: week.starts_at,
+        ended_at: week.resets_at,
+        used_percent: week.used_percent,
+        tokens: (!tokens.is_empty()).then(|| tokens.iter().sum()),
+        cost_usd: (!costs.is_empty()).then(|| costs.iter().sum()),
+        busiest_day: days
+            .iter()
+            .filter_map(|day| Some((day.date, day.total_tokens.filter(|t| *t > 0)?)))
+            .max_by_key(|(date, tokens)| (*tokens, std::cmp::Reverse(*date)))
+            .map(|(date, _)| date),
+    }
+}
+
+/// Each provider's weekly window in progress and its last finished weeks,
+/// oldest first, keyed by provider id. Persisted so a week that resets
+/// while the daemon isn't running is still recapped.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeeklySummaryLog {
+    tracked: BTreeMap<String, TrackedWeek>,
+    summaries: BTreeMap<String, Vec<WeeklySummary>>,
+}
+
+impl WeeklySummaryLog {
+    pub fn load() -> Self {
+        state_file::load("weekly-summaries")
+    }
+
+    pub fn save(&self) -> Result<()> {
+        state_file::save("weekly-summaries", self)
+    }
+
+    /// Each provider with finished weeks.
+    pub fn providers(&self) -> impl Iterator<Item = (Provider, &[WeeklySummary])> {
+        self.summaries.iter().filter_map(|(id, summaries)| {
+            Provider::from_id(id).map(|provider| (provider, summaries.as_slice()))
+        })
+    }
+
+    /// Tracks `provider`'s weekly window. A reset time later than the
+    /// tracked one means that week ended. An estimated reset time is too
+    /// rough to tell a rollover from drift, so it is ignored.
+    pub fn observe(&mut self, provider: Provider, window: &RateWindow) -> WeekUpdate {
+        let Some(resets_at) = window.resets_at.filter(|_| !window.resets_at_estimated) else {
+            return WeekUpdate::Unchanged;
+        };
+        let id = provider.id();
+        let Some(tracked) = self.tracked.get_mut(&id) else {
+            self.tracked.insert(id, TrackedWeek::new(window, resets_at));
+            return WeekUpdate::Progressed;
+        };
+
+        if same_window_reset(tracked.resets_at, resets_at) {
+            if window.used_percent <= tracked.used_percent {
+                return WeekUpdate::Unchanged;
+            }
+            tracked.used_percent = window.used_percent;
+            return WeekUpdate::Progressed;
+        }
+
+        let ended = std::mem::replace(tracked, TrackedWeek::new(window, resets_at));
+        if ended.resets_at < resets_at {
+            WeekUpdate::Ended(ended)
+        } else {
+            // A reset time that moved back, e.g. after signing in to
+            // another account: nothing to recap.
+            WeekUpdate::Progressed
+        }
+    }
+
+    pub fn push(&mut self, provider: Provider, summary: WeeklySummary) {
+        let summaries = self.summaries.entry(provider.id()).or_default();
+        summaries.push(summary);
+        let excess = summaries.len().saturating_sub(RETENTION_WEEKS);
+        summaries.drain(..excess);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(at: &str) -> DateTime<Utc> {
+        at.parse().unwrap()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+    }
+
+    fn weekly(used_percent: f64, resets_at: &str) -> RateWindow {
+        RateWindow::from_reported(used_percent, Some(10080), Some(at(resets_at)), None)
+    }
+
+    fn day(date_str: &str, tokens: Option<u64>, cost_usd: Option<f64>) -> DailyTokenUsage {
+        DailyTokenUsage {
+            date: date(date_str),
+            total_tokens: tokens,
+            cost_usd,
+            models: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_rollover_ends_the_tracked_week() {
+        let mut log = WeeklySummaryLog::default();
+        let claude = Provider::Claude;
+
+        assert_eq!(
+            log.observe(claude, &weekly(0.40, "2026-10-12T09:00:00Z")),
+            WeekUpdate::Progressed
+        );
+        assert_eq!(
+            log.observe(claude, &weekly(0.40, "2026-10-12T09:00:20Z")),
+            WeekUpdate::Unchanged
+        );
+        assert_eq!(
+            log.observe(claude, &weekly(0.78, "2026-10-12T08:59:45Z")),
+            WeekUpdate::Progressed
+        );
+        // A dip within the same week keeps the peak.
+        assert_eq!(
+            log.observe(claude, &weekly(0.70, "2026-10-12T09:00:00Z")),
+            WeekUpdate::Unchanged
+        );
+
+        assert_eq!(
+            log.observe(claude, &weekly(0.01, "2026-10-19T09:00:00Z")),
+            WeekUpdate::Ended(TrackedWeek {
+                starts_at: at("2026-10-05T09:00:00Z"),
+                resets_at: at("2026-10-12T09:00:00Z"),
+                used_percent: 0.78,
+            })
+        );
+        assert_eq!(
+            log.observe(claude, &weekly(0.02, "2026-10-19T09:00:00Z")),
+            WeekUpdate::Progressed
+        );
+
+        // A reset time that moves back starts over without a recap.
+        assert_eq!(
+            log.observe(claude, &weekly(0.10, "2026-10-15T09:00:00Z")),
+            WeekUpdate::Progressed
+        );
+        let mut estimated = weekly(0.10, "2026-10-30T09:00:00Z");
+        estimated.resets_at_estimated = true;
+        assert_eq!(log.observe(claude, &estimated), WeekUpdate::Unchanged);
+    }
+
+    #[test]
+    fn test_summarize_week() {
+        let week = TrackedWeek {
+            starts_at: at("2026-10-05T09:00:00Z"),
+            resets_at: at("2026-10-12T09:00:00Z"),
+            used_percent: 0.781,
+        };
+        let daily = vec![
+            day("2026-10-04", Some(90_000), Some(9.0)),
+            day("2026-10-05", Some(10_000), Some(1.40)),
+            day("2026-10-08", Some(50_000), Some(12.0)),
+            day("2026-10-09", Some(30_000), None),
+            day("2026-10-11", Some(20_000), Some(10.0)),
+            day("2026-10-12", Some(70_000), Some(7.0)),
+        ];
+
+        let summary = summarize_week(&week, &daily, |at| at.date_naive());
+        assert_eq!(summary.tokens, Some(110_000));
+        assert!((summary.cost_usd.unwrap() - 23.40).abs() < 1e-9);
+        assert_eq!(summary.busiest_day, Some(date("2026-10-08")));
+        assert_eq!(
+            summary.message(),
+            "Last week: 78% of weekly quota used, $23.40 in API-equivalent cost, \
+             busiest day Thursday."
+        );
+
+        // Without the token scan only the quota is known.
+        let summary = summarize_week(&week, &[], |at| at.date_naive());
+        assert_eq!(summary.tokens, None);
+        assert_eq!(summary.cost_usd, None);
+        assert_eq!(summary.busiest_day, None);
+        assert_eq!(summary.message(), "Last week: 78% of weekly quota used.");
+        assert!(summary.is_recent(at("2026-10-18T09:00:00Z")));
+        assert!(!summary.is_recent(at("2026-10-19T09:00:00Z")));
+    }
+
+    #[test]
+    fn test_push_keeps_recent_weeks() {
+        let mut log = WeeklySummaryLog::default();
+        let start = at("2026-01-05T09:00:00Z");
+        for week in 0..20 {
+            let starts_at = start + Duration::weeks(week);
+            let tracked = TrackedWeek {
+                starts_at,
+                resets_at: starts_at + Duration::weeks(1),
+                used_percent: 0.5,
+            };
+            log.push(
+                Provider::Codex,
+                summarize_week(&tracked, &[], |at| at.date_naive()),
+            );
+        }
+
+        let (provider, summaries) = log.providers().next().unwrap();
+        assert_eq!(provider, Provider::Codex);
+        assert_eq!(summaries.len(), RETENTION_WEEKS);
+        assert_eq!(summaries[0].starts_at, start + Duration::weeks(7));
+    }
+}
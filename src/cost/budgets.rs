@@ -0,0 +1,332 @@
+use crate::core::models::CostSnapshot;
+use crate::core::settings::write_atomically;
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// Percentages of a monthly budget that notify when crossed.
+pub const BUDGET_THRESHOLDS: [u32; 2] = [80, 100];
+
+/// A project's spend this month and its budget, if it has one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectSpend {
+    pub project: String,
+    pub spent: f64,
+    pub budget: Option<f64>,
+}
+
+impl ProjectSpend {
+    /// Spend as a percentage of the budget. `None` without a budget, or with
+    /// a zero budget where any spend is over.
+    pub fn percent(&self) -> Option<f64> {
+        self.budget
+            .filter(|budget| *budget > 0.0)
+            .map(|budget| self.spent / budget * 100.0)
+    }
+}
+
+/// Claude and Codex spend this calendar month against `cost.monthly_budget`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonthlySpend {
+    pub spent: f64,
+    pub budget: f64,
+}
+
+impl MonthlySpend {
+    /// Spend as a percentage of the budget, which settings validation keeps
+    /// positive.
+    pub fn percent(&self) -> f64 {
+        self.spent / self.budget * 100.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetAlert {
+    pub project: String,
+    pub threshold: u32,
+    pub spent: f64,
+    pub budget: f64,
+}
+
+/// Sums each provider's per-project spend, since a budget covers a project's
+/// Claude and Codex usage together.
+pub fn combined_project_costs<'a>(
+    costs: impl IntoIterator<Item = &'a CostSnapshot>,
+) -> BTreeMap<String, f64> {
+    let mut combined: BTreeMap<String, f64> = BTreeMap::new();
+    for cost in costs {
+        for (project, spent) in &cost.project_costs {
+            *combined.entry(project.clone()).or_default() += spent;
+        }
+    }
+    combined
+}
+
+/// This month's spend summed over `costs`. Codex workspaces share one set of
+/// logs, so callers pass only the Claude and Codex snapshots.
+pub fn combined_monthly_cost<'a>(costs: impl IntoIterator<Item = &'a CostSnapshot>) -> f64 {
+    costs.into_iter().map(|cost| cost.monthly_cost).sum()
+}
+
+/// Every scanned project plus any budgeted one, highest spend first.
+pub fn project_spend(
+    costs: &BTreeMap<String, f64>,
+    budgets: &BTreeMap<String, f64>,
+) -> Vec<ProjectSpend> {
+    let mut projects: Vec<ProjectSpend> = costs
+        .keys()
+        .chain(budgets.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|project| ProjectSpend {
+            project: project.clone(),
+            spent: costs.get(project).copied().unwrap_or(0.0),
+            budget: budgets.get(project).copied(),
+        })
+        .collect();
+    projects.sort_by(|a, b| b.spent.total_cmp(&a.spent));
+    projects
+}
+
+/// Budget keys that no scanned project matches, most likely typos.
+pub fn unmatched_budgets<'a>(
+    budgets: &'a BTreeMap<String, f64>,
+    costs: &BTreeMap<String, f64>,
+) -> Vec<&'a str> {
+    budgets
+        .keys()
+        .filter(|project| !costs.contains_key(*project))
+        .map(String::as_str)
+        .collect()
+}
+
+/// Which thresholds have already notified this month, persisted so a daemon
+/// restart doesn't repeat them.
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetState {
+    /// `YYYY-MM` of the month `fired` belongs to.
+    month: String,
+    fired: BTreeMap<String, Vec<u32>>,
+    /// Thresholds of `cost.monthly_budget` that have notified.
+    total_fired: Vec<u32>,
+}
+
+impl BudgetState {
+    fn state_path() -> Option<PathBuf> {
+        dirs::data_local_dir().map(|d| d.join("claude-bar").join("budgets.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::state_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::state_path().context("Could not determine data directory")?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        write_atomically(&path, &serde_json::to_string_pretty(self)?)?;
+        tracing::debug!(?path, "Saved budget state");
+        Ok(())
+    }
+
+    /// Records thresholds newly crossed this month and returns one alert per
+    /// project for the highest of them, so a jump straight past 100% doesn't
+    /// also announce 80%.
+    pub fn check(
+        &mut self,
+        today: NaiveDate,
+        costs: &BTreeMap<String, f64>,
+        budgets: &BTreeMap<String, f64>,
+    ) -> Vec<BudgetAlert> {
+        self.start_month(today);
+
+        let mut alerts = Vec::new();
+        for (project, &budget) in budgets {
+            let spent = costs.get(project).copied().unwrap_or(0.0);
+            let fired = self.fired.entry(project.clone()).or_default();
+            let Some(threshold) = newly_crossed(fired, spent, budget) else {
+                continue;
+            };
+            alerts.push(BudgetAlert {
+                project: project.clone(),
+                threshold,
+                spent,
+                budget,
+            });
+        }
+        self.fired.retain(|_, fired| !fired.is_empty());
+        alerts
+    }
+
+    /// Like `check`, for the combined monthly budget. Returns the highest
+    /// threshold newly crossed this month.
+    pub fn check_total(&mut self, today: NaiveDate, spend: MonthlySpend) -> Option<u32> {
+        self.start_month(today);
+        newly_crossed(&mut self.total_fired, spend.spent, spend.budget)
+    }
+
+    fn start_month(&mut self, today: NaiveDate) {
+        let month = today.format("%Y-%m").to_string();
+        if self.month != month {
+            self.month = month;
+            self.fired.clear();
+            self.total_fired.clear();
+        }
+    }
+}
+
+/// Marks every threshold `spent` has reached as fired and returns the
+/// highest one that hadn't been.
+fn newly_crossed(fired: &mut Vec<u32>, spent: f64, budget: f64) -> Option<u32> {
+    let crossed: Vec<u32> = BUDGET_THRESHOLDS
+        .into_iter()
+        .filter(|&threshold| spent > 0.0 && spent >= budget * f64::from(threshold) / 100.0)
+        .collect();
+    let threshold = *crossed.iter().rev().find(|t| !fired.contains(t))?;
+    for threshold in crossed {
+        if !fired.contains(&threshold) {
+            fired.push(threshold);
+        }
+    }
+    Some(threshold)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    fn map(entries: &[(&str, f64)]) -> BTreeMap<String, f64> {
+        entries
+            .iter()
+            .map(|(project, value)| (project.to_string(), *value))
+            .collect()
+    }
+
+    #[test]
+    fn test_thresholds_fire_once_per_month() {
+        let budgets = map(&[("acme-backend", 50.0), ("acme-web", 20.0)]);
+        let mut state = BudgetState::default();
+
+        let alerts = state.check(day(3, 10), &map(&[("acme-backend", 30.0)]), &budgets);
+        assert!(alerts.is_empty());
+
+        let alerts = state.check(day(3, 12), &map(&[("acme-backend", 41.0)]), &budgets);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].project, "acme-backend");
+        assert_eq!(alerts[0].threshold, 80);
+
+        let alerts = state.check(day(3, 13), &map(&[("acme-backend", 45.0)]), &budgets);
+        assert!(alerts.is_empty());
+
+        let alerts = state.check(day(3, 20), &map(&[("acme-backend", 50.0)]), &budgets);
+        assert_eq!(alerts[0].threshold, 100);
+
+        let alerts = state.check(day(3, 21), &map(&[("acme-backend", 80.0)]), &budgets);
+        assert!(alerts.is_empty());
+
+        // A new month starts over.
+        let alerts = state.check(day(4, 1), &map(&[("acme-backend", 45.0)]), &budgets);
+        assert_eq!(alerts[0].threshold, 80);
+    }
+
+    #[test]
+    fn test_jump_past_both_thresholds_alerts_once() {
+        let budgets = map(&[("acme-web", 20.0)]);
+        let mut state = BudgetState::default();
+
+        let alerts = state.check(day(3, 10), &map(&[("acme-web", 25.0)]), &budgets);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].threshold, 100);
+        assert!(state
+            .check(day(3, 11), &map(&[("acme-web", 26.0)]), &budgets)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_monthly_budget_fires_once_per_threshold() {
+        let spend = |spent: f64| MonthlySpend {
+            spent,
+            budget: 200.0,
+        };
+        let mut state = BudgetState::default();
+
+        assert_eq!(state.check_total(day(3, 10), spend(142.0)), None);
+        assert_eq!(spend(142.0).percent(), 71.0);
+        assert_eq!(state.check_total(day(3, 12), spend(160.0)), Some(80));
+        assert_eq!(state.check_total(day(3, 13), spend(170.0)), None);
+        assert_eq!(state.check_total(day(3, 20), spend(230.0)), Some(100));
+        assert_eq!(state.check_total(day(3, 21), spend(240.0)), None);
+
+        // Project budgets and the monthly budget are tracked apart.
+        let budgets = map(&[("acme-web", 20.0)]);
+        let alerts = state.check(day(3, 22), &map(&[("acme-web", 17.0)]), &budgets);
+        assert_eq!(alerts[0].threshold, 80);
+
+        let json = serde_json::to_string(&state).unwrap();
+        let mut restored: BudgetState = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.check_total(day(3, 23), spend(250.0)), None);
+        assert_eq!(restored.check_total(day(4, 1), spend(250.0)), Some(100));
+
+        let claude = CostSnapshot {
+            monthly_cost: 120.0,
+            ..CostSnapshot::default()
+        };
+        let codex = CostSnapshot {
+            monthly_cost: 22.0,
+            ..CostSnapshot::default()
+        };
+        assert_eq!(combined_monthly_cost([&claude, &codex]), 142.0);
+    }
+
+    #[test]
+    fn test_state_roundtrip() {
+        let budgets = map(&[("acme-backend", 50.0)]);
+        let mut state = BudgetState::default();
+        state.check(day(3, 10), &map(&[("acme-backend", 45.0)]), &budgets);
+
+        let json = serde_json::to_string(&state).unwrap();
+        let mut restored: BudgetState = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, state);
+        assert!(restored
+            .check(day(3, 11), &map(&[("acme-backend", 46.0)]), &budgets)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_project_spend_and_unmatched_budgets() {
+        let claude = CostSnapshot {
+            project_costs: map(&[("acme-backend", 12.0), ("scratch", 1.0)]),
+            ..CostSnapshot::default()
+        };
+        let codex = CostSnapshot {
+            project_costs: map(&[("acme-backend", 3.0)]),
+            ..CostSnapshot::default()
+        };
+        let costs = combined_project_costs([&claude, &codex]);
+        let budgets = map(&[("acme-backend", 50.0), ("acme-backnd", 10.0)]);
+
+        let rows = project_spend(&costs, &budgets);
+        assert_eq!(rows[0].project, "acme-backend");
+        assert_eq!(rows[0].spent, 15.0);
+        assert_eq!(rows[0].percent(), Some(30.0));
+        assert_eq!(rows[1].project, "scratch");
+        assert_eq!(rows[1].percent(), None);
+        assert_eq!(rows[2].project, "acme-backnd");
+        assert_eq!(rows[2].spent, 0.0);
+
+        assert_eq!(unmatched_budgets(&budgets, &costs), vec!["acme-backnd"]);
+    }
+}
@@ -1,15 +1,24 @@
+use crate::core::settings::CostTimezone;
+use crate::cost::claude_desktop::ClaudeDesktopLogs;
 use crate::cost::pricing::PricingStore;
-use crate::cost::scanner::{CostScanner, LogEntry};
+use crate::cost::project_filter::ProjectFilter;
+use crate::cost::scanner::{day_of, project_name, CostScanner, LogEntry, ScanContext};
 use anyhow::Result;
-use chrono::{Local, NaiveDate};
+use chrono::{NaiveDate, Utc};
 use serde::Deserialize;
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
 
+#[derive(Clone)]
 pub struct ClaudeCostScanner {
     project_dirs: Vec<PathBuf>,
+    /// Claude Desktop's logs, scanned too when `cost.include_claude_desktop`
+    /// is set.
+    desktop: Option<ClaudeDesktopLogs>,
+    project_filter: ProjectFilter,
 }
 
 impl ClaudeCostScanner {
@@ -24,25 +33,79 @@ impl ClaudeCostScanner {
             project_dirs.push(config.join("claude/projects"));
         }
 
-        Self { project_dirs }
+        Self::with_project_dirs(project_dirs)
     }
 
-    fn find_jsonl_files(&self, since: NaiveDate, until: NaiveDate) -> Vec<PathBuf> {
-        let mut files = Vec::new();
+    pub fn with_project_dirs(project_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            project_dirs,
+            desktop: None,
+            project_filter: ProjectFilter::default(),
+        }
+    }
+
+    pub fn set_include_desktop(&mut self, include: bool) {
+        self.desktop = include.then(ClaudeDesktopLogs::new);
+    }
 
+    pub fn set_project_filter(&mut self, filter: ProjectFilter) {
+        self.project_filter = filter;
+    }
+
+    /// The configured directories that exist, resolved through symlinks and
+    /// with aliases of the same directory dropped.
+    fn unique_project_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = Vec::new();
+        let mut duplicates = 0;
         for dir in &self.project_dirs {
-            if !dir.exists() {
+            let Ok(resolved) = std::fs::canonicalize(dir) else {
                 continue;
+            };
+            if dirs.contains(&resolved) {
+                duplicates += 1;
+            } else {
+                dirs.push(resolved);
             }
+        }
+        if duplicates > 0 {
+            tracing::debug!(
+                duplicates,
+                "Dropped Claude project directories that alias another"
+            );
+        }
+        dirs
+    }
 
-            if let Ok(entries) = Self::walk_dir(dir) {
+    /// The session logs that may hold entries from `since` to `until`,
+    /// and how many project directories the project filter left out.
+    fn find_jsonl_files(
+        &self,
+        since: NaiveDate,
+        until: NaiveDate,
+        timezone: CostTimezone,
+    ) -> (Vec<PathBuf>, usize) {
+        let mut files = Vec::new();
+        let mut excluded = 0;
+        // Bind mounts and symlinked subtrees can reach one file by two paths.
+        let mut seen_files: HashSet<(u64, u64)> = HashSet::new();
+
+        for dir in &self.unique_project_dirs() {
+            let mut entries = Vec::new();
+            let included = !self.project_filter.has_allowlist();
+            if let Ok(skipped) = self.walk_dir(dir, Path::new(""), included, &mut entries) {
+                excluded += skipped;
                 for entry in entries {
                     if entry.extension().is_some_and(|ext| ext == "jsonl") {
+                        if let Ok(metadata) = std::fs::metadata(&entry) {
+                            if !seen_files.insert((metadata.dev(), metadata.ino())) {
+                                continue;
+                            }
+                        }
                         if let Some(file_date) = Self::extract_date_from_path(&entry) {
                             if file_date >= since && file_date <= until {
                                 files.push(entry);
                             }
-                        } else if Self::file_mtime_in_range(&entry, since, until) {
+                        } else if Self::modified_since(&entry, since, timezone) {
                             files.push(entry);
                         }
                     }
@@ -50,24 +113,45 @@ impl ClaudeCostScanner {
             }
         }
 
-        files
+        (files, excluded)
     }
 
-    fn walk_dir(dir: &PathBuf) -> Result<Vec<PathBuf>> {
-        let mut files = Vec::new();
+    /// Adds the files under `dir`, at `relative` under its projects
+    /// directory, to `files`: only those under a directory on the allowlist
+    /// (`included`), and none under an excluded directory, which isn't even
+    /// read. Returns how many project directories were left out.
+    fn walk_dir(
+        &self,
+        dir: &Path,
+        relative: &Path,
+        included: bool,
+        files: &mut Vec<PathBuf>,
+    ) -> Result<usize> {
+        let mut excluded = 0;
 
         for entry in std::fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
 
             if path.is_dir() {
-                files.extend(Self::walk_dir(&path)?);
-            } else {
+                let relative = relative.join(entry.file_name());
+                if self.project_filter.excludes(&relative) {
+                    excluded += 1;
+                    continue;
+                }
+                let included = included || self.project_filter.includes(&relative);
+                let kept = files.len();
+                excluded += self.walk_dir(&path, &relative, included, files)?;
+                // A top-level project the allowlist left out entirely.
+                if !included && files.len() == kept && relative.components().count() == 1 {
+                    excluded += 1;
+                }
+            } else if included {
                 files.push(path);
             }
         }
 
-        Ok(files)
+        Ok(excluded)
     }
 
     fn extract_date_from_path(path: &Path) -> Option<NaiveDate> {
@@ -75,27 +159,41 @@ impl ClaudeCostScanner {
         NaiveDate::parse_from_str(file_name, "%Y-%m-%d").ok()
     }
 
-    fn file_mtime_in_range(path: &Path, since: NaiveDate, until: NaiveDate) -> bool {
+    /// Whether a session file may hold entries from `since` on, judged by
+    /// its mtime so stale sessions are skipped without being opened. A day of
+    /// slack covers clock and timezone skew; files still being appended to
+    /// are kept however recent.
+    fn modified_since(path: &Path, since: NaiveDate, timezone: CostTimezone) -> bool {
         let Ok(metadata) = std::fs::metadata(path) else {
             return true;
         };
         let Ok(modified) = metadata.modified() else {
             return true;
         };
-        let modified_date = chrono::DateTime::<chrono::Local>::from(modified).date_naive();
-        modified_date >= since && modified_date <= until
+        let modified_date = day_of(chrono::DateTime::<Utc>::from(modified), timezone);
+        modified_date >= since - chrono::Duration::days(1)
     }
 
+    /// Adds the id of every message counted to `message_ids`, for the
+    /// Desktop logs to skip.
     fn parse_file(
         &self,
         path: &PathBuf,
         since: NaiveDate,
         until: NaiveDate,
+        timezone: CostTimezone,
+        message_ids: &mut HashSet<String>,
     ) -> Result<Vec<LogEntry>> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
         let mut entries = Vec::new();
         let mut seen_ids: HashSet<String> = HashSet::new();
+        let mut project: Option<String> = None;
+        // Claude Code writes one file per session, named by its id.
+        let session = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_string);
 
         for line in reader.lines() {
             let line = match line {
@@ -118,6 +216,12 @@ impl ClaudeCostScanner {
                 }
             };
 
+            // Every line records the session's working directory; keep the
+            // last one for lines that don't.
+            if let Some(cwd) = entry.cwd.as_deref() {
+                project = project_name(cwd);
+            }
+
             if entry.entry_type != "assistant" {
                 continue;
             }
@@ -134,13 +238,14 @@ impl ClaudeCostScanner {
 
             let timestamp = match &entry.timestamp {
                 Some(ts) => match chrono::DateTime::parse_from_rfc3339(ts) {
-                    Ok(dt) => dt.with_timezone(&Local).date_naive(),
+                    Ok(dt) => dt.with_timezone(&Utc),
                     Err(_) => continue,
                 },
                 None => continue,
             };
+            let date = day_of(timestamp, timezone);
 
-            if timestamp < since || timestamp > until {
+            if date < since || date > until {
                 continue;
             }
 
@@ -156,17 +261,23 @@ impl ClaudeCostScanner {
                 }
                 seen_ids.insert(dedup_key);
             }
+            if let Some(id) = &message.id {
+                message_ids.insert(id.clone());
+            }
 
             let model = message.model.unwrap_or_else(|| "unknown".to_string());
             let model = PricingStore::normalize_model_name(&model);
 
             entries.push(LogEntry {
-                date: timestamp,
+                date,
+                timestamp: Some(timestamp),
                 model,
                 input_tokens: usage.input_tokens.unwrap_or(0),
                 output_tokens: usage.output_tokens.unwrap_or(0),
                 cache_creation_tokens: usage.cache_creation_input_tokens.unwrap_or(0),
                 cache_read_tokens: usage.cache_read_input_tokens.unwrap_or(0),
+                project: project.clone(),
+                session: session.clone(),
             });
         }
 
@@ -181,25 +292,57 @@ impl Default for ClaudeCostScanner {
 }
 
 impl CostScanner for ClaudeCostScanner {
-    fn scan_entries(&self, since: NaiveDate, until: NaiveDate) -> Result<Vec<LogEntry>> {
+    fn for_each_entry(
+        &self,
+        since: NaiveDate,
+        until: NaiveDate,
+        ctx: &ScanContext,
+        visit: &mut dyn FnMut(LogEntry),
+    ) -> Result<()> {
         tracing::debug!(dirs = ?self.project_dirs, "Scanning Claude project directories");
 
-        let files = self.find_jsonl_files(since, until);
-        tracing::debug!(count = files.len(), "Found JSONL files");
+        let (files, excluded) = self.find_jsonl_files(since, until, ctx.timezone());
+        tracing::debug!(count = files.len(), excluded, "Found JSONL files");
 
-        let entries: Vec<LogEntry> = files
-            .iter()
-            .filter_map(|file| match self.parse_file(file, since, until) {
-                Ok(entries) => Some(entries),
-                Err(e) => {
-                    tracing::debug!(?file, error = %e, "Failed to parse file");
-                    None
+        ctx.add_excluded_projects(excluded);
+
+        ctx.add_files(files.len());
+
+        let mut message_ids = HashSet::new();
+        for file in &files {
+            ctx.check_cancelled()?;
+            match self.parse_file(file, since, until, ctx.timezone(), &mut message_ids) {
+                Ok(parsed) => parsed.into_iter().for_each(&mut *visit),
+                Err(e) => tracing::debug!(?file, error = %e, "Failed to parse file"),
+            }
+            ctx.file_scanned();
+        }
+
+        if let Some(desktop) = &self.desktop {
+            let files = desktop.find_log_files(since, ctx.timezone());
+            tracing::debug!(
+                dirs = ?desktop.log_dirs(),
+                count = files.len(),
+                "Found Claude Desktop logs"
+            );
+            ctx.add_files(files.len());
+            for file in &files {
+                ctx.check_cancelled()?;
+                match ClaudeDesktopLogs::parse_file(
+                    file,
+                    since,
+                    until,
+                    ctx.timezone(),
+                    &mut message_ids,
+                ) {
+                    Ok(parsed) => parsed.into_iter().for_each(&mut *visit),
+                    Err(e) => tracing::debug!(?file, error = %e, "Failed to parse file"),
                 }
-            })
-            .flatten()
-            .collect();
+                ctx.file_scanned();
+            }
+        }
 
-        Ok(entries)
+        Ok(())
     }
 }
 
@@ -210,6 +353,8 @@ struct RawLogEntry {
     timestamp: Option<String>,
     #[serde(rename = "requestId")]
     request_id: Option<String>,
+    #[serde(default)]
+    cwd: Option<String>,
     message: Option<MessageData>,
 }
 
@@ -231,6 +376,8 @@ struct UsageData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::cost::scanner::CancellationToken;
+    use chrono::TimeZone;
 
     #[test]
     fn test_parse_log_entry() {
@@ -273,6 +420,289 @@ mod tests {
         assert_eq!(entry.entry_type, "user");
     }
 
+    #[test]
+    fn test_parse_file_buckets_days_by_timezone() {
+        // 23:30 local on Jan 17 is already Jan 18 in UTC for zones west of it.
+        let written = chrono::Local
+            .with_ymd_and_hms(2026, 1, 17, 23, 30, 0)
+            .single()
+            .unwrap()
+            .with_timezone(&Utc);
+        let line = format!(
+            r#"{{"type":"assistant","timestamp":"{}","requestId":"req_1","message":{{"id":"msg_1","model":"claude-sonnet-4","usage":{{"input_tokens":100,"output_tokens":50}}}}}}"#,
+            written.to_rfc3339()
+        );
+        let path =
+            std::env::temp_dir().join(format!("claude-bar-claude-tz-{}.jsonl", std::process::id()));
+        std::fs::write(&path, line).unwrap();
+
+        let scanner = ClaudeCostScanner::new();
+        let since = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let until = NaiveDate::from_ymd_opt(2026, 1, 31).unwrap();
+        let local = scanner
+            .parse_file(
+                &path,
+                since,
+                until,
+                CostTimezone::Local,
+                &mut HashSet::new(),
+            )
+            .unwrap();
+        let utc = scanner
+            .parse_file(&path, since, until, CostTimezone::Utc, &mut HashSet::new())
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(local[0].date, NaiveDate::from_ymd_opt(2026, 1, 17).unwrap());
+        assert_eq!(utc[0].date, written.date_naive());
+    }
+
+    #[test]
+    fn test_parse_file_attributes_project_from_cwd() {
+        let now = Utc::now();
+        let assistant = |id: &str, cwd: &str| {
+            format!(
+                r#"{{"type":"assistant","timestamp":"{}",{}"requestId":"{}","message":{{"id":"{}","model":"claude-sonnet-4","usage":{{"input_tokens":100,"output_tokens":50}}}}}}"#,
+                now.to_rfc3339(),
+                cwd,
+                id,
+                id
+            )
+        };
+        let lines = [
+            assistant("a", ""),
+            r#"{"type":"user","cwd":"/home/me/src/acme-backend","message":{"content":"hi"}}"#
+                .to_string(),
+            assistant("b", ""),
+            assistant("c", r#""cwd":"/home/me/src/acme-web","#),
+        ];
+        let path = std::env::temp_dir().join(format!(
+            "claude-bar-claude-project-{}.jsonl",
+            std::process::id()
+        ));
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let today = now.date_naive();
+        let entries = ClaudeCostScanner::new()
+            .parse_file(&path, today, today, CostTimezone::Utc, &mut HashSet::new())
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let projects: Vec<_> = entries.iter().map(|e| e.project.as_deref()).collect();
+        assert_eq!(projects, vec![None, Some("acme-backend"), Some("acme-web")]);
+        let session = format!("claude-bar-claude-project-{}", std::process::id());
+        assert!(entries
+            .iter()
+            .all(|e| e.session.as_deref() == Some(session.as_str())));
+    }
+
+    #[test]
+    fn test_symlinked_project_dir_is_counted_once() {
+        let root =
+            std::env::temp_dir().join(format!("claude-bar-claude-alias-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let projects = root.join("projects");
+        let alias = root.join("alias");
+        std::fs::create_dir_all(projects.join("my-project")).unwrap();
+        std::os::unix::fs::symlink(&projects, &alias).unwrap();
+        // A symlinked subtree inside the real directory reaches the same file.
+        std::os::unix::fs::symlink(projects.join("my-project"), projects.join("linked")).unwrap();
+        let now = Utc::now();
+        std::fs::write(
+            projects.join("my-project/session.jsonl"),
+            format!(
+                r#"{{"type":"assistant","timestamp":"{}","requestId":"req_1","message":{{"id":"msg_1","model":"claude-sonnet-4","usage":{{"input_tokens":100,"output_tokens":50}}}}}}"#,
+                now.to_rfc3339()
+            ),
+        )
+        .unwrap();
+
+        let scanner = ClaudeCostScanner::with_project_dirs(vec![
+            projects.clone(),
+            alias,
+            root.join("missing"),
+        ]);
+        let (tx, _rx) = tokio::sync::watch::channel(Default::default());
+        let ctx = ScanContext::new(CancellationToken::new(), tx, CostTimezone::Utc);
+        let today = now.date_naive();
+        let entries = scanner.scan_entries(today, today, &ctx).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(scanner.project_dirs.len(), 3);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].input_tokens, 100);
+    }
+
+    #[test]
+    fn test_desktop_logs_merge_with_code_logs() {
+        let root =
+            std::env::temp_dir().join(format!("claude-bar-claude-desktop-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let projects = root.join("projects/my-project");
+        let logs = root.join("logs");
+        std::fs::create_dir_all(&projects).unwrap();
+        std::fs::create_dir_all(&logs).unwrap();
+        let now = Utc::now();
+        std::fs::write(
+            projects.join("session.jsonl"),
+            format!(
+                r#"{{"type":"assistant","timestamp":"{}","cwd":"/home/me/my-project","requestId":"req_1","message":{{"id":"msg_shared","model":"claude-sonnet-4","usage":{{"input_tokens":100,"output_tokens":50}}}}}}"#,
+                now.to_rfc3339()
+            ),
+        )
+        .unwrap();
+        let desktop_line = |id: &str, input_tokens: u64| {
+            format!(
+                r#"[info] {{"type":"message_usage","timestamp":"{}","message_id":"{}","model":"claude-sonnet-4","usage":{{"input_tokens":{},"output_tokens":5}}}}"#,
+                now.to_rfc3339(),
+                id,
+                input_tokens
+            )
+        };
+        std::fs::write(
+            logs.join("main.log"),
+            [
+                desktop_line("msg_shared", 100),
+                desktop_line("msg_chat", 700),
+            ]
+            .join("\n"),
+        )
+        .unwrap();
+
+        let mut scanner = ClaudeCostScanner::with_project_dirs(vec![root.join("projects")]);
+        let (tx, rx) = tokio::sync::watch::channel(Default::default());
+        let ctx = ScanContext::new(CancellationToken::new(), tx, CostTimezone::Utc);
+        let today = now.date_naive();
+        assert_eq!(scanner.scan_entries(today, today, &ctx).unwrap().len(), 1);
+
+        scanner.desktop = Some(ClaudeDesktopLogs::with_log_dirs(vec![logs]));
+        let entries = scanner.scan_entries(today, today, &ctx).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let tokens: Vec<(u64, Option<&str>)> = entries
+            .iter()
+            .map(|e| (e.input_tokens, e.project.as_deref()))
+            .collect();
+        assert_eq!(tokens, vec![(100, Some("my-project")), (700, None)]);
+        assert_eq!(rx.borrow().total_files, 3);
+    }
+
+    #[test]
+    fn test_files_outside_range_are_never_opened() {
+        let root =
+            std::env::temp_dir().join(format!("claude-bar-claude-range-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let project = root.join("projects/my-project");
+        std::fs::create_dir_all(&project).unwrap();
+        let now = Utc::now();
+        let today = now.date_naive();
+        let since = today - chrono::Duration::days(6);
+        let day = |days_before_since: i64| {
+            std::time::SystemTime::from(now - chrono::Duration::days(6 + days_before_since))
+        };
+        let write = |name: &str, modified: std::time::SystemTime| {
+            let path = project.join(name);
+            std::fs::write(&path, "").unwrap();
+            File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        write("stale.jsonl", day(20));
+        write("recent.jsonl", std::time::SystemTime::from(now));
+        write("edge.jsonl", day(1));
+        write("just-stale.jsonl", day(2));
+        // A dated filename wins over the mtime either way.
+        write(
+            &format!("{}.jsonl", since - chrono::Duration::days(1)),
+            std::time::SystemTime::from(now),
+        );
+        write(&format!("{}.jsonl", since), day(20));
+
+        let scanner = ClaudeCostScanner::with_project_dirs(vec![root.join("projects")]);
+        let (mut files, _) = scanner.find_jsonl_files(since, today, CostTimezone::Utc);
+        files.sort();
+        let names: Vec<String> = files
+            .iter()
+            .map(|f| f.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+
+        let (tx, rx) = tokio::sync::watch::channel(Default::default());
+        let ctx = ScanContext::new(CancellationToken::new(), tx, CostTimezone::Utc);
+        scanner.scan_entries(since, today, &ctx).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(
+            names,
+            vec![
+                format!("{}.jsonl", since),
+                "edge.jsonl".into(),
+                "recent.jsonl".into()
+            ]
+        );
+        assert_eq!(rx.borrow().total_files, 3);
+        assert_eq!(rx.borrow().files_scanned, 3);
+    }
+
+    #[test]
+    fn test_project_filter_skips_directories() {
+        let root =
+            std::env::temp_dir().join(format!("claude-bar-claude-filter-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let projects = root.join("projects");
+        for project in [
+            "-home-me-src-acme",
+            "-home-me-scratch-1",
+            "archive/-home-me-old",
+        ] {
+            std::fs::create_dir_all(projects.join(project)).unwrap();
+            std::fs::write(projects.join(project).join("session.jsonl"), "").unwrap();
+        }
+        let today = Utc::now().date_naive();
+        let since = today - chrono::Duration::days(6);
+        let scan = |include: &[&str], exclude: &[&str]| {
+            let owned =
+                |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+            let mut scanner = ClaudeCostScanner::with_project_dirs(vec![projects.clone()]);
+            scanner
+                .set_project_filter(ProjectFilter::new(&owned(include), &owned(exclude)).unwrap());
+            let (files, excluded) = scanner.find_jsonl_files(since, today, CostTimezone::Utc);
+            let mut projects: Vec<String> = files
+                .iter()
+                .map(|f| {
+                    f.parent()
+                        .unwrap()
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            projects.sort();
+            (projects, excluded)
+        };
+
+        let everything = scan(&[], &[]);
+        let excluded = scan(&[], &["*-scratch-*", "archive/**"]);
+        let allowlisted = scan(&["-home-me-src-*"], &[]);
+        let both = scan(&["archive/**", "-home-me-s*"], &["*-scratch-*"]);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(everything.1, 0);
+        assert_eq!(everything.0.len(), 3);
+        assert_eq!(excluded, (vec!["-home-me-src-acme".to_string()], 2));
+        assert_eq!(allowlisted, (vec!["-home-me-src-acme".to_string()], 2));
+        assert_eq!(
+            both,
+            (
+                vec!["-home-me-old".to_string(), "-home-me-src-acme".to_string()],
+                1
+            )
+        );
+    }
+
     #[test]
     fn test_extract_date_from_path() {
         let path = PathBuf::from("/some/dir/2026-01-18.jsonl");
@@ -0,0 +1,195 @@
+use crate::core::settings::CostTimezone;
+use crate::cost::pricing::PricingStore;
+use crate::cost::scanner::{day_of, LogEntry};
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Usage records in Claude Desktop's logs. Unlike Claude Code's JSONL
+/// sessions these are mixed in with ordinary log lines, and each JSON record
+/// may follow an electron-log prefix such as `[2026-01-18 12:00:00.123] [info]`.
+#[derive(Clone)]
+pub struct ClaudeDesktopLogs {
+    log_dirs: Vec<PathBuf>,
+}
+
+impl ClaudeDesktopLogs {
+    pub fn new() -> Self {
+        let log_dirs = dirs::config_dir()
+            .map(|config| config.join("Claude/logs"))
+            .into_iter()
+            .collect();
+        Self::with_log_dirs(log_dirs)
+    }
+
+    pub fn with_log_dirs(log_dirs: Vec<PathBuf>) -> Self {
+        Self { log_dirs }
+    }
+
+    pub fn log_dirs(&self) -> &[PathBuf] {
+        &self.log_dirs
+    }
+
+    /// Log files, including rotated ones, last written on or after `since`.
+    pub fn find_log_files(&self, since: NaiveDate, timezone: CostTimezone) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for dir in &self.log_dirs {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let is_log = path
+                    .extension()
+                    .is_some_and(|ext| ext == "log" || ext == "jsonl");
+                if is_log && path.is_file() && Self::modified_since(&path, since, timezone) {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    fn modified_since(path: &Path, since: NaiveDate, timezone: CostTimezone) -> bool {
+        let Ok(modified) = std::fs::metadata(path).and_then(|m| m.modified()) else {
+            return true;
+        };
+        day_of(chrono::DateTime::<Utc>::from(modified), timezone) >= since
+    }
+
+    /// Usage entries in `path` between `since` and `until`. Records whose
+    /// message id is already in `message_ids`, from the Code logs or an
+    /// earlier Desktop log, are skipped; the rest are added to it.
+    pub fn parse_file(
+        path: &Path,
+        since: NaiveDate,
+        until: NaiveDate,
+        timezone: CostTimezone,
+        message_ids: &mut HashSet<String>,
+    ) -> Result<Vec<LogEntry>> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut entries = Vec::new();
+
+        for line in reader.lines() {
+            let line = match line {
+                Ok(l) => l,
+                Err(e) => {
+                    tracing::debug!(?path, error = %e, "Failed to read line");
+                    continue;
+                }
+            };
+            let Some(record) = parse_record(&line) else {
+                continue;
+            };
+
+            let Ok(timestamp) = chrono::DateTime::parse_from_rfc3339(&record.timestamp) else {
+                continue;
+            };
+            let timestamp = timestamp.with_timezone(&Utc);
+            let date = day_of(timestamp, timezone);
+            if date < since || date > until {
+                continue;
+            }
+
+            if let Some(id) = record.message_id.filter(|id| !id.is_empty()) {
+                if !message_ids.insert(id) {
+                    continue;
+                }
+            }
+
+            let model = record.model.unwrap_or_else(|| "unknown".to_string());
+            entries.push(LogEntry {
+                date,
+                timestamp: Some(timestamp),
+                model: PricingStore::normalize_model_name(&model),
+                input_tokens: record.usage.input_tokens.unwrap_or(0),
+                output_tokens: record.usage.output_tokens.unwrap_or(0),
+                cache_creation_tokens: record.usage.cache_creation_input_tokens.unwrap_or(0),
+                cache_read_tokens: record.usage.cache_read_input_tokens.unwrap_or(0),
+                project: None,
+                session: None,
+            });
+        }
+
+        Ok(entries)
+    }
+}
+
+impl Default for ClaudeDesktopLogs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The usage record on a log line, if it carries one.
+fn parse_record(line: &str) -> Option<DesktopUsageRecord> {
+    let json = &line[line.find('{')?..];
+    serde_json::from_str(json).ok()
+}
+
+#[derive(Debug, Deserialize)]
+struct DesktopUsageRecord {
+    timestamp: String,
+    #[serde(alias = "messageId")]
+    message_id: Option<String>,
+    model: Option<String>,
+    usage: DesktopUsage,
+}
+
+#[derive(Debug, Deserialize)]
+struct DesktopUsage {
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    cache_creation_input_tokens: Option<u64>,
+    cache_read_input_tokens: Option<u64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = include_str!("../../tests/fixtures/claude_desktop/main.log");
+
+    #[test]
+    fn test_parse_record_skips_plain_log_lines() {
+        let records: Vec<_> = FIXTURE.lines().filter_map(parse_record).collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0].message_id.as_deref(), Some("msg_desktop_1"));
+        assert_eq!(records[0].usage.input_tokens, Some(1200));
+        // camelCase ids from older builds.
+        assert_eq!(records[2].message_id.as_deref(), Some("msg_desktop_2"));
+        assert_eq!(records[3].message_id, None);
+    }
+
+    #[test]
+    fn test_parse_file_dedupes_by_message_id() {
+        let path = std::env::temp_dir().join(format!(
+            "claude-bar-claude-desktop-{}.log",
+            std::process::id()
+        ));
+        std::fs::write(&path, FIXTURE).unwrap();
+
+        let since = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let until = NaiveDate::from_ymd_opt(2026, 1, 31).unwrap();
+        // msg_desktop_1 was also captured by a Claude Code session.
+        let mut message_ids = HashSet::from(["msg_desktop_1".to_string()]);
+        let entries =
+            ClaudeDesktopLogs::parse_file(&path, since, until, CostTimezone::Utc, &mut message_ids)
+                .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let tokens: Vec<(u64, u64)> = entries
+            .iter()
+            .map(|e| (e.input_tokens, e.output_tokens))
+            .collect();
+        assert_eq!(tokens, vec![(300, 90), (50, 10)]);
+        assert_eq!(entries[0].model, "claude-sonnet-4");
+        assert!(entries.iter().all(|e| e.project.is_none()));
+        assert!(message_ids.contains("msg_desktop_2"));
+    }
+}
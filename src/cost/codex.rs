@@ -1,12 +1,14 @@
+use crate::core::settings::CostTimezone;
 use crate::cost::pricing::PricingStore;
-use crate::cost::scanner::{CostScanner, LogEntry};
+use crate::cost::scanner::{day_of, project_name, CostScanner, LogEntry, ScanContext};
 use anyhow::Result;
-use chrono::NaiveDate;
+use chrono::{Duration, NaiveDate, Utc};
 use serde::Deserialize;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
+#[derive(Clone)]
 pub struct CodexCostScanner {
     sessions_dir: PathBuf,
 }
@@ -29,6 +31,11 @@ impl CodexCostScanner {
             return Vec::new();
         }
 
+        // Session directories are named after the UTC day the session started,
+        // so a file one day outside the range can still hold entries inside it.
+        let since = since - Duration::days(1);
+        let until = until + Duration::days(1);
+
         Self::list_subdirs(&self.sessions_dir)
             .flat_map(|year_path| {
                 let year: i32 = Self::parse_dir_name(&year_path)?;
@@ -72,12 +79,28 @@ impl CodexCostScanner {
         path.file_name()?.to_str()?.parse().ok()
     }
 
-    fn parse_file(&self, path: &PathBuf, date: NaiveDate) -> Result<Vec<LogEntry>> {
+    /// Attributes each token count to the day of its own timestamp, falling
+    /// back to `fallback_date` (the session directory's day) for lines
+    /// without one.
+    fn parse_file(
+        &self,
+        path: &PathBuf,
+        fallback_date: NaiveDate,
+        since: NaiveDate,
+        until: NaiveDate,
+        timezone: CostTimezone,
+    ) -> Result<Vec<LogEntry>> {
         let file = File::open(path)?;
         let reader = BufReader::new(file);
         let mut entries = Vec::new();
         let mut current_model: Option<String> = None;
         let mut last_totals = CodexTotals::default();
+        let mut project: Option<String> = None;
+        // The file name ends in the session id too, for logs without one.
+        let mut session = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_string);
 
         for line in reader.lines() {
             let line = match line {
@@ -101,8 +124,14 @@ impl CodexCostScanner {
             };
 
             match entry.entry_type.as_str() {
-                "turn_context" => {
+                "session_meta" | "turn_context" => {
                     if let Some(payload) = entry.payload {
+                        if let Some(cwd) = payload.cwd.as_deref() {
+                            project = project_name(cwd);
+                        }
+                        if let Some(id) = payload.id {
+                            session = Some(id);
+                        }
                         if let Some(model) = payload.model {
                             current_model = Some(PricingStore::normalize_model_name(&model));
                         }
@@ -150,14 +179,27 @@ impl CodexCostScanner {
                             output,
                         };
 
+                        let timestamp = entry
+                            .timestamp
+                            .as_deref()
+                            .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+                            .map(|dt| dt.with_timezone(&Utc));
+                        let date = timestamp.map_or(fallback_date, |ts| day_of(ts, timezone));
+                        if date < since || date > until {
+                            continue;
+                        }
+
                         if delta_input > 0 || delta_output > 0 {
                             entries.push(LogEntry {
                                 date,
+                                timestamp,
                                 model,
                                 input_tokens: delta_input.saturating_sub(delta_cached),
                                 output_tokens: delta_output,
                                 cache_creation_tokens: 0,
                                 cache_read_tokens: delta_cached,
+                                project: project.clone(),
+                                session: session.clone(),
                             });
                         }
                     }
@@ -177,28 +219,31 @@ impl Default for CodexCostScanner {
 }
 
 impl CostScanner for CodexCostScanner {
-    fn scan_entries(&self, since: NaiveDate, until: NaiveDate) -> Result<Vec<LogEntry>> {
+    fn for_each_entry(
+        &self,
+        since: NaiveDate,
+        until: NaiveDate,
+        ctx: &ScanContext,
+        visit: &mut dyn FnMut(LogEntry),
+    ) -> Result<()> {
         tracing::debug!(dir = ?self.sessions_dir, "Scanning Codex sessions directory");
 
         let files = self.find_jsonl_files(since, until);
         tracing::debug!(count = files.len(), "Found JSONL files");
 
-        let entries: Vec<LogEntry> = files
-            .iter()
-            .filter_map(|file| {
-                let date = Self::extract_date_from_path(file).unwrap_or(since);
-                match self.parse_file(file, date) {
-                    Ok(entries) => Some(entries),
-                    Err(e) => {
-                        tracing::debug!(?file, error = %e, "Failed to parse file");
-                        None
-                    }
-                }
-            })
-            .flatten()
-            .collect();
+        ctx.add_files(files.len());
 
-        Ok(entries)
+        for file in &files {
+            ctx.check_cancelled()?;
+            let date = Self::extract_date_from_path(file).unwrap_or(since);
+            match self.parse_file(file, date, since, until, ctx.timezone()) {
+                Ok(parsed) => parsed.into_iter().for_each(&mut *visit),
+                Err(e) => tracing::debug!(?file, error = %e, "Failed to parse file"),
+            }
+            ctx.file_scanned();
+        }
+
+        Ok(())
     }
 }
 
@@ -231,6 +276,8 @@ struct RawCodexEntry {
     #[serde(rename = "type")]
     entry_type: String,
     #[serde(default)]
+    timestamp: Option<String>,
+    #[serde(default)]
     payload: Option<CodexPayload>,
 }
 
@@ -239,8 +286,12 @@ struct CodexPayload {
     #[serde(rename = "type")]
     payload_type: Option<String>,
     #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
     model: Option<String>,
     #[serde(default)]
+    cwd: Option<String>,
+    #[serde(default)]
     info: Option<CodexInfo>,
 }
 
@@ -269,6 +320,7 @@ struct CodexTokenUsage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
 
     #[test]
     fn test_parse_turn_context() {
@@ -298,6 +350,54 @@ mod tests {
         assert_eq!(usage.output_tokens, Some(10));
     }
 
+    #[test]
+    fn test_parse_file_uses_entry_timestamps() {
+        // Written at 23:30 local on Jan 17; the directory says Jan 18 when
+        // that is already the next day in UTC.
+        let written = chrono::Local
+            .with_ymd_and_hms(2026, 1, 17, 23, 30, 0)
+            .single()
+            .unwrap()
+            .with_timezone(&Utc);
+        let lines = [
+            r#"{"type":"session_meta","payload":{"id":"0199a1b2","cwd":"/home/me/src/acme-backend"}}"#
+                .to_string(),
+            r#"{"type":"turn_context","payload":{"model":"gpt-5.2-codex"}}"#.to_string(),
+            format!(
+                r#"{{"type":"event_msg","timestamp":"{}","payload":{{"type":"token_count","info":{{"total_token_usage":{{"input_tokens":100,"output_tokens":10}}}}}}}}"#,
+                written.to_rfc3339()
+            ),
+            r#"{"type":"event_msg","payload":{"type":"token_count","info":{"total_token_usage":{"input_tokens":150,"output_tokens":20}}}}"#.to_string(),
+        ];
+        let path = std::env::temp_dir().join(format!(
+            "claude-bar-codex-tz-{}.jsonl",
+            std::process::id()
+        ));
+        std::fs::write(&path, lines.join("\n")).unwrap();
+
+        let scanner = CodexCostScanner::new();
+        let directory_date = NaiveDate::from_ymd_opt(2026, 1, 20).unwrap();
+        let since = NaiveDate::from_ymd_opt(2026, 1, 1).unwrap();
+        let until = NaiveDate::from_ymd_opt(2026, 1, 31).unwrap();
+        let local = scanner
+            .parse_file(&path, directory_date, since, until, CostTimezone::Local)
+            .unwrap();
+        let utc = scanner
+            .parse_file(&path, directory_date, since, until, CostTimezone::Utc)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(local.len(), 2);
+        assert_eq!(local[0].date, NaiveDate::from_ymd_opt(2026, 1, 17).unwrap());
+        assert_eq!(local[0].timestamp, Some(written));
+        assert_eq!(local[0].project.as_deref(), Some("acme-backend"));
+        assert_eq!(local[1].session.as_deref(), Some("0199a1b2"));
+        assert_eq!(utc[0].date, written.date_naive());
+        // Lines without a timestamp fall back to the directory's day.
+        assert_eq!(local[1].date, directory_date);
+        assert_eq!(local[1].timestamp, None);
+    }
+
     #[test]
     fn test_extract_date_from_path() {
         let path = PathBuf::from("/home/user/.codex/sessions/2026/01/18/session.jsonl");
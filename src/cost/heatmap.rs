@@ -0,0 +1,117 @@
+use crate::core::models::UsageHeatmap;
+use crate::core::settings::CostTimezone;
+use crate::cost::scanner::LogEntry;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Timelike, Utc, Weekday};
+
+/// Days of history the heatmap covers, ending today.
+const HEATMAP_DAYS: i64 = 30;
+
+/// Buckets the tokens of every timestamped entry from the last 30 days by
+/// weekday and hour. Entries without a timestamp can't be placed and are
+/// skipped.
+pub fn aggregate_heatmap(
+    entries: &[LogEntry],
+    today: NaiveDate,
+    timezone: CostTimezone,
+) -> UsageHeatmap {
+    let cutoff = today - Duration::days(HEATMAP_DAYS - 1);
+    let mut heatmap = UsageHeatmap::default();
+
+    for entry in entries {
+        let Some(timestamp) = entry.timestamp else {
+            continue;
+        };
+        if entry.date < cutoff || entry.date > today {
+            continue;
+        }
+        let (weekday, hour) = weekday_and_hour(timestamp, timezone);
+        let tokens = entry.input_tokens
+            + entry.output_tokens
+            + entry.cache_creation_tokens
+            + entry.cache_read_tokens;
+        heatmap.add(weekday, hour, tokens);
+    }
+
+    heatmap
+}
+
+fn weekday_and_hour(timestamp: DateTime<Utc>, timezone: CostTimezone) -> (Weekday, u32) {
+    match timezone {
+        CostTimezone::Local => {
+            let local = timestamp.with_timezone(&Local);
+            (local.weekday(), local.hour())
+        }
+        CostTimezone::Utc => (timestamp.weekday(), timestamp.hour()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_at(timestamp: &str, tokens: u64) -> LogEntry {
+        let timestamp = DateTime::parse_from_rfc3339(timestamp)
+            .unwrap()
+            .with_timezone(&Utc);
+        LogEntry {
+            date: timestamp.date_naive(),
+            timestamp: Some(timestamp),
+            model: "claude-sonnet-4".to_string(),
+            input_tokens: tokens,
+            output_tokens: 10,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 5,
+            project: None,
+            session: None,
+        }
+    }
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, 31).unwrap()
+    }
+
+    #[test]
+    fn test_buckets_by_weekday_and_hour() {
+        let entries = vec![
+            // Tuesday
+            entry_at("2026-01-27T14:05:00Z", 100),
+            entry_at("2026-01-27T14:55:00Z", 200),
+            entry_at("2026-01-20T14:30:00Z", 300),
+            entry_at("2026-01-27T15:00:00Z", 400),
+            // Sunday
+            entry_at("2026-01-25T23:59:00Z", 1_000),
+        ];
+
+        let heatmap = aggregate_heatmap(&entries, today(), CostTimezone::Utc);
+        assert_eq!(heatmap.get(Weekday::Tue, 14), 100 + 200 + 300 + 3 * 15);
+        assert_eq!(heatmap.get(Weekday::Tue, 15), 415);
+        assert_eq!(heatmap.get(Weekday::Sun, 23), 1_015);
+        assert_eq!(heatmap.get(Weekday::Mon, 14), 0);
+        assert_eq!(heatmap.max(), 1_015);
+    }
+
+    #[test]
+    fn test_only_last_30_days_with_timestamps() {
+        let mut undated = entry_at("2026-01-27T14:05:00Z", 100);
+        undated.timestamp = None;
+        let entries = vec![
+            undated,
+            entry_at("2026-01-01T09:00:00Z", 500),
+            entry_at("2026-01-02T09:00:00Z", 700),
+            entry_at("2026-02-01T09:00:00Z", 900),
+        ];
+
+        let heatmap = aggregate_heatmap(&entries, today(), CostTimezone::Utc);
+        assert_eq!(heatmap.get(Weekday::Tue, 14), 0);
+        assert_eq!(heatmap.get(Weekday::Thu, 9), 0);
+        assert_eq!(heatmap.get(Weekday::Fri, 9), 715);
+        assert_eq!(heatmap.get(Weekday::Sun, 9), 0);
+    }
+
+    #[test]
+    fn test_empty_heatmap() {
+        let heatmap = aggregate_heatmap(&[], today(), CostTimezone::Local);
+        assert!(heatmap.is_empty());
+        assert_eq!(heatmap, UsageHeatmap::default());
+    }
+}
@@ -1,11 +1,23 @@
+mod budgets;
 mod claude;
+mod claude_desktop;
 mod codex;
+mod heatmap;
 mod pricing;
+mod project_filter;
 mod scanner;
+pub mod stats;
 mod store;
 
+pub use budgets::{
+    combined_monthly_cost, combined_project_costs, project_spend, unmatched_budgets, BudgetState,
+    MonthlySpend, ProjectSpend, BUDGET_THRESHOLDS,
+};
 #[allow(unused_imports)]
 pub use pricing::{ModelPricing, PricingStore, TokenUsage};
 #[allow(unused_imports)]
 pub use scanner::CostScanner;
-pub use store::{CostScanResult, CostStore, PricingRefreshResult};
+#[allow(unused_imports)]
+pub use scanner::{day_of, today, CancellationToken, ScanProgress, SessionWindow};
+pub use stats::{scan_usage_stats, UsageStats, MAX_STATS_DAYS};
+pub use store::{scan_in_background, CostScanResult, CostStore, PricingRefreshResult};
@@ -5,6 +5,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+/// Shortest priced family name a longer model name may borrow pricing from.
+const MIN_FUZZY_FAMILY_LEN: usize = 6;
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ModelPricing {
     pub input_price_per_million: f64,
@@ -112,7 +115,6 @@ pub struct TokenUsage {
 }
 
 impl TokenUsage {
-    #[allow(dead_code)]
     pub fn new(input: u64, output: u64) -> Self {
         Self {
             input_tokens: input,
@@ -121,7 +123,6 @@ impl TokenUsage {
         }
     }
 
-    #[allow(dead_code)]
     pub fn with_cache(mut self, creation: u64, read: u64) -> Self {
         self.cache_creation_tokens = creation;
         self.cache_read_tokens = read;
@@ -334,32 +335,53 @@ impl PricingStore {
         Ok(())
     }
 
+    /// Looks up pricing for a model, preferring an exact name, then the same
+    /// family with a different release date, then the longest priced family
+    /// the model extends. Returns `None` rather than guessing from a loosely
+    /// related model, so callers fall back to a flagged estimate.
     pub fn get_price(&self, model: &str) -> Option<&ModelPricing> {
         let normalized = Self::normalize_model_name(model);
 
-        // Try exact match first
         if let Some(price) = self.prices.get(&normalized) {
             return Some(price);
         }
 
-        // Try stripping date suffix for Claude models (e.g., claude-sonnet-4-20250514 -> claude-sonnet-4)
-        let base = normalized.trim_end_matches(|c: char| c == '-' || c.is_ascii_digit());
-        if base != normalized {
-            for (key, price) in &self.prices {
-                if key.starts_with(base) {
-                    return Some(price);
-                }
-            }
-        }
+        let (key, price) = self.fuzzy_match(&normalized)?;
+        tracing::debug!(model, matched = %key, "Using fuzzy pricing match");
+        Some(price)
+    }
 
-        // Fallback: look for partial match
-        for (key, price) in &self.prices {
-            if normalized.contains(key) || key.contains(&normalized) {
-                return Some(price);
-            }
+    fn fuzzy_match(&self, normalized: &str) -> Option<(&String, &ModelPricing)> {
+        let family = strip_date_suffix(normalized);
+
+        // Same family, different or missing release date: take the newest.
+        let same_family = self
+            .prices
+            .iter()
+            .filter(|(key, _)| strip_date_suffix(key) == family)
+            .max_by(|a, b| a.0.cmp(b.0));
+        if same_family.is_some() {
+            return same_family;
         }
 
-        None
+        // A priced family the model extends on a segment boundary, e.g.
+        // claude-sonnet-4-5 -> claude-sonnet-4. Short families like "o3" are
+        // skipped: o3-pro costs several times more than o3.
+        self.prices
+            .iter()
+            .filter(|(key, _)| {
+                let key_family = strip_date_suffix(key);
+                key_family.len() >= MIN_FUZZY_FAMILY_LEN
+                    && family
+                        .strip_prefix(key_family)
+                        .is_some_and(|rest| rest.starts_with('-'))
+            })
+            .max_by(|a, b| {
+                strip_date_suffix(a.0)
+                    .len()
+                    .cmp(&strip_date_suffix(b.0).len())
+                    .then_with(|| a.0.cmp(b.0))
+            })
     }
 
     #[allow(dead_code)]
@@ -407,6 +429,30 @@ struct ModelsDevPricing {
     cache_write: Option<f64>,
 }
 
+/// Strips a trailing release date: `-20250514` (Anthropic) or `-2024-08-06`
+/// (OpenAI).
+fn strip_date_suffix(name: &str) -> &str {
+    if let Some((head, tail)) = name.rsplit_once('-') {
+        if tail.len() == 8 && tail.bytes().all(|b| b.is_ascii_digit()) {
+            return head;
+        }
+    }
+
+    let bytes = name.as_bytes();
+    if bytes.len() > 11 {
+        let tail = &bytes[bytes.len() - 11..];
+        let is_iso_date = tail.iter().enumerate().all(|(i, b)| match i {
+            0 | 5 | 8 => *b == b'-',
+            _ => b.is_ascii_digit(),
+        });
+        if is_iso_date {
+            return &name[..name.len() - 11];
+        }
+    }
+
+    name
+}
+
 fn normalize_price_to_per_million(value: f64) -> f64 {
     if value.abs() < 0.01 {
         value * 1_000_000.0
@@ -653,6 +699,51 @@ mod tests {
         assert!(price.is_some());
     }
 
+    #[test]
+    fn test_strip_date_suffix() {
+        assert_eq!(strip_date_suffix("claude-sonnet-4-20250514"), "claude-sonnet-4");
+        assert_eq!(strip_date_suffix("gpt-4o-2024-08-06"), "gpt-4o");
+        assert_eq!(strip_date_suffix("claude-sonnet-4"), "claude-sonnet-4");
+        assert_eq!(strip_date_suffix("o3-mini"), "o3-mini");
+    }
+
+    #[test]
+    fn test_get_price_does_not_cross_model_tiers() {
+        let store = PricingStore {
+            prices: HashMap::from([(
+                "claude-3-5-sonnet-20241022".to_string(),
+                ModelPricing::new(3.0, 15.0),
+            )]),
+            last_fetch: None,
+        };
+
+        assert!(store.get_price("claude-3-5-haiku-20241022").is_none());
+        assert!(store.get_price("claude-3-5-haiku").is_none());
+        assert!(store.get_price("sonnet").is_none());
+        assert!(store.get_price("claude-3-5-sonnet-20240620").is_some());
+    }
+
+    #[test]
+    fn test_get_price_short_family_is_not_prefix_matched() {
+        let store = PricingStore::new();
+
+        assert!(store.get_price("o3").is_some());
+        assert!(store.get_price("o3-pro").is_none());
+        assert!(store.get_price("o3-pro-2025-06-10").is_none());
+    }
+
+    #[test]
+    fn test_get_price_prefers_longest_family() {
+        let store = PricingStore::new();
+
+        let extended = store.get_price("claude-opus-4-5-preview").unwrap();
+        let opus_4_5 = store.get_price("claude-opus-4-5-20251101").unwrap();
+        assert!(std::ptr::eq(extended, opus_4_5));
+
+        let mini_high = store.get_price("o3-mini-high").unwrap();
+        assert!(std::ptr::eq(mini_high, store.get_price("o3-mini").unwrap()));
+    }
+
     #[test]
     fn test_needs_refresh() {
         let store = PricingStore::new();
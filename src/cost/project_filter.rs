@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::path::Path;
+
+/// Which Claude project directories the cost scan reads, from
+/// `cost.include_projects` and `cost.exclude_projects`. Patterns match a
+/// directory's path under the projects directory, e.g. `-home-me-scratch-1`
+/// or `archive/-home-me-old`.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl ProjectFilter {
+    /// An empty `include` reads every project not excluded.
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        Ok(Self {
+            include: glob_set(include)?,
+            exclude: glob_set(exclude)?,
+        })
+    }
+
+    /// Whether the scan skips `project` and everything under it.
+    pub fn excludes(&self, project: &Path) -> bool {
+        self.exclude
+            .as_ref()
+            .is_some_and(|set| set.is_match(project))
+    }
+
+    pub fn has_allowlist(&self) -> bool {
+        self.include.is_some()
+    }
+
+    /// Whether `project` and everything under it is on the allowlist,
+    /// which without `cost.include_projects` is everything.
+    pub fn includes(&self, project: &Path) -> bool {
+        self.include
+            .as_ref()
+            .is_none_or(|set| set.is_match(project))
+    }
+}
+
+fn glob_set(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid pattern {:?}", pattern))?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> ProjectFilter {
+        let owned = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        ProjectFilter::new(&owned(include), &owned(exclude)).unwrap()
+    }
+
+    #[test]
+    fn test_project_globs() {
+        let filter = filter(&[], &["*-scratch-*", "archive/**"]);
+        assert!(filter.excludes(Path::new("-home-me-scratch-1")));
+        assert!(filter.excludes(Path::new("archive/-home-me-old")));
+        assert!(filter.excludes(Path::new("archive/-home-me-old/session")));
+        assert!(!filter.excludes(Path::new("archive")));
+        assert!(!filter.excludes(Path::new("-home-me-src-acme")));
+        assert!(filter.includes(Path::new("-home-me-src-acme")));
+
+        let allowlist = self::filter(&["-home-me-src-*"], &[]);
+        assert!(allowlist.has_allowlist());
+        assert!(allowlist.includes(Path::new("-home-me-src-acme")));
+        assert!(!allowlist.includes(Path::new("-home-me-scratch-1")));
+        assert!(!allowlist.excludes(Path::new("-home-me-scratch-1")));
+
+        let everything = ProjectFilter::default();
+        assert!(everything.includes(Path::new("-home-me-scratch-1")));
+        assert!(!everything.excludes(Path::new("-home-me-scratch-1")));
+        assert!(ProjectFilter::new(&[], &["archive/[".to_string()]).is_err());
+    }
+}
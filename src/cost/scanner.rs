@@ -1,21 +1,241 @@
-use crate::core::models::{DailyCost, DailyTokenUsage};
+use crate::core::models::{DailyCost, DailyTokenUsage, ModelTokenUsage, RateWindow};
+use crate::core::settings::CostTimezone;
 use crate::cost::pricing::{PricingStore, TokenUsage};
 use anyhow::Result;
-use chrono::NaiveDate;
-use std::collections::HashMap;
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
 
 pub trait CostScanner: Send + Sync {
-    fn scan_entries(&self, since: NaiveDate, until: NaiveDate) -> Result<Vec<LogEntry>>;
+    /// Hands `visit` the entries from `since` to `until` one log file at a
+    /// time, so callers that only aggregate never hold all of them.
+    fn for_each_entry(
+        &self,
+        since: NaiveDate,
+        until: NaiveDate,
+        ctx: &ScanContext,
+        visit: &mut dyn FnMut(LogEntry),
+    ) -> Result<()>;
+
+    fn scan_entries(
+        &self,
+        since: NaiveDate,
+        until: NaiveDate,
+        ctx: &ScanContext,
+    ) -> Result<Vec<LogEntry>> {
+        let mut entries = Vec::new();
+        self.for_each_entry(since, until, ctx, &mut |entry| entries.push(entry))?;
+        Ok(entries)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("cost scan cancelled")]
+pub struct ScanCancelled;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScanProgress {
+    pub files_scanned: usize,
+    pub total_files: usize,
+}
+
+pub struct ScanContext {
+    token: CancellationToken,
+    progress_tx: watch::Sender<ScanProgress>,
+    timezone: CostTimezone,
+    files_scanned: AtomicUsize,
+    total_files: AtomicUsize,
+    excluded_projects: AtomicUsize,
+}
+
+impl ScanContext {
+    pub fn new(
+        token: CancellationToken,
+        progress_tx: watch::Sender<ScanProgress>,
+        timezone: CostTimezone,
+    ) -> Self {
+        progress_tx.send_replace(ScanProgress::default());
+        Self {
+            token,
+            progress_tx,
+            timezone,
+            files_scanned: AtomicUsize::new(0),
+            total_files: AtomicUsize::new(0),
+            excluded_projects: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub fn timezone(&self) -> CostTimezone {
+        self.timezone
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(ScanCancelled.into());
+        }
+        Ok(())
+    }
+
+    pub fn add_files(&self, count: usize) {
+        self.total_files.fetch_add(count, Ordering::SeqCst);
+        self.publish();
+    }
+
+    pub fn file_scanned(&self) {
+        self.files_scanned.fetch_add(1, Ordering::SeqCst);
+        self.publish();
+    }
+
+    /// Counts project directories the project filter kept out of the scan.
+    pub fn add_excluded_projects(&self, count: usize) {
+        self.excluded_projects.fetch_add(count, Ordering::SeqCst);
+    }
+
+    /// The projects excluded since the last call, for the provider just
+    /// scanned.
+    pub fn take_excluded_projects(&self) -> usize {
+        self.excluded_projects.swap(0, Ordering::SeqCst)
+    }
+
+    fn publish(&self) {
+        self.progress_tx.send_replace(ScanProgress {
+            files_scanned: self.files_scanned.load(Ordering::SeqCst),
+            total_files: self.total_files.load(Ordering::SeqCst),
+        });
+    }
+}
+
+/// The day `timestamp` counts toward. Every scanner and aggregator goes
+/// through this so "today" means the same thing for all providers.
+pub fn day_of(timestamp: DateTime<Utc>, timezone: CostTimezone) -> NaiveDate {
+    match timezone {
+        CostTimezone::Local => timestamp.with_timezone(&Local).date_naive(),
+        CostTimezone::Utc => timestamp.date_naive(),
+    }
+}
+
+pub fn today(timezone: CostTimezone) -> NaiveDate {
+    day_of(Utc::now(), timezone)
 }
 
 #[derive(Debug)]
 pub struct LogEntry {
     pub date: NaiveDate,
+    pub timestamp: Option<DateTime<Utc>>,
     pub model: String,
     pub input_tokens: u64,
     pub output_tokens: u64,
     pub cache_creation_tokens: u64,
     pub cache_read_tokens: u64,
+    /// Basename of the directory the agent ran in, when the log records it.
+    pub project: Option<String>,
+    /// The Claude session file or Codex session id the entry came from.
+    pub session: Option<String>,
+}
+
+impl LogEntry {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_tokens + self.cache_read_tokens
+    }
+
+    /// Priced like the aggregates, at the fallback rate without pricing.
+    pub fn cost(&self, pricing: &PricingStore) -> f64 {
+        let usage = TokenUsage::new(self.input_tokens, self.output_tokens)
+            .with_cache(self.cache_creation_tokens, self.cache_read_tokens);
+        cost_for_usage(&self.model, &usage, pricing)
+    }
+}
+
+/// The project a working directory belongs to, e.g. `acme-backend` for
+/// `/home/me/src/acme-backend`.
+pub fn project_name(cwd: &str) -> Option<String> {
+    Path::new(cwd)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl SessionWindow {
+    pub fn from_rate_window(window: &RateWindow) -> Option<Self> {
+        let end = window.resets_at?;
+        let minutes = window.window_minutes.filter(|m| *m > 0)?;
+        Some(Self {
+            start: end - Duration::minutes(i64::from(minutes)),
+            end,
+        })
+    }
+
+    pub fn contains(&self, timestamp: DateTime<Utc>) -> bool {
+        timestamp >= self.start && timestamp < self.end
+    }
+}
+
+pub fn aggregate_session_usage(
+    entries: &[LogEntry],
+    window: SessionWindow,
+    pricing: &PricingStore,
+) -> (u64, f64) {
+    let mut usage_by_model: HashMap<String, TokenUsage> = HashMap::new();
+
+    for entry in entries {
+        if !entry.timestamp.is_some_and(|ts| window.contains(ts)) {
+            continue;
+        }
+        let usage = usage_by_model.entry(entry.model.clone()).or_default();
+        usage.input_tokens += entry.input_tokens;
+        usage.output_tokens += entry.output_tokens;
+        usage.cache_creation_tokens += entry.cache_creation_tokens;
+        usage.cache_read_tokens += entry.cache_read_tokens;
+    }
+
+    usage_by_model
+        .iter()
+        .fold((0, 0.0), |(tokens, cost), (model, usage)| {
+            (
+                tokens
+                    + usage.input_tokens
+                    + usage.output_tokens
+                    + usage.cache_creation_tokens
+                    + usage.cache_read_tokens,
+                cost + cost_for_usage(model, usage, pricing),
+            )
+        })
 }
 
 pub fn aggregate_entries(entries: &[LogEntry], pricing: &PricingStore) -> Vec<DailyCost> {
@@ -34,7 +254,16 @@ pub fn aggregate_entries(entries: &[LogEntry], pricing: &PricingStore) -> Vec<Da
         .into_iter()
         .map(|((date, model), usage)| {
             let cost = cost_for_usage(&model, &usage, pricing);
-            DailyCost { date, model, cost }
+            let tokens = usage.input_tokens
+                + usage.output_tokens
+                + usage.cache_creation_tokens
+                + usage.cache_read_tokens;
+            DailyCost {
+                date,
+                model,
+                cost,
+                tokens,
+            }
         })
         .collect();
 
@@ -43,18 +272,11 @@ pub fn aggregate_entries(entries: &[LogEntry], pricing: &PricingStore) -> Vec<Da
 }
 
 pub fn aggregate_token_usage(entries: &[LogEntry], pricing: &PricingStore) -> Vec<DailyTokenUsage> {
-    let mut tokens_by_day: HashMap<NaiveDate, u64> = HashMap::new();
-    let mut usage_by_model: HashMap<(NaiveDate, String), TokenUsage> = HashMap::new();
+    let mut usage_by_model: BTreeMap<(NaiveDate, &str), TokenUsage> = BTreeMap::new();
 
     for entry in entries {
-        let total_tokens = entry.input_tokens
-            + entry.output_tokens
-            + entry.cache_creation_tokens
-            + entry.cache_read_tokens;
-        *tokens_by_day.entry(entry.date).or_insert(0) += total_tokens;
-
         let usage = usage_by_model
-            .entry((entry.date, entry.model.clone()))
+            .entry((entry.date, entry.model.as_str()))
             .or_default();
         usage.input_tokens += entry.input_tokens;
         usage.output_tokens += entry.output_tokens;
@@ -62,26 +284,81 @@ pub fn aggregate_token_usage(entries: &[LogEntry], pricing: &PricingStore) -> Ve
         usage.cache_read_tokens += entry.cache_read_tokens;
     }
 
-    let mut cost_by_day: HashMap<NaiveDate, f64> = HashMap::new();
+    let mut models_by_day: BTreeMap<NaiveDate, Vec<ModelTokenUsage>> = BTreeMap::new();
     for ((date, model), usage) in usage_by_model {
-        let cost = cost_for_usage(&model, &usage, pricing);
-        *cost_by_day.entry(date).or_insert(0.0) += cost;
+        let cost = cost_for_usage(model, &usage, pricing);
+        models_by_day
+            .entry(date)
+            .or_default()
+            .push(ModelTokenUsage {
+                model: model.to_string(),
+                input_tokens: usage.input_tokens,
+                output_tokens: usage.output_tokens,
+                cache_creation_tokens: usage.cache_creation_tokens,
+                cache_read_tokens: usage.cache_read_tokens,
+                cost_usd: Some(cost).filter(|c| *c > 0.0),
+            });
     }
 
-    let mut daily: Vec<DailyTokenUsage> = tokens_by_day
+    models_by_day
         .into_iter()
-        .map(|(date, tokens)| {
-            let cost = cost_by_day.get(&date).copied();
+        .map(|(date, models)| {
+            let tokens: u64 = models.iter().map(ModelTokenUsage::total_tokens).sum();
+            let cost: f64 = models.iter().filter_map(|m| m.cost_usd).sum();
             DailyTokenUsage {
                 date,
-                total_tokens: if tokens > 0 { Some(tokens) } else { None },
-                cost_usd: cost.filter(|c| *c > 0.0),
+                total_tokens: Some(tokens).filter(|t| *t > 0),
+                cost_usd: Some(cost).filter(|c| *c > 0.0),
+                models,
             }
         })
-        .collect();
+        .collect()
+}
+
+/// Cost per project between `month_start` and `today`. Projects only seen
+/// earlier in the scan stay in the map at zero, so budget keys can still be
+/// matched against them.
+pub fn aggregate_project_costs(
+    entries: &[LogEntry],
+    pricing: &PricingStore,
+    month_start: NaiveDate,
+    today: NaiveDate,
+) -> BTreeMap<String, f64> {
+    let mut projects: BTreeMap<String, f64> = BTreeMap::new();
+    let mut usage_by_model: HashMap<(&str, &str), TokenUsage> = HashMap::new();
+
+    for entry in entries {
+        let Some(project) = entry.project.as_deref() else {
+            continue;
+        };
+        projects.entry(project.to_string()).or_insert(0.0);
+        if entry.date < month_start || entry.date > today {
+            continue;
+        }
+        let usage = usage_by_model
+            .entry((project, entry.model.as_str()))
+            .or_default();
+        usage.input_tokens += entry.input_tokens;
+        usage.output_tokens += entry.output_tokens;
+        usage.cache_creation_tokens += entry.cache_creation_tokens;
+        usage.cache_read_tokens += entry.cache_read_tokens;
+    }
 
-    daily.sort_by(|a, b| a.date.cmp(&b.date));
-    daily
+    for ((project, model), usage) in usage_by_model {
+        if let Some(cost) = projects.get_mut(project) {
+            *cost += cost_for_usage(model, &usage, pricing);
+        }
+    }
+    projects
+}
+
+/// Whether any model in `entries` lacks pricing and is costed at the flat
+/// fallback rate, which makes the totals an estimate.
+pub fn has_unpriced_models(entries: &[LogEntry], pricing: &PricingStore) -> bool {
+    let models: HashSet<&str> = entries.iter().map(|e| e.model.as_str()).collect();
+    models
+        .into_iter()
+        .any(|model| pricing.get_price(model).is_none())
 }
 
 fn cost_for_usage(model: &str, usage: &TokenUsage, pricing: &PricingStore) -> f64 {
@@ -100,3 +377,203 @@ fn estimate_cost(model: &str, usage: &TokenUsage) -> f64 {
     };
     (usage.input_tokens + usage.output_tokens) as f64 * fallback_price
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry_at(timestamp: DateTime<Utc>, input_tokens: u64) -> LogEntry {
+        LogEntry {
+            date: timestamp.date_naive(),
+            timestamp: Some(timestamp),
+            model: "claude-sonnet-4".to_string(),
+            input_tokens,
+            output_tokens: 0,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            project: None,
+            session: None,
+        }
+    }
+
+    #[test]
+    fn test_session_window_from_rate_window() {
+        let resets_at = Utc::now();
+        let window = RateWindow {
+            used_percent: 0.4,
+            window_minutes: Some(300),
+            resets_at: Some(resets_at),
+            reset_description: None,
+            used_percent_exact: None,
+            resets_at_estimated: false,
+        };
+
+        let session = SessionWindow::from_rate_window(&window).unwrap();
+        assert_eq!(session.end, resets_at);
+        assert_eq!(session.start, resets_at - Duration::hours(5));
+
+        let without_length = RateWindow {
+            window_minutes: None,
+            ..window
+        };
+        assert!(SessionWindow::from_rate_window(&without_length).is_none());
+    }
+
+    #[test]
+    fn test_aggregate_session_usage_filters_by_window() {
+        let end = Utc::now();
+        let window = SessionWindow {
+            start: end - Duration::hours(5),
+            end,
+        };
+        let entries = vec![
+            entry_at(end - Duration::hours(6), 1_000),
+            entry_at(end - Duration::hours(2), 200),
+            entry_at(end - Duration::minutes(10), 300),
+            LogEntry {
+                timestamp: None,
+                ..entry_at(end - Duration::hours(1), 4_000)
+            },
+        ];
+
+        let (tokens, cost) = aggregate_session_usage(&entries, window, &PricingStore::default());
+        assert_eq!(tokens, 500);
+        assert!(cost > 0.0);
+    }
+
+    #[test]
+    fn test_has_unpriced_models() {
+        let now = Utc::now();
+        let pricing = PricingStore::default();
+        let priced = vec![entry_at(now, 100)];
+        assert!(!has_unpriced_models(&priced, &pricing));
+
+        let mixed = vec![
+            entry_at(now, 100),
+            LogEntry {
+                model: "o3-pro".to_string(),
+                ..entry_at(now, 100)
+            },
+        ];
+        assert!(has_unpriced_models(&mixed, &pricing));
+    }
+
+    #[test]
+    fn test_aggregate_token_usage_by_model() {
+        let now = Utc::now();
+        let yesterday = now - Duration::days(1);
+        let entries = vec![
+            entry_at(yesterday, 100),
+            entry_at(now, 1_000),
+            LogEntry {
+                model: "claude-opus-4".to_string(),
+                output_tokens: 50,
+                ..entry_at(now, 200)
+            },
+            entry_at(now, 500),
+        ];
+
+        let daily = aggregate_token_usage(&entries, &PricingStore::default());
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily[0].date, yesterday.date_naive());
+        assert_eq!(daily[0].models.len(), 1);
+
+        let today = &daily[1];
+        assert_eq!(today.total_tokens, Some(1_750));
+        let models: Vec<(&str, u64, u64)> = today
+            .models
+            .iter()
+            .map(|m| (m.model.as_str(), m.input_tokens, m.output_tokens))
+            .collect();
+        assert_eq!(
+            models,
+            vec![("claude-opus-4", 200, 50), ("claude-sonnet-4", 1_500, 0)]
+        );
+        let model_cost: f64 = today.models.iter().filter_map(|m| m.cost_usd).sum();
+        assert_eq!(today.cost_usd, Some(model_cost));
+    }
+
+    #[test]
+    fn test_project_name() {
+        assert_eq!(
+            project_name("/home/me/src/acme-backend"),
+            Some("acme-backend".to_string())
+        );
+        assert_eq!(
+            project_name("/home/me/src/acme-backend/"),
+            Some("acme-backend".to_string())
+        );
+        assert_eq!(project_name("/"), None);
+    }
+
+    #[test]
+    fn test_aggregate_project_costs() {
+        let today = Utc::now();
+        let month_start = today.date_naive() - Duration::days(3);
+        let in_project = |project: &str, timestamp, tokens| LogEntry {
+            project: Some(project.to_string()),
+            ..entry_at(timestamp, tokens)
+        };
+        let entries = vec![
+            in_project("acme-backend", today, 50_000),
+            in_project("acme-backend", today - Duration::days(1), 50_000),
+            in_project("old-prototype", today - Duration::days(10), 50_000),
+            entry_at(today, 50_000),
+        ];
+
+        let costs = aggregate_project_costs(
+            &entries,
+            &PricingStore::default(),
+            month_start,
+            today.date_naive(),
+        );
+        assert_eq!(costs.len(), 2);
+        assert!((costs["acme-backend"] - 0.3).abs() < 0.001);
+        assert_eq!(costs["old-prototype"], 0.0);
+    }
+
+    #[test]
+    fn test_day_of_follows_timezone_setting() {
+        let timestamp = DateTime::parse_from_rfc3339("2026-01-18T04:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            day_of(timestamp, CostTimezone::Utc),
+            NaiveDate::from_ymd_opt(2026, 1, 18).unwrap()
+        );
+        assert_eq!(
+            day_of(timestamp, CostTimezone::Local),
+            timestamp.with_timezone(&Local).date_naive()
+        );
+    }
+
+    #[test]
+    fn test_scan_context_reports_progress() {
+        let (tx, rx) = watch::channel(ScanProgress::default());
+        let ctx = ScanContext::new(CancellationToken::new(), tx, CostTimezone::Local);
+
+        ctx.add_files(3);
+        ctx.file_scanned();
+
+        assert_eq!(
+            *rx.borrow(),
+            ScanProgress {
+                files_scanned: 1,
+                total_files: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_scan_context_cancellation() {
+        let (tx, _rx) = watch::channel(ScanProgress::default());
+        let token = CancellationToken::new();
+        let ctx = ScanContext::new(token.clone(), tx, CostTimezone::Local);
+
+        assert!(ctx.check_cancelled().is_ok());
+        token.cancel();
+        assert!(ctx.is_cancelled());
+        let err = ctx.check_cancelled().unwrap_err();
+        assert!(err.is::<ScanCancelled>());
+    }
+}
@@ -0,0 +1,320 @@
+use crate::core::models::Provider;
+use crate::cost::pricing::PricingStore;
+use crate::cost::scanner::{CancellationToken, LogEntry, ScanContext, ScanProgress};
+use crate::cost::store::CostStore;
+use anyhow::Result;
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use tokio::sync::watch;
+
+/// Most days `scan_usage_stats` reads, about ten years. Longer ranges only
+/// add empty days to walk.
+pub const MAX_STATS_DAYS: u32 = 3660;
+
+/// How a provider's logs say it was used over a range of days.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct UsageStats {
+    /// Sessions with any tokens in the range.
+    pub sessions: usize,
+    /// Tokens per session in the range, nearest-rank percentiles.
+    pub median_session_tokens: Option<u64>,
+    pub p90_session_tokens: Option<u64>,
+    /// Days of the current month with any tokens, whatever the range.
+    pub active_days_this_month: usize,
+    /// The model with the most tokens in the range.
+    pub busiest_model: Option<ModelTokens>,
+    /// Cost per calendar day from Monday to Friday, idle days included.
+    /// `null` when the range has no such day.
+    pub weekday_avg_cost_usd: Option<f64>,
+    pub weekend_avg_cost_usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ModelTokens {
+    pub model: String,
+    pub tokens: u64,
+}
+
+/// Builds `UsageStats` one entry at a time. It keeps a total per session
+/// and per model, never the entries, so long ranges stay small.
+pub struct StatsAccumulator {
+    since: NaiveDate,
+    today: NaiveDate,
+    session_tokens: HashMap<String, u64>,
+    model_tokens: HashMap<String, u64>,
+    month_days: BTreeSet<NaiveDate>,
+    weekday_cost: f64,
+    weekend_cost: f64,
+}
+
+impl StatsAccumulator {
+    /// The range runs from `since` to `today`; the month is `today`'s.
+    pub fn new(since: NaiveDate, today: NaiveDate) -> Self {
+        Self {
+            since,
+            today,
+            session_tokens: HashMap::new(),
+            model_tokens: HashMap::new(),
+            month_days: BTreeSet::new(),
+            weekday_cost: 0.0,
+            weekend_cost: 0.0,
+        }
+    }
+
+    /// The first day entries are needed from: the range's, or the month's
+    /// if that starts earlier.
+    pub fn scan_from(&self) -> NaiveDate {
+        self.since.min(month_start(self.today))
+    }
+
+    pub fn add(&mut self, entry: &LogEntry, pricing: &PricingStore) {
+        let tokens = entry.total_tokens();
+        if tokens == 0 || entry.date > self.today {
+            return;
+        }
+        if entry.date >= month_start(self.today) {
+            self.month_days.insert(entry.date);
+        }
+        if entry.date < self.since {
+            return;
+        }
+
+        if let Some(session) = &entry.session {
+            *self.session_tokens.entry(session.clone()).or_default() += tokens;
+        }
+        *self.model_tokens.entry(entry.model.clone()).or_default() += tokens;
+        let cost = entry.cost(pricing);
+        if is_weekend(entry.date) {
+            self.weekend_cost += cost;
+        } else {
+            self.weekday_cost += cost;
+        }
+    }
+
+    pub fn finish(self) -> UsageStats {
+        let mut sessions: Vec<u64> = self.session_tokens.into_values().collect();
+        sessions.sort_unstable();
+        // Ties go to the name that sorts first, so the answer is stable.
+        let busiest_model = self
+            .model_tokens
+            .into_iter()
+            .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+            .map(|(model, tokens)| ModelTokens { model, tokens });
+        let (weekdays, weekend_days) = count_days(self.since, self.today);
+        let average = |cost: f64, days: u32| (days > 0).then(|| cost / f64::from(days));
+
+        UsageStats {
+            sessions: sessions.len(),
+            median_session_tokens: percentile(&sessions, 0.5),
+            p90_session_tokens: percentile(&sessions, 0.9),
+            active_days_this_month: self.month_days.len(),
+            busiest_model,
+            weekday_avg_cost_usd: average(self.weekday_cost, weekdays),
+            weekend_avg_cost_usd: average(self.weekend_cost, weekend_days),
+        }
+    }
+}
+
+/// Reads `provider`'s logs for the `days` days ending `today`, at most
+/// `MAX_STATS_DAYS`, through a `StatsAccumulator`, with `store`'s scan
+/// settings and pricing.
+pub fn scan_usage_stats(
+    store: &CostStore,
+    provider: Provider,
+    days: u32,
+    today: NaiveDate,
+) -> Result<UsageStats> {
+    let days = days.clamp(1, MAX_STATS_DAYS);
+    let since = today - Days::new(u64::from(days - 1));
+    let mut stats = StatsAccumulator::new(since, today);
+    let ctx = ScanContext::new(
+        CancellationToken::new(),
+        watch::channel(ScanProgress::default()).0,
+        store.timezone(),
+    );
+    let pricing = store.pricing();
+    store
+        .scanner_for(provider)
+        .for_each_entry(stats.scan_from(), today, &ctx, &mut |entry| {
+            stats.add(&entry, pricing)
+        })?;
+    Ok(stats.finish())
+}
+
+/// The smallest value at least `fraction` of `sorted` is at or below.
+fn percentile(sorted: &[u64], fraction: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = (fraction * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn month_start(day: NaiveDate) -> NaiveDate {
+    day.with_day(1).unwrap_or(day)
+}
+
+fn is_weekend(day: NaiveDate) -> bool {
+    matches!(day.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Weekdays and weekend days from `since` to `until`, both included.
+fn count_days(since: NaiveDate, until: NaiveDate) -> (u32, u32) {
+    since
+        .iter_days()
+        .take_while(|day| *day <= until)
+        .fold((0, 0), |(weekdays, weekend), day| {
+            if is_weekend(day) {
+                (weekdays, weekend + 1)
+            } else {
+                (weekdays + 1, weekend)
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+    }
+
+    fn entry(day: NaiveDate, session: Option<&str>, model: &str, tokens: u64) -> LogEntry {
+        LogEntry {
+            date: day,
+            timestamp: None,
+            model: model.to_string(),
+            input_tokens: tokens,
+            output_tokens: 0,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 0,
+            project: None,
+            session: session.map(str::to_string),
+        }
+    }
+
+    fn stats(since: NaiveDate, today: NaiveDate, entries: &[LogEntry]) -> UsageStats {
+        let pricing = PricingStore::default();
+        let mut stats = StatsAccumulator::new(since, today);
+        for entry in entries {
+            stats.add(entry, &pricing);
+        }
+        stats.finish()
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted = [10, 20, 30, 40, 50, 60, 70, 80, 90, 100];
+        assert_eq!(percentile(&sorted, 0.5), Some(50));
+        assert_eq!(percentile(&sorted, 0.9), Some(90));
+        assert_eq!(percentile(&sorted, 0.95), Some(100));
+        assert_eq!(percentile(&sorted, 0.0), Some(10));
+        assert_eq!(percentile(&[7], 0.9), Some(7));
+        assert_eq!(percentile(&[1, 2, 3, 4], 0.5), Some(2));
+        assert_eq!(percentile(&[], 0.5), None);
+    }
+
+    #[test]
+    fn test_sessions_sum_their_entries() {
+        // Session "a" spreads over three entries and two days.
+        let entries = [
+            entry(date(2), Some("a"), "claude-sonnet-4", 100),
+            entry(date(2), Some("a"), "claude-sonnet-4", 200),
+            entry(date(3), Some("a"), "claude-opus-4", 700),
+            entry(date(3), Some("b"), "claude-sonnet-4", 50),
+            entry(date(4), Some("c"), "claude-sonnet-4", 5_000),
+            // Desktop entries have no session and only count elsewhere.
+            entry(date(4), None, "claude-sonnet-4", 9_000),
+        ];
+        let stats = stats(date(1), date(10), &entries);
+        assert_eq!(stats.sessions, 3);
+        assert_eq!(stats.median_session_tokens, Some(1_000));
+        assert_eq!(stats.p90_session_tokens, Some(5_000));
+        assert_eq!(
+            stats.busiest_model,
+            Some(ModelTokens {
+                model: "claude-sonnet-4".to_string(),
+                tokens: 14_350,
+            })
+        );
+    }
+
+    #[test]
+    fn test_entries_outside_the_range_are_left_out() {
+        let entries = [
+            entry(date(1), Some("early"), "claude-opus-4", 1_000_000),
+            entry(date(5), Some("in"), "claude-sonnet-4", 10),
+            entry(date(11), Some("late"), "claude-opus-4", 1_000_000),
+        ];
+        let stats = stats(date(5), date(10), &entries);
+        assert_eq!(stats.sessions, 1);
+        assert_eq!(stats.median_session_tokens, Some(10));
+        assert_eq!(stats.busiest_model.unwrap().model, "claude-sonnet-4");
+        // Before the range but this month: still an active day.
+        assert_eq!(stats.active_days_this_month, 2);
+    }
+
+    #[test]
+    fn test_active_days_count_this_month_only() {
+        let february = NaiveDate::from_ymd_opt(2026, 2, 27).unwrap();
+        let entries = [
+            entry(february, Some("a"), "claude-sonnet-4", 10),
+            entry(date(2), Some("b"), "claude-sonnet-4", 10),
+            entry(date(2), Some("c"), "claude-sonnet-4", 10),
+            entry(date(9), Some("d"), "claude-sonnet-4", 10),
+            entry(date(9), Some("e"), "claude-sonnet-4", 0),
+            entry(date(10), Some("f"), "claude-sonnet-4", 0),
+        ];
+        let accumulator = StatsAccumulator::new(date(5), date(10));
+        assert_eq!(accumulator.scan_from(), date(1));
+        let stats = stats(february, date(10), &entries);
+        assert_eq!(stats.active_days_this_month, 2);
+        // Entries without tokens aren't sessions either.
+        assert_eq!(stats.sessions, 4);
+    }
+
+    #[test]
+    fn test_weekday_and_weekend_averages() {
+        // March 2 to 8, 2026 is Monday to Sunday.
+        assert_eq!(date(2).weekday(), Weekday::Mon);
+        assert_eq!(count_days(date(2), date(8)), (5, 2));
+        assert_eq!(count_days(date(2), date(15)), (10, 4));
+        assert_eq!(count_days(date(7), date(7)), (0, 1));
+
+        let pricing = PricingStore::default();
+        let tuesday = entry(date(3), Some("a"), "claude-sonnet-4", 1_000_000);
+        let saturday = entry(date(7), Some("b"), "claude-sonnet-4", 1_000_000);
+        let cost = tuesday.cost(&pricing);
+        assert!(cost > 0.0);
+
+        let week = stats(date(2), date(8), &[tuesday, saturday]);
+        let weekday = week.weekday_avg_cost_usd.unwrap();
+        let weekend = week.weekend_avg_cost_usd.unwrap();
+        assert!((weekday - cost / 5.0).abs() < 1e-9);
+        assert!((weekend - cost / 2.0).abs() < 1e-9);
+
+        // A single Saturday has no weekdays to average over.
+        let day = stats(date(7), date(7), &[]);
+        assert_eq!(day.weekday_avg_cost_usd, None);
+        assert_eq!(day.weekend_avg_cost_usd, Some(0.0));
+    }
+
+    #[test]
+    fn test_busiest_model_ties_break_by_name() {
+        let entries = [
+            entry(date(2), Some("a"), "gpt-5", 100),
+            entry(date(2), Some("a"), "claude-sonnet-4", 100),
+        ];
+        let stats = stats(date(1), date(10), &entries);
+        assert_eq!(stats.busiest_model.unwrap().model, "claude-sonnet-4");
+
+        let empty = self::stats(date(1), date(10), &[]);
+        assert_eq!(empty.sessions, 0);
+        assert_eq!(empty.median_session_tokens, None);
+        assert_eq!(empty.busiest_model, None);
+        assert_eq!(empty.active_days_this_month, 0);
+    }
+}
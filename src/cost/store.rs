@@ -1,20 +1,46 @@
-use crate::core::models::{CostSnapshot, CostUsageTokenSnapshot, DailyCost, DailyTokenUsage, Provider};
+use crate::core::models::{
+    CostSnapshot, CostUsageTokenSnapshot, DailyCost, DailyTokenUsage, Provider, UsageHeatmap,
+};
+use crate::core::settings::{CostTimezone, DEFAULT_SCAN_DAYS};
 use crate::cost::claude::ClaudeCostScanner;
 use crate::cost::codex::CodexCostScanner;
+use crate::cost::heatmap::aggregate_heatmap;
 use crate::cost::pricing::PricingStore;
-use crate::cost::scanner::{aggregate_entries, aggregate_token_usage, CostScanner};
+use crate::cost::project_filter::ProjectFilter;
+use crate::cost::scanner::{
+    aggregate_entries, aggregate_project_costs, aggregate_session_usage, aggregate_token_usage,
+    has_unpriced_models, today, CancellationToken, CostScanner, ScanContext, ScanProgress,
+    SessionWindow,
+};
 use anyhow::Result;
-use chrono::{Datelike, Duration, Local, NaiveDate};
-use std::collections::HashMap;
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::{watch, RwLock};
+
+/// Days of token history a snapshot's `daily` keeps: the window the popup
+/// and `cost --json` show, however far back the scan read.
+const DISPLAY_DAYS: i64 = 30;
 
 pub struct CostStore {
     claude_scanner: ClaudeCostScanner,
     codex_scanner: CodexCostScanner,
     pricing: PricingStore,
-    cached_costs: HashMap<Provider, CostSnapshot>,
-    cached_tokens: HashMap<Provider, CostUsageTokenSnapshot>,
+    /// Shared with the `UsageStore` and the popup rather than copied.
+    cached_costs: HashMap<Provider, Arc<CostSnapshot>>,
+    cached_tokens: HashMap<Provider, Arc<CostUsageTokenSnapshot>>,
     pricing_failed: bool,
     pricing_successful: bool,
+    active_scan: Option<CancellationToken>,
+    progress_tx: watch::Sender<ScanProgress>,
+    timezone: CostTimezone,
+    budgets: BTreeMap<String, f64>,
+    monthly_budget: Option<f64>,
+    include_claude_desktop: bool,
+    scan_days: u32,
+    include_projects: Vec<String>,
+    exclude_projects: Vec<String>,
+    project_filter: ProjectFilter,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -37,7 +63,88 @@ impl CostStore {
             cached_tokens: HashMap::new(),
             pricing_failed: !pricing_successful,
             pricing_successful,
+            active_scan: None,
+            progress_tx: watch::channel(ScanProgress::default()).0,
+            timezone: CostTimezone::default(),
+            budgets: BTreeMap::new(),
+            monthly_budget: None,
+            include_claude_desktop: false,
+            scan_days: DEFAULT_SCAN_DAYS,
+            include_projects: Vec::new(),
+            exclude_projects: Vec::new(),
+            project_filter: ProjectFilter::default(),
+        }
+    }
+
+    /// Takes effect from the next scan.
+    pub fn set_timezone(&mut self, timezone: CostTimezone) {
+        self.timezone = timezone;
+    }
+
+    pub fn timezone(&self) -> CostTimezone {
+        self.timezone
+    }
+
+    /// Monthly budgets from `[cost.budgets]`, checked after each scan.
+    pub fn set_budgets(&mut self, budgets: BTreeMap<String, f64>) {
+        self.budgets = budgets;
+    }
+
+    pub fn budgets(&self) -> &BTreeMap<String, f64> {
+        &self.budgets
+    }
+
+    /// `cost.monthly_budget`, checked against combined spend after each scan.
+    pub fn set_monthly_budget(&mut self, budget: Option<f64>) {
+        self.monthly_budget = budget;
+    }
+
+    pub fn monthly_budget(&self) -> Option<f64> {
+        self.monthly_budget
+    }
+
+    /// Takes effect from the next scan.
+    pub fn set_include_claude_desktop(&mut self, include: bool) {
+        self.include_claude_desktop = include;
+        self.claude_scanner.set_include_desktop(include);
+    }
+
+    pub fn include_claude_desktop(&self) -> bool {
+        self.include_claude_desktop
+    }
+
+    /// Days of logs to read, ending today. Takes effect from the next scan.
+    pub fn set_scan_days(&mut self, days: u32) {
+        self.scan_days = days;
+    }
+
+    pub fn scan_days(&self) -> u32 {
+        self.scan_days
+    }
+
+    /// The projects the Claude scan reads, from `cost.include_projects` and
+    /// `cost.exclude_projects`. New patterns drop the cached snapshots and
+    /// any scan still running with the old ones; returns whether they
+    /// changed, in which case the caller should rescan.
+    pub fn set_project_filter(&mut self, include: &[String], exclude: &[String]) -> bool {
+        if self.include_projects == include && self.exclude_projects == exclude {
+            return false;
         }
+        let filter = match ProjectFilter::new(include, exclude) {
+            Ok(filter) => filter,
+            Err(e) => {
+                tracing::warn!(error = %e, "Ignoring invalid project filter");
+                return false;
+            }
+        };
+        self.claude_scanner.set_project_filter(filter.clone());
+        self.project_filter = filter;
+        self.include_projects = include.to_vec();
+        self.exclude_projects = exclude.to_vec();
+        self.cancel_active_scan();
+        self.cached_costs.clear();
+        self.cached_tokens.clear();
+        true
     }
 
     pub async fn refresh_pricing(&mut self, force: bool) -> Result<PricingRefreshResult> {
@@ -53,6 +160,8 @@ impl CostStore {
 
                 // Update scanners with new pricing
                 self.claude_scanner = ClaudeCostScanner::new();
+                self.claude_scanner.set_include_desktop(self.include_claude_desktop);
+                self.claude_scanner.set_project_filter(self.project_filter.clone());
                 self.codex_scanner = CodexCostScanner::new();
 
                 self.pricing_successful = true;
@@ -70,147 +179,161 @@ impl CostStore {
         }
     }
 
-    pub fn scan_all(&mut self) -> HashMap<Provider, CostScanResult> {
-        let today = Local::now().date_naive();
+    pub fn begin_scan(
+        &mut self,
+        providers: &[Provider],
+        session_windows: &HashMap<Provider, SessionWindow>,
+    ) -> CostScanJob {
+        self.cancel_active_scan();
+        let token = CancellationToken::new();
+        self.active_scan = Some(token.clone());
+
+        let today = today(self.timezone);
         let month_start = NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap_or(today);
-        let since = month_start - Duration::days(30);
+        let since = scan_since(today, month_start, self.scan_days);
+
+        CostScanJob {
+            scanners: providers
+                .iter()
+                .map(|&provider| (provider, self.scanner_for(provider)))
+                .collect(),
+            session_windows: session_windows.clone(),
+            pricing: self.pricing.clone(),
+            pricing_failed: self.pricing_failed,
+            since,
+            today,
+            month_start,
+            ctx: ScanContext::new(token, self.progress_tx.clone(), self.timezone),
+        }
+    }
 
-        let scanners: [(Provider, &dyn CostScanner); 2] = [
-            (Provider::Claude, &self.claude_scanner),
-            (Provider::Codex, &self.codex_scanner),
-        ];
+    pub fn finish_scan(
+        &mut self,
+        outcome: CostScanOutcome,
+    ) -> Option<HashMap<Provider, CostScanResult>> {
+        if outcome.token.is_cancelled() {
+            tracing::debug!("Discarding results of cancelled cost scan");
+            return None;
+        }
 
-        let mut results = HashMap::new();
-        for (provider, scanner) in scanners {
-            match scanner.scan_entries(since, today) {
-                Ok(entries) => {
-                    let costs = aggregate_entries(&entries, &self.pricing);
-                    let tokens = aggregate_token_usage(&entries, &self.pricing);
-                    let cost_snapshot =
-                        Self::aggregate_costs(&costs, today, month_start, self.pricing_failed);
-                    let token_snapshot =
-                        Self::aggregate_tokens(&tokens, today, self.pricing_failed);
-                    self.cached_costs.insert(provider, cost_snapshot.clone());
-                    self.cached_tokens
-                        .insert(provider, token_snapshot.clone());
-                    results.insert(
-                        provider,
-                        CostScanResult {
-                            cost: cost_snapshot,
-                            tokens: token_snapshot,
-                        },
-                    );
-                }
-                Err(e) => {
-                    tracing::warn!(?provider, error = %e, "Failed to scan costs");
-                    let cost_snapshot = self
-                        .cached_costs
-                        .get(&provider)
-                        .cloned()
-                        .unwrap_or_else(|| CostSnapshot {
-                            pricing_estimate: self.pricing_failed,
-                            log_error: true,
-                            ..CostSnapshot::default()
-                        });
-                    let cost_snapshot = mark_log_error(cost_snapshot, self.pricing_failed);
-                    let token_snapshot = self
-                        .cached_tokens
-                        .get(&provider)
-                        .cloned()
-                        .unwrap_or_else(|| CostUsageTokenSnapshot {
-                            session_tokens: None,
-                            session_cost_usd: None,
-                            last_30_days_tokens: None,
-                            last_30_days_cost_usd: None,
-                            daily: Vec::new(),
-                            updated_at: chrono::Utc::now(),
-                        });
-                    self.cached_costs.insert(provider, cost_snapshot.clone());
-                    self.cached_tokens
-                        .insert(provider, token_snapshot.clone());
-                    results.insert(
-                        provider,
-                        CostScanResult {
-                            cost: cost_snapshot,
-                            tokens: token_snapshot,
-                        },
-                    );
-                }
-            };
+        let results = outcome
+            .results
+            .into_iter()
+            .map(|(provider, result)| (provider, self.apply_scan_result(provider, result)))
+            .collect();
+        Some(results)
+    }
+
+    pub fn cancel_active_scan(&mut self) {
+        if let Some(token) = self.active_scan.take() {
+            token.cancel();
         }
+    }
 
-        results
+    pub fn scan_all(&mut self) -> HashMap<Provider, CostScanResult> {
+        let job = self.begin_scan(&[Provider::Claude, Provider::Codex], &HashMap::new());
+        job.run()
+            .and_then(|outcome| self.finish_scan(outcome))
+            .unwrap_or_default()
     }
 
     #[allow(dead_code)]
     pub fn scan_provider(&mut self, provider: Provider) -> Option<CostScanResult> {
-        let today = Local::now().date_naive();
-        let month_start = NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap_or(today);
-        let since = month_start - Duration::days(30);
-
-        let scanner: &dyn CostScanner = match provider {
-            Provider::Claude => &self.claude_scanner,
-            Provider::Codex => &self.codex_scanner,
-        };
+        let job = self.begin_scan(&[provider], &HashMap::new());
+        job.run()
+            .and_then(|outcome| self.finish_scan(outcome))
+            .and_then(|mut results| results.remove(&provider))
+    }
 
-        match scanner.scan_entries(since, today) {
-            Ok(entries) => {
-                let costs = aggregate_entries(&entries, &self.pricing);
-                let tokens = aggregate_token_usage(&entries, &self.pricing);
-                let cost_snapshot =
-                    Self::aggregate_costs(&costs, today, month_start, self.pricing_failed);
-                let token_snapshot = Self::aggregate_tokens(&tokens, today, self.pricing_failed);
-                self.cached_costs.insert(provider, cost_snapshot.clone());
-                self.cached_tokens
-                    .insert(provider, token_snapshot.clone());
-                Some(CostScanResult {
-                    cost: cost_snapshot,
-                    tokens: token_snapshot,
-                })
+    /// Set up like the scans this store runs.
+    pub fn scanner_for(&self, provider: Provider) -> Box<dyn CostScanner> {
+        match provider {
+            Provider::Claude => Box::new(self.claude_scanner.clone()),
+            Provider::Codex | Provider::CodexWorkspace(_) => {
+                Box::new(self.codex_scanner.clone())
             }
+        }
+    }
+
+    fn apply_scan_result(
+        &mut self,
+        provider: Provider,
+        result: Result<CostScanResult>,
+    ) -> CostScanResult {
+        let (cost_snapshot, token_snapshot) = match result {
+            Ok(result) => (result.cost, result.tokens),
             Err(e) => {
                 tracing::warn!(?provider, error = %e, "Failed to scan costs");
                 let cost_snapshot = self
                     .cached_costs
                     .get(&provider)
-                    .cloned()
+                    .map(|cost| CostSnapshot::clone(cost))
                     .unwrap_or_else(|| CostSnapshot {
                         pricing_estimate: self.pricing_failed,
                         log_error: true,
                         ..CostSnapshot::default()
                     });
-                let cost_snapshot = mark_log_error(cost_snapshot, self.pricing_failed);
+                let cost_snapshot = Arc::new(mark_log_error(cost_snapshot, self.pricing_failed));
                 let token_snapshot = self
                     .cached_tokens
                     .get(&provider)
                     .cloned()
-                    .unwrap_or_else(|| CostUsageTokenSnapshot {
-                        session_tokens: None,
-                        session_cost_usd: None,
-                        last_30_days_tokens: None,
-                        last_30_days_cost_usd: None,
-                        daily: Vec::new(),
-                        updated_at: chrono::Utc::now(),
+                    .unwrap_or_else(|| {
+                        Arc::new(CostUsageTokenSnapshot {
+                            session_tokens: None,
+                            session_cost_usd: None,
+                            session_start: None,
+                            last_30_days_tokens: None,
+                            last_30_days_cost_usd: None,
+                            daily: Vec::new(),
+                            heatmap: UsageHeatmap::default(),
+                            updated_at: chrono::Utc::now(),
+                        })
                     });
-                self.cached_costs.insert(provider, cost_snapshot.clone());
-                self.cached_tokens
-                    .insert(provider, token_snapshot.clone());
-                Some(CostScanResult {
-                    cost: cost_snapshot,
-                    tokens: token_snapshot,
-                })
+                (cost_snapshot, token_snapshot)
             }
+        };
+
+        self.cached_costs
+            .insert(provider, Arc::clone(&cost_snapshot));
+        self.cached_tokens
+            .insert(provider, Arc::clone(&token_snapshot));
+        CostScanResult {
+            cost: cost_snapshot,
+            tokens: token_snapshot,
         }
     }
 
     #[allow(dead_code)]
     pub fn get_cached(&self, provider: Provider) -> Option<&CostSnapshot> {
-        self.cached_costs.get(&provider)
+        self.cached_costs.get(&provider).map(Arc::as_ref)
     }
 
     #[allow(dead_code)]
     pub fn get_cached_tokens(&self, provider: Provider) -> Option<&CostUsageTokenSnapshot> {
-        self.cached_tokens.get(&provider)
+        self.cached_tokens.get(&provider).map(Arc::as_ref)
+    }
+
+    /// Approximate size of the caches, for the daemon's periodic memory
+    /// report.
+    pub fn sizes(&self) -> CostStoreSizes {
+        CostStoreSizes {
+            cost_days: self
+                .cached_costs
+                .values()
+                .map(|cost| cost.daily_breakdown.len())
+                .sum(),
+            token_days: self
+                .cached_tokens
+                .values()
+                .map(|tokens| tokens.daily.len())
+                .sum(),
+            projects: self
+                .cached_costs
+                .values()
+                .map(|cost| cost.project_costs.len())
+                .sum(),
+        }
     }
 
     #[allow(dead_code)]
@@ -249,6 +372,8 @@ impl CostStore {
             daily_breakdown,
             pricing_estimate,
             log_error: false,
+            project_costs: BTreeMap::new(),
+            excluded_projects: 0,
         }
     }
 
@@ -257,7 +382,7 @@ impl CostStore {
         today: NaiveDate,
         _pricing_estimate: bool,
     ) -> CostUsageTokenSnapshot {
-        let cutoff = today - chrono::Duration::days(29);
+        let cutoff = today - Duration::days(DISPLAY_DAYS - 1);
         let filtered: Vec<DailyTokenUsage> = daily
             .iter()
             .filter(|d| d.date >= cutoff && d.date <= today)
@@ -282,6 +407,7 @@ impl CostStore {
         CostUsageTokenSnapshot {
             session_tokens: current_day.and_then(|d| d.total_tokens),
             session_cost_usd: current_day.and_then(|d| d.cost_usd),
+            session_start: None,
             last_30_days_tokens: if last_30_days_tokens > 0 {
                 Some(last_30_days_tokens)
             } else {
@@ -293,11 +419,19 @@ impl CostStore {
                 None
             },
             daily: filtered,
+            heatmap: UsageHeatmap::default(),
             updated_at: chrono::Utc::now(),
         }
     }
 }
 
+/// The first day a scan reads: `scan_days` back from `today`, but never
+/// after the first of the month, which the monthly spend counts from.
+fn scan_since(today: NaiveDate, month_start: NaiveDate, scan_days: u32) -> NaiveDate {
+    let since = today - Duration::days(i64::from(scan_days.saturating_sub(1)));
+    since.min(month_start)
+}
+
 fn normalize_cost(value: f64) -> f64 {
     if value.abs() < 0.005 {
         0.0
@@ -320,14 +454,131 @@ impl Default for CostStore {
 
 #[derive(Debug, Clone)]
 pub struct CostScanResult {
-    pub cost: CostSnapshot,
-    pub tokens: CostUsageTokenSnapshot,
+    pub cost: Arc<CostSnapshot>,
+    pub tokens: Arc<CostUsageTokenSnapshot>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CostStoreSizes {
+    pub cost_days: usize,
+    pub token_days: usize,
+    pub projects: usize,
+}
+
+pub struct CostScanJob {
+    scanners: Vec<(Provider, Box<dyn CostScanner>)>,
+    session_windows: HashMap<Provider, SessionWindow>,
+    pricing: PricingStore,
+    pricing_failed: bool,
+    since: NaiveDate,
+    today: NaiveDate,
+    month_start: NaiveDate,
+    ctx: ScanContext,
+}
+
+pub struct CostScanOutcome {
+    token: CancellationToken,
+    results: Vec<(Provider, Result<CostScanResult>)>,
+}
+
+impl CostScanJob {
+    /// Runs the scan to completion, returning `None` if it was cancelled midway.
+    pub fn run(self) -> Option<CostScanOutcome> {
+        let mut results = Vec::with_capacity(self.scanners.len());
+
+        for (provider, scanner) in &self.scanners {
+            let result = scanner
+                .scan_entries(self.since, self.today, &self.ctx)
+                .map(|entries| {
+                    let pricing_estimate =
+                        self.pricing_failed || has_unpriced_models(&entries, &self.pricing);
+                    let costs = aggregate_entries(&entries, &self.pricing);
+                    let tokens = aggregate_token_usage(&entries, &self.pricing);
+                    let mut token_snapshot =
+                        CostStore::aggregate_tokens(&tokens, self.today, pricing_estimate);
+                    token_snapshot.heatmap =
+                        aggregate_heatmap(&entries, self.today, self.ctx.timezone());
+                    if let Some(window) = self.session_windows.get(provider) {
+                        let (session_tokens, session_cost) =
+                            aggregate_session_usage(&entries, *window, &self.pricing);
+                        token_snapshot.session_tokens = Some(session_tokens);
+                        token_snapshot.session_cost_usd = Some(normalize_cost(session_cost));
+                        token_snapshot.session_start = Some(window.start);
+                    }
+                    let mut cost_snapshot = CostStore::aggregate_costs(
+                        &costs,
+                        self.today,
+                        self.month_start,
+                        pricing_estimate,
+                    );
+                    cost_snapshot.project_costs = aggregate_project_costs(
+                        &entries,
+                        &self.pricing,
+                        self.month_start,
+                        self.today,
+                    );
+                    cost_snapshot.excluded_projects = self.ctx.take_excluded_projects();
+                    CostScanResult {
+                        cost: Arc::new(cost_snapshot),
+                        tokens: Arc::new(token_snapshot),
+                    }
+                });
+
+            if self.ctx.is_cancelled() {
+                tracing::debug!(?provider, "Cost scan cancelled");
+                return None;
+            }
+            results.push((*provider, result));
+        }
+
+        Some(CostScanOutcome {
+            token: self.ctx.token().clone(),
+            results,
+        })
+    }
+}
+
+/// Scans all providers on a blocking thread without holding the store lock,
+/// swapping the cached results in only if the scan was not superseded.
+pub async fn scan_in_background(
+    cost_store: &Arc<RwLock<CostStore>>,
+    session_windows: HashMap<Provider, SessionWindow>,
+) -> Option<HashMap<Provider, CostScanResult>> {
+    let job = cost_store
+        .write()
+        .await
+        .begin_scan(&[Provider::Claude, Provider::Codex], &session_windows);
+
+    let outcome = match tokio::task::spawn_blocking(move || job.run()).await {
+        Ok(outcome) => outcome?,
+        Err(e) => {
+            tracing::warn!(error = %e, "Cost scan task failed");
+            return None;
+        }
+    };
+
+    cost_store.write().await.finish_scan(outcome)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_scan_since() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 18).unwrap();
+        let month_start = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let date = |month, day| NaiveDate::from_ymd_opt(2026, month, day).unwrap();
+
+        assert_eq!(scan_since(today, month_start, 35), date(2, 12));
+        assert_eq!(scan_since(today, month_start, 1), month_start);
+        // Short ranges still cover the month so far.
+        assert_eq!(scan_since(today, month_start, 7), month_start);
+        let late = NaiveDate::from_ymd_opt(2026, 3, 30).unwrap();
+        assert_eq!(scan_since(late, month_start, 7), month_start);
+        assert_eq!(scan_since(today, today, 7), date(3, 12));
+    }
+
     #[test]
     fn test_aggregate_costs() {
         let today = NaiveDate::from_ymd_opt(2026, 1, 18).unwrap();
@@ -338,16 +589,19 @@ mod tests {
                 date: NaiveDate::from_ymd_opt(2026, 1, 15).unwrap(),
                 model: "claude-sonnet-4".to_string(),
                 cost: 5.0,
+                tokens: 0,
             },
             DailyCost {
                 date: NaiveDate::from_ymd_opt(2026, 1, 18).unwrap(),
                 model: "claude-sonnet-4".to_string(),
                 cost: 8.0,
+                tokens: 0,
             },
             DailyCost {
                 date: NaiveDate::from_ymd_opt(2026, 1, 18).unwrap(),
                 model: "claude-opus-4".to_string(),
                 cost: 4.0,
+                tokens: 0,
             },
         ];
 
@@ -371,6 +625,74 @@ mod tests {
         assert!(snapshot.daily_breakdown.is_empty());
     }
 
+    #[test]
+    fn test_aggregate_tokens_keeps_display_window() {
+        let today = NaiveDate::from_ymd_opt(2026, 3, 18).unwrap();
+        let daily: Vec<DailyTokenUsage> = (0..60)
+            .map(|days_ago| DailyTokenUsage {
+                date: today - Duration::days(days_ago),
+                total_tokens: Some(100),
+                cost_usd: Some(1.0),
+                models: Vec::new(),
+            })
+            .collect();
+
+        let tokens = CostStore::aggregate_tokens(&daily, today, false);
+
+        assert_eq!(tokens.daily.len(), DISPLAY_DAYS as usize);
+        assert_eq!(tokens.last_30_days_tokens, Some(3000));
+        let oldest = today - Duration::days(DISPLAY_DAYS - 1);
+        assert!(tokens.daily.iter().all(|day| day.date >= oldest));
+    }
+
+    #[test]
+    fn test_superseded_scan_is_discarded() {
+        let mut store = CostStore::new();
+
+        let first = store.begin_scan(&[], &HashMap::new());
+        let second = store.begin_scan(&[], &HashMap::new());
+
+        let first = first.run().expect("empty scan completes");
+        assert!(store.finish_scan(first).is_none());
+
+        let second = second.run().expect("empty scan completes");
+        assert!(store.finish_scan(second).is_some());
+    }
+
+    #[test]
+    fn test_cancelled_scan_is_discarded() {
+        let mut store = CostStore::new();
+        let job = store.begin_scan(&[], &HashMap::new());
+        store.cancel_active_scan();
+
+        let outcome = job.run().expect("empty scan completes");
+        assert!(store.finish_scan(outcome).is_none());
+    }
+
+    #[test]
+    fn test_project_filter_change_invalidates_cache() {
+        let mut store = CostStore::new();
+        let patterns = vec!["scratch-*".to_string()];
+        store
+            .cached_costs
+            .insert(Provider::Claude, Arc::new(CostSnapshot::default()));
+        let job = store.begin_scan(&[], &HashMap::new());
+
+        assert!(store.set_project_filter(&[], &patterns));
+        assert!(store.get_cached(Provider::Claude).is_none());
+        // A scan started before the change no longer lands in the cache.
+        let outcome = job.run().expect("empty scan completes");
+        assert!(store.finish_scan(outcome).is_none());
+
+        store
+            .cached_costs
+            .insert(Provider::Claude, Arc::new(CostSnapshot::default()));
+        assert!(!store.set_project_filter(&[], &patterns));
+        assert!(store.get_cached(Provider::Claude).is_some());
+        assert!(!store.set_project_filter(&[], &["archive/[".to_string()]));
+        assert!(store.get_cached(Provider::Claude).is_some());
+    }
+
     #[test]
     fn test_cost_store_new() {
         let store = CostStore::new();
@@ -1,148 +1,613 @@
 use crate::core::credentials::CredentialsWatcher;
-use crate::core::models::{CostSnapshot, CostUsageTokenSnapshot, Provider, UsageSnapshot};
+use crate::core::incidents::{Incident, IncidentTracker};
+use crate::core::instance;
+use crate::core::limits_file::{limits_file_path, write_limits_file, LimitsFile, ProviderLimits};
+use crate::core::lockouts::{Lockout, LockoutLog};
+use crate::core::models::{
+    CostSnapshot, CostUsageTokenSnapshot, ErrorEvent, FetchLatency, Provider, ProviderHealth,
+    RateWindow, UsageSnapshot,
+};
+use crate::core::notifications::{
+    notification_actions_supported, send_budget_notification, send_high_usage_notification,
+    send_weekly_summary_notification, AlertSnoozes, UsageAlert, UsageAlerts,
+};
+use crate::core::reset_estimate::WindowSlot;
 use crate::core::retry::RetryState;
-use crate::core::settings::{Settings, SettingsWatcher};
-use crate::core::store::UsageStore;
-use crate::cost::{CostStore, PricingRefreshResult};
-use crate::daemon::dbus::{start_dbus_server, DbusCommand};
-use crate::daemon::tray::{run_animation_loop, TrayEvent, TrayManager};
-use crate::providers::ProviderRegistry;
-use crate::ui::PopupWindow;
+use crate::core::schema::SCHEMA_VERSION;
+use crate::core::settings::{
+    AlertSound, AnimationMode, CostTimezone, NotificationSettings, ResetTimeFormat, Settings,
+    SettingsWatcher, ShortcutSettings, TrayWindow,
+};
+use crate::core::store::{StoreUpdate, UsageStore};
+use crate::core::urgency::{rank_by_urgency, urgency_score};
+use crate::core::velocity::Velocity;
+use crate::core::weekly_summary::{summarize_week, WeekUpdate, WeeklySummaryLog};
+use crate::cost::{
+    combined_monthly_cost, combined_project_costs, day_of, scan_in_background, today,
+    unmatched_budgets, BudgetState, CostStore, MonthlySpend, PricingRefreshResult, SessionWindow,
+};
+use crate::daemon::dbus::{bus_name, emit_cost_updated, start_dbus_server, DbusCommand};
+use crate::daemon::display::display_in_env;
+use crate::daemon::hotkey::{GlobalShortcut, ShortcutError};
+use crate::daemon::login::LoginProgress;
+use crate::daemon::popup_memory::{PopupMemory, SharedPopupMemory};
+use crate::daemon::refresh_governor::{RefreshGovernor, RefreshTrigger};
+use crate::daemon::reset_blackout::ResetBlackout;
+use crate::daemon::shutdown::{trigger_on_signals, Shutdown};
+use crate::daemon::supervisor;
+use crate::daemon::tray::{run_animation_loop, run_cycle_loop, TrayEvent, TrayManager, TrayUsage};
+use crate::daemon::webhook::run_webhook_loop;
+use crate::providers::{dump_path_in, error_kind, ProviderRegistry};
+use crate::ui::colors::ColorScheme;
+use crate::ui::{play_alert_sound, system_animations_enabled, PopupWindow, ViewChange};
 use anyhow::Result;
-use global_hotkey::hotkey::{Code, HotKey, Modifiers};
-use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager};
+use global_hotkey::GlobalHotKeyEvent;
 use gtk4::glib;
 use gtk4::prelude::*;
 use libadwaita as adw;
 use std::cell::RefCell;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{broadcast, mpsc, RwLock};
 
 const APP_ID: &str = "com.github.kabilan.claude-bar";
+const MAX_POLL_SLEEP: Duration = Duration::from_secs(600);
+/// How long loops get to stop at shutdown before they are aborted.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+const MEMORY_REPORT_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Whether the daemon fetches model prices from models.dev.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PricingRefresh {
+    Fetch,
+    /// Keeps the cached or embedded prices.
+    Disabled,
+}
+
+/// What `run` works against. `--dry-run` swaps in canned providers and turns
+/// the pricing fetch off.
+pub struct DaemonOptions {
+    pub no_ui: bool,
+    /// Builds the providers to poll from the settings loaded at startup.
+    pub providers: fn(&Settings) -> ProviderRegistry,
+    pub pricing: PricingRefresh,
+    /// Stops the daemon after this long; `None` runs until asked to quit.
+    pub run_for: Option<Duration>,
+    /// Posts to `webhook.url` when one is set.
+    pub webhook: bool,
+}
+
+impl Default for DaemonOptions {
+    fn default() -> Self {
+        Self {
+            no_ui: false,
+            providers: ProviderRegistry::new,
+            pricing: PricingRefresh::Fetch,
+            run_for: None,
+            webhook: true,
+        }
+    }
+}
 
-pub async fn run() -> Result<()> {
-    tracing::info!(app_id = APP_ID, "Initializing GTK application");
+pub async fn run(options: DaemonOptions) -> Result<()> {
+    let ui = init_ui(options.no_ui);
 
     let mut settings_watcher = SettingsWatcher::new()?;
     let settings = settings_watcher.get().await;
     settings_watcher.start_watching()?;
 
+    let shutdown = Shutdown::new();
+    shutdown.spawn(trigger_on_signals(shutdown.clone()));
+    if let Some(run_for) = options.run_for {
+        let stop = shutdown.clone();
+        shutdown.spawn(async move {
+            tokio::time::sleep(run_for).await;
+            tracing::info!(?run_for, "Run time is up");
+            stop.trigger();
+        });
+    }
+
     let store = Arc::new(UsageStore::new());
-    let cost_store = Arc::new(RwLock::new(CostStore::new()));
+    let mut cost_store = CostStore::new();
+    cost_store.set_timezone(settings.cost.timezone);
+    cost_store.set_budgets(settings.cost.budgets.clone());
+    cost_store.set_monthly_budget(settings.cost.monthly_budget);
+    cost_store.set_include_claude_desktop(settings.cost.include_claude_desktop);
+    cost_store.set_scan_days(settings.cost.scan_days);
+    cost_store.set_project_filter(
+        &settings.cost.include_projects,
+        &settings.cost.exclude_projects,
+    );
+    let cost_store = Arc::new(RwLock::new(cost_store));
     let tray_manager = Arc::new(TrayManager::new());
+    let governor = Arc::new(RefreshGovernor::new());
     let retry_states = Arc::new(RwLock::new(HashMap::<Provider, RetryState>::new()));
 
-    let registry = Arc::new(ProviderRegistry::new(&settings));
+    let registry = Arc::new((options.providers)(&settings));
 
     let cred_paths = registry.credentials_paths();
     let (_cred_watcher, cred_change_rx) = CredentialsWatcher::start(cred_paths)?;
 
-    tray_manager.start(&settings).await?;
-    tokio::spawn(run_animation_loop(Arc::clone(&tray_manager)));
+    // Without a UI the tray manager holds no icons, so updates to it are
+    // no-ops and the D-Bus tray status reads unregistered.
+    if ui {
+        tray_manager.start(&settings).await?;
+        shutdown.spawn(run_animation_loop(Arc::clone(&tray_manager)));
+        shutdown.spawn(run_cycle_loop(Arc::clone(&tray_manager)));
+    }
 
     let (ui_tx, ui_rx) = mpsc::unbounded_channel::<UiCommand>();
-
-    start_global_shortcut(
-        &settings,
-        Arc::clone(&store),
-        ui_tx.clone(),
-        Arc::clone(&registry),
-    );
+    let popup_memory = PopupMemory::shared();
+
+    let global_shortcut = if ui {
+        start_global_shortcut(
+            &settings,
+            Arc::clone(&store),
+            ui_tx.clone(),
+            Arc::clone(&registry),
+            Arc::clone(&tray_manager),
+            Arc::clone(&governor),
+            Arc::clone(&popup_memory),
+        )
+    } else {
+        None
+    };
 
     let (dbus_cmd_tx, dbus_cmd_rx) = mpsc::unbounded_channel::<DbusCommand>();
-    let _dbus_connection = start_dbus_server(dbus_cmd_tx).await?;
+    let dbus_connection = start_dbus_server(
+        dbus_cmd_tx,
+        Arc::clone(&store),
+        tray_manager.registered_flag(),
+        settings_watcher.shared(),
+    )
+    .await?;
 
-    tokio::spawn(handle_dbus_commands(
+    shutdown.spawn(handle_dbus_commands(
         dbus_cmd_rx,
         Arc::clone(&registry),
         Arc::clone(&store),
         Arc::clone(&cost_store),
         Arc::clone(&tray_manager),
+        Arc::clone(&governor),
         ui_tx.clone(),
+        dbus_connection.clone(),
+        options.pricing,
+        shutdown.clone(),
     ));
 
-    tokio::spawn(run_polling_loop(
-        Arc::clone(&registry),
+    let cred_change_rx = Arc::new(tokio::sync::Mutex::new(cred_change_rx));
+    shutdown.track(supervised("provider polling", &ui_tx, &shutdown, {
+        let registry = Arc::clone(&registry);
+        let store = Arc::clone(&store);
+        let tray = Arc::clone(&tray_manager);
+        let governor = Arc::clone(&governor);
+        let ui_tx = ui_tx.clone();
+        move || {
+            run_polling_loop(
+                Arc::clone(&registry),
+                Arc::clone(&store),
+                Arc::clone(&tray),
+                Arc::clone(&governor),
+                Arc::clone(&retry_states),
+                ui_tx.clone(),
+                Arc::clone(&cred_change_rx),
+            )
+        }
+    }));
+
+    shutdown.spawn(run_incident_loop(
         Arc::clone(&store),
         Arc::clone(&tray_manager),
-        Arc::clone(&retry_states),
+        settings_watcher.subscribe(),
+        settings.notifications.incident_check,
+    ));
+
+    shutdown.spawn(run_usage_alert_loop(
+        Arc::clone(&store),
+        settings_watcher.subscribe(),
+        settings.notifications.clone(),
         ui_tx.clone(),
-        cred_change_rx,
     ));
 
-    tokio::spawn(run_pricing_refresh_loop(Arc::clone(&cost_store)));
-    tokio::spawn(run_cost_scan_loop(
+    shutdown.spawn(run_lockout_loop(Arc::clone(&store), ui_tx.clone()));
+
+    shutdown.spawn(run_weekly_summary_loop(
+        Arc::clone(&store),
+        settings_watcher.subscribe(),
+        settings.clone(),
+    ));
+
+    shutdown.spawn(run_memory_report_loop(
+        Arc::clone(&store),
         Arc::clone(&cost_store),
+    ));
+
+    shutdown.spawn(run_limits_file_loop(
         Arc::clone(&store),
-        ui_tx.clone(),
+        settings_watcher.subscribe(),
+        settings.clone(),
     ));
 
-    let mut settings_rx = settings_watcher.subscribe();
-    let tray_for_settings = Arc::clone(&tray_manager);
-    let ui_tx_settings = ui_tx.clone();
-    tokio::spawn(async move {
-        while let Ok(new_settings) = settings_rx.recv().await {
-            tray_for_settings
-                .set_theme_mode(new_settings.theme.mode.clone())
-                .await;
-            let _ = ui_tx_settings.send(UiCommand::ApplySettings {
-                show_as_remaining: new_settings.display.show_as_remaining,
-                theme_mode: new_settings.theme.mode.clone(),
-                popup: new_settings.popup.clone(),
-            });
+    if options.webhook {
+        shutdown.spawn(run_webhook_loop(
+            Arc::clone(&store),
+            settings_watcher.subscribe(),
+            settings.clone(),
+        ));
+    }
+
+    let cost_loops = if settings.cost.enabled {
+        start_cost_loops(
+            &cost_store,
+            &store,
+            &tray_manager,
+            &ui_tx,
+            &dbus_connection,
+            options.pricing,
+            &shutdown,
+        )
+    } else {
+        tracing::info!("Cost scanning disabled in settings");
+        Vec::new()
+    };
+
+    let settings_loop = Arc::new(tokio::sync::Mutex::new(SettingsLoop {
+        settings_rx: settings_watcher.subscribe(),
+        cost_timezone: settings.cost.timezone,
+        cost_loops,
+        pricing: options.pricing,
+        shortcuts: settings.shortcuts.clone(),
+        global_shortcut,
+        ui,
+        shutdown: shutdown.clone(),
+    }));
+    shutdown.track(supervised("settings watcher", &ui_tx, &shutdown, {
+        let tray = Arc::clone(&tray_manager);
+        let store = Arc::clone(&store);
+        let cost_store = Arc::clone(&cost_store);
+        let ui_tx = ui_tx.clone();
+        let dbus = dbus_connection.clone();
+        let popup_memory = Arc::clone(&popup_memory);
+        move || {
+            run_settings_loop(
+                Arc::clone(&settings_loop),
+                Arc::clone(&tray),
+                Arc::clone(&store),
+                Arc::clone(&cost_store),
+                ui_tx.clone(),
+                dbus.clone(),
+                Arc::clone(&popup_memory),
+            )
         }
-    });
+    }));
 
-    if let Some(mut event_rx) = tray_manager.take_event_receiver().await {
-        let store_clone = Arc::clone(&store);
-        let registry_clone = Arc::clone(&registry);
-        let tray_clone = Arc::clone(&tray_manager);
-        let ui_tx_clone = ui_tx.clone();
-
-        tokio::spawn(async move {
-            while let Some(event) = event_rx.recv().await {
-                handle_tray_event(
-                    event,
-                    &store_clone,
-                    &registry_clone,
-                    &tray_clone,
-                    &ui_tx_clone,
-                )
-                .await;
+    let tray_events = if ui {
+        tray_manager.take_event_receiver().await
+    } else {
+        None
+    };
+    if let Some(event_rx) = tray_events {
+        let event_rx = Arc::new(tokio::sync::Mutex::new(event_rx));
+        let store = Arc::clone(&store);
+        let registry = Arc::clone(&registry);
+        let tray = Arc::clone(&tray_manager);
+        let governor = Arc::clone(&governor);
+        let ui_tx_events = ui_tx.clone();
+        let popup_memory = Arc::clone(&popup_memory);
+        let shutdown_events = shutdown.clone();
+
+        shutdown.track(supervised("tray events", &ui_tx, &shutdown, move || {
+            let event_rx = Arc::clone(&event_rx);
+            let store = Arc::clone(&store);
+            let registry = Arc::clone(&registry);
+            let tray = Arc::clone(&tray);
+            let governor = Arc::clone(&governor);
+            let ui_tx = ui_tx_events.clone();
+            let popup_memory = Arc::clone(&popup_memory);
+            let shutdown = shutdown_events.clone();
+            async move {
+                let mut event_rx = event_rx.lock().await;
+                while let Some(event) = event_rx.recv().await {
+                    handle_tray_event(
+                        event,
+                        &store,
+                        &registry,
+                        &tray,
+                        &governor,
+                        &ui_tx,
+                        &popup_memory,
+                        &shutdown,
+                    )
+                    .await;
+                }
             }
-        });
+        }));
     }
 
-    run_gtk_main_loop(
-        ui_rx,
-        settings.theme.mode,
-        settings.display.show_as_remaining,
-        settings.popup.clone(),
-        Arc::clone(&tray_manager),
-    )
-    .await
+    let provider_tabs = settings
+        .enabled_providers()
+        .into_iter()
+        .map(|provider| (provider, settings.provider_label(provider)))
+        .collect();
+    let result = if ui {
+        run_gtk_main_loop(
+            ui_rx,
+            ui_tx.clone(),
+            settings.theme.mode,
+            remembered_show_as_remaining(&popup_memory, settings.display.show_as_remaining),
+            settings.display.show_value_estimate,
+            settings.display.reset_time_format,
+            settings.display.hide_costs,
+            settings.cost.enabled,
+            settings.cost.timezone,
+            settings.display.sort_providers_by_urgency,
+            settings.display.animations,
+            ColorScheme::new(settings.display.colorblind_mode),
+            settings.popup.clone(),
+            settings.terminal.clone(),
+            provider_tabs,
+            Arc::clone(&tray_manager),
+            Arc::clone(&popup_memory),
+            shutdown.clone(),
+        )
+        .await
+    } else {
+        run_headless(ui_rx, &shutdown).await
+    };
+
+    // Stop every loop before tearing down what they use, so none of them
+    // sees the tray or bus disappear underneath it.
+    tracing::info!("Shutting down");
+    shutdown.trigger();
+    cost_store.write().await.cancel_active_scan();
+    if !shutdown.join(SHUTDOWN_TIMEOUT).await {
+        tracing::warn!(
+            timeout = ?SHUTDOWN_TIMEOUT,
+            "Tasks still running at shutdown were aborted"
+        );
+    }
+    tray_manager.shutdown().await;
+    if let Err(e) = dbus_connection.release_name(bus_name()).await {
+        tracing::warn!(error = %e, "Failed to release the D-Bus name");
+    }
+    tracing::info!("Daemon stopped");
+    result
 }
 
+/// Initializes GTK for the tray and popup. Returns false, leaving the daemon
+/// headless, for `--no-ui`, when no display is advertised or when GTK can't
+/// use the one that is.
+fn init_ui(no_ui: bool) -> bool {
+    if no_ui {
+        tracing::info!("Running without a UI (--no-ui)");
+        return false;
+    }
+    if !display_in_env() {
+        tracing::info!("WAYLAND_DISPLAY and DISPLAY are unset; running without a UI");
+        return false;
+    }
+
+    tracing::info!(
+        app_id = %instance::scoped(APP_ID, '.'),
+        "Initializing GTK application"
+    );
+    // libadwaita manages its own Adwaita-based theming; custom GTK themes
+    // (via GTK_THEME or ~/.config/gtk-4.0/gtk.css) are unsupported and cause
+    // warnings about missing GResource bundles.
+    std::env::remove_var("GTK_THEME");
+    if let Err(e) = gtk4::init() {
+        tracing::warn!(error = %e, "Failed to initialize GTK4; running without a UI");
+        return false;
+    }
+    true
+}
+
+/// Stands in for the GTK main loop without a UI. Polling, cost scans and
+/// D-Bus carry on; commands meant for the popup are dropped.
+async fn run_headless(
+    mut ui_rx: mpsc::UnboundedReceiver<UiCommand>,
+    shutdown: &Shutdown,
+) -> Result<()> {
+    shutdown
+        .run_until(async move { while ui_rx.recv().await.is_some() {} })
+        .await;
+    Ok(())
+}
+
+/// State of the settings watcher that must survive a restart of its task.
+struct SettingsLoop {
+    settings_rx: broadcast::Receiver<Settings>,
+    cost_timezone: crate::core::settings::CostTimezone,
+    cost_loops: Vec<tokio::task::JoinHandle<()>>,
+    pricing: PricingRefresh,
+    shortcuts: ShortcutSettings,
+    /// `None` without a UI, or when the hotkey manager couldn't start.
+    global_shortcut: Option<Arc<GlobalShortcut>>,
+    /// Without a UI there are no tray icons to rebuild.
+    ui: bool,
+    shutdown: Shutdown,
+}
+
+async fn run_settings_loop(
+    state: Arc<tokio::sync::Mutex<SettingsLoop>>,
+    tray: Arc<TrayManager>,
+    store: Arc<UsageStore>,
+    cost_store: Arc<RwLock<CostStore>>,
+    ui_tx: mpsc::UnboundedSender<UiCommand>,
+    dbus: zbus::Connection,
+    popup_memory: SharedPopupMemory,
+) {
+    let mut guard = state.lock().await;
+    let state = &mut *guard;
+    while let Ok(new_settings) = state.settings_rx.recv().await {
+        if new_settings.cost.timezone != state.cost_timezone {
+            state.cost_timezone = new_settings.cost.timezone;
+            cost_store.write().await.set_timezone(state.cost_timezone);
+            if !state.cost_loops.is_empty() {
+                tracing::info!(
+                    cost_timezone = ?state.cost_timezone,
+                    "Cost timezone changed; rescanning logs"
+                );
+                spawn_cost_scan(&cost_store, &store, &tray, &ui_tx, &dbus);
+            }
+        }
+        let projects_changed = cost_store.write().await.set_project_filter(
+            &new_settings.cost.include_projects,
+            &new_settings.cost.exclude_projects,
+        );
+        if projects_changed && !state.cost_loops.is_empty() {
+            tracing::info!("Cost project filter changed; rescanning logs");
+            spawn_cost_scan(&cost_store, &store, &tray, &ui_tx, &dbus);
+        }
+        let monthly_budget_changed = {
+            let mut cost_store = cost_store.write().await;
+            if *cost_store.budgets() != new_settings.cost.budgets {
+                cost_store.set_budgets(new_settings.cost.budgets.clone());
+            }
+            let changed = cost_store.monthly_budget() != new_settings.cost.monthly_budget;
+            if changed {
+                cost_store.set_monthly_budget(new_settings.cost.monthly_budget);
+            }
+            if cost_store.include_claude_desktop() != new_settings.cost.include_claude_desktop {
+                cost_store.set_include_claude_desktop(new_settings.cost.include_claude_desktop);
+            }
+            if cost_store.scan_days() != new_settings.cost.scan_days {
+                cost_store.set_scan_days(new_settings.cost.scan_days);
+            }
+            changed
+        };
+        if monthly_budget_changed && !state.cost_loops.is_empty() {
+            tracing::info!("Monthly budget changed; rescanning logs");
+            spawn_cost_scan(&cost_store, &store, &tray, &ui_tx, &dbus);
+        }
+        if new_settings.cost.enabled && state.cost_loops.is_empty() {
+            tracing::info!("Cost scanning enabled; starting cost loops");
+            state.cost_loops = start_cost_loops(
+                &cost_store,
+                &store,
+                &tray,
+                &ui_tx,
+                &dbus,
+                state.pricing,
+                &state.shutdown,
+            );
+        } else if !new_settings.cost.enabled && !state.cost_loops.is_empty() {
+            tracing::info!("Cost scanning disabled; stopping cost loops");
+            for handle in state.cost_loops.drain(..) {
+                handle.abort();
+            }
+            cost_store.write().await.cancel_active_scan();
+            tray.set_budget_percent(None).await;
+            let _ = ui_tx.send(UiCommand::UpdateMonthlySpend(None));
+        }
+        if new_settings.shortcuts != state.shortcuts {
+            state.shortcuts = new_settings.shortcuts.clone();
+            if let Some(shortcut) = &state.global_shortcut {
+                tracing::info!(
+                    shortcut = %state.shortcuts.popup,
+                    "Shortcut changed; registering it"
+                );
+                report_shortcut(shortcut.apply(&state.shortcuts), &ui_tx);
+            }
+        }
+        // The icons are laid out when created, so merging needs new ones,
+        // and hosts may not pick up a switch between pixmaps and names.
+        let icons_rebuilt = state.ui
+            && (tray.is_merged_mode().await != new_settings.providers.merge_icons
+                || tray.icon_mode().await != new_settings.display.icon_mode);
+        if icons_rebuilt {
+            tracing::info!(
+                merge_icons = new_settings.providers.merge_icons,
+                icon_mode = ?new_settings.display.icon_mode,
+                "Tray icon layout changed; rebuilding tray icons"
+            );
+            if let Err(e) = tray.apply_settings(&new_settings).await {
+                tracing::warn!(error = %e, "Failed to rebuild tray icons");
+            }
+        }
+        tray.set_theme_mode(new_settings.theme.mode.clone()).await;
+        tray.set_color_scheme(ColorScheme::new(new_settings.display.colorblind_mode))
+            .await;
+        tray.set_animation_mode(new_settings.display.animations)
+            .await;
+        let windows_changed = tray.tray_windows().await != new_settings.display.tray_windows;
+        tray.set_display(
+            new_settings.display.tray_title,
+            new_settings.display.tray_windows,
+            new_settings.display.tooltip_template.clone(),
+        )
+        .await;
+        tray.set_icon_cycle(new_settings.display.icon_cycle_secs)
+            .await;
+        if windows_changed || icons_rebuilt {
+            for provider in new_settings.enabled_providers() {
+                if let Some(snapshot) = store.get_snapshot(provider).await {
+                    let usage = tray_usage(&snapshot, new_settings.display.tray_windows);
+                    tray.update_icon(provider, usage).await;
+                    if icons_rebuilt {
+                        tray.set_credentials_valid(provider, true).await;
+                    }
+                }
+                if icons_rebuilt && store.get_incident(provider).await.is_some() {
+                    tray.set_incident(provider, true).await;
+                }
+            }
+        }
+        let _ = ui_tx.send(UiCommand::ApplySettings {
+            show_as_remaining: remembered_show_as_remaining(
+                &popup_memory,
+                new_settings.display.show_as_remaining,
+            ),
+            show_value_estimate: new_settings.display.show_value_estimate,
+            reset_time_format: new_settings.display.reset_time_format,
+            hide_costs: new_settings.display.hide_costs,
+            cost_enabled: new_settings.cost.enabled,
+            cost_timezone: new_settings.cost.timezone,
+            sort_by_urgency: new_settings.display.sort_providers_by_urgency,
+            animations: new_settings.display.animations,
+            color_scheme: ColorScheme::new(new_settings.display.colorblind_mode),
+            theme_mode: new_settings.theme.mode.clone(),
+            popup: new_settings.popup.clone(),
+            terminal: new_settings.terminal.clone(),
+        });
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn handle_dbus_commands(
     mut cmd_rx: mpsc::UnboundedReceiver<DbusCommand>,
     registry: Arc<ProviderRegistry>,
     store: Arc<UsageStore>,
     cost_store: Arc<RwLock<CostStore>>,
     tray: Arc<TrayManager>,
+    governor: Arc<RefreshGovernor>,
     ui_tx: mpsc::UnboundedSender<UiCommand>,
+    dbus: zbus::Connection,
+    pricing: PricingRefresh,
+    shutdown: Shutdown,
 ) {
     while let Some(cmd) = cmd_rx.recv().await {
         match cmd {
             DbusCommand::Refresh => {
                 tracing::info!("D-Bus refresh command received");
                 for provider in registry.enabled_provider_ids() {
-                    tray.set_loading(provider).await;
-                    refresh_provider(&registry, &store, &tray, &ui_tx, provider).await;
+                    request_refresh(
+                        RefreshTrigger::Dbus,
+                        provider,
+                        &governor,
+                        &registry,
+                        &store,
+                        &tray,
+                        &ui_tx,
+                    )
+                    .await;
                 }
             }
+            DbusCommand::RefreshPricing if pricing == PricingRefresh::Disabled => {
+                tracing::info!("D-Bus refresh pricing command ignored; pricing refresh is off");
+            }
             DbusCommand::RefreshPricing => {
                 tracing::info!("D-Bus refresh pricing command received");
                 let refresh_result = {
@@ -152,7 +617,7 @@ async fn handle_dbus_commands(
 
                 match refresh_result {
                     Ok(PricingRefreshResult::Refreshed) => {
-                        scan_and_update_costs(&cost_store, &store, &ui_tx).await;
+                        scan_and_update_costs(&cost_store, &store, &tray, &ui_tx, &dbus).await;
                     }
                     Ok(PricingRefreshResult::Skipped) => {}
                     Ok(PricingRefreshResult::Failed) => {}
@@ -161,6 +626,10 @@ async fn handle_dbus_commands(
                     }
                 }
             }
+            DbusCommand::Quit => {
+                tracing::info!("D-Bus quit command received");
+                shutdown.trigger();
+            }
         }
     }
 }
@@ -170,45 +639,100 @@ enum UiCommand {
     ShowPopup {
         provider: Provider,
         snapshot: Option<Box<UsageSnapshot>>,
-        cost: Option<Box<CostSnapshot>>,
-        tokens: Option<Box<CostUsageTokenSnapshot>>,
+        cost: Option<Arc<CostSnapshot>>,
+        tokens: Option<Arc<CostUsageTokenSnapshot>>,
         error: Option<(String, String)>,
+        error_history: Vec<ErrorEvent>,
+        incident: Option<Incident>,
+        health: ProviderHealth,
+        velocity: Option<Velocity>,
+        click: Option<(i32, i32)>,
     },
     ShowProviderMenu {
         providers: Vec<Provider>,
+        click: Option<(i32, i32)>,
     },
     UpdateUsage {
         provider: Provider,
         snapshot: Box<UsageSnapshot>,
+        velocity: Option<Velocity>,
+    },
+    /// A fetch returned the usage the popup already shows, as of
+    /// `updated_at`.
+    UsageConfirmed {
+        provider: Provider,
+        updated_at: chrono::DateTime<chrono::Utc>,
+        received_at: Option<Instant>,
+        velocity: Option<Velocity>,
     },
     UpdateCost {
         provider: Provider,
-        cost: Box<CostSnapshot>,
+        cost: Arc<CostSnapshot>,
     },
     UpdateTokens {
         provider: Provider,
-        tokens: Box<CostUsageTokenSnapshot>,
+        tokens: Arc<CostUsageTokenSnapshot>,
+    },
+    UpdateFetchLatency {
+        provider: Provider,
+        latency: FetchLatency,
+    },
+    LoginProgress {
+        provider: Provider,
+        progress: LoginProgress,
+    },
+    /// A supervised task crashed too often and was left stopped.
+    TaskFailed {
+        task: &'static str,
     },
     ApplySettings {
         show_as_remaining: bool,
+        show_value_estimate: bool,
+        reset_time_format: ResetTimeFormat,
+        hide_costs: bool,
+        cost_enabled: bool,
+        cost_timezone: CostTimezone,
+        sort_by_urgency: bool,
+        animations: AnimationMode,
+        color_scheme: ColorScheme,
         theme_mode: crate::core::settings::ThemeMode,
         popup: crate::core::settings::PopupSettings,
+        terminal: crate::core::settings::TerminalSettings,
+    },
+    PlayAlertSound(AlertSound),
+    UpdateMonthlySpend(Option<MonthlySpend>),
+    UpdateLockouts {
+        provider: Provider,
+        lockouts: Vec<Lockout>,
+    },
+    /// Why the global shortcut isn't registered; `None` once it is.
+    ShortcutError {
+        error: Option<String>,
     },
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_gtk_main_loop(
     mut ui_rx: mpsc::UnboundedReceiver<UiCommand>,
+    ui_tx: mpsc::UnboundedSender<UiCommand>,
     theme_mode: crate::core::settings::ThemeMode,
     show_as_remaining: bool,
+    show_value_estimate: bool,
+    reset_time_format: ResetTimeFormat,
+    hide_costs: bool,
+    cost_enabled: bool,
+    cost_timezone: CostTimezone,
+    sort_by_urgency: bool,
+    animations: AnimationMode,
+    color_scheme: ColorScheme,
     popup_settings: crate::core::settings::PopupSettings,
+    terminal_settings: crate::core::settings::TerminalSettings,
+    provider_tabs: Vec<(Provider, String)>,
     tray_manager: Arc<TrayManager>,
+    popup_memory: SharedPopupMemory,
+    shutdown: Shutdown,
 ) -> Result<()> {
-    // libadwaita manages its own Adwaita-based theming; custom GTK themes
-    // (via GTK_THEME or ~/.config/gtk-4.0/gtk.css) are unsupported and cause
-    // warnings about missing GResource bundles.
-    std::env::remove_var("GTK_THEME");
-
-    gtk4::init().expect("Failed to initialize GTK4");
+    // GTK itself was initialized by `init_ui`.
     adw::init().expect("Failed to initialize libadwaita");
 
     // The system gsettings color-scheme sets the deprecated GTK3-era
@@ -218,7 +742,9 @@ async fn run_gtk_main_loop(
         settings.set_gtk_application_prefer_dark_theme(false);
     }
 
-    let app = adw::Application::builder().application_id(APP_ID).build();
+    let app = adw::Application::builder()
+        .application_id(instance::scoped(APP_ID, '.'))
+        .build();
     let popup_holder: Rc<RefCell<Option<PopupWindow>>> = Rc::new(RefCell::new(None));
 
     let popup_holder_activate = popup_holder.clone();
@@ -228,7 +754,46 @@ async fn run_gtk_main_loop(
         tracing::info!("GTK application activated");
         let popup = PopupWindow::new(app, theme_mode.clone(), &popup_settings);
         popup.set_show_as_remaining(show_as_remaining);
+        popup.set_show_value_estimate(show_value_estimate);
+        popup.set_reset_time_format(reset_time_format);
+        popup.set_hide_costs(hide_costs);
+        popup.set_cost_enabled(cost_enabled);
+        popup.set_cost_timezone(cost_timezone);
+        popup.set_sort_by_urgency(sort_by_urgency);
+        popup.set_animation_mode(animations);
+        popup.set_color_scheme(color_scheme);
+        popup.set_terminal(&terminal_settings);
+        popup.set_providers(provider_tabs.clone());
+        let login_tx = ui_tx.clone();
+        popup.set_login_sink(Arc::new(
+            move |provider: Provider, progress: LoginProgress| {
+                let _ = login_tx.send(UiCommand::LoginProgress { provider, progress });
+            },
+        ));
+        let popup_memory = Arc::clone(&popup_memory);
+        popup.set_view_sink(Arc::new(move |change: ViewChange| {
+            let Ok(mut memory) = popup_memory.lock() else {
+                return;
+            };
+            match change {
+                ViewChange::Provider(provider) => memory.choose_provider(provider),
+                ViewChange::ShowAsRemaining(show) => memory.toggle_show_as_remaining(show),
+            }
+        }));
         *popup_holder_activate.borrow_mut() = Some(popup);
+        let tray_manager_animations = Arc::clone(&tray_manager_theme);
+        let send_system_animations = move |enabled: bool| {
+            let tray_manager = Arc::clone(&tray_manager_animations);
+            tokio::spawn(async move {
+                tray_manager.set_system_animations(enabled).await;
+            });
+        };
+        send_system_animations(system_animations_enabled());
+        if let Some(gtk_settings) = gtk4::Settings::default() {
+            gtk_settings.connect_gtk_enable_animations_notify(move |settings| {
+                send_system_animations(settings.is_gtk_enable_animations());
+            });
+        }
         if matches!(theme_mode, crate::core::settings::ThemeMode::System) {
             let is_dark = adw::StyleManager::default().is_dark();
             let tray_manager = Arc::clone(&tray_manager_theme);
@@ -247,7 +812,7 @@ async fn run_gtk_main_loop(
     let pending_ui_writer = Arc::clone(&pending_ui);
     let wake_context = main_context.clone();
 
-    tokio::spawn(async move {
+    shutdown.spawn(async move {
         while let Some(cmd) = ui_rx.recv().await {
             let Ok(mut queue) = pending_ui_writer.lock() else {
                 break;
@@ -256,6 +821,12 @@ async fn run_gtk_main_loop(
             wake_context.wakeup();
         }
     });
+    let wake_on_shutdown = main_context.clone();
+    let shutdown_waker = shutdown.clone();
+    tokio::spawn(async move {
+        shutdown_waker.triggered().await;
+        wake_on_shutdown.wakeup();
+    });
 
     let mut telemetry_start = Instant::now();
     let mut telemetry_iterations: u64 = 0;
@@ -263,11 +834,16 @@ async fn run_gtk_main_loop(
     let mut telemetry_max_queue_depth: usize = 0;
     let mut telemetry_max_batch: usize = 0;
     let mut telemetry_short_idle_wakes: u64 = 0;
+    // Commands sent before `connect_activate` built the popup.
+    let mut before_activation: Vec<UiCommand> = Vec::new();
 
     loop {
         let iteration_start = Instant::now();
         main_context.iteration(true);
         telemetry_iterations = telemetry_iterations.saturating_add(1);
+        if shutdown.is_triggered() {
+            break;
+        }
 
         let mut drained = Vec::new();
         let mut current_queue_depth = 0usize;
@@ -284,9 +860,22 @@ async fn run_gtk_main_loop(
         }
 
         if let Some(popup) = popup_holder.borrow().as_ref() {
+            if !before_activation.is_empty() {
+                let replay = collapse_updates(std::mem::take(&mut before_activation));
+                tracing::debug!(
+                    count = replay.len(),
+                    "Replaying UI commands received before activation"
+                );
+                for cmd in replay {
+                    handle_ui_command(popup, cmd);
+                }
+            }
             for cmd in drained {
                 handle_ui_command(popup, cmd);
             }
+        } else if !drained.is_empty() {
+            before_activation.extend(drained);
+            before_activation = collapse_updates(before_activation);
         }
 
         let elapsed = telemetry_start.elapsed();
@@ -312,6 +901,12 @@ async fn run_gtk_main_loop(
             telemetry_short_idle_wakes = 0;
         }
     }
+
+    if let Some(popup) = popup_holder.borrow_mut().take() {
+        popup.hide();
+    }
+    app.quit();
+    Ok(())
 }
 
 fn handle_ui_command(popup: &PopupWindow, cmd: UiCommand) {
@@ -322,7 +917,17 @@ fn handle_ui_command(popup: &PopupWindow, cmd: UiCommand) {
             cost,
             tokens,
             error,
+            error_history,
+            incident,
+            health,
+            velocity,
+            click,
         } => {
+            popup.set_click_position(click);
+            popup.set_error_history(provider, error_history);
+            popup.set_incident(provider, incident);
+            popup.set_health(provider, health);
+            popup.set_velocity(provider, velocity);
             if let Some((error_msg, hint)) = error {
                 popup.show_error(provider, &error_msg, &hint);
             } else {
@@ -330,81 +935,175 @@ fn handle_ui_command(popup: &PopupWindow, cmd: UiCommand) {
                     popup.update_usage(provider, &snap);
                 }
                 if let Some(c) = cost {
-                    popup.update_cost(provider, &c);
+                    popup.update_cost(provider, c);
                 }
                 if let Some(t) = tokens {
-                    popup.update_tokens(provider, &t);
+                    popup.update_tokens(provider, t);
                 }
             }
             popup.show(provider);
         }
-        UiCommand::ShowProviderMenu { providers } => {
+        UiCommand::ShowProviderMenu { providers, click } => {
+            popup.set_click_position(click);
             popup.show_provider_menu(&providers);
         }
-        UiCommand::UpdateUsage { provider, snapshot } => {
+        UiCommand::UpdateUsage {
+            provider,
+            snapshot,
+            velocity,
+        } => {
+            popup.set_velocity(provider, velocity);
             popup.update_usage(provider, &snapshot);
         }
+        UiCommand::UsageConfirmed {
+            provider,
+            updated_at,
+            received_at,
+            velocity,
+        } => {
+            popup.set_velocity(provider, velocity);
+            popup.confirm_usage(provider, updated_at, received_at);
+        }
         UiCommand::UpdateCost { provider, cost } => {
-            popup.update_cost(provider, &cost);
+            popup.update_cost(provider, cost);
         }
         UiCommand::UpdateTokens { provider, tokens } => {
-            popup.update_tokens(provider, &tokens);
+            popup.update_tokens(provider, tokens);
+        }
+        UiCommand::UpdateFetchLatency { provider, latency } => {
+            popup.update_fetch_latency(provider, latency);
+        }
+        UiCommand::LoginProgress { provider, progress } => {
+            popup.update_login(provider, progress);
+        }
+        UiCommand::PlayAlertSound(sound) => {
+            play_alert_sound(&sound);
+        }
+        UiCommand::UpdateMonthlySpend(spend) => {
+            popup.update_monthly_spend(spend);
+        }
+        UiCommand::UpdateLockouts { provider, lockouts } => {
+            popup.update_lockouts(provider, lockouts);
+        }
+        UiCommand::TaskFailed { task } => {
+            popup.add_failed_task(task);
+        }
+        UiCommand::ShortcutError { error } => {
+            popup.set_shortcut_error(error.as_deref());
         }
         UiCommand::ApplySettings {
             show_as_remaining,
+            show_value_estimate,
+            reset_time_format,
+            hide_costs,
+            cost_enabled,
+            cost_timezone,
+            sort_by_urgency,
+            animations,
+            color_scheme,
             theme_mode,
             popup: popup_settings,
+            terminal,
         } => {
             popup.set_show_as_remaining(show_as_remaining);
+            popup.set_show_value_estimate(show_value_estimate);
+            popup.set_reset_time_format(reset_time_format);
+            popup.set_hide_costs(hide_costs);
+            popup.set_cost_enabled(cost_enabled);
+            popup.set_cost_timezone(cost_timezone);
+            popup.set_sort_by_urgency(sort_by_urgency);
+            popup.set_animation_mode(animations);
+            popup.set_color_scheme(color_scheme);
             popup.set_theme_mode(theme_mode);
             popup.apply_popup_settings(&popup_settings);
+            popup.set_terminal(&terminal);
+        }
+    }
+}
+
+/// Drops every `Update*` command superseded by a later one of the same kind
+/// for the same provider. Everything else keeps its order.
+fn collapse_updates(commands: Vec<UiCommand>) -> Vec<UiCommand> {
+    fn update_key(cmd: &UiCommand) -> Option<(&'static str, Provider)> {
+        match cmd {
+            UiCommand::UpdateUsage { provider, .. } => Some(("usage", *provider)),
+            UiCommand::UsageConfirmed { provider, .. } => Some(("confirmed", *provider)),
+            UiCommand::UpdateCost { provider, .. } => Some(("cost", *provider)),
+            UiCommand::UpdateTokens { provider, .. } => Some(("tokens", *provider)),
+            UiCommand::UpdateFetchLatency { provider, .. } => Some(("latency", *provider)),
+            _ => None,
         }
     }
+
+    let mut seen = HashSet::new();
+    let mut kept: Vec<UiCommand> = commands
+        .into_iter()
+        .rev()
+        .filter(|cmd| update_key(cmd).is_none_or(|key| seen.insert(key)))
+        .collect();
+    kept.reverse();
+    kept
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_tray_event(
     event: TrayEvent,
     store: &Arc<UsageStore>,
     registry: &Arc<ProviderRegistry>,
     tray: &Arc<TrayManager>,
+    governor: &Arc<RefreshGovernor>,
     ui_tx: &mpsc::UnboundedSender<UiCommand>,
+    popup_memory: &SharedPopupMemory,
+    shutdown: &Shutdown,
 ) {
     match event {
-        TrayEvent::LeftClick(provider) => {
-            tracing::debug!(?provider, "Tray icon clicked");
+        TrayEvent::LeftClick(icon_provider, click) => {
+            tracing::debug!(provider = ?icon_provider, "Tray icon clicked");
 
-            if tray.is_merged_mode().await {
+            let provider = if tray.is_merged_mode().await {
                 let mut providers = registry.enabled_provider_ids();
                 if providers.is_empty() {
                     providers.push(Provider::Claude);
                 }
-                let _ = ui_tx.send(UiCommand::ShowProviderMenu { providers });
-                return;
-            }
-
-            if tray.should_refresh(provider).await {
-                tray.mark_refreshed(provider).await;
-                tray.set_loading(provider).await;
-
-                let registry_clone = Arc::clone(registry);
-                let store_clone = Arc::clone(store);
-                let tray_clone = Arc::clone(tray);
-                let ui_tx_clone = ui_tx.clone();
-                let p = provider;
+                // The merged icon reopens the provider viewed last, and
+                // offers a menu until there is one.
+                let remembered = popup_memory
+                    .lock()
+                    .ok()
+                    .and_then(|memory| memory.provider())
+                    .filter(|provider| providers.contains(provider));
+                let Some(provider) = remembered else {
+                    let _ = ui_tx.send(UiCommand::ShowProviderMenu { providers, click });
+                    return;
+                };
+                provider
+            } else {
+                record_choice(popup_memory, icon_provider);
+                icon_provider
+            };
 
-                tokio::spawn(async move {
-                    refresh_provider(&registry_clone, &store_clone, &tray_clone, &ui_tx_clone, p)
-                        .await;
-                });
-            }
+            request_refresh(
+                RefreshTrigger::TrayClick,
+                provider,
+                governor,
+                registry,
+                store,
+                tray,
+                ui_tx,
+            )
+            .await;
 
             let snapshot = store.get_snapshot(provider).await.map(Box::new);
-            let cost = store.get_cost(provider).await.map(Box::new);
-            let error = store
-                .get_error(provider)
-                .await
-                .map(|e| (e, provider_error_hint(provider).to_string()));
-            let tokens = store.get_token_snapshot(provider).await.map(Box::new);
+            let cost = store.get_cost(provider).await;
+            let error = store.get_error(provider).await.map(|e| {
+                let hint = provider_error_hint(provider, &e);
+                (e, hint)
+            });
+            let tokens = store.get_token_snapshot(provider).await;
+            let error_history = store.get_error_history(provider).await;
+            let incident = store.get_incident(provider).await;
+            let health = store.get_health(provider).await;
+            let velocity = store.get_velocity(provider).await;
 
             let _ = ui_tx.send(UiCommand::ShowPopup {
                 provider,
@@ -412,24 +1111,47 @@ async fn handle_tray_event(
                 cost,
                 tokens,
                 error,
+                error_history,
+                incident,
+                health,
+                velocity,
+                click,
             });
         }
         TrayEvent::RefreshRequested => {
             tracing::info!("Manual refresh requested");
             for provider in registry.enabled_provider_ids() {
-                tray.set_loading(provider).await;
+                request_refresh(
+                    RefreshTrigger::TrayMenu,
+                    provider,
+                    governor,
+                    registry,
+                    store,
+                    tray,
+                    ui_tx,
+                )
+                .await;
             }
-
-            let results = registry.fetch_all().await;
-            for (provider, result) in results {
-                match result {
-                    Ok(snapshot) => {
-                        apply_successful_fetch(provider, snapshot, store, tray, ui_tx).await;
-                    }
-                    Err(e) => {
-                        apply_failed_fetch(provider, &e, store, tray).await;
-                    }
-                }
+        }
+        TrayEvent::MiddleClick(provider) => {
+            // The merged icon stands for every enabled provider.
+            let providers = if tray.is_merged_mode().await {
+                registry.enabled_provider_ids()
+            } else {
+                vec![provider]
+            };
+            for provider in providers {
+                tracing::info!(?provider, "Middle-click refresh requested");
+                request_refresh(
+                    RefreshTrigger::MiddleClick,
+                    provider,
+                    governor,
+                    registry,
+                    store,
+                    tray,
+                    ui_tx,
+                )
+                .await;
             }
         }
         TrayEvent::OpenDashboard(provider) => {
@@ -441,8 +1163,7 @@ async fn handle_tray_event(
         }
         TrayEvent::Quit => {
             tracing::info!("Quit requested");
-            tray.shutdown().await;
-            std::process::exit(0);
+            shutdown.trigger();
         }
     }
 }
@@ -451,16 +1172,19 @@ async fn run_polling_loop(
     registry: Arc<ProviderRegistry>,
     store: Arc<UsageStore>,
     tray: Arc<TrayManager>,
+    governor: Arc<RefreshGovernor>,
     retry_states: Arc<RwLock<HashMap<Provider, RetryState>>>,
     ui_tx: mpsc::UnboundedSender<UiCommand>,
-    mut cred_change_rx: mpsc::UnboundedReceiver<Provider>,
+    cred_change_rx: Arc<tokio::sync::Mutex<mpsc::UnboundedReceiver<Provider>>>,
 ) {
     let providers = registry.enabled_provider_ids();
+    let mut cred_change_rx = cred_change_rx.lock().await;
 
     {
+        // A restart after a crash keeps each provider's backoff.
         let mut states = retry_states.write().await;
         for &provider in &providers {
-            states.insert(provider, RetryState::new());
+            states.entry(provider).or_insert_with(RetryState::new);
         }
     }
 
@@ -469,15 +1193,15 @@ async fn run_polling_loop(
             .await;
     }
 
-    let mut check_interval = tokio::time::interval(Duration::from_secs(5));
     let mut telemetry_start = Instant::now();
     let mut telemetry_ticks: u64 = 0;
     let mut telemetry_refresh_attempts: u64 = 0;
     let mut telemetry_credential_events: u64 = 0;
 
     loop {
+        let deadline = next_poll_deadline(&providers, &store, &retry_states).await;
         tokio::select! {
-            _ = check_interval.tick() => {
+            _ = tokio::time::sleep_until(deadline) => {
                 telemetry_ticks = telemetry_ticks.saturating_add(1);
                 for &provider in &providers {
                     let delay = {
@@ -488,7 +1212,8 @@ async fn run_polling_loop(
                             .unwrap_or_default()
                             .current_delay()
                     };
-                    let should_poll = store.should_refresh(provider, delay).await;
+                    let should_poll = store.should_refresh(provider, delay).await
+                        && !in_reset_blackout(&store, provider).await;
 
                     if should_poll {
                         telemetry_refresh_attempts = telemetry_refresh_attempts.saturating_add(1);
@@ -517,6 +1242,10 @@ async fn run_polling_loop(
                         state.record_success();
                     }
                 }
+                let Some(_slot) = governor.request(provider, RefreshTrigger::CredentialsChanged)
+                else {
+                    continue;
+                };
                 store.clear_last_fetch(provider).await;
                 refresh_provider_with_retry(
                     &registry,
@@ -551,6 +1280,12 @@ async fn run_polling_loop(
                 providers_in_backoff,
                 "Daemon loop telemetry"
             );
+            tracing::debug!(
+                component = "provider-poll-loop",
+                deadline_wakeups = telemetry_ticks,
+                credential_wakeups = telemetry_credential_events,
+                "Wakeup sources"
+            );
             telemetry_start = Instant::now();
             telemetry_ticks = 0;
             telemetry_refresh_attempts = 0;
@@ -559,6 +1294,436 @@ async fn run_polling_loop(
     }
 }
 
+/// Earliest instant any provider is due for a poll, given its last fetch,
+/// current retry delay and upcoming session reset. Refreshes triggered
+/// elsewhere only push deadlines later, so waking at a stale deadline just
+/// recomputes and sleeps again.
+async fn next_poll_deadline(
+    providers: &[Provider],
+    store: &UsageStore,
+    retry_states: &RwLock<HashMap<Provider, RetryState>>,
+) -> tokio::time::Instant {
+    let now = Instant::now();
+    let mut deadline = now + MAX_POLL_SLEEP;
+    for &provider in providers {
+        let delay = retry_states
+            .read()
+            .await
+            .get(&provider)
+            .cloned()
+            .unwrap_or_default()
+            .current_delay();
+        match store.next_refresh_at(provider, delay).await {
+            Some(due) => deadline = deadline.min(hold_for_reset(store, provider, due).await),
+            None => deadline = now,
+        }
+    }
+    tokio::time::Instant::from_std(deadline.max(now))
+}
+
+/// The blackout around `provider`'s next session reset, as its last
+/// snapshot reports it.
+async fn reset_blackout(store: &UsageStore, provider: Provider) -> Option<ResetBlackout> {
+    store
+        .get_snapshot(provider)
+        .await
+        .as_ref()
+        .and_then(ResetBlackout::for_snapshot)
+}
+
+async fn in_reset_blackout(store: &UsageStore, provider: Provider) -> bool {
+    reset_blackout(store, provider)
+        .await
+        .is_some_and(|blackout| blackout.contains(chrono::Utc::now()))
+}
+
+/// `due`, or the end of the session reset blackout when `due` falls in it.
+async fn hold_for_reset(store: &UsageStore, provider: Provider, due: Instant) -> Instant {
+    let Some(blackout) = reset_blackout(store, provider).await else {
+        return due;
+    };
+    let (now, utc_now) = (Instant::now(), chrono::Utc::now());
+    let due_at = utc_now
+        + chrono::Duration::from_std(due.saturating_duration_since(now)).unwrap_or_default();
+    let held = (blackout.defer(due_at) - due_at)
+        .to_std()
+        .unwrap_or_default();
+    if !held.is_zero() {
+        tracing::debug!(
+            ?provider,
+            held_secs = held.as_secs(),
+            "Holding the next poll until after the session reset"
+        );
+    }
+    due.max(now) + held
+}
+
+/// Checks the status page of each provider that fails, and flags a reported
+/// incident on its tray icon and in the popup until it recovers. Healthy
+/// providers never trigger a check.
+async fn run_incident_loop(
+    store: Arc<UsageStore>,
+    tray: Arc<TrayManager>,
+    mut settings_rx: broadcast::Receiver<Settings>,
+    mut enabled: bool,
+) {
+    let mut updates = store.subscribe();
+    let mut tracker = IncidentTracker::new();
+    let mut flagged = HashSet::new();
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(StoreUpdate::ErrorOccurred(provider, _)) if enabled => {
+                    let incident = tracker.check(provider).await;
+                    if incident.is_some() {
+                        flagged.insert(provider);
+                    } else {
+                        flagged.remove(&provider);
+                    }
+                    tray.set_incident(provider, incident.is_some()).await;
+                    store.set_incident(provider, incident).await;
+                }
+                Ok(StoreUpdate::ErrorCleared(provider)) => {
+                    if flagged.remove(&provider) {
+                        tray.set_incident(provider, false).await;
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Ok(settings) = settings_rx.recv() => {
+                enabled = settings.notifications.incident_check;
+                if !enabled {
+                    for provider in flagged.drain() {
+                        tray.set_incident(provider, false).await;
+                        store.set_incident(provider, None).await;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Notifies when a provider's usage reaches `notifications.threshold`.
+async fn run_usage_alert_loop(
+    store: Arc<UsageStore>,
+    mut settings_rx: broadcast::Receiver<Settings>,
+    mut notifications: NotificationSettings,
+    ui_tx: mpsc::UnboundedSender<UiCommand>,
+) {
+    let mut updates = store.subscribe();
+    let mut alerts = UsageAlerts::default();
+    let mut snoozes = AlertSnoozes::load();
+    let (snooze_tx, mut snooze_rx) = mpsc::unbounded_channel();
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(StoreUpdate::UsageUpdated(provider)) => {
+                    if let Some(snapshot) = store.get_snapshot(provider).await {
+                        let crossed = alerts.check(
+                            provider,
+                            &snapshot,
+                            notifications.threshold,
+                            &snoozes,
+                            chrono::Utc::now(),
+                        );
+                        if let Some(alert) = crossed.filter(|_| notifications.enabled) {
+                            send_usage_alert(
+                                provider,
+                                alert,
+                                &notifications.sound,
+                                &ui_tx,
+                                &snooze_tx,
+                            );
+                        }
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Some((provider, window, until)) = snooze_rx.recv() => {
+                tracing::info!(?provider, ?window, %until, "Usage notification snoozed");
+                snoozes.snooze(provider, window, until, chrono::Utc::now());
+                if let Err(e) = snoozes.save() {
+                    tracing::warn!(error = %e, "Failed to save alert snoozes");
+                }
+            }
+            Ok(settings) = settings_rx.recv() => {
+                notifications = settings.notifications;
+            }
+        }
+    }
+}
+
+/// Records the windows that run out (see `LockoutLog::record`) and keeps
+/// the popup's lockout counts current.
+async fn run_lockout_loop(store: Arc<UsageStore>, ui_tx: mpsc::UnboundedSender<UiCommand>) {
+    let mut updates = store.subscribe();
+    let mut log = LockoutLog::load();
+    for (provider, lockouts) in log.providers() {
+        let _ = ui_tx.send(UiCommand::UpdateLockouts {
+            provider,
+            lockouts: lockouts.to_vec(),
+        });
+    }
+
+    loop {
+        match updates.recv().await {
+            Ok(StoreUpdate::UsageUpdated(provider)) => {
+                let Some(snapshot) = store.get_snapshot(provider).await else {
+                    continue;
+                };
+                let recorded = log.record(provider, &snapshot, chrono::Utc::now());
+                if recorded.is_empty() {
+                    continue;
+                }
+                for lockout in &recorded {
+                    tracing::info!(
+                        ?provider,
+                        window = lockout.window.label(),
+                        "Usage locked out"
+                    );
+                }
+                if let Err(e) = log.save() {
+                    tracing::warn!(error = %e, "Failed to save lockouts");
+                }
+                let _ = ui_tx.send(UiCommand::UpdateLockouts {
+                    provider,
+                    lockouts: log.for_provider(provider).to_vec(),
+                });
+            }
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
+}
+
+/// Recaps each weekly window when it rolls over (see
+/// `WeeklySummaryLog::observe`), notifying when `notifications.enabled` and
+/// `notifications.weekly_summary` are both on.
+async fn run_weekly_summary_loop(
+    store: Arc<UsageStore>,
+    mut settings_rx: broadcast::Receiver<Settings>,
+    mut settings: Settings,
+) {
+    let mut updates = store.subscribe();
+    let mut log = WeeklySummaryLog::load();
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(StoreUpdate::UsageUpdated(provider)) => {
+                    let Some(weekly) = store
+                        .get_snapshot(provider)
+                        .await
+                        .and_then(|snapshot| snapshot.secondary)
+                    else {
+                        continue;
+                    };
+                    let summary = match log.observe(provider, &weekly) {
+                        WeekUpdate::Unchanged => continue,
+                        WeekUpdate::Progressed => None,
+                        WeekUpdate::Ended(week) => {
+                            let tokens = store.get_token_snapshot(provider).await;
+                            let daily = tokens
+                                .as_ref()
+                                .map_or(&[][..], |tokens| tokens.daily.as_slice());
+                            let timezone = settings.cost.timezone;
+                            let summary = summarize_week(&week, daily, |at| day_of(at, timezone));
+                            tracing::info!(
+                                ?provider,
+                                "Weekly window rolled over. {}",
+                                summary.message()
+                            );
+                            log.push(provider, summary.clone());
+                            Some(summary)
+                        }
+                    };
+                    if let Err(e) = log.save() {
+                        tracing::warn!(error = %e, "Failed to save weekly summaries");
+                    }
+
+                    let notify =
+                        settings.notifications.enabled && settings.notifications.weekly_summary;
+                    if let Some(summary) =
+                        summary.filter(|summary| notify && summary.is_recent(chrono::Utc::now()))
+                    {
+                        tokio::task::spawn_blocking(move || {
+                            if let Err(e) = send_weekly_summary_notification(provider, &summary) {
+                                tracing::warn!(
+                                    error = %e,
+                                    "Failed to send weekly summary notification"
+                                );
+                            }
+                        });
+                    }
+                }
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Ok(new_settings) = settings_rx.recv() => {
+                settings = new_settings;
+            }
+        }
+    }
+}
+
+/// Sends the usage notification and queues its sound on the GTK main
+/// context. Neither blocks the caller; a snooze picked from the
+/// notification comes back on `snooze_tx`.
+fn send_usage_alert(
+    provider: Provider,
+    alert: UsageAlert,
+    sound: &AlertSound,
+    ui_tx: &mpsc::UnboundedSender<UiCommand>,
+    snooze_tx: &mpsc::UnboundedSender<(Provider, WindowSlot, chrono::DateTime<chrono::Utc>)>,
+) {
+    let snooze_tx = snooze_tx.clone();
+    tokio::task::spawn_blocking(move || {
+        let with_actions = notification_actions_supported();
+        match send_high_usage_notification(provider, &alert, with_actions) {
+            Ok(Some(snooze)) => {
+                if let Some(until) = snooze.until(&alert, chrono::Utc::now()) {
+                    let _ = snooze_tx.send((provider, alert.window, until));
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(?provider, error = %e, "Failed to send usage notification");
+            }
+        }
+    });
+    if sound.is_enabled() {
+        let _ = ui_tx.send(UiCommand::PlayAlertSound(sound.clone()));
+    }
+}
+
+/// Rewrites the limits file after every usage update or failure (which drops
+/// the provider's data) while `limits_file.enabled` is set, and right away
+/// when that setting changes.
+async fn run_limits_file_loop(
+    store: Arc<UsageStore>,
+    mut settings_rx: broadcast::Receiver<Settings>,
+    mut settings: Settings,
+) {
+    let mut updates = store.subscribe();
+    update_limits_file(&store, &settings).await;
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(StoreUpdate::UsageUpdated(_) | StoreUpdate::ErrorOccurred(..))
+                | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    update_limits_file(&store, &settings).await;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Ok(new_settings) = settings_rx.recv() => {
+                let changed = new_settings.limits_file != settings.limits_file
+                    || new_settings.enabled_providers() != settings.enabled_providers();
+                settings = new_settings;
+                if changed {
+                    update_limits_file(&store, &settings).await;
+                }
+            }
+        }
+    }
+}
+
+async fn update_limits_file(store: &UsageStore, settings: &Settings) {
+    if !settings.limits_file.enabled {
+        return;
+    }
+    let Some(path) = limits_file_path(&settings.limits_file) else {
+        tracing::warn!("Could not determine limits file path");
+        return;
+    };
+
+    let enabled = settings.enabled_providers();
+    let providers = store
+        .all_providers_with_snapshots()
+        .await
+        .into_iter()
+        .filter(|(provider, _)| enabled.contains(provider))
+        .map(|(provider, snapshot)| {
+            let limits =
+                ProviderLimits::from_snapshot(settings.provider_label(provider), &snapshot);
+            (provider.id(), limits)
+        })
+        .collect();
+    let limits = LimitsFile {
+        schema_version: SCHEMA_VERSION,
+        version: SCHEMA_VERSION,
+        updated_at: chrono::Utc::now(),
+        providers,
+    };
+    if let Err(e) = write_limits_file(&path, &limits) {
+        tracing::warn!(error = %e, path = %path.display(), "Failed to write limits file");
+    }
+}
+
+/// Spawns the pricing refresh and cost scan loops. The returned handles are
+/// aborted when `cost.enabled` is switched off.
+fn start_cost_loops(
+    cost_store: &Arc<RwLock<CostStore>>,
+    store: &Arc<UsageStore>,
+    tray: &Arc<TrayManager>,
+    ui_tx: &mpsc::UnboundedSender<UiCommand>,
+    dbus: &zbus::Connection,
+    pricing: PricingRefresh,
+    shutdown: &Shutdown,
+) -> Vec<tokio::task::JoinHandle<()>> {
+    let pricing_store = Arc::clone(cost_store);
+    let scan_args = (
+        Arc::clone(cost_store),
+        Arc::clone(store),
+        Arc::clone(tray),
+        ui_tx.clone(),
+        dbus.clone(),
+    );
+    let mut loops = vec![supervised("cost scan", ui_tx, shutdown, move || {
+        let (cost_store, store, tray, ui_tx, dbus) = scan_args.clone();
+        run_cost_scan_loop(cost_store, store, tray, ui_tx, dbus)
+    })];
+    if pricing == PricingRefresh::Fetch {
+        loops.push(supervised("pricing refresh", ui_tx, shutdown, move || {
+            run_pricing_refresh_loop(Arc::clone(&pricing_store))
+        }));
+    }
+    loops
+}
+
+/// Spawns a long-lived loop that restarts after a panic and stops at
+/// shutdown. If it keeps crashing, the popup shows it as stopped until the
+/// daemon restarts.
+fn supervised<F, Fut>(
+    name: &'static str,
+    ui_tx: &mpsc::UnboundedSender<UiCommand>,
+    shutdown: &Shutdown,
+    mut make_task: F,
+) -> tokio::task::JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: std::future::Future<Output = ()> + Send + 'static,
+{
+    let ui_tx = ui_tx.clone();
+    let shutdown = shutdown.clone();
+    supervisor::supervise(
+        name,
+        move || {
+            let _ = ui_tx.send(UiCommand::TaskFailed { task: name });
+        },
+        move || {
+            let task = make_task();
+            let shutdown = shutdown.clone();
+            async move { shutdown.run_until(task).await }
+        },
+    )
+}
+
 async fn run_pricing_refresh_loop(cost_store: Arc<RwLock<CostStore>>) {
     loop {
         let refresh_result = {
@@ -585,47 +1750,105 @@ async fn run_pricing_refresh_loop(cost_store: Arc<RwLock<CostStore>>) {
     }
 }
 
+/// Logs how much the in-memory stores hold every hour, at debug level, so a
+/// daemon that has been up for days can show whether any of it grows.
+async fn run_memory_report_loop(store: Arc<UsageStore>, cost_store: Arc<RwLock<CostStore>>) {
+    let mut interval = tokio::time::interval(MEMORY_REPORT_INTERVAL);
+    // The first tick completes at once, before anything has been fetched.
+    interval.tick().await;
+
+    loop {
+        interval.tick().await;
+        let sizes = store.sizes().await;
+        let cost_sizes = cost_store.read().await.sizes();
+        tracing::debug!(
+            component = "memory",
+            providers = sizes.providers,
+            error_events = sizes.error_events,
+            latency_samples = sizes.latency_samples,
+            velocity_samples = sizes.velocity_samples,
+            rollover_trackers = sizes.rollover_trackers,
+            cost_days = sizes.cost_days,
+            token_days = sizes.token_days,
+            queued_updates = sizes.queued_updates,
+            cached_cost_days = cost_sizes.cost_days,
+            cached_token_days = cost_sizes.token_days,
+            cached_projects = cost_sizes.projects,
+            "Store sizes"
+        );
+    }
+}
+
 async fn run_cost_scan_loop(
     cost_store: Arc<RwLock<CostStore>>,
     store: Arc<UsageStore>,
+    tray: Arc<TrayManager>,
     ui_tx: mpsc::UnboundedSender<UiCommand>,
+    dbus: zbus::Connection,
 ) {
     let mut interval = tokio::time::interval(Duration::from_secs(300));
 
     interval.tick().await;
-    scan_and_update_costs(&cost_store, &store, &ui_tx).await;
+    scan_and_update_costs(&cost_store, &store, &tray, &ui_tx, &dbus).await;
 
     loop {
         interval.tick().await;
-        scan_and_update_costs(&cost_store, &store, &ui_tx).await;
+        scan_and_update_costs(&cost_store, &store, &tray, &ui_tx, &dbus).await;
     }
 }
 
+/// Rescans the logs in the background after a cost setting changed.
+fn spawn_cost_scan(
+    cost_store: &Arc<RwLock<CostStore>>,
+    store: &Arc<UsageStore>,
+    tray: &Arc<TrayManager>,
+    ui_tx: &mpsc::UnboundedSender<UiCommand>,
+    dbus: &zbus::Connection,
+) {
+    let cost_store = Arc::clone(cost_store);
+    let store = Arc::clone(store);
+    let tray = Arc::clone(tray);
+    let ui_tx = ui_tx.clone();
+    let dbus = dbus.clone();
+    tokio::spawn(async move {
+        scan_and_update_costs(&cost_store, &store, &tray, &ui_tx, &dbus).await;
+    });
+}
+
 async fn scan_and_update_costs(
     cost_store: &Arc<RwLock<CostStore>>,
     store: &Arc<UsageStore>,
+    tray: &TrayManager,
     ui_tx: &mpsc::UnboundedSender<UiCommand>,
+    dbus: &zbus::Connection,
 ) {
     let scan_start = Instant::now();
-    let costs = {
-        let mut cost_store = cost_store.write().await;
-        cost_store.scan_all()
+    let session_windows = current_session_windows(store).await;
+    let Some(costs) = scan_in_background(cost_store, session_windows).await else {
+        tracing::debug!("Cost scan superseded or cancelled");
+        return;
     };
 
     let provider_count = costs.len();
+    let snapshots: Vec<Arc<CostSnapshot>> = costs
+        .values()
+        .map(|result| Arc::clone(&result.cost))
+        .collect();
+    check_budgets(cost_store, tray, ui_tx, &snapshots).await;
     for (provider, result) in costs {
-        store.update_cost(provider, result.cost.clone()).await;
+        store.update_cost(provider, Arc::clone(&result.cost)).await;
         store
-            .update_token_snapshot(provider, result.tokens.clone())
+            .update_token_snapshot(provider, Arc::clone(&result.tokens))
             .await;
         let _ = ui_tx.send(UiCommand::UpdateCost {
             provider,
-            cost: Box::new(result.cost),
+            cost: result.cost,
         });
         let _ = ui_tx.send(UiCommand::UpdateTokens {
             provider,
-            tokens: Box::new(result.tokens),
+            tokens: result.tokens,
         });
+        emit_cost_updated(dbus, provider).await;
     }
 
     tracing::info!(
@@ -636,6 +1859,92 @@ async fn scan_and_update_costs(
     );
 }
 
+/// Rolls Claude and Codex spend up against `cost.monthly_budget` for the
+/// tray tooltip and popup, and notifies for it and for projects crossing a
+/// budget threshold for the first time this month. Warns about budgets that
+/// match no scanned project.
+async fn check_budgets(
+    cost_store: &Arc<RwLock<CostStore>>,
+    tray: &TrayManager,
+    ui_tx: &mpsc::UnboundedSender<UiCommand>,
+    costs: &[Arc<CostSnapshot>],
+) {
+    let (budgets, monthly_budget, timezone) = {
+        let cost_store = cost_store.read().await;
+        (
+            cost_store.budgets().clone(),
+            cost_store.monthly_budget(),
+            cost_store.timezone(),
+        )
+    };
+
+    let spend = monthly_budget.map(|budget| MonthlySpend {
+        spent: combined_monthly_cost(costs.iter().map(Arc::as_ref)),
+        budget,
+    });
+    tray.set_budget_percent(spend.map(|spend| spend.percent()))
+        .await;
+    let _ = ui_tx.send(UiCommand::UpdateMonthlySpend(spend));
+
+    let project_costs = combined_project_costs(costs.iter().map(Arc::as_ref));
+    for project in unmatched_budgets(&budgets, &project_costs) {
+        tracing::warn!(
+            project,
+            "Budget in [cost.budgets] matches no project in the scanned logs"
+        );
+    }
+
+    let today = today(timezone);
+    let mut state = BudgetState::load();
+    let alerts = state.check(today, &project_costs, &budgets);
+    let total = spend.and_then(|spend| Some((state.check_total(today, spend)?, spend)));
+    if alerts.is_empty() && total.is_none() {
+        return;
+    }
+    if let Err(e) = state.save() {
+        tracing::warn!(error = %e, "Failed to save budget state");
+    }
+    tokio::task::spawn_blocking(move || {
+        for alert in alerts {
+            if let Err(e) =
+                send_budget_notification(&alert.project, alert.threshold, alert.spent, alert.budget)
+            {
+                tracing::warn!(error = %e, "Failed to send budget notification");
+            }
+        }
+        if let Some((threshold, spend)) = total {
+            if let Err(e) =
+                send_budget_notification("Monthly spend", threshold, spend.spent, spend.budget)
+            {
+                tracing::warn!(error = %e, "Failed to send budget notification");
+            }
+        }
+    });
+}
+
+async fn current_session_windows(store: &Arc<UsageStore>) -> HashMap<Provider, SessionWindow> {
+    let mut windows = HashMap::new();
+    let now = chrono::Utc::now();
+
+    // Codex logs are shared by every workspace, so only Claude's session is
+    // attributed.
+    let window = store
+        .get_snapshot(Provider::Claude)
+        .await
+        .and_then(|snapshot| {
+            snapshot
+                .primary
+                .as_ref()
+                .and_then(SessionWindow::from_rate_window)
+        })
+        .filter(|window| window.end > now);
+    if let Some(window) = window {
+        windows.insert(Provider::Claude, window);
+    }
+
+    windows
+}
+
 async fn refresh_provider_with_retry(
     registry: &Arc<ProviderRegistry>,
     store: &Arc<UsageStore>,
@@ -661,7 +1970,7 @@ async fn refresh_provider_with_retry(
         return;
     }
 
-    match registry.fetch_provider(provider).await {
+    match timed_fetch(registry, store, ui_tx, provider).await {
         Ok(snapshot) => {
             {
                 let mut states = retry_states.write().await;
@@ -678,6 +1987,9 @@ async fn refresh_provider_with_retry(
             }
             apply_successful_fetch(provider, snapshot, store, tray, ui_tx).await;
         }
+        Err(e) if in_reset_blackout(store, provider).await => {
+            ignore_reset_failure(provider, &e);
+        }
         Err(e) => {
             let (next_delay, failures) = {
                 let mut states = retry_states.write().await;
@@ -694,12 +2006,37 @@ async fn refresh_provider_with_retry(
                 next_retry_secs = next_delay.as_secs(),
                 "Failed to fetch usage, backing off"
             );
-            store.set_error(provider, error_msg).await;
-            tray.set_error(provider).await;
+            record_fetch_failure(provider, &e, Some(next_delay), store, tray).await;
         }
     }
 }
 
+/// Refreshes `provider` in the background when the governor lets `trigger`
+/// through, holding its in-flight slot until the fetch is done.
+async fn request_refresh(
+    trigger: RefreshTrigger,
+    provider: Provider,
+    governor: &Arc<RefreshGovernor>,
+    registry: &Arc<ProviderRegistry>,
+    store: &Arc<UsageStore>,
+    tray: &Arc<TrayManager>,
+    ui_tx: &mpsc::UnboundedSender<UiCommand>,
+) {
+    let Some(slot) = governor.request(provider, trigger) else {
+        return;
+    };
+    tray.set_loading(provider).await;
+
+    let registry = Arc::clone(registry);
+    let store = Arc::clone(store);
+    let tray = Arc::clone(tray);
+    let ui_tx = ui_tx.clone();
+    tokio::spawn(async move {
+        refresh_provider(&registry, &store, &tray, &ui_tx, provider).await;
+        drop(slot);
+    });
+}
+
 async fn refresh_provider(
     registry: &Arc<ProviderRegistry>,
     store: &Arc<UsageStore>,
@@ -707,43 +2044,140 @@ async fn refresh_provider(
     ui_tx: &mpsc::UnboundedSender<UiCommand>,
     provider: Provider,
 ) {
-    match registry.fetch_provider(provider).await {
+    match timed_fetch(registry, store, ui_tx, provider).await {
         Ok(snapshot) => {
             apply_successful_fetch(provider, snapshot, store, tray, ui_tx).await;
         }
+        Err(e) if in_reset_blackout(store, provider).await => {
+            ignore_reset_failure(provider, &e);
+        }
         Err(e) => {
             apply_failed_fetch(provider, &e, store, tray).await;
         }
     }
 }
 
-fn provider_error_hint(provider: Provider) -> &'static str {
+/// A fetch that fails around a session reset leaves the provider's last
+/// snapshot and retry state alone; the next poll comes after the reset.
+fn ignore_reset_failure(provider: Provider, error: &anyhow::Error) {
+    tracing::debug!(
+        ?provider,
+        error = %error,
+        "Ignoring a failed fetch around the session reset"
+    );
+}
+
+/// Fetches usage and records how long the provider API call took, so slow
+/// responses can be told apart from local network trouble.
+async fn timed_fetch(
+    registry: &ProviderRegistry,
+    store: &UsageStore,
+    ui_tx: &mpsc::UnboundedSender<UiCommand>,
+    provider: Provider,
+) -> Result<UsageSnapshot> {
+    let started = Instant::now();
+    let result = registry.fetch_provider(provider).await;
+    let elapsed = started.elapsed();
+    let latency = store.record_fetch_latency(provider, elapsed).await;
+    tracing::debug!(
+        ?provider,
+        elapsed_ms = elapsed.as_millis() as u64,
+        median_ms = latency.median,
+        "Usage fetch finished"
+    );
+    let _ = ui_tx.send(UiCommand::UpdateFetchLatency { provider, latency });
+    result
+}
+
+/// What to do about `error`. A response saved by debug mode is pointed at so
+/// it can go into a bug report.
+fn provider_error_hint(provider: Provider, error: &str) -> String {
+    if let Some(path) = dump_path_in(error) {
+        return format!("Attach {} to a bug report", path);
+    }
     match provider {
         Provider::Claude => "Run `claude` to authenticate",
-        Provider::Codex => "Run `codex` to authenticate",
+        Provider::Codex | Provider::CodexWorkspace(_) => "Run `codex` to authenticate",
     }
+    .to_string()
 }
 
-fn extract_percentages(snapshot: &UsageSnapshot) -> (f64, f64) {
-    let primary = snapshot.primary.as_ref().map_or(0.0, |r| r.used_percent);
-    let secondary = snapshot.secondary.as_ref().map_or(0.0, |r| r.used_percent);
+/// Picks the window a tray bar should draw. Windows the snapshot doesn't have
+/// (e.g. an Opus carveout on a Pro plan) fall back to weekly instead of 0%.
+fn resolve_tray_window(snapshot: &UsageSnapshot, window: TrayWindow) -> Option<&RateWindow> {
+    let resolved = match window {
+        TrayWindow::Session => snapshot.primary.as_ref(),
+        TrayWindow::Weekly => snapshot.secondary.as_ref(),
+        TrayWindow::Opus => snapshot.carveout_window("opus"),
+        TrayWindow::Sonnet => snapshot.carveout_window("sonnet"),
+        TrayWindow::MaxOfAll => snapshot.max_window(),
+    };
+    resolved.or(snapshot.secondary.as_ref())
+}
+
+fn extract_percentages(snapshot: &UsageSnapshot, windows: [TrayWindow; 2]) -> (f64, f64) {
+    let [primary, secondary] =
+        windows.map(|w| resolve_tray_window(snapshot, w).map_or(0.0, |r| r.used_percent));
     (primary, secondary)
 }
 
+fn tray_usage(snapshot: &UsageSnapshot, windows: [TrayWindow; 2]) -> TrayUsage {
+    let (primary, secondary) = extract_percentages(snapshot, windows);
+    let mut extra_windows: Vec<(String, f64)> = snapshot
+        .carveouts
+        .iter()
+        .map(|c| (c.label.clone(), c.window.used_percent))
+        .collect();
+    if extra_windows.is_empty() {
+        if let Some(tertiary) = &snapshot.tertiary {
+            extra_windows.push(("Additional".to_string(), tertiary.used_percent));
+        }
+    }
+
+    TrayUsage {
+        primary,
+        secondary,
+        session_percent: snapshot.primary.as_ref().map_or(0.0, |r| r.used_percent),
+        weekly_percent: snapshot.secondary.as_ref().map_or(0.0, |r| r.used_percent),
+        session_resets_at: snapshot.primary.as_ref().and_then(|r| r.resets_at),
+        weekly_resets_at: snapshot.secondary.as_ref().and_then(|r| r.resets_at),
+        extra_windows,
+    }
+}
+
 async fn apply_successful_fetch(
     provider: Provider,
-    snapshot: UsageSnapshot,
+    mut snapshot: UsageSnapshot,
     store: &Arc<UsageStore>,
     tray: &Arc<TrayManager>,
     ui_tx: &mpsc::UnboundedSender<UiCommand>,
 ) {
-    let (primary, secondary) = extract_percentages(&snapshot);
+    store.estimate_missing_resets(provider, &mut snapshot).await;
+    // A `304 Not Modified` comes back as the previous snapshot; the popup
+    // already shows it, so it only learns the usage is still current.
+    let unchanged = store
+        .get_snapshot(provider)
+        .await
+        .is_some_and(|previous| previous.same_usage_as(&snapshot));
+    let usage = tray_usage(&snapshot, tray.tray_windows().await);
     store.update_snapshot(provider, snapshot.clone()).await;
-    tray.update_icon(provider, primary, secondary).await;
+    tray.update_icon(provider, usage).await;
     tray.set_credentials_valid(provider, true).await;
+    let velocity = store.get_velocity(provider).await;
+    if unchanged {
+        tracing::debug!(?provider, "Usage unchanged; confirming popup usage");
+        let _ = ui_tx.send(UiCommand::UsageConfirmed {
+            provider,
+            updated_at: snapshot.updated_at,
+            received_at: snapshot.received_at,
+            velocity,
+        });
+        return;
+    }
     let _ = ui_tx.send(UiCommand::UpdateUsage {
         provider,
         snapshot: Box::new(snapshot),
+        velocity,
     });
 }
 
@@ -753,141 +2187,288 @@ async fn apply_failed_fetch(
     store: &Arc<UsageStore>,
     tray: &Arc<TrayManager>,
 ) {
-    let error_msg = error.to_string();
-    tracing::warn!(?provider, error = %error_msg, "Failed to fetch usage");
-    store.set_error(provider, error_msg).await;
-    tray.set_error(provider).await;
+    tracing::warn!(?provider, error = %error, "Failed to fetch usage");
+    record_fetch_failure(provider, error, None, store, tray).await;
+}
+
+/// `retry_in` is the backoff before the next attempt, when the caller has
+/// one; the tray shows it while the icon is stale.
+async fn record_fetch_failure(
+    provider: Provider,
+    error: &anyhow::Error,
+    retry_in: Option<Duration>,
+    store: &Arc<UsageStore>,
+    tray: &Arc<TrayManager>,
+) {
+    store.set_error(provider, error.to_string()).await;
+    let retry_at = retry_in
+        .and_then(|delay| chrono::Duration::from_std(delay).ok())
+        .map(|delay| chrono::Utc::now() + delay);
+    tray.set_failed(provider, error_kind(error), retry_at).await;
 }
 
+/// Registers `shortcuts.popup` and listens for it. The returned shortcut
+/// follows later `[shortcuts]` changes; failures reach the popup as a toast.
 fn start_global_shortcut(
     settings: &Settings,
     store: Arc<UsageStore>,
     ui_tx: mpsc::UnboundedSender<UiCommand>,
     registry: Arc<ProviderRegistry>,
-) {
-    if !settings.shortcuts.enabled {
-        return;
-    }
-
-    let Some(hotkey) = parse_hotkey(&settings.shortcuts.popup) else {
-        tracing::warn!("Failed to parse shortcut; global hotkey disabled");
-        return;
-    };
-
-    let manager = match GlobalHotKeyManager::new() {
-        Ok(manager) => manager,
+    tray: Arc<TrayManager>,
+    governor: Arc<RefreshGovernor>,
+    popup_memory: SharedPopupMemory,
+) -> Option<Arc<GlobalShortcut>> {
+    let shortcut = match GlobalShortcut::new() {
+        Ok(shortcut) => Arc::new(shortcut),
         Err(e) => {
             tracing::warn!(error = %e, "Failed to create hotkey manager");
-            return;
+            return None;
         }
     };
-
-    if let Err(e) = manager.register(hotkey) {
-        tracing::warn!(error = %e, "Failed to register global hotkey");
-        return;
-    }
-
-    let provider = registry
-        .enabled_provider_ids()
-        .first()
-        .copied()
-        .unwrap_or(Provider::Claude);
+    report_shortcut(shortcut.apply(&settings.shortcuts), &ui_tx);
 
     let receiver = GlobalHotKeyEvent::receiver();
+    let listener = Arc::clone(&shortcut);
     std::thread::spawn(move || {
-        let _manager = manager;
         while let Ok(event) = receiver.recv() {
-            if event.id == hotkey.id() {
+            if listener.is_current(event.id) {
                 let store = Arc::clone(&store);
                 let ui_tx = ui_tx.clone();
+                let registry = Arc::clone(&registry);
+                let tray = Arc::clone(&tray);
+                let governor = Arc::clone(&governor);
+                let chosen = popup_memory
+                    .lock()
+                    .ok()
+                    .and_then(|memory| memory.provider());
                 tokio::spawn(async move {
+                    let provider = match chosen {
+                        Some(provider) => provider,
+                        None => most_urgent_provider(&registry, &store).await,
+                    };
+                    request_refresh(
+                        RefreshTrigger::Hotkey,
+                        provider,
+                        &governor,
+                        &registry,
+                        &store,
+                        &tray,
+                        &ui_tx,
+                    )
+                    .await;
                     let snapshot = store.get_snapshot(provider).await.map(Box::new);
-                    let cost = store.get_cost(provider).await.map(Box::new);
-                    let tokens = store.get_token_snapshot(provider).await.map(Box::new);
-                    let error = store
-                        .get_error(provider)
-                        .await
-                        .map(|e| (e, provider_error_hint(provider).to_string()));
+                    let cost = store.get_cost(provider).await;
+                    let tokens = store.get_token_snapshot(provider).await;
+                    let error = store.get_error(provider).await.map(|e| {
+                        let hint = provider_error_hint(provider, &e);
+                        (e, hint)
+                    });
+                    let error_history = store.get_error_history(provider).await;
+                    let incident = store.get_incident(provider).await;
+                    let health = store.get_health(provider).await;
+                    let velocity = store.get_velocity(provider).await;
                     let _ = ui_tx.send(UiCommand::ShowPopup {
                         provider,
                         snapshot,
                         cost,
                         tokens,
                         error,
+                        error_history,
+                        incident,
+                        health,
+                        velocity,
+                        click: None,
                     });
                 });
             }
         }
     });
+    Some(shortcut)
 }
 
-fn parse_hotkey(input: &str) -> Option<HotKey> {
-    let mut modifiers = Modifiers::empty();
-    let mut key = None;
-
-    for raw in input.split('+') {
-        let part = raw.trim().to_lowercase();
-        if part.is_empty() {
-            continue;
-        }
-        match part.as_str() {
-            "ctrl" | "control" => modifiers |= Modifiers::CONTROL,
-            "shift" => modifiers |= Modifiers::SHIFT,
-            "alt" | "option" => modifiers |= Modifiers::ALT,
-            "super" | "cmd" | "meta" => modifiers |= Modifiers::SUPER,
-            _ => {
-                key = key_code_for(&part);
-            }
-        }
-    }
-
-    let key = key?;
-    Some(HotKey::new(Some(modifiers), key))
-}
-
-fn key_code_for(input: &str) -> Option<Code> {
-    if input.len() == 1 {
-        let ch = input.chars().next()?.to_ascii_uppercase();
-        return match ch {
-            'A' => Some(Code::KeyA),
-            'B' => Some(Code::KeyB),
-            'C' => Some(Code::KeyC),
-            'D' => Some(Code::KeyD),
-            'E' => Some(Code::KeyE),
-            'F' => Some(Code::KeyF),
-            'G' => Some(Code::KeyG),
-            'H' => Some(Code::KeyH),
-            'I' => Some(Code::KeyI),
-            'J' => Some(Code::KeyJ),
-            'K' => Some(Code::KeyK),
-            'L' => Some(Code::KeyL),
-            'M' => Some(Code::KeyM),
-            'N' => Some(Code::KeyN),
-            'O' => Some(Code::KeyO),
-            'P' => Some(Code::KeyP),
-            'Q' => Some(Code::KeyQ),
-            'R' => Some(Code::KeyR),
-            'S' => Some(Code::KeyS),
-            'T' => Some(Code::KeyT),
-            'U' => Some(Code::KeyU),
-            'V' => Some(Code::KeyV),
-            'W' => Some(Code::KeyW),
-            'X' => Some(Code::KeyX),
-            'Y' => Some(Code::KeyY),
-            'Z' => Some(Code::KeyZ),
-            '0' => Some(Code::Digit0),
-            '1' => Some(Code::Digit1),
-            '2' => Some(Code::Digit2),
-            '3' => Some(Code::Digit3),
-            '4' => Some(Code::Digit4),
-            '5' => Some(Code::Digit5),
-            '6' => Some(Code::Digit6),
-            '7' => Some(Code::Digit7),
-            '8' => Some(Code::Digit8),
-            '9' => Some(Code::Digit9),
-            _ => None,
+/// Logs a shortcut that failed to register and tells the popup, or clears
+/// the popup's notice once it works.
+fn report_shortcut(result: Result<(), ShortcutError>, ui_tx: &mpsc::UnboundedSender<UiCommand>) {
+    let error = result.err().map(|e| {
+        tracing::warn!(error = %e, "Global hotkey disabled");
+        e.to_string()
+    });
+    let _ = ui_tx.send(UiCommand::ShortcutError { error });
+}
+
+fn record_choice(popup_memory: &SharedPopupMemory, provider: Provider) {
+    if let Ok(mut memory) = popup_memory.lock() {
+        memory.choose_provider(provider);
+    }
+}
+
+/// `display.show_as_remaining`, unless the popup's toggle overrides it.
+fn remembered_show_as_remaining(popup_memory: &SharedPopupMemory, configured: bool) -> bool {
+    popup_memory
+        .lock()
+        .map(|mut memory| memory.show_as_remaining(configured))
+        .unwrap_or(configured)
+}
+
+/// The enabled provider closest to its limits; the first enabled one when
+/// none stands out.
+async fn most_urgent_provider(registry: &ProviderRegistry, store: &UsageStore) -> Provider {
+    let providers = registry.enabled_provider_ids();
+    let now = chrono::Utc::now();
+    let mut scores = HashMap::new();
+    for &provider in &providers {
+        let snapshot = store.get_snapshot(provider).await;
+        let has_error = store.get_error(provider).await.is_some();
+        scores.insert(provider, urgency_score(snapshot.as_ref(), has_error, now));
+    }
+    let ranked = rank_by_urgency(&providers, |provider| {
+        scores.get(&provider).copied().flatten()
+    });
+    ranked.first().copied().unwrap_or(Provider::Claude)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::ModelWindow;
+    use chrono::Utc;
+
+    fn window(used_percent: f64) -> RateWindow {
+        RateWindow::from_reported(used_percent, None, None, None)
+    }
+
+    fn snapshot(carveouts: Vec<ModelWindow>) -> UsageSnapshot {
+        UsageSnapshot {
+            carveouts,
+            ..UsageSnapshot::new(Some(window(0.2)), Some(window(0.4)), Utc::now())
+        }
+    }
+
+    #[test]
+    fn test_collapse_updates_keeps_latest_per_provider_and_kind() {
+        let latency = |median| FetchLatency {
+            min: median,
+            median,
+            max: median,
+            samples: 1,
         };
+        let mut newer = snapshot(Vec::new());
+        newer.primary = Some(window(0.7));
+        let commands = vec![
+            UiCommand::UpdateUsage {
+                provider: Provider::Claude,
+                snapshot: Box::new(snapshot(Vec::new())),
+                velocity: None,
+            },
+            UiCommand::UpdateFetchLatency {
+                provider: Provider::Claude,
+                latency: latency(900),
+            },
+            UiCommand::TaskFailed { task: "cost scan" },
+            UiCommand::UpdateUsage {
+                provider: Provider::Codex,
+                snapshot: Box::new(snapshot(Vec::new())),
+                velocity: None,
+            },
+            UiCommand::UpdateUsage {
+                provider: Provider::Claude,
+                snapshot: Box::new(newer),
+                velocity: None,
+            },
+            UiCommand::TaskFailed {
+                task: "tray events",
+            },
+        ];
+
+        let replay = collapse_updates(commands);
+        assert_eq!(replay.len(), 5);
+        assert!(matches!(
+            replay[0],
+            UiCommand::UpdateFetchLatency {
+                provider: Provider::Claude,
+                ..
+            }
+        ));
+        assert!(matches!(
+            replay[1],
+            UiCommand::TaskFailed { task: "cost scan" }
+        ));
+        assert!(matches!(
+            replay[2],
+            UiCommand::UpdateUsage {
+                provider: Provider::Codex,
+                ..
+            }
+        ));
+        let UiCommand::UpdateUsage {
+            provider: Provider::Claude,
+            snapshot,
+            ..
+        } = &replay[3]
+        else {
+            panic!("expected the latest Claude usage, got {:?}", replay[3]);
+        };
+        assert_eq!(snapshot.primary.as_ref().unwrap().used_percent, 0.7);
+        assert!(matches!(
+            replay[4],
+            UiCommand::TaskFailed {
+                task: "tray events"
+            }
+        ));
     }
 
-    None
+    #[test]
+    fn test_error_hint_points_at_debug_dump() {
+        assert_eq!(
+            provider_error_hint(Provider::Claude, "Claude API error: 500"),
+            "Run `claude` to authenticate"
+        );
+        assert_eq!(
+            provider_error_hint(
+                Provider::Claude,
+                "Failed to parse Claude usage response; raw response saved to /tmp/claude-usage.json"
+            ),
+            "Attach /tmp/claude-usage.json to a bug report"
+        );
+    }
+
+    #[test]
+    fn test_extract_percentages_honors_tray_windows() {
+        let snapshot = snapshot(vec![ModelWindow {
+            label: "Opus Weekly".to_string(),
+            window: window(0.9),
+        }]);
+
+        let (primary, secondary) =
+            extract_percentages(&snapshot, [TrayWindow::Session, TrayWindow::Weekly]);
+        assert!((primary - 0.2).abs() < f64::EPSILON);
+        assert!((secondary - 0.4).abs() < f64::EPSILON);
+
+        let (primary, secondary) =
+            extract_percentages(&snapshot, [TrayWindow::Opus, TrayWindow::MaxOfAll]);
+        assert!((primary - 0.9).abs() < f64::EPSILON);
+        assert!((secondary - 0.9).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_missing_tray_window_falls_back_to_weekly() {
+        let snapshot = snapshot(Vec::new());
+        let (primary, secondary) =
+            extract_percentages(&snapshot, [TrayWindow::Opus, TrayWindow::Sonnet]);
+        assert!((primary - 0.4).abs() < f64::EPSILON);
+        assert!((secondary - 0.4).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_tray_usage_lists_every_window() {
+        let snapshot = snapshot(vec![ModelWindow {
+            label: "Sonnet Weekly".to_string(),
+            window: window(0.3),
+        }]);
+        let usage = tray_usage(&snapshot, [TrayWindow::Sonnet, TrayWindow::Weekly]);
+        assert!((usage.primary - 0.3).abs() < f64::EPSILON);
+        assert!((usage.session_percent - 0.2).abs() < f64::EPSILON);
+        assert!((usage.weekly_percent - 0.4).abs() < f64::EPSILON);
+        assert_eq!(usage.extra_windows, vec![("Sonnet Weekly".to_string(), 0.3)]);
+    }
 }
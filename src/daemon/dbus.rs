@@ -1,24 +1,52 @@
+use crate::core::instance;
+use crate::core::models::{
+    CostSnapshot, CostUsageTokenSnapshot, FetchLatency, ModelTokenUsage, Provider, ProviderHealth,
+    UsageReport,
+};
+use crate::core::schema::{Versioned, SCHEMA_VERSION};
+use crate::core::settings::Settings;
+use crate::core::store::UsageStore;
+use crate::cost::today;
+use crate::daemon::tray::tray_status;
+use chrono::{Duration, NaiveDate};
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::{mpsc, RwLock};
 use zbus::interface;
 
 #[derive(Debug)]
 pub enum DbusCommand {
     Refresh,
     RefreshPricing,
+    Quit,
 }
 
 pub struct ClaudeBarService {
     is_refreshing: Arc<AtomicBool>,
     command_tx: mpsc::UnboundedSender<DbusCommand>,
+    store: Arc<UsageStore>,
+    tray_registered: Arc<AtomicBool>,
+    settings: Arc<RwLock<Settings>>,
+    /// Held while `SetSetting` loads, changes and saves the config file.
+    settings_write: Mutex<()>,
 }
 
 impl ClaudeBarService {
-    fn new(command_tx: mpsc::UnboundedSender<DbusCommand>) -> Self {
+    fn new(
+        command_tx: mpsc::UnboundedSender<DbusCommand>,
+        store: Arc<UsageStore>,
+        tray_registered: Arc<AtomicBool>,
+        settings: Arc<RwLock<Settings>>,
+    ) -> Self {
         Self {
             is_refreshing: Arc::new(AtomicBool::new(false)),
             command_tx,
+            store,
+            tray_registered,
+            settings,
+            settings_write: Mutex::new(()),
         }
     }
 
@@ -47,30 +75,381 @@ impl ClaudeBarService {
         Ok(())
     }
 
+    /// Stops the daemon the same way the tray's Quit item does.
+    async fn quit(&self) -> zbus::fdo::Result<()> {
+        tracing::info!("D-Bus Quit called");
+        self.command_tx
+            .send(DbusCommand::Quit)
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+        Ok(())
+    }
+
+    #[zbus(name = "GetCostHistory")]
+    async fn get_cost_history(&self, provider: &str, days: u32) -> zbus::fdo::Result<String> {
+        tracing::debug!(provider, days, "D-Bus GetCostHistory called");
+        let provider = Provider::from_id(provider).ok_or_else(|| {
+            zbus::fdo::Error::InvalidArgs(format!(
+                "Unknown provider: {provider}. Valid providers: claude, codex, codex-<n>"
+            ))
+        })?;
+
+        let timezone = Settings::load()
+            .map(|settings| settings.cost.timezone)
+            .unwrap_or_default();
+        let tokens = self.store.get_token_snapshot(provider).await;
+        let cost = self.store.get_cost(provider).await;
+        let history = build_cost_history(
+            provider,
+            days,
+            tokens.as_deref(),
+            cost.as_deref(),
+            today(timezone),
+        );
+
+        versioned_json(&history)
+    }
+
+    #[zbus(name = "GetErrorHistory")]
+    async fn get_error_history(&self, provider: &str) -> zbus::fdo::Result<String> {
+        tracing::debug!(provider, "D-Bus GetErrorHistory called");
+        let provider = Provider::from_id(provider).ok_or_else(|| {
+            zbus::fdo::Error::InvalidArgs(format!(
+                "Unknown provider: {provider}. Valid providers: claude, codex, codex-<n>"
+            ))
+        })?;
+
+        let history = self.store.get_error_history(provider).await;
+        versioned_json(&history)
+    }
+
+    #[zbus(name = "GetUsage")]
+    async fn get_usage(&self, provider: &str) -> zbus::fdo::Result<String> {
+        tracing::debug!(provider, "D-Bus GetUsage called");
+        let provider = Provider::from_id(provider).ok_or_else(|| {
+            zbus::fdo::Error::InvalidArgs(format!(
+                "Unknown provider: {provider}. Valid providers: claude, codex, codex-<n>"
+            ))
+        })?;
+
+        let report = UsageReport {
+            snapshot: self.store.get_snapshot(provider).await,
+            error: self.store.get_error(provider).await,
+        };
+        versioned_json(&report)
+    }
+
+    #[zbus(name = "GetFetchLatency")]
+    async fn get_fetch_latency(&self) -> zbus::fdo::Result<String> {
+        tracing::debug!("D-Bus GetFetchLatency called");
+        let latencies: BTreeMap<String, FetchLatency> = self
+            .store
+            .get_fetch_latencies()
+            .await
+            .into_iter()
+            .map(|(provider, latency)| (provider.id(), latency))
+            .collect();
+
+        versioned_json(&latencies)
+    }
+
+    #[zbus(name = "GetProviderHealth")]
+    async fn get_provider_health(&self) -> zbus::fdo::Result<String> {
+        tracing::debug!("D-Bus GetProviderHealth called");
+        let health: BTreeMap<String, ProviderHealth> = self
+            .store
+            .get_all_health()
+            .await
+            .into_iter()
+            .map(|(provider, health)| (provider.id(), health))
+            .collect();
+
+        versioned_json(&health)
+    }
+
+    #[zbus(name = "GetTrayStatus")]
+    async fn get_tray_status(&self) -> zbus::fdo::Result<String> {
+        tracing::debug!("D-Bus GetTrayStatus called");
+        let status = tray_status(&self.tray_registered);
+        versioned_json(&status)
+    }
+
+    /// The settings the daemon is running with, as JSON, with the webhook
+    /// URL and headers redacted.
+    #[zbus(name = "GetSettingsJson")]
+    async fn get_settings_json(&self) -> zbus::fdo::Result<String> {
+        tracing::debug!("D-Bus GetSettingsJson called");
+        let settings = self.settings.read().await.redacted();
+        serde_json::to_string(&settings).map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+
+    /// Changes one of `settings::RUNTIME_SETTINGS` and saves it the way the
+    /// preferences window does, so the daemon applies it at once.
+    #[zbus(name = "SetSetting", out_args("ok", "error"))]
+    async fn set_setting(&self, key: &str, value: &str) -> (bool, String) {
+        tracing::info!(key, value, "D-Bus SetSetting called");
+        let _guard = self
+            .settings_write
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        match save_runtime_setting(key, value) {
+            Ok(()) => (true, String::new()),
+            Err(e) => {
+                tracing::warn!(key, error = %e, "D-Bus SetSetting failed");
+                (false, format!("{:#}", e))
+            }
+        }
+    }
+
     #[zbus(property)]
     fn is_refreshing(&self) -> bool {
         self.is_refreshing.load(Ordering::SeqCst)
     }
 
+    /// The `schema_version` of the JSON this daemon writes and serves.
+    #[zbus(property)]
+    fn schema_version(&self) -> u32 {
+        SCHEMA_VERSION
+    }
+
     #[zbus(signal)]
     async fn usage_updated(ctx: &zbus::SignalContext<'_>, provider: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn cost_updated(ctx: &zbus::SignalContext<'_>, provider: &str) -> zbus::Result<()>;
 }
 
 pub const DBUS_NAME: &str = "com.github.kabilan.ClaudeBar";
 pub const DBUS_PATH: &str = "/com/github/kabilan/ClaudeBar";
 
+/// The well-known name this daemon instance owns. The interface name stays
+/// `DBUS_NAME` for every instance.
+pub fn bus_name() -> String {
+    instance::scoped(DBUS_NAME, '.')
+}
+
+/// Serializes a method's reply inside `Versioned`.
+fn versioned_json<T: Serialize>(data: &T) -> zbus::fdo::Result<String> {
+    serde_json::to_string(&Versioned::new(data))
+        .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+}
+
+#[derive(Debug, Serialize)]
+struct CostHistory {
+    provider: String,
+    days: u32,
+    scanning: bool,
+    daily: Vec<CostHistoryDay>,
+}
+
+#[derive(Debug, Serialize)]
+struct CostHistoryDay {
+    date: NaiveDate,
+    total_tokens: Option<u64>,
+    cost_usd: Option<f64>,
+    /// Cost by model, from the cost breakdown.
+    models: BTreeMap<String, f64>,
+    /// Token counts and cost by model, from the token scan.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    model_tokens: Vec<ModelTokenUsage>,
+}
+
+fn build_cost_history(
+    provider: Provider,
+    days: u32,
+    tokens: Option<&CostUsageTokenSnapshot>,
+    cost: Option<&CostSnapshot>,
+    today: NaiveDate,
+) -> CostHistory {
+    if tokens.is_none() && cost.is_none() {
+        return CostHistory {
+            provider: provider.id(),
+            days,
+            scanning: true,
+            daily: Vec::new(),
+        };
+    }
+
+    let cutoff = today - Duration::days(i64::from(days.max(1)) - 1);
+    let mut by_date: BTreeMap<NaiveDate, CostHistoryDay> = BTreeMap::new();
+
+    for day in tokens.map(|t| t.daily.as_slice()).unwrap_or_default() {
+        if day.date < cutoff || day.date > today {
+            continue;
+        }
+        let entry = by_date
+            .entry(day.date)
+            .or_insert_with(|| empty_day(day.date));
+        entry.total_tokens = day.total_tokens;
+        entry.cost_usd = day.cost_usd;
+        entry.model_tokens = day.models.clone();
+    }
+
+    for row in cost
+        .map(|c| c.daily_breakdown.as_slice())
+        .unwrap_or_default()
+    {
+        if row.date < cutoff || row.date > today {
+            continue;
+        }
+        let entry = by_date
+            .entry(row.date)
+            .or_insert_with(|| empty_day(row.date));
+        *entry.models.entry(row.model.clone()).or_insert(0.0) += row.cost;
+    }
+
+    for day in by_date.values_mut() {
+        if day.cost_usd.is_none() && !day.models.is_empty() {
+            day.cost_usd = Some(day.models.values().sum());
+        }
+    }
+
+    CostHistory {
+        provider: provider.id(),
+        days,
+        scanning: false,
+        daily: by_date.into_values().collect(),
+    }
+}
+
+fn empty_day(date: NaiveDate) -> CostHistoryDay {
+    CostHistoryDay {
+        date,
+        total_tokens: None,
+        cost_usd: None,
+        models: BTreeMap::new(),
+        model_tokens: Vec::new(),
+    }
+}
+
+/// Starts from the config file rather than the running settings, which
+/// may not have picked up the previous call's save yet.
+fn save_runtime_setting(key: &str, value: &str) -> anyhow::Result<()> {
+    let mut settings = Settings::load()?;
+    settings.set_runtime(key, value)?;
+    settings.validate()?;
+    settings.save()
+}
+
 pub async fn start_dbus_server(
     command_tx: mpsc::UnboundedSender<DbusCommand>,
+    store: Arc<UsageStore>,
+    tray_registered: Arc<AtomicBool>,
+    settings: Arc<RwLock<Settings>>,
 ) -> anyhow::Result<zbus::Connection> {
-    let service = ClaudeBarService::new(command_tx);
+    let service = ClaudeBarService::new(command_tx, store, tray_registered, settings);
 
     let connection = zbus::connection::Builder::session()?
-        .name(DBUS_NAME)?
+        .name(bus_name())?
         .serve_at(DBUS_PATH, service)?
         .build()
         .await?;
 
-    tracing::info!("D-Bus server started at {}", DBUS_NAME);
+    tracing::info!("D-Bus server started at {}", bus_name());
 
     Ok(connection)
 }
+
+pub async fn emit_cost_updated(connection: &zbus::Connection, provider: Provider) {
+    let result = async {
+        let ctx = zbus::SignalContext::new(connection, DBUS_PATH)?;
+        ClaudeBarService::cost_updated(&ctx, &provider.id()).await
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!(?provider, error = %e, "Failed to emit CostUpdated signal");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{DailyCost, DailyTokenUsage, ModelTokenUsage, UsageHeatmap};
+    use chrono::Utc;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 1, day).unwrap()
+    }
+
+    #[test]
+    fn test_cost_history_empty_cache_reports_scanning() {
+        let history = build_cost_history(Provider::Claude, 30, None, None, date(18));
+        assert!(history.scanning);
+        assert!(history.daily.is_empty());
+
+        let json = serde_json::to_value(&history).unwrap();
+        assert_eq!(json["scanning"], true);
+        assert_eq!(json["daily"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_cost_history_merges_tokens_and_breakdown() {
+        let tokens = CostUsageTokenSnapshot {
+            session_tokens: None,
+            session_cost_usd: None,
+            session_start: None,
+            last_30_days_tokens: None,
+            last_30_days_cost_usd: None,
+            daily: vec![
+                DailyTokenUsage {
+                    date: date(10),
+                    total_tokens: Some(500),
+                    cost_usd: Some(1.5),
+                    models: Vec::new(),
+                },
+                DailyTokenUsage {
+                    date: date(17),
+                    total_tokens: Some(1_000),
+                    cost_usd: Some(3.0),
+                    models: vec![ModelTokenUsage {
+                        model: "claude-sonnet-4".to_string(),
+                        input_tokens: 600,
+                        output_tokens: 400,
+                        cache_creation_tokens: 0,
+                        cache_read_tokens: 0,
+                        cost_usd: Some(3.0),
+                    }],
+                },
+            ],
+            heatmap: UsageHeatmap::default(),
+            updated_at: Utc::now(),
+        };
+        let cost = CostSnapshot {
+            daily_breakdown: vec![
+                DailyCost {
+                    date: date(17),
+                    model: "claude-sonnet-4".to_string(),
+                    cost: 2.0,
+                    tokens: 0,
+                },
+                DailyCost {
+                    date: date(17),
+                    model: "claude-opus-4".to_string(),
+                    cost: 1.0,
+                    tokens: 0,
+                },
+                DailyCost {
+                    date: date(18),
+                    model: "claude-sonnet-4".to_string(),
+                    cost: 0.5,
+                    tokens: 0,
+                },
+            ],
+            ..CostSnapshot::default()
+        };
+
+        let history = build_cost_history(Provider::Claude, 7, Some(&tokens), Some(&cost), date(18));
+
+        assert!(!history.scanning);
+        assert_eq!(history.daily.len(), 2);
+        assert_eq!(history.daily[0].date, date(17));
+        assert_eq!(history.daily[0].total_tokens, Some(1_000));
+        assert_eq!(history.daily[0].models.len(), 2);
+        assert_eq!(history.daily[1].date, date(18));
+        assert_eq!(history.daily[1].cost_usd, Some(0.5));
+
+        let json = serde_json::to_value(&history).unwrap();
+        assert_eq!(json["daily"][0]["model_tokens"][0]["input_tokens"], 600);
+        assert!(json["daily"][1].get("model_tokens").is_none());
+    }
+}
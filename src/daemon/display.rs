@@ -0,0 +1,37 @@
+use std::ffi::OsStr;
+
+/// Whether the session advertises a Wayland or X11 display for GTK to
+/// connect to. Some service managers export the variables empty, which
+/// counts as unset.
+pub fn display_available(wayland_display: Option<&OsStr>, x11_display: Option<&OsStr>) -> bool {
+    [wayland_display, x11_display]
+        .into_iter()
+        .flatten()
+        .any(|value| !value.is_empty())
+}
+
+/// `display_available` for this process's environment.
+pub fn display_in_env() -> bool {
+    display_available(
+        std::env::var_os("WAYLAND_DISPLAY").as_deref(),
+        std::env::var_os("DISPLAY").as_deref(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_available() {
+        let set = Some(OsStr::new(":0"));
+        let wayland = Some(OsStr::new("wayland-1"));
+        let empty = Some(OsStr::new(""));
+
+        assert!(display_available(wayland, None));
+        assert!(display_available(None, set));
+        assert!(display_available(empty, set));
+        assert!(!display_available(None, None));
+        assert!(!display_available(empty, empty));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::core::hotkey::{parse_hotkey, HotkeyParseError};
+use crate::core::settings::ShortcutSettings;
+use global_hotkey::hotkey::HotKey;
+use global_hotkey::GlobalHotKeyManager;
+use std::sync::Mutex;
+
+/// The global shortcut could not be set up as configured.
+#[derive(Debug, thiserror::Error)]
+pub enum ShortcutError {
+    #[error("Shortcut {shortcut} is invalid: {source}")]
+    Invalid {
+        shortcut: String,
+        source: HotkeyParseError,
+    },
+    #[error("Shortcut {shortcut} is unavailable — already in use?")]
+    Unavailable {
+        shortcut: String,
+        source: global_hotkey::Error,
+    },
+}
+
+/// The global shortcut that opens the popup. It can be swapped for another
+/// while the daemon runs, so `[shortcuts]` changes apply without a restart.
+pub struct GlobalShortcut {
+    manager: GlobalHotKeyManager,
+    registered: Mutex<Option<HotKey>>,
+}
+
+impl GlobalShortcut {
+    pub fn new() -> global_hotkey::Result<Self> {
+        Ok(Self {
+            manager: GlobalHotKeyManager::new()?,
+            registered: Mutex::new(None),
+        })
+    }
+
+    /// Replaces the registered shortcut with the one `settings` ask for,
+    /// or with none when they turn it off. On an error none is registered.
+    pub fn apply(&self, settings: &ShortcutSettings) -> Result<(), ShortcutError> {
+        let mut registered = self
+            .registered
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if let Some(previous) = registered.take() {
+            if let Err(e) = self.manager.unregister(previous) {
+                tracing::warn!(error = %e, "Failed to unregister global hotkey");
+            }
+        }
+        if !settings.enabled {
+            return Ok(());
+        }
+
+        let shortcut = settings.popup.trim().to_string();
+        let hotkey = parse_hotkey(&shortcut).map_err(|source| ShortcutError::Invalid {
+            shortcut: shortcut.clone(),
+            source,
+        })?;
+        self.manager
+            .register(hotkey)
+            .map_err(|source| ShortcutError::Unavailable { shortcut, source })?;
+        *registered = Some(hotkey);
+        Ok(())
+    }
+
+    /// Whether a hotkey event with `id` is for the registered shortcut.
+    pub fn is_current(&self, id: u32) -> bool {
+        self.registered
+            .lock()
+            .is_ok_and(|registered| registered.is_some_and(|hotkey| hotkey.id() == id))
+    }
+}
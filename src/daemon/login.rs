@@ -1,12 +1,25 @@
 use crate::core::models::Provider;
-use crate::daemon::{DBUS_NAME, DBUS_PATH};
+use crate::daemon::{bus_name, DBUS_NAME, DBUS_PATH};
 use anyhow::Result;
 use portable_pty::{native_pty_system, CommandBuilder, PtySize};
+use std::collections::HashMap;
 use std::io::{Read, Write};
+use std::path::Path;
 use std::sync::mpsc::{self, RecvTimeoutError};
 use std::time::{Duration, Instant};
 
-#[derive(Debug)]
+/// Width of the login PTY. The CLIs hard-wrap long lines, auth links
+/// included, at this column.
+const PTY_COLUMNS: u16 = 160;
+/// Where the CLIs send you to sign in, after the scheme. Links there win
+/// over the others they print, like their docs or Codex's local callback
+/// server.
+const AUTH_LINK_PREFIXES: &[&str] = &["claude.ai/oauth", "auth.openai.com/"];
+/// How long a link on no known auth host waits for one that is before it
+/// is opened anyway.
+const OTHER_LINK_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum LoginOutcome {
     Success,
     TimedOut,
@@ -15,6 +28,12 @@ pub enum LoginOutcome {
     LaunchFailed(String),
 }
 
+/// Progress reported while a login runs, so callers can mirror it live.
+pub enum LoginEvent<'a> {
+    Output(&'a str),
+    AuthLink(&'a str),
+}
+
 #[derive(Debug)]
 pub struct LoginResult {
     pub outcome: LoginOutcome,
@@ -22,12 +41,54 @@ pub struct LoginResult {
     pub auth_link: Option<String>,
 }
 
-pub fn spawn_provider_login(provider: Provider) {
+/// Status of a login started in the background, reported as it happens.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginProgress {
+    /// The latest non-empty line the CLI printed.
+    Output(String),
+    AuthLink(String),
+    Finished(LoginOutcome),
+}
+
+/// What a login from the popup's account menu is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoginMode {
+    /// Signs in again, normally as the same account.
+    #[default]
+    Reauthenticate,
+    /// Signs in as another account, even if the current one is still valid.
+    SwitchAccount,
+}
+
+pub fn spawn_provider_login<F>(provider: Provider, mode: LoginMode, report: F)
+where
+    F: Fn(LoginProgress) + Send + 'static,
+{
     std::thread::spawn(move || {
-        let result = run_provider_login(provider);
+        let mut last_line: Option<String> = None;
+        let mut output = String::new();
+        let result = run_provider_login(provider, mode, &mut |event| match event {
+            LoginEvent::Output(text) => {
+                output.push_str(text);
+                if output.len() > 8000 {
+                    let drain = output.len() - 8000;
+                    output.drain(..drain);
+                }
+                if let Some(line) = last_output_line(&output) {
+                    if last_line.as_ref() != Some(&line) {
+                        report(LoginProgress::Output(line.clone()));
+                        last_line = Some(line);
+                    }
+                }
+            }
+            LoginEvent::AuthLink(url) => {
+                let _ = open::that(url);
+                report(LoginProgress::AuthLink(url.to_string()));
+            }
+        });
         match &result.outcome {
             LoginOutcome::Success => {
-                tracing::info!(?provider, "Login succeeded");
+                tracing::info!(?provider, ?mode, "Login succeeded");
             }
             LoginOutcome::TimedOut => {
                 tracing::warn!(?provider, "Login timed out");
@@ -45,57 +106,87 @@ pub fn spawn_provider_login(provider: Provider) {
         if !result.output.is_empty() {
             tracing::debug!(?provider, output_len = result.output.len(), "Login output captured");
         }
-        if let Some(url) = result.auth_link.as_deref() {
-            let _ = open::that(url);
-        }
-        if matches!(result.outcome, LoginOutcome::Success) {
+        let succeeded = matches!(result.outcome, LoginOutcome::Success);
+        report(LoginProgress::Finished(result.outcome));
+        if succeeded {
             let _ = trigger_refresh();
         }
     });
 }
 
-fn run_provider_login(provider: Provider) -> LoginResult {
+/// The CLI a provider's login runs.
+pub fn login_binary(provider: Provider) -> &'static str {
     match provider {
-        Provider::Claude => run_claude_login(),
-        Provider::Codex => run_codex_login(),
+        Provider::Claude => "claude",
+        Provider::Codex | Provider::CodexWorkspace(_) => "codex",
+    }
+}
+
+/// Arguments to `login_binary(provider)` for `mode`.
+pub fn login_args(provider: Provider, mode: LoginMode) -> &'static [&'static str] {
+    match (provider, mode) {
+        (Provider::Claude, _) => &["/login"],
+        (Provider::Codex | Provider::CodexWorkspace(_), LoginMode::Reauthenticate) => &["login"],
+        (Provider::Codex | Provider::CodexWorkspace(_), LoginMode::SwitchAccount) => {
+            &["login", "--force"]
+        }
     }
 }
 
-fn run_claude_login() -> LoginResult {
-    run_pty_login(
-        "claude",
-        &["/login"],
-        Duration::from_secs(120),
-        Duration::from_secs(1),
-        &[
-            "Successfully logged in",
-            "Login successful",
-            "Logged in successfully",
-        ],
-    )
+/// Query parameter added to the auth link for `mode`. `claude /login` has no
+/// flag to pick another account, so the OAuth page is asked to offer the
+/// account chooser instead of signing straight back in.
+fn auth_link_query(provider: Provider, mode: LoginMode) -> Option<&'static str> {
+    match (provider, mode) {
+        (Provider::Claude, LoginMode::SwitchAccount) => Some("prompt=select_account"),
+        _ => None,
+    }
 }
 
-fn run_codex_login() -> LoginResult {
-    run_pty_login(
-        "codex",
-        &["login"],
-        Duration::from_secs(120),
-        Duration::from_secs(0),
-        &["Logged in successfully", "Login successful"],
-    )
+pub fn run_provider_login(
+    provider: Provider,
+    mode: LoginMode,
+    on_event: &mut dyn FnMut(LoginEvent<'_>),
+) -> LoginResult {
+    match provider {
+        Provider::Claude => run_pty_login(
+            login_binary(provider),
+            login_args(provider, mode),
+            auth_link_query(provider, mode),
+            Duration::from_secs(120),
+            Duration::from_secs(1),
+            &[
+                "Successfully logged in",
+                "Login successful",
+                "Logged in successfully",
+            ],
+            on_event,
+        ),
+        Provider::Codex | Provider::CodexWorkspace(_) => run_pty_login(
+            login_binary(provider),
+            login_args(provider, mode),
+            auth_link_query(provider, mode),
+            Duration::from_secs(120),
+            Duration::from_secs(0),
+            &["Logged in successfully", "Login successful"],
+            on_event,
+        ),
+    }
 }
 
 fn run_pty_login(
     binary: &str,
     args: &[&str],
+    link_query: Option<&str>,
     timeout: Duration,
     send_enter_every: Duration,
     success_markers: &[&str],
+    on_event: &mut dyn FnMut(LoginEvent<'_>),
 ) -> LoginResult {
     let pty_system = native_pty_system();
     let pair = match pty_system.openpty(PtySize {
         rows: 50,
-        cols: 160,
+        cols: PTY_COLUMNS,
         pixel_width: 0,
         pixel_height: 0,
     }) {
@@ -129,6 +220,8 @@ fn run_pty_login(
             };
         }
     };
+    // Only the child may keep the terminal open, so reads end once it exits.
+    drop(pair.slave);
 
     let mut reader = match pair.master.try_clone_reader() {
         Ok(reader) => reader,
@@ -170,6 +263,8 @@ fn run_pty_login(
     let mut last_enter = Instant::now();
     let mut output = String::new();
     let mut auth_link: Option<String> = None;
+    let mut link_announced = false;
+    let mut other_link_since: Option<Instant> = None;
 
     loop {
         if start.elapsed() >= timeout {
@@ -193,13 +288,19 @@ fn run_pty_login(
         match rx.recv_timeout(Duration::from_millis(200)) {
             Ok(chunk) => {
                 if let Ok(text) = String::from_utf8(chunk) {
-                    output.push_str(&text);
-                    if output.len() > 8000 {
-                        let drain = output.len() - 8000;
-                        output.drain(..drain);
-                    }
-                    if auth_link.is_none() {
-                        auth_link = first_link(&output);
+                    on_event(LoginEvent::Output(&text));
+                    append_output(&mut output, &text);
+                    if !link_announced {
+                        if let Some(link) = find_auth_link(&output, usize::from(PTY_COLUMNS)) {
+                            let url = with_query(link.url, link_query);
+                            if link.known_host {
+                                on_event(LoginEvent::AuthLink(&url));
+                                link_announced = true;
+                            } else {
+                                other_link_since.get_or_insert_with(Instant::now);
+                            }
+                            auth_link = Some(url);
+                        }
                     }
                 }
             }
@@ -207,8 +308,24 @@ fn run_pty_login(
             Err(RecvTimeoutError::Disconnected) => break,
         }
 
+        if !link_announced && other_link_since.is_some_and(|at| at.elapsed() >= OTHER_LINK_GRACE) {
+            if let Some(link) = auth_link.as_deref() {
+                on_event(LoginEvent::AuthLink(link));
+                link_announced = true;
+            }
+        }
+
         if let Ok(Some(status)) = child.try_wait() {
             let _ = reader_handle.join();
+            for chunk in rx.try_iter() {
+                if let Ok(text) = String::from_utf8(chunk) {
+                    on_event(LoginEvent::Output(&text));
+                    append_output(&mut output, &text);
+                }
+            }
+            if !link_announced {
+                announce_final_link(&output, link_query, &mut auth_link, on_event);
+            }
             let outcome = if status.success() {
                 LoginOutcome::Success
             } else {
@@ -233,60 +350,372 @@ fn run_pty_login(
     }
 
     let _ = reader_handle.join();
+    if !link_announced {
+        announce_final_link(&output, link_query, &mut auth_link, on_event);
+    }
+    let outcome = match child.wait() {
+        Ok(status) if status.success() => LoginOutcome::Success,
+        Ok(status) => LoginOutcome::Failed(status.exit_code() as i32),
+        Err(_) => LoginOutcome::Failed(1),
+    };
     LoginResult {
-        outcome: LoginOutcome::Failed(1),
+        outcome,
         output,
         auth_link,
     }
 }
 
-fn first_link(text: &str) -> Option<String> {
-    let mut best: Option<String> = None;
-    let bytes = text.as_bytes();
-    let mut i = 0;
-    while i + 7 < bytes.len() {
-        let rest = &text[i..];
-        let prefix = if rest.starts_with("https://") {
-            "https://"
-        } else if rest.starts_with("http://") {
-            "http://"
-        } else {
-            i += 1;
-            continue;
+/// Keeps the last 8000 bytes of login output.
+fn append_output(output: &mut String, text: &str) {
+    output.push_str(text);
+    if output.len() > 8000 {
+        let drain = output.len() - 8000;
+        output.drain(..drain);
+    }
+}
+
+/// Reports the link the output holds once the CLI exits or closes the PTY.
+/// Nothing more will follow then, so a link on the last line is complete.
+fn announce_final_link(
+    output: &str,
+    link_query: Option<&str>,
+    auth_link: &mut Option<String>,
+    on_event: &mut dyn FnMut(LoginEvent<'_>),
+) {
+    if let Some(link) = final_auth_link(output) {
+        let url = with_query(link, link_query);
+        on_event(LoginEvent::AuthLink(&url));
+        *auth_link = Some(url);
+    }
+}
+
+/// The link to sign in with from complete output.
+fn final_auth_link(output: &str) -> Option<String> {
+    find_auth_link(&format!("{}\n", output), usize::from(PTY_COLUMNS)).map(|link| link.url)
+}
+
+/// A link found in login output.
+#[derive(Debug, PartialEq)]
+struct FoundLink {
+    url: String,
+    /// On one of `AUTH_LINK_PREFIXES`.
+    known_host: bool,
+}
+
+/// The link to sign in with from terminal output `columns` wide: the first
+/// on a known auth host, else the first of any. OSC 8 hyperlink targets
+/// come first since they are never wrapped; links in the text are read
+/// with escape sequences removed and hard-wrapped lines joined. A link
+/// nothing follows yet may still be arriving and is skipped.
+fn find_auth_link(text: &str, columns: usize) -> Option<FoundLink> {
+    let (plain, hyperlinks) = strip_escapes(text);
+    let joined = join_wrapped_lines(&plain, columns);
+    let links: Vec<FoundLink> = hyperlinks
+        .into_iter()
+        .chain(links_in(&joined))
+        .map(|url| {
+            let after_scheme = url.split_once("://").map_or("", |(_, rest)| rest);
+            let known_host = AUTH_LINK_PREFIXES
+                .iter()
+                .any(|prefix| after_scheme.starts_with(prefix));
+            FoundLink { url, known_host }
+        })
+        .collect();
+    let preferred = links.iter().position(|link| link.known_host).unwrap_or(0);
+    links.into_iter().nth(preferred)
+}
+
+/// Every http(s) link in `text` that something follows, without trailing
+/// punctuation.
+fn links_in(text: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("http") {
+        rest = &rest[start..];
+        let prefix_len = ["https://", "http://"]
+            .iter()
+            .find(|prefix| rest.starts_with(*prefix))
+            .map_or(0, |prefix| prefix.len());
+        let Some(end) = rest.find(|c: char| c.is_ascii_whitespace()) else {
+            break;
         };
+        let url = rest[..end].trim_end_matches(|c| ".,;:)]}>\"'".contains(c));
+        if prefix_len > 0 && url.len() > prefix_len {
+            links.push(url.to_string());
+        }
+        rest = &rest[end..];
+    }
+    links
+}
 
-        let mut end = i + prefix.len();
-        while end < bytes.len() {
-            let b = bytes[end];
-            if b.is_ascii_whitespace() {
-                break;
+/// Undoes the CLI's hard wrapping of long links: a line filled to the last
+/// column that ends inside a link continues on the next line.
+fn join_wrapped_lines(text: &str, columns: usize) -> String {
+    let mut joined = String::with_capacity(text.len());
+    let mut in_link = false;
+    for (i, line) in text.split('\n').enumerate() {
+        let line = line.trim_end_matches('\r');
+        if in_link {
+            joined.push_str(line.trim_start());
+        } else {
+            if i > 0 {
+                joined.push('\n');
             }
-            end += 1;
+            joined.push_str(line);
         }
+        let full = line.chars().count() >= columns && !line.ends_with(char::is_whitespace);
+        let last_word = line.split_whitespace().last().unwrap_or_default();
+        let continues_link = in_link && line.trim_start().len() == last_word.len();
+        in_link = full && (last_word.contains("://") || continues_link);
+    }
+    joined
+}
 
-        let mut url = text[i..end].to_string();
-        while let Some(last) = url.chars().last() {
-            if ".,;:)]}>\"'".contains(last) {
-                url.pop();
-            } else {
-                break;
+/// `text` with terminal escape sequences removed, and the targets of the
+/// OSC 8 hyperlinks in it.
+fn strip_escapes(text: &str) -> (String, Vec<String>) {
+    let mut plain = String::with_capacity(text.len());
+    let mut hyperlinks = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            plain.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameters up to a final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: up to BEL or ESC \
+            Some(']') => {
+                let mut payload = String::new();
+                let mut terminated = false;
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        terminated = true;
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        terminated = true;
+                        break;
+                    }
+                    payload.push(c);
+                }
+                // OSC 8 ; params ; URI, with an empty URI closing the link.
+                let uri = payload
+                    .strip_prefix("8;")
+                    .and_then(|link| link.split_once(';'))
+                    .map(|(_, uri)| uri);
+                if let Some(uri) = uri.filter(|uri| terminated && !uri.is_empty()) {
+                    hyperlinks.push(uri.to_string());
+                }
             }
+            _ => {}
         }
+    }
+    (plain, hyperlinks)
+}
 
-        if !url.is_empty() {
-            best = Some(url);
-            break;
-        }
-        i = end;
+/// `link` with `query` appended to its query string.
+fn with_query(link: String, query: Option<&str>) -> String {
+    match query {
+        Some(query) if link.contains('?') => format!("{}&{}", link, query),
+        Some(query) => format!("{}?{}", link, query),
+        None => link,
     }
-    best
 }
 
-fn trigger_refresh() -> Result<()> {
+/// Last non-blank line of terminal output, with escape sequences removed.
+fn last_output_line(text: &str) -> Option<String> {
+    let (plain, _) = strip_escapes(text);
+    plain
+        .split(['\r', '\n'])
+        .map(str::trim)
+        .rev()
+        .find(|line| !line.chars().all(|c| c.is_control()))
+        .map(str::to_string)
+}
+
+/// Opens the folder holding `path` through the desktop portal's OpenURI
+/// interface, which lets the file manager select the file itself.
+pub fn show_in_file_manager(path: &Path) -> Result<()> {
+    let file = std::fs::File::open(path)?;
+    let connection = zbus::blocking::Connection::session()?;
+    let options: HashMap<&str, zbus::zvariant::Value<'_>> = HashMap::new();
+    connection.call_method(
+        Some("org.freedesktop.portal.Desktop"),
+        "/org/freedesktop/portal/desktop",
+        Some("org.freedesktop.portal.OpenURI"),
+        "OpenDirectory",
+        &("", zbus::zvariant::Fd::from(&file), options),
+    )?;
+    Ok(())
+}
+
+pub fn trigger_refresh() -> Result<()> {
     let connection = zbus::blocking::Connection::session()?;
     let _reply: () = connection
-        .call_method(Some(DBUS_NAME), DBUS_PATH, Some(DBUS_NAME), "Refresh", &())?
+        .call_method(Some(bus_name()), DBUS_PATH, Some(DBUS_NAME), "Refresh", &())?
         .body()
         .deserialize()?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_last_output_line_strips_terminal_noise() {
+        let output = "\u{1b}[2J\u{1b}[1;1HWelcome to Codex\r\n\u{1b}]0;codex\u{7}\u{1b}[32mOpen this URL\u{1b}[0m:\r\n  \r\n";
+        assert_eq!(last_output_line(output).as_deref(), Some("Open this URL:"));
+        assert_eq!(last_output_line("\u{1b}[?25l\r\n"), None);
+    }
+
+    #[test]
+    fn test_auth_link_trims_punctuation() {
+        let output = "Visit (https://auth.openai.com/oauth?x=1). Then return";
+        assert_eq!(
+            find_auth_link(output, 160),
+            Some(FoundLink {
+                url: "https://auth.openai.com/oauth?x=1".to_string(),
+                known_host: true,
+            })
+        );
+    }
+
+    const CLAUDE_LINK: &str = "https://claude.ai/oauth/authorize?code=true\
+        &client_id=9d1c250a-e61b-44d9-88ed-5944d1962f5e&response_type=code\
+        &redirect_uri=https%3A%2F%2Fconsole.anthropic.com%2Foauth%2Fcode%2Fcallback\
+        &scope=org%3Acreate_api_key+user%3Aprofile+user%3Ainference\
+        &code_challenge=Xq3m9VJ1rP7uYw0kQ2bZtH5sLd8nCe4fGa6oIyRvUjM\
+        &code_challenge_method=S256&state=f1dRk2nPq8sT0vWx3yZa5bCe7gHi9jKl";
+
+    fn auth_link(output: &str) -> Option<String> {
+        find_auth_link(output, usize::from(PTY_COLUMNS))
+            .filter(|link| link.known_host)
+            .map(|link| link.url)
+    }
+
+    #[test]
+    fn test_auth_link_joins_wrapped_claude_output() {
+        // Colored, padded to the PTY width, with a docs link first.
+        let output = include_str!("../../tests/fixtures/login/claude_wrapped.txt");
+        assert_eq!(auth_link(output).as_deref(), Some(CLAUDE_LINK));
+
+        // The link's last line may not have arrived yet.
+        let cut = output.find("ethod=S256").unwrap();
+        assert_eq!(
+            find_auth_link(&output[..cut], usize::from(PTY_COLUMNS)),
+            Some(FoundLink {
+                url: "https://docs.anthropic.com/en/docs/claude-code/setup".to_string(),
+                known_host: false,
+            })
+        );
+    }
+
+    #[test]
+    fn test_auth_link_from_osc8_hyperlink() {
+        let output = include_str!("../../tests/fixtures/login/claude_osc8.txt");
+        let (plain, hyperlinks) = strip_escapes(output);
+        assert_eq!(hyperlinks, [CLAUDE_LINK]);
+        assert!(!plain.contains('\u{1b}'));
+        assert_eq!(auth_link(output).as_deref(), Some(CLAUDE_LINK));
+    }
+
+    #[test]
+    fn test_auth_link_joins_wrapped_codex_output() {
+        // The local callback server's address comes before the auth link.
+        let output = include_str!("../../tests/fixtures/login/codex_wrapped.txt");
+        let link = auth_link(output).unwrap();
+        assert!(link.starts_with("https://auth.openai.com/oauth/authorize?response_type=code"));
+        assert!(link.ends_with(
+            "&codex_cli_simplified_flow=true&state=Zr7Yq1Xp3Wo5Vn7Um9Tl1Sk3Rj5Qi7Ph9Og1Nf3Me5L"
+        ));
+        assert!(!link.contains(char::is_whitespace));
+        assert_eq!(link.len(), 405);
+
+        // Lines that merely fill the width aren't joined.
+        let full = format!("{}\r\nnext line\r\n", "x".repeat(160));
+        assert_eq!(
+            join_wrapped_lines(&full, 160),
+            format!("{}\nnext line\n", "x".repeat(160))
+        );
+    }
+
+    #[test]
+    fn test_auth_link_on_the_last_line() {
+        let output = "Open this URL to sign in:\r\nhttps://auth.openai.com/oauth/authorize?x=1";
+        assert_eq!(auth_link(output), None);
+        assert_eq!(
+            final_auth_link(output).as_deref(),
+            Some("https://auth.openai.com/oauth/authorize?x=1")
+        );
+
+        let mut links = Vec::new();
+        let result = run_pty_login(
+            "printf",
+            &["Sign in at https://auth.openai.com/oauth/authorize?x=1"],
+            None,
+            Duration::from_secs(10),
+            Duration::from_secs(0),
+            &[],
+            &mut |event| {
+                if let LoginEvent::AuthLink(link) = event {
+                    links.push(link.to_string());
+                }
+            },
+        );
+        assert!(matches!(result.outcome, LoginOutcome::Success));
+        assert_eq!(links, ["https://auth.openai.com/oauth/authorize?x=1"]);
+        assert_eq!(result.auth_link, links.first().cloned());
+    }
+
+    #[test]
+    fn test_switch_account_forces_account_selection() {
+        assert_eq!(
+            login_args(Provider::Claude, LoginMode::Reauthenticate),
+            ["/login"]
+        );
+        assert_eq!(
+            login_args(Provider::Claude, LoginMode::SwitchAccount),
+            ["/login"]
+        );
+        assert_eq!(
+            login_args(Provider::Codex, LoginMode::Reauthenticate),
+            ["login"]
+        );
+        assert_eq!(
+            login_args(Provider::CodexWorkspace(2), LoginMode::SwitchAccount),
+            ["login", "--force"]
+        );
+
+        let link = "https://claude.ai/oauth/authorize?code=true".to_string();
+        let query = auth_link_query(Provider::Claude, LoginMode::SwitchAccount);
+        assert_eq!(
+            with_query(link.clone(), query),
+            "https://claude.ai/oauth/authorize?code=true&prompt=select_account"
+        );
+        assert_eq!(
+            with_query(
+                link.clone(),
+                auth_link_query(Provider::Claude, LoginMode::Reauthenticate)
+            ),
+            link
+        );
+        assert_eq!(
+            with_query("https://example.com/login".to_string(), query),
+            "https://example.com/login?prompt=select_account"
+        );
+        assert_eq!(
+            auth_link_query(Provider::Codex, LoginMode::SwitchAccount),
+            None
+        );
+    }
+}
@@ -1,16 +1,44 @@
 mod app;
 pub mod dbus;
+mod display;
+mod hotkey;
 pub mod login;
+mod popup_memory;
+mod refresh_governor;
+mod reset_blackout;
+mod shutdown;
+mod supervisor;
+mod tooltip;
 pub mod tray;
+mod tray_updates;
+mod webhook;
 
+use crate::providers::ProviderRegistry;
 use anyhow::Result;
+use app::{DaemonOptions, PricingRefresh};
+use std::time::Duration;
 
 #[allow(unused_imports)]
-pub use dbus::{start_dbus_server, DbusCommand, DBUS_NAME, DBUS_PATH};
+pub use dbus::{bus_name, start_dbus_server, DbusCommand, DBUS_NAME, DBUS_PATH};
 #[allow(unused_imports)]
 pub use tray::{run_animation_loop, TrayEvent, TrayManager};
 
-pub async fn run() -> Result<()> {
+/// `no_ui` runs without the tray and popup, as happens anyway when there is
+/// no display to show them on. `dry_run` polls canned providers instead of
+/// the usage APIs, keeps the cached pricing, skips the webhook, and stops
+/// after `run_for` when given.
+pub async fn run(no_ui: bool, dry_run: bool, run_for: Option<Duration>) -> Result<()> {
     tracing::info!("Starting claude-bar daemon");
-    app::run().await
+    let mut options = DaemonOptions {
+        no_ui,
+        ..DaemonOptions::default()
+    };
+    if dry_run {
+        tracing::warn!("DRY RUN: canned usage, no usage API, pricing or webhook requests");
+        options.providers = ProviderRegistry::canned;
+        options.pricing = PricingRefresh::Disabled;
+        options.webhook = false;
+        options.run_for = run_for;
+    }
+    app::run(options).await
 }
@@ -0,0 +1,82 @@
+use crate::core::models::Provider;
+use std::sync::{Arc, Mutex};
+
+/// Shared between the tray handler, the hotkey thread and the popup.
+pub type SharedPopupMemory = Arc<Mutex<PopupMemory>>;
+
+/// What the popup last showed, so reopening it picks up where the user left
+/// off. Kept only while the daemon runs; nothing here goes to the config.
+#[derive(Debug, Default)]
+pub struct PopupMemory {
+    /// The provider the user last picked, from a tray icon or the popup.
+    provider: Option<Provider>,
+    /// The popup's used/remaining toggle, until the config's value changes.
+    show_as_remaining: Option<bool>,
+    /// `display.show_as_remaining` as of the last settings load.
+    configured_show_as_remaining: Option<bool>,
+}
+
+impl PopupMemory {
+    pub fn shared() -> SharedPopupMemory {
+        Arc::new(Mutex::new(Self::default()))
+    }
+
+    pub fn choose_provider(&mut self, provider: Provider) {
+        self.provider = Some(provider);
+    }
+
+    /// `None` until the user picks one; the hotkey then opens the most urgent
+    /// provider and the merged icon offers a menu.
+    pub fn provider(&self) -> Option<Provider> {
+        self.provider
+    }
+
+    pub fn toggle_show_as_remaining(&mut self, show_as_remaining: bool) {
+        self.show_as_remaining = Some(show_as_remaining);
+    }
+
+    /// Whether to show remaining usage with `configured` in the config. A
+    /// toggle in the popup wins until the config itself changes.
+    pub fn show_as_remaining(&mut self, configured: bool) -> bool {
+        if self
+            .configured_show_as_remaining
+            .is_some_and(|previous| previous != configured)
+        {
+            self.show_as_remaining = None;
+        }
+        self.configured_show_as_remaining = Some(configured);
+        self.show_as_remaining.unwrap_or(configured)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remembers_last_provider() {
+        let mut memory = PopupMemory::default();
+        assert_eq!(memory.provider(), None);
+
+        memory.choose_provider(Provider::Codex);
+        memory.choose_provider(Provider::CodexWorkspace(1));
+        assert_eq!(memory.provider(), Some(Provider::CodexWorkspace(1)));
+    }
+
+    #[test]
+    fn test_toggle_outlives_reloads_until_config_changes() {
+        let mut memory = PopupMemory::default();
+        assert!(!memory.show_as_remaining(false));
+
+        memory.toggle_show_as_remaining(true);
+        // Reloads for unrelated settings keep the toggle.
+        assert!(memory.show_as_remaining(false));
+        assert!(memory.show_as_remaining(false));
+
+        // Editing the setting itself takes over again.
+        assert!(memory.show_as_remaining(true));
+        memory.toggle_show_as_remaining(false);
+        assert!(!memory.show_as_remaining(true));
+        assert!(!memory.show_as_remaining(false));
+    }
+}
@@ -0,0 +1,280 @@
+use crate::core::models::Provider;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+/// How long after a refresh starts passive triggers leave the provider alone.
+const REFRESH_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// What asked for a refresh. Scheduled polls keep their own interval and
+/// don't go through the governor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshTrigger {
+    /// Left-clicking a tray icon, which opens the popup.
+    TrayClick,
+    /// The global shortcut, which opens the popup.
+    Hotkey,
+    /// Middle-clicking a tray icon.
+    MiddleClick,
+    /// The tray menu's "Refresh Now".
+    TrayMenu,
+    /// The D-Bus `Refresh` method: the popup's "Refresh Now" button,
+    /// `claude-bar refresh` and finished logins.
+    Dbus,
+    /// The provider's credentials file changed on disk.
+    CredentialsChanged,
+}
+
+impl RefreshTrigger {
+    /// Whether someone asked for this refresh, so it skips the cooldown.
+    pub fn is_explicit(self) -> bool {
+        match self {
+            RefreshTrigger::MiddleClick | RefreshTrigger::TrayMenu | RefreshTrigger::Dbus => true,
+            RefreshTrigger::TrayClick
+            | RefreshTrigger::Hotkey
+            | RefreshTrigger::CredentialsChanged => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshDecision {
+    /// Fetch now, holding the `RefreshSlot` until done.
+    Start,
+    /// A fetch for the provider is already running and answers this one too.
+    Coalesced,
+    /// A passive trigger this long before the cooldown ends.
+    CoolingDown(Duration),
+}
+
+#[derive(Debug, Default)]
+struct ProviderRefresh {
+    last_started: Option<Instant>,
+    in_flight: bool,
+}
+
+/// Decides, per provider, whether a refresh request fetches. Every refresh
+/// source asks here: one already running answers any request that comes in
+/// meanwhile, explicit requests otherwise always fetch, and passive ones
+/// wait out `REFRESH_COOLDOWN` after the last refresh started.
+#[derive(Debug, Default)]
+pub struct RefreshGovernor {
+    providers: Mutex<HashMap<Provider, ProviderRefresh>>,
+}
+
+impl RefreshGovernor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<Provider, ProviderRefresh>> {
+        self.providers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Decides on `trigger` refreshing `provider`, claiming the in-flight
+    /// slot when it may start.
+    pub fn request(
+        self: &Arc<Self>,
+        provider: Provider,
+        trigger: RefreshTrigger,
+    ) -> Option<RefreshSlot> {
+        let decision = self.request_at(provider, trigger, Instant::now());
+        tracing::debug!(?provider, ?trigger, ?decision, "Refresh governor decision");
+        (decision == RefreshDecision::Start).then(|| RefreshSlot {
+            governor: Arc::clone(self),
+            provider,
+        })
+    }
+
+    fn request_at(
+        &self,
+        provider: Provider,
+        trigger: RefreshTrigger,
+        now: Instant,
+    ) -> RefreshDecision {
+        let mut providers = self.lock();
+        let state = providers.entry(provider).or_default();
+        if state.in_flight {
+            return RefreshDecision::Coalesced;
+        }
+        if !trigger.is_explicit() {
+            let cooldown_ends = state.last_started.map(|at| at + REFRESH_COOLDOWN);
+            if let Some(ends) = cooldown_ends.filter(|ends| now < *ends) {
+                return RefreshDecision::CoolingDown(ends - now);
+            }
+        }
+        state.in_flight = true;
+        state.last_started = Some(now);
+        RefreshDecision::Start
+    }
+
+    /// Releases the in-flight slot a `Start` claimed.
+    fn finish(&self, provider: Provider) {
+        if let Some(state) = self.lock().get_mut(&provider) {
+            state.in_flight = false;
+        }
+    }
+}
+
+/// A provider's in-flight slot, released when dropped. A fetch that panics
+/// or is aborted releases it too, so later requests aren't coalesced into
+/// a refresh that will never finish.
+#[must_use]
+#[derive(Debug)]
+pub struct RefreshSlot {
+    governor: Arc<RefreshGovernor>,
+    provider: Provider,
+}
+
+impl Drop for RefreshSlot {
+    fn drop(&mut self) {
+        self.governor.finish(self.provider);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRIGGERS: [RefreshTrigger; 6] = [
+        RefreshTrigger::TrayClick,
+        RefreshTrigger::Hotkey,
+        RefreshTrigger::MiddleClick,
+        RefreshTrigger::TrayMenu,
+        RefreshTrigger::Dbus,
+        RefreshTrigger::CredentialsChanged,
+    ];
+
+    #[test]
+    fn test_arbitration_matrix() {
+        let start = Instant::now();
+        let cooling = start + Duration::from_secs(2);
+        let cooled = start + REFRESH_COOLDOWN;
+
+        for trigger in TRIGGERS {
+            // Idle with no earlier refresh: everything starts.
+            let governor = RefreshGovernor::new();
+            assert_eq!(
+                governor.request_at(Provider::Claude, trigger, start),
+                RefreshDecision::Start,
+                "{:?} when idle",
+                trigger
+            );
+
+            // In flight: everything coalesces, even explicit requests.
+            assert_eq!(
+                governor.request_at(Provider::Claude, trigger, cooling),
+                RefreshDecision::Coalesced,
+                "{:?} while in flight",
+                trigger
+            );
+
+            // Finished but within the cooldown: only explicit requests start.
+            governor.finish(Provider::Claude);
+            let expected = if trigger.is_explicit() {
+                RefreshDecision::Start
+            } else {
+                RefreshDecision::CoolingDown(Duration::from_secs(3))
+            };
+            assert_eq!(
+                governor.request_at(Provider::Claude, trigger, cooling),
+                expected,
+                "{:?} during the cooldown",
+                trigger
+            );
+
+            // Past the cooldown: everything starts again.
+            governor.finish(Provider::Claude);
+            assert_eq!(
+                governor.request_at(Provider::Claude, trigger, cooled + Duration::from_secs(2)),
+                RefreshDecision::Start,
+                "{:?} after the cooldown",
+                trigger
+            );
+        }
+    }
+
+    #[test]
+    fn test_explicit_refresh_restarts_the_cooldown() {
+        let governor = RefreshGovernor::new();
+        let start = Instant::now();
+
+        // A tray click opens the popup and refreshes; "Refresh Now" right
+        // after it coalesces while that fetch runs and starts once it's done.
+        let click = RefreshTrigger::TrayClick;
+        assert_eq!(
+            governor.request_at(Provider::Claude, click, start),
+            RefreshDecision::Start
+        );
+        let soon = start + Duration::from_secs(1);
+        assert_eq!(
+            governor.request_at(Provider::Claude, RefreshTrigger::Dbus, soon),
+            RefreshDecision::Coalesced
+        );
+        governor.finish(Provider::Claude);
+        assert_eq!(
+            governor.request_at(Provider::Claude, RefreshTrigger::Dbus, soon),
+            RefreshDecision::Start
+        );
+        governor.finish(Provider::Claude);
+
+        // The cooldown now runs from the explicit refresh.
+        let after_click_cooldown = start + REFRESH_COOLDOWN;
+        assert_eq!(
+            governor.request_at(Provider::Claude, click, after_click_cooldown),
+            RefreshDecision::CoolingDown(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_providers_are_governed_separately() {
+        let governor = RefreshGovernor::new();
+        let now = Instant::now();
+        let trigger = RefreshTrigger::MiddleClick;
+
+        assert_eq!(
+            governor.request_at(Provider::Claude, trigger, now),
+            RefreshDecision::Start
+        );
+        assert_eq!(
+            governor.request_at(Provider::Codex, trigger, now),
+            RefreshDecision::Start
+        );
+        assert_eq!(
+            governor.request_at(Provider::CodexWorkspace(1), RefreshTrigger::TrayClick, now),
+            RefreshDecision::Start
+        );
+        governor.finish(Provider::Codex);
+        assert_eq!(
+            governor.request_at(Provider::Claude, trigger, now),
+            RefreshDecision::Coalesced
+        );
+        assert_eq!(
+            governor.request_at(Provider::Codex, trigger, now),
+            RefreshDecision::Start
+        );
+    }
+
+    #[test]
+    fn test_slot_is_released_when_the_fetch_panics() {
+        let governor = Arc::new(RefreshGovernor::new());
+        let fetch = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let slot = governor.request(Provider::Claude, RefreshTrigger::Dbus);
+            assert!(slot.is_some());
+            assert!(governor
+                .request(Provider::Claude, RefreshTrigger::Dbus)
+                .is_none());
+            panic!("fetch failed");
+        }));
+        assert!(fetch.is_err());
+
+        let slot = governor.request(Provider::Claude, RefreshTrigger::Dbus);
+        assert!(slot.is_some());
+        drop(slot);
+        assert!(governor
+            .request(Provider::Claude, RefreshTrigger::TrayMenu)
+            .is_some());
+    }
+}
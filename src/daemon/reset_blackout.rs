@@ -0,0 +1,121 @@
+use crate::core::models::UsageSnapshot;
+use chrono::{DateTime, Duration, Utc};
+
+/// A scheduled poll due this close before a session reset waits it out.
+const RESET_LEAD_SECS: i64 = 90;
+/// How long after the reset polling resumes.
+const RESET_SETTLE_SECS: i64 = 30;
+
+/// The minutes around a session window reset in which the usage API can
+/// answer with figures from either side of it (0%, then the old value
+/// again) or fail outright. Scheduled polls skip it, and failures inside it
+/// don't change the provider's error state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResetBlackout {
+    resets_at: DateTime<Utc>,
+}
+
+impl ResetBlackout {
+    pub fn around(resets_at: DateTime<Utc>) -> Self {
+        Self { resets_at }
+    }
+
+    /// The blackout around `snapshot`'s session reset. An estimated reset
+    /// time is too rough to hold polls for.
+    pub fn for_snapshot(snapshot: &UsageSnapshot) -> Option<Self> {
+        let session = snapshot.primary.as_ref()?;
+        if session.resets_at_estimated {
+            return None;
+        }
+        session.resets_at.map(Self::around)
+    }
+
+    pub fn starts_at(&self) -> DateTime<Utc> {
+        self.resets_at - Duration::seconds(RESET_LEAD_SECS)
+    }
+
+    pub fn ends_at(&self) -> DateTime<Utc> {
+        self.resets_at + Duration::seconds(RESET_SETTLE_SECS)
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.starts_at() <= at && at < self.ends_at()
+    }
+
+    /// When a poll due at `due` runs: as due, unless that falls inside the
+    /// blackout, in which case once it is over.
+    pub fn defer(&self, due: DateTime<Utc>) -> DateTime<Utc> {
+        if self.contains(due) {
+            self.ends_at()
+        } else {
+            due
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::RateWindow;
+    use chrono::TimeZone;
+
+    fn reset() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 18, 15, 0, 0).unwrap()
+    }
+
+    fn at(offset_secs: i64) -> DateTime<Utc> {
+        reset() + Duration::seconds(offset_secs)
+    }
+
+    fn snapshot(resets_at: Option<DateTime<Utc>>, estimated: bool) -> UsageSnapshot {
+        let window = RateWindow {
+            resets_at_estimated: estimated,
+            ..RateWindow::from_reported(0.8, Some(300), resets_at, None)
+        };
+        UsageSnapshot::new(Some(window), None, at(-600))
+    }
+
+    #[test]
+    fn test_blackout_spans_the_reset() {
+        let blackout = ResetBlackout::around(reset());
+
+        assert!(!blackout.contains(at(-91)));
+        assert!(blackout.contains(at(-90)));
+        assert!(blackout.contains(at(0)));
+        assert!(blackout.contains(at(29)));
+        assert!(!blackout.contains(at(30)));
+    }
+
+    #[test]
+    fn test_polls_near_the_reset_wait_until_after_it() {
+        let blackout = ResetBlackout::around(reset());
+
+        // Polls every 60s from ten minutes before the reset: the one that
+        // would land 60s before it runs 30s after it instead.
+        let timeline: Vec<_> = (-10..=2)
+            .map(|minute| blackout.defer(at(minute * 60)))
+            .collect();
+        assert_eq!(timeline[8], at(-120));
+        assert_eq!(timeline[9], at(30));
+        assert_eq!(timeline[10], at(30));
+        assert_eq!(timeline[11], at(60));
+        assert!(timeline.iter().all(|due| !blackout.contains(*due)));
+
+        // Polls far from the reset are left alone.
+        assert_eq!(blackout.defer(at(-3600)), at(-3600));
+        assert_eq!(blackout.defer(at(3600)), at(3600));
+    }
+
+    #[test]
+    fn test_blackout_from_snapshot() {
+        assert_eq!(
+            ResetBlackout::for_snapshot(&snapshot(Some(reset()), false)),
+            Some(ResetBlackout::around(reset()))
+        );
+        assert_eq!(ResetBlackout::for_snapshot(&snapshot(None, false)), None);
+        assert_eq!(
+            ResetBlackout::for_snapshot(&snapshot(Some(reset()), true)),
+            None
+        );
+    }
+}
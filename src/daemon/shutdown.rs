@@ -0,0 +1,171 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// Asks the daemon's long-running tasks to stop. The tray's Quit item, the
+/// D-Bus `Quit` method and SIGTERM/SIGINT all trigger the same shutdown.
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: Arc<watch::Sender<bool>>,
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            requested: Arc::new(watch::channel(false).0),
+            tasks: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Safe to call more than once.
+    pub fn trigger(&self) {
+        self.requested.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Resolves once shutdown is triggered, immediately if it already was.
+    pub async fn triggered(&self) {
+        let mut requested = self.requested.subscribe();
+        let _ = requested.wait_for(|requested| *requested).await;
+    }
+
+    /// Runs `task` until it finishes or shutdown is triggered.
+    pub async fn run_until<F>(&self, task: F)
+    where
+        F: Future<Output = ()>,
+    {
+        tokio::select! {
+            () = task => {}
+            () = self.triggered() => {}
+        }
+    }
+
+    /// Spawns `task` to stop at shutdown; `join` waits for it.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let shutdown = self.clone();
+        self.track(tokio::spawn(async move { shutdown.run_until(task).await }));
+    }
+
+    /// Has `join` wait for a task that stops on its own at shutdown, such as
+    /// a supervisor whose task goes through `run_until`.
+    pub fn track(&self, handle: JoinHandle<()>) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            tasks.retain(|task| !task.is_finished());
+            tasks.push(handle);
+        }
+    }
+
+    /// Waits up to `timeout` for every tracked task to finish. Tasks still
+    /// running after that are aborted and `false` is returned.
+    pub async fn join(&self, timeout: Duration) -> bool {
+        let handles = match self.tasks.lock() {
+            Ok(mut tasks) => std::mem::take(&mut *tasks),
+            Err(_) => return false,
+        };
+        let aborts: Vec<_> = handles.iter().map(JoinHandle::abort_handle).collect();
+        let all_finished = async {
+            for handle in handles {
+                let _ = handle.await;
+            }
+        };
+        if tokio::time::timeout(timeout, all_finished).await.is_ok() {
+            return true;
+        }
+        for abort in aborts {
+            abort.abort();
+        }
+        false
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Triggers `shutdown` on SIGTERM (systemd stopping the unit) or SIGINT.
+pub async fn trigger_on_signals(shutdown: Shutdown) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigterm = match signal(SignalKind::terminate()) {
+        Ok(sigterm) => sigterm,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to listen for SIGTERM");
+            return;
+        }
+    };
+    tokio::select! {
+        _ = sigterm.recv() => tracing::info!("SIGTERM received; shutting down"),
+        _ = tokio::signal::ctrl_c() => tracing::info!("SIGINT received; shutting down"),
+    }
+    shutdown.trigger();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_all_loops_exit_after_trigger() {
+        let shutdown = Shutdown::new();
+        let stopped = Arc::new(AtomicUsize::new(0));
+
+        for _ in 0..3 {
+            let stopped = Arc::clone(&stopped);
+            shutdown.spawn(async move {
+                let _guard = CountOnDrop(stopped);
+                loop {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                }
+            });
+        }
+        // A loop that stops itself, the way supervised tasks do.
+        let inner = shutdown.clone();
+        let stopped_inner = Arc::clone(&stopped);
+        shutdown.track(tokio::spawn(async move {
+            inner.triggered().await;
+            stopped_inner.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        tokio::task::yield_now().await;
+        assert!(!shutdown.is_triggered());
+        shutdown.trigger();
+        shutdown.trigger();
+
+        assert!(shutdown.join(Duration::from_secs(1)).await);
+        assert_eq!(stopped.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_join_aborts_tasks_that_ignore_shutdown() {
+        let shutdown = Shutdown::new();
+        shutdown.track(tokio::spawn(async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        }));
+        shutdown.trigger();
+
+        assert!(!shutdown.join(Duration::from_millis(50)).await);
+        // Spawning after the trigger stops right away.
+        shutdown.spawn(std::future::pending());
+        assert!(shutdown.join(Duration::from_secs(1)).await);
+    }
+
+    struct CountOnDrop(Arc<AtomicUsize>);
+
+    impl Drop for CountOnDrop {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::{JoinError, JoinHandle};
+use tokio::time::Instant;
+
+/// How often a crashing task is restarted before the supervisor gives up.
+#[derive(Debug, Clone, Copy)]
+pub struct RestartPolicy {
+    pub max_restarts: usize,
+    pub window: Duration,
+    /// Multiplied by the number of crashes inside `window`.
+    pub backoff: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            window: Duration::from_secs(60),
+            backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Runs the task built by `make_task`, restarting it whenever it panics. A
+/// task that returns or is cancelled stays stopped. After more than
+/// `max_restarts` crashes within the window the task is left down and
+/// `on_give_up` is called once.
+///
+/// Aborting the returned handle also aborts the running task.
+pub fn supervise<F, Fut>(
+    name: &'static str,
+    on_give_up: impl FnOnce() + Send + 'static,
+    make_task: F,
+) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    supervise_with(RestartPolicy::default(), name, on_give_up, make_task)
+}
+
+pub fn supervise_with<F, Fut>(
+    policy: RestartPolicy,
+    name: &'static str,
+    on_give_up: impl FnOnce() + Send + 'static,
+    mut make_task: F,
+) -> JoinHandle<()>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut crashes: VecDeque<Instant> = VecDeque::new();
+        loop {
+            let mut task = AbortOnDrop(tokio::spawn(make_task()));
+            let error = match (&mut task.0).await {
+                Ok(()) => {
+                    tracing::debug!(task = name, "Supervised task finished");
+                    return;
+                }
+                Err(e) if e.is_cancelled() => return,
+                Err(e) => e,
+            };
+
+            let now = Instant::now();
+            crashes.push_back(now);
+            while crashes
+                .front()
+                .is_some_and(|crash| now.duration_since(*crash) > policy.window)
+            {
+                crashes.pop_front();
+            }
+
+            if crashes.len() > policy.max_restarts {
+                tracing::error!(
+                    task = name,
+                    crashes = crashes.len(),
+                    panic = %panic_message(error),
+                    "Task keeps crashing; giving up"
+                );
+                on_give_up();
+                return;
+            }
+
+            let backoff = policy.backoff * crashes.len() as u32;
+            tracing::error!(
+                task = name,
+                panic = %panic_message(error),
+                restart_in = ?backoff,
+                "Task panicked; restarting"
+            );
+            tokio::time::sleep(backoff).await;
+        }
+    })
+}
+
+/// Aborts the inner task when the supervisor itself is aborted.
+struct AbortOnDrop(JoinHandle<()>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+fn panic_message(error: JoinError) -> String {
+    let Ok(payload) = error.try_into_panic() else {
+        return "task failed".to_string();
+    };
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+
+    fn quick_policy() -> RestartPolicy {
+        RestartPolicy {
+            max_restarts: 5,
+            window: Duration::from_secs(60),
+            backoff: Duration::from_millis(1),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_panicking_scanner_recovers() {
+        let scans = Arc::new(AtomicUsize::new(0));
+        let gave_up = Arc::new(AtomicBool::new(false));
+        let (scan_tx, mut scan_rx) = mpsc::unbounded_channel();
+
+        let handle = supervise_with(
+            quick_policy(),
+            "cost scan",
+            {
+                let gave_up = Arc::clone(&gave_up);
+                move || gave_up.store(true, Ordering::SeqCst)
+            },
+            {
+                let scans = Arc::clone(&scans);
+                move || {
+                    let scans = Arc::clone(&scans);
+                    let scan_tx = scan_tx.clone();
+                    async move {
+                        loop {
+                            let scan = scans.fetch_add(1, Ordering::SeqCst) + 1;
+                            assert!(scan > 2, "NaN cost in scan {}", scan);
+                            scan_tx.send(scan).unwrap();
+                            tokio::time::sleep(Duration::from_millis(1)).await;
+                        }
+                    }
+                }
+            },
+        );
+
+        assert_eq!(scan_rx.recv().await, Some(3));
+        assert_eq!(scan_rx.recv().await, Some(4));
+        assert!(!gave_up.load(Ordering::SeqCst));
+
+        handle.abort();
+        assert!(handle.await.unwrap_err().is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_repeated_crashes() {
+        let starts = Arc::new(AtomicUsize::new(0));
+        let gave_up = Arc::new(AtomicBool::new(false));
+
+        let handle = supervise_with(
+            quick_policy(),
+            "tray events",
+            {
+                let gave_up = Arc::clone(&gave_up);
+                move || gave_up.store(true, Ordering::SeqCst)
+            },
+            {
+                let starts = Arc::clone(&starts);
+                move || {
+                    starts.fetch_add(1, Ordering::SeqCst);
+                    async { panic!("always broken") }
+                }
+            },
+        );
+
+        handle.await.unwrap();
+        assert!(gave_up.load(Ordering::SeqCst));
+        // The first run plus five restarts.
+        assert_eq!(starts.load(Ordering::SeqCst), 6);
+    }
+
+    #[tokio::test]
+    async fn test_finished_task_is_not_restarted() {
+        let starts = Arc::new(AtomicUsize::new(0));
+        let handle = supervise_with(quick_policy(), "pricing refresh", || {}, {
+            let starts = Arc::clone(&starts);
+            move || {
+                starts.fetch_add(1, Ordering::SeqCst);
+                async {}
+            }
+        });
+
+        handle.await.unwrap();
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+    }
+}
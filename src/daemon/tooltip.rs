@@ -0,0 +1,361 @@
+use crate::icons::IconState;
+use chrono::{DateTime, Utc};
+
+/// Tooltip markers for the window on the cycling bottom bar and the rest.
+const CYCLE_SHOWN: &str = "●";
+const CYCLE_HIDDEN: &str = "○";
+
+/// How a tray host renders tooltip descriptions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TooltipDialect {
+    /// Plain text broken at newlines, e.g. GNOME's AppIndicator extension.
+    #[default]
+    Plain,
+    /// KDE Plasma, which reads a small HTML subset and shows bare newlines
+    /// as nothing at all.
+    Plasma,
+}
+
+impl TooltipDialect {
+    /// The dialect for `XDG_CURRENT_DESKTOP`, a colon-separated list such as
+    /// "KDE" or "ubuntu:GNOME".
+    pub fn for_desktop(desktop: Option<&str>) -> Self {
+        let is_kde = desktop
+            .unwrap_or_default()
+            .split(':')
+            .any(|name| name.eq_ignore_ascii_case("kde"));
+        if is_kde {
+            TooltipDialect::Plasma
+        } else {
+            TooltipDialect::Plain
+        }
+    }
+
+    /// `for_desktop` for this process's environment.
+    pub fn detect() -> Self {
+        Self::for_desktop(std::env::var("XDG_CURRENT_DESKTOP").ok().as_deref())
+    }
+}
+
+/// What an icon's tooltip describes, apart from how the host renders it.
+pub struct TooltipData<'a> {
+    pub label: &'a str,
+    /// `display.tooltip_template`.
+    pub template: &'a str,
+    pub state: IconState,
+    /// Used fraction and reset time of each window.
+    pub session: (f64, Option<DateTime<Utc>>),
+    pub weekly: (f64, Option<DateTime<Utc>>),
+    pub extra_windows: &'a [(String, f64)],
+    /// The bottom bar's windows and the one shown, while it cycles.
+    pub cycle: Option<(Vec<(&'a str, f64)>, usize)>,
+    pub incident: bool,
+    pub budget_percent: Option<f64>,
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+/// The tooltip's lines: the state or the filled-in template (split at its
+/// " | " separators), reset countdowns unless the template has them, the
+/// other windows, the budget and the middle-click hint.
+fn tooltip_lines(data: &TooltipData, now: DateTime<Utc>) -> Vec<String> {
+    let mut lines = match data.state {
+        IconState::Loading => vec!["Loading...".to_string()],
+        IconState::Error => vec!["Authentication required".to_string()],
+        IconState::Stale => vec![stale_notice(data.retry_at, now)],
+        IconState::Normal => Vec::new(),
+    };
+    if matches!(data.state, IconState::Normal | IconState::Stale) {
+        let (session, session_resets_at) = data.session;
+        let (weekly, weekly_resets_at) = data.weekly;
+        let summary = data
+            .template
+            .replace("{provider}", data.label)
+            .replace("{session}", &format_percent(session))
+            .replace("{weekly}", &format_percent(weekly))
+            .replace("{reset_session}", &format_reset(session_resets_at, now))
+            .replace("{reset_weekly}", &format_reset(weekly_resets_at, now));
+        lines.extend(
+            summary
+                .split(['\n', '|'])
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string),
+        );
+        if !data.template.contains("{reset_") {
+            lines.extend(reset_line(session_resets_at, weekly_resets_at, now));
+        }
+        match &data.cycle {
+            Some((windows, shown)) => lines.extend(format_cycle(windows, *shown)),
+            None => {
+                lines.extend(data.extra_windows.iter().map(|(label, percent)| {
+                    format!("{}: {} used", label, format_percent(*percent))
+                }))
+            }
+        }
+    }
+    if data.incident {
+        if let Some(first) = lines.first_mut() {
+            first.push_str(" (incident)");
+        }
+    }
+    if let Some(percent) = data.budget_percent {
+        lines.push(format!("Budget: {:.0}%", percent));
+    }
+    lines.push("Middle-click to refresh".to_string());
+    lines
+}
+
+/// E.g. "Resets: session in 2h 14m, weekly in 3d 4h", leaving out windows
+/// whose reset time is unknown.
+fn reset_line(
+    session: Option<DateTime<Utc>>,
+    weekly: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let resets: Vec<String> = [("session", session), ("weekly", weekly)]
+        .into_iter()
+        .filter_map(|(name, resets_at)| {
+            let countdown = format_reset(Some(resets_at?), now);
+            Some(if countdown == "now" {
+                format!("{} now", name)
+            } else {
+                format!("{} in {}", name, countdown)
+            })
+        })
+        .collect();
+    (!resets.is_empty()).then(|| format!("Resets: {}", resets.join(", ")))
+}
+
+/// The tooltip description as `dialect` renders it: plain lines, or for
+/// Plasma escaped lines with bold labels joined by `<br/>`.
+pub fn tooltip_description(
+    data: &TooltipData,
+    dialect: TooltipDialect,
+    now: DateTime<Utc>,
+) -> String {
+    let lines = tooltip_lines(data, now);
+    match dialect {
+        TooltipDialect::Plain => lines.join("\n"),
+        TooltipDialect::Plasma => lines
+            .iter()
+            .map(|line| match line.split_once(": ") {
+                Some((label, rest)) => {
+                    format!("<b>{}:</b> {}", escape_markup(label), escape_markup(rest))
+                }
+                None => escape_markup(line),
+            })
+            .collect::<Vec<_>>()
+            .join("<br/>"),
+    }
+}
+
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn format_percent(percent: f64) -> String {
+    format!("{:.0}%", percent * 100.0)
+}
+
+/// E.g. "● Weekly: 18% used" and "○ Opus Weekly: 30% used", marking the
+/// window at `shown`.
+fn format_cycle(windows: &[(&str, f64)], shown: usize) -> Vec<String> {
+    windows
+        .iter()
+        .enumerate()
+        .map(|(position, (label, percent))| {
+            let marker = if position == shown {
+                CYCLE_SHOWN
+            } else {
+                CYCLE_HIDDEN
+            };
+            format!("{} {}: {} used", marker, label, format_percent(*percent))
+        })
+        .collect()
+}
+
+fn format_reset(resets_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> String {
+    let Some(resets_at) = resets_at else {
+        return "unknown".to_string();
+    };
+
+    let total_minutes = resets_at.signed_duration_since(now).num_minutes();
+    if total_minutes <= 0 {
+        return "now".to_string();
+    }
+
+    let days = total_minutes / (24 * 60);
+    let hours = (total_minutes % (24 * 60)) / 60;
+    let minutes = total_minutes % 60;
+
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {:02}m", hours, minutes)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// E.g. "Temporary API error, retrying in 45s".
+fn stale_notice(retry_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> String {
+    let seconds = retry_at.map_or(0, |retry_at| (retry_at - now).num_seconds());
+    let when = if seconds <= 0 {
+        "soon".to_string()
+    } else if seconds < 60 {
+        format!("in {}s", seconds)
+    } else if seconds % 60 == 0 {
+        format!("in {}m", seconds / 60)
+    } else {
+        format!("in {}m {}s", seconds / 60, seconds % 60)
+    };
+    format!("Temporary API error, retrying {}", when)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_reset() {
+        let now = Utc::now();
+        assert_eq!(format_reset(None, now), "unknown");
+        assert_eq!(
+            format_reset(Some(now - chrono::Duration::minutes(5)), now),
+            "now"
+        );
+        assert_eq!(
+            format_reset(Some(now + chrono::Duration::minutes(134)), now),
+            "2h 14m"
+        );
+    }
+
+    #[test]
+    fn test_stale_notice() {
+        let now = Utc::now();
+        let at = |seconds: i64| Some(now + chrono::Duration::seconds(seconds));
+        assert_eq!(
+            stale_notice(at(45), now),
+            "Temporary API error, retrying in 45s"
+        );
+        assert_eq!(
+            stale_notice(at(150), now),
+            "Temporary API error, retrying in 2m 30s"
+        );
+        assert_eq!(
+            stale_notice(at(300), now),
+            "Temporary API error, retrying in 5m"
+        );
+        assert_eq!(
+            stale_notice(at(-5), now),
+            "Temporary API error, retrying soon"
+        );
+        assert_eq!(
+            stale_notice(None, now),
+            "Temporary API error, retrying soon"
+        );
+    }
+
+    #[test]
+    fn test_format_cycle_marks_shown_window() {
+        let windows = [("Weekly", 0.18), ("Opus Weekly", 0.3)];
+        assert_eq!(
+            format_cycle(&windows, 1),
+            vec!["○ Weekly: 18% used", "● Opus Weekly: 30% used"]
+        );
+    }
+
+    fn data<'a>(extra_windows: &'a [(String, f64)], now: DateTime<Utc>) -> TooltipData<'a> {
+        TooltipData {
+            label: "Claude Code",
+            template: "Session: {session} used | Weekly: {weekly} used",
+            state: IconState::Normal,
+            session: (0.45, Some(now + chrono::Duration::minutes(134))),
+            weekly: (0.3, Some(now + chrono::Duration::hours(76))),
+            extra_windows,
+            cycle: None,
+            incident: false,
+            budget_percent: Some(71.0),
+            retry_at: None,
+        }
+    }
+
+    #[test]
+    fn test_tooltip_dialect_from_desktop() {
+        assert_eq!(
+            TooltipDialect::for_desktop(Some("KDE")),
+            TooltipDialect::Plasma
+        );
+        assert_eq!(
+            TooltipDialect::for_desktop(Some("ubuntu:GNOME")),
+            TooltipDialect::Plain
+        );
+        assert_eq!(
+            TooltipDialect::for_desktop(Some("X-Custom:kde")),
+            TooltipDialect::Plasma
+        );
+        assert_eq!(TooltipDialect::for_desktop(None), TooltipDialect::Plain);
+    }
+
+    #[test]
+    fn test_plain_tooltip_breaks_lines() {
+        let now = Utc::now();
+        let extra = [("Opus Weekly".to_string(), 0.2)];
+
+        assert_eq!(
+            tooltip_description(&data(&extra, now), TooltipDialect::Plain, now),
+            "Session: 45% used\n\
+             Weekly: 30% used\n\
+             Resets: session in 2h 14m, weekly in 3d 4h\n\
+             Opus Weekly: 20% used\n\
+             Budget: 71%\n\
+             Middle-click to refresh"
+        );
+
+        // A template with its own reset placeholders gets no extra line.
+        let mut custom = data(&[], now);
+        custom.template = "{provider} <{session}> resets {reset_session}";
+        custom.state = IconState::Stale;
+        custom.incident = true;
+        custom.budget_percent = None;
+        assert_eq!(
+            tooltip_description(&custom, TooltipDialect::Plain, now),
+            "Temporary API error, retrying soon (incident)\n\
+             Claude Code <45%> resets 2h 14m\n\
+             Middle-click to refresh"
+        );
+    }
+
+    #[test]
+    fn test_plasma_tooltip_uses_markup() {
+        let now = Utc::now();
+        let mut tooltip = data(&[], now);
+        tooltip.weekly.1 = None;
+
+        assert_eq!(
+            tooltip_description(&tooltip, TooltipDialect::Plasma, now),
+            "<b>Session:</b> 45% used<br/>\
+             <b>Weekly:</b> 30% used<br/>\
+             <b>Resets:</b> session in 2h 14m<br/>\
+             <b>Budget:</b> 71%<br/>\
+             Middle-click to refresh"
+        );
+
+        tooltip.template = "{provider} <{session}>";
+        tooltip.state = IconState::Loading;
+        assert_eq!(
+            tooltip_description(&tooltip, TooltipDialect::Plasma, now),
+            "Loading...<br/><b>Budget:</b> 71%<br/>Middle-click to refresh"
+        );
+        tooltip.state = IconState::Normal;
+        tooltip.budget_percent = None;
+        assert_eq!(
+            tooltip_description(&tooltip, TooltipDialect::Plasma, now),
+            "Claude Code &lt;45%&gt;<br/>\
+             <b>Resets:</b> session in 2h 14m<br/>\
+             Middle-click to refresh"
+        );
+    }
+}
@@ -1,46 +1,109 @@
-use crate::core::models::Provider;
+use crate::core::instance;
+use crate::core::models::{ErrorKind, Provider, TrayStatus};
+use crate::core::retry::RetryState;
 use crate::core::settings::Settings;
-use crate::core::settings::ThemeMode;
-use crate::icons::{IconRenderer, IconState};
-use ksni::{self, menu::StandardItem, Handle, MenuItem, Tray, TrayMethods};
+use crate::core::settings::{AnimationMode, IconMode, ThemeMode, TrayTitleMode, TrayWindow};
+use crate::daemon::tooltip::{format_percent, tooltip_description, TooltipData, TooltipDialect};
+use crate::daemon::tray_updates::{CoalescedTray, TraySink};
+use crate::icons::{
+    icon_theme_dir, named_icon_files, IconCache, IconKey, IconRenderer, IconState, NamedIcon,
+};
+use crate::ui::colors::ColorScheme;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use ksni::{self, menu::StandardItem, Handle, MenuItem, OfflineReason, Tray, TrayMethods};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use std::time::{Duration, Instant};
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, watch, RwLock};
 
-const ICON_SIZE: i32 = 22;
 const ANIMATION_FPS: u64 = 15;
 const ANIMATION_INTERVAL: Duration = Duration::from_millis(1000 / ANIMATION_FPS);
-const REFRESH_COOLDOWN: Duration = Duration::from_secs(5);
+/// Bar fill shown for the loading state while animations are off.
+const STATIC_LOADING_FRAME: (f64, f64) = (0.5, 0.5);
+/// Backoff between attempts to register icons that failed to register.
+const REGISTER_RETRY_BASE: Duration = Duration::from_secs(2);
+const REGISTER_RETRY_MAX: Duration = Duration::from_secs(120);
+const STATUS_NOTIFIER_WATCHER: &str = "org.kde.StatusNotifierWatcher";
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TrayEvent {
-    LeftClick(Provider),
+    LeftClick(Provider, Option<(i32, i32)>),
     RefreshRequested,
     OpenDashboard(Provider),
+    /// Refresh one provider right away (see `RefreshGovernor`).
+    MiddleClick(Provider),
     Quit,
 }
 
+/// Values for one tray icon: the two drawn bars plus every window the
+/// tooltip lists.
+#[derive(Debug, Clone, Default)]
+pub struct TrayUsage {
+    pub primary: f64,
+    pub secondary: f64,
+    pub session_percent: f64,
+    pub weekly_percent: f64,
+    pub session_resets_at: Option<DateTime<Utc>>,
+    pub weekly_resets_at: Option<DateTime<Utc>>,
+    pub extra_windows: Vec<(String, f64)>,
+}
+
+#[derive(Clone)]
 struct ClaudeBarTray {
     provider: Provider,
+    label: String,
     primary_percent: f64,
     secondary_percent: f64,
+    session_percent: f64,
+    weekly_percent: f64,
+    session_resets_at: Option<DateTime<Utc>>,
+    weekly_resets_at: Option<DateTime<Utc>>,
+    extra_windows: Vec<(String, f64)>,
+    /// What the bottom bar draws when it isn't cycling, e.g. "Weekly".
+    secondary_label: &'static str,
+    /// The window on the bottom bar while `display.icon_cycle_secs` is set:
+    /// 0 for `secondary_percent`, then each of `extra_windows`.
+    cycle_position: Option<usize>,
     state: IconState,
     animation_phase: f64,
+    animate: bool,
     has_credentials: bool,
+    /// The provider's status page reports an incident.
+    incident: bool,
+    /// Combined spend as a percentage of `cost.monthly_budget`.
+    budget_percent: Option<f64>,
+    /// When the next fetch runs while the icon is stale after a failure.
+    retry_at: Option<DateTime<Utc>>,
     theme_mode: ThemeMode,
+    color_scheme: ColorScheme,
+    title_mode: TrayTitleMode,
+    tooltip_template: String,
+    tooltip_dialect: TooltipDialect,
     system_is_dark: bool,
+    icon_mode: IconMode,
+    /// Where `install-icons` puts the named icons, for hosts that only look
+    /// in the installed themes.
+    icon_theme_path: String,
     merged_mode: bool,
-    providers: Vec<Provider>,
+    providers: Vec<(Provider, String)>,
+    /// Whether a StatusNotifierWatcher is around to show the icon.
+    host_visible: Arc<AtomicBool>,
+    icon_cache: Arc<IconCache>,
     event_tx: mpsc::UnboundedSender<TrayEvent>,
 }
 
 impl Tray for ClaudeBarTray {
     fn id(&self) -> String {
-        match self.provider {
-            Provider::Claude => "claude-bar-claude".to_string(),
-            Provider::Codex => "claude-bar-codex".to_string(),
-        }
+        format!(
+            "{}-{}",
+            instance::scoped("claude-bar", '-'),
+            self.provider.id()
+        )
     }
 
     fn category(&self) -> ksni::Category {
@@ -48,53 +111,77 @@ impl Tray for ClaudeBarTray {
     }
 
     fn title(&self) -> String {
-        self.provider.name().to_string()
+        format_title(
+            self.title_mode,
+            &self.label,
+            self.short_label(),
+            self.primary_percent,
+            self.shown_secondary(),
+        )
     }
 
-    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
-        let renderer = IconRenderer::new();
+    fn icon_theme_path(&self) -> String {
+        match self.icon_mode {
+            IconMode::Named => self.icon_theme_path.clone(),
+            IconMode::Pixmap => String::new(),
+        }
+    }
+
+    fn icon_name(&self) -> String {
+        match self.icon_mode {
+            IconMode::Named => {
+                NamedIcon::for_state(self.state, self.primary_percent, self.shown_secondary())
+                    .name(self.provider)
+            }
+            IconMode::Pixmap => String::new(),
+        }
+    }
 
-        let (primary, secondary) = if self.state == IconState::Loading {
+    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+        if self.icon_mode == IconMode::Named {
+            return Vec::new();
+        }
+        let (primary, secondary) = if self.state == IconState::Loading && self.animate {
             IconRenderer::knight_rider_frame(self.animation_phase)
+        } else if self.state == IconState::Loading {
+            STATIC_LOADING_FRAME
         } else {
-            (self.primary_percent, self.secondary_percent)
+            (self.primary_percent, self.shown_secondary())
+        };
+        let cycle = match self.cycle_position {
+            Some(position) if self.state != IconState::Loading => {
+                Some((position, self.cycle_len()))
+            }
+            _ => None,
         };
 
-        let pixels = renderer.render(
+        let key = IconKey::new(
             self.provider,
-            primary,
-            secondary,
+            (primary, secondary),
             self.state,
             self.is_dark(),
+            self.color_scheme,
+            cycle,
         );
-
-        vec![ksni::Icon {
-            width: ICON_SIZE,
-            height: ICON_SIZE,
-            data: argb_to_network_order(&pixels, ICON_SIZE as usize),
-        }]
+        self.icon_cache
+            .render(key)
+            .iter()
+            .map(|icon| ksni::Icon {
+                width: icon.size as i32,
+                height: icon.size as i32,
+                data: argb_to_network_order(&icon.rgba, icon.size as usize),
+            })
+            .collect()
     }
 
     fn tool_tip(&self) -> ksni::ToolTip {
-        let title = self.provider.name().to_string();
-        let description = match self.state {
-            IconState::Loading => "Loading...".to_string(),
-            IconState::Error => "Authentication required".to_string(),
-            IconState::Stale => format!(
-                "Session: {:.0}% used | Weekly: {:.0}% used (stale data)",
-                self.primary_percent * 100.0,
-                self.secondary_percent * 100.0
-            ),
-            IconState::Normal => format!(
-                "Session: {:.0}% used | Weekly: {:.0}% used",
-                self.primary_percent * 100.0,
-                self.secondary_percent * 100.0
-            ),
-        };
-
         ksni::ToolTip {
-            title,
-            description,
+            title: self.label.clone(),
+            description: tooltip_description(
+                &self.tooltip_data(),
+                self.tooltip_dialect,
+                Utc::now(),
+            ),
             icon_name: String::new(),
             icon_pixmap: Vec::new(),
         }
@@ -110,10 +197,10 @@ impl Tray for ClaudeBarTray {
         })];
 
         if self.merged_mode {
-            for provider in &self.providers {
+            for (provider, label) in &self.providers {
                 let provider = *provider;
                 items.push(MenuItem::Standard(StandardItem {
-                    label: format!("Open {} Dashboard", provider.name()),
+                    label: format!("Open {} Dashboard", label),
                     activate: Box::new(move |tray: &mut Self| {
                         let _ = tray.event_tx.send(TrayEvent::OpenDashboard(provider));
                     }),
@@ -122,7 +209,7 @@ impl Tray for ClaudeBarTray {
             }
         } else if self.has_credentials {
             items.push(MenuItem::Standard(StandardItem {
-                label: format!("Open {} Dashboard", self.provider.name()),
+                label: format!("Open {} Dashboard", self.label),
                 activate: Box::new(|tray: &mut Self| {
                     let _ = tray.event_tx.send(TrayEvent::OpenDashboard(tray.provider));
                 }),
@@ -143,11 +230,54 @@ impl Tray for ClaudeBarTray {
         items
     }
 
-    fn activate(&mut self, _x: i32, _y: i32) {
-        let _ = self.event_tx.send(TrayEvent::LeftClick(self.provider));
+    fn activate(&mut self, x: i32, y: i32) {
+        // Hosts that don't know the pointer position report (0, 0).
+        let click = (x != 0 || y != 0).then_some((x, y));
+        let _ = self.event_tx.send(TrayEvent::LeftClick(self.provider, click));
+    }
+
+    fn secondary_activate(&mut self, _x: i32, _y: i32) {
+        let _ = self.event_tx.send(TrayEvent::MiddleClick(self.provider));
+    }
+
+    fn watcher_online(&self) {
+        self.host_visible.store(true, Ordering::Relaxed);
+        tracing::debug!(provider = ?self.provider, "Tray host registered");
+    }
+
+    fn watcher_offline(&self, reason: OfflineReason) -> bool {
+        self.host_visible.store(false, Ordering::Relaxed);
+        tracing::debug!(provider = ?self.provider, reason = ?reason, "Tray host went away");
+        // Keep the service running so the icon comes back with the host.
+        true
     }
 }
 
+#[async_trait]
+impl TraySink<ClaudeBarTray> for Handle<ClaudeBarTray> {
+    async fn replace(&self, state: ClaudeBarTray) -> bool {
+        self.update(move |tray| *tray = state).await.is_some()
+    }
+}
+
+/// Hash of everything a host renders for the icon: the pixmap or icon
+/// name, title, tooltip and the menu's contents.
+fn rendered_fingerprint(tray: &ClaudeBarTray) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for icon in tray.icon_pixmap() {
+        icon.data.hash(&mut hasher);
+    }
+    tray.icon_name().hash(&mut hasher);
+    tray.title().hash(&mut hasher);
+    let tooltip = tray.tool_tip();
+    tooltip.title.hash(&mut hasher);
+    tooltip.description.hash(&mut hasher);
+    tray.has_credentials.hash(&mut hasher);
+    tray.merged_mode.hash(&mut hasher);
+    tray.providers.hash(&mut hasher);
+    hasher.finish()
+}
+
 impl ClaudeBarTray {
     fn is_dark(&self) -> bool {
         match self.theme_mode {
@@ -156,6 +286,97 @@ impl ClaudeBarTray {
             ThemeMode::System => self.system_is_dark,
         }
     }
+
+    fn tooltip_data(&self) -> TooltipData<'_> {
+        TooltipData {
+            label: &self.label,
+            template: &self.tooltip_template,
+            state: self.state,
+            session: (self.session_percent, self.session_resets_at),
+            weekly: (self.weekly_percent, self.weekly_resets_at),
+            extra_windows: &self.extra_windows,
+            cycle: self
+                .cycle_position
+                .filter(|_| self.cycle_len() > 1)
+                .map(|position| (self.cycle_windows(), position)),
+            incident: self.incident,
+            budget_percent: self.budget_percent,
+            retry_at: self.retry_at,
+        }
+    }
+
+    /// The windows the bottom bar cycles through, in order.
+    fn cycle_windows(&self) -> Vec<(&str, f64)> {
+        std::iter::once((self.secondary_label, self.secondary_percent))
+            .chain(
+                self.extra_windows
+                    .iter()
+                    .map(|(label, percent)| (label.as_str(), *percent)),
+            )
+            .collect()
+    }
+
+    fn cycle_len(&self) -> usize {
+        1 + self.extra_windows.len()
+    }
+
+    /// The bottom bar's value: the cycle's current window, if cycling.
+    fn shown_secondary(&self) -> f64 {
+        match self.cycle_position {
+            Some(position) if position > 0 => self
+                .extra_windows
+                .get(position - 1)
+                .map_or(self.secondary_percent, |(_, percent)| *percent),
+            _ => self.secondary_percent,
+        }
+    }
+
+    fn short_label(&self) -> &str {
+        match self.provider {
+            Provider::Claude => "Claude",
+            Provider::Codex | Provider::CodexWorkspace(_) => &self.label,
+        }
+    }
+}
+
+/// The window after `position` in a cycle of `len`, wrapping around.
+fn next_cycle_position(position: usize, len: usize) -> usize {
+    if len == 0 {
+        0
+    } else {
+        (position + 1) % len
+    }
+}
+
+fn format_title(
+    mode: TrayTitleMode,
+    label: &str,
+    short_label: &str,
+    primary: f64,
+    secondary: f64,
+) -> String {
+    match mode {
+        TrayTitleMode::Name => label.to_string(),
+        TrayTitleMode::Percent => format_percent(primary),
+        TrayTitleMode::Both => format!(
+            "{} {:.0}/{:.0}%",
+            short_label,
+            primary * 100.0,
+            secondary * 100.0
+        ),
+        TrayTitleMode::None => String::new(),
+    }
+}
+
+/// Rate limits, server errors and network trouble keep the last usage on a
+/// stale icon, so an outage doesn't read as a broken login. Anything else,
+/// or any failure before there is usage to keep, is an error.
+fn failure_state(kind: ErrorKind, has_usage: bool) -> IconState {
+    if kind.is_transient() && has_usage {
+        IconState::Stale
+    } else {
+        IconState::Error
+    }
 }
 
 fn argb_to_network_order(rgba: &[u8], size: usize) -> Vec<u8> {
@@ -179,22 +400,36 @@ struct TrayState {
     state: IconState,
     animation_phase: f64,
     has_credentials: bool,
-    last_refresh: Instant,
+    /// Usage has been drawn since the icon was created.
+    has_usage: bool,
+    /// The bottom bar's window and how many it cycles through.
+    cycle_position: usize,
+    cycle_len: usize,
+    host_visible: Arc<AtomicBool>,
     handle: Option<Handle<ClaudeBarTray>>,
+    updates: Option<CoalescedTray<ClaudeBarTray>>,
+    /// The icon's model while it is not registered yet; changes apply here
+    /// and are published once registration succeeds.
+    unregistered: Mutex<Option<ClaudeBarTray>>,
 }
 
 impl TrayState {
     fn sync_to_tray<F>(&self, updater: F)
     where
-        F: FnOnce(&mut ClaudeBarTray) + Send + 'static,
+        F: FnOnce(&mut ClaudeBarTray),
     {
-        if let Some(handle) = &self.handle {
-            let handle = handle.clone();
-            tokio::spawn(async move {
-                let _ = handle.update(updater).await;
-            });
+        if let Some(updates) = &self.updates {
+            updates.update(updater);
+        } else if let Some(tray) = self.lock_unregistered().as_mut() {
+            updater(tray);
         }
     }
+
+    fn lock_unregistered(&self) -> MutexGuard<'_, Option<ClaudeBarTray>> {
+        self.unregistered
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
 }
 
 impl Default for TrayState {
@@ -205,8 +440,13 @@ impl Default for TrayState {
             state: IconState::Loading,
             animation_phase: 0.0,
             has_credentials: false,
-            last_refresh: Instant::now() - REFRESH_COOLDOWN,
+            has_usage: false,
+            cycle_position: 0,
+            cycle_len: 1,
+            host_visible: Arc::new(AtomicBool::new(true)),
             handle: None,
+            updates: None,
+            unregistered: Mutex::new(None),
         }
     }
 }
@@ -215,7 +455,24 @@ struct TrayManagerInner {
     states: HashMap<Provider, TrayState>,
     merged_mode: bool,
     theme_mode: ThemeMode,
+    color_scheme: ColorScheme,
     system_is_dark: bool,
+    icon_mode: IconMode,
+    title_mode: TrayTitleMode,
+    tray_windows: [TrayWindow; 2],
+    tooltip_template: String,
+    animation_mode: AnimationMode,
+    system_animations: bool,
+    /// Time between bottom bar windows; zero while cycling is off.
+    icon_cycle: Duration,
+    budget_percent: Option<f64>,
+    icon_cache: Arc<IconCache>,
+}
+
+impl TrayManagerInner {
+    fn animations_enabled(&self) -> bool {
+        self.animation_mode.enabled(self.system_animations)
+    }
 }
 
 impl Default for TrayManagerInner {
@@ -224,7 +481,17 @@ impl Default for TrayManagerInner {
             states: HashMap::new(),
             merged_mode: false,
             theme_mode: ThemeMode::System,
+            color_scheme: ColorScheme::default(),
             system_is_dark: false,
+            icon_mode: IconMode::Pixmap,
+            title_mode: TrayTitleMode::Name,
+            tray_windows: [TrayWindow::Session, TrayWindow::Weekly],
+            tooltip_template: String::new(),
+            animation_mode: AnimationMode::Auto,
+            system_animations: true,
+            icon_cycle: Duration::ZERO,
+            budget_percent: None,
+            icon_cache: Arc::new(IconCache::default()),
         }
     }
 }
@@ -233,35 +500,94 @@ pub struct TrayManager {
     inner: Arc<RwLock<TrayManagerInner>>,
     event_tx: mpsc::UnboundedSender<TrayEvent>,
     event_rx: Arc<RwLock<Option<mpsc::UnboundedReceiver<TrayEvent>>>>,
+    loading_tx: watch::Sender<bool>,
+    cycle_tx: watch::Sender<Duration>,
+    /// Whether every icon is registered; false while retrying.
+    registered: Arc<AtomicBool>,
 }
 
 impl TrayManager {
     pub fn new() -> Self {
         let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let (loading_tx, _) = watch::channel(false);
+        let (cycle_tx, _) = watch::channel(Duration::ZERO);
         Self {
             inner: Arc::new(RwLock::new(TrayManagerInner::default())),
             event_tx,
             event_rx: Arc::new(RwLock::new(Some(event_rx))),
+            loading_tx,
+            cycle_tx,
+            registered: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// Shared flag behind `tray_status`, for the D-Bus service.
+    pub fn registered_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.registered)
+    }
+
+    /// Watches whether any icon is animating the loading state, so the
+    /// animation loop can sleep while nothing is, or animations are off.
+    pub fn subscribe_loading(&self) -> watch::Receiver<bool> {
+        self.loading_tx.subscribe()
+    }
+
+    fn publish_loading(&self, inner: &TrayManagerInner) {
+        let any_loading = inner.animations_enabled()
+            && inner
+                .states
+                .values()
+                .any(|state| state.state == IconState::Loading);
+        self.loading_tx.send_if_modified(|loading| {
+            let changed = *loading != any_loading;
+            *loading = any_loading;
+            changed
+        });
+    }
+
+    /// Watches `display.icon_cycle_secs`, so the cycle loop can sleep while
+    /// cycling is off.
+    pub fn subscribe_cycle(&self) -> watch::Receiver<Duration> {
+        self.cycle_tx.subscribe()
+    }
+
     pub async fn take_event_receiver(&self) -> Option<mpsc::UnboundedReceiver<TrayEvent>> {
         self.event_rx.write().await.take()
     }
 
+    /// Creates the icons and registers them with the tray host. Icons that
+    /// fail to register are retried in the background with backoff, and
+    /// right away when a StatusNotifierWatcher appears.
     pub async fn start(&self, settings: &Settings) -> anyhow::Result<()> {
+        self.create_icons(settings).await;
+        if register_pending(&self.inner).await {
+            self.registered.store(true, Ordering::Relaxed);
+        } else {
+            tracing::warn!("Tray unavailable; running without an icon and retrying");
+            self.registered.store(false, Ordering::Relaxed);
+            tokio::spawn(retry_registration(
+                Arc::clone(&self.inner),
+                Arc::clone(&self.registered),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn create_icons(&self, settings: &Settings) {
         let mut inner = self.inner.write().await;
         inner.merged_mode = settings.providers.merge_icons;
         inner.theme_mode = settings.theme.mode.clone();
+        inner.color_scheme = ColorScheme::new(settings.display.colorblind_mode);
         inner.system_is_dark = matches!(settings.theme.mode, ThemeMode::Dark);
+        inner.icon_mode = settings.display.icon_mode;
+        inner.title_mode = settings.display.tray_title;
+        inner.tray_windows = settings.display.tray_windows;
+        inner.tooltip_template = settings.display.tooltip_template.clone();
+        inner.animation_mode = settings.display.animations;
+        inner.icon_cycle = Duration::from_secs(settings.display.icon_cycle_secs);
+        self.cycle_tx.send_replace(inner.icon_cycle);
 
-        let mut enabled_providers = Vec::new();
-        if settings.providers.claude.enabled {
-            enabled_providers.push(Provider::Claude);
-        }
-        if settings.providers.codex.enabled {
-            enabled_providers.push(Provider::Codex);
-        }
+        let mut enabled_providers = settings.enabled_providers();
         if enabled_providers.is_empty() {
             enabled_providers.push(Provider::Claude);
         }
@@ -272,53 +598,101 @@ impl TrayManager {
             enabled_providers.clone()
         };
 
+        let data_dir = dirs::data_dir();
+        if inner.icon_mode == IconMode::Named
+            && data_dir
+                .as_deref()
+                .is_none_or(|dir| named_icon_files(dir).iter().any(|(path, _)| !path.exists()))
+        {
+            tracing::warn!("Named tray icons are not installed; run `claude-bar install-icons`");
+        }
+        let icon_theme_path = data_dir
+            .map(|dir| icon_theme_dir(&dir).display().to_string())
+            .unwrap_or_default();
+
+        let tooltip_dialect = TooltipDialect::detect();
+        tracing::debug!(?tooltip_dialect, "Tray tooltip dialect");
         for provider in providers_to_show {
+            let host_visible = Arc::new(AtomicBool::new(true));
             let tray = ClaudeBarTray {
                 provider,
+                label: settings.provider_label(provider),
                 primary_percent: 0.0,
                 secondary_percent: 0.0,
+                session_percent: 0.0,
+                weekly_percent: 0.0,
+                session_resets_at: None,
+                weekly_resets_at: None,
+                extra_windows: Vec::new(),
+                secondary_label: inner.tray_windows[1].label(),
+                cycle_position: (!inner.icon_cycle.is_zero()).then_some(0),
                 state: IconState::Loading,
                 animation_phase: 0.0,
+                animate: inner.animations_enabled(),
                 has_credentials: false,
+                incident: false,
+                budget_percent: inner.budget_percent,
+                retry_at: None,
                 theme_mode: inner.theme_mode.clone(),
+                color_scheme: inner.color_scheme,
+                title_mode: inner.title_mode,
+                tooltip_template: inner.tooltip_template.clone(),
+                tooltip_dialect,
                 system_is_dark: inner.system_is_dark,
+                icon_mode: inner.icon_mode,
+                icon_theme_path: icon_theme_path.clone(),
                 merged_mode: inner.merged_mode,
                 providers: if inner.merged_mode {
-                    enabled_providers.clone()
+                    enabled_providers
+                        .iter()
+                        .map(|p| (*p, settings.provider_label(*p)))
+                        .collect()
                 } else {
-                    vec![provider]
+                    vec![(provider, settings.provider_label(provider))]
                 },
+                host_visible: Arc::clone(&host_visible),
+                icon_cache: Arc::clone(&inner.icon_cache),
                 event_tx: self.event_tx.clone(),
             };
 
-            let handle = tray.spawn().await?;
-
             inner.states.insert(
                 provider,
                 TrayState {
-                    handle: Some(handle),
+                    host_visible,
+                    unregistered: Mutex::new(Some(tray)),
                     ..Default::default()
                 },
             );
-
-            tracing::info!(provider = ?provider, "Tray icon registered");
         }
 
-        Ok(())
+        self.publish_loading(&inner);
     }
 
-    pub async fn update_icon(&self, provider: Provider, primary: f64, secondary: f64) {
+    pub async fn update_icon(&self, provider: Provider, usage: TrayUsage) {
         let mut inner = self.inner.write().await;
+        let cycling = !inner.icon_cycle.is_zero();
         if let Some(state) = inner.states.get_mut(&provider) {
-            state.primary_percent = primary;
-            state.secondary_percent = secondary;
+            state.primary_percent = usage.primary;
+            state.secondary_percent = usage.secondary;
             state.state = IconState::Normal;
+            state.has_usage = true;
+            // A window that went away may have been the one shown.
+            state.cycle_len = 1 + usage.extra_windows.len();
+            state.cycle_position %= state.cycle_len;
+            let position = cycling.then_some(state.cycle_position);
             state.sync_to_tray(move |tray| {
-                tray.primary_percent = primary;
-                tray.secondary_percent = secondary;
+                tray.primary_percent = usage.primary;
+                tray.secondary_percent = usage.secondary;
+                tray.session_percent = usage.session_percent;
+                tray.weekly_percent = usage.weekly_percent;
+                tray.session_resets_at = usage.session_resets_at;
+                tray.weekly_resets_at = usage.weekly_resets_at;
+                tray.extra_windows = usage.extra_windows;
+                tray.cycle_position = position;
                 tray.state = IconState::Normal;
             });
         }
+        self.publish_loading(&inner);
     }
 
     pub async fn set_loading(&self, provider: Provider) {
@@ -331,6 +705,7 @@ impl TrayManager {
                 tray.animation_phase = 0.0;
             });
         }
+        self.publish_loading(&inner);
     }
 
     pub async fn set_error(&self, provider: Provider) {
@@ -356,17 +731,41 @@ impl TrayManager {
                 });
             }
         }
+        self.publish_loading(&inner);
+    }
+
+    /// Shows a failed fetch of `kind`; see `failure_state`.
+    pub async fn set_failed(
+        &self,
+        provider: Provider,
+        kind: ErrorKind,
+        retry_at: Option<DateTime<Utc>>,
+    ) {
+        let has_usage = self
+            .inner
+            .read()
+            .await
+            .states
+            .get(&provider)
+            .is_some_and(|state| state.has_usage);
+        match failure_state(kind, has_usage) {
+            IconState::Stale => self.set_stale(provider, retry_at).await,
+            _ => self.set_error(provider).await,
+        }
     }
 
-    #[allow(dead_code)]
-    pub async fn set_stale(&self, provider: Provider) {
+    /// Keeps the last usage on the icon after a failure that should pass
+    /// on its own, with the time of the next attempt for the tooltip.
+    pub async fn set_stale(&self, provider: Provider, retry_at: Option<DateTime<Utc>>) {
         let mut inner = self.inner.write().await;
         if let Some(state) = inner.states.get_mut(&provider) {
             state.state = IconState::Stale;
-            state.sync_to_tray(|tray| {
+            state.sync_to_tray(move |tray| {
                 tray.state = IconState::Stale;
+                tray.retry_at = retry_at;
             });
         }
+        self.publish_loading(&inner);
     }
 
     pub async fn set_credentials_valid(&self, provider: Provider, valid: bool) {
@@ -379,6 +778,26 @@ impl TrayManager {
         }
     }
 
+    pub async fn set_incident(&self, provider: Provider, incident: bool) {
+        let inner = self.inner.read().await;
+        if let Some(state) = inner.states.get(&provider) {
+            state.sync_to_tray(move |tray| {
+                tray.incident = incident;
+            });
+        }
+    }
+
+    /// Shown in every icon's tooltip; `None` hides it.
+    pub async fn set_budget_percent(&self, percent: Option<f64>) {
+        let mut inner = self.inner.write().await;
+        inner.budget_percent = percent;
+        for state in inner.states.values() {
+            state.sync_to_tray(move |tray| {
+                tray.budget_percent = percent;
+            });
+        }
+    }
+
     pub async fn set_system_is_dark(&self, is_dark: bool) {
         let mut inner = self.inner.write().await;
         inner.system_is_dark = is_dark;
@@ -389,6 +808,29 @@ impl TrayManager {
         }
     }
 
+    pub async fn set_animation_mode(&self, mode: AnimationMode) {
+        let mut inner = self.inner.write().await;
+        inner.animation_mode = mode;
+        self.apply_animations(&inner);
+    }
+
+    /// The desktop's enable-animations preference, used in `auto` mode.
+    pub async fn set_system_animations(&self, enabled: bool) {
+        let mut inner = self.inner.write().await;
+        inner.system_animations = enabled;
+        self.apply_animations(&inner);
+    }
+
+    fn apply_animations(&self, inner: &TrayManagerInner) {
+        let animate = inner.animations_enabled();
+        for state in inner.states.values() {
+            state.sync_to_tray(move |tray| {
+                tray.animate = animate;
+            });
+        }
+        self.publish_loading(inner);
+    }
+
     pub async fn set_theme_mode(&self, theme_mode: ThemeMode) {
         let mut inner = self.inner.write().await;
         inner.theme_mode = theme_mode.clone();
@@ -400,43 +842,106 @@ impl TrayManager {
         }
     }
 
+    pub async fn set_color_scheme(&self, scheme: ColorScheme) {
+        let mut inner = self.inner.write().await;
+        inner.color_scheme = scheme;
+        for state in inner.states.values() {
+            state.sync_to_tray(move |tray| {
+                tray.color_scheme = scheme;
+            });
+        }
+    }
+
+    pub async fn tray_windows(&self) -> [TrayWindow; 2] {
+        self.inner.read().await.tray_windows
+    }
+
+    pub async fn set_display(
+        &self,
+        title_mode: TrayTitleMode,
+        tray_windows: [TrayWindow; 2],
+        tooltip_template: String,
+    ) {
+        let mut inner = self.inner.write().await;
+        inner.title_mode = title_mode;
+        inner.tray_windows = tray_windows;
+        inner.tooltip_template = tooltip_template.clone();
+        let secondary_label = tray_windows[1].label();
+        for state in inner.states.values() {
+            let template = tooltip_template.clone();
+            state.sync_to_tray(move |tray| {
+                tray.title_mode = title_mode;
+                tray.tooltip_template = template;
+                tray.secondary_label = secondary_label;
+            });
+        }
+    }
+
+    /// Cycles the bottom bar every `secs`; 0 puts it back on its own window.
+    pub async fn set_icon_cycle(&self, secs: u64) {
+        let mut inner = self.inner.write().await;
+        inner.icon_cycle = Duration::from_secs(secs);
+        let cycling = secs > 0;
+        for state in inner.states.values_mut() {
+            if !cycling {
+                state.cycle_position = 0;
+            }
+            let position = cycling.then_some(state.cycle_position);
+            state.sync_to_tray(move |tray| {
+                tray.cycle_position = position;
+            });
+        }
+        self.cycle_tx.send_replace(inner.icon_cycle);
+    }
+
+    /// Moves every icon's bottom bar to its next window, except icons that
+    /// are loading or failed, which keep theirs until they have usage again.
+    pub async fn tick_cycle(&self) {
+        let mut inner = self.inner.write().await;
+        if inner.icon_cycle.is_zero() {
+            return;
+        }
+        for state in inner.states.values_mut() {
+            if matches!(state.state, IconState::Loading | IconState::Error) || state.cycle_len < 2 {
+                continue;
+            }
+            state.cycle_position = next_cycle_position(state.cycle_position, state.cycle_len);
+            let position = state.cycle_position;
+            state.sync_to_tray(move |tray| {
+                tray.cycle_position = Some(position);
+            });
+        }
+    }
+
     pub async fn tick_animation(&self) -> bool {
         let mut inner = self.inner.write().await;
         let mut updated = false;
         for state in inner.states.values_mut() {
-            if state.state == IconState::Loading {
+            if state.state != IconState::Loading {
+                continue;
+            }
+            // Keep reporting the icon as animating so the loop stays alive
+            // until the host is back, but send it nothing meanwhile.
+            updated = true;
+            if state.host_visible.load(Ordering::Relaxed) {
                 state.animation_phase += std::f64::consts::PI / 30.0;
                 let phase = state.animation_phase;
                 state.sync_to_tray(move |tray| {
                     tray.animation_phase = phase;
                 });
-                updated = true;
             }
         }
         updated
     }
 
-    pub async fn should_refresh(&self, provider: Provider) -> bool {
-        let inner = self.inner.read().await;
-        inner
-            .states
-            .get(&provider)
-            .map(|s| s.last_refresh.elapsed() >= REFRESH_COOLDOWN)
-            .unwrap_or(true)
-    }
-
-    pub async fn mark_refreshed(&self, provider: Provider) {
-        let mut inner = self.inner.write().await;
-        if let Some(state) = inner.states.get_mut(&provider) {
-            state.last_refresh = Instant::now();
-        }
-    }
-
-    #[allow(dead_code)]
     pub async fn is_merged_mode(&self) -> bool {
         self.inner.read().await.merged_mode
     }
 
+    pub async fn icon_mode(&self) -> IconMode {
+        self.inner.read().await.icon_mode
+    }
+
     pub async fn shutdown(&self) {
         let handles: Vec<Handle<ClaudeBarTray>> = {
             let mut inner = self.inner.write().await;
@@ -447,6 +952,7 @@ impl TrayManager {
                 }
             }
             inner.states.clear();
+            self.publish_loading(&inner);
             handles
         };
 
@@ -458,7 +964,6 @@ impl TrayManager {
         tracing::info!(shutdown_count, "Tray icons shut down");
     }
 
-    #[allow(dead_code)]
     pub async fn apply_settings(&self, settings: &Settings) -> anyhow::Result<()> {
         self.shutdown().await;
         self.start(settings).await
@@ -471,6 +976,128 @@ impl Default for TrayManager {
     }
 }
 
+/// Registers every icon that isn't yet. Returns whether all icons are now
+/// registered.
+async fn register_pending(inner: &RwLock<TrayManagerInner>) -> bool {
+    let pending: Vec<(Provider, ClaudeBarTray)> = inner
+        .read()
+        .await
+        .states
+        .iter()
+        .filter_map(|(provider, state)| {
+            let tray = state.lock_unregistered().clone()?;
+            Some((*provider, tray))
+        })
+        .collect();
+
+    let mut all_registered = true;
+    for (provider, tray) in pending {
+        let model = tray.clone();
+        let handle = match tray.spawn().await {
+            Ok(handle) => handle,
+            Err(e) => {
+                tracing::warn!(provider = ?provider, error = %e, "Failed to register tray icon");
+                all_registered = false;
+                continue;
+            }
+        };
+
+        let mut inner = inner.write().await;
+        // Take whatever changed while spawning; gone if the icons were shut
+        // down or another attempt got there first.
+        let Some(state) = inner.states.get_mut(&provider) else {
+            drop(inner);
+            handle.shutdown().await;
+            continue;
+        };
+        let Some(latest) = state.lock_unregistered().take() else {
+            drop(inner);
+            handle.shutdown().await;
+            continue;
+        };
+        let updates =
+            CoalescedTray::spawn(provider.id(), model, handle.clone(), rendered_fingerprint);
+        updates.update(move |tray| *tray = latest);
+        state.handle = Some(handle);
+        state.updates = Some(updates);
+
+        tracing::info!(provider = ?provider, "Tray icon registered");
+    }
+    all_registered
+}
+
+async fn retry_registration(inner: Arc<RwLock<TrayManagerInner>>, registered: Arc<AtomicBool>) {
+    let mut retry = RetryState::with_delays(REGISTER_RETRY_BASE, REGISTER_RETRY_MAX);
+    let watcher = watcher_proxy().await;
+    let mut watcher_owner = match &watcher {
+        Some(proxy) => proxy.receive_owner_changed().await.ok(),
+        None => None,
+    };
+
+    loop {
+        retry.record_failure();
+        let delay = retry.current_delay();
+        tracing::debug!(
+            retry_in_secs = delay.as_secs(),
+            "Retrying tray registration"
+        );
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = watcher_appeared(&mut watcher_owner) => {
+                tracing::debug!("StatusNotifierWatcher appeared; retrying tray registration");
+            }
+        }
+
+        if inner.read().await.states.is_empty() {
+            return;
+        }
+        if register_pending(&inner).await {
+            registered.store(true, Ordering::Relaxed);
+            tracing::info!(
+                attempts = retry.consecutive_failures(),
+                "Tray icons registered after retrying"
+            );
+            return;
+        }
+    }
+}
+
+/// A proxy for the tray host's watcher, used to notice when it starts.
+async fn watcher_proxy() -> Option<zbus::Proxy<'static>> {
+    let connection = zbus::Connection::session().await.ok()?;
+    zbus::Proxy::new(
+        &connection,
+        STATUS_NOTIFIER_WATCHER,
+        "/StatusNotifierWatcher",
+        STATUS_NOTIFIER_WATCHER,
+    )
+    .await
+    .ok()
+}
+
+/// Resolves when the watcher name gains an owner; never without a stream.
+async fn watcher_appeared(owner_changes: &mut Option<zbus::proxy::OwnerChangedStream<'_>>) {
+    if let Some(stream) = owner_changes {
+        while let Some(owner) = stream.next().await {
+            if owner.is_some() {
+                return;
+            }
+        }
+        *owner_changes = None;
+    }
+    std::future::pending::<()>().await
+}
+
+/// What `claude-bar status` reports for the tray.
+pub fn tray_status(registered: &AtomicBool) -> TrayStatus {
+    if registered.load(Ordering::Relaxed) {
+        TrayStatus::Registered
+    } else {
+        TrayStatus::Unavailable
+    }
+}
+
 async fn shutdown_all_handles<T, F, Fut>(handles: Vec<T>, mut shutdown: F)
 where
     F: FnMut(T) -> Fut,
@@ -482,13 +1109,55 @@ where
 }
 
 pub async fn run_animation_loop(tray_manager: Arc<TrayManager>) {
-    let mut interval = tokio::time::interval(ANIMATION_INTERVAL);
+    let mut loading_rx = tray_manager.subscribe_loading();
 
     loop {
-        interval.tick().await;
-        if !tray_manager.tick_animation().await {
-            tokio::time::sleep(Duration::from_millis(500)).await;
-            interval = tokio::time::interval(ANIMATION_INTERVAL);
+        if loading_rx.wait_for(|loading| *loading).await.is_err() {
+            return;
+        }
+
+        let started = Instant::now();
+        let mut frames: u64 = 0;
+        let mut interval = tokio::time::interval(ANIMATION_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            if !tray_manager.tick_animation().await {
+                break;
+            }
+            frames = frames.saturating_add(1);
+        }
+
+        tracing::debug!(
+            component = "tray-animation",
+            frames,
+            active_secs = started.elapsed().as_secs_f64(),
+            "Animation paused; no icons loading"
+        );
+    }
+}
+
+/// Steps the bottom bars through their windows every
+/// `display.icon_cycle_secs`, and sleeps while that is 0.
+pub async fn run_cycle_loop(tray_manager: Arc<TrayManager>) {
+    let mut cycle_rx = tray_manager.subscribe_cycle();
+
+    loop {
+        let period = *cycle_rx.borrow_and_update();
+        if period.is_zero() {
+            if cycle_rx.changed().await.is_err() {
+                return;
+            }
+            continue;
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(period) => tray_manager.tick_cycle().await,
+            changed = cycle_rx.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+            }
         }
     }
 }
@@ -506,12 +1175,239 @@ mod tests {
         assert_eq!(argb, vec![200, 255, 128, 64]);
     }
 
+    #[test]
+    fn test_format_title_modes() {
+        assert_eq!(
+            format_title(TrayTitleMode::Name, "Claude Code", "Claude", 0.45, 0.62),
+            "Claude Code"
+        );
+        assert_eq!(
+            format_title(TrayTitleMode::Percent, "Claude Code", "Claude", 0.45, 0.62),
+            "45%"
+        );
+        assert_eq!(
+            format_title(TrayTitleMode::Both, "Claude Code", "Claude", 0.45, 0.62),
+            "Claude 45/62%"
+        );
+        assert_eq!(
+            format_title(TrayTitleMode::None, "Codex", "Codex", 0.45, 0.62),
+            ""
+        );
+        assert_eq!(
+            format_title(TrayTitleMode::Both, "Team", "Team", 0.1, 0.2),
+            "Team 10/20%"
+        );
+    }
+
+    #[test]
+    fn test_failure_state_by_error_class() {
+        let state_for =
+            |status: u16, has_usage| failure_state(ErrorKind::from_status(status), has_usage);
+        assert_eq!(state_for(401, true), IconState::Error);
+        assert_eq!(state_for(403, true), IconState::Error);
+        assert_eq!(state_for(429, true), IconState::Stale);
+        assert_eq!(state_for(500, true), IconState::Stale);
+        assert_eq!(state_for(503, true), IconState::Stale);
+        assert_eq!(failure_state(ErrorKind::Network, true), IconState::Stale);
+        assert_eq!(failure_state(ErrorKind::Other, true), IconState::Error);
+
+        // Nothing to keep yet.
+        assert_eq!(state_for(429, false), IconState::Error);
+        assert_eq!(state_for(503, false), IconState::Error);
+        assert_eq!(failure_state(ErrorKind::Network, false), IconState::Error);
+    }
+
+    async fn icon_state(manager: &TrayManager, provider: Provider) -> IconState {
+        manager.inner.read().await.states[&provider].state
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_keep_usage() {
+        let manager = TrayManager::new();
+        // A second icon, so one in error isn't reset as "every icon failed".
+        for provider in [Provider::Claude, Provider::Codex] {
+            manager
+                .inner
+                .write()
+                .await
+                .states
+                .insert(provider, TrayState::default());
+        }
+
+        manager
+            .set_failed(Provider::Claude, ErrorKind::Server, None)
+            .await;
+        assert_eq!(
+            icon_state(&manager, Provider::Claude).await,
+            IconState::Error
+        );
+
+        let usage = TrayUsage {
+            primary: 0.4,
+            ..TrayUsage::default()
+        };
+        manager.update_icon(Provider::Claude, usage).await;
+        // The store drops the snapshot on failure; the icon still has it.
+        for _ in 0..2 {
+            manager
+                .set_failed(Provider::Claude, ErrorKind::RateLimited, None)
+                .await;
+            assert_eq!(
+                icon_state(&manager, Provider::Claude).await,
+                IconState::Stale
+            );
+        }
+        assert_eq!(
+            manager.inner.read().await.states[&Provider::Claude].primary_percent,
+            0.4
+        );
+
+        manager
+            .set_failed(Provider::Claude, ErrorKind::Auth, None)
+            .await;
+        assert_eq!(
+            icon_state(&manager, Provider::Claude).await,
+            IconState::Error
+        );
+    }
+
+    #[test]
+    fn test_next_cycle_position() {
+        assert_eq!(next_cycle_position(0, 2), 1);
+        assert_eq!(next_cycle_position(1, 2), 0);
+        assert_eq!(next_cycle_position(0, 0), 0);
+    }
+
+    #[tokio::test]
+    async fn test_cycle_skips_loading_and_failed_icons() {
+        let manager = TrayManager::new();
+        // A second icon, so one in error isn't reset as "every icon failed".
+        for provider in [Provider::Claude, Provider::Codex] {
+            manager
+                .inner
+                .write()
+                .await
+                .states
+                .insert(provider, TrayState::default());
+        }
+        let position = |manager: &TrayManager| {
+            let inner = manager.inner.try_read().unwrap();
+            inner.states[&Provider::Claude].cycle_position
+        };
+        let usage = TrayUsage {
+            extra_windows: vec![("Opus".to_string(), 0.3), ("Sonnet".to_string(), 0.1)],
+            ..TrayUsage::default()
+        };
+
+        // Off by default.
+        manager.update_icon(Provider::Claude, usage.clone()).await;
+        manager.tick_cycle().await;
+        assert_eq!(position(&manager), 0);
+
+        manager.set_icon_cycle(5).await;
+        assert_eq!(*manager.subscribe_cycle().borrow(), Duration::from_secs(5));
+        manager.tick_cycle().await;
+        manager.tick_cycle().await;
+        assert_eq!(position(&manager), 2);
+
+        manager.set_loading(Provider::Claude).await;
+        manager.tick_cycle().await;
+        assert_eq!(position(&manager), 2);
+        manager.set_error(Provider::Claude).await;
+        manager.tick_cycle().await;
+        assert_eq!(position(&manager), 2);
+
+        manager.update_icon(Provider::Claude, usage).await;
+        manager.tick_cycle().await;
+        assert_eq!(position(&manager), 0);
+
+        // The shown window went away.
+        manager.tick_cycle().await;
+        manager
+            .update_icon(Provider::Claude, TrayUsage::default())
+            .await;
+        assert_eq!(position(&manager), 0);
+
+        manager.set_icon_cycle(0).await;
+        assert!(manager.subscribe_cycle().borrow().is_zero());
+    }
+
     #[tokio::test]
     async fn test_tray_manager_creation() {
         let manager = TrayManager::new();
         assert!(!manager.is_merged_mode().await);
     }
 
+    #[tokio::test]
+    async fn test_loading_watch_tracks_icon_states() {
+        let manager = TrayManager::new();
+        let loading_rx = manager.subscribe_loading();
+        manager
+            .inner
+            .write()
+            .await
+            .states
+            .insert(Provider::Claude, TrayState::default());
+
+        manager.set_loading(Provider::Claude).await;
+        assert!(*loading_rx.borrow());
+
+        manager
+            .update_icon(Provider::Claude, TrayUsage::default())
+            .await;
+        assert!(!*loading_rx.borrow());
+    }
+
+    #[tokio::test]
+    async fn test_loading_watch_stays_idle_without_animations() {
+        let manager = TrayManager::new();
+        let loading_rx = manager.subscribe_loading();
+        manager
+            .inner
+            .write()
+            .await
+            .states
+            .insert(Provider::Claude, TrayState::default());
+
+        manager.set_system_animations(false).await;
+        manager.set_loading(Provider::Claude).await;
+        assert!(!*loading_rx.borrow());
+
+        manager.set_animation_mode(AnimationMode::On).await;
+        assert!(*loading_rx.borrow());
+    }
+
+    #[tokio::test]
+    async fn test_middle_click_sends_refresh_event() {
+        let manager = TrayManager::new();
+        let mut events = manager.take_event_receiver().await.unwrap();
+        manager.create_icons(&Settings::default()).await;
+
+        let mut tray = manager.inner.read().await.states[&Provider::Claude]
+            .lock_unregistered()
+            .clone()
+            .unwrap();
+        assert!(tray
+            .tool_tip()
+            .description
+            .ends_with("Middle-click to refresh"));
+
+        tray.secondary_activate(0, 0);
+        assert_eq!(
+            events.try_recv().unwrap(),
+            TrayEvent::MiddleClick(Provider::Claude)
+        );
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_tray_status_follows_registration() {
+        let registered = AtomicBool::new(false);
+        assert_eq!(tray_status(&registered), TrayStatus::Unavailable);
+        registered.store(true, Ordering::Relaxed);
+        assert_eq!(tray_status(&registered), TrayStatus::Registered);
+    }
+
     #[tokio::test]
     async fn test_shutdown_all_handles_invokes_every_handle_once() {
         let called = Arc::new(AtomicUsize::new(0));
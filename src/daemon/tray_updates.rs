@@ -0,0 +1,271 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Minimum time between two updates published for the same icon.
+pub const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+/// How often the emitted update rate is logged while an icon is changing.
+const RATE_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where coalesced icon state gets published; the ksni handle in the daemon.
+#[async_trait]
+pub trait TraySink<T>: Send + Sync + 'static {
+    /// Replaces the published state. Returns `false` once the icon is gone.
+    async fn replace(&self, state: T) -> bool;
+}
+
+struct Pending<T> {
+    model: T,
+    dirty: bool,
+    closed: bool,
+}
+
+struct Shared<T> {
+    pending: Mutex<Pending<T>>,
+    notify: Notify,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, Pending<T>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A shadow copy of one icon's state. Changes apply to it immediately and are
+/// published at most once per flush interval, skipping states whose
+/// fingerprint matches the last one published. Publishing stops on drop.
+pub struct CoalescedTray<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Clone + Send + 'static> CoalescedTray<T> {
+    /// `model` is what the sink already shows; `fingerprint` hashes everything
+    /// the host would render from a state.
+    pub fn spawn(
+        name: String,
+        model: T,
+        sink: impl TraySink<T>,
+        fingerprint: fn(&T) -> u64,
+    ) -> Self {
+        Self::spawn_with_interval(name, model, sink, fingerprint, FLUSH_INTERVAL)
+    }
+
+    fn spawn_with_interval(
+        name: String,
+        model: T,
+        sink: impl TraySink<T>,
+        fingerprint: fn(&T) -> u64,
+        interval: Duration,
+    ) -> Self {
+        let published = fingerprint(&model);
+        let shared = Arc::new(Shared {
+            pending: Mutex::new(Pending {
+                model,
+                dirty: false,
+                closed: false,
+            }),
+            notify: Notify::new(),
+        });
+        tokio::spawn(flush_loop(
+            name,
+            Arc::clone(&shared),
+            sink,
+            fingerprint,
+            interval,
+            published,
+        ));
+        Self { shared }
+    }
+
+    pub fn update(&self, updater: impl FnOnce(&mut T)) {
+        let mut pending = self.shared.lock();
+        if pending.closed {
+            return;
+        }
+        updater(&mut pending.model);
+        pending.dirty = true;
+        drop(pending);
+        self.shared.notify.notify_one();
+    }
+}
+
+impl<T> Drop for CoalescedTray<T> {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.notify.notify_one();
+    }
+}
+
+async fn flush_loop<T: Clone>(
+    name: String,
+    shared: Arc<Shared<T>>,
+    sink: impl TraySink<T>,
+    fingerprint: fn(&T) -> u64,
+    interval: Duration,
+    mut published: u64,
+) {
+    let mut rate = UpdateRate::new();
+    loop {
+        shared.notify.notified().await;
+        let state = {
+            let mut pending = shared.lock();
+            if pending.closed {
+                break;
+            }
+            if !pending.dirty {
+                continue;
+            }
+            pending.dirty = false;
+            pending.model.clone()
+        };
+
+        let rendered = fingerprint(&state);
+        if rendered == published {
+            rate.skipped += 1;
+        } else {
+            if !sink.replace(state).await {
+                break;
+            }
+            published = rendered;
+            rate.emitted += 1;
+        }
+        if rate.started.elapsed() >= RATE_LOG_INTERVAL {
+            rate.log(&name);
+            rate = UpdateRate::new();
+        }
+        tokio::time::sleep(interval).await;
+    }
+    rate.log(&name);
+}
+
+struct UpdateRate {
+    started: Instant,
+    emitted: u64,
+    skipped: u64,
+}
+
+impl UpdateRate {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            emitted: 0,
+            skipped: 0,
+        }
+    }
+
+    fn log(&self, name: &str) {
+        if self.emitted == 0 && self.skipped == 0 {
+            return;
+        }
+        let secs = self.started.elapsed().as_secs_f64().max(1.0);
+        tracing::debug!(
+            component = "tray-updates",
+            icon = name,
+            emitted = self.emitted,
+            skipped = self.skipped,
+            per_sec = self.emitted as f64 / secs,
+            "Tray update rate"
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const INTERVAL: Duration = Duration::from_millis(20);
+
+    #[derive(Clone, Default)]
+    struct MockSink {
+        published: Arc<Mutex<Vec<(u32, u32)>>>,
+        gone: Arc<AtomicBool>,
+    }
+
+    impl MockSink {
+        fn published(&self) -> Vec<(u32, u32)> {
+            self.published.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl TraySink<(u32, u32)> for MockSink {
+        async fn replace(&self, state: (u32, u32)) -> bool {
+            if self.gone.load(Ordering::SeqCst) {
+                return false;
+            }
+            self.published.lock().unwrap().push(state);
+            true
+        }
+    }
+
+    /// Only the first field is drawn; the second never reaches the host.
+    fn drawn(state: &(u32, u32)) -> u64 {
+        u64::from(state.0)
+    }
+
+    fn spawn(sink: &MockSink) -> CoalescedTray<(u32, u32)> {
+        CoalescedTray::spawn_with_interval(
+            "test".to_string(),
+            (0, 0),
+            sink.clone(),
+            drawn,
+            INTERVAL,
+        )
+    }
+
+    async fn settle() {
+        tokio::time::sleep(INTERVAL * 5).await;
+    }
+
+    #[tokio::test]
+    async fn test_burst_publishes_first_and_latest_state() {
+        let sink = MockSink::default();
+        let tray = spawn(&sink);
+
+        for n in 1..=50 {
+            tray.update(|state| state.0 = n);
+        }
+        settle().await;
+
+        let published = sink.published();
+        assert!(published.len() <= 2, "{:?}", published);
+        assert_eq!(published.last(), Some(&(50, 0)));
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_rendering_is_skipped() {
+        let sink = MockSink::default();
+        let tray = spawn(&sink);
+
+        tray.update(|state| state.1 = 7);
+        settle().await;
+        assert!(sink.published().is_empty());
+
+        tray.update(|state| state.0 = 3);
+        settle().await;
+        tray.update(|state| state.0 = 3);
+        settle().await;
+        assert_eq!(sink.published(), vec![(3, 7)]);
+    }
+
+    #[tokio::test]
+    async fn test_stops_after_drop_or_when_sink_is_gone() {
+        let sink = MockSink::default();
+        let tray = spawn(&sink);
+        tray.update(|state| state.0 = 1);
+        settle().await;
+        drop(tray);
+        assert_eq!(sink.published(), vec![(1, 0)]);
+
+        let tray = spawn(&sink);
+        sink.gone.store(true, Ordering::SeqCst);
+        tray.update(|state| state.0 = 2);
+        settle().await;
+        sink.gone.store(false, Ordering::SeqCst);
+        tray.update(|state| state.0 = 3);
+        settle().await;
+        assert_eq!(sink.published(), vec![(1, 0)]);
+    }
+}
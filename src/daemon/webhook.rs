@@ -0,0 +1,403 @@
+use crate::core::lockouts::LockoutLog;
+use crate::core::models::CostSnapshot;
+use crate::core::settings::{Settings, WebhookSettings};
+use crate::core::status_output::{DaemonProviderState, StatusOutput};
+use crate::core::store::{StoreUpdate, UsageStore};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What the webhook posts: the `status --json` document, plus each
+/// provider's cost scan keyed like `providers` with `webhook.include_cost`.
+#[derive(Serialize)]
+pub struct WebhookPayload {
+    #[serde(flatten)]
+    status: StatusOutput,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    costs: Option<BTreeMap<String, CostSnapshot>>,
+}
+
+impl WebhookPayload {
+    /// The enabled providers as the store holds them now, with their
+    /// recorded `lockouts`.
+    pub async fn build(
+        store: &UsageStore,
+        settings: &Settings,
+        lockouts: &LockoutLog,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut latencies: HashMap<_, _> = store.get_fetch_latencies().await.into_iter().collect();
+        let mut providers = Vec::new();
+        let mut costs = BTreeMap::new();
+        for provider in settings.enabled_providers() {
+            let name = settings.provider_label(provider);
+            if settings.webhook.include_cost {
+                if let Some(cost) = store.get_cost(provider).await {
+                    costs.insert(name.clone(), Arc::unwrap_or_clone(cost));
+                }
+            }
+            providers.push(DaemonProviderState {
+                provider,
+                name,
+                snapshot: store.get_snapshot(provider).await,
+                error: store.get_error(provider).await,
+                health: store.get_health(provider).await,
+                fetch_latency: latencies.remove(&provider),
+                lockouts: lockouts.for_provider(provider).to_vec(),
+            });
+        }
+        Self {
+            status: StatusOutput::from_daemon(providers, now),
+            costs: settings.webhook.include_cost.then_some(costs),
+        }
+    }
+}
+
+/// Posts usage to `webhook.url`, at most once per `webhook.interval_secs`.
+pub struct Webhook {
+    http_client: reqwest::Client,
+    url: String,
+    headers: BTreeMap<String, String>,
+    interval: Duration,
+    last_attempt: Option<Instant>,
+}
+
+impl Webhook {
+    /// `None` without `webhook.url`, so nothing is ever sent.
+    pub fn new(settings: &WebhookSettings) -> Option<Self> {
+        let url = settings.url.clone()?;
+        let http_client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .unwrap_or_else(|e| {
+                tracing::warn!(error = %e, "Failed to build webhook HTTP client; falling back to default");
+                reqwest::Client::new()
+            });
+        Some(Self {
+            http_client,
+            url,
+            headers: settings.headers.clone(),
+            interval: Duration::from_secs(settings.interval_secs),
+            last_attempt: None,
+        })
+    }
+
+    /// Carries the time of the last post over from the webhook this one
+    /// replaces, so editing the settings doesn't skip the wait.
+    pub fn continue_from(mut self, previous: Option<&Webhook>) -> Self {
+        self.last_attempt = previous.and_then(|previous| previous.last_attempt);
+        self
+    }
+
+    /// When the next post may go out; `None` if it may go now.
+    pub fn not_before(&self, now: Instant) -> Option<Instant> {
+        self.last_attempt
+            .map(|at| at + self.interval)
+            .filter(|next| now < *next)
+    }
+
+    /// Posts `payload`, trying once more if that fails. A failed post still
+    /// starts the interval, so a broken endpoint isn't hit on every update.
+    pub async fn post(&mut self, payload: &WebhookPayload, now: Instant) -> Result<()> {
+        self.last_attempt = Some(now);
+        if let Err(e) = self.send(payload).await {
+            tracing::debug!(error = %e, "Webhook post failed, retrying once");
+            self.send(payload).await?;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, payload: &WebhookPayload) -> Result<()> {
+        let mut request = self.http_client.post(&self.url).json(payload);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Posts after successful refreshes, holding them to one per interval.
+pub struct WebhookPublisher {
+    webhook: Option<Webhook>,
+    pending: bool,
+}
+
+impl WebhookPublisher {
+    pub fn new(settings: &WebhookSettings) -> Self {
+        Self {
+            webhook: Webhook::new(settings),
+            pending: false,
+        }
+    }
+
+    pub fn update_settings(&mut self, settings: &WebhookSettings) {
+        self.webhook =
+            Webhook::new(settings).map(|webhook| webhook.continue_from(self.webhook.as_ref()));
+        self.pending &= self.webhook.is_some();
+    }
+
+    pub fn usage_updated(&mut self) {
+        self.pending = self.webhook.is_some();
+    }
+
+    /// Posts the latest usage if a refresh succeeded since the last post
+    /// and the interval allows it; otherwise returns when it will.
+    pub async fn flush(
+        &mut self,
+        store: &UsageStore,
+        settings: &Settings,
+        now: Instant,
+    ) -> Option<Instant> {
+        let webhook = self.webhook.as_mut().filter(|_| self.pending)?;
+        if let Some(next) = webhook.not_before(now) {
+            return Some(next);
+        }
+        self.pending = false;
+        let lockouts = LockoutLog::load();
+        let payload = WebhookPayload::build(store, settings, &lockouts, Utc::now()).await;
+        if let Err(e) = webhook.post(&payload, now).await {
+            tracing::warn!(error = %e, "Failed to post usage to webhook");
+        }
+        None
+    }
+}
+
+/// Posts usage to `webhook.url` after each successful refresh, at most once
+/// per `webhook.interval_secs`; an update in between goes out when the
+/// interval ends.
+pub async fn run_webhook_loop(
+    store: Arc<UsageStore>,
+    mut settings_rx: broadcast::Receiver<Settings>,
+    mut settings: Settings,
+) {
+    let mut publisher = WebhookPublisher::new(&settings.webhook);
+    let mut updates = store.subscribe();
+    let mut deferred_until: Option<Instant> = None;
+
+    loop {
+        let wake = deferred_until.unwrap_or_else(Instant::now);
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(StoreUpdate::UsageUpdated(_)) | Err(broadcast::error::RecvError::Lagged(_)) => {
+                    publisher.usage_updated();
+                }
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            Ok(new_settings) = settings_rx.recv() => {
+                if new_settings.webhook != settings.webhook {
+                    publisher.update_settings(&new_settings.webhook);
+                }
+                settings = new_settings;
+                continue;
+            }
+            _ = tokio::time::sleep_until(wake.into()), if deferred_until.is_some() => {}
+        }
+        deferred_until = publisher.flush(&store, &settings, Instant::now()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{Provider, ProviderIdentity, RateWindow, UsageSnapshot};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answers one connection per status in `statuses` and returns the
+    /// requests it received, head and body.
+    async fn mock_server(statuses: Vec<u16>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/usage", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                let body_start = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break end + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+                let length: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: "))
+                    .map_or(0, |length| length.trim().parse().unwrap());
+                while request.len() < body_start + length {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                requests.push(String::from_utf8(request).unwrap());
+                let response = format!(
+                    "HTTP/1.1 {} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    fn settings(url: &str, include_cost: bool) -> Settings {
+        let mut settings = Settings::default();
+        settings.providers.codex.enabled = false;
+        settings.webhook.url = Some(url.to_string());
+        settings.webhook.include_cost = include_cost;
+        settings.webhook.headers =
+            BTreeMap::from([("Authorization".to_string(), "Bearer secret".to_string())]);
+        settings
+    }
+
+    async fn store(now: DateTime<Utc>) -> UsageStore {
+        let store = UsageStore::new();
+        let snapshot = UsageSnapshot {
+            identity: ProviderIdentity {
+                plan: Some("Max".to_string()),
+                ..ProviderIdentity::default()
+            },
+            ..UsageSnapshot::new(
+                Some(RateWindow::from_reported(0.42, Some(300), None, None)),
+                None,
+                now,
+            )
+        };
+        store.update_snapshot(Provider::Claude, snapshot).await;
+        store
+            .update_cost(
+                Provider::Claude,
+                Arc::new(CostSnapshot {
+                    today_cost: 1.5,
+                    ..CostSnapshot::default()
+                }),
+            )
+            .await;
+        store
+    }
+
+    fn body(request: &str) -> serde_json::Value {
+        let (_, body) = request.split_once("\r\n\r\n").unwrap();
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_posts_status_document() {
+        let now = Utc::now();
+        let store = store(now).await;
+        let (url, server) = mock_server(vec![200, 200]).await;
+
+        let without_cost = settings(&url, false);
+        let mut webhook = Webhook::new(&without_cost.webhook).unwrap();
+        let payload =
+            WebhookPayload::build(&store, &without_cost, &LockoutLog::default(), now).await;
+        webhook.post(&payload, Instant::now()).await.unwrap();
+
+        let with_cost = settings(&url, true);
+        let mut webhook = Webhook::new(&with_cost.webhook).unwrap();
+        let payload = WebhookPayload::build(&store, &with_cost, &LockoutLog::default(), now).await;
+        webhook.post(&payload, Instant::now()).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /usage HTTP/1.1\r\n"));
+        let head = requests[0].to_lowercase();
+        assert!(head.contains("authorization: bearer secret\r\n"));
+        assert!(head.contains("content-type: application/json\r\n"));
+
+        let json = body(&requests[0]);
+        assert_eq!(json["schema_version"], crate::core::schema::SCHEMA_VERSION);
+        assert_eq!(json["fetched_at"], now.timestamp());
+        let claude = &json["providers"]["Claude Code"];
+        assert_eq!(claude["session"]["used_percent"], 0.42);
+        assert_eq!(claude["identity"]["plan"], "Max");
+        assert!(json.get("costs").is_none());
+
+        let json = body(&requests[1]);
+        assert_eq!(json["costs"]["Claude Code"]["today_cost"], 1.5);
+    }
+
+    #[tokio::test]
+    async fn test_post_retries_once() {
+        let now = Utc::now();
+        let store = store(now).await;
+        let (url, server) = mock_server(vec![500, 200, 503, 502]).await;
+        let settings = settings(&url, false);
+        let payload = WebhookPayload::build(&store, &settings, &LockoutLog::default(), now).await;
+        let mut webhook = Webhook::new(&settings.webhook).unwrap();
+
+        assert!(webhook.post(&payload, Instant::now()).await.is_ok());
+        assert!(webhook.post(&payload, Instant::now()).await.is_err());
+        assert_eq!(server.await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_publisher_holds_updates_for_the_interval() {
+        let now = Utc::now();
+        let store = store(now).await;
+        // Only two posts are answered; a third would never connect.
+        let (url, server) = mock_server(vec![200, 200]).await;
+        let settings = settings(&url, false);
+        let mut publisher = WebhookPublisher::new(&settings.webhook);
+        let start = Instant::now();
+
+        // Nothing goes out before a refresh succeeds.
+        assert_eq!(publisher.flush(&store, &settings, start).await, None);
+
+        publisher.usage_updated();
+        assert_eq!(publisher.flush(&store, &settings, start).await, None);
+
+        // Refreshes within the interval wait for it to end, then post once.
+        let next = start + Duration::from_secs(300);
+        for secs in [60, 120] {
+            publisher.usage_updated();
+            let at = start + Duration::from_secs(secs);
+            assert_eq!(publisher.flush(&store, &settings, at).await, Some(next));
+        }
+        assert_eq!(publisher.flush(&store, &settings, next).await, None);
+        assert_eq!(publisher.flush(&store, &settings, next).await, None);
+        assert_eq!(server.await.unwrap().len(), 2);
+
+        // Without a URL there is never anything to post.
+        let mut publisher = WebhookPublisher::new(&WebhookSettings::default());
+        publisher.usage_updated();
+        assert_eq!(publisher.flush(&store, &settings, start).await, None);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let mut settings = WebhookSettings::default();
+        assert!(Webhook::new(&settings).is_none());
+
+        settings.url = Some("http://127.0.0.1:9/usage".to_string());
+        let mut webhook = Webhook::new(&settings).unwrap();
+        let start = Instant::now();
+        assert_eq!(webhook.not_before(start), None);
+
+        // A post, failed or not, holds the next one for the interval.
+        webhook.last_attempt = Some(start);
+        let next = start + Duration::from_secs(300);
+        assert_eq!(
+            webhook.not_before(start + Duration::from_secs(299)),
+            Some(next)
+        );
+        assert_eq!(webhook.not_before(next), None);
+
+        // A webhook rebuilt after a settings change keeps waiting.
+        let rebuilt = Webhook::new(&settings)
+            .unwrap()
+            .continue_from(Some(&webhook));
+        assert_eq!(
+            rebuilt.not_before(start + Duration::from_secs(10)),
+            Some(next)
+        );
+    }
+}
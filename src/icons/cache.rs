@@ -0,0 +1,178 @@
+use super::renderer::{IconRenderer, IconState};
+use crate::core::models::Provider;
+use crate::ui::colors::ColorScheme;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Sizes each tray icon is drawn at. Hosts pick the closest to their panel
+/// instead of scaling a single 22px one.
+pub const ICON_SIZES: [u32; 4] = [16, 22, 32, 48];
+/// Renderings kept before the cache starts over. The loading animation
+/// sweeps through about sixty per provider.
+const MAX_ENTRIES: usize = 512;
+
+/// Everything an icon's pixels depend on, with the bars rounded to whole
+/// percents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct IconKey {
+    provider: Provider,
+    primary: u8,
+    secondary: u8,
+    state: IconState,
+    is_dark: bool,
+    scheme: ColorScheme,
+    cycle: Option<(usize, usize)>,
+}
+
+impl IconKey {
+    pub fn new(
+        provider: Provider,
+        (primary, secondary): (f64, f64),
+        state: IconState,
+        is_dark: bool,
+        scheme: ColorScheme,
+        cycle: Option<(usize, usize)>,
+    ) -> Self {
+        Self {
+            provider,
+            primary: whole_percent(primary),
+            secondary: whole_percent(secondary),
+            state,
+            is_dark,
+            scheme,
+            cycle,
+        }
+    }
+}
+
+fn whole_percent(fraction: f64) -> u8 {
+    if fraction.is_nan() {
+        return 0;
+    }
+    (fraction.clamp(0.0, 1.0) * 100.0).round() as u8
+}
+
+/// One size of an icon, as RGBA.
+pub struct RenderedIcon {
+    pub size: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Icons already drawn at every `ICON_SIZES` size, shared by all tray icons
+/// so hosts re-reading an unchanged icon and repeated animation frames
+/// don't draw again.
+#[derive(Default)]
+pub struct IconCache {
+    entries: Mutex<HashMap<IconKey, Arc<[RenderedIcon]>>>,
+}
+
+impl IconCache {
+    pub fn render(&self, key: IconKey) -> Arc<[RenderedIcon]> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(icons) = entries.get(&key) {
+            return Arc::clone(icons);
+        }
+        if entries.len() >= MAX_ENTRIES {
+            entries.clear();
+        }
+        let icons: Arc<[RenderedIcon]> = ICON_SIZES
+            .iter()
+            .map(|&size| RenderedIcon {
+                size,
+                rgba: IconRenderer::with_size(size)
+                    .with_scheme(key.scheme)
+                    .render(
+                        key.provider,
+                        f64::from(key.primary) / 100.0,
+                        f64::from(key.secondary) / 100.0,
+                        key.state,
+                        key.is_dark,
+                        key.cycle,
+                    ),
+            })
+            .collect();
+        entries.insert(key, Arc::clone(&icons));
+        icons
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(primary: f64, state: IconState) -> IconKey {
+        IconKey::new(
+            Provider::Claude,
+            (primary, 0.25),
+            state,
+            true,
+            ColorScheme::Standard,
+            None,
+        )
+    }
+
+    #[test]
+    fn test_renders_every_size_once() {
+        let cache = IconCache::default();
+        let icons = cache.render(key(0.5, IconState::Normal));
+        let sizes: Vec<_> = icons.iter().map(|icon| icon.size).collect();
+        assert_eq!(sizes, ICON_SIZES);
+        for icon in icons.iter() {
+            assert_eq!(icon.rgba.len(), (icon.size * icon.size * 4) as usize);
+        }
+        assert_eq!(
+            icons[1].rgba,
+            IconRenderer::new().render(Provider::Claude, 0.5, 0.25, IconState::Normal, true, None)
+        );
+
+        // Within the same percent: the same rendering.
+        let again = cache.render(key(0.5004, IconState::Normal));
+        assert!(Arc::ptr_eq(&icons, &again));
+        assert_eq!(cache.len(), 1);
+
+        let loading = cache.render(key(0.5, IconState::Loading));
+        assert!(!Arc::ptr_eq(&icons, &loading));
+        cache.render(key(0.51, IconState::Normal));
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_cache_starts_over_when_full() {
+        let cache = IconCache::default();
+        let states = [
+            IconState::Normal,
+            IconState::Loading,
+            IconState::Error,
+            IconState::Stale,
+        ];
+        for (index, state) in states.iter().cycle().take(MAX_ENTRIES).enumerate() {
+            let cycle = Some((index / 4, MAX_ENTRIES));
+            let key = IconKey::new(
+                Provider::Claude,
+                (0.5, 0.5),
+                *state,
+                true,
+                ColorScheme::Standard,
+                cycle,
+            );
+            cache.render(key);
+        }
+        assert_eq!(cache.len(), MAX_ENTRIES);
+        cache.render(key(0.1, IconState::Normal));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_whole_percent() {
+        assert_eq!(whole_percent(0.504), 50);
+        assert_eq!(whole_percent(0.506), 51);
+        assert_eq!(whole_percent(1.7), 100);
+        assert_eq!(whole_percent(-0.2), 0);
+        assert_eq!(whole_percent(f64::NAN), 0);
+    }
+}
@@ -1,3 +1,7 @@
+mod cache;
+mod named;
 mod renderer;
 
+pub use cache::{IconCache, IconKey};
+pub use named::{icon_theme_dir, named_icon_files, NamedIcon};
 pub use renderer::{IconRenderer, IconState};
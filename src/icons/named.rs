@@ -0,0 +1,169 @@
+use crate::core::models::Provider;
+use crate::icons::IconState;
+use crate::ui::colors::{self, ColorScheme};
+use std::path::{Path, PathBuf};
+
+/// Usage at which a named icon turns to its warning variant.
+const WARN_AT: f64 = 0.8;
+/// Geometry of the 22px rendered icon.
+const SIZE: f64 = 22.0;
+const MARGIN: f64 = 2.0;
+const BAR_HEIGHT: f64 = 7.0;
+const BAR_GAP: f64 = 2.0;
+const EMPTY_OPACITY: f64 = 0.55;
+const PROVIDERS: [Provider; 2] = [Provider::Claude, Provider::Codex];
+
+/// The icons `display.icon_mode = "named"` shows, one set per provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NamedIcon {
+    Normal,
+    Warn,
+    Error,
+}
+
+impl NamedIcon {
+    const ALL: [NamedIcon; 3] = [NamedIcon::Normal, NamedIcon::Warn, NamedIcon::Error];
+
+    /// The icon standing in for a rendered one in `state` with bars at
+    /// `primary` and `secondary`. Stale usage is grayed out like an error.
+    pub fn for_state(state: IconState, primary: f64, secondary: f64) -> Self {
+        match state {
+            IconState::Error | IconState::Stale => Self::Error,
+            IconState::Loading => Self::Normal,
+            IconState::Normal if primary.max(secondary) >= WARN_AT => Self::Warn,
+            IconState::Normal => Self::Normal,
+        }
+    }
+
+    /// The icon theme name, e.g. `claude-bar-codex-warn`. Codex
+    /// workspaces share Codex's icons.
+    pub fn name(self, provider: Provider) -> String {
+        let provider = match provider {
+            Provider::Claude => "claude",
+            Provider::Codex | Provider::CodexWorkspace(_) => "codex",
+        };
+        let variant = match self {
+            Self::Normal => "normal",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        };
+        format!("claude-bar-{}-{}", provider, variant)
+    }
+
+    /// Two bars like the rendered icon's, at fixed fills: part full in the
+    /// provider's color, nearly full in the warning color, or empty and gray.
+    pub fn svg(self, provider: Provider) -> String {
+        let (color, (primary, secondary)) = match self {
+            Self::Normal => (colors::provider_rgb(provider), (0.6, 0.35)),
+            Self::Warn => (ColorScheme::Standard.warning_rgb(), (0.9, 0.6)),
+            Self::Error => ((128, 128, 128), (0.0, 0.0)),
+        };
+        let bar = |y: f64, fill: f64| {
+            let mut rects = format!(
+                r##"  <rect x="{MARGIN}" y="{y}" width="{width}" height="{BAR_HEIGHT}" fill="{empty}" fill-opacity="{EMPTY_OPACITY}"/>
+"##,
+                width = SIZE - 2.0 * MARGIN,
+                empty = hex(colors::muted_rgb(color)),
+            );
+            if fill > 0.0 {
+                rects.push_str(&format!(
+                    r##"  <rect x="{MARGIN}" y="{y}" width="{width}" height="{BAR_HEIGHT}" fill="{fill}"/>
+"##,
+                    width = ((SIZE - 2.0 * MARGIN) * fill).round(),
+                    fill = hex(color),
+                ));
+            }
+            rects
+        };
+        format!(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="{SIZE}" height="{SIZE}" viewBox="0 0 {SIZE} {SIZE}">
+  <rect width="{SIZE}" height="{SIZE}" rx="5" fill="#808080" fill-opacity="0.25"/>
+{primary}{secondary}</svg>
+"##,
+            primary = bar(MARGIN, primary),
+            secondary = bar(MARGIN + BAR_HEIGHT + BAR_GAP, secondary),
+        )
+    }
+}
+
+/// Where `install-icons` puts the icons under the XDG data directory, and
+/// the theme path the tray hands to hosts so they find them there.
+pub fn icon_theme_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("icons")
+}
+
+/// Every named icon's file under `data_dir` with its contents.
+pub fn named_icon_files(data_dir: &Path) -> Vec<(PathBuf, String)> {
+    let status_dir = icon_theme_dir(data_dir).join("hicolor/scalable/status");
+    let mut files = Vec::new();
+    for provider in PROVIDERS {
+        for icon in NamedIcon::ALL {
+            let path = status_dir.join(format!("{}.svg", icon.name(provider)));
+            files.push((path, icon.svg(provider)));
+        }
+    }
+    files
+}
+
+fn hex((r, g, b): (u8, u8, u8)) -> String {
+    format!("#{:02X}{:02X}{:02X}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_icon_follows_state_and_usage() {
+        assert_eq!(
+            NamedIcon::for_state(IconState::Normal, 0.5, 0.79),
+            NamedIcon::Normal
+        );
+        assert_eq!(
+            NamedIcon::for_state(IconState::Normal, 0.2, 0.8),
+            NamedIcon::Warn
+        );
+        assert_eq!(
+            NamedIcon::for_state(IconState::Loading, 1.0, 1.0),
+            NamedIcon::Normal
+        );
+        assert_eq!(
+            NamedIcon::for_state(IconState::Stale, 0.1, 0.1),
+            NamedIcon::Error
+        );
+        assert_eq!(
+            NamedIcon::for_state(IconState::Normal, f64::NAN, 0.9),
+            NamedIcon::Warn
+        );
+        assert_eq!(
+            NamedIcon::Warn.name(Provider::CodexWorkspace(2)),
+            "claude-bar-codex-warn"
+        );
+    }
+
+    #[test]
+    fn test_svg_bars() {
+        let normal = NamedIcon::Normal.svg(Provider::Claude);
+        assert!(normal.starts_with("<svg "));
+        assert!(normal.contains(r##"y="2" width="11" height="7" fill="#F5A623""##));
+        assert!(normal.contains(r##"y="11" width="6" height="7" fill="#F5A623""##));
+
+        let error = NamedIcon::Error.svg(Provider::Codex);
+        assert_eq!(error.matches("<rect ").count(), 3);
+    }
+
+    #[test]
+    fn test_named_icon_files() {
+        let files = named_icon_files(Path::new("/home/me/.local/share"));
+        assert_eq!(files.len(), 6);
+        assert_eq!(
+            files[0].0,
+            Path::new(
+                "/home/me/.local/share/icons/hicolor/scalable/status/claude-bar-claude-normal.svg"
+            )
+        );
+        assert!(files
+            .iter()
+            .any(|(path, _)| path.ends_with("claude-bar-codex-error.svg")));
+    }
+}
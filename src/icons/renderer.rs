@@ -1,33 +1,50 @@
 use crate::core::models::Provider;
-use crate::ui::colors;
+use crate::ui::colors::{self, ColorScheme};
 
 const ICON_SIZE: u32 = 22;
 const BACKGROUND_ALPHA_DARK: u8 = 70;
 const BACKGROUND_ALPHA_LIGHT: u8 = 60;
+const EMPTY_ALPHA_DARK: u8 = 140;
+const EMPTY_ALPHA_LIGHT: u8 = 200;
+const DIVIDER_ALPHA: u8 = 220;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IconState {
     Normal,
     Loading,
     Error,
-    #[allow(dead_code)]
     Stale,
 }
 
 pub struct IconRenderer {
     size: u32,
+    scheme: ColorScheme,
 }
 
 impl IconRenderer {
     pub fn new() -> Self {
-        Self { size: ICON_SIZE }
+        Self {
+            size: ICON_SIZE,
+            scheme: ColorScheme::default(),
+        }
     }
 
-    #[allow(dead_code)]
     pub fn with_size(size: u32) -> Self {
-        Self { size }
+        Self {
+            size,
+            scheme: ColorScheme::default(),
+        }
+    }
+
+    /// In colorblind mode the bars are a pixel further apart, with a
+    /// divider between them.
+    pub fn with_scheme(mut self, scheme: ColorScheme) -> Self {
+        self.scheme = scheme;
+        self
     }
 
+    /// `cycle` is the bottom bar's `(position, count)` while it cycles
+    /// through windows, drawn as a row of dots under it.
     pub fn render(
         &self,
         provider: Provider,
@@ -35,70 +52,141 @@ impl IconRenderer {
         secondary: f64,
         state: IconState,
         is_dark: bool,
+        cycle: Option<(usize, usize)>,
     ) -> Vec<u8> {
         let width = self.size as usize;
         let height = self.size as usize;
         let mut pixels = vec![0u8; width * height * 4]; // RGBA
 
-        let (r, g, b) = match state {
-            IconState::Normal => colors::provider_rgb(provider),
-            IconState::Loading => colors::provider_rgb(provider),
+        let base = match state {
+            IconState::Normal | IconState::Loading => colors::provider_rgb(provider),
             IconState::Error => (128, 128, 128), // Gray
             IconState::Stale => (180, 180, 180), // Light gray
         };
-        let muted = colors::muted_rgb((r, g, b));
-
-        let background_alpha = if is_dark {
-            BACKGROUND_ALPHA_DARK
-        } else {
-            BACKGROUND_ALPHA_LIGHT
-        };
-        let background_color = if is_dark {
-            (240, 240, 240, background_alpha)
-        } else {
-            (0, 0, 0, background_alpha)
-        };
-        self.draw_rounded_rect(&mut pixels, width, height, 5.0, background_color);
-
-        // Draw two horizontal bars
-        let bar_height = (height as f64 * 0.35) as usize;
-        let bar_gap = 2;
-        let bar_width = width - 4;
-        let bar_x = 2;
+        let is_accent = matches!(state, IconState::Normal | IconState::Loading);
+        let palette = Palette::new(base, is_accent, is_dark);
+        let layout = self.layout();
+        self.draw_rounded_rect(
+            &mut pixels,
+            width,
+            height,
+            layout.radius,
+            palette.background,
+        );
 
         // Primary bar (top)
-        let primary_y = 2;
-        let primary_fill = ((bar_width as f64) * primary.clamp(0.0, 1.0)) as usize;
+        let primary_fill = fill_width(layout.bar_width, primary);
         self.draw_bar(
             &mut pixels,
             width,
-            bar_x,
-            primary_y,
-            bar_width,
-            bar_height,
+            layout.margin,
+            layout.primary_y,
+            layout.bar_width,
+            layout.bar_height,
             primary_fill,
-            (r, g, b),
-            muted,
+            &palette,
         );
 
+        if let Some(divider_y) = layout.divider_y {
+            self.draw_divider(&mut pixels, width, &layout, divider_y, &palette);
+        }
+
         // Secondary bar (bottom)
-        let secondary_y = primary_y + bar_height + bar_gap;
-        let secondary_fill = ((bar_width as f64) * secondary.clamp(0.0, 1.0)) as usize;
+        let secondary_fill = fill_width(layout.bar_width, secondary);
         self.draw_bar(
             &mut pixels,
             width,
-            bar_x,
-            secondary_y,
-            bar_width,
-            bar_height,
+            layout.margin,
+            layout.secondary_y,
+            layout.bar_width,
+            layout.bar_height,
             secondary_fill,
-            (r, g, b),
-            muted,
+            &palette,
         );
 
+        if let Some((position, count)) = cycle {
+            self.draw_cycle_dots(&mut pixels, width, &layout, position, count, &palette);
+        }
+
         pixels
     }
 
+    /// The 22px icon's geometry scaled to this renderer's size.
+    fn layout(&self) -> Layout {
+        let size = self.size as usize;
+        let scaled = |px: usize| ((px * size) as f64 / ICON_SIZE as f64).round().max(1.0) as usize;
+        let margin = scaled(2);
+        let line = scaled(1);
+        let bar_height = (size as f64 * 0.35) as usize;
+        let primary_y = margin;
+        // The divider gets a line's worth of space on either side.
+        let (bar_gap, divider_y) = if self.scheme.is_colorblind() {
+            (3 * line, Some(primary_y + bar_height + line))
+        } else {
+            (scaled(2), None)
+        };
+        let secondary_y = primary_y + bar_height + bar_gap;
+        Layout {
+            margin,
+            radius: 5.0 * self.size as f32 / ICON_SIZE as f32,
+            line,
+            bar_width: size.saturating_sub(2 * margin),
+            bar_height,
+            primary_y,
+            secondary_y,
+            divider_y,
+            dots_y: secondary_y + bar_height + line,
+        }
+    }
+
+    /// One square dot per window with a dot's space between them, centered;
+    /// the shown window's dot is filled.
+    fn draw_cycle_dots(
+        &self,
+        pixels: &mut [u8],
+        stride: usize,
+        layout: &Layout,
+        position: usize,
+        count: usize,
+        palette: &Palette,
+    ) {
+        // Within the bars' margins.
+        let dot = layout.line;
+        let count = count.min((layout.bar_width + dot) / (2 * dot));
+        if count < 2 {
+            return;
+        }
+        let start = (stride - (2 * count - 1) * dot) / 2;
+        for index in 0..count {
+            let color = if index == position {
+                let (r, g, b) = palette.fill;
+                (r, g, b, 255)
+            } else {
+                palette.empty
+            };
+            let x = start + 2 * dot * index;
+            fill_rect(pixels, stride, (x, layout.dots_y), (dot, dot), color);
+        }
+    }
+
+    /// A line across the bars' width, in the panel's contrasting tone.
+    fn draw_divider(
+        &self,
+        pixels: &mut [u8],
+        stride: usize,
+        layout: &Layout,
+        y: usize,
+        palette: &Palette,
+    ) {
+        fill_rect(
+            pixels,
+            stride,
+            (layout.margin, y),
+            (layout.bar_width, layout.line),
+            palette.divider,
+        );
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn draw_bar(
         &self,
@@ -109,11 +197,10 @@ impl IconRenderer {
         width: usize,
         height: usize,
         fill: usize,
-        color: (u8, u8, u8),
-        empty_color: (u8, u8, u8),
+        palette: &Palette,
     ) {
-        let (r, g, b) = color;
-        let (er, eg, eb) = empty_color;
+        let (r, g, b) = palette.fill;
+        let (er, eg, eb, ea) = palette.empty;
 
         for dy in 0..height {
             for dx in 0..width {
@@ -133,7 +220,7 @@ impl IconRenderer {
                         pixels[idx] = er;
                         pixels[idx + 1] = eg;
                         pixels[idx + 2] = eb;
-                        pixels[idx + 3] = 140;
+                        pixels[idx + 3] = ea;
                     }
                 }
             }
@@ -173,12 +260,92 @@ impl IconRenderer {
     }
 }
 
+/// Where an icon's parts go, in pixels.
+struct Layout {
+    /// Between the icon's edge and the bars.
+    margin: usize,
+    radius: f32,
+    /// The thickness of the divider and the cycle dots.
+    line: usize,
+    bar_width: usize,
+    bar_height: usize,
+    primary_y: usize,
+    secondary_y: usize,
+    divider_y: Option<usize>,
+    dots_y: usize,
+}
+
 impl Default for IconRenderer {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Colors for one icon. Light panels get a darker, more opaque empty bar and
+/// a toned-down accent; dark panels keep the original look.
+struct Palette {
+    fill: (u8, u8, u8),
+    empty: (u8, u8, u8, u8),
+    background: (u8, u8, u8, u8),
+    divider: (u8, u8, u8, u8),
+}
+
+impl Palette {
+    fn new(base: (u8, u8, u8), is_accent: bool, is_dark: bool) -> Self {
+        if is_dark {
+            let (r, g, b) = colors::muted_rgb(base);
+            Self {
+                fill: base,
+                empty: (r, g, b, EMPTY_ALPHA_DARK),
+                background: (240, 240, 240, BACKGROUND_ALPHA_DARK),
+                divider: (240, 240, 240, DIVIDER_ALPHA),
+            }
+        } else {
+            let (r, g, b) = colors::muted_rgb_light(base);
+            Self {
+                fill: if is_accent {
+                    colors::accent_rgb_light(base)
+                } else {
+                    base
+                },
+                empty: (r, g, b, EMPTY_ALPHA_LIGHT),
+                background: (0, 0, 0, BACKGROUND_ALPHA_LIGHT),
+                divider: (0, 0, 0, DIVIDER_ALPHA),
+            }
+        }
+    }
+}
+
+/// Filled pixels for a bar at `fraction`. Out-of-range or NaN usage from a
+/// misbehaving API never draws past the bar.
+fn fill_width(bar_width: usize, fraction: f64) -> usize {
+    if fraction.is_nan() {
+        return 0;
+    }
+    ((bar_width as f64) * fraction.clamp(0.0, 1.0)) as usize
+}
+
+fn fill_rect(
+    pixels: &mut [u8],
+    stride: usize,
+    (x, y): (usize, usize),
+    (width, height): (usize, usize),
+    color: (u8, u8, u8, u8),
+) {
+    let (r, g, b, a) = color;
+    for py in y..y + height {
+        for px in x..x + width {
+            let idx = (py * stride + px) * 4;
+            if idx + 3 < pixels.len() {
+                pixels[idx] = r;
+                pixels[idx + 1] = g;
+                pixels[idx + 2] = b;
+                pixels[idx + 3] = a;
+            }
+        }
+    }
+}
+
 fn inside_rounded_rect(x: usize, y: usize, width: usize, height: usize, radius: f32) -> bool {
     let x = x as f32;
     let y = y as f32;
@@ -207,10 +374,224 @@ mod tests {
     #[test]
     fn test_render_produces_correct_size() {
         let renderer = IconRenderer::new();
-        let pixels = renderer.render(Provider::Claude, 0.5, 0.5, IconState::Normal, false);
+        let pixels = renderer.render(Provider::Claude, 0.5, 0.5, IconState::Normal, false, None);
+        assert_eq!(pixels.len(), 22 * 22 * 4);
+    }
+
+    #[test]
+    fn test_fill_width_stays_within_bar() {
+        assert_eq!(fill_width(18, 0.5), 9);
+        assert_eq!(fill_width(18, 2.5), 18);
+        assert_eq!(fill_width(18, -0.3), 0);
+        assert_eq!(fill_width(18, f64::NAN), 0);
+        assert_eq!(fill_width(18, f64::INFINITY), 18);
+
+        let renderer = IconRenderer::new();
+        let pixels = renderer.render(
+            Provider::Claude,
+            2.5,
+            f64::NAN,
+            IconState::Normal,
+            false,
+            None,
+        );
         assert_eq!(pixels.len(), 22 * 22 * 4);
     }
 
+    /// WCAG relative luminance of an RGBA pixel blended over an opaque panel.
+    fn luminance_over(pixel: &[u8], panel: (u8, u8, u8)) -> f64 {
+        let alpha = f64::from(pixel[3]) / 255.0;
+        let channel = |value: u8, panel: u8| {
+            let c = (f64::from(value) * alpha + f64::from(panel) * (1.0 - alpha)) / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * channel(pixel[0], panel.0)
+            + 0.7152 * channel(pixel[1], panel.1)
+            + 0.0722 * channel(pixel[2], panel.2)
+    }
+
+    fn contrast(a: f64, b: f64) -> f64 {
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    #[test]
+    fn test_filled_and_empty_bars_contrast_on_both_panels() {
+        let renderer = IconRenderer::new();
+        let pixel = |pixels: &[u8], x: usize, y: usize| pixels[(y * 22 + x) * 4..][..4].to_vec();
+
+        for (is_dark, panel) in [(true, (48, 48, 48)), (false, (235, 235, 235))] {
+            let panel_luminance = luminance_over(&[0, 0, 0, 0], panel);
+            for provider in [Provider::Claude, Provider::Codex] {
+                for state in [IconState::Normal, IconState::Error, IconState::Stale] {
+                    // Full primary bar on top, empty secondary bar below.
+                    let pixels = renderer.render(provider, 1.0, 0.0, state, is_dark, None);
+                    let filled = luminance_over(&pixel(&pixels, 10, 4), panel);
+                    let empty = luminance_over(&pixel(&pixels, 10, 14), panel);
+
+                    let label = format!("{:?} {:?} dark={}", provider, state, is_dark);
+                    assert!(
+                        contrast(filled, empty) >= 2.0,
+                        "{}: filled {:.3} vs empty {:.3}",
+                        label,
+                        filled,
+                        empty
+                    );
+                    // Dark panels keep the original subdued track.
+                    if !is_dark {
+                        assert!(
+                            contrast(empty, panel_luminance) >= 1.5,
+                            "{}: empty bar blends into the panel",
+                            label
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cycle_dots_mark_the_shown_window() {
+        let renderer = IconRenderer::new();
+        let render =
+            |cycle| renderer.render(Provider::Claude, 0.5, 0.5, IconState::Normal, true, cycle);
+        let pixel = |pixels: &[u8], x: usize| pixels[(19 * 22 + x) * 4..][..4].to_vec();
+
+        // Three dots at x = 8, 10 and 12 on the row under the bottom bar.
+        let plain = render(None);
+        let cycling = render(Some((1, 3)));
+        assert_eq!(pixel(&cycling, 9), pixel(&plain, 9));
+        assert_eq!(pixel(&cycling, 10)[3], 255);
+        assert!(pixel(&cycling, 8)[3] < 255);
+        assert_eq!(pixel(&cycling, 8), pixel(&cycling, 12));
+        assert_ne!(pixel(&cycling, 8), pixel(&plain, 8));
+
+        // A single window has nothing to cycle through.
+        assert_eq!(render(Some((0, 1))), plain);
+    }
+
+    #[test]
+    fn test_colorblind_divider_separates_the_bars() {
+        let pixel = |pixels: &[u8], x: usize, y: usize| pixels[(y * 22 + x) * 4..][..4].to_vec();
+        for (is_dark, divider) in [(true, [240, 240, 240, 220]), (false, [0, 0, 0, 220])] {
+            let render = |scheme| {
+                IconRenderer::new().with_scheme(scheme).render(
+                    Provider::Claude,
+                    1.0,
+                    1.0,
+                    IconState::Normal,
+                    is_dark,
+                    None,
+                )
+            };
+            let standard = render(ColorScheme::Standard);
+            let colorblind = render(ColorScheme::Colorblind);
+
+            // Bars span x = 2..20 over rows 2..9, then 11..18 (12..19 with
+            // the divider on row 10).
+            for x in 2..20 {
+                assert_eq!(pixel(&colorblind, x, 10), divider, "x={}", x);
+                assert_ne!(pixel(&standard, x, 10), divider);
+                assert_eq!(pixel(&colorblind, x, 9), pixel(&standard, x, 9));
+                assert_eq!(pixel(&colorblind, x, 12), pixel(&standard, x, 12));
+            }
+            assert_eq!(pixel(&colorblind, 1, 10), pixel(&standard, 1, 10));
+            assert_eq!(pixel(&colorblind, 20, 10), pixel(&standard, 20, 10));
+            assert_eq!(pixel(&colorblind, 10, 11), pixel(&standard, 10, 9));
+            assert_eq!(pixel(&colorblind, 10, 18), pixel(&standard, 10, 12));
+        }
+    }
+
+    #[test]
+    fn test_geometry_at_16px() {
+        let pixels = IconRenderer::with_size(16).render(
+            Provider::Claude,
+            1.0,
+            0.0,
+            IconState::Normal,
+            true,
+            Some((0, 2)),
+        );
+        assert_eq!(pixels.len(), 16 * 16 * 4);
+        let alpha = |x: usize, y: usize| pixels[(y * 16 + x) * 4 + 3];
+
+        // 1px margins, 5px bars 1px apart: rows 1..6 and 7..12.
+        let background = alpha(0, 8);
+        assert_eq!(background, BACKGROUND_ALPHA_DARK);
+        for x in 1..15 {
+            assert_eq!(alpha(x, 1), 255, "x={}", x);
+            assert_eq!(alpha(x, 5), 255);
+            assert_eq!(alpha(x, 6), background);
+            assert_eq!(alpha(x, 7), EMPTY_ALPHA_DARK);
+            assert_eq!(alpha(x, 11), EMPTY_ALPHA_DARK);
+        }
+        assert_eq!(alpha(15, 3), background);
+        // Two 1px dots on row 13, at x = 6 and 8.
+        assert_eq!(alpha(6, 13), 255);
+        assert_eq!(alpha(7, 13), background);
+        assert_eq!(alpha(8, 13), EMPTY_ALPHA_DARK);
+        // The corners are rounded off.
+        assert_eq!(alpha(0, 0), 0);
+    }
+
+    #[test]
+    fn test_geometry_at_48px() {
+        let render = |scheme| {
+            IconRenderer::with_size(48).with_scheme(scheme).render(
+                Provider::Claude,
+                1.0,
+                0.0,
+                IconState::Normal,
+                true,
+                Some((0, 3)),
+            )
+        };
+        let standard = render(ColorScheme::Standard);
+        assert_eq!(standard.len(), 48 * 48 * 4);
+        let alpha = |pixels: &[u8], x: usize, y: usize| pixels[(y * 48 + x) * 4 + 3];
+
+        // 4px margins, 16px bars 4px apart: rows 4..20 and 24..40.
+        let background = alpha(&standard, 3, 20);
+        assert_eq!(background, BACKGROUND_ALPHA_DARK);
+        for x in 4..44 {
+            assert_eq!(alpha(&standard, x, 4), 255, "x={}", x);
+            assert_eq!(alpha(&standard, x, 19), 255);
+            assert_eq!(alpha(&standard, x, 20), background);
+            assert_eq!(alpha(&standard, x, 23), background);
+            assert_eq!(alpha(&standard, x, 24), EMPTY_ALPHA_DARK);
+            assert_eq!(alpha(&standard, x, 39), EMPTY_ALPHA_DARK);
+            assert_eq!(alpha(&standard, x, 40), background);
+        }
+        assert_eq!(alpha(&standard, 44, 10), background);
+        // Three 2px dots from x = 19 on rows 42 and 43.
+        for (x, expected) in [
+            (19, 255),
+            (20, 255),
+            (21, background),
+            (23, EMPTY_ALPHA_DARK),
+        ] {
+            assert_eq!(alpha(&standard, x, 42), expected, "x={}", x);
+            assert_eq!(alpha(&standard, x, 43), expected);
+        }
+        assert_eq!(alpha(&standard, 19, 44), background);
+        // A 5px corner radius at 22px is about 11px here.
+        assert_eq!(alpha(&standard, 2, 2), 0);
+
+        // Colorblind: a 2px divider on rows 22 and 23, the bottom bar at 26.
+        let colorblind = render(ColorScheme::Colorblind);
+        for x in 4..44 {
+            assert_eq!(alpha(&colorblind, x, 21), background);
+            assert_eq!(alpha(&colorblind, x, 22), DIVIDER_ALPHA);
+            assert_eq!(alpha(&colorblind, x, 23), DIVIDER_ALPHA);
+            assert_eq!(alpha(&colorblind, x, 25), background);
+            assert_eq!(alpha(&colorblind, x, 26), EMPTY_ALPHA_DARK);
+            assert_eq!(alpha(&colorblind, x, 41), EMPTY_ALPHA_DARK);
+        }
+    }
+
     #[test]
     fn test_knight_rider_animation() {
         let (p1, s1) = IconRenderer::knight_rider_frame(0.0);
@@ -1,6 +1,5 @@
-use clap::{CommandFactory, Parser, Subcommand};
+use clap::{Args, CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
-use std::fs::{self, OpenOptions};
 use std::io;
 use std::path::PathBuf;
 use tracing_subscriber::{
@@ -18,10 +17,27 @@ mod icons;
 mod providers;
 mod ui;
 
+use crate::core::log_file::{
+    log_file_path, non_blocking, LogGuard, RotatingFile, KEPT_LOG_FILES, MAX_LOG_BYTES,
+};
+
 #[derive(Parser)]
 #[command(name = "claude-bar")]
 #[command(author, version, about = "Linux system tray for AI coding assistant usage monitoring")]
 struct Cli {
+    /// Daemon instance to run or talk to (defaults to daemon.instance_suffix)
+    #[arg(long, global = true, env = core::instance::INSTANCE_ENV)]
+    instance: Option<String>,
+
+    /// Config file to use instead of ~/.config/claude-bar/config.toml
+    /// [env: CLAUDE_BAR_CONFIG]
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Color text output: auto colors a terminal unless NO_COLOR is set
+    #[arg(long, global = true, value_enum, default_value_t = cli::style::ColorMode::Auto)]
+    color: cli::style::ColorMode,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -29,7 +45,21 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Start the tray daemon
-    Daemon,
+    Daemon {
+        /// Run without the tray and popup, keeping polling, cost scans and
+        /// D-Bus (the default when no display is available)
+        #[arg(long)]
+        no_ui: bool,
+
+        /// Poll canned usage instead of the provider APIs and skip the
+        /// pricing fetch, e.g. to check packaging without credentials
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Stop the dry run after this many seconds (default: run until quit)
+        #[arg(long, value_name = "SECS", requires = "dry_run")]
+        dry_run_duration: Option<u64>,
+    },
 
     /// Show current usage status
     Status {
@@ -40,6 +70,37 @@ enum Commands {
         /// Filter by provider name
         #[arg(long)]
         provider: Option<String>,
+
+        /// Show recent fetch errors and recoveries recorded by the daemon
+        #[arg(long, conflicts_with_all = ["claude_credentials", "codex_credentials"])]
+        errors: bool,
+
+        /// Print one line from a template, e.g. '{claude.session.used_percent:.1%}'
+        #[arg(long, conflicts_with_all = ["json", "errors"])]
+        format: Option<String>,
+
+        /// Also show the plan, every usage window and extra usage (--json always does)
+        #[arg(long, conflicts_with_all = ["json", "errors", "format"])]
+        all_windows: bool,
+
+        /// Keep printing usage until interrupted
+        #[arg(long, conflicts_with_all = ["json", "errors", "format", "claude_credentials", "codex_credentials"])]
+        watch: bool,
+
+        /// Seconds between refreshes with --watch
+        #[arg(long, default_value = "60", requires = "watch")]
+        interval: u64,
+
+        /// What --watch prints: json-lines gives one JSON object per provider per refresh
+        #[arg(long, value_enum, default_value_t = cli::status::WatchOutput::Text, requires = "watch")]
+        output: cli::status::WatchOutput,
+
+        /// Print the JSON Schema of the JSON output (--json, --errors --json or --output json-lines)
+        #[arg(long, conflicts_with_all = ["format", "all_windows"])]
+        schema: bool,
+
+        #[command(flatten)]
+        credentials: CredentialArgs,
     },
 
     /// Show cost summary
@@ -48,16 +109,174 @@ enum Commands {
         #[arg(long)]
         json: bool,
 
+        /// Output per-model token and cost rows per day as CSV
+        #[arg(long, conflicts_with_all = ["json", "by_project"])]
+        csv: bool,
+
         /// Number of days to include (default: 30)
         #[arg(long, default_value = "30")]
         days: u32,
+
+        /// Show this month's spend per project against `[cost.budgets]`
+        #[arg(long)]
+        by_project: bool,
+
+        /// Print the JSON Schema of --json
+        #[arg(long, conflicts_with = "csv")]
+        schema: bool,
+    },
+
+    /// Show lockouts per week and how sessions use tokens and money
+    ///
+    /// The daemon records a lockout whenever a window reaches 99.5% used,
+    /// once per window period; those cover the last 13 weeks. Session sizes,
+    /// the busiest model and average daily cost come from the cost logs.
+    /// With --weekly, recaps each weekly window that reset instead.
+    Stats {
+        /// Recap each weekly window the daemon saw reset instead
+        #[arg(long)]
+        weekly: bool,
+
+        /// Number of days of logs the session statistics cover
+        #[arg(long, default_value = "90", conflicts_with = "weekly")]
+        days: u32,
+
+        /// Only show this provider (claude or codex)
+        #[arg(long, conflicts_with = "weekly")]
+        provider: Option<String>,
+
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Print the JSON Schema of --json
+        #[arg(long)]
+        schema: bool,
+    },
+
+    /// Stream usage to swaybar/i3bar using the i3bar JSON protocol
+    Swaybar {
+        /// Seconds between updates
+        #[arg(long, default_value = "30")]
+        interval: u64,
+
+        /// Usage fraction at which a block turns the warning color
+        #[arg(long, default_value = "0.75")]
+        warning: f64,
+
+        /// Usage fraction at which a block turns the critical color
+        #[arg(long, default_value = "0.9")]
+        critical: f64,
     },
 
     /// Trigger daemon refresh via D-Bus
-    Refresh,
+    Refresh {
+        /// Start the daemon via D-Bus activation if it is not running
+        #[arg(long)]
+        autostart: bool,
+    },
 
     /// Trigger pricing refresh via D-Bus
-    RefreshPricing,
+    RefreshPricing {
+        /// Start the daemon via D-Bus activation if it is not running
+        #[arg(long)]
+        autostart: bool,
+    },
+
+    /// Log in to a provider by running its CLI login flow
+    Login {
+        /// Provider to log in to (claude, codex, codex-<n>)
+        provider: String,
+
+        /// Print the auth URL instead of opening a browser
+        #[arg(long)]
+        no_browser: bool,
+    },
+
+    /// Install the systemd user unit and D-Bus activation file
+    InstallService {
+        /// Print the files that would be written without touching disk
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Remove previously installed service files
+        #[arg(long)]
+        uninstall: bool,
+
+        /// Overwrite existing files with different content
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Install the tray icons used by `display.icon_mode = "named"`
+    InstallIcons {
+        /// Print the files that would be written without touching disk
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Remove previously installed icons
+        #[arg(long)]
+        uninstall: bool,
+    },
+
+    /// Show the daemon log
+    Logs {
+        /// Keep printing new entries as they are written
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Only show entries newer than this, e.g. 30m, 1h or 2d
+        #[arg(long, value_parser = cli::logs::parse_since)]
+        since: Option<chrono::Duration>,
+
+        /// Only show entries at this level or above
+        #[arg(long, value_enum)]
+        level: Option<cli::logs::LogLevel>,
+    },
+
+    /// Exit with status 2 when a provider's usage is over a threshold
+    ///
+    /// Compares the higher of the session and weekly usage against --max, so
+    /// scripts and Claude Code PreToolUse hooks can stop before a window runs
+    /// out. Status 2 makes such a hook block the tool call; failing to read
+    /// usage exits with 1.
+    ///
+    /// With `limits_file.enabled = true` the daemon also writes the same data
+    /// to ~/.local/share/claude-bar/limits.json (or `limits_file.path`) on
+    /// every update, replacing it atomically. Timestamps are Unix seconds and
+    /// percentages fractions:
+    ///
+    ///   {
+    ///     "schema_version": 2,
+    ///     "version": 2,
+    ///     "updated_at": 1772629200,
+    ///     "providers": {
+    ///       "claude": {
+    ///         "name": "Claude Code",
+    ///         "max_used_percent": 0.93,
+    ///         "session": { "used_percent": 0.93, "resets_at": 1772632800 },
+    ///         "weekly": { "used_percent": 0.41, "resets_at": 1773100800 },
+    ///         "updated_at": 1772629195
+    ///       }
+    ///     }
+    ///   }
+    #[command(verbatim_doc_comment)]
+    LimitCheck {
+        /// Provider to check (claude, codex, codex-<n>)
+        #[arg(long, default_value = "claude")]
+        provider: String,
+
+        /// Usage fraction above which the check fails
+        #[arg(long, default_value = "0.9")]
+        max: f64,
+
+        /// Print the JSON Schema of the limits file
+        #[arg(long)]
+        schema: bool,
+
+        #[command(flatten)]
+        credentials: CredentialArgs,
+    },
 
     /// Generate shell completions
     Completions {
@@ -67,11 +286,32 @@ enum Commands {
     },
 }
 
-fn log_file_path() -> Option<PathBuf> {
-    dirs::data_local_dir().map(|d| d.join("claude-bar").join("claude-bar.log"))
+/// Credential files that replace the configured providers for one run,
+/// e.g. a CI service account's secret mount. Neither the config file nor
+/// the daemon is read.
+#[derive(Args)]
+struct CredentialArgs {
+    /// Read Claude credentials from this file and skip the config and daemon
+    #[arg(long, value_name = "PATH")]
+    claude_credentials: Option<PathBuf>,
+
+    /// Read Codex credentials (auth.json) from this file and skip the config and daemon
+    #[arg(long, value_name = "PATH")]
+    codex_credentials: Option<PathBuf>,
+}
+
+impl From<CredentialArgs> for cli::source::CredentialOverrides {
+    fn from(args: CredentialArgs) -> Self {
+        Self {
+            claude: args.claude_credentials,
+            codex: args.codex_credentials,
+        }
+    }
 }
 
-fn init_logging(for_daemon: bool) {
+/// Sets up tracing. For the daemon this also starts the log writer thread,
+/// which flushes and stops when the returned guard is dropped.
+fn init_logging(for_daemon: bool) -> Option<LogGuard> {
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
 
     let registry = tracing_subscriber::registry().with(filter);
@@ -79,23 +319,15 @@ fn init_logging(for_daemon: bool) {
     if for_daemon {
         let journald_layer = tracing_journald::layer().ok();
 
-        let file_layer = log_file_path().and_then(|path| {
-            if let Some(parent) = path.parent() {
-                if fs::create_dir_all(parent).is_err() {
-                    return None;
-                }
-            }
-            OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&path)
-                .ok()
-                .map(|file| {
-                    fmt::layer()
-                        .json()
-                        .with_writer(file)
-                        .with_span_events(FmtSpan::NONE)
-                })
+        let (file_writer, guard) = log_file_path()
+            .and_then(|path| RotatingFile::open(&path, MAX_LOG_BYTES, KEPT_LOG_FILES).ok())
+            .map(non_blocking)
+            .unzip();
+        let file_layer = file_writer.map(|writer| {
+            fmt::layer()
+                .json()
+                .with_writer(writer)
+                .with_span_events(FmtSpan::NONE)
         });
 
         let console_layer = fmt::layer().with_target(true).with_level(true);
@@ -105,40 +337,158 @@ fn init_logging(for_daemon: bool) {
             .with(file_layer)
             .with(console_layer)
             .init();
+        guard
     } else {
+        // Keep stdout for command output such as JSON or the swaybar stream.
         let console_layer = fmt::layer()
+            .with_writer(io::stderr)
             .with_target(false)
             .with_level(true)
             .compact();
 
         registry.with(console_layer).init();
+        None
     }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
+    crate::core::settings::Settings::select_config_path(cli.config)?;
+    let instance = cli.instance.or_else(|| {
+        crate::core::settings::Settings::load()
+            .ok()
+            .and_then(|settings| settings.daemon.instance_suffix)
+    });
+    core::instance::select(instance)?;
 
     match cli.command {
-        Commands::Daemon => {
-            init_logging(true);
-            daemon::run().await
+        Commands::Daemon {
+            no_ui,
+            dry_run,
+            dry_run_duration,
+        } => {
+            let _log_guard = init_logging(true);
+            let run_for = dry_run_duration.map(std::time::Duration::from_secs);
+            daemon::run(no_ui, dry_run, run_for).await
+        }
+        Commands::Status {
+            json,
+            provider,
+            errors,
+            format,
+            all_windows,
+            watch,
+            interval,
+            output,
+            schema,
+            credentials,
+        } => {
+            init_logging(false);
+            if schema {
+                cli::status::print_schema(errors, watch.then_some(output))
+            } else if watch {
+                cli::status::watch(provider, interval, output, all_windows, cli.color).await
+            } else {
+                cli::status::run(
+                    json,
+                    provider,
+                    errors,
+                    format,
+                    all_windows,
+                    credentials.into(),
+                    cli.color,
+                )
+                .await
+            }
+        }
+        Commands::Cost {
+            json,
+            csv,
+            days,
+            by_project,
+            schema,
+        } => {
+            init_logging(false);
+            if schema {
+                cli::cost::print_schema()
+            } else {
+                cli::cost::run(json, csv, days, by_project, cli.color).await
+            }
+        }
+        Commands::Stats {
+            weekly,
+            days,
+            provider,
+            json,
+            schema,
+        } => {
+            init_logging(false);
+            if schema {
+                cli::stats::print_schema(weekly)
+            } else if weekly {
+                cli::stats::run_weekly(json)
+            } else {
+                cli::stats::run(json, days, provider.as_deref()).await
+            }
+        }
+        Commands::Swaybar {
+            interval,
+            warning,
+            critical,
+        } => {
+            init_logging(false);
+            cli::swaybar::run(interval, cli::swaybar::Thresholds { warning, critical }).await
+        }
+        Commands::Refresh { autostart } => {
+            init_logging(false);
+            cli::refresh::run(autostart).await
+        }
+        Commands::RefreshPricing { autostart } => {
+            init_logging(false);
+            cli::refresh_pricing::run(autostart).await
+        }
+        Commands::Login {
+            provider,
+            no_browser,
+        } => {
+            init_logging(false);
+            cli::login::run(provider, no_browser).await
         }
-        Commands::Status { json, provider } => {
+        Commands::InstallService {
+            dry_run,
+            uninstall,
+            force,
+        } => {
             init_logging(false);
-            cli::status::run(json, provider).await
+            cli::install_service::run(dry_run, uninstall, force).await
         }
-        Commands::Cost { json, days } => {
+        Commands::InstallIcons { dry_run, uninstall } => {
             init_logging(false);
-            cli::cost::run(json, days).await
+            cli::install_icons::run(dry_run, uninstall).await
         }
-        Commands::Refresh => {
+        Commands::Logs {
+            follow,
+            since,
+            level,
+        } => {
             init_logging(false);
-            cli::refresh::run().await
+            cli::logs::run(follow, since, level).await
         }
-        Commands::RefreshPricing => {
+        Commands::LimitCheck {
+            provider,
+            max,
+            schema,
+            credentials,
+        } => {
             init_logging(false);
-            cli::refresh_pricing::run().await
+            if schema {
+                return cli::limit_check::print_schema();
+            }
+            if !cli::limit_check::run(provider, max, credentials.into()).await? {
+                std::process::exit(cli::limit_check::EXCEEDED_EXIT_CODE);
+            }
+            Ok(())
         }
         Commands::Completions { shell } => {
             let mut cmd = Cli::command();
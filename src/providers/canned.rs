@@ -0,0 +1,109 @@
+use super::UsageProvider;
+use crate::core::models::{Provider, ProviderIdentity, RateWindow, UsageSnapshot};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use std::path::PathBuf;
+
+/// Stands in for a provider under `daemon --dry-run`: answers with the same
+/// made-up usage after `delay`, without credentials or network calls.
+pub struct CannedProvider {
+    provider: Provider,
+    label: String,
+    delay: std::time::Duration,
+}
+
+impl CannedProvider {
+    pub fn new(provider: Provider, label: String, delay: std::time::Duration) -> Self {
+        Self {
+            provider,
+            label,
+            delay,
+        }
+    }
+}
+
+#[async_trait]
+impl UsageProvider for CannedProvider {
+    fn name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    fn display_name(&self) -> String {
+        self.label.clone()
+    }
+
+    fn identifier(&self) -> Provider {
+        self.provider
+    }
+
+    async fn fetch_usage(&self) -> Result<UsageSnapshot> {
+        tokio::time::sleep(self.delay).await;
+        Ok(canned_snapshot(Utc::now()))
+    }
+
+    fn dashboard_url(&self) -> &'static str {
+        match self.provider {
+            Provider::Claude => "https://console.anthropic.com/settings/billing",
+            Provider::Codex | Provider::CodexWorkspace(_) => {
+                "https://chatgpt.com/codex/settings/usage"
+            }
+        }
+    }
+
+    fn has_valid_credentials(&self) -> bool {
+        true
+    }
+
+    fn credentials_expire_at(&self) -> Option<DateTime<Utc>> {
+        None
+    }
+
+    fn credential_error_hint(&self) -> &'static str {
+        "Dry run: no credentials are used"
+    }
+
+    fn credentials_path(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// A session window 42% used and a weekly one 18% used, resetting in 2h 14m
+/// and 3 days from `now`.
+fn canned_snapshot(now: DateTime<Utc>) -> UsageSnapshot {
+    let session = RateWindow::from_reported(
+        0.42,
+        Some(300),
+        Some(now + Duration::minutes(2 * 60 + 14)),
+        None,
+    );
+    let weekly = RateWindow::from_reported(0.18, Some(10080), Some(now + Duration::days(3)), None);
+    UsageSnapshot {
+        identity: ProviderIdentity {
+            plan: Some("Dry run".to_string()),
+            ..ProviderIdentity::default()
+        },
+        ..UsageSnapshot::new(Some(session), Some(weekly), now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_canned_provider_answers_without_credentials() {
+        let provider = CannedProvider::new(
+            Provider::CodexWorkspace(1),
+            "Work".to_string(),
+            std::time::Duration::ZERO,
+        );
+        assert_eq!(provider.display_name(), "Work");
+        assert!(provider.has_valid_credentials());
+        assert_eq!(provider.credentials_path(), None);
+
+        let snapshot = provider.fetch_usage().await.unwrap();
+        assert_eq!(snapshot.primary.unwrap().used_percent, 0.42);
+        assert_eq!(snapshot.secondary.unwrap().used_percent, 0.18);
+    }
+}
@@ -1,7 +1,10 @@
+use crate::core::clock::server_clock_skew;
 use crate::core::models::{
     ModelWindow, Provider, ProviderCostSnapshot, ProviderIdentity, RateWindow, UsageSnapshot,
 };
-use crate::providers::UsageProvider;
+use crate::providers::conditional::ConditionalCache;
+use crate::providers::debug_dump::{debug_dir, save_response, UnknownKeys, DUMP_SAVED_PREFIX};
+use crate::providers::{ApiStatusError, UsageProvider};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 #[cfg(test)]
@@ -9,11 +12,33 @@ use chrono::Datelike;
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
 use std::time::Duration;
-use tracing::{debug, warn};
+use tracing::{debug, info, warn};
 
 const DEFAULT_CREDENTIALS_PATH: &str = ".claude/.credentials.json";
 const API_ENDPOINT: &str = "https://api.anthropic.com/api/oauth/usage";
+/// `anthropic-beta` values the usage endpoint is known to accept, newest
+/// first. Tried in order when the API rejects the one in use.
+const KNOWN_BETA_HEADERS: &[&str] = &["oauth-2025-04-20"];
+
+/// Labels for the surfaces in the usage breakdown, by the keys the endpoint
+/// may report them under.
+const SURFACE_LABELS: &[(&[&str], &str)] = &[
+    (&["claude_code", "code"], "Code usage"),
+    (&["claude_ai", "web"], "Web usage"),
+    (&["api"], "API usage"),
+];
+
+/// Top-level keys of the usage response that `OAuthUsageResponse` reads.
+const KNOWN_USAGE_KEYS: &[&str] = &[
+    "five_hour",
+    "seven_day",
+    "seven_day_sonnet",
+    "seven_day_opus",
+    "extra_usage",
+    "surfaces",
+];
 
 #[derive(Debug, Deserialize)]
 struct CredentialsFile {
@@ -28,7 +53,6 @@ struct ClaudeOAuthCredentials {
     #[allow(dead_code)]
     refresh_token: Option<String>,
     expires_at: Option<i64>,
-    #[allow(dead_code)]
     scopes: Option<Vec<String>>,
     rate_limit_tier: Option<String>,
 }
@@ -43,6 +67,22 @@ struct OAuthUsageResponse {
     seven_day_opus: Option<UsageWindow>,
     #[serde(rename = "extra_usage")]
     extra_usage: Option<OAuthExtraUsage>,
+    /// Usage by surface, e.g. `{"claude_code": {"utilization": 12.0}}`, which
+    /// only some accounts get. Left loose so an unexpected shape is ignored
+    /// rather than failing the whole response.
+    #[serde(default)]
+    surfaces: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorResponse {
+    error: ApiError,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    message: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -60,14 +100,33 @@ struct OAuthExtraUsage {
     #[serde(rename = "used_credits")]
     used_credits: Option<f64>,
     currency: Option<String>,
+    /// End of the current billing period, when the pool refills.
+    #[serde(alias = "period_end")]
+    resets_at: Option<String>,
 }
 
 pub struct ClaudeProvider {
     credentials_path: PathBuf,
+    api_endpoint: String,
     http_client: reqwest::Client,
+    usage_cache: ConditionalCache,
+    /// Configured `anthropic-beta` value, tried before the known ones.
+    beta_header: Option<String>,
+    /// The `anthropic-beta` value the API last accepted.
+    active_beta: Mutex<Option<String>>,
+    /// `debug = true`: save unparseable responses and log unknown fields.
+    debug: bool,
+    unknown_keys: UnknownKeys,
 }
 
 impl ClaudeProvider {
+    pub fn with_beta_header(beta_header: Option<String>) -> Self {
+        Self {
+            beta_header,
+            ..Self::new()
+        }
+    }
+
     pub fn new() -> Self {
         let credentials_path = dirs::home_dir()
             .map(|p| p.join(DEFAULT_CREDENTIALS_PATH))
@@ -83,10 +142,108 @@ impl ClaudeProvider {
 
         Self {
             credentials_path,
+            api_endpoint: API_ENDPOINT.to_string(),
             http_client,
+            usage_cache: ConditionalCache::default(),
+            beta_header: None,
+            active_beta: Mutex::new(None),
+            debug: false,
+            unknown_keys: UnknownKeys::new(KNOWN_USAGE_KEYS),
+        }
+    }
+
+    /// Reads credentials from `credentials_path` instead of
+    /// `~/.claude/.credentials.json`, e.g. a service account's secret mount.
+    pub fn with_credentials_path(credentials_path: PathBuf) -> Self {
+        Self {
+            credentials_path,
+            ..Self::new()
+        }
+    }
+
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// The error for a usage response that doesn't parse. In debug mode the
+    /// body is saved for bug reports and the error says where; it never
+    /// contains the bearer token.
+    fn parse_failure(&self, body: &str, error: serde_json::Error) -> anyhow::Error {
+        let error = anyhow::Error::new(error);
+        if !self.debug {
+            return error.context("Failed to parse Claude usage response");
+        }
+        let saved = debug_dir()
+            .context("No data directory for debug dumps")
+            .and_then(|dir| save_response(&dir, "claude-usage", body, Utc::now()));
+        match saved {
+            Ok(path) => error.context(format!(
+                "Failed to parse Claude usage response; {}{}",
+                DUMP_SAVED_PREFIX,
+                path.display()
+            )),
+            Err(e) => {
+                warn!(error = %e, "Failed to save the unparseable Claude usage response");
+                error.context("Failed to parse Claude usage response")
+            }
+        }
+    }
+
+    fn report_unknown_keys(&self, body: &str) {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(body) else {
+            return;
+        };
+        if let Some(keys) = self.unknown_keys.check(&value, Utc::now().date_naive()) {
+            info!(
+                ?keys,
+                "Claude usage response has fields claude-bar doesn't read"
+            );
         }
     }
 
+    /// `anthropic-beta` values to try, in order: the last one accepted, the
+    /// configured one, then the known ones.
+    fn beta_candidates(&self) -> Vec<String> {
+        let active = self
+            .active_beta
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        let mut candidates: Vec<String> = Vec::new();
+        let known = KNOWN_BETA_HEADERS.iter().map(|value| value.to_string());
+        for value in active
+            .into_iter()
+            .chain(self.beta_header.clone())
+            .chain(known)
+        {
+            if !value.is_empty() && !candidates.contains(&value) {
+                candidates.push(value);
+            }
+        }
+        candidates
+    }
+
+    fn remember_beta(&self, beta: &str) {
+        *self
+            .active_beta
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(beta.to_string());
+    }
+
+    /// Whether a 403 body blames the `anthropic-beta` header rather than the
+    /// credentials.
+    fn is_beta_rejection(body: &str) -> bool {
+        serde_json::from_str::<ApiErrorResponse>(body)
+            .is_ok_and(|response| response.error.message.to_lowercase().contains("beta"))
+    }
+
+    /// Why the credentials file can't be used, if it can't: unreadable,
+    /// malformed or without an access token. Expiry isn't checked.
+    pub fn check_credentials(&self) -> Result<()> {
+        self.load_credentials().map(|_| ())
+    }
+
     fn load_credentials(&self) -> Result<ClaudeOAuthCredentials> {
         let content = std::fs::read_to_string(&self.credentials_path).with_context(|| {
             format!(
@@ -95,8 +252,12 @@ impl ClaudeProvider {
             )
         })?;
 
-        let file: CredentialsFile =
-            serde_json::from_str(&content).context("Failed to parse Claude credentials")?;
+        let file: CredentialsFile = serde_json::from_str(&content).with_context(|| {
+            format!(
+                "Failed to parse Claude credentials in {}",
+                self.credentials_path.display()
+            )
+        })?;
 
         if file.claude_ai_oauth.access_token.is_empty() {
             anyhow::bail!("Claude access token is empty");
@@ -123,15 +284,94 @@ impl ClaudeProvider {
     ) -> Option<RateWindow> {
         window.and_then(|w| {
             let utilization = w.utilization?;
-            Some(RateWindow {
-                used_percent: utilization / 100.0,
-                window_minutes: Some(window_minutes),
-                resets_at: Self::parse_reset_time(w.resets_at.as_deref()),
-                reset_description: Some(description.to_string()),
-            })
+            Some(RateWindow::from_reported_percent(
+                utilization,
+                Some(window_minutes),
+                Self::parse_reset_time(w.resets_at.as_deref()),
+                Some(description.to_string()),
+            ))
         })
     }
 
+    fn build_snapshot(
+        usage: &OAuthUsageResponse,
+        credentials: &ClaudeOAuthCredentials,
+    ) -> UsageSnapshot {
+        let primary = Self::window_to_rate_window(usage.five_hour.as_ref(), 300, "5-hour session");
+
+        let secondary =
+            Self::window_to_rate_window(usage.seven_day.as_ref(), 10080, "Weekly quota");
+
+        let model_specific = usage
+            .seven_day_sonnet
+            .as_ref()
+            .or(usage.seven_day_opus.as_ref());
+        let tertiary = Self::window_to_rate_window(model_specific, 10080, "Model weekly");
+
+        let mut carveouts = Vec::new();
+        if let Some(window) =
+            Self::window_to_rate_window(usage.seven_day_sonnet.as_ref(), 10080, "Sonnet weekly")
+        {
+            carveouts.push(ModelWindow {
+                label: "Sonnet Weekly".to_string(),
+                window,
+            });
+        }
+        if let Some(window) =
+            Self::window_to_rate_window(usage.seven_day_opus.as_ref(), 10080, "Opus weekly")
+        {
+            carveouts.push(ModelWindow {
+                label: "Opus Weekly".to_string(),
+                window,
+            });
+        }
+
+        let plan = Self::infer_plan_from_tier(credentials.rate_limit_tier.as_deref());
+        let provider_cost = Self::map_extra_usage(&usage.extra_usage, plan.as_deref());
+        let login_method = Self::infer_login_method(
+            credentials.scopes.as_deref(),
+            credentials.rate_limit_tier.as_deref(),
+        );
+
+        UsageSnapshot {
+            primary,
+            secondary,
+            tertiary,
+            provider_cost,
+            carveouts,
+            surfaces: Self::map_surfaces(usage.surfaces.as_ref()),
+            updated_at: Utc::now(),
+            received_at: None,
+            identity: ProviderIdentity {
+                email: None,
+                organization: None,
+                plan,
+                login_method,
+            },
+        }
+    }
+
+    /// The surfaces in `surfaces` that have a label, in `SURFACE_LABELS`
+    /// order. Entries that don't parse as a usage window are skipped.
+    fn map_surfaces(surfaces: Option<&serde_json::Value>) -> Vec<ModelWindow> {
+        let Some(surfaces) = surfaces.and_then(|value| value.as_object()) else {
+            return Vec::new();
+        };
+        SURFACE_LABELS
+            .iter()
+            .filter_map(|(keys, label)| {
+                let value = keys.iter().find_map(|key| surfaces.get(*key))?;
+                let window = serde_json::from_value::<UsageWindow>(value.clone()).ok()?;
+                // The breakdown is of the weekly quota.
+                let window = Self::window_to_rate_window(Some(&window), 10080, label)?;
+                Some(ModelWindow {
+                    label: label.to_string(),
+                    window,
+                })
+            })
+            .collect()
+    }
+
     fn infer_plan_from_tier(tier: Option<&str>) -> Option<String> {
         let tier = tier.unwrap_or("").to_lowercase();
         if tier.contains("max") {
@@ -149,6 +389,19 @@ impl ClaudeProvider {
         None
     }
 
+    /// A Claude.ai subscription login can call the API itself; a Console
+    /// login only gets to create API keys.
+    fn infer_login_method(scopes: Option<&[String]>, tier: Option<&str>) -> Option<String> {
+        let has_scope = |wanted: &str| scopes.is_some_and(|s| s.iter().any(|s| s == wanted));
+        if has_scope("user:inference") || Self::infer_plan_from_tier(tier).is_some() {
+            return Some("Claude subscription (OAuth)".to_string());
+        }
+        if has_scope("org:create_api_key") {
+            return Some("Console account".to_string());
+        }
+        None
+    }
+
     fn map_extra_usage(
         extra: &Option<OAuthExtraUsage>,
         plan: Option<&str>,
@@ -168,7 +421,7 @@ impl ClaudeProvider {
             limit: normalized.1,
             currency_code: currency_code.to_string(),
             period: Some("Monthly".to_string()),
-            resets_at: None,
+            resets_at: Self::parse_reset_time(extra.resets_at.as_deref()),
             updated_at: Utc::now(),
         };
         Self::rescale_extra_usage_if_needed(snapshot, plan)
@@ -233,88 +486,93 @@ impl UsageProvider for ClaudeProvider {
             }
         }
 
-        debug!("Fetching Claude usage from {}", API_ENDPOINT);
+        let candidates = self.beta_candidates();
+        let mut accepted = None;
+        for beta in &candidates {
+            debug!(beta = %beta, "Fetching Claude usage from {}", self.api_endpoint);
 
-        let response = self
-            .http_client
-            .get(API_ENDPOINT)
-            .header(
-                "Authorization",
-                format!("Bearer {}", credentials.access_token),
-            )
-            .header("Accept", "application/json")
-            .header("Content-Type", "application/json")
-            .header("anthropic-beta", "oauth-2025-04-20")
-            .header("User-Agent", "claude-bar")
-            .send()
-            .await
-            .context("Failed to fetch Claude usage")?;
+            let request = self
+                .http_client
+                .get(&self.api_endpoint)
+                .header(
+                    "Authorization",
+                    format!("Bearer {}", credentials.access_token),
+                )
+                .header("Accept", "application/json")
+                .header("Content-Type", "application/json")
+                .header("anthropic-beta", beta.as_str())
+                .header("User-Agent", "claude-bar");
+            let response = self
+                .usage_cache
+                .apply(request, &credentials.access_token)
+                .send()
+                .await
+                .context("Failed to fetch Claude usage")?;
+            let status = response.status();
+            if status != reqwest::StatusCode::FORBIDDEN {
+                if status.is_success() || status == reqwest::StatusCode::NOT_MODIFIED {
+                    if beta != &candidates[0] {
+                        info!(beta = %beta, "Claude API accepted a fallback anthropic-beta header");
+                    }
+                    self.remember_beta(beta);
+                }
+                accepted = Some(response);
+                break;
+            }
+            let body = response.text().await.unwrap_or_default();
+            if !Self::is_beta_rejection(&body) {
+                anyhow::bail!(
+                    "Claude access forbidden. Credentials may be missing required scope (user:profile)."
+                );
+            }
+            warn!(beta = %beta, body = %body, "Claude API rejected the anthropic-beta header");
+        }
+        let Some(response) = accepted else {
+            anyhow::bail!(
+                "Claude API rejected every anthropic-beta header tried ({}). Set providers.claude.beta_header to a current value.",
+                candidates.join(", ")
+            );
+        };
+        let skew = server_clock_skew(response.headers(), Utc::now());
 
         let status = response.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            debug!("Claude usage unchanged since last fetch");
+            return self
+                .usage_cache
+                .not_modified(&credentials.access_token)
+                .map(|snapshot| snapshot.mark_received(skew))
+                .context("Claude API returned 304 Not Modified without a cached response");
+        }
         if !status.is_success() {
             let body = response.text().await.unwrap_or_default();
             if status.as_u16() == 401 {
                 anyhow::bail!("Claude authentication failed. Run `claude` to refresh credentials.");
-            } else if status.as_u16() == 403 {
-                anyhow::bail!(
-                    "Claude access forbidden. Credentials may be missing required scope (user:profile)."
-                );
             }
-            anyhow::bail!("Claude API error: {} - {}", status, body);
+            return Err(ApiStatusError {
+                provider: "Claude",
+                status,
+                body,
+            }
+            .into());
         }
 
+        let headers = response.headers().clone();
         let body = response.text().await?;
         debug!("Claude API response: {}", body);
 
-        let usage: OAuthUsageResponse =
-            serde_json::from_str(&body).context("Failed to parse Claude usage response")?;
-
-        let primary = Self::window_to_rate_window(usage.five_hour.as_ref(), 300, "5-hour session");
-
-        let secondary =
-            Self::window_to_rate_window(usage.seven_day.as_ref(), 10080, "Weekly quota");
-
-        let model_specific = usage
-            .seven_day_sonnet
-            .as_ref()
-            .or(usage.seven_day_opus.as_ref());
-        let tertiary = Self::window_to_rate_window(model_specific, 10080, "Model weekly");
-
-        let mut carveouts = Vec::new();
-        if let Some(window) =
-            Self::window_to_rate_window(usage.seven_day_sonnet.as_ref(), 10080, "Sonnet weekly")
-        {
-            carveouts.push(ModelWindow {
-                label: "Sonnet Weekly".to_string(),
-                window,
-            });
-        }
-        if let Some(window) =
-            Self::window_to_rate_window(usage.seven_day_opus.as_ref(), 10080, "Opus weekly")
-        {
-            carveouts.push(ModelWindow {
-                label: "Opus Weekly".to_string(),
-                window,
-            });
+        let usage: OAuthUsageResponse = match serde_json::from_str(&body) {
+            Ok(usage) => usage,
+            Err(e) => return Err(self.parse_failure(&body, e)),
+        };
+        if self.debug {
+            self.report_unknown_keys(&body);
         }
 
-        let plan = Self::infer_plan_from_tier(credentials.rate_limit_tier.as_deref());
-        let provider_cost = Self::map_extra_usage(&usage.extra_usage, plan.as_deref());
-
-        Ok(UsageSnapshot {
-            primary,
-            secondary,
-            tertiary,
-            provider_cost,
-            carveouts,
-            updated_at: Utc::now(),
-            identity: ProviderIdentity {
-                email: None,
-                organization: None,
-                plan: plan.clone(),
-                login_method: plan,
-            },
-        })
+        let snapshot = Self::build_snapshot(&usage, &credentials);
+        self.usage_cache
+            .store(&headers, &credentials.access_token, &snapshot);
+        Ok(snapshot.mark_received(skew))
     }
 
     fn dashboard_url(&self) -> &'static str {
@@ -334,6 +592,11 @@ impl UsageProvider for ClaudeProvider {
         true
     }
 
+    fn credentials_expire_at(&self) -> Option<DateTime<Utc>> {
+        let expires_at_ms = self.load_credentials().ok()?.expires_at?;
+        DateTime::from_timestamp_millis(expires_at_ms)
+    }
+
     fn credential_error_hint(&self) -> &'static str {
         "Run `claude` to authenticate"
     }
@@ -385,6 +648,12 @@ mod tests {
             "seven_day_opus": {
                 "utilization": 15.0,
                 "resets_at": "2026-01-24T00:00:00Z"
+            },
+            "extra_usage": {
+                "is_enabled": true,
+                "monthly_limit": 5000,
+                "used_credits": 1250,
+                "period_end": "2026-02-01T00:00:00Z"
             }
         }"#;
 
@@ -399,6 +668,100 @@ mod tests {
         assert_eq!(seven_day.utilization, Some(32.0));
 
         assert!(usage.seven_day_opus.is_some());
+
+        let extra = ClaudeProvider::map_extra_usage(&usage.extra_usage, None).unwrap();
+        assert_eq!(
+            extra.resets_at,
+            Some("2026-02-01T00:00:00Z".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn test_exact_utilization_survives_snapshot_roundtrip() {
+        let credentials = ClaudeOAuthCredentials {
+            access_token: "token".to_string(),
+            refresh_token: None,
+            expires_at: None,
+            scopes: None,
+            rate_limit_tier: None,
+        };
+        let usage: OAuthUsageResponse = serde_json::from_str(
+            r#"{"five_hour":{"utilization":45.5},"seven_day":{"utilization":0.1}}"#,
+        )
+        .unwrap();
+        let snapshot = ClaudeProvider::build_snapshot(&usage, &credentials);
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let restored: UsageSnapshot = serde_json::from_str(&json).unwrap();
+        let session = restored.primary.unwrap();
+        assert_eq!(session.used_percent_exact, Some(45.5));
+        assert_eq!(session.used_text(), "45.5%");
+        let weekly = restored.secondary.unwrap();
+        assert_eq!(weekly.used_percent_exact, Some(0.1));
+        assert_eq!(weekly.used_text(), "0.1%");
+    }
+
+    #[test]
+    fn test_surface_breakdown_is_optional() {
+        let credentials = ClaudeOAuthCredentials {
+            access_token: "token".to_string(),
+            refresh_token: None,
+            expires_at: None,
+            scopes: None,
+            rate_limit_tier: Some("default_claude_max_5x".to_string()),
+        };
+        let parse = |json: &str| {
+            let usage: OAuthUsageResponse = serde_json::from_str(json).unwrap();
+            ClaudeProvider::build_snapshot(&usage, &credentials)
+        };
+        let plain = parse(include_str!("../../tests/fixtures/claude/usage.json"));
+        let with_surfaces = parse(include_str!(
+            "../../tests/fixtures/claude/usage_surfaces.json"
+        ));
+
+        assert!(plain.surfaces.is_empty());
+        let surfaces: Vec<(&str, f64)> = with_surfaces
+            .surfaces
+            .iter()
+            .map(|s| (s.label.as_str(), s.window.used_percent))
+            .collect();
+        // Unknown surfaces and malformed entries are skipped.
+        assert_eq!(surfaces, vec![("Code usage", 0.21), ("Web usage", 0.41)]);
+
+        // Everything else comes out the same either way.
+        let mut without_surfaces = with_surfaces.clone();
+        without_surfaces.surfaces.clear();
+        assert!(without_surfaces.same_usage_as(&plain));
+
+        let odd = parse(r#"{"five_hour":{"utilization":1.0},"surfaces":["code"]}"#);
+        assert!(odd.surfaces.is_empty());
+        assert!(odd.primary.is_some());
+    }
+
+    #[test]
+    fn test_debug_mode_flags_drift_and_dumps_bad_responses() {
+        let today = Utc::now().date_naive();
+        for fixture in [
+            include_str!("../../tests/fixtures/claude/usage.json"),
+            include_str!("../../tests/fixtures/claude/usage_surfaces.json"),
+        ] {
+            let value: serde_json::Value = serde_json::from_str(fixture).unwrap();
+            assert_eq!(
+                UnknownKeys::new(KNOWN_USAGE_KEYS).check(&value, today),
+                None
+            );
+        }
+        let drifted = serde_json::json!({"five_hour": null, "seven_day_cowork": null});
+        assert_eq!(
+            UnknownKeys::new(KNOWN_USAGE_KEYS).check(&drifted, today),
+            Some(vec!["seven_day_cowork".to_string()])
+        );
+
+        let body = r#"{"five_hour":{"utilization":"high"}}"#;
+        let error = serde_json::from_str::<OAuthUsageResponse>(body).unwrap_err();
+        let message = ClaudeProvider::new().parse_failure(body, error).to_string();
+        assert_eq!(message, "Failed to parse Claude usage response");
+        assert_eq!(crate::providers::dump_path_in(&message), None);
     }
 
     #[test]
@@ -437,6 +800,46 @@ mod tests {
         assert_eq!(rw.reset_description, Some("5-hour session".to_string()));
     }
 
+    #[test]
+    fn test_window_to_rate_window_clamps_out_of_range() {
+        let window = |utilization| UsageWindow {
+            utilization: Some(utilization),
+            resets_at: None,
+        };
+
+        let rw = ClaudeProvider::window_to_rate_window(Some(&window(250.0)), 300, "5-hour session")
+            .unwrap();
+        assert_eq!(rw.used_percent, 1.0);
+        assert_eq!(rw.used_percent_exact, Some(250.0));
+
+        let rw = ClaudeProvider::window_to_rate_window(Some(&window(-5.0)), 300, "5-hour session")
+            .unwrap();
+        assert_eq!(rw.used_percent, 0.0);
+        assert_eq!(rw.used_percent_exact, Some(-5.0));
+
+        let rw =
+            ClaudeProvider::window_to_rate_window(Some(&window(f64::NAN)), 300, "5-hour session")
+                .unwrap();
+        assert_eq!(rw.used_percent, 0.0);
+        assert!(rw.was_clamped());
+
+        let rw = ClaudeProvider::window_to_rate_window(Some(&window(78.5)), 300, "5-hour session")
+            .unwrap();
+        assert!(!rw.was_clamped());
+    }
+
+    #[test]
+    fn test_parse_usage_response_without_session_window() {
+        let json = r#"{"seven_day":{"utilization":250.0}}"#;
+        let usage: OAuthUsageResponse = serde_json::from_str(json).unwrap();
+        assert!(ClaudeProvider::window_to_rate_window(usage.five_hour.as_ref(), 300, "").is_none());
+
+        let weekly =
+            ClaudeProvider::window_to_rate_window(usage.seven_day.as_ref(), 10080, "Weekly quota")
+                .unwrap();
+        assert_eq!(weekly.used_percent, 1.0);
+    }
+
     #[test]
     fn test_infer_plan_from_tier() {
         assert_eq!(
@@ -462,6 +865,39 @@ mod tests {
         assert_eq!(ClaudeProvider::infer_plan_from_tier(None), None);
     }
 
+    #[test]
+    fn test_infer_login_method() {
+        let scopes = |scopes: &[&str]| scopes.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            ClaudeProvider::infer_login_method(
+                Some(&scopes(&["user:inference", "user:profile"])),
+                None
+            ),
+            Some("Claude subscription (OAuth)".to_string())
+        );
+        assert_eq!(
+            ClaudeProvider::infer_login_method(
+                Some(&scopes(&["user:profile"])),
+                Some("default_claude_max_20x")
+            ),
+            Some("Claude subscription (OAuth)".to_string())
+        );
+        assert_eq!(
+            ClaudeProvider::infer_login_method(
+                Some(&scopes(&["org:create_api_key", "user:profile"])),
+                None
+            ),
+            Some("Console account".to_string())
+        );
+        // The plan is no longer passed off as the login method.
+        assert_eq!(
+            ClaudeProvider::infer_login_method(Some(&scopes(&["user:profile"])), None),
+            None
+        );
+        assert_eq!(ClaudeProvider::infer_login_method(None, None), None);
+    }
+
     #[test]
     fn test_map_extra_usage_normalization() {
         let extra = OAuthExtraUsage {
@@ -469,6 +905,7 @@ mod tests {
             monthly_limit: Some(12345.0),
             used_credits: Some(2345.0),
             currency: Some("USD".to_string()),
+            resets_at: Some("2026-02-01T00:00:00Z".to_string()),
         };
 
         let snapshot = ClaudeProvider::map_extra_usage(&Some(extra), Some("Claude Pro")).unwrap();
@@ -476,6 +913,10 @@ mod tests {
         assert!((snapshot.limit - 123.45).abs() < 0.001);
         assert_eq!(snapshot.currency_code, "USD");
         assert_eq!(snapshot.period.as_deref(), Some("Monthly"));
+        assert_eq!(
+            snapshot.resets_at,
+            Some("2026-02-01T00:00:00Z".parse().unwrap())
+        );
     }
 
     #[test]
@@ -485,6 +926,7 @@ mod tests {
             monthly_limit: Some(250_000.0),
             used_credits: Some(50_000.0),
             currency: Some("USD".to_string()),
+            resets_at: None,
         };
 
         let snapshot = ClaudeProvider::map_extra_usage(&Some(extra), Some("Claude Pro")).unwrap();
@@ -506,4 +948,133 @@ mod tests {
             "Run `claude` to authenticate"
         );
     }
+
+    /// Serves `responses` in order, one per connection, and returns the
+    /// request heads it received.
+    async fn mock_server(responses: Vec<String>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/oauth/usage", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.ends_with(b"\r\n\r\n") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    head.extend_from_slice(&buf[..n]);
+                }
+                requests.push(String::from_utf8(head).unwrap().to_lowercase());
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+            requests
+        });
+        (url, server)
+    }
+
+    #[tokio::test]
+    async fn test_not_modified_reuses_last_snapshot() {
+        const BODY: &str = r#"{"five_hour":{"utilization":45.0},"seven_day":{"utilization":30.0}}"#;
+        let ok = format!(
+            "HTTP/1.1 200 OK\r\nETag: \"v1\"\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            BODY.len(),
+            BODY
+        );
+        let not_modified =
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                .to_string();
+        let (url, server) = mock_server(vec![ok, not_modified]).await;
+
+        let credentials_path = std::env::temp_dir().join(format!(
+            "claude-bar-claude-etag-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(
+            &credentials_path,
+            r#"{"claudeAiOauth":{"accessToken":"test-token"}}"#,
+        )
+        .unwrap();
+        let provider = ClaudeProvider {
+            credentials_path: credentials_path.clone(),
+            api_endpoint: url,
+            http_client: reqwest::Client::new(),
+            usage_cache: ConditionalCache::default(),
+            beta_header: None,
+            active_beta: Mutex::new(None),
+            debug: false,
+            unknown_keys: UnknownKeys::new(KNOWN_USAGE_KEYS),
+        };
+
+        let first = provider.fetch_usage().await.unwrap();
+        let second = provider.fetch_usage().await.unwrap();
+        std::fs::remove_file(&credentials_path).unwrap();
+
+        assert!(second.same_usage_as(&first));
+        assert!(second.updated_at >= first.updated_at);
+        assert!((second.primary.unwrap().used_percent - 0.45).abs() < 0.001);
+
+        let requests = server.await.unwrap();
+        assert!(!requests[0].contains("if-none-match"));
+        assert!(requests[1].contains("if-none-match: \"v1\""));
+    }
+
+    #[test]
+    fn test_is_beta_rejection() {
+        assert!(ClaudeProvider::is_beta_rejection(
+            r#"{"type":"error","error":{"type":"invalid_request_error","message":"Unexpected value(s) `oauth-2024-01-01` for the `anthropic-beta` header."}}"#
+        ));
+        assert!(!ClaudeProvider::is_beta_rejection(
+            r#"{"type":"error","error":{"type":"permission_error","message":"OAuth token does not meet scope requirement user:profile"}}"#
+        ));
+        assert!(!ClaudeProvider::is_beta_rejection("Forbidden"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_beta_header_falls_back_and_is_remembered() {
+        const BODY: &str = r#"{"five_hour":{"utilization":45.0}}"#;
+        const REJECTED: &str = r#"{"type":"error","error":{"type":"invalid_request_error","message":"Unexpected value(s) `oauth-2099-01-01` for the `anthropic-beta` header."}}"#;
+        let ok = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            BODY.len(),
+            BODY
+        );
+        let forbidden = format!(
+            "HTTP/1.1 403 Forbidden\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            REJECTED.len(),
+            REJECTED
+        );
+        let (url, server) = mock_server(vec![forbidden, ok.clone(), ok]).await;
+
+        let credentials_path = std::env::temp_dir().join(format!(
+            "claude-bar-claude-beta-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(
+            &credentials_path,
+            r#"{"claudeAiOauth":{"accessToken":"test-token"}}"#,
+        )
+        .unwrap();
+        let provider = ClaudeProvider {
+            credentials_path: credentials_path.clone(),
+            api_endpoint: url,
+            beta_header: Some("oauth-2099-01-01".to_string()),
+            ..ClaudeProvider::new()
+        };
+
+        let first = provider.fetch_usage().await.unwrap();
+        let second = provider.fetch_usage().await.unwrap();
+        std::fs::remove_file(&credentials_path).unwrap();
+
+        assert!((first.primary.unwrap().used_percent - 0.45).abs() < 0.001);
+        assert!(second.primary.is_some());
+
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].contains("anthropic-beta: oauth-2099-01-01"));
+        assert!(requests[1].contains("anthropic-beta: oauth-2025-04-20"));
+        // The accepted value is used straight away from then on.
+        assert!(requests[2].contains("anthropic-beta: oauth-2025-04-20"));
+    }
 }
@@ -1,5 +1,9 @@
-use crate::core::models::{Provider, ProviderIdentity, RateWindow, UsageSnapshot};
-use crate::providers::UsageProvider;
+use crate::core::clock::server_clock_skew;
+use crate::core::models::{
+    Provider, ProviderCostSnapshot, ProviderIdentity, RateWindow, UsageSnapshot,
+};
+use crate::providers::conditional::ConditionalCache;
+use crate::providers::{ApiStatusError, UsageProvider};
 use anyhow::{Context, Result};
 use async_trait::async_trait;
 use base64::Engine;
@@ -34,6 +38,10 @@ struct TokenData {
 struct CodexUsageResponse {
     plan_type: Option<String>,
     rate_limit: Option<RateLimitInfo>,
+    /// Only present while a bonus credits pool is active. Kept raw so an
+    /// unexpected shape drops the pool instead of failing the whole fetch.
+    #[serde(default)]
+    credits: Option<Value>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -49,12 +57,49 @@ struct RateLimitWindow {
     limit_window_seconds: Option<i32>,
 }
 
+#[derive(Debug, Deserialize)]
+struct CreditsInfo {
+    has_credits: Option<bool>,
+    unlimited: Option<bool>,
+    used: Option<f64>,
+    limit: Option<f64>,
+    /// Remaining credits, sometimes sent as a string.
+    balance: Option<Value>,
+    expires_at: Option<i64>,
+}
+
 pub struct CodexProvider {
+    provider: Provider,
+    account_id: Option<String>,
+    label: Option<String>,
     credentials_path: PathBuf,
     http_client: reqwest::Client,
+    usage_cache: ConditionalCache,
 }
 
 impl CodexProvider {
+    pub fn for_workspace(
+        provider: Provider,
+        account_id: Option<String>,
+        label: Option<String>,
+    ) -> Self {
+        Self {
+            provider,
+            account_id,
+            label,
+            ..Self::new()
+        }
+    }
+
+    /// Reads credentials from `credentials_path` instead of
+    /// `$CODEX_HOME/auth.json`, e.g. a service account's secret mount.
+    pub fn with_credentials_path(credentials_path: PathBuf) -> Self {
+        Self {
+            credentials_path,
+            ..Self::new()
+        }
+    }
+
     pub fn new() -> Self {
         let credentials_path = std::env::var("CODEX_HOME")
             .map(|home| PathBuf::from(home).join("auth.json"))
@@ -73,11 +118,21 @@ impl CodexProvider {
             });
 
         Self {
+            provider: Provider::Codex,
+            account_id: None,
+            label: None,
             credentials_path,
             http_client,
+            usage_cache: ConditionalCache::default(),
         }
     }
 
+    /// Why the credentials file can't be used, if it can't: unreadable,
+    /// malformed or without an access token. Expiry isn't checked.
+    pub fn check_credentials(&self) -> Result<()> {
+        self.load_credentials().map(|_| ())
+    }
+
     fn load_credentials(&self) -> Result<TokenData> {
         let content = std::fs::read_to_string(&self.credentials_path).with_context(|| {
             format!(
@@ -86,8 +141,12 @@ impl CodexProvider {
             )
         })?;
 
-        let file: CredentialsFile =
-            serde_json::from_str(&content).context("Failed to parse Codex credentials")?;
+        let file: CredentialsFile = serde_json::from_str(&content).with_context(|| {
+            format!(
+                "Failed to parse Codex credentials in {}",
+                self.credentials_path.display()
+            )
+        })?;
 
         if file.tokens.access_token.is_empty() {
             anyhow::bail!("Codex access token is empty");
@@ -105,18 +164,48 @@ impl CodexProvider {
         })
     }
 
+    fn credits_to_provider_cost(credits: Option<&Value>) -> Option<ProviderCostSnapshot> {
+        let credits: CreditsInfo = match serde_json::from_value(credits?.clone()) {
+            Ok(credits) => credits,
+            Err(e) => {
+                debug!(error = %e, "Ignoring unrecognized Codex credits object");
+                return None;
+            }
+        };
+        if credits.has_credits == Some(false) || credits.unlimited == Some(true) {
+            return None;
+        }
+        let limit = credits.limit.filter(|limit| *limit > 0.0)?;
+        let balance = credits
+            .balance
+            .as_ref()
+            .and_then(|b| b.as_f64().or_else(|| b.as_str()?.trim().parse().ok()));
+        let used = credits
+            .used
+            .or_else(|| balance.map(|balance| limit - balance))?;
+
+        Some(ProviderCostSnapshot {
+            used: used.max(0.0),
+            limit,
+            currency_code: "Credits".to_string(),
+            period: None,
+            resets_at: Self::parse_reset_time(credits.expires_at),
+            updated_at: Utc::now(),
+        })
+    }
+
     fn window_to_rate_window(
         window: Option<&RateLimitWindow>,
         description: &str,
     ) -> Option<RateWindow> {
         window.map(|w| {
             let window_minutes = w.limit_window_seconds.map(|s| s / 60);
-            RateWindow {
-                used_percent: f64::from(w.used_percent) / 100.0,
+            RateWindow::from_reported_percent(
+                f64::from(w.used_percent),
                 window_minutes,
-                resets_at: Self::parse_reset_time(w.reset_at),
-                reset_description: Some(description.to_string()),
-            }
+                Self::parse_reset_time(w.reset_at),
+                Some(description.to_string()),
+            )
         })
     }
 
@@ -252,6 +341,34 @@ impl CodexProvider {
         }
     }
 
+    /// How the ChatGPT account behind the tokens signs in, from the id
+    /// token's `auth_provider` claim, e.g. "ChatGPT account (Google)".
+    fn resolve_login_method(id_token: Option<&str>) -> Option<String> {
+        let payload = Self::decode_jwt_payload(id_token?)?;
+        let auth = payload
+            .get("https://api.openai.com/auth")
+            .and_then(|v| v.as_object());
+        let auth_provider = payload
+            .get("auth_provider")
+            .or_else(|| auth.and_then(|a| a.get("auth_provider")))
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|p| !p.is_empty());
+        if let Some(auth_provider) = auth_provider {
+            let label = match auth_provider.to_lowercase().as_str() {
+                "google" | "google-oauth2" => "Google".to_string(),
+                "apple" => "Apple".to_string(),
+                "microsoft" | "windowslive" => "Microsoft".to_string(),
+                "password" | "email" | "auth0" => "email".to_string(),
+                _ => auth_provider.to_string(),
+            };
+            return Some(format!("ChatGPT account ({})", label));
+        }
+        let has_plan = auth.is_some_and(|a| a.contains_key("chatgpt_plan_type"))
+            || payload.get("chatgpt_plan_type").is_some();
+        has_plan.then(|| "ChatGPT account (OAuth)".to_string())
+    }
+
     fn resolve_plan_from_jwt(id_token: Option<&str>) -> Option<String> {
         let payload = Self::decode_jwt_payload(id_token?)?;
         let auth = payload
@@ -282,8 +399,12 @@ impl UsageProvider for CodexProvider {
         "Codex"
     }
 
+    fn display_name(&self) -> String {
+        self.label.clone().unwrap_or_else(|| self.name().to_string())
+    }
+
     fn identifier(&self) -> Provider {
-        Provider::Codex
+        self.provider
     }
 
     async fn fetch_usage(&self) -> Result<UsageSnapshot> {
@@ -309,26 +430,44 @@ impl UsageProvider for CodexProvider {
             .header("Accept", "application/json")
             .header("User-Agent", "claude-bar");
 
-        if let Some(account_id) = &credentials.account_id {
+        let account_id = self.account_id.as_ref().or(credentials.account_id.as_ref());
+        if let Some(account_id) = account_id {
             if !account_id.is_empty() {
                 request = request.header("ChatGPT-Account-Id", account_id);
             }
         }
 
-        let response = request
+        let response = self
+            .usage_cache
+            .apply(request, &credentials.access_token)
             .send()
             .await
             .context("Failed to fetch Codex usage")?;
+        let skew = server_clock_skew(response.headers(), Utc::now());
 
         let status = response.status();
+        if status == reqwest::StatusCode::NOT_MODIFIED {
+            debug!("Codex usage unchanged since last fetch");
+            return self
+                .usage_cache
+                .not_modified(&credentials.access_token)
+                .map(|snapshot| snapshot.mark_received(skew))
+                .context("Codex API returned 304 Not Modified without a cached response");
+        }
         if !status.is_success() {
             let body = response.text().await.unwrap_or_default();
             if status.as_u16() == 401 || status.as_u16() == 403 {
                 anyhow::bail!("Codex authentication failed. Run `codex` to refresh credentials.");
             }
-            anyhow::bail!("Codex API error: {} - {}", status, body);
+            return Err(ApiStatusError {
+                provider: "Codex",
+                status,
+                body,
+            }
+            .into());
         }
 
+        let headers = response.headers().clone();
         let body = response.text().await?;
         debug!("Codex API response: {}", body);
 
@@ -342,6 +481,8 @@ impl UsageProvider for CodexProvider {
             )
         });
 
+        let provider_cost = Self::credits_to_provider_cost(usage.credits.as_ref());
+
         let plan = usage
             .plan_type
             .as_deref()
@@ -351,21 +492,27 @@ impl UsageProvider for CodexProvider {
                     .map(|p| Self::normalize_plan_label(&p))
             });
         let email = Self::resolve_account_email(credentials.id_token.as_deref());
+        let login_method = Self::resolve_login_method(credentials.id_token.as_deref());
 
-        Ok(UsageSnapshot {
+        let snapshot = UsageSnapshot {
             primary,
             secondary,
             tertiary: None,
-            provider_cost: None,
+            provider_cost,
             carveouts: Vec::new(),
+            surfaces: Vec::new(),
             updated_at: Utc::now(),
+            received_at: None,
             identity: ProviderIdentity {
                 email,
-                organization: None,
-                plan: plan.clone(),
-                login_method: plan,
+                organization: self.label.clone(),
+                plan,
+                login_method,
             },
-        })
+        };
+        self.usage_cache
+            .store(&headers, &credentials.access_token, &snapshot);
+        Ok(snapshot.mark_received(skew))
     }
 
     fn dashboard_url(&self) -> &'static str {
@@ -385,6 +532,11 @@ impl UsageProvider for CodexProvider {
         true
     }
 
+    fn credentials_expire_at(&self) -> Option<DateTime<Utc>> {
+        let expires_at_ms = self.load_credentials().ok()?.expires_at?;
+        DateTime::from_timestamp_millis(expires_at_ms)
+    }
+
     fn credential_error_hint(&self) -> &'static str {
         "Run `codex` to authenticate"
     }
@@ -462,6 +614,56 @@ mod tests {
         let usage: CodexUsageResponse = serde_json::from_str(json).unwrap();
         assert!(usage.plan_type.is_none());
         assert!(usage.rate_limit.is_none());
+        assert!(usage.credits.is_none());
+    }
+
+    #[test]
+    fn test_parse_usage_response_with_credits() {
+        let json = r#"{
+            "plan_type": "plus",
+            "rate_limit": {
+                "primary_window": {
+                    "used_percent": 45,
+                    "reset_at": 1737298200,
+                    "limit_window_seconds": 10800
+                },
+                "secondary_window": null
+            },
+            "credits": {
+                "has_credits": true,
+                "unlimited": false,
+                "limit": 500,
+                "balance": "380",
+                "expires_at": 1737903000
+            }
+        }"#;
+
+        let usage: CodexUsageResponse = serde_json::from_str(json).unwrap();
+        assert!(usage.rate_limit.unwrap().primary_window.is_some());
+
+        let cost = CodexProvider::credits_to_provider_cost(usage.credits.as_ref()).unwrap();
+        assert_eq!(cost.currency_code, "Credits");
+        assert_eq!(cost.used, 120.0);
+        assert_eq!(cost.limit, 500.0);
+        assert_eq!(cost.resets_at.unwrap().timestamp(), 1737903000);
+    }
+
+    #[test]
+    fn test_credits_to_provider_cost_skips_inactive_or_unknown() {
+        assert!(CodexProvider::credits_to_provider_cost(None).is_none());
+
+        let credits = |json: &str| {
+            let value: Value = serde_json::from_str(json).unwrap();
+            CodexProvider::credits_to_provider_cost(Some(&value))
+        };
+        assert!(credits(r#"{"has_credits": false, "limit": 500, "used": 10}"#).is_none());
+        assert!(credits(r#"{"has_credits": true, "unlimited": true, "balance": "0"}"#).is_none());
+        assert!(credits(r#"{"has_credits": true, "balance": "12"}"#).is_none());
+        assert!(credits(r#"{"limit": "lots"}"#).is_none());
+
+        let cost = credits(r#"{"used": 30, "limit": 100}"#).unwrap();
+        assert_eq!(cost.used, 30.0);
+        assert!(cost.resets_at.is_none());
     }
 
     #[test]
@@ -489,11 +691,29 @@ mod tests {
 
         let rw = rate_window.unwrap();
         assert!((rw.used_percent - 0.45).abs() < 0.001);
+        assert_eq!(rw.used_percent_exact, Some(45.0));
         assert_eq!(rw.window_minutes, Some(180));
         assert!(rw.resets_at.is_some());
         assert_eq!(rw.reset_description, Some("Session limit".to_string()));
     }
 
+    #[test]
+    fn test_window_to_rate_window_clamps_out_of_range() {
+        let window = |used_percent| RateLimitWindow {
+            used_percent,
+            reset_at: None,
+            limit_window_seconds: Some(10800),
+        };
+
+        let rw = CodexProvider::window_to_rate_window(Some(&window(130)), "Session limit").unwrap();
+        assert_eq!(rw.used_percent, 1.0);
+        assert_eq!(rw.used_percent_exact, Some(130.0));
+
+        let rw = CodexProvider::window_to_rate_window(Some(&window(-10)), "Session limit").unwrap();
+        assert_eq!(rw.used_percent, 0.0);
+        assert_eq!(rw.used_percent_exact, Some(-10.0));
+    }
+
     #[test]
     fn test_format_plan_type() {
         assert_eq!(
@@ -523,6 +743,38 @@ mod tests {
         assert_eq!(CodexProvider::format_plan_type(None), None);
     }
 
+    #[test]
+    fn test_resolve_login_method() {
+        let id_token = |payload: serde_json::Value| {
+            let payload = base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .encode(serde_json::to_vec(&payload).unwrap());
+            format!("header.{}.signature", payload)
+        };
+
+        let google = id_token(serde_json::json!({
+            "email": "me@example.com",
+            "auth_provider": "google",
+            "https://api.openai.com/auth": {"chatgpt_plan_type": "plus"}
+        }));
+        assert_eq!(
+            CodexProvider::resolve_login_method(Some(&google)),
+            Some("ChatGPT account (Google)".to_string())
+        );
+
+        let plan_only = id_token(serde_json::json!({
+            "https://api.openai.com/auth": {"chatgpt_plan_type": "pro"}
+        }));
+        assert_eq!(
+            CodexProvider::resolve_login_method(Some(&plan_only)),
+            Some("ChatGPT account (OAuth)".to_string())
+        );
+
+        let bare = id_token(serde_json::json!({"email": "me@example.com"}));
+        assert_eq!(CodexProvider::resolve_login_method(Some(&bare)), None);
+        assert_eq!(CodexProvider::resolve_login_method(Some("not-a-jwt")), None);
+        assert_eq!(CodexProvider::resolve_login_method(None), None);
+    }
+
     #[test]
     fn test_provider_metadata() {
         let provider = CodexProvider::new();
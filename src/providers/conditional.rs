@@ -0,0 +1,167 @@
+use crate::core::models::UsageSnapshot;
+use chrono::Utc;
+use reqwest::header::{HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::RequestBuilder;
+use std::sync::Mutex;
+
+/// Validators from the last successful usage response, so the next poll can
+/// ask the API whether anything changed instead of downloading it again.
+#[derive(Default)]
+pub struct ConditionalCache {
+    last: Mutex<Option<CachedResponse>>,
+}
+
+struct CachedResponse {
+    /// Validators are only sent with the token they were issued for, so a
+    /// re-login always gets a full response.
+    access_token: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    snapshot: UsageSnapshot,
+}
+
+impl ConditionalCache {
+    /// Adds `If-None-Match`/`If-Modified-Since` when there's a cached
+    /// response for `access_token`.
+    pub fn apply(&self, request: RequestBuilder, access_token: &str) -> RequestBuilder {
+        let last = self.last.lock().unwrap();
+        let Some(cached) = last.as_ref().filter(|c| c.access_token == access_token) else {
+            return request;
+        };
+
+        let mut request = request;
+        if let Some(etag) = &cached.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+
+    /// The cached snapshot, marked as fetched now, for a `304 Not Modified`.
+    /// A provider cost whose period has since reset is dropped.
+    pub fn not_modified(&self, access_token: &str) -> Option<UsageSnapshot> {
+        let mut last = self.last.lock().unwrap();
+        let cached = last.as_mut().filter(|c| c.access_token == access_token)?;
+        let now = Utc::now();
+        cached.snapshot.updated_at = now;
+        cached.snapshot.clear_expired_provider_cost(now);
+        Some(cached.snapshot.clone())
+    }
+
+    /// Remembers a fresh response. Responses without validators clear the
+    /// cache, since there's nothing to revalidate with.
+    pub fn store(&self, headers: &HeaderMap, access_token: &str, snapshot: &UsageSnapshot) {
+        let header = |name| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+
+        *self.last.lock().unwrap() = if etag.is_some() || last_modified.is_some() {
+            Some(CachedResponse {
+                access_token: access_token.to_string(),
+                etag,
+                last_modified,
+                snapshot: snapshot.clone(),
+            })
+        } else {
+            None
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{ProviderCostSnapshot, RateWindow};
+    use reqwest::header::HeaderValue;
+
+    fn snapshot() -> UsageSnapshot {
+        UsageSnapshot::new(
+            Some(RateWindow::from_reported(0.4, Some(300), None, None)),
+            None,
+            Utc::now() - chrono::Duration::minutes(5),
+        )
+    }
+
+    fn request_headers(cache: &ConditionalCache, access_token: &str) -> HeaderMap {
+        let request = reqwest::Client::new().get("http://localhost/usage");
+        cache
+            .apply(request, access_token)
+            .build()
+            .unwrap()
+            .headers()
+            .clone()
+    }
+
+    #[test]
+    fn test_validators_are_sent_for_the_same_token() {
+        let cache = ConditionalCache::default();
+        assert!(request_headers(&cache, "token").is_empty());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        headers.insert(
+            LAST_MODIFIED,
+            HeaderValue::from_static("Wed, 21 Oct 2026 07:28:00 GMT"),
+        );
+        cache.store(&headers, "token", &snapshot());
+
+        let sent = request_headers(&cache, "token");
+        assert_eq!(sent[IF_NONE_MATCH], "\"abc\"");
+        assert_eq!(sent[IF_MODIFIED_SINCE], "Wed, 21 Oct 2026 07:28:00 GMT");
+        assert!(request_headers(&cache, "other-token").is_empty());
+        assert!(cache.not_modified("other-token").is_none());
+    }
+
+    #[test]
+    fn test_not_modified_refreshes_fetch_time() {
+        let cache = ConditionalCache::default();
+        let original = snapshot();
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        cache.store(&headers, "token", &original);
+
+        let reused = cache.not_modified("token").unwrap();
+        assert!(reused.same_usage_as(&original));
+        assert!(reused.updated_at > original.updated_at);
+    }
+
+    #[test]
+    fn test_not_modified_drops_provider_cost_after_reset() {
+        let cache = ConditionalCache::default();
+        let mut original = snapshot();
+        original.provider_cost = Some(ProviderCostSnapshot {
+            used: 40.0,
+            limit: 50.0,
+            currency_code: "USD".to_string(),
+            period: Some("Monthly".to_string()),
+            resets_at: Some(Utc::now() - chrono::Duration::minutes(1)),
+            updated_at: original.updated_at,
+        });
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        cache.store(&headers, "token", &original);
+
+        let reused = cache.not_modified("token").unwrap();
+        assert!(reused.provider_cost.is_none());
+        assert_eq!(reused.primary, original.primary);
+    }
+
+    #[test]
+    fn test_response_without_validators_clears_cache() {
+        let cache = ConditionalCache::default();
+        let mut headers = HeaderMap::new();
+        headers.insert(ETAG, HeaderValue::from_static("\"abc\""));
+        cache.store(&headers, "token", &snapshot());
+
+        cache.store(&HeaderMap::new(), "token", &snapshot());
+        assert!(request_headers(&cache, "token").is_empty());
+        assert!(cache.not_modified("token").is_none());
+    }
+}
@@ -0,0 +1,151 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Raw responses kept per provider; older ones are deleted.
+pub const MAX_DUMPS: usize = 5;
+
+/// Follows the path in a parse error, so the popup can point at the dump.
+pub const DUMP_SAVED_PREFIX: &str = "raw response saved to ";
+
+/// `~/.local/share/claude-bar/debug`, where unparseable responses go.
+pub fn debug_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join("claude-bar").join("debug"))
+}
+
+/// Writes `body` to `<dir>/<name>-<timestamp>.json` and deletes all but the
+/// newest `MAX_DUMPS` files of that name.
+pub fn save_response(dir: &Path, name: &str, body: &str, now: DateTime<Utc>) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!(
+        "{}-{}.json",
+        name,
+        now.format("%Y%m%dT%H%M%S%.3fZ")
+    ));
+    std::fs::write(&path, body)?;
+
+    let prefix = format!("{}-", name);
+    let mut dumps: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".json"))
+        })
+        .collect();
+    // The timestamp sorts oldest first.
+    dumps.sort();
+    let excess = dumps.len().saturating_sub(MAX_DUMPS);
+    for old in &dumps[..excess] {
+        if let Err(e) = std::fs::remove_file(old) {
+            tracing::debug!(path = %old.display(), error = %e, "Failed to delete old debug dump");
+        }
+    }
+    Ok(path)
+}
+
+/// The dump path in an error message built with `DUMP_SAVED_PREFIX`.
+pub fn dump_path_in(error: &str) -> Option<&str> {
+    let (_, path) = error.split_once(DUMP_SAVED_PREFIX)?;
+    Some(path.trim()).filter(|path| !path.is_empty())
+}
+
+/// Top-level keys of successful responses that the typed struct doesn't
+/// read, reported at most once a day as early warning of API changes.
+pub struct UnknownKeys {
+    known: &'static [&'static str],
+    reported_on: Mutex<Option<NaiveDate>>,
+}
+
+impl UnknownKeys {
+    pub const fn new(known: &'static [&'static str]) -> Self {
+        Self {
+            known,
+            reported_on: Mutex::new(None),
+        }
+    }
+
+    /// The unknown keys in `body`, unless already reported today.
+    pub fn check(&self, body: &serde_json::Value, today: NaiveDate) -> Option<Vec<String>> {
+        let object = body.as_object()?;
+        let unknown: Vec<String> = object
+            .keys()
+            .filter(|key| !self.known.contains(&key.as_str()))
+            .cloned()
+            .collect();
+        if unknown.is_empty() {
+            return None;
+        }
+        let mut reported_on = self.reported_on.lock().ok()?;
+        if *reported_on == Some(today) {
+            return None;
+        }
+        *reported_on = Some(today);
+        Some(unknown)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_save_response_keeps_newest_dumps() {
+        let dir = std::env::temp_dir().join(format!("claude-bar-debug-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("codex-usage-20260101T000000.000Z.json"), "{}").unwrap();
+
+        let start: DateTime<Utc> = "2026-03-04T12:00:00Z".parse().unwrap();
+        let paths: Vec<PathBuf> = (0..7)
+            .map(|i| {
+                let body = format!("{{\"n\":{}}}", i);
+                save_response(&dir, "claude-usage", &body, start + Duration::seconds(i)).unwrap()
+            })
+            .collect();
+
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        left.sort();
+        assert_eq!(left.len(), MAX_DUMPS + 1);
+        assert_eq!(left[0], "claude-usage-20260304T120002.000Z.json");
+        assert!(left.contains(&"codex-usage-20260101T000000.000Z.json".to_string()));
+        assert_eq!(std::fs::read_to_string(&paths[6]).unwrap(), "{\"n\":6}");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_dump_path_in_error() {
+        let error = format!(
+            "Failed to parse Claude usage response; {}/tmp/debug/claude-usage.json",
+            DUMP_SAVED_PREFIX
+        );
+        assert_eq!(dump_path_in(&error), Some("/tmp/debug/claude-usage.json"));
+        assert_eq!(dump_path_in("Failed to parse Claude usage response"), None);
+    }
+
+    #[test]
+    fn test_unknown_keys_reported_once_a_day() {
+        static KNOWN: &[&str] = &["five_hour", "seven_day"];
+        let keys = UnknownKeys::new(KNOWN);
+        let today = NaiveDate::from_ymd_opt(2026, 3, 4).unwrap();
+        let known_only = serde_json::json!({"five_hour": null, "seven_day": {}});
+        let drifted = serde_json::json!({"five_hour": null, "seven_day_cowork": {}});
+
+        assert_eq!(keys.check(&known_only, today), None);
+        assert_eq!(
+            keys.check(&drifted, today),
+            Some(vec!["seven_day_cowork".to_string()])
+        );
+        assert_eq!(keys.check(&drifted, today), None);
+        assert_eq!(
+            keys.check(&drifted, today + Duration::days(1)),
+            Some(vec!["seven_day_cowork".to_string()])
+        );
+        assert_eq!(keys.check(&serde_json::json!([1, 2]), today), None);
+    }
+}
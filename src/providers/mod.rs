@@ -1,25 +1,71 @@
+mod canned;
 mod claude;
 mod codex;
+mod conditional;
+mod debug_dump;
 
-use crate::core::models::{Provider, UsageSnapshot};
+use crate::core::models::{ErrorKind, Provider, UsageSnapshot};
 use crate::core::settings::Settings;
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
+pub use canned::CannedProvider;
 pub use claude::ClaudeProvider;
 pub use codex::CodexProvider;
+pub use debug_dump::dump_path_in;
+
+/// How long a `CannedProvider` takes to answer, so the tray's loading state
+/// shows as it would on a real fetch.
+const CANNED_FETCH_DELAY: Duration = Duration::from_millis(500);
+
+/// A usage API answered with an error status. It stays on the error chain
+/// so the daemon can tell an outage from a broken login.
+#[derive(Debug, thiserror::Error)]
+#[error("{provider} API error: {status} - {body}")]
+pub struct ApiStatusError {
+    pub provider: &'static str,
+    pub status: reqwest::StatusCode,
+    pub body: String,
+}
+
+/// What kind of failure a `fetch_usage` error is, from the status or
+/// transport error on its chain when there is one.
+pub fn error_kind(error: &anyhow::Error) -> ErrorKind {
+    for cause in error.chain() {
+        if let Some(api) = cause.downcast_ref::<ApiStatusError>() {
+            return ErrorKind::from_status(api.status.as_u16());
+        }
+        if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+            if error.is_decode() {
+                return ErrorKind::InvalidResponse;
+            }
+            if let Some(status) = error.status() {
+                return ErrorKind::from_status(status.as_u16());
+            }
+            return ErrorKind::Network;
+        }
+    }
+    ErrorKind::classify(&error.to_string())
+}
 
 #[async_trait]
 pub trait UsageProvider: Send + Sync {
     fn name(&self) -> &'static str;
+    fn display_name(&self) -> String {
+        self.name().to_string()
+    }
     fn identifier(&self) -> Provider;
     async fn fetch_usage(&self) -> Result<UsageSnapshot>;
     #[allow(dead_code)]
     fn dashboard_url(&self) -> &'static str;
     fn has_valid_credentials(&self) -> bool;
+    /// When the stored access token expires, if the credentials say.
+    fn credentials_expire_at(&self) -> Option<DateTime<Utc>>;
     fn credential_error_hint(&self) -> &'static str;
     fn credentials_path(&self) -> Option<PathBuf>;
 }
@@ -33,16 +79,43 @@ impl ProviderRegistry {
         let mut providers: Vec<Arc<dyn UsageProvider>> = Vec::new();
 
         if settings.providers.claude.enabled {
-            providers.push(Arc::new(ClaudeProvider::new()));
+            providers.push(Arc::new(
+                ClaudeProvider::with_beta_header(settings.providers.claude.beta_header.clone())
+                    .with_debug(settings.debug),
+            ));
         }
 
         if settings.providers.codex.enabled {
-            providers.push(Arc::new(CodexProvider::new()));
+            let codex = &settings.providers.codex;
+            for provider in codex.instances() {
+                providers.push(Arc::new(CodexProvider::for_workspace(
+                    provider,
+                    codex.account_id_for(provider),
+                    codex.label_for(provider),
+                )));
+            }
         }
 
         Self { providers }
     }
 
+    /// A `CannedProvider` for each provider `settings` enables, for
+    /// `daemon --dry-run`.
+    pub fn canned(settings: &Settings) -> Self {
+        let providers = settings
+            .enabled_providers()
+            .into_iter()
+            .map(|provider| {
+                Arc::new(CannedProvider::new(
+                    provider,
+                    settings.provider_label(provider),
+                    CANNED_FETCH_DELAY,
+                )) as Arc<dyn UsageProvider>
+            })
+            .collect();
+        Self { providers }
+    }
+
     #[allow(dead_code)]
     pub fn enabled_providers(&self) -> impl Iterator<Item = &dyn UsageProvider> {
         self.providers.iter().map(|p| p.as_ref())
@@ -57,6 +130,7 @@ impl ProviderRegistry {
         self.providers.first().map(|p| p.as_ref())
     }
 
+    #[allow(dead_code)]
     pub async fn fetch_all(&self) -> HashMap<Provider, Result<UsageSnapshot>> {
         let mut results = HashMap::new();
 
@@ -91,3 +165,91 @@ impl ProviderRegistry {
             .map(|p| p.as_ref())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_canned_registry_follows_settings() {
+        let mut settings = Settings::default();
+        settings.providers.codex.enabled = true;
+        let registry = ProviderRegistry::canned(&settings);
+        assert_eq!(
+            registry.enabled_provider_ids(),
+            settings.enabled_providers()
+        );
+        assert!(registry.credentials_paths().is_empty());
+    }
+
+    #[test]
+    fn test_error_kind_reads_status_from_chain() {
+        let status = |code: u16| -> anyhow::Error {
+            ApiStatusError {
+                provider: "Claude",
+                status: reqwest::StatusCode::from_u16(code).unwrap(),
+                body: "{}".to_string(),
+            }
+            .into()
+        };
+        assert_eq!(error_kind(&status(429)), ErrorKind::RateLimited);
+        assert_eq!(error_kind(&status(503)), ErrorKind::Server);
+        assert_eq!(error_kind(&status(401)), ErrorKind::Auth);
+        assert_eq!(
+            status(502).to_string(),
+            "Claude API error: 502 Bad Gateway - {}"
+        );
+
+        let wrapped = Err::<(), _>(status(500))
+            .context("Refreshing Claude usage")
+            .unwrap_err();
+        assert_eq!(error_kind(&wrapped), ErrorKind::Server);
+
+        let untyped =
+            anyhow::anyhow!("Claude authentication failed. Run `claude` to refresh credentials.");
+        assert_eq!(error_kind(&untyped), ErrorKind::Auth);
+    }
+
+    #[tokio::test]
+    async fn test_error_kind_tells_reqwest_errors_apart() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for response in [
+                "HTTP/1.1 200 OK\r\nContent-Length: 9\r\nConnection: close\r\n\r\nnot json!",
+                "HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            ] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await.unwrap();
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let client = reqwest::Client::new();
+        let decode = client
+            .get(&url)
+            .send()
+            .await
+            .unwrap()
+            .json::<serde_json::Value>()
+            .await
+            .unwrap_err();
+        assert_eq!(error_kind(&decode.into()), ErrorKind::InvalidResponse);
+
+        let status = client
+            .get(&url)
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap_err();
+        assert_eq!(error_kind(&status.into()), ErrorKind::Server);
+
+        let refused = client.get("http://127.0.0.1:1/").send().await.unwrap_err();
+        assert_eq!(error_kind(&refused.into()), ErrorKind::Network);
+    }
+}
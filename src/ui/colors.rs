@@ -6,25 +6,110 @@ pub const CODEX_HEX: &str = "#10A37F";
 pub const CLAUDE_RGB: (u8, u8, u8) = (245, 166, 35);
 pub const CODEX_RGB: (u8, u8, u8) = (16, 163, 127);
 
+/// Adwaita's yellow and red, for bars nearing and past a limit.
+const WARNING_RGB: (u8, u8, u8) = (229, 165, 10);
+const CRITICAL_RGB: (u8, u8, u8) = (224, 27, 36);
+const DEFICIT_RGB: (u8, u8, u8) = (230, 77, 77);
+const RESERVE_RGB: (u8, u8, u8) = (77, 179, 102);
+
+/// Okabe-Ito orange, vermillion and blue, which stay apart with red-green
+/// color blindness.
+const COLORBLIND_WARNING_RGB: (u8, u8, u8) = (230, 159, 0);
+const COLORBLIND_CRITICAL_RGB: (u8, u8, u8) = (213, 94, 0);
+const COLORBLIND_RESERVE_RGB: (u8, u8, u8) = (0, 114, 178);
+
+/// The colors for states, from `display.colorblind_mode`. Everything that
+/// colors a warning, a limit or a pace marker asks this.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ColorScheme {
+    #[default]
+    Standard,
+    /// A red-green safe palette, with pace markers told apart by shape and
+    /// the tray icon's bars by a divider rather than color alone.
+    Colorblind,
+}
+
+impl ColorScheme {
+    pub fn new(colorblind_mode: bool) -> Self {
+        if colorblind_mode {
+            Self::Colorblind
+        } else {
+            Self::Standard
+        }
+    }
+
+    pub fn is_colorblind(self) -> bool {
+        self == Self::Colorblind
+    }
+
+    /// Usage nearing a limit.
+    pub fn warning_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Standard => WARNING_RGB,
+            Self::Colorblind => COLORBLIND_WARNING_RGB,
+        }
+    }
+
+    /// Usage at a limit, and errors.
+    pub fn critical_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Standard => CRITICAL_RGB,
+            Self::Colorblind => COLORBLIND_CRITICAL_RGB,
+        }
+    }
+
+    /// A pace marker for usage ahead of an even pace.
+    pub fn deficit_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Standard => DEFICIT_RGB,
+            Self::Colorblind => COLORBLIND_CRITICAL_RGB,
+        }
+    }
+
+    /// A pace marker for usage behind an even pace.
+    pub fn reserve_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Self::Standard => RESERVE_RGB,
+            Self::Colorblind => COLORBLIND_RESERVE_RGB,
+        }
+    }
+}
+
 pub fn provider_hex(provider: Provider) -> &'static str {
     match provider {
         Provider::Claude => CLAUDE_HEX,
-        Provider::Codex => CODEX_HEX,
+        Provider::Codex | Provider::CodexWorkspace(_) => CODEX_HEX,
     }
 }
 
 pub fn provider_rgb(provider: Provider) -> (u8, u8, u8) {
     match provider {
         Provider::Claude => CLAUDE_RGB,
-        Provider::Codex => CODEX_RGB,
+        Provider::Codex | Provider::CodexWorkspace(_) => CODEX_RGB,
     }
 }
 
 pub fn muted_rgb(color: (u8, u8, u8)) -> (u8, u8, u8) {
+    scale_rgb(color, 0.35)
+}
+
+/// Empty-bar tone for light panels. `muted_rgb` lands close to a light panel
+/// once blended, so this goes darker.
+pub fn muted_rgb_light(color: (u8, u8, u8)) -> (u8, u8, u8) {
+    scale_rgb(color, 0.2)
+}
+
+/// Provider accent toned down for light panels so the bright orange doesn't
+/// bloom against them.
+pub fn accent_rgb_light(color: (u8, u8, u8)) -> (u8, u8, u8) {
+    scale_rgb(color, 0.85)
+}
+
+fn scale_rgb(color: (u8, u8, u8), factor: f32) -> (u8, u8, u8) {
     let (r, g, b) = color;
     (
-        (r as f32 * 0.35) as u8,
-        (g as f32 * 0.35) as u8,
-        (b as f32 * 0.35) as u8,
+        (r as f32 * factor) as u8,
+        (g as f32 * factor) as u8,
+        (b as f32 * factor) as u8,
     )
 }
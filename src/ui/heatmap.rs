@@ -0,0 +1,162 @@
+use crate::ui::popup_model::HeatmapCell;
+use gtk4::gdk;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+
+const ROWS: usize = 7;
+const COLUMNS: usize = 24;
+const CELL_HEIGHT: f32 = 12.0;
+const GAP: f32 = 2.0;
+
+glib::wrapper! {
+    pub struct HeatmapGrid(ObjectSubclass<imp::HeatmapGridPriv>)
+        @extends gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
+}
+
+impl HeatmapGrid {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    /// Seven rows of 24 cells, as built by `InsightsModel`.
+    pub fn set_cells(&self, rows: &[Vec<HeatmapCell>]) {
+        self.imp().rows.replace(rows.to_vec());
+        self.queue_draw();
+    }
+
+    pub fn set_accent(&self, accent: gdk::RGBA) {
+        self.imp().accent.replace(accent);
+        self.queue_draw();
+    }
+}
+
+impl Default for HeatmapGrid {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn cell_width(width: f32) -> f32 {
+    ((width - GAP * (COLUMNS - 1) as f32) / COLUMNS as f32).max(1.0)
+}
+
+/// The (row, column) under a point, or `None` over a gap or outside the grid.
+fn cell_at(width: f32, x: f32, y: f32) -> Option<(usize, usize)> {
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+    let column_pitch = cell_width(width) + GAP;
+    let row_pitch = CELL_HEIGHT + GAP;
+    let (column, row) = ((x / column_pitch) as usize, (y / row_pitch) as usize);
+    let in_cell = x % column_pitch < cell_width(width) && y % row_pitch < CELL_HEIGHT;
+    (in_cell && row < ROWS && column < COLUMNS).then_some((row, column))
+}
+
+mod imp {
+    use super::*;
+    use std::cell::RefCell;
+
+    pub struct HeatmapGridPriv {
+        pub rows: RefCell<Vec<Vec<HeatmapCell>>>,
+        pub accent: RefCell<gdk::RGBA>,
+    }
+
+    impl Default for HeatmapGridPriv {
+        fn default() -> Self {
+            Self {
+                rows: RefCell::new(Vec::new()),
+                accent: RefCell::new(gdk::RGBA::new(0.96, 0.65, 0.14, 1.0)),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for HeatmapGridPriv {
+        const NAME: &'static str = "ClaudeBarHeatmapGrid";
+        type Type = super::HeatmapGrid;
+        type ParentType = gtk4::Widget;
+    }
+
+    impl ObjectImpl for HeatmapGridPriv {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("heatmap-grid");
+            obj.set_has_tooltip(true);
+            obj.connect_query_tooltip(|grid, x, y, _keyboard, tooltip| {
+                let Some((row, column)) = cell_at(grid.width() as f32, x as f32, y as f32) else {
+                    return false;
+                };
+                let rows = grid.imp().rows.borrow();
+                let Some(cell) = rows.get(row).and_then(|cells| cells.get(column)) else {
+                    return false;
+                };
+                tooltip.set_text(Some(cell.tooltip.as_str()));
+                true
+            });
+        }
+    }
+
+    impl WidgetImpl for HeatmapGridPriv {
+        fn snapshot(&self, snapshot: &gtk4::Snapshot) {
+            let width = self.obj().width() as f32;
+            if width <= 0.0 {
+                return;
+            }
+
+            let cell_width = cell_width(width);
+            let accent = *self.accent.borrow();
+            let corner = gtk4::graphene::Size::new(2.0, 2.0);
+            for (row, cells) in self.rows.borrow().iter().enumerate() {
+                for (column, cell) in cells.iter().enumerate() {
+                    // Idle hours stay faintly visible so the grid keeps its shape.
+                    let alpha = if cell.intensity > 0.0 {
+                        0.15 + 0.85 * cell.intensity.clamp(0.0, 1.0) as f32
+                    } else {
+                        0.06
+                    };
+                    let color = gdk::RGBA::new(accent.red(), accent.green(), accent.blue(), alpha);
+                    let rect = gtk4::graphene::Rect::new(
+                        column as f32 * (cell_width + GAP),
+                        row as f32 * (CELL_HEIGHT + GAP),
+                        cell_width,
+                        CELL_HEIGHT,
+                    );
+                    let rounded = gtk4::gsk::RoundedRect::new(rect, corner, corner, corner, corner);
+                    snapshot.push_rounded_clip(&rounded);
+                    snapshot.append_color(&color, &rect);
+                    snapshot.pop();
+                }
+            }
+        }
+
+        fn measure(&self, orientation: gtk4::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+            let height = (CELL_HEIGHT * ROWS as f32 + GAP * (ROWS - 1) as f32) as i32;
+            match orientation {
+                gtk4::Orientation::Horizontal => (COLUMNS as i32 * 6, COLUMNS as i32 * 10, -1, -1),
+                gtk4::Orientation::Vertical => (height, height, -1, -1),
+                _ => (0, 0, -1, -1),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_at() {
+        // 24 cells of 10px with 2px gaps.
+        let width = 24.0 * 10.0 + 23.0 * 2.0;
+        assert_eq!(cell_at(width, 0.0, 0.0), Some((0, 0)));
+        assert_eq!(cell_at(width, 12.5, 14.5), Some((1, 1)));
+        assert_eq!(cell_at(width, 10.5, 0.0), None);
+        assert_eq!(cell_at(width, 5.0, 12.5), None);
+        assert_eq!(cell_at(width, width - 1.0, 6.0 * 14.0 + 1.0), Some((6, 23)));
+        assert_eq!(cell_at(width, 5.0, 7.0 * 14.0 + 1.0), None);
+        assert_eq!(cell_at(width, -1.0, 5.0), None);
+    }
+}
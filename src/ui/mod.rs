@@ -1,10 +1,18 @@
+mod heatmap;
 mod popup;
+mod popup_model;
 mod pace;
+mod placement;
 mod progress;
+mod sound;
+mod week_strip;
 pub mod styles;
 pub mod colors;
 
-pub use popup::PopupWindow;
+pub use heatmap::HeatmapGrid;
+pub use popup::{system_animations_enabled, PopupWindow, ViewChange};
 pub use pace::{UsagePaceStage, UsagePaceText};
+pub use sound::play_alert_sound;
 #[allow(unused_imports)]
 pub use progress::UsageProgressBar;
+pub use week_strip::WeekStrip;
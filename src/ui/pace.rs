@@ -22,7 +22,11 @@ pub struct UsagePace {
 }
 
 impl UsagePace {
-    pub fn weekly(window: &RateWindow, now: DateTime<Utc>, default_window_minutes: i32) -> Option<Self> {
+    pub fn for_window(
+        window: &RateWindow,
+        now: DateTime<Utc>,
+        default_window_minutes: i32,
+    ) -> Option<Self> {
         let resets_at = window.resets_at?;
         let minutes = window.window_minutes.unwrap_or(default_window_minutes);
         if minutes <= 0 {
@@ -74,7 +78,7 @@ impl UsagePace {
     }
 }
 
-pub struct WeeklyPaceDetail {
+pub struct PaceDetail {
     pub left_label: String,
     pub right_label: Option<String>,
     pub expected_used_percent: f64,
@@ -86,32 +90,58 @@ pub struct UsagePaceText;
 impl UsagePaceText {
     const MINIMUM_EXPECTED_PERCENT: f64 = 3.0;
 
-    pub fn weekly_summary(provider: Provider, window: &RateWindow, now: DateTime<Utc>) -> Option<String> {
-        let detail = Self::weekly_detail(provider, window, now)?;
+    /// The pace line for a window `default_window_minutes` long unless it
+    /// reports its own length. With `show_as_remaining` the run-out estimate
+    /// talks about what is left rather than the limit.
+    pub fn summary(
+        provider: Provider,
+        window: &RateWindow,
+        default_window_minutes: i32,
+        show_as_remaining: bool,
+        now: DateTime<Utc>,
+    ) -> Option<String> {
+        let detail = Self::detail(
+            provider,
+            window,
+            default_window_minutes,
+            show_as_remaining,
+            now,
+        )?;
         if let Some(right) = detail.right_label.as_ref() {
             return Some(format!("Pace: {} · {}", detail.left_label, right));
         }
         Some(format!("Pace: {}", detail.left_label))
     }
 
-    pub fn weekly_detail(provider: Provider, window: &RateWindow, now: DateTime<Utc>) -> Option<WeeklyPaceDetail> {
-        let pace = Self::weekly_pace(provider, window, now)?;
-        Some(WeeklyPaceDetail {
+    pub fn detail(
+        provider: Provider,
+        window: &RateWindow,
+        default_window_minutes: i32,
+        show_as_remaining: bool,
+        now: DateTime<Utc>,
+    ) -> Option<PaceDetail> {
+        let pace = Self::pace(provider, window, default_window_minutes, now)?;
+        Some(PaceDetail {
             left_label: Self::detail_left_label(&pace),
-            right_label: Self::detail_right_label(&pace, now),
+            right_label: Self::detail_right_label(&pace, show_as_remaining, now),
             expected_used_percent: pace.expected_used_percent,
             stage: pace.stage,
         })
     }
 
-    fn weekly_pace(provider: Provider, window: &RateWindow, now: DateTime<Utc>) -> Option<UsagePace> {
-        if provider != Provider::Claude && provider != Provider::Codex {
+    fn pace(
+        provider: Provider,
+        window: &RateWindow,
+        default_window_minutes: i32,
+        now: DateTime<Utc>,
+    ) -> Option<UsagePace> {
+        if provider != Provider::Claude && !provider.is_codex() {
             return None;
         }
         if window.remaining_percent() <= 0.0 {
             return None;
         }
-        let pace = UsagePace::weekly(window, now, 10080)?;
+        let pace = UsagePace::for_window(window, now, default_window_minutes)?;
         if pace.expected_used_percent < Self::MINIMUM_EXPECTED_PERCENT {
             return None;
         }
@@ -131,16 +161,22 @@ impl UsagePaceText {
         }
     }
 
-    fn detail_right_label(pace: &UsagePace, now: DateTime<Utc>) -> Option<String> {
+    fn detail_right_label(
+        pace: &UsagePace,
+        show_as_remaining: bool,
+        now: DateTime<Utc>,
+    ) -> Option<String> {
         if pace.will_last_to_reset {
             return Some("Lasts until reset".to_string());
         }
         let eta_seconds = pace.eta_seconds?;
         let eta_text = duration_text(eta_seconds, now);
-        if eta_text == "now" {
-            return Some("Runs out now".to_string());
-        }
-        Some(format!("Runs out in {}", eta_text))
+        Some(match (show_as_remaining, eta_text == "now") {
+            (false, true) => "Runs out now".to_string(),
+            (false, false) => format!("Runs out in {}", eta_text),
+            (true, true) => "Remaining runs out now".to_string(),
+            (true, false) => format!("Remaining lasts {}", eta_text),
+        })
     }
 }
 
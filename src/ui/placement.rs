@@ -0,0 +1,239 @@
+use crate::core::settings::PopupAnchor;
+use gtk4::gdk;
+use gtk4::prelude::*;
+use gtk4_layer_shell::LayerShell;
+
+/// Kept clear between the popup and the screen edge, so its shadow shows.
+const EDGE_GAP: i32 = 8;
+/// A popup never gets shorter than this, however small the screen.
+const MIN_POPUP_HEIGHT: i32 = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rect {
+    fn contains(&self, (x, y): (i32, i32)) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// Places a popup of `width` x `height` next to the tray click point when
+/// layer-shell is unavailable. The anchor picks which popup corner sits on the
+/// click point (e.g. `TopRight` drops the popup down and to the left of a
+/// top-right tray), and the result is clamped into the monitor workarea.
+pub fn fallback_position(
+    click: (i32, i32),
+    width: i32,
+    height: i32,
+    anchor: &PopupAnchor,
+    workarea: Rect,
+) -> (i32, i32) {
+    let (click_x, click_y) = click;
+    let (x, y) = match anchor {
+        PopupAnchor::TopLeft => (click_x, click_y),
+        PopupAnchor::TopRight => (click_x - width, click_y),
+        PopupAnchor::BottomLeft => (click_x, click_y - height),
+        PopupAnchor::BottomRight => (click_x - width, click_y - height),
+    };
+
+    let max_x = (workarea.x + workarea.width - width).max(workarea.x);
+    let max_y = (workarea.y + workarea.height - height).max(workarea.y);
+    (x.clamp(workarea.x, max_x), y.clamp(workarea.y, max_y))
+}
+
+/// Tallest a popup may be in a workarea `area_height` tall, with `margins`
+/// (the layer-shell top and bottom margins) kept clear.
+pub fn max_popup_height(area_height: i32, margins: i32) -> i32 {
+    (area_height - margins - 2 * EDGE_GAP).max(MIN_POPUP_HEIGHT)
+}
+
+/// Height of the workarea of the monitor `window` is on, or the first
+/// monitor before it is shown. Wayland doesn't report workareas, so there
+/// it is the whole monitor.
+pub fn workarea_height(window: &impl IsA<gtk4::Window>) -> Option<i32> {
+    let window = window.upcast_ref::<gtk4::Window>();
+    let display = WidgetExt::display(window);
+    let monitor = window
+        .is_layer_window()
+        .then(|| window.monitor())
+        .flatten()
+        .or_else(|| {
+            window
+                .surface()
+                .and_then(|surface| display.monitor_at_surface(&surface))
+        })
+        .or_else(|| {
+            display
+                .monitors()
+                .item(0)
+                .and_then(|item| item.downcast::<gdk::Monitor>().ok())
+        })?;
+    Some(monitor_area(&monitor).height)
+}
+
+/// The output a layer-shell popup should open on: the one the tray was
+/// clicked on, else the one under the pointer when it is over one of our
+/// surfaces. `None` leaves the choice to the compositor, which on Sway and
+/// Hyprland means the focused output.
+pub fn output_for_show(display: &gdk::Display, click: Option<(i32, i32)>) -> Option<gdk::Monitor> {
+    if let Some(monitor) = click.and_then(|click| monitor_at_point(display, click)) {
+        return Some(monitor);
+    }
+    let pointer = display.default_seat()?.pointer()?;
+    let (surface, _, _) = pointer.surface_at_position();
+    display.monitor_at_surface(&surface?)
+}
+
+/// Moves an undecorated popup to the fallback position on X11. Plain Wayland
+/// sessions do not let clients position toplevels, so the window is left
+/// where the compositor put it.
+pub fn move_near_click(
+    window: &impl IsA<gtk4::Native>,
+    click: (i32, i32),
+    width: i32,
+    height: i32,
+    anchor: &PopupAnchor,
+) -> bool {
+    let Some(surface) = window.surface() else {
+        return false;
+    };
+    let Some(x11_surface) = surface.downcast_ref::<gdk4_x11::X11Surface>() else {
+        tracing::debug!("Popup fallback positioning needs X11; leaving placement to compositor");
+        return false;
+    };
+    let Some(x11_display) = surface.display().downcast::<gdk4_x11::X11Display>().ok() else {
+        return false;
+    };
+
+    let workarea = monitor_workarea(&surface.display(), click).unwrap_or(Rect {
+        x: 0,
+        y: 0,
+        width: i32::MAX / 2,
+        height: i32::MAX / 2,
+    });
+    let (x, y) = fallback_position(click, width, height, anchor, workarea);
+
+    // SAFETY: the display and window handles come from a realized GDK X11
+    // surface owned by this process and stay valid for the call.
+    unsafe {
+        let xdisplay = x11_display.xdisplay();
+        gdk4_x11::x11::xlib::XMoveWindow(xdisplay, x11_surface.xid(), x, y);
+        gdk4_x11::x11::xlib::XFlush(xdisplay);
+    }
+    true
+}
+
+fn monitor_workarea(display: &gdk::Display, click: (i32, i32)) -> Option<Rect> {
+    monitor_at_point(display, click)
+        .or_else(|| monitors(display).next())
+        .map(|monitor| monitor_area(&monitor))
+}
+
+fn monitor_at_point(display: &gdk::Display, point: (i32, i32)) -> Option<gdk::Monitor> {
+    monitors(display).find(|monitor| {
+        let geometry = monitor.geometry();
+        Rect {
+            x: geometry.x(),
+            y: geometry.y(),
+            width: geometry.width(),
+            height: geometry.height(),
+        }
+        .contains(point)
+    })
+}
+
+fn monitors(display: &gdk::Display) -> impl Iterator<Item = gdk::Monitor> {
+    let monitors = display.monitors();
+    (0..monitors.n_items()).filter_map(move |index| {
+        monitors
+            .item(index)
+            .and_then(|item| item.downcast::<gdk::Monitor>().ok())
+    })
+}
+
+/// The monitor's workarea on X11, its whole geometry elsewhere.
+fn monitor_area(monitor: &gdk::Monitor) -> Rect {
+    let area = monitor
+        .downcast_ref::<gdk4_x11::X11Monitor>()
+        .map(|m| m.workarea())
+        .unwrap_or_else(|| monitor.geometry());
+    Rect {
+        x: area.x(),
+        y: area.y(),
+        width: area.width(),
+        height: area.height(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCREEN: Rect = Rect {
+        x: 0,
+        y: 30,
+        width: 1920,
+        height: 1050,
+    };
+
+    #[test]
+    fn test_fallback_position_respects_anchor_corner() {
+        assert_eq!(
+            fallback_position((1800, 40), 350, 400, &PopupAnchor::TopRight, SCREEN),
+            (1450, 40)
+        );
+        assert_eq!(
+            fallback_position((100, 40), 350, 400, &PopupAnchor::TopLeft, SCREEN),
+            (100, 40)
+        );
+        assert_eq!(
+            fallback_position((1800, 1060), 350, 400, &PopupAnchor::BottomRight, SCREEN),
+            (1450, 660)
+        );
+        assert_eq!(
+            fallback_position((100, 1060), 350, 400, &PopupAnchor::BottomLeft, SCREEN),
+            (100, 660)
+        );
+    }
+
+    #[test]
+    fn test_fallback_position_clamps_to_workarea() {
+        // A top-right anchor clicked near the left edge would run off screen.
+        assert_eq!(
+            fallback_position((50, 0), 350, 400, &PopupAnchor::TopRight, SCREEN),
+            (0, 30)
+        );
+        // A bottom-docked panel with a top anchor gets pushed back up.
+        assert_eq!(
+            fallback_position((1900, 1070), 350, 400, &PopupAnchor::TopLeft, SCREEN),
+            (1570, 680)
+        );
+        // Popups taller than the workarea pin to its top-left.
+        assert_eq!(
+            fallback_position((500, 500), 350, 2000, &PopupAnchor::TopLeft, SCREEN),
+            (500, 30)
+        );
+    }
+
+    #[test]
+    fn test_rect_contains_excludes_far_edges() {
+        assert!(SCREEN.contains((0, 30)));
+        assert!(SCREEN.contains((1919, 1079)));
+        assert!(!SCREEN.contains((1920, 500)));
+        assert!(!SCREEN.contains((500, 1080)));
+        assert!(!SCREEN.contains((500, 29)));
+    }
+
+    #[test]
+    fn test_max_popup_height_fits_small_screens() {
+        // A 768px laptop with the default 40px top margin.
+        assert_eq!(max_popup_height(768, 40), 712);
+        assert_eq!(max_popup_height(SCREEN.height, 40 + 20), 974);
+        assert_eq!(max_popup_height(200, 40), 240);
+    }
+}
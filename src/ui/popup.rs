@@ -1,8 +1,32 @@
+use crate::core::hotkey::parse_hotkey;
+use crate::core::incidents::Incident;
+use crate::core::lockouts::Lockout;
+use crate::core::log_file::log_file_path;
 use crate::core::models::{
-    CostSnapshot, CostUsageTokenSnapshot, Provider, ProviderCostSnapshot, RateWindow, UsageSnapshot,
+    CostSnapshot, CostUsageTokenSnapshot, ErrorEvent, FetchLatency, Provider, ProviderHealth,
+    UsageSnapshot,
 };
-use crate::core::settings::{PopupAnchor, PopupSettings, ThemeMode};
-use crate::ui::{colors, styles, UsagePaceStage, UsagePaceText, UsageProgressBar};
+use crate::core::settings::{
+    AnimationMode, CostTimezone, PopupAnchor, PopupLayout, PopupSettings, ReadOnlyConfig,
+    ResetTimeFormat, Settings, TerminalSettings, ThemeMode,
+};
+use crate::core::terminal::{find_terminal, run_in_terminal};
+use crate::core::urgency::{rank_by_urgency, urgency_score};
+use crate::core::velocity::Velocity;
+use crate::cost::MonthlySpend;
+use crate::daemon::login::{
+    show_in_file_manager, spawn_provider_login, LoginMode, LoginOutcome, LoginProgress,
+};
+use crate::providers::{ClaudeProvider, CodexProvider, UsageProvider};
+use crate::ui::colors::{self, ColorScheme};
+use crate::ui::popup_model::{
+    format_relative_time, AccountAction, BudgetLevel, CostDetailModel, CostSectionModel,
+    CredentialStatus, FooterAction, HeaderModel, InsightsModel, LoginModel, LoginState,
+    MonthlyBudgetModel, OverviewInput, OverviewModel, PendingRebuild, PopupBody, PopupInput,
+    PopupModel, ProviderCostModel, ShareModel, StackedModel, Trend, UsageRowModel,
+    WeeklyTileModel, COSTS_HIDDEN, WEEKDAY_LABELS,
+};
+use crate::ui::{placement, styles, HeatmapGrid, UsageProgressBar, WeekStrip};
 use chrono::{DateTime, Utc};
 use gtk4::gdk;
 use gtk4::glib::{self, clone};
@@ -13,10 +37,29 @@ use libadwaita::prelude::*;
 use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
 
-const POPUP_WIDTH: i32 = 350;
+/// The content box's side margins, taken off the popup width for the share
+/// bar.
+const CONTENT_SIDE_MARGINS: i32 = 28;
 const UPDATE_INTERVAL_MS: u32 = 1000;
 
+/// Carries progress from a background login thread back to the GTK loop.
+pub type LoginSink = Arc<dyn Fn(Provider, LoginProgress) + Send + Sync>;
+
+/// Something the user changed in the popup, for the daemon to remember the
+/// next time it opens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ViewChange {
+    /// Picked a provider, so the hotkey and the merged icon reopen it.
+    Provider(Provider),
+    /// Flipped between used and remaining by clicking a percentage.
+    ShowAsRemaining(bool),
+}
+
+pub type ViewChangeSink = Arc<dyn Fn(ViewChange) + Send + Sync>;
+
 fn label(text: &str, css_class: &str, align: gtk4::Align) -> gtk4::Label {
     let label = gtk4::Label::new(Some(text));
     label.add_css_class(css_class);
@@ -42,65 +85,128 @@ fn build_content_box() -> gtk4::Box {
 }
 
 fn provider_rgba(provider: Provider, alpha: f32) -> gdk::RGBA {
-    let (r, g, b) = colors::provider_rgb(provider);
-    gdk::RGBA::new(
-        r as f32 / 255.0,
-        g as f32 / 255.0,
-        b as f32 / 255.0,
-        alpha,
-    )
+    rgb_to_rgba(colors::provider_rgb(provider), alpha)
+}
+
+fn rgb_to_rgba((r, g, b): (u8, u8, u8), alpha: f32) -> gdk::RGBA {
+    gdk::RGBA::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, alpha)
 }
 
 #[derive(Clone)]
 pub struct PopupWindow {
     window: adw::Window,
+    /// Scrolls the pages once they are taller than the screen allows;
+    /// layer-shell surfaces can't be scrolled or moved by the compositor.
+    scroller: gtk4::ScrolledWindow,
     stack: gtk4::Stack,
     content_primary: gtk4::Box,
     content_secondary: gtk4::Box,
     active_primary: Rc<Cell<bool>>,
     provider_state: Rc<RefCell<ProviderState>>,
+    /// Each provider's part of the stacked layout, for Tab to scroll to and
+    /// the live labels to update.
+    sections: Rc<RefCell<Vec<(Provider, gtk4::Box)>>>,
+    pending_rebuild: Rc<RefCell<PendingRebuild>>,
     update_source: Rc<Cell<Option<glib::SourceId>>>,
     dismiss_source: Rc<Cell<Option<glib::SourceId>>>,
     dismiss_timeout_ms: Rc<Cell<u64>>,
+    /// Set while a footer menu is open, whose popover takes focus from the
+    /// popup without the user having clicked away.
+    dismiss_held: Rc<Cell<bool>>,
+    anchor: Rc<RefCell<PopupAnchor>>,
+    live_update_stats: Rc<Cell<(u64, u64)>>,
+    click_position: Rc<Cell<Option<(i32, i32)>>>,
+    login_sink: Rc<RefCell<Option<LoginSink>>>,
+    view_sink: Rc<RefCell<Option<ViewChangeSink>>>,
+    animation_mode: Rc<Cell<AnimationMode>>,
+    color_scheme: Rc<Cell<ColorScheme>>,
     css_provider: gtk4::CssProvider,
+    width: Rc<Cell<i32>>,
+    /// The top and bottom layer-shell margins, kept clear of the popup.
+    vertical_margins: Rc<Cell<i32>>,
+    font_scale: Rc<Cell<f64>>,
+    /// The terminal "Run in terminal" opens; `None` disables the button.
+    terminal: Rc<RefCell<Option<String>>>,
+    toast_overlay: adw::ToastOverlay,
+    shortcut_toast: Rc<RefCell<Option<adw::Toast>>>,
 }
 
 struct ProviderState {
     provider: Provider,
+    tabs: Vec<(Provider, String)>,
     snapshots: HashMap<Provider, UsageSnapshot>,
-    costs: HashMap<Provider, CostSnapshot>,
-    token_snapshots: HashMap<Provider, CostUsageTokenSnapshot>,
+    costs: HashMap<Provider, Arc<CostSnapshot>>,
+    token_snapshots: HashMap<Provider, Arc<CostUsageTokenSnapshot>>,
     errors: HashMap<Provider, (String, String)>,
+    error_history: HashMap<Provider, Vec<ErrorEvent>>,
+    incidents: HashMap<Provider, Incident>,
+    health: HashMap<Provider, ProviderHealth>,
+    failed_tasks: Vec<&'static str>,
+    fetch_latencies: HashMap<Provider, FetchLatency>,
+    velocities: HashMap<Provider, Velocity>,
+    lockouts: HashMap<Provider, Vec<Lockout>>,
+    /// Where each provider's page was scrolled to when last left.
+    scroll_offsets: HashMap<Provider, f64>,
+    logins: HashMap<Provider, LoginState>,
+    monthly_spend: Option<MonthlySpend>,
     show_as_remaining: bool,
+    show_value_estimate: bool,
+    reset_time_format: ResetTimeFormat,
+    hide_costs: bool,
+    cost_enabled: bool,
+    cost_timezone: CostTimezone,
+    sort_by_urgency: bool,
+    layout: PopupLayout,
     showing_provider_menu: bool,
-}
-
-struct UsageRow<'a> {
-    title: String,
-    window: &'a RateWindow,
-    show_pace: bool,
+    showing_overview: bool,
+    showing_insights: bool,
 }
 
 impl Default for ProviderState {
     fn default() -> Self {
         Self {
             provider: Provider::Claude,
+            tabs: [Provider::Claude, Provider::Codex]
+                .into_iter()
+                .map(|provider| (provider, provider.name().to_string()))
+                .collect(),
             snapshots: HashMap::new(),
             costs: HashMap::new(),
             token_snapshots: HashMap::new(),
             errors: HashMap::new(),
+            error_history: HashMap::new(),
+            incidents: HashMap::new(),
+            health: HashMap::new(),
+            failed_tasks: Vec::new(),
+            fetch_latencies: HashMap::new(),
+            velocities: HashMap::new(),
+            lockouts: HashMap::new(),
+            scroll_offsets: HashMap::new(),
+            logins: HashMap::new(),
+            monthly_spend: None,
             show_as_remaining: false,
+            show_value_estimate: false,
+            reset_time_format: ResetTimeFormat::Relative,
+            hide_costs: false,
+            cost_enabled: true,
+            cost_timezone: CostTimezone::default(),
+            sort_by_urgency: false,
+            layout: PopupLayout::Tabs,
             showing_provider_menu: false,
+            showing_overview: false,
+            showing_insights: false,
         }
     }
 }
 
 impl PopupWindow {
     pub fn new(app: &adw::Application, theme_mode: ThemeMode, popup_settings: &PopupSettings) -> Self {
+        let width = popup_settings.clamped_width();
+        let font_scale = popup_settings.clamped_font_scale();
         let window = adw::Window::builder()
             .application(app)
             .title("Claude Bar")
-            .default_width(POPUP_WIDTH)
+            .default_width(width)
             .resizable(false)
             .deletable(true)
             .decorated(false)
@@ -109,7 +215,8 @@ impl PopupWindow {
 
         window.add_css_class("popup-window");
 
-        if gtk4_layer_shell::is_supported() {
+        let layer_shell = gtk4_layer_shell::is_supported();
+        if layer_shell {
             window.init_layer_shell();
             window.set_layer(gtk4_layer_shell::Layer::Top);
             window.set_keyboard_mode(gtk4_layer_shell::KeyboardMode::OnDemand);
@@ -119,7 +226,7 @@ impl PopupWindow {
         }
 
         let css_provider = gtk4::CssProvider::new();
-        let css = styles::css_for_provider(Provider::Claude);
+        let css = styles::css_for_provider(Provider::Claude, font_scale);
         css_provider.load_from_data(&css);
 
         if let Some(display) = gtk4::gdk::Display::default() {
@@ -133,45 +240,89 @@ impl PopupWindow {
         let content_primary = build_content_box();
         let content_secondary = build_content_box();
         let stack = gtk4::Stack::new();
-        stack.set_transition_type(gtk4::StackTransitionType::Crossfade);
         stack.set_transition_duration(150);
         stack.add_named(&content_primary, Some("primary"));
         stack.add_named(&content_secondary, Some("secondary"));
         stack.set_visible_child(&content_primary);
 
+        // The viewport scrolls whatever takes keyboard focus into view.
+        let viewport = gtk4::Viewport::new(None::<&gtk4::Adjustment>, None::<&gtk4::Adjustment>);
+        viewport.set_scroll_to_focus(true);
+        viewport.set_child(Some(&stack));
+        let scroller = gtk4::ScrolledWindow::builder()
+            .hscrollbar_policy(gtk4::PolicyType::Never)
+            .vscrollbar_policy(gtk4::PolicyType::Automatic)
+            .overlay_scrolling(true)
+            .propagate_natural_height(true)
+            .child(&viewport)
+            .build();
+
         let frame = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
         frame.add_css_class("popup-frame");
-        frame.append(&stack);
-        window.set_content(Some(&frame));
-
-        let provider_state = Rc::new(RefCell::new(ProviderState::default()));
+        frame.append(&scroller);
+        let toast_overlay = adw::ToastOverlay::new();
+        toast_overlay.set_child(Some(&frame));
+        window.set_content(Some(&toast_overlay));
+
+        let provider_state = Rc::new(RefCell::new(ProviderState {
+            layout: popup_settings.layout,
+            ..ProviderState::default()
+        }));
+        let sections = Rc::new(RefCell::new(Vec::new()));
         let update_source = Rc::new(Cell::new(None));
         let active_primary = Rc::new(Cell::new(true));
         let dismiss_source = Rc::new(Cell::new(None));
         let dismiss_timeout_ms = Rc::new(Cell::new(popup_settings.dismiss_timeout_ms));
+        let dismiss_held = Rc::new(Cell::new(false));
+        let live_update_stats = Rc::new(Cell::new((0, 0)));
+        let animation_mode = Rc::new(Cell::new(AnimationMode::Auto));
+
+        if let Some(settings) = gtk4::Settings::default() {
+            let stack = stack.clone();
+            let mode = Rc::clone(&animation_mode);
+            settings.connect_gtk_enable_animations_notify(move |_| {
+                apply_stack_transition(&stack, mode.get());
+            });
+        }
+
+        // Focus-out dismissal closes the window directly, so stop the label
+        // timer on any hide rather than only in `hide()`.
+        {
+            let update_src = Rc::clone(&update_source);
+            let stats = Rc::clone(&live_update_stats);
+            window.connect_hide(move |_| {
+                stop_live_update_source(&update_src, &stats);
+            });
+        }
+        {
+            let state = Rc::clone(&provider_state);
+            let sections = Rc::clone(&sections);
+            let content_primary = content_primary.clone();
+            let content_secondary = content_secondary.clone();
+            let stack = stack.clone();
+            window.connect_is_active_notify(move |window| {
+                if !window.is_active() || !window.is_visible() {
+                    return;
+                }
+                let content = if stack.visible_child().as_ref()
+                    == Some(content_secondary.upcast_ref::<gtk4::Widget>())
+                {
+                    &content_secondary
+                } else {
+                    &content_primary
+                };
+                update_dynamic_labels(&state, content, &sections);
+            });
+        }
 
         let focus_controller = gtk4::EventControllerFocus::new();
         {
             let window_close = window.clone();
             let dismiss_src = Rc::clone(&dismiss_source);
+            let held = Rc::clone(&dismiss_held);
             let timeout_ms = Rc::clone(&dismiss_timeout_ms);
             focus_controller.connect_leave(move |_| {
-                let ms = timeout_ms.get();
-                if ms == 0 {
-                    window_close.close();
-                    return;
-                }
-
-                let window_deferred = window_close.clone();
-                let dismiss_src_inner = Rc::clone(&dismiss_src);
-                let source_id = glib::timeout_add_local_once(
-                    std::time::Duration::from_millis(ms),
-                    move || {
-                        dismiss_src_inner.set(None);
-                        window_deferred.close();
-                    },
-                );
-                dismiss_src.set(Some(source_id));
+                schedule_dismiss(&window_close, &dismiss_src, &held, timeout_ms.get());
             });
         }
         {
@@ -184,61 +335,191 @@ impl PopupWindow {
         }
         window.add_controller(focus_controller);
 
+        if !layer_shell {
+            // Without layer-shell the popup is a plain toplevel; the window
+            // manager deactivating it is the reliable "clicked away" signal.
+            let dismiss_src = Rc::clone(&dismiss_source);
+            let held = Rc::clone(&dismiss_held);
+            let timeout_ms = Rc::clone(&dismiss_timeout_ms);
+            window.connect_is_active_notify(move |window| {
+                if window.is_active() {
+                    if let Some(source_id) = dismiss_src.take() {
+                        source_id.remove();
+                    }
+                } else if window.is_visible() {
+                    schedule_dismiss(window, &dismiss_src, &held, timeout_ms.get());
+                }
+            });
+        }
+
         let popup = Self {
             window,
+            scroller,
             stack,
             content_primary,
             content_secondary,
             active_primary,
             provider_state,
+            sections,
+            pending_rebuild: Rc::new(RefCell::new(PendingRebuild::default())),
             update_source,
             dismiss_source,
             dismiss_timeout_ms,
+            dismiss_held,
+            anchor: Rc::new(RefCell::new(popup_settings.anchor.clone())),
+            live_update_stats,
+            click_position: Rc::new(Cell::new(None)),
+            login_sink: Rc::new(RefCell::new(None)),
+            view_sink: Rc::new(RefCell::new(None)),
+            animation_mode,
+            color_scheme: Rc::new(Cell::new(ColorScheme::default())),
             css_provider,
+            width: Rc::new(Cell::new(width)),
+            vertical_margins: Rc::new(Cell::new(
+                popup_settings.margin_top + popup_settings.margin_bottom,
+            )),
+            font_scale: Rc::new(Cell::new(font_scale)),
+            terminal: Rc::new(RefCell::new(None)),
+            toast_overlay,
+            shortcut_toast: Rc::new(RefCell::new(None)),
         };
 
+        apply_stack_transition(&popup.stack, AnimationMode::Auto);
         popup.apply_theme_mode(theme_mode);
         popup.install_key_controller();
+        popup.install_scroll_memory();
         popup
     }
 
     pub fn apply_popup_settings(&self, settings: &PopupSettings) {
         self.dismiss_timeout_ms.set(settings.dismiss_timeout_ms);
+        self.anchor.replace(settings.anchor.clone());
+        self.vertical_margins
+            .set(settings.margin_top + settings.margin_bottom);
         if gtk4_layer_shell::is_supported() {
             apply_layer_shell_position(&self.window, settings);
         }
+
+        let width = settings.clamped_width();
+        let font_scale = settings.clamped_font_scale();
+        if width != self.width.get() || font_scale != self.font_scale.get() {
+            self.width.set(width);
+            self.font_scale.set(font_scale);
+            self.window.set_default_width(width);
+            self.apply_provider_styles(self.provider_state.borrow().provider);
+            self.rebuild_if_visible();
+        }
+        self.set_layout(settings.layout);
+    }
+
+    pub fn set_layout(&self, layout: PopupLayout) {
+        {
+            let mut state = self.provider_state.borrow_mut();
+            if state.layout == layout {
+                return;
+            }
+            state.layout = layout;
+        }
+        self.rebuild_if_visible();
+    }
+
+    pub fn set_terminal(&self, settings: &TerminalSettings) {
+        let terminal = find_terminal(settings.preferred.as_deref());
+        if *self.terminal.borrow() != terminal {
+            self.terminal.replace(terminal);
+            self.rebuild_if_visible();
+        }
+    }
+
+    pub fn set_providers(&self, tabs: Vec<(Provider, String)>) {
+        if tabs.is_empty() {
+            return;
+        }
+        self.provider_state.borrow_mut().tabs = tabs;
+        self.rebuild_if_visible();
     }
 
     pub fn show(&self, provider: Provider) {
+        if self.window.is_visible() {
+            self.remember_scroll();
+        }
         {
             let mut state = self.provider_state.borrow_mut();
             state.provider = provider;
             state.showing_provider_menu = false;
+            state.showing_overview = false;
+            state.showing_insights = false;
         }
 
         self.cancel_pending_dismiss();
+        self.follow_click_output();
         self.apply_provider_styles(provider);
         self.rebuild_content();
+        self.scroll_to_provider(provider);
 
         self.window.set_visible(true);
         self.window.present();
+        self.place_near_click();
 
         self.start_live_updates();
     }
 
+    /// Shows the Overview tab: the monthly spend split and every tab's
+    /// weekly usage. Nothing on it counts down, so live updates stay off.
+    pub fn show_overview(&self) {
+        self.stop_live_updates();
+        {
+            let mut state = self.provider_state.borrow_mut();
+            state.showing_provider_menu = false;
+            state.showing_overview = true;
+            state.showing_insights = false;
+        }
+
+        self.cancel_pending_dismiss();
+        self.follow_click_output();
+        self.rebuild_content();
+
+        self.window.set_visible(true);
+        self.window.present();
+        self.place_near_click();
+    }
+
+    /// Shows the Insights view for the current tab: when in the week its
+    /// tokens were spent. Like the Overview it has nothing live to update.
+    fn show_insights(&self) {
+        self.stop_live_updates();
+        {
+            let mut state = self.provider_state.borrow_mut();
+            state.showing_provider_menu = false;
+            state.showing_overview = false;
+            state.showing_insights = true;
+        }
+        self.rebuild_content();
+    }
+
     pub fn show_provider_menu(&self, providers: &[Provider]) {
         self.stop_live_updates();
         {
             let mut state = self.provider_state.borrow_mut();
             state.showing_provider_menu = true;
+            state.showing_overview = false;
+            state.showing_insights = false;
         }
 
         self.cancel_pending_dismiss();
+        self.follow_click_output();
         let content = self.current_content();
         self.rebuild_provider_menu_in(&content, providers);
 
         self.window.set_visible(true);
         self.window.present();
+        self.place_near_click();
+    }
+
+    /// Records where the tray icon was clicked so the next show can open the
+    /// popup on that output, or next to it when layer-shell is unavailable.
+    pub fn set_click_position(&self, click: Option<(i32, i32)>) {
+        self.click_position.set(click);
     }
 
     #[allow(dead_code)]
@@ -252,26 +533,205 @@ impl PopupWindow {
             let mut state = self.provider_state.borrow_mut();
             state.snapshots.insert(provider, snapshot.clone());
             state.errors.remove(&provider);
+            // The refresh after a successful login has landed.
+            if state
+                .logins
+                .get(&provider)
+                .is_some_and(|login| login.outcome == Some(LoginOutcome::Success))
+            {
+                state.logins.remove(&provider);
+            }
+        }
+        self.rebuild_if_visible();
+    }
+
+    /// Marks the shown usage as current as of `updated_at` without
+    /// replacing it, so the header's age and the burn rate stay fresh.
+    pub fn confirm_usage(
+        &self,
+        provider: Provider,
+        updated_at: DateTime<Utc>,
+        received_at: Option<Instant>,
+    ) {
+        {
+            let mut state = self.provider_state.borrow_mut();
+            let Some(snapshot) = state.snapshots.get_mut(&provider) else {
+                return;
+            };
+            snapshot.updated_at = updated_at;
+            snapshot.received_at = received_at;
+        }
+        self.rebuild_if_visible();
+    }
+
+    pub fn set_login_sink(&self, sink: LoginSink) {
+        *self.login_sink.borrow_mut() = Some(sink);
+    }
+
+    pub fn set_view_sink(&self, sink: ViewChangeSink) {
+        *self.view_sink.borrow_mut() = Some(sink);
+    }
+
+    pub fn set_animation_mode(&self, mode: AnimationMode) {
+        self.animation_mode.set(mode);
+        apply_stack_transition(&self.stack, mode);
+    }
+
+    pub fn set_color_scheme(&self, scheme: ColorScheme) {
+        self.color_scheme.set(scheme);
+        self.rebuild_if_visible();
+    }
+
+    pub fn set_sort_by_urgency(&self, sort: bool) {
+        self.provider_state.borrow_mut().sort_by_urgency = sort;
+        self.rebuild_if_visible();
+    }
+
+    /// Shows a provider the user picked, as opposed to one picked for them.
+    fn choose_provider(&self, provider: Provider) {
+        self.notify_view(ViewChange::Provider(provider));
+        self.show(provider);
+    }
+
+    fn notify_view(&self, change: ViewChange) {
+        let sink = self.view_sink.borrow().clone();
+        if let Some(sink) = sink {
+            sink(change);
+        }
+    }
+
+    /// Kept until the daemon restarts, since the task stays stopped.
+    pub fn add_failed_task(&self, task: &'static str) {
+        {
+            let mut state = self.provider_state.borrow_mut();
+            if state.failed_tasks.contains(&task) {
+                return;
+            }
+            state.failed_tasks.push(task);
+        }
+        self.rebuild_if_visible();
+    }
+
+    /// Shows why the global shortcut doesn't work until it is dismissed or
+    /// fixed; `None` takes the toast down.
+    pub fn set_shortcut_error(&self, error: Option<&str>) {
+        if let Some(toast) = self.shortcut_toast.borrow_mut().take() {
+            toast.dismiss();
+        }
+        if let Some(error) = error {
+            let toast = adw::Toast::new(error);
+            toast.set_timeout(0);
+            self.toast_overlay.add_toast(toast.clone());
+            *self.shortcut_toast.borrow_mut() = Some(toast);
+        }
+    }
+
+    pub fn update_login(&self, provider: Provider, progress: LoginProgress) {
+        self.provider_state
+            .borrow_mut()
+            .logins
+            .entry(provider)
+            .or_default()
+            .apply(progress);
+        self.rebuild_if_visible();
+    }
+
+    fn start_login(&self, provider: Provider, mode: LoginMode) {
+        {
+            let mut state = self.provider_state.borrow_mut();
+            if state
+                .logins
+                .get(&provider)
+                .is_some_and(LoginState::is_running)
+            {
+                return;
+            }
+            state.logins.insert(provider, LoginState::default());
         }
         self.rebuild_if_visible();
+
+        let sink = self.login_sink.borrow().clone();
+        spawn_provider_login(provider, mode, move |progress| {
+            if let Some(sink) = &sink {
+                sink(provider, progress);
+            }
+        });
     }
 
-    pub fn update_cost(&self, provider: Provider, cost: &CostSnapshot) {
+    pub fn update_cost(&self, provider: Provider, cost: Arc<CostSnapshot>) {
         {
             let mut state = self.provider_state.borrow_mut();
-            state.costs.insert(provider, cost.clone());
+            state.costs.insert(provider, cost);
         }
         self.rebuild_if_visible();
     }
 
-    pub fn update_tokens(&self, provider: Provider, tokens: &CostUsageTokenSnapshot) {
+    /// Combined spend against `cost.monthly_budget`; `None` without one.
+    pub fn update_monthly_spend(&self, spend: Option<MonthlySpend>) {
+        self.provider_state.borrow_mut().monthly_spend = spend;
+        self.rebuild_if_visible();
+    }
+
+    pub fn update_tokens(&self, provider: Provider, tokens: Arc<CostUsageTokenSnapshot>) {
         {
             let mut state = self.provider_state.borrow_mut();
-            state.token_snapshots.insert(provider, tokens.clone());
+            state.token_snapshots.insert(provider, tokens);
         }
         self.rebuild_if_visible();
     }
 
+    pub fn update_lockouts(&self, provider: Provider, lockouts: Vec<Lockout>) {
+        self.provider_state
+            .borrow_mut()
+            .lockouts
+            .insert(provider, lockouts);
+        self.rebuild_if_visible();
+    }
+
+    /// Stored without a rebuild; it arrives with the popup being shown.
+    pub fn set_error_history(&self, provider: Provider, history: Vec<ErrorEvent>) {
+        self.provider_state
+            .borrow_mut()
+            .error_history
+            .insert(provider, history);
+    }
+
+    /// Stored without a rebuild; it arrives with the popup being shown.
+    pub fn set_incident(&self, provider: Provider, incident: Option<Incident>) {
+        let mut state = self.provider_state.borrow_mut();
+        match incident {
+            Some(incident) => state.incidents.insert(provider, incident),
+            None => state.incidents.remove(&provider),
+        };
+    }
+
+    /// Stored without a rebuild; it arrives with the popup being shown.
+    pub fn set_health(&self, provider: Provider, health: ProviderHealth) {
+        self.provider_state
+            .borrow_mut()
+            .health
+            .insert(provider, health);
+    }
+
+    /// Stored without a rebuild; it arrives with a usage update or the
+    /// popup being shown.
+    pub fn set_velocity(&self, provider: Provider, velocity: Option<Velocity>) {
+        let mut state = self.provider_state.borrow_mut();
+        match velocity {
+            Some(velocity) => state.velocities.insert(provider, velocity),
+            None => state.velocities.remove(&provider),
+        };
+    }
+
+    /// Stored without a rebuild; the usage update that follows every fetch
+    /// redraws the header.
+    pub fn update_fetch_latency(&self, provider: Provider, latency: FetchLatency) {
+        self.provider_state
+            .borrow_mut()
+            .fetch_latencies
+            .insert(provider, latency);
+    }
+
     pub fn show_error(&self, provider: Provider, error: &str, hint: &str) {
         {
             let mut state = self.provider_state.borrow_mut();
@@ -289,17 +749,103 @@ impl PopupWindow {
         self.rebuild_if_visible();
     }
 
+    pub fn set_show_value_estimate(&self, show_value_estimate: bool) {
+        self.provider_state.borrow_mut().show_value_estimate = show_value_estimate;
+        self.rebuild_if_visible();
+    }
+
+    pub fn set_reset_time_format(&self, reset_time_format: ResetTimeFormat) {
+        self.provider_state.borrow_mut().reset_time_format = reset_time_format;
+        self.rebuild_if_visible();
+    }
+
+    pub fn set_hide_costs(&self, hide_costs: bool) {
+        self.provider_state.borrow_mut().hide_costs = hide_costs;
+        self.rebuild_if_visible();
+    }
+
+    /// The footer's "Hide costs" action. Saved like any other setting, so it
+    /// survives a restart.
+    fn toggle_hide_costs(&self) {
+        let hide_costs = !self.provider_state.borrow().hide_costs;
+        let mut settings = crate::core::settings::Settings::load().unwrap_or_default();
+        settings.display.hide_costs = hide_costs;
+        if let Err(e) = settings.save() {
+            tracing::warn!(error = %e, "Failed to save settings");
+        }
+        self.set_hide_costs(hide_costs);
+    }
+
+    pub fn set_cost_enabled(&self, cost_enabled: bool) {
+        self.provider_state.borrow_mut().cost_enabled = cost_enabled;
+        self.rebuild_if_visible();
+    }
+
+    pub fn set_cost_timezone(&self, cost_timezone: CostTimezone) {
+        self.provider_state.borrow_mut().cost_timezone = cost_timezone;
+        self.rebuild_if_visible();
+    }
+
     pub fn set_theme_mode(&self, mode: ThemeMode) {
         self.apply_theme_mode(mode);
     }
 
+    /// Rebuilds on the next main-loop iteration, once for all the updates
+    /// that arrive before then.
     fn rebuild_if_visible(&self) {
+        if self.pending_rebuild.borrow_mut().request() {
+            let popup = self.clone();
+            glib::idle_add_local_once(move || popup.run_pending_rebuild());
+        }
+    }
+
+    fn run_pending_rebuild(&self) {
+        let requests = self.pending_rebuild.borrow_mut().take();
+        if requests == 0 {
+            return;
+        }
+        tracing::trace!(requests, "Rebuilding popup");
         let showing_menu = self.provider_state.borrow().showing_provider_menu;
         if self.window.is_visible() && !showing_menu {
             self.rebuild_content();
         }
     }
 
+    /// Moves the layer-shell popup to the output that was clicked or is under
+    /// the pointer. The anchor and margins apply per output, so only the
+    /// output changes; when neither is known the compositor picks.
+    fn follow_click_output(&self) {
+        if !gtk4_layer_shell::is_supported() {
+            return;
+        }
+        let output = placement::output_for_show(
+            &WidgetExt::display(&self.window),
+            self.click_position.get(),
+        );
+        if self.window.monitor() != output {
+            self.window.set_monitor(output.as_ref());
+        }
+    }
+
+    fn place_near_click(&self) {
+        let click = self.click_position.take();
+        if gtk4_layer_shell::is_supported() {
+            return;
+        }
+        let Some(click) = click else {
+            return;
+        };
+
+        let (width, height) = self.window.default_size();
+        placement::move_near_click(
+            &self.window,
+            click,
+            width.max(self.width.get()),
+            height.max(0),
+            &self.anchor.borrow(),
+        );
+    }
+
     fn current_content(&self) -> gtk4::Box {
         if self.active_primary.get() {
             self.content_primary.clone()
@@ -324,6 +870,81 @@ impl PopupWindow {
         }
     }
 
+    fn install_scroll_memory(&self) {
+        let popup = self.clone();
+        self.window.connect_hide(move |_| popup.remember_scroll());
+    }
+
+    /// On a provider's own page, rather than the menu, Overview, Insights or
+    /// the stacked layout's shared page.
+    fn showing_provider_page(&self) -> bool {
+        let state = self.provider_state.borrow();
+        !state.showing_provider_menu
+            && !state.showing_overview
+            && !state.showing_insights
+            && state.layout == PopupLayout::Tabs
+    }
+
+    fn remember_scroll(&self) {
+        if !self.showing_provider_page() {
+            return;
+        }
+        let offset = self.scroller.vadjustment().value();
+        let mut state = self.provider_state.borrow_mut();
+        let provider = state.provider;
+        state.scroll_offsets.insert(provider, offset);
+    }
+
+    /// Scrolls back to where `provider`'s page was left, once the rebuilt
+    /// content has been laid out and the scroll range is known.
+    fn restore_scroll(&self, provider: Provider) {
+        let offset = self
+            .provider_state
+            .borrow()
+            .scroll_offsets
+            .get(&provider)
+            .copied()
+            .unwrap_or(0.0);
+        let adjustment = self.scroller.vadjustment();
+        adjustment.set_value(0.0);
+        if offset > 0.0 {
+            glib::idle_add_local_once(move || adjustment.set_value(offset));
+        }
+    }
+
+    /// Where a freshly built page opens: `provider`'s section when stacked,
+    /// otherwise wherever its tab was left.
+    fn scroll_to_provider(&self, provider: Provider) {
+        if self.provider_state.borrow().layout == PopupLayout::Stacked {
+            self.scroll_to_section(provider);
+        } else {
+            self.restore_scroll(provider);
+        }
+    }
+
+    /// Scrolls the stacked layout to `provider`'s section once the rebuilt
+    /// content has been laid out.
+    fn scroll_to_section(&self, provider: Provider) {
+        let section = self
+            .sections
+            .borrow()
+            .iter()
+            .find(|(section_provider, _)| *section_provider == provider)
+            .map(|(_, section)| section.clone());
+        let adjustment = self.scroller.vadjustment();
+        let Some(section) = section else {
+            adjustment.set_value(0.0);
+            return;
+        };
+        let stack = self.stack.clone();
+        glib::idle_add_local_once(move || {
+            let origin = gtk4::graphene::Point::new(0.0, 0.0);
+            if let Some(point) = section.compute_point(&stack, &origin) {
+                adjustment.set_value(f64::from(point.y()));
+            }
+        });
+    }
+
     fn install_key_controller(&self) {
         let popup = self.clone();
         let controller = gtk4::EventControllerKey::new();
@@ -349,25 +970,50 @@ impl PopupWindow {
     }
 
     fn switch_provider(&self, backwards: bool) {
-        let next = next_provider(self.provider_state.borrow().provider, backwards);
+        let next = {
+            let state = self.provider_state.borrow();
+            next_provider(&ordered_tabs(&state), state.provider, backwards)
+        };
         {
-            let mut state = self.provider_state.borrow_mut();
-            if state.provider == next {
+            let state = self.provider_state.borrow();
+            if state.provider == next && !state.showing_overview && !state.showing_insights {
                 return;
             }
+        }
+        let stacked_page = {
+            let state = self.provider_state.borrow();
+            state.layout == PopupLayout::Stacked
+                && !state.showing_overview
+                && !state.showing_insights
+                && !state.showing_provider_menu
+        };
+        if stacked_page {
+            // Every provider is already on the page; Tab moves between them.
+            self.provider_state.borrow_mut().provider = next;
+            self.notify_view(ViewChange::Provider(next));
+            self.scroll_to_section(next);
+            return;
+        }
+        self.remember_scroll();
+        {
+            let mut state = self.provider_state.borrow_mut();
             state.provider = next;
             state.showing_provider_menu = false;
+            state.showing_overview = false;
+            state.showing_insights = false;
         }
 
+        self.notify_view(ViewChange::Provider(next));
         self.apply_provider_styles(next);
         let content = self.swap_content();
         self.rebuild_content_in(&content);
         self.stack.set_visible_child(&content);
+        self.scroll_to_provider(next);
         self.start_live_updates();
     }
 
     fn apply_provider_styles(&self, provider: Provider) {
-        let css = styles::css_for_provider(provider);
+        let css = styles::css_for_provider(provider, self.font_scale.get());
         self.css_provider.load_from_data(&css);
     }
 
@@ -386,52 +1032,176 @@ impl PopupWindow {
     }
 
     fn rebuild_content_in(&self, content: &gtk4::Box) {
+        self.pending_rebuild.borrow_mut().clear();
+        self.sections.borrow_mut().clear();
         while let Some(child) = content.first_child() {
             content.remove(&child);
         }
 
         let state = self.provider_state.borrow();
-        let snapshot = state.snapshots.get(&state.provider);
-        let cost = state.costs.get(&state.provider);
-        let tokens = state.token_snapshots.get(&state.provider);
-        let error = state.errors.get(&state.provider);
+        if state.showing_overview {
+            drop(state);
+            self.rebuild_overview_in(content);
+            return;
+        }
+        if state.showing_insights {
+            drop(state);
+            self.rebuild_insights_in(content);
+            return;
+        }
+        if state.layout == PopupLayout::Stacked {
+            drop(state);
+            self.rebuild_stacked_in(content);
+            return;
+        }
+
+        let model = PopupModel::build(
+            &popup_input(
+                &state,
+                state.provider,
+                tab_label(&state.tabs, state.provider),
+            ),
+            Utc::now(),
+        );
 
         self.build_provider_switcher(content, &state);
-        self.build_header(content, &state, snapshot, error);
+        self.build_header(content, &model.header);
+        if let Some(warning) = &model.task_warning {
+            let warning_label = label(warning, "error", gtk4::Align::Start);
+            warning_label.set_wrap(true);
+            content.append(&warning_label);
+        }
         content.append(&separator());
+        self.build_body(content, state.provider, &model.body);
 
-        if let Some((error, hint)) = error {
-            self.build_error_section(content, error, hint);
-        } else if let Some(snapshot) = snapshot {
-            let usage_rows = collect_usage_rows(state.provider, snapshot);
-            let accent = provider_rgba(state.provider, 0.75);
-            let trough = provider_rgba(state.provider, 0.12);
-            self.build_usage_sections(
-                content,
-                state.provider,
-                &usage_rows,
-                state.show_as_remaining,
-                &accent,
-                &trough,
-            );
+        if let Some(login) = &model.login {
+            self.build_login_section(content, login);
+        }
+
+        self.build_footer_actions(content, state.provider, &model.footer);
+        self.resize_to_content(content);
+    }
+
+    /// Every enabled provider on one page, each in a section tinted with its
+    /// color, above one Cost section and footer.
+    fn rebuild_stacked_in(&self, content: &gtk4::Box) {
+        let state = self.provider_state.borrow();
+        let tabs = ordered_tabs(&state);
+        let inputs: Vec<PopupInput<'_>> = tabs
+            .iter()
+            .map(|(provider, title)| popup_input(&state, *provider, title.clone()))
+            .collect();
+        let model = StackedModel::build(&inputs, Utc::now());
+
+        if let Some(warning) = &model.task_warning {
+            let warning_label = label(warning, "error", gtk4::Align::Start);
+            warning_label.set_wrap(true);
+            content.append(&warning_label);
+        }
 
-            if let Some(provider_cost) = snapshot.provider_cost.as_ref() {
-                self.build_provider_cost_section(content, provider_cost, &accent, &trough);
+        let mut sections = Vec::with_capacity(model.sections.len());
+        for (index, section) in model.sections.iter().enumerate() {
+            if index > 0 {
+                content.append(&separator());
+            }
+            let section_box = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+            section_box.add_css_class("provider-section");
+            section_box.add_css_class(&format!(
+                "provider-section-{}",
+                provider_css_name(section.provider)
+            ));
+            self.build_header(&section_box, &section.header);
+            self.build_body(&section_box, section.provider, &section.body);
+            if let Some(login) = &section.login {
+                self.build_login_section(&section_box, login);
             }
+            content.append(&section_box);
+            sections.push((section.provider, section_box));
+        }
+        self.sections.replace(sections);
 
-            if cost.is_some() || tokens.is_some() {
+        if let Some(cost) = &model.cost {
+            content.append(&separator());
+            self.build_cost_section(content, cost, model.cost_note.as_deref());
+        }
+        if let Some(budget) = &model.budget {
+            if model.cost.is_none() {
                 content.append(&separator());
-                self.build_cost_section(content, cost, tokens);
             }
-        } else {
-            content.append(&label("No usage data yet", "dim-label", gtk4::Align::Start));
+            let accent = provider_rgba(state.provider, 0.75);
+            let trough = provider_rgba(state.provider, 0.12);
+            self.build_monthly_budget(content, budget, &accent, &trough);
         }
 
-        let updated_at = snapshot.map(|s| s.updated_at);
-        self.build_footer_actions(content, updated_at);
+        self.build_footer_actions(content, state.provider, &model.footer);
+        drop(state);
+
         self.resize_to_content(content);
     }
 
+    fn build_body(&self, content: &gtk4::Box, provider: Provider, body: &PopupBody) {
+        match body {
+            PopupBody::Error {
+                message,
+                hint,
+                hint_link,
+                hint_command,
+                recent_errors,
+            } => {
+                self.build_error_section(
+                    content,
+                    message,
+                    hint,
+                    hint_link.as_deref(),
+                    hint_command.as_deref(),
+                    recent_errors,
+                );
+            }
+            PopupBody::Usage {
+                rows,
+                surfaces,
+                provider_cost,
+                cost,
+                cost_note,
+                budget,
+                lockouts,
+            } => {
+                let accent = provider_rgba(provider, 0.75);
+                let trough = provider_rgba(provider, 0.12);
+                for row in rows {
+                    self.build_usage_row(content, row, &accent, &trough);
+                }
+
+                if let Some(lockouts) = lockouts {
+                    content.append(&label(lockouts, "dim-label", gtk4::Align::Start));
+                }
+
+                if !surfaces.is_empty() {
+                    self.build_surface_details(content, surfaces, &accent, &trough);
+                }
+
+                if let Some(provider_cost) = provider_cost {
+                    self.build_provider_cost_section(content, provider_cost, &accent, &trough);
+                }
+
+                if let Some(cost) = cost {
+                    content.append(&separator());
+                    self.build_cost_section(content, cost, cost_note.as_deref());
+                }
+
+                if let Some(budget) = budget {
+                    if cost.is_none() {
+                        content.append(&separator());
+                    }
+                    self.build_monthly_budget(content, budget, &accent, &trough);
+                }
+            }
+            PopupBody::Empty => {
+                content.append(&label("No usage data yet", "dim-label", gtk4::Align::Start));
+            }
+        }
+    }
+
     fn rebuild_provider_menu_in(&self, content: &gtk4::Box, providers: &[Provider]) {
         while let Some(child) = content.first_child() {
             content.remove(&child);
@@ -440,42 +1210,224 @@ impl PopupWindow {
         content.append(&label("Select provider", "heading", gtk4::Align::Start));
         content.append(&separator());
 
+        let state = self.provider_state.borrow();
         for provider in providers {
-            let button = gtk4::Button::with_label(provider.name());
+            let button = gtk4::Button::with_label(&tab_label(&state.tabs, *provider));
             button.add_css_class("provider-choice");
             button.set_halign(gtk4::Align::Start);
             let popup = self.clone();
             let provider = *provider;
             button.connect_clicked(move |_| {
-                popup.show(provider);
+                popup.choose_provider(provider);
             });
             content.append(&button);
         }
 
+        let share = if state.cost_enabled {
+            ShareModel::build(&state.costs, state.hide_costs)
+        } else {
+            None
+        };
+        drop(state);
+
+        if let Some(share) = share {
+            content.append(&separator());
+            self.build_share_section(content, &share);
+        }
+
         self.resize_to_content(content);
     }
 
-    fn resize_to_content(&self, content: &gtk4::Box) {
-        let (_, natural, _, _) = content.measure(gtk4::Orientation::Vertical, POPUP_WIDTH);
-        self.window.set_default_height(natural);
+    fn rebuild_overview_in(&self, content: &gtk4::Box) {
+        let state = self.provider_state.borrow();
+        let model = OverviewModel::build(&OverviewInput {
+            tabs: &state.tabs,
+            snapshots: &state.snapshots,
+            costs: &state.costs,
+            show_as_remaining: state.show_as_remaining,
+            hide_costs: state.hide_costs,
+            cost_enabled: state.cost_enabled,
+        });
+
+        self.build_provider_switcher(content, &state);
+        content.append(&label("Overview", "title-3", gtk4::Align::Start));
+        content.append(&separator());
+
+        if let Some(share) = &model.share {
+            self.build_share_section(content, share);
+        } else if state.cost_enabled {
+            content.append(&label("No cost data yet", "dim-label", gtk4::Align::Start));
+        }
+        self.build_weekly_tiles(content, &model.weekly);
+
+        self.build_footer_actions(
+            content,
+            state.provider,
+            &[
+                FooterAction::RefreshNow,
+                FooterAction::HideCosts {
+                    hidden: state.hide_costs,
+                },
+                FooterAction::Settings,
+            ],
+        );
+        drop(state);
+
+        self.resize_to_content(content);
+    }
+
+    fn rebuild_insights_in(&self, content: &gtk4::Box) {
+        let state = self.provider_state.borrow();
+        let model =
+            InsightsModel::build(state.token_snapshots.get(&state.provider).map(Arc::as_ref));
+
+        self.build_provider_switcher(content, &state);
+        content.append(&label("Insights", "title-3", gtk4::Align::Start));
+        content.append(&separator());
+        self.build_heatmap_section(content, state.provider, &model);
+
+        self.build_footer_actions(
+            content,
+            state.provider,
+            &[FooterAction::RefreshNow, FooterAction::Settings],
+        );
+        drop(state);
+
+        self.resize_to_content(content);
     }
 
-    fn build_header(
+    fn build_heatmap_section(
         &self,
         content: &gtk4::Box,
-        state: &ProviderState,
-        snapshot: Option<&UsageSnapshot>,
-        error: Option<&(String, String)>,
+        provider: Provider,
+        model: &InsightsModel,
     ) {
+        let section = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+        section.append(&label(
+            "Tokens by hour, last 30 days",
+            "heading",
+            gtk4::Align::Start,
+        ));
+        if model.rows.is_empty() {
+            section.append(&label(&model.summary, "dim-label", gtk4::Align::Start));
+            content.append(&section);
+            return;
+        }
+
+        let grid = gtk4::Grid::new();
+        grid.set_column_spacing(6);
+        grid.set_row_spacing(2);
+        grid.set_margin_top(4);
+
+        let weekdays = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+        weekdays.set_homogeneous(true);
+        for weekday in WEEKDAY_LABELS {
+            weekdays.append(&label(weekday, "heatmap-axis", gtk4::Align::Start));
+        }
+        grid.attach(&weekdays, 0, 0, 1, 1);
+
+        let heatmap = HeatmapGrid::new();
+        heatmap.set_hexpand(true);
+        heatmap.set_cells(&model.rows);
+        heatmap.set_accent(provider_rgba(provider, 1.0));
+        grid.attach(&heatmap, 1, 0, 1, 1);
+
+        let hours = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+        hours.set_homogeneous(true);
+        for hour in ["00", "06", "12", "18"] {
+            hours.append(&label(hour, "heatmap-axis", gtk4::Align::Start));
+        }
+        grid.attach(&hours, 1, 1, 1, 1);
+
+        section.append(&grid);
+        section.append(&label(&model.summary, "cost-line", gtk4::Align::Start));
+        content.append(&section);
+    }
+
+    fn build_share_section(&self, content: &gtk4::Box, share: &ShareModel) {
+        let section = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+        section.append(&label("This month", "heading", gtk4::Align::Start));
+
+        let bar = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
+        bar.add_css_class("share-bar");
+        let bar_width = self.width.get() - CONTENT_SIDE_MARGINS;
+        bar.set_size_request(bar_width, -1);
+        for segment in &share.segments {
+            let button = gtk4::Button::new();
+            button.add_css_class("share-segment");
+            match segment.provider {
+                Provider::Claude => button.add_css_class("provider-dot-claude"),
+                Provider::Codex | Provider::CodexWorkspace(_) => {
+                    button.add_css_class("provider-dot-codex")
+                }
+            }
+            let width = (segment.fraction * f64::from(bar_width)).round() as i32;
+            button.set_size_request(width.max(1), -1);
+            button.set_tooltip_text(Some(&segment.tooltip));
+
+            let popup = self.clone();
+            let provider = segment.provider;
+            button.connect_clicked(move |_| {
+                popup.choose_provider(provider);
+            });
+            bar.append(&button);
+        }
+        section.append(&bar);
+        section.append(&label(&share.summary, "cost-line", gtk4::Align::Start));
+
+        content.append(&section);
+    }
+
+    fn build_weekly_tiles(&self, content: &gtk4::Box, tiles: &[WeeklyTileModel]) {
+        let section = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+        section.set_margin_top(14);
+        section.append(&label("Weekly", "heading", gtk4::Align::Start));
+
+        let row = gtk4::Box::new(gtk4::Orientation::Horizontal, 12);
+        row.set_homogeneous(true);
+        for tile in tiles {
+            let column = gtk4::Box::new(gtk4::Orientation::Vertical, 3);
+            column.append(&label(&tile.title, "provider-tab-label", gtk4::Align::Start));
+
+            let progress_bar = UsageProgressBar::new();
+            progress_bar.set_hexpand(true);
+            progress_bar.set_progress(tile.progress);
+            progress_bar.set_colors(
+                provider_rgba(tile.provider, 0.75),
+                provider_rgba(tile.provider, 0.12),
+            );
+            column.append(&progress_bar);
+            column.append(&label(&tile.percent_text, "usage-label", gtk4::Align::Start));
+            row.append(&column);
+        }
+        section.append(&row);
+
+        content.append(&section);
+    }
+
+    /// Sizes the popup to `content`, capped at what fits on the monitor; the
+    /// rest scrolls.
+    fn resize_to_content(&self, content: &gtk4::Box) {
+        let (_, natural, _, _) = content.measure(gtk4::Orientation::Vertical, self.width.get());
+        let height = placement::workarea_height(&self.window)
+            .map(|area| placement::max_popup_height(area, self.vertical_margins.get()))
+            .map_or(natural, |max| natural.min(max));
+        // The scroller asks for its child's full height up to this, so the
+        // window's natural size matches the default height set here.
+        self.scroller.set_max_content_height(height);
+        self.window.set_default_height(height);
+    }
+
+    fn build_header(&self, content: &gtk4::Box, header: &HeaderModel) {
         let header_box = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
         header_box.set_margin_bottom(4);
 
         let title_row = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
-        let provider_name = label(state.provider.name(), "title-3", gtk4::Align::Start);
+        let provider_name = label(&header.title, "title-3", gtk4::Align::Start);
         provider_name.set_hexpand(true);
         title_row.append(&provider_name);
 
-        if let Some(plan) = snapshot.and_then(|s| s.identity.plan.as_ref()) {
+        if let Some(plan) = &header.plan {
             let plan_badge = label(plan, "plan-badge", gtk4::Align::End);
             plan_badge.set_valign(gtk4::Align::Center);
             title_row.append(&plan_badge);
@@ -484,22 +1436,24 @@ impl PopupWindow {
         header_box.append(&title_row);
 
         let subtitle_row = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
-        let updated_text = if error.is_some() {
-            "Unable to load usage".to_string()
-        } else if let Some(snapshot) = snapshot {
-            format_relative_time(snapshot.updated_at)
-        } else {
-            "Loading\u{2026}".to_string()
-        };
-        let updated_label = label(&updated_text, "header-updated", gtk4::Align::Start);
+        let updated_label = label(&header.status, "header-updated", gtk4::Align::Start);
         updated_label.set_hexpand(true);
+        updated_label.set_wrap(true);
+        updated_label.set_tooltip_text(header.status_tooltip.as_deref());
         subtitle_row.append(&updated_label);
 
-        if let Some(email) = snapshot.and_then(|s| s.identity.email.as_ref()) {
+        if let Some(email) = &header.email {
             subtitle_row.append(&label(email, "dim-label", gtk4::Align::End));
         }
 
         header_box.append(&subtitle_row);
+
+        if let Some(login_method) = &header.login_method {
+            let login_label = label(login_method, "dim-label", gtk4::Align::End);
+            login_label.add_css_class("caption");
+            header_box.append(&login_label);
+        }
+
         content.append(&header_box);
     }
 
@@ -507,11 +1461,32 @@ impl PopupWindow {
         let switcher = gtk4::Box::new(gtk4::Orientation::Horizontal, 4);
         switcher.add_css_class("provider-switcher");
 
-        for provider in [Provider::Claude, Provider::Codex] {
+        if state.tabs.len() > 1 {
+            let button = gtk4::Button::new();
+            button.add_css_class("provider-tab");
+            button.set_hexpand(true);
+            if state.showing_overview {
+                button.add_css_class("selected");
+            }
+            button.set_child(Some(&label(
+                "Overview",
+                "provider-tab-label",
+                gtk4::Align::Center,
+            )));
+
+            let popup = self.clone();
+            button.connect_clicked(move |_| {
+                popup.show_overview();
+            });
+            switcher.append(&button);
+        }
+
+        for (provider, tab_name) in &ordered_tabs(state) {
+            let provider = *provider;
             let button = gtk4::Button::new();
             button.add_css_class("provider-tab");
             button.set_hexpand(true);
-            if provider == state.provider {
+            if provider == state.provider && !state.showing_overview {
                 button.add_css_class("selected");
             }
 
@@ -519,19 +1494,16 @@ impl PopupWindow {
             let dot = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
             dot.set_size_request(6, 6);
             dot.add_css_class("provider-dot");
-            match provider {
-                Provider::Claude => dot.add_css_class("provider-dot-claude"),
-                Provider::Codex => dot.add_css_class("provider-dot-codex"),
-            }
+            dot.add_css_class(&format!("provider-dot-{}", provider_css_name(provider)));
 
-            let name = label(provider.name(), "provider-tab-label", gtk4::Align::Start);
+            let name = label(tab_name, "provider-tab-label", gtk4::Align::Start);
             inner.append(&dot);
             inner.append(&name);
             button.set_child(Some(&inner));
 
             let popup = self.clone();
             button.connect_clicked(move |_| {
-                popup.show(provider);
+                popup.choose_provider(provider);
             });
 
             switcher.append(&button);
@@ -540,161 +1512,216 @@ impl PopupWindow {
         content.append(&switcher);
     }
 
-    fn build_usage_sections(
-        &self,
-        content: &gtk4::Box,
-        provider: Provider,
-        usage_rows: &[UsageRow<'_>],
-        show_as_remaining: bool,
-        accent: &gdk::RGBA,
-        trough: &gdk::RGBA,
-    ) {
-        for row in usage_rows {
-            self.build_usage_row(
-                content,
-                provider,
-                row.title.as_str(),
-                row.window,
-                show_as_remaining,
-                accent,
-                trough,
-                row.show_pace,
-            );
-        }
-    }
-
-    #[allow(clippy::too_many_arguments)]
     fn build_usage_row(
         &self,
         content: &gtk4::Box,
-        provider: Provider,
-        title: &str,
-        window: &RateWindow,
-        show_as_remaining: bool,
+        row: &UsageRowModel,
         accent: &gdk::RGBA,
         trough: &gdk::RGBA,
-        show_pace: bool,
     ) {
         let section = gtk4::Box::new(gtk4::Orientation::Vertical, 3);
         section.set_margin_top(10);
-        section.append(&label(title, "heading", gtk4::Align::Start));
+        if row.quota_adjusted {
+            let title_row = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+            let title = label(&row.title, "heading", gtk4::Align::Start);
+            title.set_hexpand(true);
+            title_row.append(&title);
+            let badge = label("Quota adjusted?", "plan-badge", gtk4::Align::End);
+            badge.add_css_class("warning");
+            badge.set_valign(gtk4::Align::Center);
+            badge.set_tooltip_text(Some(
+                "Usage dropped without a reset; the provider may have changed this limit",
+            ));
+            title_row.append(&badge);
+            section.append(&title_row);
+        } else {
+            section.append(&label(&row.title, "heading", gtk4::Align::Start));
+        }
 
         let progress_bar = UsageProgressBar::new();
         progress_bar.set_hexpand(true);
-        let display_percent = if show_as_remaining {
-            window.remaining_percent()
-        } else {
-            window.used_percent
-        };
-        progress_bar.set_progress(display_percent.clamp(0.0, 1.0));
+        progress_bar.set_progress(row.progress);
         progress_bar.set_colors(*accent, *trough);
-        if show_pace {
-            if let Some(detail) = UsagePaceText::weekly_detail(provider, window, Utc::now()) {
-                let marker = detail.expected_used_percent / 100.0;
-                let is_deficit = matches!(
-                    detail.stage,
-                    UsagePaceStage::SlightlyAhead
-                        | UsagePaceStage::Ahead
-                        | UsagePaceStage::FarAhead
-                );
-                progress_bar.set_pace_marker(Some(marker), is_deficit);
-            }
+        if let Some(marker) = row.pace_marker {
+            progress_bar.set_pace_marker(
+                Some(marker.position),
+                marker.is_deficit,
+                self.color_scheme.get(),
+            );
         }
         section.append(&progress_bar);
 
+        let week_strip = WeekStrip::new();
+        week_strip.set_hexpand(true);
+        week_strip.set_colors(*accent, *trough);
+        week_strip.set_days(&row.week_strip);
+        section.append(&week_strip);
+
         let details_row = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
-        let percent_text = if show_as_remaining {
-            format!("{:.0}% remaining", window.remaining_percent() * 100.0)
-        } else {
-            format!("{:.0}% used", window.used_percent * 100.0)
-        };
-        let percent_label = label(&percent_text, "usage-label", gtk4::Align::Start);
+        let percent_label = label(&row.percent_text, "usage-label", gtk4::Align::Start);
         percent_label.set_hexpand(true);
+        self.attach_usage_toggle(&percent_label);
         details_row.append(&percent_label);
 
-        if let Some(resets_at) = &window.resets_at {
-            details_row.append(&label(&format_reset_time(*resets_at), "countdown-label", gtk4::Align::End));
+        if let Some(reset_text) = &row.reset_text {
+            let reset_label = label(reset_text, "countdown-label", gtk4::Align::End);
+            reset_label.set_tooltip_text(row.reset_tooltip.as_deref());
+            details_row.append(&reset_label);
         }
 
         section.append(&details_row);
 
-        if show_pace {
-            if let Some(summary) = UsagePaceText::weekly_summary(provider, window, Utc::now()) {
-                section.append(&label(&summary, "pace-label", gtk4::Align::Start));
+        if let Some(summary) = &row.pace_summary {
+            section.append(&label(summary, "pace-label", gtk4::Align::Start));
+        }
+        if let Some(value_estimate) = &row.value_estimate {
+            section.append(&label(value_estimate, "pace-label", gtk4::Align::Start));
+        }
+        if let Some(week_over_week) = &row.week_over_week {
+            let trend = label(&week_over_week.text, "pace-label", gtk4::Align::Start);
+            // More usage than last week reads as a warning, less as good news.
+            match week_over_week.trend {
+                Trend::Up => trend.add_css_class("warning"),
+                Trend::Down => trend.add_css_class("success"),
+                Trend::Flat => {}
             }
+            section.append(&trend);
+        }
+        if let Some(velocity) = &row.velocity {
+            section.append(&label(velocity, "pace-label", gtk4::Align::Start));
         }
         content.append(&section);
     }
 
-    fn build_cost_section(
+    /// Clicking a percentage flips every row between used and remaining.
+    /// The config keeps its value; the daemon remembers the flip instead.
+    fn attach_usage_toggle(&self, percent_label: &gtk4::Label) {
+        let tooltip = if self.provider_state.borrow().show_as_remaining {
+            "Click to show used"
+        } else {
+            "Click to show remaining"
+        };
+        percent_label.set_tooltip_text(Some(tooltip));
+        percent_label.set_can_target(true);
+
+        let click = gtk4::GestureClick::new();
+        let popup = self.clone();
+        click.connect_released(move |_, _, _, _| {
+            let show_as_remaining = !popup.provider_state.borrow().show_as_remaining;
+            popup.notify_view(ViewChange::ShowAsRemaining(show_as_remaining));
+            // Rebuilding removes this label, so wait until its click is done.
+            let popup = popup.clone();
+            glib::idle_add_local_once(move || popup.set_show_as_remaining(show_as_remaining));
+        });
+        percent_label.add_controller(click);
+    }
+
+    fn build_surface_details(
         &self,
         content: &gtk4::Box,
-        cost: Option<&CostSnapshot>,
-        tokens: Option<&CostUsageTokenSnapshot>,
+        surfaces: &[UsageRowModel],
+        accent: &gdk::RGBA,
+        trough: &gdk::RGBA,
     ) {
+        let list = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+        for row in surfaces {
+            self.build_usage_row(&list, row, accent, trough);
+        }
+        let expander = gtk4::Expander::new(Some("Details"));
+        expander.set_margin_top(10);
+        expander.set_child(Some(&list));
+        let popup = self.clone();
+        expander.connect_expanded_notify(move |_| {
+            popup.resize_to_content(&popup.current_content());
+        });
+        content.append(&expander);
+    }
+
+    fn build_cost_section(&self, content: &gtk4::Box, cost: &CostSectionModel, note: Option<&str>) {
         let section = gtk4::Box::new(gtk4::Orientation::Vertical, 3);
         section.set_margin_top(4);
+        section.set_tooltip_text(note);
         section.append(&label("Cost", "heading", gtk4::Align::Start));
 
-        if let Some(cost) = cost {
-            if cost.log_error {
+        match cost {
+            CostSectionModel::LogError => {
                 let error_label = label("Error reading logs", "cost-error", gtk4::Align::Start);
                 attach_log_copy_handler(&error_label);
                 section.append(&error_label);
-                content.append(&section);
-                return;
+            }
+            CostSectionModel::Lines(lines) => {
+                for line in lines {
+                    let line_label = label(&line.text, "cost-line", gtk4::Align::Start);
+                    if let Some(detail) = &line.detail {
+                        self.attach_cost_detail(&line_label, detail);
+                    }
+                    section.append(&line_label);
+                }
+            }
+            CostSectionModel::Empty => {
+                section.append(&label("No cost data yet", "dim-label", gtk4::Align::Start));
+            }
+            CostSectionModel::Hidden => {
+                section.append(&label(COSTS_HIDDEN, "dim-label", gtk4::Align::Start));
             }
         }
 
-        if let Some(tokens) = tokens {
-            let prefix = cost.map_or("", |c| if c.pricing_estimate { "~" } else { "" });
-            let session_cost = tokens
-                .session_cost_usd
-                .or_else(|| cost.map(|c| c.today_cost))
-                .map(|v| format!("{}{}", prefix, format_currency(v)));
-            let month_cost = tokens
-                .last_30_days_cost_usd
-                .or_else(|| cost.map(|c| c.monthly_cost))
-                .map(|v| format!("{}{}", prefix, format_currency(v)));
-
-            let session_tokens = tokens.session_tokens.map(format_token_count);
-            let session_line = if let Some(cost_text) = session_cost {
-                if let Some(tokens_text) = session_tokens {
-                    format!("Today: {} · {} tokens", cost_text, tokens_text)
-                } else {
-                    format!("Today: {}", cost_text)
-                }
-            } else {
-                "Today: —".to_string()
-            };
+        content.append(&section);
+    }
 
-            let month_tokens = tokens.last_30_days_tokens.map(format_token_count);
-            let month_line = if let Some(cost_text) = month_cost {
-                if let Some(tokens_text) = month_tokens {
-                    format!("Last 30 days: {} · {} tokens", cost_text, tokens_text)
-                } else {
-                    format!("Last 30 days: {}", cost_text)
-                }
-            } else {
-                "Last 30 days: —".to_string()
-            };
+    /// Clicking `line` opens its per-model breakdown in a popover.
+    fn attach_cost_detail(&self, line: &gtk4::Label, detail: &CostDetailModel) {
+        let popover = gtk4::Popover::new();
+        popover.set_child(Some(&cost_detail_content(detail)));
+        popover.set_position(gtk4::PositionType::Bottom);
+        popover.set_parent(line);
+        self.hold_dismiss_while_open(&popover);
+
+        line.set_tooltip_text(Some("Click for a breakdown by model"));
+        line.set_can_target(true);
+        line.set_cursor_from_name(Some("pointer"));
+        let click = gtk4::GestureClick::new();
+        let popover_clone = popover.clone();
+        click.connect_released(move |_, _, _, _| popover_clone.popup());
+        line.add_controller(click);
+        // Labels don't unparent extra children when the page is rebuilt.
+        line.connect_destroy(move |_| popover.unparent());
+    }
+
+    fn build_monthly_budget(
+        &self,
+        content: &gtk4::Box,
+        budget: &MonthlyBudgetModel,
+        accent: &gdk::RGBA,
+        trough: &gdk::RGBA,
+    ) {
+        let section = gtk4::Box::new(gtk4::Orientation::Vertical, 3);
+        section.set_margin_top(6);
+        section.append(&label(&budget.line, "cost-line", gtk4::Align::Start));
 
-            section.append(&label(&session_line, "cost-line", gtk4::Align::Start));
-            section.append(&label(&month_line, "cost-line", gtk4::Align::Start));
-        } else if let Some(cost) = cost {
-            let prefix = if cost.pricing_estimate { "~" } else { "" };
-            let today = format!("Today: {}{}", prefix, format_currency(cost.today_cost));
-            let month = format!("Last 30 days: {}{}", prefix, format_currency(cost.monthly_cost));
-            section.append(&label(&today, "cost-line", gtk4::Align::Start));
-            section.append(&label(&month, "cost-line", gtk4::Align::Start));
-        } else {
-            section.append(&label("No cost data yet", "dim-label", gtk4::Align::Start));
-        }
+        let color = match budget.level {
+            BudgetLevel::Normal => *accent,
+            BudgetLevel::Warning => rgb_to_rgba(self.color_scheme.get().warning_rgb(), 0.85),
+            BudgetLevel::Critical => rgb_to_rgba(self.color_scheme.get().critical_rgb(), 0.85),
+        };
+        let progress_bar = UsageProgressBar::new();
+        progress_bar.set_hexpand(true);
+        progress_bar.set_progress(budget.progress);
+        progress_bar.set_colors(color, *trough);
+        section.append(&progress_bar);
 
         content.append(&section);
     }
 
-    fn build_error_section(&self, content: &gtk4::Box, error: &str, hint: &str) {
+    fn build_error_section(
+        &self,
+        content: &gtk4::Box,
+        error: &str,
+        hint: &str,
+        hint_link: Option<&str>,
+        hint_command: Option<&str>,
+        recent_errors: &[String],
+    ) {
         let section = gtk4::Box::new(gtk4::Orientation::Vertical, 8);
 
         let error_label = label(error, "error", gtk4::Align::Start);
@@ -704,98 +1731,201 @@ impl PopupWindow {
         let hint_box = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
         hint_box.add_css_class("error-hint");
         let hint_label = gtk4::Label::new(Some(hint));
+        if let Some(url) = hint_link {
+            hint_label.set_markup(&format!(
+                "<a href=\"{}\">{}</a>",
+                glib::markup_escape_text(url),
+                glib::markup_escape_text(hint)
+            ));
+            hint_label.set_tooltip_text(Some(url));
+            hint_label.set_wrap(true);
+        }
         hint_label.set_selectable(true);
         hint_label.set_halign(gtk4::Align::Start);
+        hint_label.set_hexpand(true);
         hint_box.append(&hint_label);
+        if let Some(command) = hint_command {
+            self.append_command_buttons(&hint_box, command);
+        }
         section.append(&hint_box);
 
+        if !recent_errors.is_empty() {
+            let list = gtk4::Box::new(gtk4::Orientation::Vertical, 2);
+            list.set_margin_top(4);
+            for line in recent_errors {
+                let entry = label(line, "dim-label", gtk4::Align::Start);
+                entry.set_wrap(true);
+                entry.set_xalign(0.0);
+                list.append(&entry);
+            }
+            let expander = gtk4::Expander::new(Some("Recent errors"));
+            expander.set_child(Some(&list));
+            let popup = self.clone();
+            expander.connect_expanded_notify(move |_| {
+                popup.resize_to_content(&popup.current_content());
+            });
+            section.append(&expander);
+        }
+
+        content.append(&section);
+    }
+
+    /// Copy and run buttons for the command a hint quotes.
+    fn append_command_buttons(&self, hint_box: &gtk4::Box, command: &str) {
+        let command = Rc::new(command.to_string());
+
+        let copy = gtk4::Button::from_icon_name("edit-copy-symbolic");
+        copy.add_css_class("flat");
+        copy.set_valign(gtk4::Align::Center);
+        copy.set_tooltip_text(Some(&format!("Copy `{}`", command)));
+        let copied = Rc::clone(&command);
+        copy.connect_clicked(move |button| {
+            button.clipboard().set_text(&copied);
+            button.set_tooltip_text(Some("Copied!"));
+        });
+        hint_box.append(&copy);
+
+        let run = gtk4::Button::from_icon_name("utilities-terminal-symbolic");
+        run.add_css_class("flat");
+        run.set_valign(gtk4::Align::Center);
+        match self.terminal.borrow().clone() {
+            Some(terminal) => {
+                run.set_tooltip_text(Some(&format!("Run in terminal ({})", terminal)));
+                run.connect_clicked(move |_| {
+                    if let Err(e) = run_in_terminal(&terminal, &command) {
+                        tracing::warn!(terminal, error = %e, "Failed to open terminal");
+                    }
+                });
+            }
+            None => {
+                run.set_sensitive(false);
+                run.set_tooltip_text(Some(
+                    "No terminal found; set terminal.preferred in the config",
+                ));
+            }
+        }
+        hint_box.append(&run);
+    }
+
+    fn build_login_section(&self, content: &gtk4::Box, login: &LoginModel) {
+        let section = gtk4::Box::new(gtk4::Orientation::Vertical, 4);
+        section.set_margin_top(12);
+
+        let status = gtk4::Box::new(gtk4::Orientation::Horizontal, 8);
+        if login.running {
+            let spinner = gtk4::Spinner::new();
+            spinner.start();
+            status.append(&spinner);
+        }
+        let css_class = if login.failed { "error" } else { "dim-label" };
+        let message = label(&login.message, css_class, gtk4::Align::Start);
+        message.set_wrap(true);
+        message.set_xalign(0.0);
+        message.set_hexpand(true);
+        status.append(&message);
+        section.append(&status);
+
+        if let Some(url) = &login.auth_link {
+            let link = gtk4::Label::new(None);
+            link.set_markup(&format!(
+                "<a href=\"{0}\">{0}</a>",
+                glib::markup_escape_text(url)
+            ));
+            link.set_ellipsize(gtk4::pango::EllipsizeMode::Middle);
+            link.set_tooltip_text(Some(url));
+            link.set_halign(gtk4::Align::Start);
+            section.append(&link);
+        }
+
         content.append(&section);
     }
 
     fn build_provider_cost_section(
         &self,
         content: &gtk4::Box,
-        cost: &ProviderCostSnapshot,
+        cost: &ProviderCostModel,
         accent: &gdk::RGBA,
         trough: &gdk::RGBA,
     ) {
-        if cost.limit <= 0.0 {
-            return;
-        }
-
-        let title = if cost.currency_code == "Quota" {
-            "Quota usage".to_string()
-        } else {
-            "Extra usage".to_string()
-        };
-
-        let used = if cost.currency_code == "Quota" {
-            format!("{:.0}", cost.used)
-        } else {
-            format_currency_with_code(cost.used, &cost.currency_code)
-        };
-        let limit = if cost.currency_code == "Quota" {
-            format!("{:.0}", cost.limit)
-        } else {
-            format_currency_with_code(cost.limit, &cost.currency_code)
-        };
-        let period = cost.period.as_deref().unwrap_or("This month");
-        let spend_line = format!("{}: {} / {}", period, used, limit);
-
-        let percent_used = (cost.used / cost.limit).clamp(0.0, 1.0);
-
         let section = gtk4::Box::new(gtk4::Orientation::Vertical, 3);
         section.set_margin_top(14);
-        section.append(&label(&title, "heading", gtk4::Align::Start));
+        section.append(&label(&cost.title, "heading", gtk4::Align::Start));
 
         let progress_bar = UsageProgressBar::new();
         progress_bar.set_hexpand(true);
-        progress_bar.set_progress(percent_used);
+        progress_bar.set_progress(cost.progress);
         progress_bar.set_colors(*accent, *trough);
         section.append(&progress_bar);
 
         let details = gtk4::Box::new(gtk4::Orientation::Horizontal, 0);
-        let spend_label = label(&spend_line, "cost-line", gtk4::Align::Start);
+        let spend_label = label(&cost.spend_line, "cost-line", gtk4::Align::Start);
         spend_label.set_hexpand(true);
         details.append(&spend_label);
-        details.append(&label(
-            &format!("{:.0}% used", percent_used * 100.0),
-            "countdown-label",
-            gtk4::Align::End,
-        ));
+        details.append(&label(&cost.percent_text, "countdown-label", gtk4::Align::End));
+        if let Some(reset_text) = &cost.reset_text {
+            let reset_label = label(reset_text, "countdown-label", gtk4::Align::End);
+            reset_label.set_margin_start(8);
+            reset_label.set_tooltip_text(cost.reset_tooltip.as_deref());
+            details.append(&reset_label);
+        }
 
         section.append(&details);
         content.append(&section);
     }
 
-    fn build_footer_actions(&self, content: &gtk4::Box, _updated_at: Option<DateTime<Utc>>) {
+    fn build_footer_actions(
+        &self,
+        content: &gtk4::Box,
+        provider: Provider,
+        footer: &[FooterAction],
+    ) {
         content.append(&separator());
 
         let actions = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
         actions.add_css_class("footer-actions");
 
-        let provider = self.provider_state.borrow().provider;
-        let has_error = self.provider_state.borrow().errors.contains_key(&provider);
-        let login_label = if has_error { "Add Account" } else { "Switch Account" };
-
-        actions.append(&self.action_button(login_label, move || {
-            crate::daemon::login::spawn_provider_login(provider);
-        }));
-        actions.append(&self.action_button("Usage Dashboard", move || {
-            open::that(provider.dashboard_url()).ok();
-        }));
-        actions.append(&self.action_button("Status Page", move || {
-            open::that(provider.status_url()).ok();
-        }));
-        actions.append(&self.action_button("Refresh Now", move || {
-            trigger_refresh();
-        }));
-        actions.append(&self.action_button("Settings", {
-            let popup = self.clone();
-            move || {
-                popup.open_settings_window();
-            }
-        }));
+        for action in footer {
+            let button = match action {
+                FooterAction::Login { add_account: true } => self.action_button(action.label(), {
+                    let popup = self.clone();
+                    move || {
+                        popup.start_login(provider, LoginMode::Reauthenticate);
+                    }
+                }),
+                FooterAction::Login { add_account: false } => {
+                    actions.append(&self.account_menu_button(action.label(), provider));
+                    continue;
+                }
+                FooterAction::UsageDashboard => self.action_button(action.label(), move || {
+                    open::that(provider.dashboard_url()).ok();
+                }),
+                FooterAction::StatusPage => self.action_button(action.label(), move || {
+                    open::that(provider.status_url()).ok();
+                }),
+                FooterAction::RefreshNow => self.action_button(action.label(), move || {
+                    trigger_refresh();
+                }),
+                FooterAction::Insights => self.action_button(action.label(), {
+                    let popup = self.clone();
+                    move || {
+                        popup.show_insights();
+                    }
+                }),
+                FooterAction::HideCosts { .. } => self.action_button(action.label(), {
+                    let popup = self.clone();
+                    move || {
+                        popup.toggle_hide_costs();
+                    }
+                }),
+                FooterAction::Settings => self.action_button(action.label(), {
+                    let popup = self.clone();
+                    move || {
+                        popup.open_settings_window();
+                    }
+                }),
+            };
+            actions.append(&button);
+        }
         content.append(&actions);
 
         let version_label = label(
@@ -807,6 +1937,77 @@ impl PopupWindow {
         content.append(&version_label);
     }
 
+    /// The footer's Account entry: a menu of `AccountAction`s. Auto-dismiss
+    /// is held while it is open, since its popover takes the popup's focus.
+    fn account_menu_button(&self, label_text: &str, provider: Provider) -> gtk4::MenuButton {
+        let popover = gtk4::Popover::new();
+        let menu = gtk4::Box::new(gtk4::Orientation::Vertical, 0);
+        let credentials = credentials_path(provider).filter(|path| path.exists());
+
+        for action in AccountAction::ALL {
+            let button = self.action_button(action.label(), {
+                let popup = self.clone();
+                let credentials = credentials.clone();
+                clone!(
+                    #[weak]
+                    popover,
+                    move || {
+                        popover.popdown();
+                        match action.login_mode() {
+                            Some(mode) => popup.start_login(provider, mode),
+                            None => {
+                                if let Some(path) = credentials.clone() {
+                                    show_credentials_file(path);
+                                }
+                            }
+                        }
+                    }
+                )
+            });
+            if action == AccountAction::ShowCredentials && credentials.is_none() {
+                button.set_sensitive(false);
+                button.set_tooltip_text(Some("No credentials file found"));
+            }
+            menu.append(&button);
+        }
+        popover.set_child(Some(&menu));
+        self.hold_dismiss_while_open(&popover);
+
+        let button = gtk4::MenuButton::new();
+        button.set_label(label_text);
+        button.add_css_class("footer-action");
+        button.set_halign(gtk4::Align::Fill);
+        button.set_popover(Some(&popover));
+        button
+    }
+
+    /// Auto-dismiss is held while `popover` is open, since it takes the
+    /// popup's focus without the user having clicked away.
+    fn hold_dismiss_while_open(&self, popover: &gtk4::Popover) {
+        {
+            let popup = self.clone();
+            popover.connect_show(move |_| {
+                popup.dismiss_held.set(true);
+                popup.cancel_pending_dismiss();
+            });
+        }
+        {
+            let popup = self.clone();
+            popover.connect_closed(move |_| {
+                popup.dismiss_held.set(false);
+                // A click outside both closes the popover and leaves the popup.
+                if popup.window.is_visible() && !popup.window.is_active() {
+                    schedule_dismiss(
+                        &popup.window,
+                        &popup.dismiss_source,
+                        &popup.dismiss_held,
+                        popup.dismiss_timeout_ms.get(),
+                    );
+                }
+            });
+        }
+    }
+
     fn action_button<F>(&self, label_text: &str, action: F) -> gtk4::Button
     where
         F: Fn() + 'static,
@@ -833,6 +2034,7 @@ impl PopupWindow {
             .default_width(360)
             .default_height(420)
             .build();
+        let saver = Rc::new(PreferencesSaver::new(&window));
 
         let page = adw::PreferencesPage::new();
         let group = adw::PreferencesGroup::new();
@@ -847,14 +2049,13 @@ impl PopupWindow {
         show_remaining_row.set_activatable_widget(Some(&show_remaining_switch));
         {
             let settings = Rc::clone(&settings);
+            let saver = Rc::clone(&saver);
             let popup = self.clone();
             show_remaining_switch.connect_state_set(move |_, state| {
                 {
                     let mut settings = settings.borrow_mut();
                     settings.display.show_as_remaining = state;
-                    if let Err(e) = settings.save() {
-                        tracing::warn!(error = %e, "Failed to save settings");
-                    }
+                    saver.save(&settings);
                 }
                 popup.set_show_as_remaining(state);
                 glib::Propagation::Proceed
@@ -862,6 +2063,30 @@ impl PopupWindow {
         }
         group.add(&show_remaining_row);
 
+        let hide_costs_row = adw::ActionRow::builder()
+            .title("Hide costs")
+            .subtitle("Hide dollar amounts, e.g. while screen sharing")
+            .build();
+        let hide_costs_switch = gtk4::Switch::new();
+        hide_costs_switch.set_active(settings.borrow().display.hide_costs);
+        hide_costs_row.add_suffix(&hide_costs_switch);
+        hide_costs_row.set_activatable_widget(Some(&hide_costs_switch));
+        {
+            let settings = Rc::clone(&settings);
+            let saver = Rc::clone(&saver);
+            let popup = self.clone();
+            hide_costs_switch.connect_state_set(move |_, state| {
+                {
+                    let mut settings = settings.borrow_mut();
+                    settings.display.hide_costs = state;
+                    saver.save(&settings);
+                }
+                popup.set_hide_costs(state);
+                glib::Propagation::Proceed
+            });
+        }
+        group.add(&hide_costs_row);
+
         let merge_icons_row = adw::ActionRow::builder()
             .title("Merge tray icons")
             .build();
@@ -871,13 +2096,12 @@ impl PopupWindow {
         merge_icons_row.set_activatable_widget(Some(&merge_icons_switch));
         {
             let settings = Rc::clone(&settings);
+            let saver = Rc::clone(&saver);
             merge_icons_switch.connect_state_set(move |_, state| {
                 {
                     let mut settings = settings.borrow_mut();
                     settings.providers.merge_icons = state;
-                    if let Err(e) = settings.save() {
-                        tracing::warn!(error = %e, "Failed to save settings");
-                    }
+                    saver.save(&settings);
                 }
                 glib::Propagation::Proceed
             });
@@ -895,6 +2119,7 @@ impl PopupWindow {
         });
         {
             let settings = Rc::clone(&settings);
+            let saver = Rc::clone(&saver);
             let popup = self.clone();
             theme_row.connect_selected_notify(move |row| {
                 let mode = match row.selected() {
@@ -905,15 +2130,120 @@ impl PopupWindow {
                 {
                     let mut settings = settings.borrow_mut();
                     settings.theme.mode = mode.clone();
-                    if let Err(e) = settings.save() {
-                        tracing::warn!(error = %e, "Failed to save settings");
-                    }
+                    saver.save(&settings);
                 }
                 popup.set_theme_mode(mode);
             });
         }
         group.add(&theme_row);
 
+        let layout_row = adw::ComboRow::new();
+        layout_row.set_title("Popup layout");
+        layout_row.set_subtitle("Stacked shows every provider on one page");
+        let layout_model = gtk4::StringList::new(&["Tabs", "Stacked"]);
+        layout_row.set_model(Some(&layout_model));
+        layout_row.set_selected(match settings.borrow().popup.layout {
+            PopupLayout::Tabs => 0,
+            PopupLayout::Stacked => 1,
+        });
+        {
+            let settings = Rc::clone(&settings);
+            let saver = Rc::clone(&saver);
+            let popup = self.clone();
+            layout_row.connect_selected_notify(move |row| {
+                let layout = match row.selected() {
+                    1 => PopupLayout::Stacked,
+                    _ => PopupLayout::Tabs,
+                };
+                {
+                    let mut settings = settings.borrow_mut();
+                    settings.popup.layout = layout;
+                    saver.save(&settings);
+                }
+                popup.set_layout(layout);
+            });
+        }
+        group.add(&layout_row);
+
+        let providers_group = adw::PreferencesGroup::new();
+        providers_group.set_title("Providers");
+        providers_group.set_description(Some(
+            "Enabling or disabling a provider takes effect after a restart",
+        ));
+        let running: Vec<Provider> = self
+            .provider_state
+            .borrow()
+            .tabs
+            .iter()
+            .map(|(provider, _)| *provider)
+            .collect();
+        let providers: [(Box<dyn UsageProvider>, fn(&mut Settings) -> &mut bool); 2] = [
+            (Box::new(ClaudeProvider::new()), |settings| {
+                &mut settings.providers.claude.enabled
+            }),
+            (Box::new(CodexProvider::new()), |settings| {
+                &mut settings.providers.codex.enabled
+            }),
+        ];
+        let now = Utc::now();
+        for (provider, enabled) in providers {
+            let id = provider.identifier();
+            let is_running = running.iter().any(|running| match id {
+                Provider::Codex => running.is_codex(),
+                _ => *running == id,
+            });
+
+            let row = adw::ActionRow::builder().title(provider.name()).build();
+            if let Some(path) = provider.credentials_path() {
+                row.set_subtitle(&path.display().to_string());
+            }
+            let expires_at = provider.credentials_expire_at();
+            let status = CredentialStatus::build(provider.has_valid_credentials(), expires_at, now);
+            let status_icon = gtk4::Image::from_icon_name(status.icon_name());
+            status_icon.add_css_class(status.css_class());
+            status_icon.set_tooltip_text(Some(&status.describe(
+                expires_at,
+                provider.credential_error_hint(),
+                now,
+            )));
+            row.add_prefix(&status_icon);
+
+            let restart_label = gtk4::Label::new(Some("Restart required"));
+            restart_label.add_css_class("dim-label");
+            restart_label.add_css_class("caption");
+            row.add_suffix(&restart_label);
+
+            let login_button = gtk4::Button::with_label("Log in…");
+            login_button.set_valign(gtk4::Align::Center);
+            {
+                let popup = self.clone();
+                login_button
+                    .connect_clicked(move |_| popup.start_login(id, LoginMode::Reauthenticate));
+            }
+            row.add_suffix(&login_button);
+
+            let enabled_switch = gtk4::Switch::new();
+            enabled_switch.set_valign(gtk4::Align::Center);
+            enabled_switch.set_active(*enabled(&mut settings.borrow_mut()));
+            restart_label.set_visible(enabled_switch.is_active() != is_running);
+            row.add_suffix(&enabled_switch);
+            row.set_activatable_widget(Some(&enabled_switch));
+            {
+                let settings = Rc::clone(&settings);
+                let saver = Rc::clone(&saver);
+                enabled_switch.connect_state_set(move |_, state| {
+                    {
+                        let mut settings = settings.borrow_mut();
+                        *enabled(&mut settings) = state;
+                        saver.save(&settings);
+                    }
+                    restart_label.set_visible(state != is_running);
+                    glib::Propagation::Proceed
+                });
+            }
+            providers_group.add(&row);
+        }
+
         let notifications_group = adw::PreferencesGroup::new();
         notifications_group.set_title("Notifications");
         let threshold_row = adw::ActionRow::builder()
@@ -926,13 +2256,12 @@ impl PopupWindow {
         threshold_row.set_activatable_widget(Some(&threshold_spin));
         {
             let settings = Rc::clone(&settings);
+            let saver = Rc::clone(&saver);
             threshold_spin.connect_value_changed(move |spin| {
                 {
                     let mut settings = settings.borrow_mut();
                     settings.notifications.threshold = spin.value();
-                    if let Err(e) = settings.save() {
-                        tracing::warn!(error = %e, "Failed to save settings");
-                    }
+                    saver.save(&settings);
                 }
             });
         }
@@ -946,17 +2275,22 @@ impl PopupWindow {
         let shortcut_entry = gtk4::Entry::new();
         shortcut_entry.set_text(&settings.borrow().shortcuts.popup);
         shortcut_entry.set_width_chars(12);
+        validate_shortcut_entry(&shortcut_entry);
         shortcut_row.add_suffix(&shortcut_entry);
         shortcut_row.set_activatable_widget(Some(&shortcut_entry));
         {
             let settings = Rc::clone(&settings);
+            let saver = Rc::clone(&saver);
             shortcut_entry.connect_changed(move |entry| {
+                // A half-typed shortcut isn't saved for the daemon to
+                // register.
+                if !validate_shortcut_entry(entry) {
+                    return;
+                }
                 {
                     let mut settings = settings.borrow_mut();
                     settings.shortcuts.popup = entry.text().to_string();
-                    if let Err(e) = settings.save() {
-                        tracing::warn!(error = %e, "Failed to save settings");
-                    }
+                    saver.save(&settings);
                 }
             });
         }
@@ -965,13 +2299,12 @@ impl PopupWindow {
         shortcut_row.add_suffix(&shortcut_switch);
         {
             let settings = Rc::clone(&settings);
+            let saver = Rc::clone(&saver);
             shortcut_switch.connect_state_set(move |_, state| {
                 {
                     let mut settings = settings.borrow_mut();
                     settings.shortcuts.enabled = state;
-                    if let Err(e) = settings.save() {
-                        tracing::warn!(error = %e, "Failed to save settings");
-                    }
+                    saver.save(&settings);
                 }
                 glib::Propagation::Proceed
             });
@@ -979,6 +2312,7 @@ impl PopupWindow {
         shortcuts_group.add(&shortcut_row);
 
         page.add(&group);
+        page.add(&providers_group);
         page.add(&notifications_group);
         page.add(&shortcuts_group);
         window.add(&page);
@@ -989,7 +2323,10 @@ impl PopupWindow {
         self.stop_live_updates();
 
         let state = Rc::clone(&self.provider_state);
+        let sections = Rc::clone(&self.sections);
         let content = self.current_content();
+        let window = self.window.clone();
+        let stats = Rc::clone(&self.live_update_stats);
 
         let source_id = glib::timeout_add_local(
             std::time::Duration::from_millis(UPDATE_INTERVAL_MS.into()),
@@ -998,10 +2335,20 @@ impl PopupWindow {
                 state,
                 #[weak]
                 content,
+                #[weak]
+                window,
                 #[upgrade_or]
                 glib::ControlFlow::Break,
                 move || {
-                    update_dynamic_labels(&state, &content);
+                    let (ticks, skipped) = stats.get();
+                    // Nobody is reading the countdowns while another window
+                    // has focus; they catch up when the popup is focused again.
+                    if !window.is_active() {
+                        stats.set((ticks, skipped + 1));
+                        return glib::ControlFlow::Continue;
+                    }
+                    stats.set((ticks + 1, skipped));
+                    update_dynamic_labels(&state, &content, &sections);
                     glib::ControlFlow::Continue
                 }
             ),
@@ -1011,9 +2358,7 @@ impl PopupWindow {
     }
 
     fn stop_live_updates(&self) {
-        if let Some(source_id) = self.update_source.take() {
-            source_id.remove();
-        }
+        stop_live_update_source(&self.update_source, &self.live_update_stats);
     }
 }
 
@@ -1024,6 +2369,128 @@ impl Drop for PopupWindow {
     }
 }
 
+/// Saves changes made in the settings window. A read-only config, such as
+/// one home-manager links from the Nix store, gets a toast saying so rather
+/// than a log line nobody reads; a new toast replaces the last one so typing
+/// a shortcut doesn't queue one per keystroke.
+struct PreferencesSaver {
+    window: glib::WeakRef<adw::PreferencesWindow>,
+    toast: RefCell<Option<adw::Toast>>,
+}
+
+impl PreferencesSaver {
+    fn new(window: &adw::PreferencesWindow) -> Self {
+        Self {
+            window: window.downgrade(),
+            toast: RefCell::new(None),
+        }
+    }
+
+    fn save(&self, settings: &Settings) {
+        let Err(e) = settings.save() else {
+            return;
+        };
+        match (e.downcast_ref::<ReadOnlyConfig>(), self.window.upgrade()) {
+            (Some(read_only), Some(window)) => {
+                if let Some(toast) = self.toast.borrow_mut().take() {
+                    toast.dismiss();
+                }
+                let toast = adw::Toast::new(&read_only.to_string());
+                window.add_toast(toast.clone());
+                *self.toast.borrow_mut() = Some(toast);
+            }
+            _ => tracing::warn!(error = %e, "Failed to save settings"),
+        }
+    }
+}
+
+/// Marks `entry` with a warning icon, whose tooltip says what is wrong,
+/// unless it holds a valid shortcut. Returns whether it does.
+fn validate_shortcut_entry(entry: &gtk4::Entry) -> bool {
+    match parse_hotkey(&entry.text()) {
+        Ok(_) => {
+            entry.remove_css_class("error");
+            entry.set_secondary_icon_name(None);
+            entry.set_secondary_icon_tooltip_text(None);
+            true
+        }
+        Err(e) => {
+            entry.add_css_class("error");
+            entry.set_secondary_icon_name(Some("dialog-warning-symbolic"));
+            entry.set_secondary_icon_tooltip_text(Some(&e.to_string()));
+            false
+        }
+    }
+}
+
+fn stop_live_update_source(
+    update_source: &Rc<Cell<Option<glib::SourceId>>>,
+    stats: &Rc<Cell<(u64, u64)>>,
+) {
+    if let Some(source_id) = update_source.take() {
+        source_id.remove();
+        let (ticks, skipped) = stats.replace((0, 0));
+        tracing::debug!(
+            component = "popup-live-updates",
+            label_updates = ticks,
+            skipped_unfocused = skipped,
+            "Wakeup sources"
+        );
+    }
+}
+
+fn schedule_dismiss(
+    window: &adw::Window,
+    dismiss_source: &Rc<Cell<Option<glib::SourceId>>>,
+    held: &Cell<bool>,
+    timeout_ms: u64,
+) {
+    if held.get() {
+        return;
+    }
+    if timeout_ms == 0 {
+        window.close();
+        return;
+    }
+
+    if let Some(source_id) = dismiss_source.take() {
+        source_id.remove();
+    }
+    let window_deferred = window.clone();
+    let dismiss_src_inner = Rc::clone(dismiss_source);
+    let source_id = glib::timeout_add_local_once(
+        std::time::Duration::from_millis(timeout_ms),
+        move || {
+            dismiss_src_inner.set(None);
+            window_deferred.close();
+        },
+    );
+    dismiss_source.set(Some(source_id));
+}
+
+/// The credentials file `provider` reads; Codex workspaces share one.
+fn credentials_path(provider: Provider) -> Option<std::path::PathBuf> {
+    match provider {
+        Provider::Claude => ClaudeProvider::new().credentials_path(),
+        Provider::Codex | Provider::CodexWorkspace(_) => CodexProvider::new().credentials_path(),
+    }
+}
+
+/// Shows `path` in the file manager, falling back to opening its folder
+/// when no desktop portal answers.
+fn show_credentials_file(path: std::path::PathBuf) {
+    std::thread::spawn(move || {
+        if let Err(e) = show_in_file_manager(&path) {
+            tracing::debug!(error = %e, "File manager portal unavailable");
+            if let Some(folder) = path.parent() {
+                if let Err(e) = open::that(folder) {
+                    tracing::warn!(error = %e, "Failed to open credentials folder");
+                }
+            }
+        }
+    });
+}
+
 fn apply_layer_shell_position(window: &adw::Window, settings: &PopupSettings) {
     let (anchor_v, anchor_h) = match settings.anchor {
         PopupAnchor::TopLeft => (gtk4_layer_shell::Edge::Top, gtk4_layer_shell::Edge::Left),
@@ -1046,70 +2513,47 @@ fn apply_layer_shell_position(window: &adw::Window, settings: &PopupSettings) {
     window.set_margin(gtk4_layer_shell::Edge::Left, settings.margin_left);
 }
 
-fn collect_usage_rows(provider: Provider, snapshot: &UsageSnapshot) -> Vec<UsageRow<'_>> {
-    let mut rows = Vec::new();
-
-    if let Some(primary) = &snapshot.primary {
-        let label = match provider {
-            Provider::Claude => "5-hour session",
-            Provider::Codex => "Session",
-        };
-        rows.push(UsageRow {
-            title: label.to_string(),
-            window: primary,
-            show_pace: false,
-        });
-    }
-
-    if let Some(secondary) = &snapshot.secondary {
-        let label = match provider {
-            Provider::Claude => "Weekly quota",
-            Provider::Codex => "Weekly",
-        };
-        rows.push(UsageRow {
-            title: label.to_string(),
-            window: secondary,
-            show_pace: true,
-        });
-    }
-
-    if let Some(tertiary) = &snapshot.tertiary {
-        let label = resolve_tertiary_label(snapshot, provider);
-        rows.push(UsageRow {
-            title: label,
-            window: tertiary,
-            show_pace: false,
-        });
-    }
-
-    rows
-}
-
-fn resolve_tertiary_label(snapshot: &UsageSnapshot, provider: Provider) -> String {
-    let Some(tertiary) = snapshot.tertiary.as_ref() else {
-        return "Model".to_string();
-    };
-
-    for carveout in &snapshot.carveouts {
-        if windows_match(&carveout.window, tertiary) {
-            return carveout
-                .label
-                .trim_end_matches(" Weekly")
-                .to_string();
-        }
-    }
-
-    match provider {
-        Provider::Claude => "Model".to_string(),
-        Provider::Codex => "Additional".to_string(),
+fn cost_detail_content(detail: &CostDetailModel) -> gtk4::Box {
+    let content = gtk4::Box::new(gtk4::Orientation::Vertical, 6);
+    content.set_margin_top(6);
+    content.set_margin_bottom(6);
+    content.set_margin_start(6);
+    content.set_margin_end(6);
+    content.append(&label(&detail.title, "heading", gtk4::Align::Start));
+
+    let grid = gtk4::Grid::new();
+    grid.set_column_spacing(12);
+    grid.set_row_spacing(2);
+    for (row, model) in (0..).zip(&detail.models) {
+        grid.attach(
+            &label(&model.model, "cost-line", gtk4::Align::Start),
+            0,
+            row,
+            1,
+            1,
+        );
+        grid.attach(
+            &label(&model.tokens, "cost-period", gtk4::Align::End),
+            1,
+            row,
+            1,
+            1,
+        );
+        grid.attach(
+            &label(&model.cost, "cost-amount", gtk4::Align::End),
+            2,
+            row,
+            1,
+            1,
+        );
     }
-}
+    content.append(&grid);
 
-fn windows_match(left: &RateWindow, right: &RateWindow) -> bool {
-    let percent_close = (left.used_percent - right.used_percent).abs() < 0.001;
-    let reset_same = left.resets_at == right.resets_at;
-    let window_same = left.window_minutes == right.window_minutes;
-    percent_close && reset_same && window_same
+    let split = label(&detail.split, "pace-label", gtk4::Align::Start);
+    split.set_wrap(true);
+    split.set_xalign(0.0);
+    content.append(&split);
+    content
 }
 
 fn attach_log_copy_handler(label: &gtk4::Label) {
@@ -1140,17 +2584,98 @@ fn attach_log_copy_handler(label: &gtk4::Label) {
     label.add_controller(click);
 }
 
+/// Crossfades between popup pages unless animations are off.
+fn apply_stack_transition(stack: &gtk4::Stack, mode: AnimationMode) {
+    let transition = if mode.enabled(system_animations_enabled()) {
+        gtk4::StackTransitionType::Crossfade
+    } else {
+        gtk4::StackTransitionType::None
+    };
+    stack.set_transition_type(transition);
+}
+
+/// GTK's `gtk-enable-animations`, which follows the desktop preference.
+pub fn system_animations_enabled() -> bool {
+    gtk4::Settings::default().is_none_or(|settings| settings.is_gtk_enable_animations())
+}
+
 fn daemon_log_path() -> Option<String> {
-    dirs::data_local_dir().map(|d| {
-        d.join("claude-bar")
-            .join("claude-bar.log")
-            .display()
-            .to_string()
+    log_file_path().map(|path| path.display().to_string())
+}
+
+/// The `claude` or `codex` suffix of the per-provider CSS classes.
+fn provider_css_name(provider: Provider) -> &'static str {
+    match provider {
+        Provider::Claude => "claude",
+        Provider::Codex | Provider::CodexWorkspace(_) => "codex",
+    }
+}
+
+fn popup_input(state: &ProviderState, provider: Provider, title: String) -> PopupInput<'_> {
+    PopupInput {
+        provider,
+        title,
+        snapshot: state.snapshots.get(&provider),
+        cost: state.costs.get(&provider).map(Arc::as_ref),
+        tokens: state.token_snapshots.get(&provider).map(Arc::as_ref),
+        error: state.errors.get(&provider),
+        error_history: state
+            .error_history
+            .get(&provider)
+            .map(Vec::as_slice)
+            .unwrap_or_default(),
+        incident: state.incidents.get(&provider),
+        health: state.health.get(&provider),
+        failed_tasks: &state.failed_tasks,
+        latency: state.fetch_latencies.get(&provider),
+        velocity: state.velocities.get(&provider),
+        login: state.logins.get(&provider),
+        show_as_remaining: state.show_as_remaining,
+        show_value_estimate: state.show_value_estimate,
+        reset_time_format: state.reset_time_format,
+        hide_costs: state.hide_costs,
+        cost_enabled: state.cost_enabled,
+        cost_timezone: state.cost_timezone,
+        monthly_spend: state.monthly_spend.as_ref(),
+        lockouts: state
+            .lockouts
+            .get(&provider)
+            .map(Vec::as_slice)
+            .unwrap_or_default(),
+    }
+}
+
+fn tab_label(tabs: &[(Provider, String)], provider: Provider) -> String {
+    tabs.iter()
+        .find(|(p, _)| *p == provider)
+        .map(|(_, label)| label.clone())
+        .unwrap_or_else(|| provider.name().to_string())
+}
+
+/// Tabs in configured order, or most urgent first when sorting is on.
+fn ordered_tabs(state: &ProviderState) -> Vec<(Provider, String)> {
+    if !state.sort_by_urgency {
+        return state.tabs.clone();
+    }
+    let now = Utc::now();
+    let providers: Vec<Provider> = state.tabs.iter().map(|(provider, _)| *provider).collect();
+    rank_by_urgency(&providers, |provider| {
+        urgency_score(
+            state.snapshots.get(&provider),
+            state.errors.contains_key(&provider),
+            now,
+        )
     })
+    .into_iter()
+    .map(|provider| (provider, tab_label(&state.tabs, provider)))
+    .collect()
 }
 
-fn next_provider(current: Provider, backwards: bool) -> Provider {
-    let providers = [Provider::Claude, Provider::Codex];
+fn next_provider(tabs: &[(Provider, String)], current: Provider, backwards: bool) -> Provider {
+    let providers: Vec<Provider> = tabs.iter().map(|(provider, _)| *provider).collect();
+    if providers.is_empty() {
+        return current;
+    }
     let current_idx = providers
         .iter()
         .position(|p| *p == current)
@@ -1163,8 +2688,20 @@ fn next_provider(current: Provider, backwards: bool) -> Provider {
     providers[next_idx]
 }
 
-fn update_dynamic_labels(state: &Rc<RefCell<ProviderState>>, content: &gtk4::Box) {
+fn update_dynamic_labels(
+    state: &Rc<RefCell<ProviderState>>,
+    content: &gtk4::Box,
+    sections: &RefCell<Vec<(Provider, gtk4::Box)>>,
+) {
     let state_ref = state.borrow();
+    if state_ref.layout == PopupLayout::Stacked {
+        for (provider, section) in sections.borrow().iter() {
+            if let Some(snapshot) = state_ref.snapshots.get(provider) {
+                update_labels_in_box(section, snapshot);
+            }
+        }
+        return;
+    }
     let snapshot = state_ref.snapshots.get(&state_ref.provider);
 
     if let Some(snapshot) = snapshot {
@@ -1173,7 +2710,7 @@ fn update_dynamic_labels(state: &Rc<RefCell<ProviderState>>, content: &gtk4::Box
             if let Some(label) = widget.downcast_ref::<gtk4::Label>() {
                 let text = label.text();
                 if text.starts_with("Updated ") {
-                    let new_text = format_relative_time(snapshot.updated_at);
+                    let new_text = format_relative_time(snapshot.age(Utc::now()));
                     label.set_text(&new_text);
                 }
             }
@@ -1193,7 +2730,7 @@ fn update_labels_in_box(box_widget: &gtk4::Box, snapshot: &UsageSnapshot) {
         if let Some(label) = widget.downcast_ref::<gtk4::Label>() {
             let text = label.text();
             if text.starts_with("Updated ") {
-                let new_text = format_relative_time(snapshot.updated_at);
+                let new_text = format_relative_time(snapshot.age(Utc::now()));
                 label.set_text(&new_text);
             }
         }
@@ -1206,77 +2743,6 @@ fn update_labels_in_box(box_widget: &gtk4::Box, snapshot: &UsageSnapshot) {
     }
 }
 
-fn format_relative_time(timestamp: DateTime<Utc>) -> String {
-    let now = Utc::now();
-    let duration = now.signed_duration_since(timestamp);
-
-    let seconds = duration.num_seconds();
-    if seconds < 0 {
-        return "Updated just now".to_string();
-    }
-
-    if seconds < 60 {
-        return format!("Updated {}s ago", seconds);
-    }
-
-    let minutes = duration.num_minutes();
-    if minutes < 60 {
-        return format!("Updated {}m ago", minutes);
-    }
-
-    let hours = duration.num_hours();
-    if hours < 24 {
-        return format!("Updated {}h ago", hours);
-    }
-
-    let days = duration.num_days();
-    format!("Updated {}d ago", days)
-}
-
-fn format_reset_time(reset_at: DateTime<Utc>) -> String {
-    let now = Utc::now();
-    let duration = reset_at.signed_duration_since(now);
-
-    if duration.num_seconds() <= 0 {
-        return "resets now".to_string();
-    }
-
-    let total_minutes = duration.num_minutes();
-    let hours = total_minutes / 60;
-    let minutes = total_minutes % 60;
-
-    if hours > 24 {
-        let days = hours / 24;
-        let remaining_hours = hours % 24;
-        format!("resets in {}d {}h", days, remaining_hours)
-    } else if hours > 0 {
-        format!("resets in {}h {}m", hours, minutes)
-    } else {
-        format!("resets in {}m", minutes)
-    }
-}
-
-fn format_currency(value: f64) -> String {
-    format!("${:.2}", value)
-}
-
-fn format_currency_with_code(value: f64, code: &str) -> String {
-    if code == "USD" {
-        return format_currency(value);
-    }
-    format!("{} {:.2}", code, value)
-}
-
-fn format_token_count(tokens: u64) -> String {
-    if tokens >= 1_000_000 {
-        format!("{:.1}M", tokens as f64 / 1_000_000.0)
-    } else if tokens >= 1_000 {
-        format!("{:.1}K", tokens as f64 / 1_000.0)
-    } else {
-        tokens.to_string()
-    }
-}
-
 fn trigger_refresh() {
     tokio::spawn(async {
         let connection = match zbus::Connection::session().await {
@@ -1288,7 +2754,7 @@ fn trigger_refresh() {
         };
         let result: zbus::Result<()> = connection
             .call_method(
-                Some(crate::daemon::DBUS_NAME),
+                Some(crate::daemon::bus_name()),
                 crate::daemon::DBUS_PATH,
                 Some(crate::daemon::DBUS_NAME),
                 "Refresh",
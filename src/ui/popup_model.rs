@@ -0,0 +1,3205 @@
+use crate::core::clock::{format_reset_clock, format_reset_tooltip, HourCycle};
+use crate::core::incidents::Incident;
+use crate::core::lockouts::{month_summary, Lockout};
+use crate::core::models::{
+    CostSnapshot, CostUsageTokenSnapshot, DailyTokenUsage, ErrorEvent, FetchLatency,
+    ModelTokenUsage, Provider, ProviderCostSnapshot, ProviderHealth, RateWindow, UsageHeatmap,
+    UsageSnapshot,
+};
+use crate::core::quota_value;
+use crate::core::reset_estimate::WindowSlot;
+use crate::core::settings::{CostTimezone, ResetTimeFormat};
+use crate::core::velocity::Velocity;
+use crate::core::week_compare::{daily_token_series, week_over_week_percent};
+use crate::cost::{day_of, MonthlySpend, BUDGET_THRESHOLDS};
+use crate::daemon::login::{login_binary, LoginMode, LoginOutcome, LoginProgress};
+use crate::ui::{UsagePaceStage, UsagePaceText};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, Utc, Weekday};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Stands in for dollar amounts while `display.hide_costs` is on.
+pub const COSTS_HIDDEN: &str = "Amounts hidden";
+
+/// How long a window shows "Quota adjusted?" after its usage dropped
+/// without a reset.
+const QUOTA_ADJUSTED_BADGE_MINUTES: i64 = 60;
+
+/// Provider state the popup renders from, borrowed from the popup's caches.
+pub struct PopupInput<'a> {
+    pub provider: Provider,
+    pub title: String,
+    pub snapshot: Option<&'a UsageSnapshot>,
+    pub cost: Option<&'a CostSnapshot>,
+    pub tokens: Option<&'a CostUsageTokenSnapshot>,
+    pub error: Option<&'a (String, String)>,
+    /// Oldest first, as kept by the store.
+    pub error_history: &'a [ErrorEvent],
+    /// Reported by the provider's status page while it is failing.
+    pub incident: Option<&'a Incident>,
+    pub health: Option<&'a ProviderHealth>,
+    /// Daemon tasks that crashed too often and were left stopped.
+    pub failed_tasks: &'a [&'static str],
+    pub latency: Option<&'a FetchLatency>,
+    /// Burn rate of the session window, estimated by the daemon.
+    pub velocity: Option<&'a Velocity>,
+    pub login: Option<&'a LoginState>,
+    pub show_as_remaining: bool,
+    pub show_value_estimate: bool,
+    pub reset_time_format: ResetTimeFormat,
+    pub hide_costs: bool,
+    pub cost_enabled: bool,
+    /// `cost.timezone`, which decides the day the "Today" breakdown covers.
+    pub cost_timezone: CostTimezone,
+    /// Combined spend against `cost.monthly_budget`, when one is set.
+    pub monthly_spend: Option<&'a MonthlySpend>,
+    /// Every recorded lockout of the provider, oldest first.
+    pub lockouts: &'a [Lockout],
+}
+
+/// Coalesces popup rebuilds. A refresh lands as a usage update and a cost
+/// scan as a cost and a token update, each asking for a rebuild; the popup
+/// answers them all with one rebuild on the next main-loop iteration.
+#[derive(Debug, Default)]
+pub struct PendingRebuild {
+    scheduled: bool,
+    requests: u32,
+}
+
+impl PendingRebuild {
+    /// Records a request. True when no rebuild is scheduled yet, so the
+    /// caller should schedule one.
+    pub fn request(&mut self) -> bool {
+        self.requests += 1;
+        !std::mem::replace(&mut self.scheduled, true)
+    }
+
+    /// Called by the scheduled rebuild: the requests it answers, 0 when a
+    /// rebuild since then already did.
+    pub fn take(&mut self) -> u32 {
+        self.scheduled = false;
+        std::mem::take(&mut self.requests)
+    }
+
+    /// A rebuild done right away, e.g. on show, answers the pending requests.
+    pub fn clear(&mut self) {
+        self.requests = 0;
+    }
+}
+
+/// A login started from the popup, as reported by the login thread so far.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LoginState {
+    pub last_line: Option<String>,
+    pub auth_link: Option<String>,
+    /// `None` while the CLI is still running.
+    pub outcome: Option<LoginOutcome>,
+}
+
+impl LoginState {
+    pub fn is_running(&self) -> bool {
+        self.outcome.is_none()
+    }
+
+    pub fn apply(&mut self, progress: LoginProgress) {
+        match progress {
+            LoginProgress::Output(line) => self.last_line = Some(line),
+            LoginProgress::AuthLink(url) => self.auth_link = Some(url),
+            LoginProgress::Finished(outcome) => self.outcome = Some(outcome),
+        }
+    }
+}
+
+/// Everything the popup shows for one provider. The GTK layer only turns
+/// this into widgets, so all text and percentages are decided here.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PopupModel {
+    pub header: HeaderModel,
+    pub task_warning: Option<String>,
+    pub body: PopupBody,
+    pub login: Option<LoginModel>,
+    pub footer: Vec<FooterAction>,
+}
+
+/// Every provider at once, for `popup.layout = "stacked"`: each provider's
+/// header and usage in turn, then one Cost section and footer for them all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackedModel {
+    pub sections: Vec<StackedSection>,
+    pub task_warning: Option<String>,
+    pub cost: Option<CostSectionModel>,
+    pub cost_note: Option<String>,
+    pub budget: Option<MonthlyBudgetModel>,
+    pub footer: Vec<FooterAction>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackedSection {
+    pub provider: Provider,
+    pub header: HeaderModel,
+    /// Without the Cost section and budget, which the layout shares.
+    pub body: PopupBody,
+    pub login: Option<LoginModel>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeaderModel {
+    pub title: String,
+    pub plan: Option<String>,
+    pub status: String,
+    pub status_tooltip: Option<String>,
+    pub email: Option<String>,
+    /// How the account is signed in, shown under the email.
+    pub login_method: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PopupBody {
+    Error {
+        message: String,
+        hint: String,
+        /// The status page, when the hint points at an incident.
+        hint_link: Option<String>,
+        /// The command quoted in the hint, for the copy and run buttons.
+        hint_command: Option<String>,
+        /// Newest first.
+        recent_errors: Vec<String>,
+    },
+    Usage {
+        rows: Vec<UsageRowModel>,
+        /// Usage by surface, shown collapsed under "Details".
+        surfaces: Vec<UsageRowModel>,
+        provider_cost: Option<ProviderCostModel>,
+        cost: Option<CostSectionModel>,
+        /// The Cost section's tooltip, e.g. "(3 projects excluded)".
+        cost_note: Option<String>,
+        budget: Option<MonthlyBudgetModel>,
+        /// "Lockouts this month: 3 (last: Tue 14:05, weekly)", once the
+        /// provider has locked you out at all.
+        lockouts: Option<String>,
+    },
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRowModel {
+    pub title: String,
+    pub progress: f64,
+    pub percent_text: String,
+    pub reset_text: Option<String>,
+    /// The local reset time, e.g. "today 17:42", shown on hover.
+    pub reset_tooltip: Option<String>,
+    pub pace_marker: Option<PaceMarker>,
+    pub pace_summary: Option<String>,
+    /// Usage dropped without a reset in the last hour.
+    pub quota_adjusted: bool,
+    pub value_estimate: Option<String>,
+    pub week_over_week: Option<WeekOverWeekModel>,
+    /// "Burning ~2.1%/h (≈ 220K tokens/h)" under the session row.
+    pub velocity: Option<String>,
+    /// The weekly row's days up to the reset, oldest first; empty on other
+    /// rows and when the window's length or reset time is unknown.
+    pub week_strip: Vec<WeekStripDay>,
+}
+
+/// A tick in the week strip under the weekly bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekStripDay {
+    pub state: DayState,
+    /// The weekday initial, on the reset day only.
+    pub label: Option<char>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayState {
+    Elapsed,
+    Today,
+    Upcoming,
+}
+
+/// "vs last week: +22% ↑" under the weekly row, from the token scan.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeekOverWeekModel {
+    pub text: String,
+    pub trend: Trend,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Up,
+    Down,
+    /// Within half a percent of last week.
+    Flat,
+}
+
+impl WeekOverWeekModel {
+    pub fn build(percent: f64) -> Self {
+        let (trend, arrow) = if percent >= 0.5 {
+            (Trend::Up, " \u{2191}")
+        } else if percent <= -0.5 {
+            (Trend::Down, " \u{2193}")
+        } else {
+            (Trend::Flat, "")
+        };
+        let percent = if trend == Trend::Flat { 0.0 } else { percent };
+        Self {
+            text: format!("vs last week: {:+.0}%{}", percent, arrow),
+            trend,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PaceMarker {
+    pub position: f64,
+    pub is_deficit: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProviderCostModel {
+    pub title: String,
+    pub spend_line: String,
+    pub progress: f64,
+    pub percent_text: String,
+    pub reset_text: Option<String>,
+    pub reset_tooltip: Option<String>,
+}
+
+/// The Cost section's bar for `cost.monthly_budget`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonthlyBudgetModel {
+    pub line: String,
+    pub progress: f64,
+    pub level: BudgetLevel,
+}
+
+/// How close combined spend is to the monthly budget; picks the bar color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetLevel {
+    Normal,
+    /// Past the first notification threshold.
+    Warning,
+    /// At or over the budget.
+    Critical,
+}
+
+impl MonthlyBudgetModel {
+    pub fn build(spend: &MonthlySpend) -> Self {
+        let percent = spend.percent();
+        let level = if percent >= 100.0 {
+            BudgetLevel::Critical
+        } else if percent >= f64::from(BUDGET_THRESHOLDS[0]) {
+            BudgetLevel::Warning
+        } else {
+            BudgetLevel::Normal
+        };
+        Self {
+            line: format!(
+                "Month: ${:.0} / ${:.0} ({:.0}%)",
+                spend.spent, spend.budget, percent
+            ),
+            progress: (percent / 100.0).clamp(0.0, 1.0),
+            level,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CostSectionModel {
+    LogError,
+    Lines(Vec<CostLineModel>),
+    Empty,
+    /// `display.hide_costs` is on.
+    Hidden,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostLineModel {
+    pub text: String,
+    /// Shown in a popover when the line is clicked; `None` when the scan has
+    /// no per-model tokens for the line's period.
+    pub detail: Option<CostDetailModel>,
+}
+
+/// Which models a cost line's tokens went to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostDetailModel {
+    pub title: String,
+    /// The biggest models by tokens, at most `MAX_DETAIL_MODELS`.
+    pub models: Vec<ModelCostRow>,
+    /// Input, output and cache tokens across every model, not just the top.
+    pub split: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelCostRow {
+    pub model: String,
+    pub tokens: String,
+    pub cost: String,
+}
+
+const MAX_DETAIL_MODELS: usize = 5;
+
+/// Progress row for a login started from the popup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoginModel {
+    pub running: bool,
+    pub failed: bool,
+    pub message: String,
+    /// Only offered while the CLI is still waiting for the browser.
+    pub auth_link: Option<String>,
+}
+
+impl LoginModel {
+    pub fn build(provider: Provider, state: &LoginState) -> Self {
+        let message = match &state.outcome {
+            None => state
+                .last_line
+                .clone()
+                .unwrap_or_else(|| "Starting login\u{2026}".to_string()),
+            Some(LoginOutcome::Success) => "Logged in. Refreshing usage\u{2026}".to_string(),
+            Some(LoginOutcome::TimedOut) => "Login timed out".to_string(),
+            Some(LoginOutcome::MissingBinary) => {
+                format!("{} CLI not found in PATH", login_binary(provider))
+            }
+            Some(LoginOutcome::Failed(code)) => format!("Login failed (exit code {})", code),
+            Some(LoginOutcome::LaunchFailed(message)) => {
+                format!("Login failed to start: {}", message)
+            }
+        };
+
+        Self {
+            running: state.is_running(),
+            failed: !matches!(state.outcome, None | Some(LoginOutcome::Success)),
+            message,
+            auth_link: state.auth_link.clone().filter(|_| state.is_running()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FooterAction {
+    Login { add_account: bool },
+    UsageDashboard,
+    StatusPage,
+    RefreshNow,
+    Insights,
+    HideCosts { hidden: bool },
+    Settings,
+}
+
+impl FooterAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FooterAction::Login { add_account: true } => "Add Account",
+            FooterAction::Login { add_account: false } => "Account",
+            FooterAction::UsageDashboard => "Usage Dashboard",
+            FooterAction::StatusPage => "Status Page",
+            FooterAction::RefreshNow => "Refresh Now",
+            FooterAction::Insights => "Insights",
+            FooterAction::HideCosts { hidden: false } => "Hide costs",
+            FooterAction::HideCosts { hidden: true } => "Show costs",
+            FooterAction::Settings => "Settings",
+        }
+    }
+}
+
+/// Entries of the footer's Account menu, which replaces a single login
+/// button so that picking another account is never a side effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountAction {
+    Reauthenticate,
+    SwitchAccount,
+    ShowCredentials,
+}
+
+impl AccountAction {
+    pub const ALL: [AccountAction; 3] = [
+        AccountAction::Reauthenticate,
+        AccountAction::SwitchAccount,
+        AccountAction::ShowCredentials,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AccountAction::Reauthenticate => "Re-authenticate",
+            AccountAction::SwitchAccount => "Log in with a different account",
+            AccountAction::ShowCredentials => "Show credentials file",
+        }
+    }
+
+    /// The login this entry starts; `None` for entries that don't log in.
+    pub fn login_mode(&self) -> Option<LoginMode> {
+        match self {
+            AccountAction::Reauthenticate => Some(LoginMode::Reauthenticate),
+            AccountAction::SwitchAccount => Some(LoginMode::SwitchAccount),
+            AccountAction::ShowCredentials => None,
+        }
+    }
+}
+
+/// Tokens expiring sooner than this are flagged in the Settings window.
+const CREDENTIAL_EXPIRY_WARNING_HOURS: i64 = 24;
+
+/// Credential health shown for each provider in the Settings window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialStatus {
+    Valid,
+    /// Still valid, but the access token expires within a day.
+    Expiring,
+    /// Missing, unreadable or already expired.
+    Missing,
+}
+
+impl CredentialStatus {
+    pub fn build(valid: bool, expires_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Self {
+        if !valid {
+            return Self::Missing;
+        }
+        let warn_after = now + chrono::Duration::hours(CREDENTIAL_EXPIRY_WARNING_HOURS);
+        match expires_at {
+            Some(expires_at) if expires_at <= warn_after => Self::Expiring,
+            _ => Self::Valid,
+        }
+    }
+
+    pub fn icon_name(&self) -> &'static str {
+        match self {
+            Self::Valid => "emblem-ok-symbolic",
+            Self::Expiring => "dialog-warning-symbolic",
+            Self::Missing => "dialog-error-symbolic",
+        }
+    }
+
+    /// Libadwaita style class that colors the icon green, yellow or red.
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            Self::Valid => "success",
+            Self::Expiring => "warning",
+            Self::Missing => "error",
+        }
+    }
+
+    /// Tooltip for the status icon. `hint` says how to log in.
+    pub fn describe(
+        &self,
+        expires_at: Option<DateTime<Utc>>,
+        hint: &str,
+        now: DateTime<Utc>,
+    ) -> String {
+        match (self, expires_at) {
+            (Self::Missing, _) => format!("Missing or expired. {}", hint),
+            (Self::Expiring, Some(expires_at)) => {
+                format!("Token expires at {}", format_clock_time(expires_at, now))
+            }
+            _ => "Credentials valid".to_string(),
+        }
+    }
+}
+
+/// Provider state the Overview tab renders from.
+pub struct OverviewInput<'a> {
+    pub tabs: &'a [(Provider, String)],
+    pub snapshots: &'a HashMap<Provider, UsageSnapshot>,
+    pub costs: &'a HashMap<Provider, Arc<CostSnapshot>>,
+    pub show_as_remaining: bool,
+    pub hide_costs: bool,
+    pub cost_enabled: bool,
+}
+
+/// Claude and Codex side by side: this month's spend split and each tab's
+/// weekly usage.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverviewModel {
+    pub share: Option<ShareModel>,
+    pub weekly: Vec<WeeklyTileModel>,
+}
+
+/// How this month's spend splits between providers. Segments only cover
+/// providers with a non-zero spend; `summary` names every provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareModel {
+    pub segments: Vec<ShareSegment>,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareSegment {
+    pub provider: Provider,
+    pub fraction: f64,
+    pub tooltip: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeeklyTileModel {
+    pub provider: Provider,
+    pub title: String,
+    pub progress: f64,
+    pub percent_text: String,
+}
+
+/// Row labels for the heatmap, Monday first like `UsageHeatmap`.
+pub const WEEKDAY_LABELS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// The Insights view: at which hours of the week a provider's tokens were
+/// spent over the last 30 days.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InsightsModel {
+    /// Seven rows of 24 hourly cells, Monday first. Empty when there's no
+    /// usage to show.
+    pub rows: Vec<Vec<HeatmapCell>>,
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeatmapCell {
+    /// Share of the busiest cell's tokens, 0 for idle hours.
+    pub intensity: f64,
+    pub tooltip: String,
+}
+
+impl InsightsModel {
+    pub fn build(tokens: Option<&CostUsageTokenSnapshot>) -> Self {
+        let heatmap = tokens.map(|tokens| &tokens.heatmap);
+        let Some(heatmap) = heatmap.filter(|heatmap| !heatmap.is_empty()) else {
+            return Self {
+                rows: Vec::new(),
+                summary: "No token usage in the last 30 days".to_string(),
+            };
+        };
+
+        let max = heatmap.max() as f64;
+        let rows = heatmap
+            .tokens
+            .iter()
+            .zip(WEEKDAY_LABELS)
+            .map(|(hours, weekday)| {
+                hours
+                    .iter()
+                    .enumerate()
+                    .map(|(hour, &tokens)| HeatmapCell {
+                        intensity: tokens as f64 / max,
+                        tooltip: format!(
+                            "{} {} · {} tokens",
+                            weekday,
+                            hour_range(hour),
+                            format_compact_tokens(tokens)
+                        ),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            rows,
+            summary: format!("Busiest: {}", busiest_hour(heatmap)),
+        }
+    }
+}
+
+fn busiest_hour(heatmap: &UsageHeatmap) -> String {
+    let mut busiest = (0, 0, 0);
+    for (day, hours) in heatmap.tokens.iter().enumerate() {
+        for (hour, &tokens) in hours.iter().enumerate() {
+            if tokens > busiest.2 {
+                busiest = (day, hour, tokens);
+            }
+        }
+    }
+    format!("{} {}", WEEKDAY_LABELS[busiest.0], hour_range(busiest.1))
+}
+
+fn hour_range(hour: usize) -> String {
+    format!("{:02}:00–{:02}:00", hour, hour + 1)
+}
+
+/// `format_token_count` without a trailing `.0`, e.g. `340K`.
+pub fn format_compact_tokens(tokens: u64) -> String {
+    format_token_count(tokens)
+        .replace(".0K", "K")
+        .replace(".0M", "M")
+}
+
+impl OverviewModel {
+    pub fn build(input: &OverviewInput<'_>) -> Self {
+        let share = if input.cost_enabled {
+            ShareModel::build(input.costs, input.hide_costs)
+        } else {
+            None
+        };
+
+        let weekly = input
+            .tabs
+            .iter()
+            .map(|(provider, title)| {
+                let weekly = input
+                    .snapshots
+                    .get(provider)
+                    .and_then(|snapshot| snapshot.secondary.as_ref());
+                let (progress, percent_text) = match weekly {
+                    Some(window) if input.show_as_remaining => (
+                        window.remaining_percent(),
+                        format!("{} left{}", window.remaining_text(), clamped_marker(window)),
+                    ),
+                    Some(window) => (
+                        window.used_percent,
+                        format!("{} used{}", window.used_text(), clamped_marker(window)),
+                    ),
+                    None => (0.0, "No data".to_string()),
+                };
+                WeeklyTileModel {
+                    provider: *provider,
+                    title: title.clone(),
+                    progress: progress.clamp(0.0, 1.0),
+                    percent_text,
+                }
+            })
+            .collect();
+
+        Self { share, weekly }
+    }
+}
+
+impl ShareModel {
+    /// Codex workspaces share one set of local logs, so the split is always
+    /// Claude against Codex. `None` until either provider has cost data.
+    /// With `hide_costs` only the proportions are shown.
+    pub fn build(costs: &HashMap<Provider, Arc<CostSnapshot>>, hide_costs: bool) -> Option<Self> {
+        let amounts: Vec<(Provider, Option<f64>)> = [Provider::Claude, Provider::Codex]
+            .into_iter()
+            .map(|provider| (provider, costs.get(&provider).map(|c| c.monthly_cost)))
+            .collect();
+        if amounts.iter().all(|(_, amount)| amount.is_none()) {
+            return None;
+        }
+
+        let total: f64 = amounts.iter().filter_map(|(_, amount)| *amount).sum();
+        let segments = amounts
+            .iter()
+            .filter_map(|(provider, amount)| {
+                let amount = amount.filter(|a| *a > 0.0)?;
+                let fraction = amount / total;
+                let tooltip = if hide_costs {
+                    format!("{} ({:.0}%)", share_label(*provider), fraction * 100.0)
+                } else {
+                    format!(
+                        "{} {} ({:.0}%)",
+                        share_label(*provider),
+                        format_currency(amount),
+                        fraction * 100.0
+                    )
+                };
+                Some(ShareSegment {
+                    provider: *provider,
+                    fraction,
+                    tooltip,
+                })
+            })
+            .collect();
+        if hide_costs {
+            return Some(Self {
+                segments,
+                summary: COSTS_HIDDEN.to_string(),
+            });
+        }
+        let summary = amounts
+            .iter()
+            .map(|(provider, amount)| match amount {
+                Some(amount) => format!("{} ${:.0}", share_label(*provider), amount),
+                None => format!("{} no data", share_label(*provider)),
+            })
+            .collect::<Vec<_>>()
+            .join(" \u{00b7} ");
+
+        Some(Self { segments, summary })
+    }
+}
+
+/// The bar is narrow, so it names providers as briefly as possible.
+fn share_label(provider: Provider) -> &'static str {
+    match provider {
+        Provider::Claude => "Claude",
+        Provider::Codex | Provider::CodexWorkspace(_) => "Codex",
+    }
+}
+
+struct UsageRowSource<'a> {
+    title: String,
+    window: &'a RateWindow,
+    /// The snapshot window the row shows; `None` for surfaces.
+    slot: Option<WindowSlot>,
+    /// Window length pace assumes when the window doesn't report one; `None`
+    /// leaves the row without pace.
+    pace_window_minutes: Option<i32>,
+    /// The weekly row also carries the value estimate and week-over-week line.
+    is_weekly: bool,
+    show_velocity: bool,
+}
+
+impl PopupModel {
+    pub fn build(input: &PopupInput<'_>, now: DateTime<Utc>) -> Self {
+        let snapshot = input.snapshot;
+
+        let status = if input.error.is_some() {
+            error_status(input.health, now)
+        } else if let Some(snapshot) = snapshot {
+            format_relative_time(snapshot.age(now))
+        } else {
+            "Loading\u{2026}".to_string()
+        };
+        let header = HeaderModel {
+            title: input.title.clone(),
+            plan: snapshot.and_then(|s| s.identity.plan.clone()),
+            status,
+            status_tooltip: input
+                .latency
+                .map(|latency| format!("API: {}ms", latency.median)),
+            email: snapshot.and_then(|s| s.identity.email.clone()),
+            login_method: snapshot.and_then(|s| s.identity.login_method.clone()),
+        };
+
+        let body = if let Some((message, hint)) = input.error {
+            let (hint, hint_link) = match input.incident {
+                Some(incident) => (incident.hint(input.provider), Some(incident.url.clone())),
+                None => (hint.clone(), None),
+            };
+            PopupBody::Error {
+                message: message.clone(),
+                hint_command: hint_command(&hint),
+                hint,
+                hint_link,
+                recent_errors: recent_errors(input.error_history),
+            }
+        } else if let Some(snapshot) = snapshot {
+            let cost = if !input.cost_enabled || (input.cost.is_none() && input.tokens.is_none()) {
+                None
+            } else if input.hide_costs {
+                Some(CostSectionModel::Hidden)
+            } else {
+                Some(cost_section(
+                    input.cost,
+                    input.tokens,
+                    day_of(now, input.cost_timezone),
+                ))
+            };
+            let cost_note = cost
+                .as_ref()
+                .and(input.cost)
+                .and_then(|cost| excluded_projects_note(cost.excluded_projects));
+            let budget = input
+                .monthly_spend
+                .filter(|_| input.cost_enabled && !input.hide_costs)
+                .map(MonthlyBudgetModel::build);
+            PopupBody::Usage {
+                rows: usage_rows(input, collect_usage_rows(input.provider, snapshot), now),
+                surfaces: usage_rows(input, surface_rows(snapshot), now),
+                provider_cost: snapshot.provider_cost.as_ref().and_then(|cost| {
+                    provider_cost_section(cost, input.hide_costs, input.reset_time_format, now)
+                }),
+                cost,
+                cost_note,
+                budget,
+                lockouts: lockout_line(input.lockouts, now, &Local),
+            }
+        } else {
+            PopupBody::Empty
+        };
+
+        let mut footer = vec![
+            FooterAction::Login {
+                add_account: input.error.is_some(),
+            },
+            FooterAction::UsageDashboard,
+            FooterAction::StatusPage,
+            FooterAction::RefreshNow,
+        ];
+        // The heatmap comes from the same log scan as the Cost section.
+        if input.cost_enabled {
+            footer.push(FooterAction::Insights);
+        }
+        footer.extend([
+            FooterAction::HideCosts {
+                hidden: input.hide_costs,
+            },
+            FooterAction::Settings,
+        ]);
+
+        Self {
+            header,
+            task_warning: task_warning(input.failed_tasks),
+            body,
+            login: input
+                .login
+                .map(|state| LoginModel::build(input.provider, state)),
+            footer,
+        }
+    }
+}
+
+impl StackedModel {
+    /// `inputs` in the order the sections are shown. The shared parts come
+    /// from the first, as every input carries the same display settings.
+    pub fn build(inputs: &[PopupInput<'_>], now: DateTime<Utc>) -> Self {
+        let sections = inputs
+            .iter()
+            .map(|input| {
+                let model = PopupModel::build(
+                    &PopupInput {
+                        title: input.title.clone(),
+                        cost_enabled: false,
+                        monthly_spend: None,
+                        ..*input
+                    },
+                    now,
+                );
+                StackedSection {
+                    provider: input.provider,
+                    header: model.header,
+                    body: model.body,
+                    login: model.login,
+                }
+            })
+            .collect();
+
+        let Some(first) = inputs.first() else {
+            return Self {
+                sections,
+                task_warning: None,
+                cost: None,
+                cost_note: None,
+                budget: None,
+                footer: Vec::new(),
+            };
+        };
+        let lines: Vec<CostLineModel> = inputs
+            .iter()
+            .filter_map(|input| combined_cost_line(&input.title, input.cost, input.tokens))
+            .collect();
+        let cost = if !first.cost_enabled || lines.is_empty() {
+            None
+        } else if first.hide_costs {
+            Some(CostSectionModel::Hidden)
+        } else {
+            Some(CostSectionModel::Lines(lines))
+        };
+        let excluded = inputs
+            .iter()
+            .filter_map(|input| input.cost)
+            .map(|cost| cost.excluded_projects)
+            .sum();
+
+        Self {
+            sections,
+            task_warning: task_warning(first.failed_tasks),
+            cost_note: cost.as_ref().and_then(|_| excluded_projects_note(excluded)),
+            cost,
+            budget: first
+                .monthly_spend
+                .filter(|_| first.cost_enabled && !first.hide_costs)
+                .map(MonthlyBudgetModel::build),
+            footer: vec![
+                FooterAction::RefreshNow,
+                FooterAction::HideCosts {
+                    hidden: first.hide_costs,
+                },
+                FooterAction::Settings,
+            ],
+        }
+    }
+}
+
+/// How many projects `cost.include_projects`/`cost.exclude_projects` kept
+/// out of the totals, when any.
+fn excluded_projects_note(excluded: usize) -> Option<String> {
+    match excluded {
+        0 => None,
+        1 => Some("(1 project excluded)".to_string()),
+        n => Some(format!("({} projects excluded)", n)),
+    }
+}
+
+/// One provider's line in the stacked layout's Cost section, totalled the
+/// way its own Cost section would be.
+fn combined_cost_line(
+    title: &str,
+    cost: Option<&CostSnapshot>,
+    tokens: Option<&CostUsageTokenSnapshot>,
+) -> Option<CostLineModel> {
+    if cost.is_none() && tokens.is_none() {
+        return None;
+    }
+    if cost.is_some_and(|c| c.log_error) {
+        return Some(CostLineModel {
+            text: format!("{}: error reading logs", title),
+            detail: None,
+        });
+    }
+
+    let prefix = cost.map_or("", |c| if c.pricing_estimate { "~" } else { "" });
+    let amount = |value: Option<f64>| {
+        value.map_or_else(
+            || "—".to_string(),
+            |value| format!("{}{}", prefix, format_currency(value)),
+        )
+    };
+    let session = tokens.is_some_and(|t| t.session_start.is_some());
+    let recent = tokens
+        .and_then(|t| t.session_cost_usd)
+        .or_else(|| cost.map(|c| c.today_cost));
+    let month = tokens
+        .and_then(|t| t.last_30_days_cost_usd)
+        .or_else(|| cost.map(|c| c.monthly_cost));
+    Some(CostLineModel {
+        text: format!(
+            "{}: {} {} · {} last 30 days",
+            title,
+            amount(recent),
+            if session { "this session" } else { "today" },
+            amount(month)
+        ),
+        detail: tokens
+            .and_then(|t| cost_detail(&format!("{} · Last 30 days", title), &t.daily, prefix)),
+    })
+}
+
+fn usage_rows(
+    input: &PopupInput<'_>,
+    sources: Vec<UsageRowSource<'_>>,
+    now: DateTime<Utc>,
+) -> Vec<UsageRowModel> {
+    let estimate_cost = input
+        .cost
+        .filter(|_| input.show_value_estimate && input.cost_enabled);
+
+    sources
+        .into_iter()
+        .map(|row| {
+            let window = row.window;
+            let progress = if input.show_as_remaining {
+                window.remaining_percent()
+            } else {
+                window.used_percent
+            };
+            let percent_text = if input.show_as_remaining {
+                format!(
+                    "{} remaining{}",
+                    window.remaining_text(),
+                    clamped_marker(window)
+                )
+            } else {
+                format!("{} used{}", window.used_text(), clamped_marker(window))
+            };
+
+            let (pace_marker, pace_summary) = match row.pace_window_minutes {
+                Some(minutes) => (
+                    pace_marker(
+                        input.provider,
+                        window,
+                        minutes,
+                        input.show_as_remaining,
+                        now,
+                    ),
+                    UsagePaceText::summary(
+                        input.provider,
+                        window,
+                        minutes,
+                        input.show_as_remaining,
+                        now,
+                    ),
+                ),
+                None => (None, None),
+            };
+
+            let value_estimate = estimate_cost
+                .filter(|_| row.is_weekly)
+                .and_then(|cost| value_estimate_text(window, cost, input.hide_costs));
+            let week_over_week = input
+                .tokens
+                .filter(|_| row.is_weekly && input.cost_enabled)
+                .and_then(|tokens| week_over_week_line(window, tokens, now));
+            let velocity = input
+                .velocity
+                .filter(|_| row.show_velocity)
+                .map(velocity_text);
+            let week_strip = if row.is_weekly {
+                week_strip(window, now, &Local)
+            } else {
+                Vec::new()
+            };
+            let quota_adjusted = row
+                .slot
+                .is_some_and(|slot| recently_adjusted(input.error_history, slot, now));
+
+            UsageRowModel {
+                title: row.title,
+                progress: progress.clamp(0.0, 1.0),
+                percent_text,
+                reset_text: window_reset_text(window, input.reset_time_format, now),
+                reset_tooltip: window
+                    .resets_at
+                    .map(|resets_at| reset_tooltip(resets_at, now)),
+                pace_marker,
+                pace_summary,
+                quota_adjusted,
+                value_estimate,
+                week_over_week,
+                velocity,
+                week_strip,
+            }
+        })
+        .collect()
+}
+
+fn recently_adjusted(history: &[ErrorEvent], slot: WindowSlot, now: DateTime<Utc>) -> bool {
+    history.iter().any(|event| match event {
+        ErrorEvent::QuotaAdjusted { at, window, .. } => {
+            *window == slot && now - *at < chrono::Duration::minutes(QUOTA_ADJUSTED_BADGE_MINUTES)
+        }
+        _ => false,
+    })
+}
+
+/// Where the bar should be by now. In remaining mode the bar shows what is
+/// left, so the marker sits at the share expected to remain.
+fn pace_marker(
+    provider: Provider,
+    window: &RateWindow,
+    default_window_minutes: i32,
+    show_as_remaining: bool,
+    now: DateTime<Utc>,
+) -> Option<PaceMarker> {
+    let detail = UsagePaceText::detail(
+        provider,
+        window,
+        default_window_minutes,
+        show_as_remaining,
+        now,
+    )?;
+    let expected_used = detail.expected_used_percent / 100.0;
+    Some(PaceMarker {
+        position: if show_as_remaining {
+            1.0 - expected_used
+        } else {
+            expected_used
+        },
+        is_deficit: matches!(
+            detail.stage,
+            UsagePaceStage::SlightlyAhead | UsagePaceStage::Ahead | UsagePaceStage::FarAhead
+        ),
+    })
+}
+
+fn collect_usage_rows(provider: Provider, snapshot: &UsageSnapshot) -> Vec<UsageRowSource<'_>> {
+    let mut rows = Vec::new();
+
+    if let Some(primary) = &snapshot.primary {
+        let label = match provider {
+            Provider::Claude => "5-hour session",
+            Provider::Codex | Provider::CodexWorkspace(_) => "Session",
+        };
+        rows.push(UsageRowSource {
+            title: label.to_string(),
+            window: primary,
+            slot: Some(WindowSlot::Primary),
+            pace_window_minutes: Some(300),
+            is_weekly: false,
+            show_velocity: true,
+        });
+    }
+
+    if let Some(secondary) = &snapshot.secondary {
+        let label = match provider {
+            Provider::Claude => "Weekly quota",
+            Provider::Codex | Provider::CodexWorkspace(_) => "Weekly",
+        };
+        rows.push(UsageRowSource {
+            title: label.to_string(),
+            window: secondary,
+            slot: Some(WindowSlot::Secondary),
+            pace_window_minutes: Some(10080),
+            is_weekly: true,
+            show_velocity: false,
+        });
+    }
+
+    if let Some(tertiary) = &snapshot.tertiary {
+        rows.push(UsageRowSource {
+            title: resolve_tertiary_label(snapshot, provider),
+            window: tertiary,
+            slot: Some(WindowSlot::Tertiary),
+            pace_window_minutes: None,
+            is_weekly: false,
+            show_velocity: false,
+        });
+    }
+
+    rows
+}
+
+fn week_over_week_line(
+    window: &RateWindow,
+    tokens: &CostUsageTokenSnapshot,
+    now: DateTime<Utc>,
+) -> Option<WeekOverWeekModel> {
+    let week_start = quota_value::window_start_day(window)?;
+    let today = now.with_timezone(&Local).date_naive();
+    week_over_week_percent(&daily_token_series(&tokens.daily), week_start, today)
+        .map(WeekOverWeekModel::build)
+}
+
+/// The week strip of a window, in `tz`'s days. The window starts
+/// `window_minutes` before `resets_at`.
+fn week_strip<Tz: chrono::TimeZone>(
+    window: &RateWindow,
+    now: DateTime<Utc>,
+    tz: &Tz,
+) -> Vec<WeekStripDay> {
+    let (Some(resets_at), Some(minutes)) = (window.resets_at, window.window_minutes) else {
+        return Vec::new();
+    };
+    let starts_at = resets_at - chrono::Duration::minutes(minutes.into());
+    let local = |at: DateTime<Utc>| at.with_timezone(tz).naive_local();
+    week_strip_days(local(starts_at), local(resets_at), local(now))
+}
+
+/// One tick per day of a window given in local time, ending on the reset
+/// day. The count comes from the window's length, so a window that gains or
+/// loses an hour to a DST change still gets seven days a week. The part of
+/// the start day before the first tick counts as that tick.
+fn week_strip_days(
+    starts_at: NaiveDateTime,
+    resets_at: NaiveDateTime,
+    now: NaiveDateTime,
+) -> Vec<WeekStripDay> {
+    let days = ((resets_at - starts_at).num_minutes() as f64 / (24.0 * 60.0)).round() as i64;
+    if days < 1 {
+        return Vec::new();
+    }
+    let reset_day = resets_at.date();
+    let today = now.date().max(reset_day - chrono::Duration::days(days - 1));
+    (0..days)
+        .map(|i| {
+            let day = reset_day - chrono::Duration::days(days - 1 - i);
+            let state = match day.cmp(&today) {
+                Ordering::Less => DayState::Elapsed,
+                Ordering::Equal => DayState::Today,
+                Ordering::Greater => DayState::Upcoming,
+            };
+            let label = (day == reset_day).then(|| match day.weekday() {
+                Weekday::Mon => 'M',
+                Weekday::Tue | Weekday::Thu => 'T',
+                Weekday::Wed => 'W',
+                Weekday::Fri => 'F',
+                Weekday::Sat | Weekday::Sun => 'S',
+            });
+            WeekStripDay { state, label }
+        })
+        .collect()
+}
+
+fn velocity_text(velocity: &Velocity) -> String {
+    match velocity {
+        Velocity::Idle => "Idle".to_string(),
+        Velocity::Burning {
+            percent_per_hour,
+            tokens_per_hour,
+        } => {
+            let rate = format!("Burning ~{:.1}%/h", percent_per_hour * 100.0);
+            match tokens_per_hour {
+                Some(tokens) => format!(
+                    "{} (\u{2248} {} tokens/h)",
+                    rate,
+                    format_token_count(tokens.round() as u64)
+                ),
+                None => rate,
+            }
+        }
+    }
+}
+
+fn surface_rows(snapshot: &UsageSnapshot) -> Vec<UsageRowSource<'_>> {
+    snapshot
+        .surfaces
+        .iter()
+        .map(|surface| UsageRowSource {
+            title: surface.label.clone(),
+            window: &surface.window,
+            slot: None,
+            pace_window_minutes: None,
+            is_weekly: false,
+            show_velocity: false,
+        })
+        .collect()
+}
+
+fn value_estimate_text(
+    window: &RateWindow,
+    cost: &CostSnapshot,
+    hide_costs: bool,
+) -> Option<String> {
+    quota_value::estimate_for_window(window, &cost.daily_breakdown).map(|estimate| {
+        let tokens = format_token_count(estimate.remaining_tokens);
+        if hide_costs {
+            format!("Remaining \u{2248} {} tokens", tokens)
+        } else {
+            format!(
+                "Remaining \u{2248} {} tokens (\u{2248} ${:.0} at API prices)",
+                tokens, estimate.remaining_cost_usd
+            )
+        }
+    })
+}
+
+fn resolve_tertiary_label(snapshot: &UsageSnapshot, provider: Provider) -> String {
+    let Some(tertiary) = snapshot.tertiary.as_ref() else {
+        return "Model".to_string();
+    };
+
+    for carveout in &snapshot.carveouts {
+        if windows_match(&carveout.window, tertiary) {
+            return carveout.label.trim_end_matches(" Weekly").to_string();
+        }
+    }
+
+    match provider {
+        Provider::Claude => "Model".to_string(),
+        Provider::Codex | Provider::CodexWorkspace(_) => "Additional".to_string(),
+    }
+}
+
+fn windows_match(left: &RateWindow, right: &RateWindow) -> bool {
+    let percent_close = (left.used_percent - right.used_percent).abs() < 0.001;
+    let reset_same = left.resets_at == right.resets_at;
+    let window_same = left.window_minutes == right.window_minutes;
+    percent_close && reset_same && window_same
+}
+
+/// Hidden spend keeps its progress bar but drops the amounts. A cost whose
+/// period has reset is left out until the next fetch reports the new one.
+fn provider_cost_section(
+    cost: &ProviderCostSnapshot,
+    hide_costs: bool,
+    reset_format: ResetTimeFormat,
+    now: DateTime<Utc>,
+) -> Option<ProviderCostModel> {
+    if cost.limit <= 0.0 || cost.has_reset(now) {
+        return None;
+    }
+
+    // Quota and bonus credit pools are counts rather than money.
+    let (title, is_count, default_period) = match cost.currency_code.as_str() {
+        "Quota" => ("Quota usage", true, "This month"),
+        "Credits" => ("Bonus credits", true, "Used"),
+        _ => ("Extra usage", false, "This month"),
+    };
+    let (used, limit) = if is_count {
+        (format!("{:.0}", cost.used), format!("{:.0}", cost.limit))
+    } else {
+        (
+            format_currency_with_code(cost.used, &cost.currency_code),
+            format_currency_with_code(cost.limit, &cost.currency_code),
+        )
+    };
+    let period = cost.period.as_deref().unwrap_or(default_period);
+    let progress = (cost.used / cost.limit).clamp(0.0, 1.0);
+    let spend_line = if hide_costs && !is_count {
+        format!("{}: {}", period, COSTS_HIDDEN)
+    } else {
+        format!("{}: {} / {}", period, used, limit)
+    };
+
+    Some(ProviderCostModel {
+        title: title.to_string(),
+        spend_line,
+        progress,
+        percent_text: format!("{:.0}% used", progress * 100.0),
+        reset_text: cost
+            .resets_at
+            .map(|resets_at| reset_text(resets_at, false, reset_format, now)),
+        reset_tooltip: cost
+            .resets_at
+            .map(|resets_at| reset_tooltip(resets_at, now)),
+    })
+}
+
+fn cost_section(
+    cost: Option<&CostSnapshot>,
+    tokens: Option<&CostUsageTokenSnapshot>,
+    today: NaiveDate,
+) -> CostSectionModel {
+    if cost.is_some_and(|c| c.log_error) {
+        return CostSectionModel::LogError;
+    }
+
+    if let Some(tokens) = tokens {
+        let prefix = cost.map_or("", |c| if c.pricing_estimate { "~" } else { "" });
+        let session_cost = tokens
+            .session_cost_usd
+            .or_else(|| cost.map(|c| c.today_cost))
+            .map(|v| format!("{}{}", prefix, format_currency(v)));
+        let month_cost = tokens
+            .last_30_days_cost_usd
+            .or_else(|| cost.map(|c| c.monthly_cost))
+            .map(|v| format!("{}{}", prefix, format_currency(v)));
+
+        let session_label = if tokens.session_start.is_some() {
+            "This session"
+        } else {
+            "Today"
+        };
+        let session_line = CostLineModel {
+            text: cost_line(
+                session_label,
+                session_cost,
+                tokens.session_tokens.map(format_token_count),
+            ),
+            // Tokens are only broken down by day, so a session shows the
+            // whole of today.
+            detail: tokens
+                .daily
+                .iter()
+                .find(|day| day.date == today)
+                .and_then(|day| cost_detail("Today", std::slice::from_ref(day), prefix)),
+        };
+        let month_line = CostLineModel {
+            text: cost_line(
+                "Last 30 days",
+                month_cost,
+                tokens.last_30_days_tokens.map(format_token_count),
+            ),
+            detail: cost_detail("Last 30 days", &tokens.daily, prefix),
+        };
+        CostSectionModel::Lines(vec![session_line, month_line])
+    } else if let Some(cost) = cost {
+        let prefix = if cost.pricing_estimate { "~" } else { "" };
+        let line = |text: String| CostLineModel { text, detail: None };
+        CostSectionModel::Lines(vec![
+            line(format!(
+                "Today: {}{}",
+                prefix,
+                format_currency(cost.today_cost)
+            )),
+            line(format!(
+                "Last 30 days: {}{}",
+                prefix,
+                format_currency(cost.monthly_cost)
+            )),
+        ])
+    } else {
+        CostSectionModel::Empty
+    }
+}
+
+/// `prefix` marks estimated prices, as on the cost lines themselves.
+fn cost_detail(title: &str, days: &[DailyTokenUsage], prefix: &str) -> Option<CostDetailModel> {
+    let mut by_model: BTreeMap<&str, ModelTokenUsage> = BTreeMap::new();
+    for usage in days.iter().flat_map(|day| &day.models) {
+        let total = by_model
+            .entry(usage.model.as_str())
+            .or_insert_with(|| ModelTokenUsage {
+                model: usage.model.clone(),
+                input_tokens: 0,
+                output_tokens: 0,
+                cache_creation_tokens: 0,
+                cache_read_tokens: 0,
+                cost_usd: None,
+            });
+        total.input_tokens += usage.input_tokens;
+        total.output_tokens += usage.output_tokens;
+        total.cache_creation_tokens += usage.cache_creation_tokens;
+        total.cache_read_tokens += usage.cache_read_tokens;
+        if let Some(cost) = usage.cost_usd {
+            *total.cost_usd.get_or_insert(0.0) += cost;
+        }
+    }
+    if by_model.is_empty() {
+        return None;
+    }
+
+    let mut models: Vec<ModelTokenUsage> = by_model.into_values().collect();
+    // Stable, so equal counts stay in model order.
+    models.sort_by_key(|usage| std::cmp::Reverse(usage.total_tokens()));
+    let sum = |tokens: fn(&ModelTokenUsage) -> u64| models.iter().map(tokens).sum::<u64>();
+    let split = format!(
+        "Input {} · Output {} · Cache write {} · Cache read {}",
+        format_token_count(sum(|m| m.input_tokens)),
+        format_token_count(sum(|m| m.output_tokens)),
+        format_token_count(sum(|m| m.cache_creation_tokens)),
+        format_token_count(sum(|m| m.cache_read_tokens)),
+    );
+
+    Some(CostDetailModel {
+        title: title.to_string(),
+        models: models
+            .iter()
+            .take(MAX_DETAIL_MODELS)
+            .map(|usage| ModelCostRow {
+                model: usage.model.clone(),
+                tokens: format!("{} tokens", format_token_count(usage.total_tokens())),
+                cost: usage.cost_usd.map_or_else(
+                    || "—".to_string(),
+                    |cost| format!("{}{}", prefix, format_currency(cost)),
+                ),
+            })
+            .collect(),
+        split,
+    })
+}
+
+fn cost_line(label: &str, cost_text: Option<String>, tokens_text: Option<String>) -> String {
+    match (cost_text, tokens_text) {
+        (Some(cost_text), Some(tokens_text)) => {
+            format!("{}: {} · {} tokens", label, cost_text, tokens_text)
+        }
+        (Some(cost_text), None) => format!("{}: {}", label, cost_text),
+        (None, _) => format!("{}: —", label),
+    }
+}
+
+/// The first command quoted in backticks, e.g. "claude" from "Run `claude`
+/// to authenticate".
+pub fn hint_command(hint: &str) -> Option<String> {
+    let (_, rest) = hint.split_once('`')?;
+    let (command, _) = rest.split_once('`')?;
+    let command = command.trim();
+    (!command.is_empty()).then(|| command.to_string())
+}
+
+fn task_warning(failed_tasks: &[&str]) -> Option<String> {
+    if failed_tasks.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "Stopped after repeated crashes: {}. Restart claude-bar to resume.",
+        failed_tasks.join(", ")
+    ))
+}
+
+fn lockout_line<Tz: chrono::TimeZone>(
+    lockouts: &[Lockout],
+    now: DateTime<Utc>,
+    tz: &Tz,
+) -> Option<String>
+where
+    Tz::Offset: std::fmt::Display,
+{
+    if lockouts.is_empty() {
+        return None;
+    }
+    let summary = month_summary(lockouts, now, tz);
+    let mut line = format!("Lockouts this month: {}", summary.count);
+    if let Some(last) = summary.last {
+        line.push_str(&format!(
+            " (last: {}, {})",
+            last.at.with_timezone(tz).format("%a %H:%M"),
+            last.window.label()
+        ));
+    }
+    Some(line)
+}
+
+fn recent_errors(history: &[ErrorEvent]) -> Vec<String> {
+    history
+        .iter()
+        .rev()
+        .map(|event| {
+            format!(
+                "{}  {}",
+                event.at().with_timezone(&Local).format("%b %-d %H:%M"),
+                event.describe()
+            )
+        })
+        .collect()
+}
+
+/// "Unable to load usage since 14:32 (last good data 13:58)", with whichever
+/// times the store knows.
+fn error_status(health: Option<&ProviderHealth>, now: DateTime<Utc>) -> String {
+    let mut status = "Unable to load usage".to_string();
+    let Some(health) = health else {
+        return status;
+    };
+    if let Some(since) = health.error_since {
+        status.push_str(&format!(" since {}", format_clock_time(since, now)));
+    }
+    if let Some(last_success) = health.last_success_at {
+        status.push_str(&format!(
+            " (last good data {})",
+            format_clock_time(last_success, now)
+        ));
+    }
+    status
+}
+
+/// Local wall-clock time, with the date once it is a day or more ago.
+fn format_clock_time(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let local = at.with_timezone(&Local);
+    if now.signed_duration_since(at) < chrono::Duration::days(1) {
+        local.format("%H:%M").to_string()
+    } else {
+        local.format("%b %-d %H:%M").to_string()
+    }
+}
+
+/// "Updated 3m ago" for a snapshot of the given age (see
+/// `UsageSnapshot::age`).
+pub fn format_relative_time(age: chrono::Duration) -> String {
+    let duration = age.max(chrono::Duration::zero());
+
+    let seconds = duration.num_seconds();
+    if seconds < 60 {
+        return format!("Updated {}s ago", seconds);
+    }
+
+    let minutes = duration.num_minutes();
+    if minutes < 60 {
+        return format!("Updated {}m ago", minutes);
+    }
+
+    let hours = duration.num_hours();
+    if hours < 24 {
+        return format!("Updated {}h ago", hours);
+    }
+
+    let days = duration.num_days();
+    format!("Updated {}d ago", days)
+}
+
+/// Flags percentages the API reported out of range, since the shown value
+/// was clamped.
+fn clamped_marker(window: &RateWindow) -> &'static str {
+    if window.was_clamped() {
+        " (!)"
+    } else {
+        ""
+    }
+}
+
+fn window_reset_text(
+    window: &RateWindow,
+    format: ResetTimeFormat,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    Some(reset_text(
+        window.resets_at?,
+        window.resets_at_estimated,
+        format,
+        now,
+    ))
+}
+
+/// "resets in 2h 14m", "resets 17:42" or both, as `format` says. An
+/// estimated reset time gets a "~" in front of its countdown and time.
+fn reset_text(
+    resets_at: DateTime<Utc>,
+    estimated: bool,
+    format: ResetTimeFormat,
+    now: DateTime<Utc>,
+) -> String {
+    let text = format_reset_time(resets_at, now);
+    let Some(countdown) = text.strip_prefix("resets in ") else {
+        return text;
+    };
+    let approx = if estimated { "~" } else { "" };
+    let clock = format_reset_clock(resets_at, now, &Local, HourCycle::from_env());
+    format!(
+        "resets {}",
+        format.render(
+            &format!("{}{}", approx, countdown),
+            &format!("{}{}", approx, clock)
+        )
+    )
+}
+
+fn reset_tooltip(resets_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    format_reset_tooltip(resets_at, now, &Local, HourCycle::from_env())
+}
+
+fn format_reset_time(reset_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let duration = reset_at.signed_duration_since(now);
+
+    if duration.num_seconds() <= 0 {
+        return "resets now".to_string();
+    }
+
+    let total_minutes = duration.num_minutes();
+    let hours = total_minutes / 60;
+    let minutes = total_minutes % 60;
+
+    if hours > 24 {
+        let days = hours / 24;
+        let remaining_hours = hours % 24;
+        format!("resets in {}d {}h", days, remaining_hours)
+    } else if hours > 0 {
+        format!("resets in {}h {}m", hours, minutes)
+    } else {
+        format!("resets in {}m", minutes)
+    }
+}
+
+fn format_currency(value: f64) -> String {
+    format!("${:.2}", value)
+}
+
+fn format_currency_with_code(value: f64, code: &str) -> String {
+    if code == "USD" {
+        return format_currency(value);
+    }
+    format!("{} {:.2}", code, value)
+}
+
+fn format_token_count(tokens: u64) -> String {
+    if tokens >= 1_000_000 {
+        format!("{:.1}M", tokens as f64 / 1_000_000.0)
+    } else if tokens >= 1_000 {
+        format!("{:.1}K", tokens as f64 / 1_000.0)
+    } else {
+        tokens.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::models::{ErrorKind, ModelWindow, ProviderIdentity};
+    use chrono::{Duration, TimeZone};
+
+    fn window(used_percent: f64) -> RateWindow {
+        RateWindow {
+            used_percent,
+            window_minutes: None,
+            resets_at: None,
+            reset_description: None,
+            used_percent_exact: None,
+            resets_at_estimated: false,
+        }
+    }
+
+    fn snapshot(now: DateTime<Utc>) -> UsageSnapshot {
+        UsageSnapshot {
+            primary: Some(window(0.25)),
+            secondary: Some(window(0.6)),
+            tertiary: None,
+            provider_cost: None,
+            carveouts: Vec::new(),
+            surfaces: Vec::new(),
+            updated_at: now - Duration::minutes(3),
+            received_at: None,
+            identity: ProviderIdentity {
+                email: Some("me@example.com".to_string()),
+                organization: None,
+                plan: Some("Max".to_string()),
+                login_method: Some("Claude subscription (OAuth)".to_string()),
+            },
+        }
+    }
+
+    fn cost(pricing_estimate: bool) -> CostSnapshot {
+        CostSnapshot {
+            today_cost: 1.5,
+            monthly_cost: 42.25,
+            pricing_estimate,
+            ..CostSnapshot::default()
+        }
+    }
+
+    fn input(provider: Provider, snapshot: Option<&UsageSnapshot>) -> PopupInput<'_> {
+        PopupInput {
+            provider,
+            title: provider.name().to_string(),
+            snapshot,
+            cost: None,
+            tokens: None,
+            error: None,
+            error_history: &[],
+            incident: None,
+            health: None,
+            failed_tasks: &[],
+            latency: None,
+            velocity: None,
+            login: None,
+            show_as_remaining: false,
+            show_value_estimate: false,
+            reset_time_format: ResetTimeFormat::Relative,
+            hide_costs: false,
+            cost_enabled: true,
+            cost_timezone: CostTimezone::Utc,
+            monthly_spend: None,
+            lockouts: &[],
+        }
+    }
+
+    fn rows(model: &PopupModel) -> &[UsageRowModel] {
+        match &model.body {
+            PopupBody::Usage { rows, .. } => rows,
+            other => panic!("expected usage body, got {:?}", other),
+        }
+    }
+
+    fn cost_lines(model: &PopupModel) -> Option<&CostSectionModel> {
+        match &model.body {
+            PopupBody::Usage { cost, .. } => cost.as_ref(),
+            other => panic!("expected usage body, got {:?}", other),
+        }
+    }
+
+    fn cost_texts(model: &PopupModel) -> Vec<&str> {
+        match cost_lines(model) {
+            Some(CostSectionModel::Lines(lines)) => {
+                lines.iter().map(|line| line.text.as_str()).collect()
+            }
+            other => panic!("expected cost lines, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_used_and_remaining_display() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        let used = rows(&model);
+        assert_eq!(used[0].title, "5-hour session");
+        assert_eq!(used[0].percent_text, "25% used");
+        assert!((used[0].progress - 0.25).abs() < f64::EPSILON);
+        assert_eq!(used[1].title, "Weekly quota");
+
+        let mut remaining_input = input(Provider::Claude, Some(&snapshot));
+        remaining_input.show_as_remaining = true;
+        let model = PopupModel::build(&remaining_input, now);
+        let remaining = rows(&model);
+        assert_eq!(remaining[0].percent_text, "75% remaining");
+        assert!((remaining[0].progress - 0.75).abs() < f64::EPSILON);
+        assert_eq!(remaining[1].percent_text, "40% remaining");
+    }
+
+    #[test]
+    fn test_progress_is_clamped() {
+        let now = Utc::now();
+        let mut snapshot = snapshot(now);
+        snapshot.primary = Some(RateWindow::from_reported(2.5, Some(300), None, None));
+        snapshot.secondary = Some(RateWindow::from_reported(-0.1, Some(10080), None, None));
+
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert_eq!(rows(&model)[0].progress, 1.0);
+        assert_eq!(rows(&model)[0].percent_text, "100% used (!)");
+        assert_eq!(rows(&model)[1].progress, 0.0);
+        assert_eq!(rows(&model)[1].percent_text, "0% used (!)");
+
+        snapshot.primary = Some(window(0.4));
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert_eq!(rows(&model)[0].percent_text, "40% used");
+    }
+
+    #[test]
+    fn test_error_takes_precedence_over_snapshot() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let error = (
+            "Token expired".to_string(),
+            "Run `claude login`".to_string(),
+        );
+        let mut with_error = input(Provider::Claude, Some(&snapshot));
+        with_error.error = Some(&error);
+
+        let model = PopupModel::build(&with_error, now);
+        assert_eq!(
+            model.body,
+            PopupBody::Error {
+                message: "Token expired".to_string(),
+                hint: "Run `claude login`".to_string(),
+                hint_link: None,
+                hint_command: Some("claude login".to_string()),
+                recent_errors: Vec::new(),
+            }
+        );
+        assert_eq!(model.header.status, "Unable to load usage");
+        // Identity from the last good snapshot is still shown.
+        assert_eq!(model.header.plan.as_deref(), Some("Max"));
+        assert_eq!(model.footer[0], FooterAction::Login { add_account: true });
+        assert_eq!(model.footer[0].label(), "Add Account");
+    }
+
+    #[test]
+    fn test_account_menu_separates_login_from_switching() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert_eq!(model.footer[0], FooterAction::Login { add_account: false });
+        assert_eq!(model.footer[0].label(), "Account");
+
+        let modes: Vec<_> = AccountAction::ALL
+            .iter()
+            .map(AccountAction::login_mode)
+            .collect();
+        assert_eq!(
+            modes,
+            vec![
+                Some(LoginMode::Reauthenticate),
+                Some(LoginMode::SwitchAccount),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn test_task_warning_lists_stopped_tasks() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let mut input = input(Provider::Claude, Some(&snapshot));
+        assert_eq!(PopupModel::build(&input, now).task_warning, None);
+
+        input.failed_tasks = &["cost scan", "tray events"];
+        assert_eq!(
+            PopupModel::build(&input, now).task_warning.as_deref(),
+            Some("Stopped after repeated crashes: cost scan, tray events. Restart claude-bar to resume.")
+        );
+    }
+
+    #[test]
+    fn test_error_status_shows_outage_times() {
+        let local = |day, hour, minute| {
+            Local
+                .with_ymd_and_hms(2026, 3, day, hour, minute, 0)
+                .single()
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        let now = local(10, 15, 0);
+        let error = (
+            "Network error".to_string(),
+            "Check your connection".to_string(),
+        );
+        let mut failing = input(Provider::Claude, None);
+        failing.error = Some(&error);
+
+        let health = ProviderHealth {
+            last_success_at: Some(local(10, 13, 58)),
+            error_since: Some(local(10, 14, 32)),
+        };
+        failing.health = Some(&health);
+        assert_eq!(
+            PopupModel::build(&failing, now).header.status,
+            "Unable to load usage since 14:32 (last good data 13:58)"
+        );
+
+        // Over a day old, the date is included.
+        let long_outage = ProviderHealth {
+            last_success_at: Some(local(8, 9, 5)),
+            error_since: Some(local(9, 14, 59)),
+        };
+        failing.health = Some(&long_outage);
+        assert_eq!(
+            PopupModel::build(&failing, now).header.status,
+            "Unable to load usage since Mar 9 14:59 (last good data Mar 8 09:05)"
+        );
+
+        // Never loaded since the daemon started.
+        let never_loaded = ProviderHealth {
+            last_success_at: None,
+            error_since: Some(local(10, 14, 32)),
+        };
+        failing.health = Some(&never_loaded);
+        assert_eq!(
+            PopupModel::build(&failing, now).header.status,
+            "Unable to load usage since 14:32"
+        );
+    }
+
+    #[test]
+    fn test_incident_replaces_error_hint() {
+        let now = Utc::now();
+        let error = ("HTTP 529".to_string(), "Run `claude login`".to_string());
+        let incident = Incident {
+            description: "Partial System Outage".to_string(),
+            url: "https://status.claude.com".to_string(),
+        };
+        let mut failing = input(Provider::Claude, None);
+        failing.error = Some(&error);
+        failing.incident = Some(&incident);
+
+        let model = PopupModel::build(&failing, now);
+        let PopupBody::Error {
+            hint,
+            hint_link,
+            hint_command,
+            ..
+        } = &model.body
+        else {
+            panic!("expected error body, got {:?}", model.body);
+        };
+        assert_eq!(
+            hint,
+            "Anthropic is reporting an incident: Partial System Outage"
+        );
+        assert_eq!(hint_link.as_deref(), Some("https://status.claude.com"));
+        assert_eq!(*hint_command, None);
+    }
+
+    #[test]
+    fn test_hint_command_extraction() {
+        assert_eq!(
+            hint_command("Run `claude` to authenticate").as_deref(),
+            Some("claude")
+        );
+        assert_eq!(
+            hint_command("Run `codex login --device-auth` then `codex`").as_deref(),
+            Some("codex login --device-auth")
+        );
+        assert_eq!(hint_command("Check your network connection"), None);
+        assert_eq!(hint_command("Unbalanced `quote"), None);
+        assert_eq!(hint_command("Empty `` quotes"), None);
+    }
+
+    #[test]
+    fn test_login_progress() {
+        let now = Utc::now();
+        let build = |state: &LoginState| {
+            let mut login = input(Provider::Codex, None);
+            login.login = Some(state);
+            PopupModel::build(&login, now).login.unwrap()
+        };
+
+        let mut state = LoginState::default();
+        let model = build(&state);
+        assert!(model.running);
+        assert_eq!(model.message, "Starting login\u{2026}");
+
+        state.apply(LoginProgress::Output("Open this URL:".to_string()));
+        state.apply(LoginProgress::AuthLink("https://auth.example".to_string()));
+        let model = build(&state);
+        assert_eq!(model.message, "Open this URL:");
+        assert_eq!(model.auth_link.as_deref(), Some("https://auth.example"));
+        assert!(!model.failed);
+
+        state.apply(LoginProgress::Finished(LoginOutcome::Success));
+        let model = build(&state);
+        assert!(!model.running);
+        assert!(!model.failed);
+        assert_eq!(model.message, "Logged in. Refreshing usage\u{2026}");
+        assert_eq!(model.auth_link, None);
+    }
+
+    #[test]
+    fn test_login_failures_are_specific() {
+        let message = |provider, outcome| {
+            let state = LoginState {
+                outcome: Some(outcome),
+                ..LoginState::default()
+            };
+            let model = LoginModel::build(provider, &state);
+            assert!(model.failed && !model.running);
+            model.message
+        };
+
+        assert_eq!(
+            message(Provider::CodexWorkspace(1), LoginOutcome::MissingBinary),
+            "codex CLI not found in PATH"
+        );
+        assert_eq!(
+            message(Provider::Claude, LoginOutcome::MissingBinary),
+            "claude CLI not found in PATH"
+        );
+        assert_eq!(
+            message(Provider::Claude, LoginOutcome::TimedOut),
+            "Login timed out"
+        );
+        assert_eq!(
+            message(Provider::Codex, LoginOutcome::Failed(2)),
+            "Login failed (exit code 2)"
+        );
+    }
+
+    #[test]
+    fn test_error_history_is_listed_newest_first() {
+        let now = Utc::now();
+        let error = ("Rate limited".to_string(), String::new());
+        let history = vec![
+            ErrorEvent::Failed {
+                at: now - Duration::minutes(30),
+                kind: ErrorKind::Network,
+                message: "Failed to fetch Claude usage".to_string(),
+            },
+            ErrorEvent::Recovered {
+                at: now - Duration::minutes(20),
+                failures: 1,
+                outage_secs: 600,
+            },
+            ErrorEvent::Failed {
+                at: now,
+                kind: ErrorKind::RateLimited,
+                message: "Rate limited".to_string(),
+            },
+        ];
+        let mut with_error = input(Provider::Claude, None);
+        with_error.error = Some(&error);
+        with_error.error_history = &history;
+
+        let model = PopupModel::build(&with_error, now);
+        let PopupBody::Error { recent_errors, .. } = &model.body else {
+            panic!("expected error body, got {:?}", model.body);
+        };
+        assert_eq!(recent_errors.len(), 3);
+        assert!(recent_errors[0].ends_with("rate limited: Rate limited"));
+        assert!(recent_errors[1].ends_with("ok after 1 failure, 10m outage"));
+        assert!(recent_errors[2].ends_with("network: Failed to fetch Claude usage"));
+    }
+
+    #[test]
+    fn test_quota_adjusted_badge_lasts_an_hour() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let adjusted = |minutes_ago| ErrorEvent::QuotaAdjusted {
+            at: now - Duration::minutes(minutes_ago),
+            window: WindowSlot::Secondary,
+            from: 0.8,
+            to: 0.4,
+        };
+        let badges = |history: &[ErrorEvent]| {
+            let mut input = input(Provider::Claude, Some(&snapshot));
+            input.error_history = history;
+            rows(&PopupModel::build(&input, now))
+                .iter()
+                .map(|row| row.quota_adjusted)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(badges(&[]), [false, false]);
+        // Only the weekly row, and only for the hour after the drop.
+        assert_eq!(badges(&[adjusted(10)]), [false, true]);
+        assert_eq!(badges(&[adjusted(61)]), [false, false]);
+    }
+
+    #[test]
+    fn test_empty_state() {
+        let now = Utc::now();
+        let model = PopupModel::build(&input(Provider::Codex, None), now);
+
+        assert_eq!(model.body, PopupBody::Empty);
+        assert_eq!(model.header.status, "Loading\u{2026}");
+        assert_eq!(model.header.plan, None);
+        assert_eq!(model.footer[0].label(), "Account");
+        assert_eq!(
+            model
+                .footer
+                .iter()
+                .map(FooterAction::label)
+                .collect::<Vec<_>>(),
+            vec![
+                "Account",
+                "Usage Dashboard",
+                "Status Page",
+                "Refresh Now",
+                "Insights",
+                "Hide costs",
+                "Settings"
+            ]
+        );
+
+        let mut without_cost = input(Provider::Codex, None);
+        without_cost.cost_enabled = false;
+        let model = PopupModel::build(&without_cost, now);
+        assert!(!model.footer.contains(&FooterAction::Insights));
+    }
+
+    #[test]
+    fn test_insights_heatmap() {
+        let now = Utc::now();
+        let mut heatmap = UsageHeatmap::default();
+        heatmap.add(chrono::Weekday::Tue, 14, 340_000);
+        heatmap.add(chrono::Weekday::Sun, 23, 85_000);
+        let tokens = CostUsageTokenSnapshot {
+            session_tokens: None,
+            session_cost_usd: None,
+            session_start: None,
+            last_30_days_tokens: Some(425_000),
+            last_30_days_cost_usd: None,
+            daily: Vec::new(),
+            heatmap,
+            updated_at: now,
+        };
+
+        let model = InsightsModel::build(Some(&tokens));
+        assert_eq!(model.rows.len(), 7);
+        assert!(model.rows.iter().all(|row| row.len() == 24));
+        assert_eq!(model.rows[1][14].intensity, 1.0);
+        assert_eq!(model.rows[1][14].tooltip, "Tue 14:00–15:00 · 340K tokens");
+        assert_eq!(model.rows[6][23].intensity, 0.25);
+        assert_eq!(model.rows[6][23].tooltip, "Sun 23:00–24:00 · 85K tokens");
+        assert_eq!(model.rows[0][0].intensity, 0.0);
+        assert_eq!(model.rows[0][0].tooltip, "Mon 00:00–01:00 · 0 tokens");
+        assert_eq!(model.summary, "Busiest: Tue 14:00–15:00");
+    }
+
+    #[test]
+    fn test_insights_without_usage() {
+        let model = InsightsModel::build(None);
+        assert!(model.rows.is_empty());
+        assert_eq!(model.summary, "No token usage in the last 30 days");
+        assert_eq!(format_compact_tokens(1_260_000), "1.3M");
+        assert_eq!(format_compact_tokens(2_000_000), "2M");
+        assert_eq!(format_compact_tokens(12_345), "12.3K");
+    }
+
+    #[test]
+    fn test_header_uses_relative_update_time() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+
+        assert_eq!(model.header.status, "Updated 3m ago");
+        assert_eq!(model.header.status_tooltip, None);
+        assert_eq!(model.header.email.as_deref(), Some("me@example.com"));
+        assert_eq!(
+            model.header.login_method.as_deref(),
+            Some("Claude subscription (OAuth)")
+        );
+    }
+
+    #[test]
+    fn test_header_tooltip_shows_median_latency() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let latency = FetchLatency {
+            min: 310,
+            median: 840,
+            max: 2_050,
+            samples: 12,
+        };
+        let mut with_latency = input(Provider::Claude, Some(&snapshot));
+        with_latency.latency = Some(&latency);
+
+        let model = PopupModel::build(&with_latency, now);
+        assert_eq!(model.header.status_tooltip.as_deref(), Some("API: 840ms"));
+    }
+
+    #[test]
+    fn test_carveout_label_trimming() {
+        let now = Utc::now();
+        let mut snapshot = snapshot(now);
+        snapshot.tertiary = Some(window(0.8));
+        snapshot.carveouts = vec![
+            ModelWindow {
+                label: "Sonnet Weekly".to_string(),
+                window: window(0.1),
+            },
+            ModelWindow {
+                label: "Opus Weekly".to_string(),
+                window: window(0.8),
+            },
+        ];
+
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert_eq!(rows(&model)[2].title, "Opus");
+    }
+
+    #[test]
+    fn test_surface_rows_stay_out_of_main_rows() {
+        let now = Utc::now();
+        let mut snapshot = snapshot(now);
+        let surfaces = |model: &PopupModel| match &model.body {
+            PopupBody::Usage { surfaces, .. } => surfaces.clone(),
+            other => panic!("expected usage body, got {:?}", other),
+        };
+
+        let plain = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert!(surfaces(&plain).is_empty());
+
+        snapshot.surfaces = vec![
+            ModelWindow {
+                label: "Code usage".to_string(),
+                window: window(0.21),
+            },
+            ModelWindow {
+                label: "Web usage".to_string(),
+                window: window(0.41),
+            },
+        ];
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert_eq!(rows(&model), rows(&plain));
+        let surfaces = surfaces(&model);
+        assert_eq!(surfaces[0].title, "Code usage");
+        assert_eq!(surfaces[1].title, "Web usage");
+        assert_eq!(surfaces[1].percent_text, "41% used");
+        assert!(surfaces[1].pace_marker.is_none());
+    }
+
+    #[test]
+    fn test_tertiary_label_without_matching_carveout() {
+        let now = Utc::now();
+        let mut snapshot = snapshot(now);
+        snapshot.tertiary = Some(window(0.8));
+
+        let claude = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert_eq!(rows(&claude)[2].title, "Model");
+
+        let codex = PopupModel::build(&input(Provider::CodexWorkspace(1), Some(&snapshot)), now);
+        assert_eq!(rows(&codex)[0].title, "Session");
+        assert_eq!(rows(&codex)[1].title, "Weekly");
+        assert_eq!(rows(&codex)[2].title, "Additional");
+    }
+
+    #[test]
+    fn test_cost_lines_prefix_estimates() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+
+        let estimated = cost(true);
+        let mut with_cost = input(Provider::Claude, Some(&snapshot));
+        with_cost.cost = Some(&estimated);
+        let model = PopupModel::build(&with_cost, now);
+        assert_eq!(
+            cost_texts(&model),
+            vec!["Today: ~$1.50", "Last 30 days: ~$42.25"]
+        );
+
+        let exact = cost(false);
+        with_cost.cost = Some(&exact);
+        let model = PopupModel::build(&with_cost, now);
+        assert_eq!(
+            cost_texts(&model),
+            vec!["Today: $1.50", "Last 30 days: $42.25"]
+        );
+    }
+
+    #[test]
+    fn test_cost_lines_with_token_snapshot() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let estimated = cost(true);
+        let tokens = CostUsageTokenSnapshot {
+            session_tokens: Some(12_345),
+            session_cost_usd: Some(0.5),
+            session_start: Some(now - Duration::hours(1)),
+            last_30_days_tokens: None,
+            last_30_days_cost_usd: None,
+            daily: Vec::new(),
+            heatmap: UsageHeatmap::default(),
+            updated_at: now,
+        };
+        let mut with_tokens = input(Provider::Claude, Some(&snapshot));
+        with_tokens.cost = Some(&estimated);
+        with_tokens.tokens = Some(&tokens);
+
+        let model = PopupModel::build(&with_tokens, now);
+        assert_eq!(
+            cost_texts(&model),
+            vec![
+                "This session: ~$0.50 · 12.3K tokens",
+                "Last 30 days: ~$42.25",
+            ]
+        );
+
+        with_tokens.cost = None;
+        let model = PopupModel::build(&with_tokens, now);
+        assert_eq!(
+            cost_texts(&model),
+            vec!["This session: $0.50 · 12.3K tokens", "Last 30 days: —"]
+        );
+    }
+
+    #[test]
+    fn test_stacked_layout_shares_cost_and_footer() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let claude_cost = cost(true);
+        let tokens = CostUsageTokenSnapshot {
+            session_tokens: Some(12_345),
+            session_cost_usd: Some(0.5),
+            session_start: Some(now - Duration::hours(1)),
+            last_30_days_tokens: None,
+            last_30_days_cost_usd: Some(8.0),
+            daily: Vec::new(),
+            heatmap: UsageHeatmap::default(),
+            updated_at: now,
+        };
+        let spend = MonthlySpend {
+            spent: 142.0,
+            budget: 200.0,
+        };
+        let error = ("Token expired".to_string(), "Run `codex`".to_string());
+        let mut claude = input(Provider::Claude, Some(&snapshot));
+        claude.cost = Some(&claude_cost);
+        claude.monthly_spend = Some(&spend);
+        let mut codex = input(Provider::Codex, Some(&snapshot));
+        codex.tokens = Some(&tokens);
+        codex.monthly_spend = Some(&spend);
+        let mut workspace = input(Provider::CodexWorkspace(1), None);
+        workspace.error = Some(&error);
+
+        let model = StackedModel::build(&[claude, codex, workspace], now);
+        let providers: Vec<Provider> = model.sections.iter().map(|s| s.provider).collect();
+        assert_eq!(
+            providers,
+            vec![
+                Provider::Claude,
+                Provider::Codex,
+                Provider::CodexWorkspace(1)
+            ]
+        );
+        for section in &model.sections[..2] {
+            match &section.body {
+                PopupBody::Usage {
+                    rows, cost, budget, ..
+                } => {
+                    assert_eq!(rows.len(), 2);
+                    assert_eq!(*cost, None);
+                    assert_eq!(*budget, None);
+                }
+                other => panic!("expected usage body, got {:?}", other),
+            }
+        }
+        assert!(matches!(model.sections[2].body, PopupBody::Error { .. }));
+        assert_eq!(model.sections[0].header.title, "Claude Code");
+        match &model.cost {
+            Some(CostSectionModel::Lines(lines)) => assert_eq!(
+                lines.iter().map(|l| l.text.as_str()).collect::<Vec<_>>(),
+                vec![
+                    "Claude Code: ~$1.50 today · ~$42.25 last 30 days",
+                    "Codex: $0.50 this session · $8.00 last 30 days",
+                ]
+            ),
+            other => panic!("expected cost lines, got {:?}", other),
+        }
+        assert_eq!(model.budget.unwrap().line, "Month: $142 / $200 (71%)");
+        assert_eq!(
+            model.footer,
+            vec![
+                FooterAction::RefreshNow,
+                FooterAction::HideCosts { hidden: false },
+                FooterAction::Settings,
+            ]
+        );
+
+        let mut claude = input(Provider::Claude, Some(&snapshot));
+        claude.cost = Some(&claude_cost);
+        claude.hide_costs = true;
+        let model = StackedModel::build(std::slice::from_ref(&claude), now);
+        assert_eq!(model.cost, Some(CostSectionModel::Hidden));
+        claude.cost_enabled = false;
+        assert_eq!(StackedModel::build(&[claude], now).cost, None);
+    }
+
+    #[test]
+    fn test_cost_detail_lists_top_models() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let estimated = cost(true);
+        let usage = |model: &str, input_tokens: u64, cost_usd: Option<f64>| ModelTokenUsage {
+            model: model.to_string(),
+            input_tokens,
+            output_tokens: 1_000,
+            cache_creation_tokens: 0,
+            cache_read_tokens: 10_000,
+            cost_usd,
+        };
+        let day = |offset: i64, models: Vec<ModelTokenUsage>| DailyTokenUsage {
+            date: now.date_naive() - Duration::days(offset),
+            total_tokens: Some(models.iter().map(ModelTokenUsage::total_tokens).sum()),
+            cost_usd: None,
+            models,
+        };
+        let tokens = CostUsageTokenSnapshot {
+            session_tokens: None,
+            session_cost_usd: None,
+            session_start: None,
+            last_30_days_tokens: None,
+            last_30_days_cost_usd: None,
+            daily: vec![
+                day(3, vec![usage("claude-opus-4", 500_000, Some(9.0))]),
+                day(
+                    0,
+                    vec![
+                        usage("claude-haiku-4", 1_000, Some(0.01)),
+                        usage("claude-opus-4", 200_000, Some(4.0)),
+                        usage("claude-sonnet-4", 50_000, Some(0.5)),
+                        usage("custom-a", 2_000, None),
+                        usage("custom-b", 3_000, None),
+                        usage("custom-c", 4_000, None),
+                    ],
+                ),
+            ],
+            heatmap: UsageHeatmap::default(),
+            updated_at: now,
+        };
+        let mut with_tokens = input(Provider::Claude, Some(&snapshot));
+        with_tokens.cost = Some(&estimated);
+        with_tokens.tokens = Some(&tokens);
+
+        let model = PopupModel::build(&with_tokens, now);
+        let Some(CostSectionModel::Lines(lines)) = cost_lines(&model) else {
+            panic!("expected cost lines");
+        };
+        let today = lines[0].detail.as_ref().expect("today's breakdown");
+        assert_eq!(today.title, "Today");
+        let names: Vec<&str> = today.models.iter().map(|row| row.model.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "claude-opus-4",
+                "claude-sonnet-4",
+                "custom-c",
+                "custom-b",
+                "custom-a"
+            ]
+        );
+        assert_eq!(today.models[0].tokens, "211.0K tokens");
+        assert_eq!(today.models[0].cost, "~$4.00");
+        assert_eq!(today.models[2].cost, "—");
+        // The split covers the model left out of the list too.
+        assert_eq!(
+            today.split,
+            "Input 260.0K · Output 6.0K · Cache write 0 · Cache read 60.0K"
+        );
+
+        let month = lines[1].detail.as_ref().expect("the month's breakdown");
+        assert_eq!(month.models[0].tokens, "722.0K tokens");
+        assert_eq!(month.models[0].cost, "~$13.00");
+
+        with_tokens.cost = None;
+        let model = PopupModel::build(&with_tokens, now);
+        let Some(CostSectionModel::Lines(lines)) = cost_lines(&model) else {
+            panic!("expected cost lines");
+        };
+        assert_eq!(lines[0].detail.as_ref().unwrap().models[0].cost, "$4.00");
+
+        // Nothing from an earlier day stands in for today.
+        let stale = CostUsageTokenSnapshot {
+            daily: tokens.daily[..1].to_vec(),
+            ..tokens.clone()
+        };
+        with_tokens.tokens = Some(&stale);
+        let model = PopupModel::build(&with_tokens, now);
+        let Some(CostSectionModel::Lines(lines)) = cost_lines(&model) else {
+            panic!("expected cost lines");
+        };
+        assert_eq!(lines[0].detail, None);
+        assert!(lines[1].detail.is_some());
+    }
+
+    #[test]
+    fn test_cost_section_log_error_and_absence() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let broken = CostSnapshot {
+            log_error: true,
+            ..cost(false)
+        };
+        let mut with_cost = input(Provider::Claude, Some(&snapshot));
+        with_cost.cost = Some(&broken);
+        let model = PopupModel::build(&with_cost, now);
+        assert_eq!(cost_lines(&model), Some(&CostSectionModel::LogError));
+
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert_eq!(cost_lines(&model), None);
+    }
+
+    #[test]
+    fn test_cost_section_notes_excluded_projects() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let note = |excluded| {
+            let filtered = CostSnapshot {
+                excluded_projects: excluded,
+                ..cost(false)
+            };
+            let mut with_cost = input(Provider::Claude, Some(&snapshot));
+            with_cost.cost = Some(&filtered);
+            match PopupModel::build(&with_cost, now).body {
+                PopupBody::Usage { cost_note, .. } => cost_note,
+                other => panic!("expected usage body, got {:?}", other),
+            }
+        };
+
+        assert_eq!(note(0), None);
+        assert_eq!(note(1).as_deref(), Some("(1 project excluded)"));
+        assert_eq!(note(3).as_deref(), Some("(3 projects excluded)"));
+    }
+
+    #[test]
+    fn test_monthly_budget_bar() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let budget = |model: &PopupModel| match &model.body {
+            PopupBody::Usage { budget, .. } => budget.clone(),
+            other => panic!("expected usage body, got {:?}", other),
+        };
+
+        let spend = MonthlySpend {
+            spent: 142.0,
+            budget: 200.0,
+        };
+        let mut with_budget = input(Provider::Claude, Some(&snapshot));
+        with_budget.monthly_spend = Some(&spend);
+        let model = budget(&PopupModel::build(&with_budget, now)).unwrap();
+        assert_eq!(model.line, "Month: $142 / $200 (71%)");
+        assert!((model.progress - 0.71).abs() < 1e-9);
+        assert_eq!(model.level, BudgetLevel::Normal);
+
+        let near = MonthlySpend {
+            spent: 170.0,
+            budget: 200.0,
+        };
+        assert_eq!(MonthlyBudgetModel::build(&near).level, BudgetLevel::Warning);
+        let over = MonthlySpend {
+            spent: 230.0,
+            budget: 200.0,
+        };
+        let over = MonthlyBudgetModel::build(&over);
+        assert_eq!(over.level, BudgetLevel::Critical);
+        assert_eq!(over.progress, 1.0);
+
+        with_budget.hide_costs = true;
+        assert_eq!(budget(&PopupModel::build(&with_budget, now)), None);
+        with_budget.hide_costs = false;
+        with_budget.cost_enabled = false;
+        assert_eq!(budget(&PopupModel::build(&with_budget, now)), None);
+    }
+
+    #[test]
+    fn test_lockout_line() {
+        let now: DateTime<Utc> = "2026-10-16T10:00:00Z".parse().unwrap();
+        let lockout = |at: &str, window| Lockout {
+            at: at.parse().unwrap(),
+            window,
+            resets_at: None,
+        };
+        let lockouts = [
+            lockout("2026-09-29T08:00:00Z", WindowSlot::Primary),
+            lockout("2026-10-02T09:30:00Z", WindowSlot::Primary),
+            lockout("2026-10-13T14:05:00Z", WindowSlot::Secondary),
+        ];
+
+        assert_eq!(lockout_line(&[], now, &Utc), None);
+        assert_eq!(
+            lockout_line(&lockouts, now, &Utc).as_deref(),
+            Some("Lockouts this month: 2 (last: Tue 14:05, weekly)")
+        );
+        let next_month = now + chrono::Duration::days(30);
+        assert_eq!(
+            lockout_line(&lockouts, next_month, &Utc).as_deref(),
+            Some("Lockouts this month: 0")
+        );
+
+        let snapshot = snapshot(now);
+        let mut with_lockouts = input(Provider::Claude, Some(&snapshot));
+        with_lockouts.lockouts = &lockouts;
+        let PopupBody::Usage { lockouts, .. } = PopupModel::build(&with_lockouts, now).body else {
+            panic!("expected usage body");
+        };
+        assert!(lockouts.unwrap().starts_with("Lockouts this month: 2"));
+    }
+
+    #[test]
+    fn test_cost_section_hidden_when_disabled() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let exact = cost(false);
+        let mut disabled = input(Provider::Claude, Some(&snapshot));
+        disabled.cost = Some(&exact);
+        disabled.show_value_estimate = true;
+        disabled.cost_enabled = false;
+
+        let model = PopupModel::build(&disabled, now);
+        assert_eq!(cost_lines(&model), None);
+        assert!(rows(&model).iter().all(|row| row.value_estimate.is_none()));
+    }
+
+    #[test]
+    fn test_provider_cost_section() {
+        let now = Utc::now();
+        let mut snapshot = snapshot(now);
+        snapshot.provider_cost = Some(ProviderCostSnapshot {
+            used: 12.5,
+            limit: 50.0,
+            currency_code: "EUR".to_string(),
+            period: None,
+            resets_at: Some(now + Duration::days(9) + Duration::hours(2)),
+            updated_at: now,
+        });
+
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        let PopupBody::Usage { provider_cost, .. } = &model.body else {
+            panic!("expected usage body");
+        };
+        let provider_cost = provider_cost.as_ref().unwrap();
+        assert_eq!(provider_cost.title, "Extra usage");
+        assert_eq!(
+            provider_cost.spend_line,
+            "This month: EUR 12.50 / EUR 50.00"
+        );
+        assert_eq!(provider_cost.percent_text, "25% used");
+        assert_eq!(provider_cost.reset_text.as_deref(), Some("resets in 9d 2h"));
+
+        // Last period's spend is gone once the period resets.
+        let after_reset = now + Duration::days(10);
+        assert_eq!(
+            provider_cost_section(
+                snapshot.provider_cost.as_ref().unwrap(),
+                false,
+                ResetTimeFormat::Relative,
+                after_reset
+            ),
+            None
+        );
+
+        let mut quota = snapshot.provider_cost.clone().unwrap();
+        quota.currency_code = "Quota".to_string();
+        quota.limit = 0.0;
+        assert_eq!(
+            provider_cost_section(&quota, false, ResetTimeFormat::Relative, now),
+            None
+        );
+
+        let mut credits = snapshot.provider_cost.clone().unwrap();
+        credits.currency_code = "Credits".to_string();
+        credits.used = 120.0;
+        credits.limit = 500.0;
+        credits.resets_at = None;
+        let section =
+            provider_cost_section(&credits, true, ResetTimeFormat::Relative, now).unwrap();
+        assert_eq!(section.title, "Bonus credits");
+        assert_eq!(section.spend_line, "Used: 120 / 500");
+        assert_eq!(section.percent_text, "24% used");
+        assert_eq!(section.reset_text, None);
+    }
+
+    #[test]
+    fn test_hide_costs_drops_amounts() {
+        let now = Utc::now();
+        let mut snapshot = snapshot(now);
+        snapshot.provider_cost = Some(ProviderCostSnapshot {
+            used: 12.5,
+            limit: 50.0,
+            currency_code: "USD".to_string(),
+            period: Some("Monthly".to_string()),
+            resets_at: None,
+            updated_at: now,
+        });
+        let exact = cost(false);
+        let mut hidden = input(Provider::Claude, Some(&snapshot));
+        hidden.cost = Some(&exact);
+        hidden.hide_costs = true;
+
+        let model = PopupModel::build(&hidden, now);
+        assert_eq!(cost_lines(&model), Some(&CostSectionModel::Hidden));
+        let PopupBody::Usage { provider_cost, .. } = &model.body else {
+            panic!("expected usage body");
+        };
+        let provider_cost = provider_cost.as_ref().unwrap();
+        assert_eq!(provider_cost.spend_line, "Monthly: Amounts hidden");
+        assert_eq!(provider_cost.percent_text, "25% used");
+        assert!(model
+            .footer
+            .contains(&FooterAction::HideCosts { hidden: true }));
+        assert_eq!(
+            FooterAction::HideCosts { hidden: true }.label(),
+            "Show costs"
+        );
+
+        let costs = HashMap::from([
+            (Provider::Claude, monthly(92.0)),
+            (Provider::Codex, monthly(31.0)),
+        ]);
+        let share = ShareModel::build(&costs, true).unwrap();
+        assert_eq!(share.summary, "Amounts hidden");
+        assert_eq!(share.segments[1].tooltip, "Codex (25%)");
+    }
+
+    #[test]
+    fn test_value_estimate_only_on_weekly_row_when_enabled() {
+        let now = Utc::now();
+        let mut snapshot = snapshot(now);
+        let weekly = RateWindow {
+            used_percent: 0.25,
+            window_minutes: Some(7 * 24 * 60),
+            resets_at: Some(now + Duration::days(3)),
+            reset_description: None,
+            used_percent_exact: None,
+            resets_at_estimated: false,
+        };
+        snapshot.primary = Some(weekly.clone());
+        snapshot.secondary = Some(weekly);
+        let priced = CostSnapshot {
+            daily_breakdown: vec![crate::core::models::DailyCost {
+                date: now.with_timezone(&chrono::Local).date_naive(),
+                model: "claude-sonnet-4".to_string(),
+                cost: 6.0,
+                tokens: 2_000_000,
+            }],
+            ..cost(false)
+        };
+        let mut with_cost = input(Provider::Claude, Some(&snapshot));
+        with_cost.cost = Some(&priced);
+
+        let model = PopupModel::build(&with_cost, now);
+        assert!(rows(&model).iter().all(|row| row.value_estimate.is_none()));
+
+        with_cost.show_value_estimate = true;
+        let model = PopupModel::build(&with_cost, now);
+        assert_eq!(rows(&model)[0].value_estimate, None);
+        assert_eq!(
+            rows(&model)[1].value_estimate.as_deref(),
+            Some("Remaining \u{2248} 6.0M tokens (\u{2248} $18 at API prices)")
+        );
+    }
+
+    #[test]
+    fn test_week_over_week_line_on_weekly_row() {
+        let now = Utc::now();
+        let today = now.with_timezone(&chrono::Local).date_naive();
+        let mut snapshot = snapshot(now);
+        snapshot.secondary = Some(RateWindow {
+            used_percent: 0.4,
+            window_minutes: Some(7 * 24 * 60),
+            resets_at: Some(now + Duration::days(7)),
+            reset_description: None,
+            used_percent_exact: None,
+            resets_at_estimated: false,
+        });
+        let day = |date, total_tokens| crate::core::models::DailyTokenUsage {
+            date,
+            total_tokens: Some(total_tokens),
+            cost_usd: None,
+            models: Vec::new(),
+        };
+        let this_week = CostUsageTokenSnapshot {
+            session_tokens: None,
+            session_cost_usd: None,
+            session_start: None,
+            last_30_days_tokens: None,
+            last_30_days_cost_usd: None,
+            daily: vec![day(today, 1_220)],
+            heatmap: UsageHeatmap::default(),
+            updated_at: now,
+        };
+        let both_weeks = CostUsageTokenSnapshot {
+            daily: vec![day(today - Duration::days(7), 1_000), day(today, 1_220)],
+            ..this_week.clone()
+        };
+        let mut with_tokens = input(Provider::Claude, Some(&snapshot));
+        with_tokens.tokens = Some(&this_week);
+
+        // Nothing logged last week: no line.
+        let model = PopupModel::build(&with_tokens, now);
+        assert!(rows(&model).iter().all(|row| row.week_over_week.is_none()));
+
+        with_tokens.tokens = Some(&both_weeks);
+        let model = PopupModel::build(&with_tokens, now);
+        assert_eq!(rows(&model)[0].week_over_week, None);
+        assert_eq!(
+            rows(&model)[1].week_over_week,
+            Some(WeekOverWeekModel {
+                text: "vs last week: +22% \u{2191}".to_string(),
+                trend: Trend::Up,
+            })
+        );
+
+        with_tokens.cost_enabled = false;
+        let model = PopupModel::build(&with_tokens, now);
+        assert_eq!(rows(&model)[1].week_over_week, None);
+    }
+
+    fn local(date: &str, time: &str) -> NaiveDateTime {
+        format!("{}T{}", date, time).parse().unwrap()
+    }
+
+    fn strip_states(days: &[WeekStripDay]) -> String {
+        days.iter()
+            .map(|day| match day.state {
+                DayState::Elapsed => '#',
+                DayState::Today => '*',
+                DayState::Upcoming => '.',
+            })
+            .collect()
+    }
+
+    fn strip_labels(days: &[WeekStripDay]) -> Vec<Option<char>> {
+        days.iter().map(|day| day.label).collect()
+    }
+
+    #[test]
+    fn test_week_strip_across_a_month_boundary() {
+        // Wednesday Oct 28 17:00 to Wednesday Nov 4 17:00.
+        let starts_at = local("2026-10-28", "17:00:00");
+        let resets_at = local("2026-11-04", "17:00:00");
+
+        let days = week_strip_days(starts_at, resets_at, local("2026-10-31", "09:00:00"));
+        assert_eq!(strip_states(&days), "##*....");
+        let mut labels = vec![None; 6];
+        labels.push(Some('W'));
+        assert_eq!(strip_labels(&days), labels);
+
+        // The evening of the start day belongs to the first tick.
+        let days = week_strip_days(starts_at, resets_at, local("2026-10-28", "18:00:00"));
+        assert_eq!(strip_states(&days), "*......");
+        let days = week_strip_days(starts_at, resets_at, local("2026-11-04", "16:00:00"));
+        assert_eq!(strip_states(&days), "######*");
+        let days = week_strip_days(starts_at, resets_at, local("2026-11-05", "08:00:00"));
+        assert_eq!(strip_states(&days), "#######");
+    }
+
+    #[test]
+    fn test_week_strip_across_dst_changes() {
+        // Clocks go forward on Sunday Mar 8: a week of 168 hours ends an
+        // hour later on the wall clock, and it's still seven days.
+        let days = week_strip_days(
+            local("2026-03-04", "17:00:00"),
+            local("2026-03-11", "18:00:00"),
+            local("2026-03-08", "12:00:00"),
+        );
+        assert_eq!(strip_states(&days), "###*...");
+        assert_eq!(days[6].label, Some('W'));
+
+        // Clocks go back on Sunday Nov 1: a week starting just after
+        // midnight on Wednesday resets late on Tuesday.
+        let days = week_strip_days(
+            local("2026-10-28", "00:30:00"),
+            local("2026-11-03", "23:30:00"),
+            local("2026-10-28", "00:45:00"),
+        );
+        assert_eq!(strip_states(&days), "*......");
+        assert_eq!(days[6].label, Some('T'));
+    }
+
+    #[test]
+    fn test_week_strip_only_on_weekly_row() {
+        let now: DateTime<Utc> = "2026-10-31T09:00:00Z".parse().unwrap();
+        let mut weekly = window(0.4);
+        weekly.window_minutes = Some(7 * 24 * 60);
+        weekly.resets_at = Some("2026-11-04T17:00:00Z".parse().unwrap());
+        assert_eq!(strip_states(&week_strip(&weekly, now, &Utc)), "##*....");
+        let east = chrono::FixedOffset::east_opt(9 * 3600).unwrap();
+        assert_eq!(strip_states(&week_strip(&weekly, now, &east)), "#*.....");
+
+        let mut snapshot = snapshot(now);
+        snapshot.primary.as_mut().unwrap().resets_at = weekly.resets_at;
+        snapshot.primary.as_mut().unwrap().window_minutes = Some(300);
+        snapshot.secondary = Some(weekly.clone());
+        let model = PopupModel::build(&input(Provider::Claude, Some(&snapshot)), now);
+        assert!(rows(&model)[0].week_strip.is_empty());
+        assert_eq!(rows(&model)[1].week_strip.len(), 7);
+
+        // Without the window length or reset time the strip is hidden.
+        weekly.window_minutes = None;
+        assert!(week_strip(&weekly, now, &Utc).is_empty());
+        weekly.window_minutes = Some(7 * 24 * 60);
+        weekly.resets_at = None;
+        assert!(week_strip(&weekly, now, &Utc).is_empty());
+    }
+
+    #[test]
+    fn test_session_pace_marker_in_both_display_modes() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap();
+        // A quarter of the way through the 5-hour window.
+        let session = |used_percent| RateWindow {
+            window_minutes: Some(300),
+            resets_at: Some(now + Duration::minutes(225)),
+            ..window(used_percent)
+        };
+        let session_row = |used_percent, show_as_remaining| {
+            let mut snapshot = snapshot(now);
+            snapshot.primary = Some(session(used_percent));
+            let mut input = input(Provider::Claude, Some(&snapshot));
+            input.show_as_remaining = show_as_remaining;
+            let model = PopupModel::build(&input, now);
+            let row = rows(&model)[0].clone();
+            (row.pace_marker.unwrap(), row.pace_summary.unwrap())
+        };
+
+        let (marker, summary) = session_row(0.35, false);
+        assert_eq!(marker.position, 0.25);
+        assert!(marker.is_deficit);
+        assert_eq!(summary, "Pace: 10% in deficit \u{00b7} Runs out in 2h 19m");
+        let (marker, summary) = session_row(0.35, true);
+        assert_eq!(marker.position, 0.75);
+        assert!(marker.is_deficit);
+        assert_eq!(
+            summary,
+            "Pace: 10% in deficit \u{00b7} Remaining lasts 2h 19m"
+        );
+
+        let (marker, summary) = session_row(0.15, false);
+        assert_eq!(marker.position, 0.25);
+        assert!(!marker.is_deficit);
+        assert_eq!(summary, "Pace: 10% in reserve \u{00b7} Lasts until reset");
+        let (marker, summary) = session_row(0.15, true);
+        assert_eq!(marker.position, 0.75);
+        assert!(!marker.is_deficit);
+        assert_eq!(summary, "Pace: 10% in reserve \u{00b7} Lasts until reset");
+    }
+
+    #[test]
+    fn test_velocity_line_under_session_row() {
+        let now = Utc::now();
+        let snapshot = snapshot(now);
+        let burning = Velocity::Burning {
+            percent_per_hour: 0.021,
+            tokens_per_hour: Some(220_000.0),
+        };
+        let mut with_velocity = input(Provider::Claude, Some(&snapshot));
+        with_velocity.velocity = Some(&burning);
+
+        let model = PopupModel::build(&with_velocity, now);
+        assert_eq!(
+            rows(&model)[0].velocity.as_deref(),
+            Some("Burning ~2.1%/h (\u{2248} 220.0K tokens/h)")
+        );
+        assert!(rows(&model)[1..].iter().all(|row| row.velocity.is_none()));
+
+        let without_tokens = Velocity::Burning {
+            percent_per_hour: 0.05,
+            tokens_per_hour: None,
+        };
+        with_velocity.velocity = Some(&without_tokens);
+        let model = PopupModel::build(&with_velocity, now);
+        assert_eq!(rows(&model)[0].velocity.as_deref(), Some("Burning ~5.0%/h"));
+
+        with_velocity.velocity = Some(&Velocity::Idle);
+        let model = PopupModel::build(&with_velocity, now);
+        assert_eq!(rows(&model)[0].velocity.as_deref(), Some("Idle"));
+    }
+
+    #[test]
+    fn test_week_over_week_trend() {
+        assert_eq!(
+            WeekOverWeekModel::build(-37.6).text,
+            "vs last week: -38% \u{2193}"
+        );
+        assert_eq!(WeekOverWeekModel::build(-37.6).trend, Trend::Down);
+        let flat = WeekOverWeekModel::build(-0.3);
+        assert_eq!(flat.text, "vs last week: +0%");
+        assert_eq!(flat.trend, Trend::Flat);
+    }
+
+    #[test]
+    fn test_credential_status() {
+        let now = Utc::now();
+        let hint = "Run `claude` to authenticate";
+
+        let missing = CredentialStatus::build(false, None, now);
+        assert_eq!(missing, CredentialStatus::Missing);
+        assert_eq!(missing.css_class(), "error");
+        assert_eq!(
+            missing.describe(None, hint, now),
+            "Missing or expired. Run `claude` to authenticate"
+        );
+
+        assert_eq!(
+            CredentialStatus::build(true, None, now),
+            CredentialStatus::Valid
+        );
+        let far = Some(now + Duration::days(3));
+        assert_eq!(
+            CredentialStatus::build(true, far, now),
+            CredentialStatus::Valid
+        );
+
+        let soon = Some(now + Duration::hours(2));
+        let expiring = CredentialStatus::build(true, soon, now);
+        assert_eq!(expiring, CredentialStatus::Expiring);
+        assert_eq!(expiring.icon_name(), "dialog-warning-symbolic");
+        assert!(expiring
+            .describe(soon, hint, now)
+            .starts_with("Token expires at "));
+    }
+
+    #[test]
+    fn test_format_reset_time() {
+        let now = Utc::now();
+        assert_eq!(
+            format_reset_time(now - Duration::minutes(1), now),
+            "resets now"
+        );
+        assert_eq!(
+            format_reset_time(now + Duration::minutes(42), now),
+            "resets in 42m"
+        );
+        assert_eq!(
+            format_reset_time(now + Duration::minutes(3 * 60 + 5), now),
+            "resets in 3h 5m"
+        );
+        assert_eq!(
+            format_reset_time(now + Duration::hours(50), now),
+            "resets in 2d 2h"
+        );
+    }
+
+    #[test]
+    fn test_pending_rebuild_coalesces_requests() {
+        let mut pending = PendingRebuild::default();
+        // Usage, cost and tokens for one provider in a row: one rebuild.
+        assert!(pending.request());
+        assert!(!pending.request());
+        assert!(!pending.request());
+        assert_eq!(pending.take(), 3);
+        assert_eq!(pending.take(), 0);
+
+        // The next update schedules again.
+        assert!(pending.request());
+        // Showing the popup rebuilt it before the scheduled rebuild ran.
+        pending.clear();
+        assert!(!pending.request());
+        assert_eq!(pending.take(), 1);
+        pending.clear();
+        assert_eq!(pending.take(), 0);
+        assert!(pending.request());
+    }
+
+    #[test]
+    fn test_estimated_reset_marked_with_tilde() {
+        let now = Utc::now();
+        let mut estimated = RateWindow {
+            resets_at: Some(now + Duration::minutes(3 * 60 + 5)),
+            resets_at_estimated: true,
+            ..window(0.4)
+        };
+        let relative = ResetTimeFormat::Relative;
+        assert_eq!(
+            window_reset_text(&estimated, relative, now).as_deref(),
+            Some("resets in ~3h 5m")
+        );
+        let clock = format_reset_clock(
+            estimated.resets_at.unwrap(),
+            now,
+            &Local,
+            HourCycle::from_env(),
+        );
+        assert_eq!(
+            window_reset_text(&estimated, ResetTimeFormat::Both, now),
+            Some(format!("resets in ~3h 5m (~{})", clock))
+        );
+        estimated.resets_at_estimated = false;
+        assert_eq!(
+            window_reset_text(&estimated, relative, now).as_deref(),
+            Some("resets in 3h 5m")
+        );
+        estimated.resets_at = None;
+        assert_eq!(window_reset_text(&estimated, relative, now), None);
+    }
+
+    #[test]
+    fn test_reset_time_format_and_tooltip() {
+        let now = Utc::now();
+        let resets_at = now + Duration::minutes(2 * 60 + 14);
+        let mut snapshot = snapshot(now);
+        snapshot.primary = Some(RateWindow {
+            resets_at: Some(resets_at),
+            ..window(0.25)
+        });
+        let clock = format_reset_clock(resets_at, now, &Local, HourCycle::from_env());
+        let mut input = input(Provider::Claude, Some(&snapshot));
+        let session = |input: &PopupInput<'_>| rows(&PopupModel::build(input, now))[0].clone();
+
+        let row = session(&input);
+        assert_eq!(row.reset_text.as_deref(), Some("resets in 2h 14m"));
+        assert_eq!(
+            row.reset_tooltip,
+            Some(format_reset_tooltip(
+                resets_at,
+                now,
+                &Local,
+                HourCycle::from_env()
+            ))
+        );
+        input.reset_time_format = ResetTimeFormat::Absolute;
+        assert_eq!(
+            session(&input).reset_text,
+            Some(format!("resets {}", clock))
+        );
+        input.reset_time_format = ResetTimeFormat::Both;
+        assert_eq!(
+            session(&input).reset_text,
+            Some(format!("resets in 2h 14m ({})", clock))
+        );
+        // Nothing to say about a window that already reset.
+        assert_eq!(
+            reset_text(now, false, ResetTimeFormat::Both, now),
+            "resets now"
+        );
+    }
+
+    fn overview_input<'a>(
+        tabs: &'a [(Provider, String)],
+        snapshots: &'a HashMap<Provider, UsageSnapshot>,
+        costs: &'a HashMap<Provider, Arc<CostSnapshot>>,
+    ) -> OverviewInput<'a> {
+        OverviewInput {
+            tabs,
+            snapshots,
+            costs,
+            show_as_remaining: false,
+            hide_costs: false,
+            cost_enabled: true,
+        }
+    }
+
+    fn monthly(amount: f64) -> Arc<CostSnapshot> {
+        Arc::new(CostSnapshot {
+            monthly_cost: amount,
+            ..CostSnapshot::default()
+        })
+    }
+
+    #[test]
+    fn test_share_splits_monthly_spend() {
+        let costs = HashMap::from([
+            (Provider::Claude, monthly(92.0)),
+            (Provider::Codex, monthly(31.0)),
+        ]);
+
+        let share = ShareModel::build(&costs, false).unwrap();
+        assert_eq!(share.summary, "Claude $92 \u{00b7} Codex $31");
+        assert_eq!(share.segments.len(), 2);
+        assert_eq!(share.segments[0].provider, Provider::Claude);
+        assert!((share.segments[0].fraction - 92.0 / 123.0).abs() < 1e-9);
+        assert!((share.segments[1].fraction - 31.0 / 123.0).abs() < 1e-9);
+        assert_eq!(share.segments[1].tooltip, "Codex $31.00 (25%)");
+    }
+
+    #[test]
+    fn test_share_with_one_provider() {
+        let costs = HashMap::from([(Provider::Codex, monthly(12.4))]);
+        let share = ShareModel::build(&costs, false).unwrap();
+        assert_eq!(share.summary, "Claude no data \u{00b7} Codex $12");
+        assert_eq!(share.segments.len(), 1);
+        assert_eq!(share.segments[0].fraction, 1.0);
+
+        // A provider with data but no spend gets no segment.
+        let costs = HashMap::from([
+            (Provider::Claude, monthly(0.0)),
+            (Provider::Codex, monthly(0.0)),
+        ]);
+        let share = ShareModel::build(&costs, false).unwrap();
+        assert!(share.segments.is_empty());
+
+        assert!(ShareModel::build(&HashMap::new(), false).is_none());
+    }
+
+    #[test]
+    fn test_overview_shows_weekly_usage_per_tab() {
+        let now = Utc::now();
+        let tabs = vec![
+            (Provider::Claude, "Claude".to_string()),
+            (Provider::Codex, "Work".to_string()),
+        ];
+        let snapshots = HashMap::from([(Provider::Claude, snapshot(now))]);
+        let costs = HashMap::from([(Provider::Claude, monthly(10.0))]);
+
+        let mut input = overview_input(&tabs, &snapshots, &costs);
+        let model = OverviewModel::build(&input);
+        assert!(model.share.is_some());
+        assert_eq!(model.weekly.len(), 2);
+        assert_eq!(model.weekly[0].percent_text, "60% used");
+        assert!((model.weekly[0].progress - 0.6).abs() < f64::EPSILON);
+        assert_eq!(model.weekly[1].title, "Work");
+        assert_eq!(model.weekly[1].percent_text, "No data");
+
+        input.show_as_remaining = true;
+        input.cost_enabled = false;
+        let model = OverviewModel::build(&input);
+        assert!(model.share.is_none());
+        assert_eq!(model.weekly[0].percent_text, "40% left");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::ui::colors::ColorScheme;
 use gtk4::gdk;
 use gtk4::glib;
 use gtk4::prelude::*;
@@ -40,10 +41,18 @@ impl UsageProgressBar {
         self.queue_draw();
     }
 
-    pub fn set_pace_marker(&self, marker_progress: Option<f64>, is_deficit: bool) {
+    /// In colorblind mode the marker is a triangle in deficit and a circle
+    /// in reserve rather than a line colored by which it is.
+    pub fn set_pace_marker(
+        &self,
+        marker_progress: Option<f64>,
+        is_deficit: bool,
+        scheme: ColorScheme,
+    ) {
         let imp = self.imp();
         imp.pace_marker.set(marker_progress.unwrap_or(-1.0));
         imp.pace_deficit.set(is_deficit);
+        imp.pace_scheme.set(scheme);
         self.queue_draw();
     }
 }
@@ -54,6 +63,44 @@ impl Default for UsageProgressBar {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MarkerShape {
+    Line,
+    Triangle,
+    Circle,
+}
+
+impl MarkerShape {
+    fn for_pace(is_deficit: bool, scheme: ColorScheme) -> Self {
+        match (scheme.is_colorblind(), is_deficit) {
+            (false, _) => Self::Line,
+            (true, true) => Self::Triangle,
+            (true, false) => Self::Circle,
+        }
+    }
+}
+
+/// The marker's pixel rows as `(left, top, width)`, centered on `x` in a bar
+/// `height` tall. The triangle points up and the circle is as wide as the
+/// bar is tall, so both take the same room.
+fn marker_rows(shape: MarkerShape, x: f32, height: f32) -> Vec<(f32, f32, f32)> {
+    let rows = height.ceil().max(1.0) as usize;
+    let radius = height / 2.0;
+    (0..rows)
+        .map(|row| {
+            let width = match shape {
+                MarkerShape::Line => 1.5,
+                MarkerShape::Triangle => height * (row + 1) as f32 / rows as f32,
+                MarkerShape::Circle => {
+                    let dy = row as f32 + 0.5 - radius;
+                    2.0 * (radius * radius - dy * dy).max(0.0).sqrt()
+                }
+            };
+            (x - width / 2.0, row as f32, width)
+        })
+        .collect()
+}
+
 fn draw_rounded_bar(
     snapshot: &gtk4::Snapshot,
     width: f32,
@@ -80,6 +127,7 @@ mod imp {
         pub trough: RefCell<gdk::RGBA>,
         pub pace_marker: Cell<f64>,
         pub pace_deficit: Cell<bool>,
+        pub pace_scheme: Cell<ColorScheme>,
     }
 
     impl Default for UsageProgressBarPriv {
@@ -91,6 +139,7 @@ mod imp {
                 trough: RefCell::new(gdk::RGBA::new(0.25, 0.25, 0.25, 0.2)),
                 pace_marker: Cell::new(-1.0),
                 pace_deficit: Cell::new(false),
+                pace_scheme: Cell::new(ColorScheme::default()),
             }
         }
     }
@@ -146,13 +195,20 @@ mod imp {
             let marker = self.pace_marker.get();
             if (0.0..=1.0).contains(&marker) {
                 let x = (width * marker) as f32;
-                let color = if self.pace_deficit.get() {
-                    gdk::RGBA::new(0.9, 0.3, 0.3, 0.7)
+                let is_deficit = self.pace_deficit.get();
+                let scheme = self.pace_scheme.get();
+                let (r, g, b) = if is_deficit {
+                    scheme.deficit_rgb()
                 } else {
-                    gdk::RGBA::new(0.3, 0.7, 0.4, 0.7)
+                    scheme.reserve_rgb()
                 };
-                let rect = gtk4::graphene::Rect::new(x - 0.75, 0.0, 1.5, height as f32);
-                snapshot.append_color(&color, &rect);
+                let color =
+                    gdk::RGBA::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 0.7);
+                let shape = MarkerShape::for_pace(is_deficit, scheme);
+                for (left, top, row_width) in marker_rows(shape, x, height as f32) {
+                    let rect = gtk4::graphene::Rect::new(left, top, row_width, 1.0);
+                    snapshot.append_color(&color, &rect);
+                }
             }
         }
 
@@ -200,6 +256,42 @@ mod tests {
         assert!((bar.progress() - 0.0).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn test_marker_shapes() {
+        let widths = |shape| -> Vec<f32> {
+            marker_rows(shape, 40.0, 5.0)
+                .iter()
+                .map(|&(left, top, width)| {
+                    // Every row is centered on the marker.
+                    assert!((left + width / 2.0 - 40.0).abs() < 1e-4, "row {}", top);
+                    width
+                })
+                .collect()
+        };
+
+        assert_eq!(widths(MarkerShape::Line), vec![1.5; 5]);
+        assert_eq!(widths(MarkerShape::Triangle), vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+        let circle = widths(MarkerShape::Circle);
+        assert_eq!(circle.len(), 5);
+        assert_eq!(circle[2], 5.0);
+        assert_eq!(circle[0], circle[4]);
+        assert_eq!(circle[1], circle[3]);
+        assert!(circle[0] < circle[1] && circle[1] < circle[2]);
+
+        assert_eq!(
+            MarkerShape::for_pace(true, ColorScheme::Standard),
+            MarkerShape::Line
+        );
+        assert_eq!(
+            MarkerShape::for_pace(true, ColorScheme::Colorblind),
+            MarkerShape::Triangle
+        );
+        assert_eq!(
+            MarkerShape::for_pace(false, ColorScheme::Colorblind),
+            MarkerShape::Circle
+        );
+    }
+
     #[test]
     fn test_label() {
         if !init_gtk() {
@@ -0,0 +1,65 @@
+use crate::core::settings::AlertSound;
+use gtk4::gdk;
+use gtk4::prelude::*;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// The freedesktop sound theme's warning sound, under an XDG data dir.
+const WARNING_SOUND: &str = "sounds/freedesktop/stereo/dialog-warning.oga";
+
+thread_local! {
+    /// The stream being played; dropping it stops playback.
+    static PLAYING: RefCell<Option<gtk4::MediaFile>> = const { RefCell::new(None) };
+    /// Sound files already reported missing.
+    static WARNED: RefCell<HashSet<PathBuf>> = RefCell::new(HashSet::new());
+}
+
+/// Plays the sound for a usage threshold notification. Must be called on the
+/// GTK main thread; playback itself runs in GStreamer's threads.
+pub fn play_alert_sound(sound: &AlertSound) {
+    let path = match sound {
+        AlertSound::Enabled(false) => return,
+        AlertSound::Enabled(true) => match theme_warning_sound() {
+            Some(path) => path,
+            None => {
+                tracing::debug!("No freedesktop warning sound installed; beeping instead");
+                if let Some(display) = gdk::Display::default() {
+                    display.beep();
+                }
+                return;
+            }
+        },
+        AlertSound::File(path) => PathBuf::from(path),
+    };
+
+    if !path.is_file() {
+        WARNED.with(|warned| {
+            if warned.borrow_mut().insert(path.clone()) {
+                tracing::warn!(path = %path.display(), "Alert sound file not found; skipping");
+            }
+        });
+        return;
+    }
+
+    let media = gtk4::MediaFile::for_filename(&path);
+    media.connect_notify_local(Some("error"), move |media, _| {
+        if let Some(error) = media.error() {
+            tracing::warn!(path = %path.display(), error = %error, "Failed to play alert sound");
+        }
+    });
+    media.play();
+    PLAYING.with(|playing| playing.replace(Some(media)));
+}
+
+fn theme_warning_sound() -> Option<PathBuf> {
+    let system_dirs = std::env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| "/usr/local/share:/usr/share".to_string());
+    dirs::data_dir()
+        .into_iter()
+        .chain(std::env::split_paths(&system_dirs))
+        .map(|dir| dir.join(WARNING_SOUND))
+        .find(|path| path.is_file())
+}
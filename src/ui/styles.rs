@@ -1,8 +1,14 @@
 use crate::core::models::Provider;
 use crate::ui::colors;
 
-pub fn css_for_provider(provider: Provider) -> String {
+/// `font_scale` scales every font in the popup, as the sizes below are
+/// relative to the frame's. `@provider_accent` follows the shown tab; the
+/// stacked layout colors each provider's section by its own class instead.
+pub fn css_for_provider(provider: Provider, font_scale: f64) -> String {
     let accent = colors::provider_hex(provider);
+    let claude = colors::CLAUDE_HEX;
+    let codex = colors::CODEX_HEX;
+    let font_percent = (font_scale * 100.0).round();
     format!(
         r#"
 @define-color provider_accent {accent};
@@ -14,6 +20,7 @@ pub fn css_for_provider(provider: Provider) -> String {
     border: 1px solid alpha(@theme_fg_color, 0.06);
     box-shadow: 0 8px 32px rgba(0, 0, 0, 0.28), 0 2px 8px rgba(0, 0, 0, 0.12);
     padding: 2px;
+    font-size: {font_percent}%;
 }}
 
 .provider-switcher {{
@@ -45,11 +52,32 @@ pub fn css_for_provider(provider: Provider) -> String {
 }}
 
 .provider-dot-claude {{
-    background-color: #F5A623;
+    background-color: {claude};
 }}
 
 .provider-dot-codex {{
-    background-color: #10A37F;
+    background-color: {codex};
+}}
+
+.provider-section {{
+    padding-left: 10px;
+    border-left: 3px solid alpha(@theme_fg_color, 0.1);
+}}
+
+.provider-section-claude {{
+    border-left-color: alpha({claude}, 0.7);
+}}
+
+.provider-section-codex {{
+    border-left-color: alpha({codex}, 0.7);
+}}
+
+.provider-section-claude .title-3 {{
+    color: {claude};
+}}
+
+.provider-section-codex .title-3 {{
+    color: {codex};
 }}
 
 .subtitle {{
@@ -140,6 +168,15 @@ pub fn css_for_provider(provider: Provider) -> String {
     background-color: alpha(@theme_fg_color, 0.06);
 }}
 
+menubutton.footer-action > button {{
+    padding: 0;
+    min-height: 0;
+    background: transparent;
+    border: none;
+    box-shadow: none;
+    font-weight: 400;
+}}
+
 .version-footer {{
     font-size: 0.72em;
     font-weight: 400;
@@ -188,6 +225,41 @@ pub fn css_for_provider(provider: Provider) -> String {
 .provider-choice {{
     padding: 6px 8px;
 }}
+
+.share-bar {{
+    margin-top: 2px;
+    margin-bottom: 2px;
+    border-radius: 999px;
+    background-color: alpha(@theme_fg_color, 0.06);
+}}
+
+.share-segment {{
+    min-height: 8px;
+    min-width: 0;
+    padding: 0;
+    border: none;
+    border-radius: 0;
+    box-shadow: none;
+}}
+
+.share-segment:first-child {{
+    border-top-left-radius: 999px;
+    border-bottom-left-radius: 999px;
+}}
+
+.share-segment:last-child {{
+    border-top-right-radius: 999px;
+    border-bottom-right-radius: 999px;
+}}
+
+.share-segment:hover {{
+    opacity: 0.8;
+}}
+
+.heatmap-axis {{
+    font-size: 0.7em;
+    color: @theme_unfocused_fg_color;
+}}
 "#
     )
 }
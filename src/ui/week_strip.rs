@@ -0,0 +1,170 @@
+use crate::ui::popup_model::{DayState, WeekStripDay};
+use gtk4::gdk;
+use gtk4::glib;
+use gtk4::prelude::*;
+use gtk4::subclass::prelude::*;
+
+const TICK_HEIGHT: f32 = 4.0;
+/// Today's tick stands taller than the rest.
+const TODAY_HEIGHT: f32 = 6.0;
+const GAP: f32 = 3.0;
+const LABEL_GAP: f32 = 1.0;
+
+glib::wrapper! {
+    pub struct WeekStrip(ObjectSubclass<imp::WeekStripPriv>)
+        @extends gtk4::Widget,
+        @implements gtk4::Accessible, gtk4::Buildable, gtk4::ConstraintTarget;
+}
+
+impl WeekStrip {
+    pub fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    /// The days built by `UsageRowModel`; without any the strip hides.
+    pub fn set_days(&self, days: &[WeekStripDay]) {
+        self.imp().days.replace(days.to_vec());
+        self.set_visible(!days.is_empty());
+        self.queue_resize();
+    }
+
+    pub fn set_colors(&self, accent: gdk::RGBA, trough: gdk::RGBA) {
+        let imp = self.imp();
+        imp.accent.replace(accent);
+        imp.trough.replace(trough);
+        self.queue_draw();
+    }
+}
+
+impl Default for WeekStrip {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The left edge and width of tick `index` of `count` across `width`.
+fn tick_span(width: f32, count: usize, index: usize) -> (f32, f32) {
+    let gaps = GAP * count.saturating_sub(1) as f32;
+    let tick_width = ((width - gaps) / count.max(1) as f32).max(1.0);
+    (index as f32 * (tick_width + GAP), tick_width)
+}
+
+mod imp {
+    use super::*;
+    use std::cell::RefCell;
+
+    pub struct WeekStripPriv {
+        pub days: RefCell<Vec<WeekStripDay>>,
+        pub accent: RefCell<gdk::RGBA>,
+        pub trough: RefCell<gdk::RGBA>,
+    }
+
+    impl Default for WeekStripPriv {
+        fn default() -> Self {
+            Self {
+                days: RefCell::new(Vec::new()),
+                accent: RefCell::new(gdk::RGBA::new(0.96, 0.65, 0.14, 0.85)),
+                trough: RefCell::new(gdk::RGBA::new(0.25, 0.25, 0.25, 0.2)),
+            }
+        }
+    }
+
+    #[glib::object_subclass]
+    impl ObjectSubclass for WeekStripPriv {
+        const NAME: &'static str = "ClaudeBarWeekStrip";
+        type Type = super::WeekStrip;
+        type ParentType = gtk4::Widget;
+    }
+
+    impl ObjectImpl for WeekStripPriv {
+        fn constructed(&self) {
+            self.parent_constructed();
+            let obj = self.obj();
+            obj.add_css_class("week-strip");
+            obj.add_css_class("caption");
+            obj.set_visible(false);
+        }
+    }
+
+    impl WeekStripPriv {
+        fn label_height(&self) -> f32 {
+            let has_label = self.days.borrow().iter().any(|day| day.label.is_some());
+            if !has_label {
+                return 0.0;
+            }
+            let layout = self.obj().create_pango_layout(Some("W"));
+            LABEL_GAP + layout.pixel_size().1 as f32
+        }
+    }
+
+    impl WidgetImpl for WeekStripPriv {
+        fn snapshot(&self, snapshot: &gtk4::Snapshot) {
+            let widget = self.obj();
+            let width = widget.width() as f32;
+            let days = self.days.borrow();
+            if width <= 0.0 || days.is_empty() {
+                return;
+            }
+
+            let accent = *self.accent.borrow();
+            let trough = *self.trough.borrow();
+            let elapsed = gdk::RGBA::new(accent.red(), accent.green(), accent.blue(), 0.45);
+            for (index, day) in days.iter().enumerate() {
+                let (x, tick_width) = tick_span(width, days.len(), index);
+                let (color, height) = match day.state {
+                    DayState::Elapsed => (elapsed, TICK_HEIGHT),
+                    DayState::Today => (accent, TODAY_HEIGHT),
+                    DayState::Upcoming => (trough, TICK_HEIGHT),
+                };
+                // Ticks share a baseline so today's rises above the others.
+                let rect = gtk4::graphene::Rect::new(x, TODAY_HEIGHT - height, tick_width, height);
+                let corner = gtk4::graphene::Size::new(1.5, 1.5);
+                let rounded = gtk4::gsk::RoundedRect::new(rect, corner, corner, corner, corner);
+                snapshot.push_rounded_clip(&rounded);
+                snapshot.append_color(&color, &rect);
+                snapshot.pop();
+
+                if let Some(label) = day.label {
+                    let layout = widget.create_pango_layout(Some(label.to_string().as_str()));
+                    let (label_width, _) = layout.pixel_size();
+                    let point = gtk4::graphene::Point::new(
+                        x + (tick_width - label_width as f32) / 2.0,
+                        TODAY_HEIGHT + LABEL_GAP,
+                    );
+                    snapshot.save();
+                    snapshot.translate(&point);
+                    snapshot.append_layout(&layout, &accent);
+                    snapshot.restore();
+                }
+            }
+        }
+
+        fn measure(&self, orientation: gtk4::Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+            match orientation {
+                gtk4::Orientation::Horizontal => (70, 200, -1, -1),
+                gtk4::Orientation::Vertical => {
+                    let height = (TODAY_HEIGHT + self.label_height()).ceil() as i32;
+                    (height, height, -1, -1)
+                }
+                _ => (0, 0, -1, -1),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick_span() {
+        // Seven ticks of 10px with 3px gaps.
+        let width = 7.0 * 10.0 + 6.0 * 3.0;
+        assert_eq!(tick_span(width, 7, 0), (0.0, 10.0));
+        assert_eq!(tick_span(width, 7, 1), (13.0, 10.0));
+        assert_eq!(tick_span(width, 7, 6), (78.0, 10.0));
+        assert_eq!(tick_span(width, 1, 0), (0.0, width));
+        // Too narrow for the gaps: ticks keep a pixel.
+        assert_eq!(tick_span(10.0, 7, 2), (8.0, 1.0));
+    }
+}
@@ -0,0 +1,93 @@
+//! Runs the daemon binary with no display in its environment and checks that
+//! it stays up in headless mode and answers on D-Bus.
+
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+const BIN: &str = env!("CARGO_BIN_EXE_claude-bar");
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Kills the daemon if the test fails before stopping it.
+struct Daemon(Child);
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+fn scratch_home() -> PathBuf {
+    let home = std::env::temp_dir().join(format!("claude-bar-headless-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&home);
+    std::fs::create_dir_all(&home).unwrap();
+    home
+}
+
+/// A `claude-bar` command for `instance`, with no display and its files
+/// kept under `home`.
+fn claude_bar(instance: &str, home: &Path) -> Command {
+    let mut command = Command::new(BIN);
+    command
+        .args(["--instance", instance])
+        .env_remove("DISPLAY")
+        .env_remove("WAYLAND_DISPLAY")
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_DATA_HOME", home.join(".local/share"))
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env("XDG_STATE_HOME", home.join(".local/state"));
+    command
+}
+
+#[test]
+fn test_daemon_runs_without_a_display() {
+    if std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_none_or(|address| address.is_empty()) {
+        eprintln!("skipping: no session D-Bus to serve on");
+        return;
+    }
+
+    let home = scratch_home();
+    let instance = format!("headless-test-{}", std::process::id());
+    let mut daemon = Daemon(
+        claude_bar(&instance, &home)
+            .arg("daemon")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start the daemon"),
+    );
+
+    // `refresh` fails until the daemon owns its bus name.
+    let started = Instant::now();
+    loop {
+        if let Some(status) = daemon.0.try_wait().unwrap() {
+            panic!("daemon exited without a display: {}", status);
+        }
+        let refresh = claude_bar(&instance, &home)
+            .arg("refresh")
+            .output()
+            .unwrap();
+        if refresh.status.success() {
+            break;
+        }
+        assert!(
+            started.elapsed() < STARTUP_TIMEOUT,
+            "daemon never answered on D-Bus: {}",
+            String::from_utf8_lossy(&refresh.stderr)
+        );
+        sleep(Duration::from_millis(250));
+    }
+
+    let stopped = Command::new("kill")
+        .args(["-TERM", &daemon.0.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(stopped.success());
+    let exit = daemon.0.wait().unwrap();
+    assert!(exit.success(), "daemon exited with {}", exit);
+
+    let _ = std::fs::remove_dir_all(&home);
+}